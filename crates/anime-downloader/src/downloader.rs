@@ -3,14 +3,82 @@
 //! Downloads anime episodes using ani-cli with disk-aware coordination.
 
 use anyhow::{Context, Result};
-use shared::{DataPaths, DiskMonitor, Job, JobQueue, JobStage};
-use std::path::PathBuf;
-use std::process::Command;
+use shared::progress::ProgressBar;
+use shared::{
+    episode_file_stem, estimate_episode_bytes, process, is_video_file, sanitize_filename, Confidence, DataPaths,
+    DiskCategory, DiskMonitor, DownloaderConfig, Job, JobQueue, JobStage, JobRetryPolicy, RunBudget, StageLimits,
+    NEEDS_REVIEW_ERROR, VIDEO_EXTENSIONS,
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
+/// Error for selections whose confidence is below the download threshold.
+///
+/// These jobs are failed immediately instead of being retried.
+#[derive(Debug)]
+pub struct NeedsReview {
+    pub title: String,
+    pub confidence: Confidence,
+    pub minimum: Confidence,
+}
+
+impl std::fmt::Display for NeedsReview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: selection for {} has confidence '{}', below the minimum '{}'",
+            NEEDS_REVIEW_ERROR, self.title, self.confidence, self.minimum
+        )
+    }
+}
+
+impl std::error::Error for NeedsReview {}
+
+/// Error for shows ani-cli cannot find on the provider.
+///
+/// Retrying does not make the show appear, so these jobs are failed
+/// immediately instead of using up their retries.
+#[derive(Debug)]
+pub struct NoResults {
+    pub search_title: String,
+}
+
+impl std::fmt::Display for NoResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ani-cli found no results for '{}' on the provider", self.search_title)
+    }
+}
+
+impl std::error::Error for NoResults {}
+
+/// Why an ani-cli run failed, judged from its stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AniCliFailure {
+    /// The search matched nothing; retrying will not help
+    NoResults,
+    /// Network errors, timeouts and anything unrecognised; worth retrying
+    Transient,
+}
+
+/// Classify a failed ani-cli run from its stderr
+pub fn classify_ani_cli_failure(stderr: &str) -> AniCliFailure {
+    if stderr.to_ascii_lowercase().contains("no results found") {
+        AniCliFailure::NoResults
+    } else {
+        AniCliFailure::Transient
+    }
+}
+
+/// Whether a failed download can succeed on a later attempt
+fn is_retryable(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<NeedsReview>().is_none() && e.downcast_ref::<NoResults>().is_none()
+}
+
 /// Anime downloader worker.
 pub struct AnimeDownloader {
     /// Worker ID for logging
@@ -25,6 +93,24 @@ pub struct AnimeDownloader {
     dry_run: bool,
     /// Filter by specific anime ID (optional)
     filter_anime_id: Option<u32>,
+    /// Job count / time limit shared with the other workers
+    budget: RunBudget,
+    /// Per-stage concurrency limits shared with the other workers
+    stage_limits: StageLimits,
+    /// Minimum selection confidence required to download
+    min_confidence: Confidence,
+    /// Whether (and how often) failed downloads are retried
+    retry_policy: JobRetryPolicy,
+    /// Bitrate assumed for episodes of anime with nothing downloaded yet (kbit/s)
+    estimated_bitrate_kbps: u32,
+    /// How often to re-check disk usage while paused
+    space_check_interval: Duration,
+    /// Time an ani-cli run may take before it is killed (None = no limit)
+    process_timeout: Option<Duration>,
+    /// Bar advanced for each job downloaded or failed for good
+    progress_bar: ProgressBar,
+    /// Number of times downloads were paused for disk space
+    pauses: usize,
     /// Number of completed downloads
     completed: usize,
     /// Number of failed downloads
@@ -33,6 +119,7 @@ pub struct AnimeDownloader {
 
 impl AnimeDownloader {
     /// Create a new downloader worker.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         worker_id: usize,
         queue: Arc<Mutex<JobQueue>>,
@@ -40,6 +127,8 @@ impl AnimeDownloader {
         data_paths: DataPaths,
         dry_run: bool,
         filter_anime_id: Option<u32>,
+        budget: RunBudget,
+        min_confidence: Confidence,
     ) -> Self {
         Self {
             worker_id,
@@ -48,11 +137,64 @@ impl AnimeDownloader {
             data_paths,
             dry_run,
             filter_anime_id,
+            budget,
+            stage_limits: StageLimits::unlimited(),
+            min_confidence,
+            retry_policy: JobRetryPolicy::default(),
+            estimated_bitrate_kbps: DownloaderConfig::default().estimated_bitrate_kbps,
+            space_check_interval: Duration::from_secs(30),
+            process_timeout: DownloaderConfig::default().process_timeout(),
+            progress_bar: ProgressBar::hidden(),
+            pauses: 0,
             completed: 0,
             failed: 0,
         }
     }
 
+    /// Override how failed downloads are retried.
+    pub fn with_retry_policy(mut self, retry_policy: JobRetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Override the bitrate used to estimate episode sizes.
+    pub fn with_estimated_bitrate(mut self, kbps: u32) -> Self {
+        self.estimated_bitrate_kbps = kbps;
+        self
+    }
+
+    /// Run at most as many downloads at once as `limits` allows, across
+    /// every worker sharing them.
+    pub fn with_stage_limits(mut self, limits: StageLimits) -> Self {
+        self.stage_limits = limits;
+        self
+    }
+
+    /// Override how often disk usage is re-checked while paused.
+    pub fn with_space_check_interval(mut self, interval: Duration) -> Self {
+        self.space_check_interval = interval;
+        self
+    }
+
+    /// Kill an ani-cli run that takes longer than `timeout` (None = no
+    /// limit) and retry the download like any other transient failure.
+    pub fn with_process_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.process_timeout = timeout;
+        self
+    }
+
+    /// Advance `bar` (usually shared with the other workers) for each job
+    /// downloaded or failed for good; retried jobs are counted once done.
+    pub fn with_progress_bar(mut self, bar: ProgressBar) -> Self {
+        self.progress_bar = bar;
+        self
+    }
+
+    /// Number of times this worker paused for disk space.
+    pub fn pauses(&self) -> usize {
+        self.pauses
+    }
+
     /// Get worker ID.
     pub fn worker_id(&self) -> usize {
         self.worker_id
@@ -65,9 +207,22 @@ impl AnimeDownloader {
         loop {
             // Check disk space before attempting download
             if self.disk_monitor.should_pause_downloads()? {
-                self.wait_for_space().await?;
+                self.wait_for_space(None).await?;
             }
 
+            // Stop picking up new work once the run budget is spent
+            if !self.budget.try_start_job() {
+                info!(
+                    worker_id = self.worker_id,
+                    jobs_started = self.budget.jobs_started(),
+                    "Run budget reached, leaving remaining jobs queued"
+                );
+                break;
+            }
+
+            // Wait for a download slot; it is held until the job is done
+            let _permit = self.stage_limits.acquire(JobStage::Downloading).await;
+
             // Try to get next job from queue (with optional anime filter)
             let job = match self.filter_anime_id {
                 Some(anime_id) => {
@@ -77,6 +232,7 @@ impl AnimeDownloader {
                             let err_msg = format!("{}", e);
                             if err_msg.contains("No jobs available") {
                                 debug!(worker_id = self.worker_id, anime_id = anime_id, "No more jobs for this anime");
+                                self.budget.release_job();
                                 break;
                             }
                             return Err(e).context("Failed to dequeue job");
@@ -90,6 +246,7 @@ impl AnimeDownloader {
                             let err_msg = format!("{}", e);
                             if err_msg.contains("No jobs available") {
                                 debug!(worker_id = self.worker_id, "No more jobs in queue");
+                                self.budget.release_job();
                                 break;
                             }
                             return Err(e).context("Failed to dequeue job");
@@ -106,6 +263,15 @@ impl AnimeDownloader {
                 "Processing job"
             );
 
+            // Leave the job queued rather than start a download that would
+            // run out of space half-way
+            let estimate = self.estimate_episode_size(&job)?;
+            if !self.disk_monitor.has_room_for(estimate)? {
+                self.budget.release_job();
+                self.wait_for_space(Some(estimate)).await?;
+                continue;
+            }
+
             // Update job stage to downloading
             self.queue
                 .lock()
@@ -143,9 +309,10 @@ impl AnimeDownloader {
                         .context("Failed to update job stage")?;
 
                     self.completed += 1;
+                    self.progress_bar.inc(1);
 
-                    // Invalidate disk cache to reflect new file
-                    self.disk_monitor.invalidate_cache();
+                    // Account for the new file without rescanning
+                    self.disk_monitor.add_bytes(DiskCategory::Videos, video_size);
                 }
                 Err(e) => {
                     error!(
@@ -155,12 +322,13 @@ impl AnimeDownloader {
                         "Download failed"
                     );
 
-                    // Check if we should retry
-                    if job.retry_count < job.max_retries {
+                    // Check if we should retry (low-confidence selections and
+                    // shows missing from the provider never succeed on retry)
+                    if is_retryable(&e) && self.retry_policy.should_retry(&job) {
                         warn!(
                             job_id = job.id,
                             retry_count = job.retry_count + 1,
-                            max_retries = job.max_retries,
+                            max_retries = self.retry_policy.max_retries(&job),
                             "Retrying job"
                         );
 
@@ -178,7 +346,7 @@ impl AnimeDownloader {
                     } else {
                         error!(
                             job_id = job.id,
-                            "Max retries exceeded, marking job as failed"
+                            "Not retrying, marking job as failed"
                         );
 
                         // Mark as failed
@@ -189,6 +357,7 @@ impl AnimeDownloader {
                             .context("Failed to update job as failed")?;
 
                         self.failed += 1;
+                        self.progress_bar.inc(1);
                     }
                 }
             }
@@ -204,18 +373,42 @@ impl AnimeDownloader {
         Ok(())
     }
 
-    /// Wait for disk space to be freed.
-    async fn wait_for_space(&self) -> Result<()> {
+    /// Estimate the size of the job's episode, to check it fits under the
+    /// hard limit before downloading it.
+    fn estimate_episode_size(&self, job: &Job) -> Result<u64> {
+        let (video_sizes, duration_minutes) = {
+            let queue = self.queue.lock().unwrap();
+            (queue.get_video_sizes(job.mal_id)?, queue.get_anime_duration(job.mal_id)?)
+        };
+        let estimate = estimate_episode_bytes(&video_sizes, duration_minutes, self.estimated_bitrate_kbps);
+
+        debug!(
+            worker_id = self.worker_id,
+            job_id = job.id,
+            estimated_mb = estimate / 1_000_000,
+            "Estimated episode size"
+        );
+
+        Ok(estimate)
+    }
+
+    /// Wait for disk space to be freed: until usage is below the resume
+    /// threshold and, if given, `needed_bytes` more fit under the hard limit
+    /// (the estimate of an episode that did not fit).
+    async fn wait_for_space(&mut self, needed_bytes: Option<u64>) -> Result<()> {
         info!(
             worker_id = self.worker_id,
             "Disk space limit reached, pausing downloads"
         );
+        self.pauses += 1;
 
         loop {
             // Wait before checking again
-            sleep(Duration::from_secs(30)).await;
+            sleep(self.space_check_interval).await;
 
-            if self.disk_monitor.can_resume_downloads()? {
+            if self.disk_monitor.can_resume_downloads()?
+                && needed_bytes.map_or(Ok(true), |bytes| self.disk_monitor.has_room_for(bytes))?
+            {
                 info!(
                     worker_id = self.worker_id,
                     "Disk space freed, resuming downloads"
@@ -250,13 +443,23 @@ impl AnimeDownloader {
             })?;
 
         // Check if anime was selected with acceptable confidence
-        if selection.confidence == "no_candidates" {
+        if selection.confidence == Confidence::NoCandidates {
             anyhow::bail!(
                 "Anime {} has no candidates on AllAnime, cannot download",
                 job.anime_title
             );
         }
 
+        // Refuse selections below the configured confidence threshold
+        if selection.confidence < self.min_confidence {
+            return Err(NeedsReview {
+                title: job.anime_title.clone(),
+                confidence: selection.confidence,
+                minimum: self.min_confidence,
+            }
+            .into());
+        }
+
         // Use selected_title (AllAnime title) for download, not MAL title
         // IMPORTANT: Strip episode count suffix like " (12 eps)" from AllAnime titles
         // because ani-cli searches don't recognize that format
@@ -281,19 +484,15 @@ impl AnimeDownloader {
         // Create output directory
         std::fs::create_dir_all(&output_dir)?;
 
-        // Build output filename
-        let safe_title = sanitize_filename(&job.anime_title);
-        let filename = format!("{}_ep{:03}.mp4", safe_title, job.episode);
-        let output_path = output_dir.join(&filename);
-
-        // Check if file already exists
-        if output_path.exists() {
+        // Check if file already exists, in whatever container ani-cli delivered
+        let stem = episode_file_stem(&job.anime_title, job.episode);
+        if let Some(existing) = existing_video(&output_dir, &stem) {
             warn!(
                 job_id = job.id,
-                path = %output_path.display(),
+                path = %existing.display(),
                 "Video file already exists, skipping download"
             );
-            return Ok(output_path);
+            return Ok(existing);
         }
 
         if self.dry_run {
@@ -306,6 +505,7 @@ impl AnimeDownloader {
             );
 
             // Create empty file for testing
+            let output_path = self.data_paths.video_file(job.mal_id, &job.anime_title, job.episode, VIDEO_EXTENSIONS[0]);
             std::fs::write(&output_path, b"")?;
             return Ok(output_path);
         }
@@ -315,100 +515,428 @@ impl AnimeDownloader {
             job_id = job.id,
             anime_title = %download_title,
             episode = job.episode,
-            output_path = %output_path.display(),
+            output_dir = %output_dir.display(),
             "Starting download with ani-cli"
         );
 
         // Get list of existing files before download
-        let before_files: std::collections::HashSet<_> = std::fs::read_dir(&output_dir)?
+        let before_files: HashSet<_> = std::fs::read_dir(&output_dir)?
             .filter_map(|e| e.ok())
             .map(|e| e.path())
             .collect();
 
         // Build ani-cli command
         // ani-cli -d -e episode_num -S 1 "anime title"
-        // Note: ani-cli downloads to current directory, so we need to change directory first
+        // Note: ani-cli downloads to current directory, so it runs in output_dir
         // IMPORTANT: Use selected_title from AllAnime, not MAL title
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(format!(
-                "cd '{}' && ani-cli -d -e {} -S 1 '{}'",
-                output_dir.display(),
-                job.episode,
-                download_title
-            ))
-            .status()
+        // ani-cli is started in its own process group so a timeout kills the
+        // downloader it runs too
+        // stderr is captured to tell a missing show from a network error
+        let mut child = process::own_process_group(&mut Command::new("ani-cli"))
+            .args(ani_cli_args(job.episode, download_title))
+            .current_dir(&output_dir)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .spawn()
             .context("Failed to execute ani-cli command")?;
+        let stderr = process::collect_output(child.stderr.take().context("Failed to capture ani-cli stderr")?);
+        let status = match process::wait_with_timeout(&mut child, "ani-cli", self.process_timeout, |_| {}) {
+            Ok(status) => status,
+            Err(e) => {
+                remove_partial_downloads(&output_dir, &before_files, &job.anime_title);
+                return Err(e);
+            }
+        };
 
         if !status.success() {
-            anyhow::bail!(
-                "ani-cli failed with exit code: {:?}",
-                status.code().unwrap_or(-1)
-            );
-        }
+            remove_partial_downloads(&output_dir, &before_files, &job.anime_title);
+            let stderr = stderr.join().unwrap_or_default();
+            debug!(job_id = job.id, stderr = %stderr, "ani-cli failed");
 
-        // Find newly created .mp4 files
-        let after_files: Vec<_> = std::fs::read_dir(&output_dir)?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter(|p| {
-                p.extension().and_then(|s| s.to_str()) == Some("mp4")
-                    && !before_files.contains(p)
-            })
-            .collect();
+            if classify_ani_cli_failure(&stderr) == AniCliFailure::NoResults {
+                return Err(NoResults {
+                    search_title: download_title.to_string(),
+                }
+                .into());
+            }
 
-        if after_files.is_empty() {
-            anyhow::bail!("No video file was created by ani-cli in {}", output_dir.display());
+            // Last stderr line on a line of its own, so failures still group by exit code
+            let last_line = stderr.lines().rev().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+            anyhow::bail!(
+                "ani-cli failed with exit code: {:?}\n{}",
+                status.code().unwrap_or(-1),
+                last_line
+            );
         }
 
-        // Use the first new file (there should only be one)
-        let downloaded_file = &after_files[0];
-
-        // Rename to our expected format if needed
-        if downloaded_file != &output_path {
+        let downloaded_file = find_new_video(&output_dir, &before_files)?
+            .with_context(|| format!("No video file was created by ani-cli in {}", output_dir.display()))?;
+
+        // Rename to our expected format if needed, keeping the real container
+        let extension = downloaded_file
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or(VIDEO_EXTENSIONS[0])
+            .to_ascii_lowercase();
+        let output_path = self.data_paths.video_file(job.mal_id, &job.anime_title, job.episode, &extension);
+        if downloaded_file != output_path {
             info!(
                 job_id = job.id,
                 from = %downloaded_file.display(),
                 to = %output_path.display(),
                 "Renaming downloaded file"
             );
-            std::fs::rename(downloaded_file, &output_path)?;
+            std::fs::rename(&downloaded_file, &output_path)?;
         }
 
         Ok(output_path)
     }
 }
 
-/// Sanitize filename by removing/replacing invalid characters.
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            _ => c,
-        })
-        .collect::<String>()
-        .trim()
-        .to_string()
+/// ani-cli arguments downloading `episode` of the first search result for
+/// `title`, e.g. `ani-cli -d -e 3 -S 1 "Test Anime"`
+fn ani_cli_args(episode: u32, title: &str) -> Vec<String> {
+    vec![
+        "-d".to_string(),
+        "-e".to_string(),
+        episode.to_string(),
+        "-S".to_string(),
+        "1".to_string(),
+        title.to_string(),
+    ]
+}
+
+/// Delete what a failed or killed ani-cli run left in `dir`: every file not
+/// in `before`, except finished episodes of `title` (`<title>_epNNN.<ext>`)
+/// that other workers moved into place meanwhile
+fn remove_partial_downloads(dir: &Path, before: &HashSet<PathBuf>, title: &str) {
+    let finished_prefix = format!("{}_ep", sanitize_filename(title));
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let finished = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(&finished_prefix));
+        if !path.is_file() || before.contains(&path) || finished {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => debug!(path = %path.display(), "Removed partial download"),
+            Err(e) => warn!(path = %path.display(), error = %e, "Failed to remove partial download"),
+        }
+    }
+}
+
+/// An already downloaded `<stem>.<ext>` in `dir`, for any of the `VIDEO_EXTENSIONS`
+fn existing_video(dir: &Path, stem: &str) -> Option<PathBuf> {
+    VIDEO_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .find(|path| path.exists())
+}
+
+/// The video file in `dir` that was not there before the download.
+///
+/// Partial downloads (`.part`, `.ytdl`, ...) don't count. If ani-cli left
+/// several, the largest is taken.
+fn find_new_video(dir: &Path, before: &HashSet<PathBuf>) -> Result<Option<PathBuf>> {
+    let mut candidates = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_file() && is_video_file(&path) && !before.contains(&path) {
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            candidates.push((size, path));
+        }
+    }
+    Ok(candidates.into_iter().max().map(|(_, path)| path))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shared::{Anime, Database, NewJob};
+    use tempfile::TempDir;
+
+    /// Add a one-episode anime with a cached selection of the given confidence
+    fn add_anime(queue: &mut JobQueue, mal_id: u32, title: &str, confidence: Confidence) -> Result<i64> {
+        let anime = Anime::test_fixture(mal_id, title, 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        queue.cache_selection(
+            mal_id, title, title, 1, title, confidence, Some("test"), Some(1), Some(1), Some("exact"),
+        )?;
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id,
+            anime_title: title.to_string(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_low_confidence_needs_review() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+
+        let db = Database::open(temp_dir.path().join("jobs.db"))?;
+        let mut queue = JobQueue::new(db);
+        let low_job = add_anime(&mut queue, 1, "Uncertain Anime", Confidence::Low)?;
+        add_anime(&mut queue, 2, "Certain Anime", Confidence::High)?;
+
+        let disk_monitor =
+            DiskMonitor::new(temp_dir.path(), temp_dir.path(), 10, 9, 8, Duration::from_secs(1))?;
+
+        let queue = Arc::new(Mutex::new(queue));
+        let mut downloader = AnimeDownloader::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths,
+            true,
+            None,
+            RunBudget::unlimited(),
+            Confidence::Medium,
+        );
+        downloader.run().await?;
+
+        let queue = queue.lock().unwrap();
+        let stats = queue.get_stats()?;
+        assert_eq!(stats.downloaded, 1);
+        assert_eq!(stats.failed, 1);
+
+        // Failed straight away, without burning retries
+        let blocked = queue.get_jobs_needing_review()?;
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].id, low_job);
+        assert_eq!(blocked[0].retry_count, 0);
+        assert!(blocked[0]
+            .error_message
+            .as_deref()
+            .unwrap()
+            .starts_with(NEEDS_REVIEW_ERROR));
+
+        Ok(())
+    }
+
+    /// Run a downloader over a single job that always fails (no cached
+    /// selection) until the job is marked failed, skipping the retry delay
+    /// between runs, and return the job afterwards
+    async fn run_failing_job(retry_policy: JobRetryPolicy) -> Result<Job> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(3, "Unselected Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+
+        let disk_monitor =
+            DiskMonitor::new(temp_dir.path(), temp_dir.path(), 10, 9, 8, Duration::from_secs(1))?;
+
+        let queue = Arc::new(Mutex::new(queue));
+        let mut downloader = AnimeDownloader::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths,
+            true,
+            None,
+            RunBudget::unlimited(),
+            Confidence::Low,
+        )
+        .with_retry_policy(retry_policy);
+
+        loop {
+            downloader.run().await?;
+            if let Some(job) = queue.lock().unwrap().get_jobs_by_stage(JobStage::Failed)?.pop() {
+                return Ok(job);
+            }
+            Database::open(temp_dir.path().join("jobs.db"))?
+                .conn()
+                .execute("UPDATE jobs SET next_attempt_at = NULL", [])?;
+        }
+    }
 
     #[test]
-    fn test_sanitize_filename() {
+    fn test_no_room_for_episode_on_near_full_disk() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let large_job = add_anime(&mut queue, 1, "Long Episodes", Confidence::High)?;
+        add_anime(&mut queue, 2, "Nothing Downloaded", Confidence::High)?;
+        // An earlier 2 GB episode of the first anime sets its estimate
+        queue.update_job_with_video(large_job, data_paths.video_file(1, "Long Episodes", 1, "mkv"), 2_000_000_000)?;
+        let jobs = queue.get_all_jobs()?;
+
+        // 8.5 GB of 10 GB used, still below the 9 GB pause threshold
+        let disk_monitor = DiskMonitor::new(temp_dir.path(), temp_dir.path(), 10, 9, 8, Duration::from_secs(1))?
+            .with_reconcile_interval(Some(Duration::from_secs(3600)));
+        disk_monitor.reconcile()?;
+        disk_monitor.add_bytes(DiskCategory::Videos, 8_500_000_000);
+        assert!(!disk_monitor.should_pause_downloads()?);
+
+        let downloader = AnimeDownloader::new(
+            0,
+            Arc::new(Mutex::new(queue)),
+            disk_monitor,
+            data_paths,
+            true,
+            None,
+            RunBudget::unlimited(),
+            Confidence::Low,
+        );
+
+        // 2 GB would cross the hard limit; 24 minutes at 1500 kbit/s fits
+        let job = |mal_id| jobs.iter().find(|j| j.mal_id == mal_id).unwrap();
+        let fits = |mal_id| downloader.disk_monitor.has_room_for(downloader.estimate_episode_size(job(mal_id))?);
+        assert!(!fits(1)?);
+        assert!(fits(2)?);
+
+        Ok(())
+    }
+
+    /// A download paused for an episode that did not fit resumes only once
+    /// it fits, not as soon as usage drops below the resume threshold
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_space_until_episode_fits() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let disk_monitor = DiskMonitor::tracking_only(10, 9, 8)?;
+        disk_monitor.add_bytes(DiskCategory::Videos, 9_500_000_000);
+        let mut downloader = AnimeDownloader::new(
+            0,
+            Arc::new(Mutex::new(queue)),
+            disk_monitor.clone(),
+            DataPaths::new(temp_dir.path()),
+            true,
+            None,
+            RunBudget::unlimited(),
+            Confidence::Low,
+        )
+        .with_space_check_interval(Duration::from_secs(10));
+
+        let freeing = tokio::spawn(async move {
+            // 7.5 GB is below the 8 GB resume threshold, but 3 GB more would
+            // still cross the 10 GB hard limit
+            sleep(Duration::from_secs(60)).await;
+            disk_monitor.sub_bytes(DiskCategory::Videos, 2_000_000_000);
+            sleep(Duration::from_secs(60)).await;
+            disk_monitor.sub_bytes(DiskCategory::Videos, 500_000_000);
+        });
+
+        let start = tokio::time::Instant::now();
+        downloader.wait_for_space(Some(3_000_000_000)).await?;
+        assert!(start.elapsed() >= Duration::from_secs(120), "{:?}", start.elapsed());
+        assert_eq!(downloader.pauses(), 1);
+        freeing.await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_new_video() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("Test Anime_ep001.mp4"), vec![0u8; 100])?;
+        let before: HashSet<_> = std::fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+        assert_eq!(find_new_video(dir, &before)?, None);
+
+        // A provider that delivers Matroska, next to a leftover partial file
+        std::fs::write(dir.join("Test Anime Episode 2.mkv"), vec![0u8; 1000])?;
+        std::fs::write(dir.join("Test Anime Episode 2.mkv.part"), vec![0u8; 5000])?;
+        std::fs::write(dir.join("Test Anime Episode 2.vtt"), b"WEBVTT")?;
+        assert_eq!(find_new_video(dir, &before)?, Some(dir.join("Test Anime Episode 2.mkv")));
+
+        assert_eq!(existing_video(dir, "Test Anime_ep001"), Some(dir.join("Test Anime_ep001.mp4")));
+        assert_eq!(existing_video(dir, "Test Anime_ep002"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_partial_downloads() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("Test Anime_ep001.mp4"), b"")?;
+        let before: HashSet<_> = std::fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+
+        // What a killed run leaves behind, next to an episode another worker
+        // finished meanwhile
+        std::fs::write(dir.join("Test Anime Episode 2.mkv.part"), b"")?;
+        std::fs::write(dir.join("Test Anime Episode 2.vtt"), b"")?;
+        std::fs::write(dir.join("Test Anime_ep003.mp4"), b"")?;
+
+        remove_partial_downloads(dir, &before, "Test Anime");
+        let mut left: Vec<_> = std::fs::read_dir(dir)?.map(|e| e.map(|e| e.file_name())).collect::<Result<_, _>>()?;
+        left.sort();
+        assert_eq!(left, ["Test Anime_ep001.mp4", "Test Anime_ep003.mp4"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failure_retry_policy() -> Result<()> {
+        // Default: retried until the job's own max_retries is used up
+        let job = run_failing_job(JobRetryPolicy::default()).await?;
+        assert_eq!(job.retry_count, 3);
+
+        // Overridden retry limit
+        let job = run_failing_job(JobRetryPolicy::new(true, Some(1))).await?;
+        assert_eq!(job.retry_count, 1);
+
+        // --no-auto-retry: failed straight away
+        let job = run_failing_job(JobRetryPolicy::new(false, None)).await?;
+        assert_eq!(job.retry_count, 0);
+        assert!(job.error_message.unwrap().contains("No anime selection found"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_classify_ani_cli_failure() {
+        // ani-cli's `die` wraps the message in colour codes
+        assert_eq!(
+            classify_ani_cli_failure("\x1b[2K\r\x1b[1;31mNo results found!\x1b[0m\n"),
+            AniCliFailure::NoResults
+        );
         assert_eq!(
-            sanitize_filename("Fullmetal Alchemist: Brotherhood"),
-            "Fullmetal Alchemist_ Brotherhood"
+            classify_ani_cli_failure("curl: (6) Could not resolve host: api.allanime.day\n"),
+            AniCliFailure::Transient
         );
         assert_eq!(
-            sanitize_filename("Attack on Titan: Season 2"),
-            "Attack on Titan_ Season 2"
+            classify_ani_cli_failure("curl: (28) Operation timed out after 30001 milliseconds with 0 bytes received\n"),
+            AniCliFailure::Transient
         );
-        assert_eq!(sanitize_filename("Normal Title"), "Normal Title");
+        assert_eq!(classify_ani_cli_failure(""), AniCliFailure::Transient);
+
+        assert!(!is_retryable(&NoResults { search_title: "Missing Anime".to_string() }.into()));
+        assert!(is_retryable(&anyhow::anyhow!("ani-cli failed with exit code: 1")));
+        // A hung ani-cli that was killed is retried too
+        assert!(is_retryable(
+            &shared::process::ProcessTimeout {
+                program: "ani-cli".to_string(),
+                timeout: Duration::from_secs(3600),
+            }
+            .into()
+        ));
+    }
+
+    #[test]
+    fn test_ani_cli_args() {
+        // Passed as-is, not through a shell, so quotes in titles need no escaping
         assert_eq!(
-            sanitize_filename("Title/with\\invalid:chars"),
-            "Title_with_invalid_chars"
+            ani_cli_args(3, "JoJo's Bizarre Adventure"),
+            ["-d", "-e", "3", "-S", "1", "JoJo's Bizarre Adventure"]
         );
     }
 }
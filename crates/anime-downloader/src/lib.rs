@@ -0,0 +1,16 @@
+//! Anime downloader library.
+//!
+//! The `anime-downloader` binary runs these workers on their own; the
+//! pipeline coordinator runs them in the same process as the transcriber.
+
+pub mod downloader;
+
+pub use downloader::{classify_ani_cli_failure, AniCliFailure, AnimeDownloader, NeedsReview, NoResults};
+
+use shared::preflight::ExternalTool;
+
+/// External tools the downloader shells out to
+pub const EXTERNAL_TOOLS: &[ExternalTool] = &[
+    ExternalTool { name: "ani-cli", version_args: &["--version"], python_package: None },
+    ExternalTool { name: "aria2c", version_args: &["--version"], python_package: None },
+];
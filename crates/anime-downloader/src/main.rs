@@ -5,27 +5,38 @@
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use shared::{Config, Database, DataPaths, DiskMonitor, JobQueue};
-use std::path::PathBuf;
+use shared::preflight;
+use shared::progress::ProgressBar;
+use shared::{
+    parse_year_range, AnimeFilter, AnimeFilterConfig, Config, Database, DequeueOrder, DiskMonitor, JobQueue, JobRetryPolicy, JobStage, RunBudget,
+    RunManifest, DEFAULT_CONFIG_PATH,
+};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::{error, info, warn};
 
-mod downloader;
-
-use downloader::AnimeDownloader;
+use anime_downloader::{AnimeDownloader, EXTERNAL_TOOLS};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config.toml")]
-    config: PathBuf,
+    /// Path to configuration file (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
 
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
 
+    /// Show a progress bar over the queued jobs (console logs are limited to warnings; ignored when stderr is not a terminal or with --verbose)
+    #[arg(long)]
+    progress: bool,
+
     /// Number of concurrent download workers
     #[arg(short = 'w', long)]
     workers: Option<usize>,
@@ -37,15 +48,99 @@ struct Args {
     /// Only download episodes for this specific anime (by MAL ID)
     #[arg(long)]
     anime_id: Option<u32>,
+
+    /// Mark failed downloads as failed straight away instead of retrying
+    #[arg(long)]
+    no_auto_retry: bool,
+
+    /// Retry failed downloads at most N times (overrides [pipeline.retry] and each job's max_retries)
+    #[arg(long, value_name = "N", conflicts_with = "no_auto_retry")]
+    max_retries: Option<u32>,
+
+    /// Stop after this many jobs have been processed (across all workers)
+    #[arg(long)]
+    max_jobs: Option<usize>,
+
+    /// Take one episode of each anime in turn instead of the highest-priority anime first
+    #[arg(long)]
+    breadth_first: bool,
+
+    /// Stop picking up new jobs after this many seconds
+    #[arg(long)]
+    max_duration: Option<u64>,
+
+    /// Write final queue statistics as JSON to this file
+    #[arg(long)]
+    stats_json: Option<PathBuf>,
+
+    /// Only download anime with all of these genres (repeatable; the --filter-* flags replace [pipeline.anime_filter])
+    #[arg(long, value_name = "GENRE")]
+    filter_genre: Vec<String>,
+
+    /// Only download anime with all of these themes (repeatable)
+    #[arg(long, value_name = "THEME")]
+    filter_theme: Vec<String>,
+
+    /// Only download anime with one of these demographics (repeatable)
+    #[arg(long, value_name = "DEMOGRAPHIC")]
+    filter_demographic: Vec<String>,
+
+    /// Only download anime made by one of these studios (repeatable)
+    #[arg(long, value_name = "STUDIO")]
+    filter_studio: Vec<String>,
+
+    /// Only download anime of one of these MAL types, e.g. TV (repeatable)
+    #[arg(long, value_name = "TYPE")]
+    filter_type: Vec<String>,
+
+    /// Only download anime that started airing in these years: 2020, 2018..2022, 2018.. or ..2010
+    #[arg(long, value_name = "YEARS", value_parser = parse_year_range)]
+    filter_year: Option<(Option<i32>, Option<i32>)>,
+
+    /// Check config, database, directories and external tools, then exit without downloading
+    #[arg(long)]
+    validate: bool,
+}
+
+impl Args {
+    /// The anime filter set by the --filter-* flags, or `configured` without any
+    fn anime_filter(&self, configured: &AnimeFilter) -> AnimeFilter {
+        let (min_year, max_year) = self.filter_year.unwrap_or_default();
+        let filter = AnimeFilter {
+            genres: self.filter_genre.clone(),
+            themes: self.filter_theme.clone(),
+            demographics: self.filter_demographic.clone(),
+            studios: self.filter_studio.clone(),
+            kind: AnimeFilterConfig {
+                types: self.filter_type.clone(),
+                ..Default::default()
+            },
+            min_year,
+            max_year,
+        };
+        if filter.is_active() {
+            filter
+        } else {
+            configured.clone()
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    if args.validate {
+        return validate(args.config.as_deref(), args.profile.as_deref());
+    }
+
     // Load configuration
-    let config = Config::from_file(&args.config)
-        .with_context(|| format!("Failed to load config from {}", args.config.display()))?;
+    let config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+
+    // Progress bars have to be on before logging is set up around them
+    if shared::progress::should_enable(args.progress, args.verbose) {
+        shared::progress::enable();
+    }
 
     // Initialize logging
     let log_level = if args.verbose {
@@ -61,18 +156,32 @@ async fn main() -> Result<()> {
         console: true,
         file: true,
         json_format: false,
+        module_levels: config.logging.module_levels.clone(),
     })?;
 
     info!("Anime Downloader starting");
-    info!(config_file = %args.config.display(), "Loaded configuration");
+    info!(
+        config_file = %args.config.as_deref().unwrap_or(Path::new(DEFAULT_CONFIG_PATH)).display(),
+        "Loaded configuration"
+    );
+    let dequeue_order = if args.breadth_first {
+        DequeueOrder::BreadthFirst
+    } else {
+        config.pipeline.dequeue_order
+    };
     info!(
         workers = args.workers.unwrap_or(config.disk_management.max_concurrent_downloads),
         dry_run = args.dry_run,
+        no_auto_retry = args.no_auto_retry,
+        max_retries = ?args.max_retries,
+        max_jobs = ?args.max_jobs,
+        max_duration_secs = ?args.max_duration,
+        dequeue_order = ?dequeue_order,
         "Runtime configuration"
     );
 
     // Initialize data paths (with separate storage directory for videos)
-    let data_paths = DataPaths::new_with_storage(config.data_dir(), config.storage_dir());
+    let data_paths = config.data_paths();
     data_paths
         .create_dirs()
         .context("Failed to create data directories")?;
@@ -81,7 +190,15 @@ async fn main() -> Result<()> {
     let db_path = config.database_path();
     info!(db_path = %db_path.display(), "Opening database");
     let database = Database::open(&db_path).context("Failed to open database")?;
-    let job_queue = JobQueue::new(database);
+    let anime_filter = args.anime_filter(&config.pipeline.anime_filter);
+    let job_queue = JobQueue::new(database)
+        .with_dequeue_order(dequeue_order)
+        .with_retry_policy(config.pipeline.retry);
+    if anime_filter.is_active() {
+        let matching = job_queue.find_anime(&anime_filter)?.len();
+        info!(filter = ?anime_filter, matching_anime = matching, "Only downloading anime matching the filter");
+    }
+    let job_queue = job_queue.with_anime_filter(anime_filter);
 
     // Initialize disk monitor (monitors both local SSD and external HDD)
     let disk_monitor = DiskMonitor::new(
@@ -92,7 +209,14 @@ async fn main() -> Result<()> {
         config.disk_management.resume_threshold_gb,
         Duration::from_secs(config.disk_management.cache_duration_seconds),
     )
-    .context("Failed to initialize disk monitor")?;
+    .context("Failed to initialize disk monitor")?
+    .with_layout(config.path_layout())
+    .with_reconcile_interval(
+        config
+            .disk_management
+            .reconcile_interval_seconds
+            .map(Duration::from_secs),
+    );
 
     // Check initial disk usage
     let breakdown = disk_monitor.get_breakdown()?;
@@ -112,6 +236,9 @@ async fn main() -> Result<()> {
         warn!("Waiting for transcriber to free up space...");
     }
 
+    // `kill -USR1 <pid>` rescans the disk after out-of-band changes
+    disk_monitor.refresh_on_sigusr1()?;
+
     // Get number of workers
     let num_workers = args
         .workers
@@ -133,9 +260,21 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Record the tool versions and config this run downloads with
+    let (mut manifest, manifest_path) = RunManifest::start("anime-downloader", &config, EXTERNAL_TOOLS, &data_paths)?;
+
+    // One bar for every worker, over the jobs queued at the start
+    let queued = job_queue.count_pending(JobStage::Queued, args.anime_id)? as u64;
+    let progress = ProgressBar::new("downloads", args.max_jobs.map_or(queued, |max| queued.min(max as u64)));
+
     // Wrap queue in Arc for sharing between workers
     let job_queue = Arc::new(Mutex::new(job_queue));
 
+    // Shared budget so the job limit applies to the run as a whole
+    let budget = RunBudget::new(args.max_jobs, args.max_duration.map(Duration::from_secs));
+    let max_retries = args.max_retries.or(config.pipeline.retry.map(|retry| retry.max_retries()));
+    let retry_policy = JobRetryPolicy::new(!args.no_auto_retry, max_retries);
+
     // Initialize downloaders
     let mut downloaders = Vec::new();
     for worker_id in 0..num_workers {
@@ -146,7 +285,14 @@ async fn main() -> Result<()> {
             data_paths.clone(),
             args.dry_run,
             args.anime_id,
-        );
+            budget.clone(),
+            config.downloader.min_download_confidence,
+        )
+        .with_retry_policy(retry_policy)
+        .with_estimated_bitrate(config.downloader.estimated_bitrate_kbps)
+        .with_process_timeout(config.downloader.process_timeout())
+        .with_progress_bar(progress.clone())
+        .with_space_check_interval(Duration::from_secs(config.disk_management.check_interval_seconds));
         downloaders.push(downloader);
     }
 
@@ -181,6 +327,8 @@ async fn main() -> Result<()> {
         }
     }
 
+    progress.finish();
+
     // Final statistics
     let final_stats = job_queue
         .lock()
@@ -193,6 +341,25 @@ async fn main() -> Result<()> {
     info!("Downloaded: {}", final_stats.downloaded);
     info!("Failed: {}", final_stats.failed);
 
+    let needs_review = job_queue
+        .lock()
+        .unwrap()
+        .get_jobs_needing_review()
+        .context("Failed to get jobs needing review")?;
+    if !needs_review.is_empty() {
+        warn!(
+            count = needs_review.len(),
+            min_confidence = %config.downloader.min_download_confidence,
+            "Jobs blocked on low-confidence selections (run anime-selector --review)"
+        );
+    }
+
+    if let Some(path) = &args.stats_json {
+        std::fs::write(path, final_stats.to_json()?)
+            .with_context(|| format!("Failed to write stats to {}", path.display()))?;
+        info!(path = %path.display(), "Wrote queue statistics");
+    }
+
     let final_breakdown = disk_monitor.get_breakdown()?;
     info!(
         total_gb = final_breakdown.usage.total_gb(),
@@ -201,7 +368,19 @@ async fn main() -> Result<()> {
         "Final disk usage"
     );
 
+    manifest.finish(&manifest_path)?;
     info!("Anime Downloader finished successfully");
 
     Ok(())
 }
+
+/// Run the pre-flight checks and print the report
+fn validate(config_path: Option<&Path>, profile: Option<&str>) -> Result<()> {
+    let report = preflight::run(config_path, profile, EXTERNAL_TOOLS);
+    println!("{}", report);
+
+    if !report.passed() {
+        anyhow::bail!("Pre-flight checks failed");
+    }
+    Ok(())
+}
@@ -0,0 +1,579 @@
+//! Claude requests of the anime selector.
+//!
+//! `ClaudeClient` asks Claude Haiku through the Messages API which AllAnime
+//! candidate is the MAL entry's main series, one anime per request or a
+//! whole batch in one. Requests go through a `RetryingClient`, so they are
+//! paced to `[anthropic] requests_per_minute` across every worker, and rate
+//! limited (429), overloaded (529), server and network failures are retried
+//! as `[anthropic.retry]` says, after any `Retry-After` delay.
+//!
+//! Claude's pick is checked before it is used: an index outside the
+//! candidate list falls back to the first candidate at low confidence, and a
+//! pick whose episode count is far from MAL's has its confidence lowered.
+
+use crate::{AnimeRecord, SelectionResult};
+use anyhow::{Context, Result};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use shared::config::AnthropicConfig;
+use shared::models::Confidence;
+use shared::{HttpStatusError, RateLimiter, RetryingClient};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::debug;
+
+/// Claude model used for selections
+pub const MODEL: &str = "claude-3-5-haiku-20241022";
+
+/// Base URL of the Anthropic API
+const API_URL: &str = "https://api.anthropic.com";
+
+/// Value of the `anthropic-version` header
+const API_VERSION: &str = "2023-06-01";
+
+/// Timeout of each request attempt
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Output tokens allowed for a single selection
+const MAX_TOKENS: u32 = 300;
+
+/// Output tokens allowed per anime of a batch
+const MAX_TOKENS_PER_BATCH_ITEM: u32 = 150;
+
+/// Selection criteria shared by the single and batch prompts
+const CRITERIA: &str = r#"Selection Criteria (in order of importance):
+1. **Main series vs Specials/OVA**: Strongly prefer the main TV series over specials, recaps, or OVAs
+2. **Episode count**: The candidate should have a similar number of episodes to the MAL data
+3. **Series vs Season**: If the anime has multiple seasons, match the correct season
+4. **Title similarity**: Consider romanization variants and alternative titles
+5. **Year**: Should be close to the MAL year (within 1-2 years is acceptable)
+
+IMPORTANT NOTES:
+- "Specials", "Recap", "OVA", "ONA" usually indicate extra content, NOT the main series
+- If episode count differs significantly (>3 episodes), it's likely the wrong match
+- Be cautious with very short titles that might match multiple series
+- If no good match exists, select the closest one but mark confidence as "low""#;
+
+/// Returned when Anthropic answered 401 (the API key was rejected); retrying
+/// cannot help
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("Anthropic API rejected the API key (401 Unauthorized): check api_key under [anthropic] or {}", AnthropicConfig::API_KEY_ENV_VARS[0])]
+pub struct Unauthorized;
+
+/// Claude's answer for one anime, before it is checked
+#[derive(Debug, Deserialize)]
+struct Answer {
+    /// Number of the anime in a batch prompt
+    #[serde(default)]
+    anime: Option<u64>,
+    /// 1-based candidate index; anything else is replaced by the first candidate
+    index: serde_json::Value,
+    confidence: Confidence,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Messages API response, reduced to its text
+#[derive(Debug, Deserialize)]
+struct Message {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    text: Option<String>,
+}
+
+/// Client for selection requests to Claude
+pub struct ClaudeClient {
+    /// HTTP client, rate limited and retrying failed requests
+    http: RetryingClient,
+    api_key: String,
+    base_url: String,
+}
+
+impl ClaudeClient {
+    /// Client using `api_key`, paced and retried as `config` says
+    pub fn new(config: &AnthropicConfig, api_key: String) -> Result<Self> {
+        let requests_per_minute = config.requests_per_minute;
+        let http = RetryingClient::new(Client::builder().build()?)
+            .with_retry_policy(&config.retry_policy())
+            .with_timeout(REQUEST_TIMEOUT)
+            .with_rate_limiter(RateLimiter::new(f64::from(requests_per_minute) / 60.0, requests_per_minute));
+
+        Ok(Self {
+            http,
+            api_key,
+            base_url: API_URL.to_string(),
+        })
+    }
+
+    /// Send requests to `base_url` instead of the Anthropic API
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Pick the best candidate for one anime
+    pub async fn select(&self, anime: &AnimeRecord, candidates: &[String]) -> Result<SelectionResult> {
+        if let Some(result) = without_claude(candidates)? {
+            return Ok(result);
+        }
+
+        let text = self.ask(&selection_prompt(anime, candidates), MAX_TOKENS, 1).await?;
+        let answer = serde_json::from_value(parse_reply(&text)?).context("Invalid response format from Claude")?;
+        Ok(check_selection(answer, anime, candidates))
+    }
+
+    /// Pick the best candidate for several anime with one request
+    ///
+    /// Results are returned in input order. Anime with fewer than two
+    /// candidates need no request, and an anime whose answer is missing or
+    /// malformed in the reply is asked about on its own. If the request
+    /// itself fails, every anime in it gets that error.
+    pub async fn select_batch(&self, items: &[(&AnimeRecord, Vec<String>)]) -> Vec<Result<SelectionResult>> {
+        let mut results: Vec<Option<Result<SelectionResult>>> = items
+            .iter()
+            .map(|(_, candidates)| without_claude(candidates).transpose())
+            .collect();
+        let pending: Vec<usize> = (0..items.len()).filter(|&i| results[i].is_none()).collect();
+
+        if pending.len() > 1 {
+            let batch: Vec<(&AnimeRecord, &[String])> =
+                pending.iter().map(|&i| (items[i].0, items[i].1.as_slice())).collect();
+            let max_tokens = MAX_TOKENS_PER_BATCH_ITEM * pending.len() as u32;
+
+            match self.ask(&batch_prompt(&batch), max_tokens, pending.len()).await {
+                Ok(text) => {
+                    let mut answers = batch_answers(&text);
+                    for (number, &i) in (1..).zip(&pending) {
+                        if let Some(answer) = answers.remove(&number) {
+                            results[i] = Some(Ok(check_selection(answer, items[i].0, &items[i].1)));
+                        }
+                    }
+                }
+                Err(e) => {
+                    let unauthorized = e.is::<Unauthorized>();
+                    let message = format!("{:#}", e);
+                    for &i in &pending {
+                        results[i] = Some(if unauthorized {
+                            Err(Unauthorized.into())
+                        } else {
+                            Err(anyhow::anyhow!("{}", message))
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut selections = Vec::with_capacity(items.len());
+        for ((anime, candidates), result) in items.iter().zip(results) {
+            selections.push(match result {
+                Some(result) => result,
+                None => self.select(anime, candidates).await,
+            });
+        }
+        selections
+    }
+
+    /// Send `prompt` to Claude and return the text of its answer
+    ///
+    /// The request takes `slots` rate limit slots, as a batch request grows
+    /// with every anime in it.
+    async fn ask(&self, prompt: &str, max_tokens: u32, slots: usize) -> Result<String> {
+        if let Some(limiter) = self.http.rate_limiter() {
+            // The client itself takes the last slot
+            let mut limiter = limiter.lock().await;
+            for _ in 1..slots {
+                limiter.acquire().await;
+            }
+        }
+
+        let url = format!("{}/v1/messages", self.base_url);
+        let body = serde_json::json!({
+            "model": MODEL,
+            "max_tokens": max_tokens,
+            // Deterministic selection
+            "temperature": 0.0,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        debug!(url = %url, max_tokens, "Asking Claude");
+
+        let response = self
+            .http
+            .send(|client| {
+                client
+                    .post(&url)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", API_VERSION)
+                    .json(&body)
+            })
+            .await
+            .map_err(|e| match e.downcast_ref::<HttpStatusError>() {
+                Some(error) if error.status == StatusCode::UNAUTHORIZED => Unauthorized.into(),
+                _ => e,
+            })?;
+
+        let message: Message = response.json().await.context("Failed to parse Claude response")?;
+        message
+            .content
+            .into_iter()
+            .find_map(|block| block.text)
+            .context("Claude response has no text")
+    }
+}
+
+/// MAL details of an anime as listed in the prompts
+fn mal_details(anime: &AnimeRecord) -> String {
+    let or_unknown = |value: Option<String>| value.unwrap_or_else(|| "Unknown".to_string());
+    format!(
+        "- Title: \"{}\"\n- Episodes: {}\n- Year: {}\n- Type: {}",
+        anime.title,
+        or_unknown(anime.episodes_total.map(|e| e.to_string())),
+        or_unknown(anime.year.map(|y| y.to_string())),
+        or_unknown(anime.anime_type.clone()),
+    )
+}
+
+/// Numbered candidate list, each line starting with `indent`
+fn candidate_list(candidates: &[String], indent: &str) -> String {
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| format!("{}{}. {}", indent, i + 1, candidate))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prompt asking Claude to pick the best candidate for one anime
+pub fn selection_prompt(anime: &AnimeRecord, candidates: &[String]) -> String {
+    format!(
+        r#"You are an anime title matching expert. Your task is to select the BEST matching anime from a list of search results.
+
+MAL (MyAnimeList) Information:
+{}
+
+Available Candidates from ani-cli search:
+{}
+
+{}
+
+Respond with ONLY valid JSON (no markdown, no explanation outside JSON):
+{{
+  "index": <number from 1 to {}>,
+  "confidence": "high|medium|low",
+  "reason": "<brief 1-sentence explanation of why this match was selected>"
+}}"#,
+        mal_details(anime),
+        candidate_list(candidates, ""),
+        CRITERIA,
+        candidates.len(),
+    )
+}
+
+/// Prompt asking Claude to pick the best candidate for each of several anime
+pub fn batch_prompt(items: &[(&AnimeRecord, &[String])]) -> String {
+    let sections: Vec<String> = items
+        .iter()
+        .enumerate()
+        .map(|(i, (anime, candidates))| {
+            format!(
+                "Anime {}:\n{}\n- Candidates from ani-cli search:\n{}",
+                i + 1,
+                mal_details(anime),
+                candidate_list(candidates, "   "),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"You are an anime title matching expert. For EACH of the {} anime below, select the BEST matching entry from its own list of search results.
+
+{}
+
+{}
+
+Respond with ONLY a valid JSON array (no markdown, no explanation outside JSON), one object per anime in order:
+[
+  {{
+    "anime": <anime number>,
+    "index": <candidate number from that anime's list>,
+    "confidence": "high|medium|low",
+    "reason": "<brief 1-sentence explanation of why this match was selected>"
+  }}
+]"#,
+        items.len(),
+        sections.join("\n\n"),
+        CRITERIA,
+    )
+}
+
+/// Parse the JSON Claude answered with, ignoring a markdown code fence
+/// around it
+fn parse_reply(text: &str) -> Result<serde_json::Value> {
+    let text = text.trim();
+    let text = if text.starts_with("```") {
+        // Drop the fence lines, e.g. "```json" and "```"
+        let lines: Vec<&str> = text.lines().collect();
+        lines.get(1..lines.len() - 1).unwrap_or_default().join("\n")
+    } else {
+        text.to_string()
+    };
+    let text = text.strip_prefix("json").unwrap_or(&text).trim();
+    serde_json::from_str(text).context("Failed to parse Claude response")
+}
+
+/// Well-formed answers of a batch reply, by anime number; a reply that is
+/// not a JSON array yields none
+fn batch_answers(text: &str) -> HashMap<u64, Answer> {
+    let Ok(serde_json::Value::Array(values)) = parse_reply(text) else {
+        return HashMap::new();
+    };
+    values
+        .into_iter()
+        .filter_map(|value| serde_json::from_value::<Answer>(value).ok())
+        .filter_map(|answer| Some((answer.anime?, answer)))
+        .collect()
+}
+
+/// Result for candidate lists that need no Claude request, if any
+fn without_claude(candidates: &[String]) -> Result<Option<SelectionResult>> {
+    match candidates {
+        [] => anyhow::bail!("No candidates provided"),
+        [_] => Ok(Some(SelectionResult {
+            index: 1,
+            confidence: Confidence::High,
+            reason: "Only one candidate available".to_string(),
+            mal_episodes: None,
+            selected_episodes: None,
+            episode_match: None,
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Check Claude's answer for one anime and add the episode check
+fn check_selection(answer: Answer, anime: &AnimeRecord, candidates: &[String]) -> SelectionResult {
+    let valid = answer
+        .index
+        .as_i64()
+        .filter(|index| (1..=candidates.len() as i64).contains(index));
+    let (index, confidence, reason) = match valid {
+        Some(index) => (index as i32, answer.confidence, answer.reason),
+        None => (1, Confidence::Low, format!("Invalid index {}, using first candidate", answer.index)),
+    };
+
+    let selected_episodes = parse_episode_count(&candidates[index as usize - 1]);
+    let episode_match = episode_match(anime.episodes_total, selected_episodes);
+    let confidence = match (episode_match, confidence) {
+        ("mismatch", Confidence::High) => Confidence::Medium,
+        ("mismatch", Confidence::Medium) => Confidence::Low,
+        (_, confidence) => confidence,
+    };
+
+    SelectionResult {
+        index,
+        confidence,
+        reason,
+        mal_episodes: anime.episodes_total,
+        selected_episodes,
+        episode_match: Some(episode_match.to_string()),
+    }
+}
+
+/// Episode count of an ani-cli title like `Title (64 eps)`
+fn parse_episode_count(title: &str) -> Option<i32> {
+    title.match_indices('(').find_map(|(start, _)| {
+        let rest = &title[start + 1..];
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let count = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].strip_prefix(char::is_whitespace)?.trim_start();
+        let unit = unit.get(..unit.find(')')?)?;
+        (unit.eq_ignore_ascii_case("ep") || unit.eq_ignore_ascii_case("eps")).then_some(count)
+    })
+}
+
+/// How well the selected episode count matches MAL's: `exact`, `close`
+/// (within 2), `acceptable` (within 5 or 10%), `mismatch` or `unknown`
+fn episode_match(mal_episodes: Option<i32>, selected_episodes: Option<i32>) -> &'static str {
+    let (Some(mal), Some(selected)) = (mal_episodes, selected_episodes) else {
+        return "unknown";
+    };
+    let diff = (selected - mal).abs();
+    let diff_percent = if mal > 0 { f64::from(diff) / f64::from(mal) } else { 0.0 };
+
+    match diff {
+        0 => "exact",
+        1..=2 => "close",
+        _ if diff <= 5 || diff_percent <= 0.1 => "acceptable",
+        _ => "mismatch",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::test_server::{TestResponse, TestServer};
+    use shared::RetryPolicy;
+
+    fn anime(title: &str, episodes: Option<i32>) -> AnimeRecord {
+        AnimeRecord {
+            mal_id: 5114,
+            title: title.to_string(),
+            episodes_total: episodes,
+            year: Some(2009),
+            anime_type: Some("TV".to_string()),
+        }
+    }
+
+    fn candidates(titles: &[&str]) -> Vec<String> {
+        titles.iter().map(|t| t.to_string()).collect()
+    }
+
+    /// Messages API response whose text is `text`
+    fn reply(text: &str) -> TestResponse {
+        TestResponse::json(&serde_json::json!({
+            "type": "message",
+            "content": [{"type": "text", "text": text}],
+        }))
+    }
+
+    fn client(server: &TestServer) -> Result<ClaudeClient> {
+        let config = AnthropicConfig {
+            requests_per_minute: 6000,
+            retry: Some(RetryPolicy {
+                max_attempts: 4,
+                base_delay_ms: 1,
+                max_delay_ms: 1,
+                jitter: 0.0,
+            }),
+            ..AnthropicConfig::default()
+        };
+        Ok(ClaudeClient::new(&config, "sk-ant-test".to_string())?.with_base_url(server.url()))
+    }
+
+    #[tokio::test]
+    async fn test_select_retries_failed_requests() -> Result<()> {
+        let server = TestServer::sequence(vec![
+            TestResponse::new(429).with_header("Retry-After", "0"),
+            TestResponse::new(529),
+            reply("```json\n{\"index\": 2, \"confidence\": \"high\", \"reason\": \"Main series\"}\n```"),
+        ])
+        .await?;
+
+        let fmab = anime("Fullmetal Alchemist: Brotherhood", Some(64));
+        let result = client(&server)?
+            .select(&fmab, &candidates(&["FMA: Brotherhood Specials (4 eps)", "FMA: Brotherhood (64 eps)"]))
+            .await?;
+        assert_eq!((result.index, result.confidence), (2, Confidence::High));
+        assert_eq!(result.reason, "Main series");
+        assert_eq!((result.mal_episodes, result.selected_episodes), (Some(64), Some(64)));
+        assert_eq!(result.episode_match.as_deref(), Some("exact"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|r| r.line.starts_with("POST /v1/messages ")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_select_batch_asks_once() -> Result<()> {
+        // Anime 2's answer is malformed, so it is asked about on its own
+        let server = TestServer::sequence(vec![
+            reply(r#"[{"anime": 1, "index": 1, "confidence": "high", "reason": "Main series"}, {"anime": 2}]"#),
+            reply(r#"{"index": 2, "confidence": "medium", "reason": "Closest season"}"#),
+        ])
+        .await?;
+
+        let (first, second, single) = (anime("First", Some(12)), anime("Second", Some(24)), anime("Single", None));
+        let items = [
+            (&first, candidates(&["First (12 eps)", "First Specials (2 eps)"])),
+            (&single, candidates(&["Single (1 ep)"])),
+            (&second, candidates(&["Second (12 eps)", "Second (24 eps)"])),
+        ];
+        let results: Vec<SelectionResult> = client(&server)?
+            .select_batch(&items)
+            .await
+            .into_iter()
+            .collect::<Result<_>>()?;
+
+        let picks: Vec<_> = results.iter().map(|r| (r.index, r.confidence)).collect();
+        assert_eq!(picks, [(1, Confidence::High), (1, Confidence::High), (2, Confidence::Medium)]);
+        assert_eq!(results[1].reason, "Only one candidate available");
+        assert_eq!(server.request_count(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_api_key() -> Result<()> {
+        let server = TestServer::sequence(vec![TestResponse::new(401); 2]).await?;
+        let client = client(&server)?;
+
+        let fmab = anime("Fullmetal Alchemist: Brotherhood", Some(64));
+        let pair = candidates(&["FMA (51 eps)", "FMA: Brotherhood (64 eps)"]);
+        let err = client.select(&fmab, &pair).await.unwrap_err();
+        assert!(err.is::<Unauthorized>());
+        assert!(err.to_string().contains("401"));
+
+        // Every anime of a rejected batch gets the error, without a retry
+        let results = client.select_batch(&[(&fmab, pair.clone()), (&fmab, pair.clone())]).await;
+        assert!(results.iter().all(|r| r.as_ref().is_err_and(|e| e.is::<Unauthorized>())));
+        assert_eq!(server.request_count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_selection() {
+        let fmab = anime("Fullmetal Alchemist: Brotherhood", Some(64));
+        let list = candidates(&["FMA: Brotherhood (64 eps)", "Fullmetal Alchemist (51 eps)"]);
+        let answer = |index: serde_json::Value, confidence| Answer {
+            anime: None,
+            index,
+            confidence,
+            reason: "Title matches".to_string(),
+        };
+
+        // Indexes outside the candidate list fall back to the first one
+        for index in [serde_json::json!(0), serde_json::json!(-1), serde_json::json!(3), serde_json::json!("2")] {
+            let result = check_selection(answer(index.clone(), Confidence::High), &fmab, &list);
+            assert_eq!((result.index, result.confidence), (1, Confidence::Low));
+            assert_eq!(result.reason, format!("Invalid index {}, using first candidate", index));
+        }
+
+        // A far-off episode count lowers the confidence one level
+        let result = check_selection(answer(serde_json::json!(2), Confidence::High), &fmab, &list);
+        assert_eq!((result.index, result.confidence), (2, Confidence::Medium));
+        assert_eq!(result.episode_match.as_deref(), Some("mismatch"));
+
+        assert!(without_claude(&[]).is_err());
+    }
+
+    #[test]
+    fn test_episode_count_and_match() {
+        assert_eq!(parse_episode_count("Naruto (220 eps)"), Some(220));
+        assert_eq!(parse_episode_count("Movie (1 EP)"), Some(1));
+        assert_eq!(parse_episode_count("Steins;Gate (2011) (24 eps)"), Some(24));
+        assert_eq!(parse_episode_count("Steins;Gate (24)"), None);
+        assert_eq!(parse_episode_count("No count"), None);
+
+        assert_eq!(episode_match(Some(12), Some(12)), "exact");
+        assert_eq!(episode_match(Some(12), Some(14)), "close");
+        assert_eq!(episode_match(Some(64), Some(69)), "acceptable");
+        assert_eq!(episode_match(Some(12), Some(25)), "mismatch");
+        assert_eq!(episode_match(None, Some(12)), "unknown");
+    }
+
+    #[test]
+    fn test_parse_reply() -> Result<()> {
+        let expected = serde_json::json!({"index": 1});
+        assert_eq!(parse_reply("{\"index\": 1}")?, expected);
+        assert_eq!(parse_reply("```json\n{\"index\": 1}\n```")?, expected);
+        assert_eq!(parse_reply("```\n{\"index\": 1}\n```")?, expected);
+        assert!(parse_reply("I pick the first one").is_err());
+        Ok(())
+    }
+}
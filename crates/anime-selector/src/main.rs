@@ -3,11 +3,21 @@
 //! This tool queries AllAnime API for each anime in the database and uses
 //! Claude Haiku to intelligently select the main series vs specials/OVAs.
 //! Results are cached in the anime_selection_cache table.
+//!
+//! Claude is asked over HTTP (see `claude`): requests are paced to
+//! `anthropic.requests_per_minute` across all workers, and rate-limited,
+//! overloaded and failed requests are retried as `[anthropic.retry]` says
+//! instead of being counted as errors.
+
+mod claude;
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use shared::config::Config;
+use claude::ClaudeClient;
+use shared::config::{AnthropicConfig, Config};
 use shared::db::Database;
+use shared::models::Confidence;
+use shared::paths::DataPaths;
 use shared::queue::JobQueue;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
@@ -20,11 +30,15 @@ use tracing::{debug, error, info, warn};
 #[command(name = "anime-selector")]
 #[command(about = "Pre-select correct anime titles using Claude Haiku")]
 struct Args {
-    /// Configuration file path
-    #[arg(short, long, default_value = "config.toml")]
-    config: PathBuf,
+    /// Configuration file path (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
 
-    /// Number of concurrent workers
+    /// Number of concurrent workers (requests are still paced to anthropic.requests_per_minute)
     #[arg(short, long, default_value = "5")]
     workers: usize,
 
@@ -32,6 +46,11 @@ struct Args {
     #[arg(long)]
     dry_run: bool,
 
+    /// Count the anime that would be sent to Claude and estimate tokens and cost, then exit
+    /// (no API key needed, nothing is searched or selected)
+    #[arg(long)]
+    estimate: bool,
+
     /// Process only specific MAL ID
     #[arg(long)]
     mal_id: Option<u32>,
@@ -39,23 +58,51 @@ struct Args {
     /// Review mode: show low-confidence selections only
     #[arg(long)]
     review: bool,
+
+    /// Review mode: show selections whose episode count does not match MAL
+    #[arg(long)]
+    review_mismatch: bool,
+
+    /// Re-query anime previously marked as having no candidates
+    #[arg(long)]
+    recheck_no_candidates: bool,
+
+    /// Only process anime without a cached selection (e.g. after an incremental scrape)
+    #[arg(long, conflicts_with = "recheck_no_candidates")]
+    only_uncached: bool,
+
+    /// Number of anime asked about in one Claude request
+    #[arg(long, default_value = "1")]
+    batch_size: usize,
+
+    /// Reset all jobs of this MAL ID to queued and clear its cached selection (unless it was set manually), then exit
+    #[arg(long, value_name = "MAL_ID")]
+    reset: Option<u32>,
+
+    /// With --mal-id: select candidate INDEX (1-based), titled TITLE, by hand, then exit.
+    /// Later selection runs keep it
+    #[arg(long, num_args = 2, value_names = ["INDEX", "TITLE"], requires = "mal_id")]
+    set_selection: Option<Vec<String>>,
+
+    /// With --reset: also delete the anime's videos, audio, transcripts, tokens and analysis
+    #[arg(long, requires = "reset")]
+    delete_files: bool,
 }
 
 #[derive(Debug, serde::Deserialize)]
 struct AnimeRecord {
     mal_id: u32,
     title: String,
-    title_english: Option<String>,
     episodes_total: Option<i32>,
     year: Option<i32>,
     #[serde(rename = "type")]
     anime_type: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug)]
 struct SelectionResult {
     index: i32,
-    confidence: String,
+    confidence: Confidence,
     reason: String,
     mal_episodes: Option<i32>,
     selected_episodes: Option<i32>,
@@ -88,6 +135,31 @@ impl SelectionStats {
         }
     }
 
+    /// Record the outcome of processing one anime
+    fn record(&mut self, result: &Result<Option<Confidence>>) {
+        self.total += 1;
+
+        match result {
+            Ok(Some(confidence)) => match confidence {
+                Confidence::NoCandidates => self.no_candidates += 1,
+                Confidence::High => {
+                    self.selected += 1;
+                    self.high_confidence += 1;
+                }
+                Confidence::Medium => {
+                    self.selected += 1;
+                    self.medium_confidence += 1;
+                }
+                Confidence::Low => {
+                    self.selected += 1;
+                    self.low_confidence += 1;
+                }
+            },
+            Ok(None) => self.cached += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+
     fn print_summary(&self) {
         info!("=== Selection Summary ===");
         info!("Total anime: {}", self.total);
@@ -110,30 +182,76 @@ async fn main() -> Result<()> {
 
     info!("Starting anime selector");
     info!("Workers: {}", args.workers);
+    if args.batch_size > 1 {
+        info!("Batch size: {}", args.batch_size);
+    }
     if args.dry_run {
         info!("DRY RUN MODE - selections will not be cached");
     }
 
     // Load configuration
-    let config = Config::from_file(&args.config)
-        .with_context(|| format!("Failed to load config from {:?}", args.config))?;
+    let config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+    info!("Anthropic requests per minute: {}", config.anthropic.requests_per_minute);
 
     // Open database (use database_path() to get correct absolute path)
     let db_path = config.database_path();
     let db = Database::open(&db_path)
         .context("Failed to open database")?;
 
+    // Reset mode: wipe an anime so it is selected and processed again
+    if let Some(mal_id) = args.reset {
+        let data_paths = config.data_paths();
+        return reset_anime(db, mal_id, args.delete_files.then_some(&data_paths));
+    }
+
+    // Manual correction of a selection
+    if let (Some(values), Some(mal_id)) = (&args.set_selection, args.mal_id) {
+        let index: i32 = values[0]
+            .parse()
+            .with_context(|| format!("Invalid candidate index: {}", values[0]))?;
+        JobQueue::new(db).set_manual_selection(mal_id, index, &values[1])?;
+        info!("Selected \"{}\" (candidate {}) for MAL ID {}", values[1], index, mal_id);
+        return Ok(());
+    }
+
     // Review mode: just show low-confidence selections
     if args.review {
         return review_selections(&db);
     }
 
+    // Review mode: show episode-count mismatches
+    if args.review_mismatch {
+        return review_mismatches(&db);
+    }
+
     // Get list of anime to process
-    let anime_list = get_anime_list(&db, args.mal_id)?;
+    let anime_list = if args.recheck_no_candidates {
+        info!("Re-checking anime previously marked as having no candidates");
+        get_no_candidates_list(&db, args.mal_id)?
+    } else {
+        if args.only_uncached {
+            info!("Only processing anime without a cached selection");
+        }
+        get_anime_list(&db, args.mal_id, args.only_uncached)?
+    };
     info!("Found {} anime to process", anime_list.len());
 
+    // Estimate mode: count what a real run would send to Claude
+    if args.estimate {
+        let queue = JobQueue::new(db);
+        SelectionEstimate::new(&queue, &anime_list, args.recheck_no_candidates)?.print();
+        return Ok(());
+    }
+
+    // Fail now rather than on every selection
+    let api_key = require_api_key(&config.anthropic, args.dry_run, |name| std::env::var(name).ok())?;
+
     if anime_list.is_empty() {
-        info!("No anime to process. Run mal-scraper first.");
+        if args.recheck_no_candidates {
+            info!("No anime marked as no_candidates.");
+        } else {
+            info!("No anime to process. Run mal-scraper first.");
+        }
         return Ok(());
     }
 
@@ -141,8 +259,11 @@ async fn main() -> Result<()> {
     let stats = process_anime_batch(
         anime_list,
         &config,
+        api_key,
         args.workers,
+        args.batch_size,
         args.dry_run,
+        args.recheck_no_candidates,
     ).await?;
 
     // Print summary
@@ -151,31 +272,99 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// The Anthropic API key for selections (see `AnthropicConfig::resolve_api_key`)
+///
+/// Without one, every selection would fail after its AllAnime search, so this
+/// is an error unless `dry_run`, which gets an empty key.
+fn require_api_key(
+    config: &AnthropicConfig,
+    dry_run: bool,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<String> {
+    match config.resolve_api_key(var) {
+        Some(key) => Ok(key),
+        None if dry_run => {
+            warn!("No Anthropic API key configured, selections will fail");
+            Ok(String::new())
+        }
+        None => anyhow::bail!(
+            "No Anthropic API key configured: set api_key under [anthropic] in config.toml \
+             or the {} environment variable, or run with --dry-run",
+            AnthropicConfig::API_KEY_ENV_VARS.join(" or ")
+        ),
+    }
+}
+
+/// Reset an anime's jobs and cached selection (see `JobQueue::reset_anime`)
+fn reset_anime(db: Database, mal_id: u32, data_paths: Option<&DataPaths>) -> Result<()> {
+    let mut queue = JobQueue::new(db);
+    let jobs = queue
+        .reset_anime(mal_id, data_paths)
+        .with_context(|| format!("Failed to reset anime {}", mal_id))?;
+
+    if jobs == 0 {
+        warn!("No jobs found for MAL ID {}", mal_id);
+    } else {
+        info!("Reset {} jobs for MAL ID {} to queued", jobs, mal_id);
+    }
+    if data_paths.is_some() {
+        info!("Deleted files for MAL ID {}", mal_id);
+    }
+    info!("Run `anime-selector --mal-id {}` to select it again", mal_id);
+
+    Ok(())
+}
+
 /// Get list of anime from database
-fn get_anime_list(db: &Database, mal_id: Option<u32>) -> Result<Vec<AnimeRecord>> {
+///
+/// With `only_uncached`, anime that already have a row in
+/// `anime_selection_cache` (of any confidence) are left out.
+fn get_anime_list(db: &Database, mal_id: Option<u32>, only_uncached: bool) -> Result<Vec<AnimeRecord>> {
     let conn = db.conn();
 
-    let query = if let Some(id) = mal_id {
-        format!(
-            "SELECT mal_id, title, title_english, episodes_total, year, type
-             FROM anime WHERE mal_id = {}",
-            id
-        )
-    } else {
-        "SELECT mal_id, title, title_english, episodes_total, year, type
-         FROM anime
-         ORDER BY rank ASC".to_string()
-    };
+    let mut stmt = conn.prepare(
+        "SELECT a.mal_id, a.title, a.episodes_total, a.year, a.type
+         FROM anime a
+         LEFT JOIN anime_selection_cache s ON s.mal_id = a.mal_id
+         WHERE (?1 IS NULL OR a.mal_id = ?1) AND (?2 = 0 OR s.mal_id IS NULL)
+         ORDER BY a.rank ASC"
+    )?;
+    let anime_iter = stmt.query_map((mal_id, only_uncached), |row| {
+        Ok(AnimeRecord {
+            mal_id: row.get(0)?,
+            title: row.get(1)?,
+            episodes_total: row.get(2)?,
+            year: row.get(3)?,
+            anime_type: row.get(4)?,
+        })
+    })?;
+
+    let mut anime_list = Vec::new();
+    for anime in anime_iter {
+        anime_list.push(anime?);
+    }
+
+    Ok(anime_list)
+}
 
-    let mut stmt = conn.prepare(&query)?;
-    let anime_iter = stmt.query_map([], |row| {
+/// Get anime whose cached selection is `no_candidates`
+fn get_no_candidates_list(db: &Database, mal_id: Option<u32>) -> Result<Vec<AnimeRecord>> {
+    let conn = db.conn();
+
+    let mut stmt = conn.prepare(
+        "SELECT a.mal_id, a.title, a.episodes_total, a.year, a.type
+         FROM anime a
+         JOIN anime_selection_cache s ON s.mal_id = a.mal_id
+         WHERE s.confidence = ?1 AND (?2 IS NULL OR a.mal_id = ?2)
+         ORDER BY a.rank ASC"
+    )?;
+    let anime_iter = stmt.query_map((Confidence::NoCandidates.as_str(), mal_id), |row| {
         Ok(AnimeRecord {
             mal_id: row.get(0)?,
             title: row.get(1)?,
-            title_english: row.get(2)?,
-            episodes_total: row.get(3)?,
-            year: row.get(4)?,
-            anime_type: row.get(5)?,
+            episodes_total: row.get(2)?,
+            year: row.get(3)?,
+            anime_type: row.get(4)?,
         })
     })?;
 
@@ -188,60 +377,53 @@ fn get_anime_list(db: &Database, mal_id: Option<u32>) -> Result<Vec<AnimeRecord>
 }
 
 /// Process batch of anime with concurrent workers
+///
+/// Anime are grouped into chunks of `batch_size`; each chunk is handled by one
+/// worker and selected with a single `select_batch` call, so Claude gets one
+/// request per chunk.
 async fn process_anime_batch(
     anime_list: Vec<AnimeRecord>,
     config: &Config,
+    api_key: String,
     workers: usize,
+    batch_size: usize,
     dry_run: bool,
+    recheck: bool,
 ) -> Result<SelectionStats> {
     let stats = Arc::new(tokio::sync::Mutex::new(SelectionStats::new()));
     let semaphore = Arc::new(Semaphore::new(workers));
     let db_path = config.database_path().to_string_lossy().to_string();
-    let api_key = config.anthropic.api_key.clone();
+    let backend = Arc::new(LiveBackend {
+        claude: ClaudeClient::new(&config.anthropic, api_key)?,
+    });
 
     let mut tasks = Vec::new();
+    let mut anime_iter = anime_list.into_iter().peekable();
 
-    for anime in anime_list {
+    while anime_iter.peek().is_some() {
+        let chunk: Vec<AnimeRecord> = anime_iter.by_ref().take(batch_size.max(1)).collect();
         let sem_permit = semaphore.clone().acquire_owned().await?;
         let stats_clone = stats.clone();
         let db_path_clone = db_path.clone();
-        let api_key_clone = api_key.clone();
+        let backend_clone = backend.clone();
 
         let task = tokio::spawn(async move {
-            let result = process_anime(anime, &db_path_clone, &api_key_clone, dry_run).await;
+            let results = if chunk.len() == 1 {
+                let anime = chunk.into_iter().next().unwrap();
+                vec![process_anime(anime, &db_path_clone, backend_clone.as_ref(), dry_run, recheck).await]
+            } else {
+                process_anime_chunk(chunk, &db_path_clone, backend_clone.as_ref(), dry_run, recheck)
+                    .await
+                    .unwrap_or_else(|e| vec![Err(e)])
+            };
 
             // Update stats
             let mut stats_guard = stats_clone.lock().await;
-            stats_guard.total += 1;
-
-            match &result {
-                Ok(Some(ref confidence)) => {
-                    match confidence.as_str() {
-                        "no_candidates" => {
-                            stats_guard.no_candidates += 1;
-                        }
-                        "high" | "medium" | "low" => {
-                            stats_guard.selected += 1;
-                            match confidence.as_str() {
-                                "high" => stats_guard.high_confidence += 1,
-                                "medium" => stats_guard.medium_confidence += 1,
-                                "low" => stats_guard.low_confidence += 1,
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                Ok(None) => {
-                    stats_guard.cached += 1;
-                }
-                Err(_) => {
-                    stats_guard.errors += 1;
-                }
+            for result in &results {
+                stats_guard.record(result);
             }
 
             drop(sem_permit);
-            result
         });
 
         tasks.push(task);
@@ -256,24 +438,156 @@ async fn process_anime_batch(
     Ok(final_stats)
 }
 
-/// Process a single anime
-async fn process_anime(
-    anime: AnimeRecord,
-    db_path: &str,
-    api_key: &str,
-    dry_run: bool,
-) -> Result<Option<String>> {
-    // Check if already cached
-    let db = Database::open(db_path)?;
-    let mut queue = JobQueue::new(db);
+/// Source of candidates and selections for an anime
+trait SelectionBackend {
+    /// Search AllAnime for candidate titles
+    async fn candidates(&self, title: &str) -> Result<Vec<String>>;
+
+    /// Pick the best candidate for the given MAL entry
+    async fn select(&self, anime: &AnimeRecord, candidates: &[String]) -> Result<SelectionResult>;
+
+    /// Pick the best candidate for several MAL entries at once
+    ///
+    /// Results are returned in input order. The default implementation
+    /// selects one anime at a time.
+    async fn select_batch(
+        &self,
+        items: &[(&AnimeRecord, Vec<String>)],
+    ) -> Result<Vec<Result<SelectionResult>>> {
+        let mut results = Vec::with_capacity(items.len());
+        for (anime, candidates) in items {
+            results.push(self.select(anime, candidates).await);
+        }
+        Ok(results)
+    }
+}
+
+/// Backend searching AllAnime with `get_anime_candidates.sh` and asking
+/// Claude over HTTP
+struct LiveBackend {
+    claude: ClaudeClient,
+}
+
+impl SelectionBackend for LiveBackend {
+    async fn candidates(&self, title: &str) -> Result<Vec<String>> {
+        get_anime_candidates(title).await
+    }
+
+    async fn select(&self, anime: &AnimeRecord, candidates: &[String]) -> Result<SelectionResult> {
+        self.claude.select(anime, candidates).await
+    }
+
+    async fn select_batch(
+        &self,
+        items: &[(&AnimeRecord, Vec<String>)],
+    ) -> Result<Vec<Result<SelectionResult>>> {
+        Ok(self.claude.select_batch(items).await)
+    }
+}
+
+/// Rough input tokens of one selection prompt (instructions plus a typical
+/// candidate list, as built by `claude::selection_prompt`)
+const INPUT_TOKENS_PER_SELECTION: u64 = 600;
+
+/// Rough output tokens of one selection (a short JSON answer)
+const OUTPUT_TOKENS_PER_SELECTION: u64 = 80;
+
+/// Claude Haiku price in USD per million input and output tokens
+const PRICE_PER_MILLION_TOKENS: (f64, f64) = (0.80, 4.00);
+
+/// Expected Claude usage of a selection run, for `--estimate`
+#[derive(Debug, Clone, PartialEq)]
+struct SelectionEstimate {
+    /// Anime in the run
+    total: usize,
+    /// Anime that would be searched and sent to Claude (the rest are cached)
+    to_select: usize,
+}
+
+impl SelectionEstimate {
+    /// Count the anime of `anime_list` that a run would select, skipping
+    /// cached ones exactly as `prepare_anime` does
+    fn new(queue: &JobQueue, anime_list: &[AnimeRecord], recheck: bool) -> Result<Self> {
+        let mut to_select = 0;
+        for anime in anime_list {
+            if needs_selection(queue, anime.mal_id, recheck)? {
+                to_select += 1;
+            }
+        }
+
+        Ok(Self {
+            total: anime_list.len(),
+            to_select,
+        })
+    }
+
+    /// Claude requests, at most one per anime to select (fewer in practice:
+    /// anime with no or a single candidate are settled without one)
+    fn requests(&self) -> u64 {
+        self.to_select as u64
+    }
+
+    fn input_tokens(&self) -> u64 {
+        self.requests() * INPUT_TOKENS_PER_SELECTION
+    }
+
+    fn output_tokens(&self) -> u64 {
+        self.requests() * OUTPUT_TOKENS_PER_SELECTION
+    }
+
+    fn cost_usd(&self) -> f64 {
+        let (input, output) = PRICE_PER_MILLION_TOKENS;
+        (self.input_tokens() as f64 * input + self.output_tokens() as f64 * output) / 1_000_000.0
+    }
+
+    fn print(&self) {
+        info!("=== Selection Estimate ===");
+        info!("Total anime: {}", self.total);
+        info!("Already cached: {}", self.total - self.to_select);
+        info!("To select: {}", self.to_select);
+        info!("Claude requests: up to {}", self.requests());
+        info!("Tokens: ~{} input, ~{} output", self.input_tokens(), self.output_tokens());
+        info!("Cost: ~${:.2}", self.cost_usd());
+    }
+}
 
-    if let Some(_selection) = queue.get_selection(anime.mal_id)? {
+/// Whether an anime still needs a selection, i.e. has no cached one or
+/// (with `recheck`) one that was not set manually
+fn needs_selection(queue: &JobQueue, mal_id: u32, recheck: bool) -> Result<bool> {
+    Ok(match queue.get_selection(mal_id)? {
+        Some(selection) => recheck && !selection.manual,
+        None => true,
+    })
+}
+
+/// Outcome of the pre-selection steps for one anime
+enum Prepared {
+    /// A selection is already cached
+    Cached,
+    /// AllAnime returned nothing (already recorded as `no_candidates`)
+    NoCandidates,
+    /// Candidates are ready for selection
+    Candidates(Vec<String>),
+}
+
+/// Check the cache and fetch candidates for an anime
+///
+/// With `recheck`, an existing cached selection is ignored, unless it was set
+/// manually.
+async fn prepare_anime(
+    anime: &AnimeRecord,
+    queue: &mut JobQueue,
+    backend: &impl SelectionBackend,
+    dry_run: bool,
+    recheck: bool,
+) -> Result<Prepared> {
+    if !needs_selection(queue, anime.mal_id, recheck)? {
         debug!(
             mal_id = anime.mal_id,
             title = %anime.title,
             "Using cached selection"
         );
-        return Ok(None);
+        return Ok(Prepared::Cached);
     }
 
     info!(
@@ -283,7 +597,7 @@ async fn process_anime(
     );
 
     // Get candidates from AllAnime
-    let candidates = match get_anime_candidates(&anime.title).await {
+    let candidates = match backend.candidates(&anime.title).await {
         Ok(c) if !c.is_empty() => c,
         Ok(_) | Err(_) => {
             // No candidates found or API error - mark as skipped
@@ -301,7 +615,7 @@ async fn process_anime(
                     &anime.title,
                     -1,  // Special marker for "no candidates"
                     "N/A",
-                    "no_candidates",
+                    Confidence::NoCandidates,
                     Some("No candidates found from AllAnime (likely adult content or not available)"),
                     anime.episodes_total,
                     None,
@@ -309,7 +623,7 @@ async fn process_anime(
                 )?;
             }
 
-            return Ok(Some("no_candidates".to_string()));
+            return Ok(Prepared::NoCandidates);
         }
     };
 
@@ -319,23 +633,26 @@ async fn process_anime(
         "Got candidates from AllAnime"
     );
 
-    // Use Claude to select
-    let selection_result = match select_with_claude(&anime, &candidates, api_key).await {
-        Ok(r) => r,
-        Err(e) => {
-            error!(
-                mal_id = anime.mal_id,
-                title = %anime.title,
-                error = %e,
-                "Failed to select with Claude"
-            );
-            return Err(e);
-        }
-    };
+    Ok(Prepared::Candidates(candidates))
+}
 
-    let selected_title = candidates.get((selection_result.index - 1) as usize)
+/// Cache a selection result and return its confidence
+fn finish_anime(
+    anime: &AnimeRecord,
+    candidates: &[String],
+    selection_result: SelectionResult,
+    queue: &mut JobQueue,
+    dry_run: bool,
+) -> Result<Confidence> {
+    let selected_title = usize::try_from(selection_result.index)
+        .ok()
+        .and_then(|index| index.checked_sub(1))
+        .and_then(|index| candidates.get(index))
         .cloned()
-        .unwrap_or_else(|| candidates[0].clone());
+        .with_context(|| format!(
+            "Selected index {} for MAL {} is outside 1..={}",
+            selection_result.index, anime.mal_id, candidates.len()
+        ))?;
 
     info!(
         mal_id = anime.mal_id,
@@ -354,7 +671,7 @@ async fn process_anime(
             &anime.title,
             selection_result.index,
             &selected_title,
-            &selection_result.confidence,
+            selection_result.confidence,
             Some(&selection_result.reason),
             selection_result.mal_episodes,
             selection_result.selected_episodes,
@@ -362,118 +679,130 @@ async fn process_anime(
         )?;
     }
 
-    Ok(Some(selection_result.confidence))
+    Ok(selection_result.confidence)
 }
 
-/// Get anime candidates from AllAnime API
-async fn get_anime_candidates(title: &str) -> Result<Vec<String>> {
-    let output = Command::new("zsh")
-        .arg("scripts/get_anime_candidates.sh")
-        .arg(title)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .context("Failed to execute get_anime_candidates.sh")?;
+/// Process a single anime
+///
+/// With `recheck`, an existing cached selection is ignored and overwritten,
+/// unless it was set manually.
+async fn process_anime(
+    anime: AnimeRecord,
+    db_path: &str,
+    backend: &impl SelectionBackend,
+    dry_run: bool,
+    recheck: bool,
+) -> Result<Option<Confidence>> {
+    let db = Database::open(db_path)?;
+    let mut queue = JobQueue::new(db);
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow::anyhow!("get_anime_candidates.sh failed: {}", stderr));
-    }
+    let candidates = match prepare_anime(&anime, &mut queue, backend, dry_run, recheck).await? {
+        Prepared::Cached => return Ok(None),
+        Prepared::NoCandidates => return Ok(Some(Confidence::NoCandidates)),
+        Prepared::Candidates(c) => c,
+    };
 
-    let candidates: Vec<String> = serde_json::from_slice(&output.stdout)
-        .context("Failed to parse candidates JSON")?;
+    // Use Claude to select
+    let selection_result = match backend.select(&anime, &candidates).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!(
+                mal_id = anime.mal_id,
+                title = %anime.title,
+                error = %e,
+                "Failed to select with Claude"
+            );
+            return Err(e);
+        }
+    };
 
-    Ok(candidates)
+    finish_anime(&anime, &candidates, selection_result, &mut queue, dry_run).map(Some)
 }
 
-/// Select anime using Claude Haiku
-async fn select_with_claude(
-    anime: &AnimeRecord,
-    candidates: &[String],
-    api_key: &str,
-) -> Result<SelectionResult> {
-    let candidates_json = serde_json::to_string(candidates)?;
-
-    // Helper function to quote arguments for use inside zsh -c '...'
-    // Use double quotes and escape ", $, `, and \
-    fn shell_quote(s: &str) -> String {
-        let escaped = s
-            .replace('\\', r"\\")
-            .replace('"', r#"\""#)
-            .replace('$', r"\$")
-            .replace('`', r"\`");
-        format!(r#""{}""#, escaped)
-    }
-
-    // Helper function to use single quotes (for strings without variables/substitution)
-    // Single quotes prevent all special character processing including !
-    // To include a literal single quote, we end the quote, add \', and start a new quote
-    fn shell_quote_single(s: &str) -> String {
-        let escaped = s.replace('\'', r"'\''");
-        format!("'{}'", escaped)
-    }
-
-    // Build Python command with properly quoted arguments
-    // Use single quotes for candidates JSON to avoid ! expansion issues
-    let mut python_cmd = format!(
-        "scripts/select_anime.py --mal-title {} --candidates {}",
-        shell_quote(&anime.title),
-        shell_quote_single(&candidates_json)
-    );
+/// Process several anime with a single batched selection call
+///
+/// Returns one result per input anime, in input order.
+async fn process_anime_chunk(
+    chunk: Vec<AnimeRecord>,
+    db_path: &str,
+    backend: &impl SelectionBackend,
+    dry_run: bool,
+    recheck: bool,
+) -> Result<Vec<Result<Option<Confidence>>>> {
+    let db = Database::open(db_path)?;
+    let mut queue = JobQueue::new(db);
 
-    if let Some(episodes) = anime.episodes_total {
-        python_cmd.push_str(&format!(" --episodes {}", episodes));
-    }
+    let mut results: Vec<Option<Result<Option<Confidence>>>> = Vec::with_capacity(chunk.len());
+    let mut pending: Vec<(usize, Vec<String>)> = Vec::new();
 
-    if let Some(year) = anime.year {
-        python_cmd.push_str(&format!(" --year {}", year));
+    for (i, anime) in chunk.iter().enumerate() {
+        match prepare_anime(anime, &mut queue, backend, dry_run, recheck).await {
+            Ok(Prepared::Cached) => results.push(Some(Ok(None))),
+            Ok(Prepared::NoCandidates) => results.push(Some(Ok(Some(Confidence::NoCandidates)))),
+            Ok(Prepared::Candidates(c)) => {
+                results.push(None);
+                pending.push((i, c));
+            }
+            Err(e) => results.push(Some(Err(e))),
+        }
     }
 
-    if let Some(ref anime_type) = anime.anime_type {
-        python_cmd.push_str(&format!(" --anime-type {}", shell_quote(anime_type)));
-    }
+    if !pending.is_empty() {
+        let items: Vec<(&AnimeRecord, Vec<String>)> = pending
+            .iter()
+            .map(|(i, c)| (&chunk[*i], c.clone()))
+            .collect();
+
+        debug!(count = items.len(), "Selecting batch with Claude");
+        let selections = backend.select_batch(&items).await?;
+        if selections.len() != items.len() {
+            anyhow::bail!(
+                "Batch selection returned {} results for {} anime",
+                selections.len(),
+                items.len()
+            );
+        }
 
-    if !api_key.is_empty() {
-        python_cmd.push_str(&format!(" --api-key {}", shell_quote(api_key)));
+        for ((i, candidates), selection) in pending.iter().zip(selections) {
+            let anime = &chunk[*i];
+            let result = match selection {
+                Ok(r) => finish_anime(anime, candidates, r, &mut queue, dry_run).map(Some),
+                Err(e) => {
+                    error!(
+                        mal_id = anime.mal_id,
+                        title = %anime.title,
+                        error = %e,
+                        "Failed to select with Claude"
+                    );
+                    Err(e)
+                }
+            };
+            results[*i] = Some(result);
+        }
     }
 
-    // Use zsh with conda activation - CRITICAL: zsh required for conda
-    // Disable history expansion with 'set +H' to prevent ! from being escaped
-    let full_cmd = format!(
-        r#"set +H && eval "$(conda shell.zsh hook)" && conda activate GDA2025 && python3 {}"#,
-        python_cmd
-    );
-
-    debug!("Executing command: zsh -c '{}'", full_cmd);
-
-    let mut cmd = Command::new("zsh");
-    cmd.arg("-c").arg(&full_cmd);
+    Ok(results.into_iter().map(|r| r.expect("every anime has a result")).collect())
+}
 
-    let output = cmd
+/// Get anime candidates from AllAnime API
+async fn get_anime_candidates(title: &str) -> Result<Vec<String>> {
+    let output = Command::new("zsh")
+        .arg("scripts/get_anime_candidates.sh")
+        .arg(title)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .output()
-        .context("Failed to execute select_anime.py")?;
+        .context("Failed to execute get_anime_candidates.sh")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        error!(
-            "select_anime.py failed\nstdout: {}\nstderr: {}",
-            stdout, stderr
-        );
-        return Err(anyhow::anyhow!(
-            "select_anime.py failed with exit code {:?}\nstdout: {}\nstderr: {}",
-            output.status.code(),
-            stdout,
-            stderr
-        ));
+        return Err(anyhow::anyhow!("get_anime_candidates.sh failed: {}", stderr));
     }
 
-    let result: SelectionResult = serde_json::from_slice(&output.stdout)
-        .context("Failed to parse selection result JSON")?;
+    let candidates: Vec<String> = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse candidates JSON")?;
 
-    Ok(result)
+    Ok(candidates)
 }
 
 /// Review low-confidence selections
@@ -484,11 +813,11 @@ fn review_selections(db: &Database) -> Result<()> {
     let mut stmt = conn.prepare(
         "SELECT mal_id, anime_title, selected_title, confidence, reason
          FROM anime_selection_cache
-         WHERE confidence = 'low'
+         WHERE confidence = ?1
          ORDER BY mal_id"
     )?;
 
-    let selections = stmt.query_map([], |row| {
+    let selections = stmt.query_map([Confidence::Low.as_str()], |row| {
         Ok((
             row.get::<_, u32>(0)?,
             row.get::<_, String>(1)?,
@@ -516,8 +845,97 @@ fn review_selections(db: &Database) -> Result<()> {
     info!("Total low-confidence selections: {}", count);
 
     if count > 0 {
-        info!("To manually correct a selection, use:");
-        info!("  sqlite3 data/jobs.db \"UPDATE anime_selection_cache SET selected_index=N, selected_title='Title' WHERE mal_id=XXXXX\"");
+        info!("To manually correct a selection (kept by later selection runs), use:");
+        info!("  anime-selector --mal-id XXXXX --set-selection N 'Title'");
+    }
+
+    Ok(())
+}
+
+/// Cached selection whose episode count does not match MAL
+#[derive(Debug)]
+struct EpisodeMismatch {
+    mal_id: u32,
+    anime_title: String,
+    selected_title: String,
+    confidence: Confidence,
+    reason: Option<String>,
+    mal_episodes: Option<i32>,
+    selected_episodes: Option<i32>,
+}
+
+/// Get cached selections marked as episode-count mismatches
+fn get_episode_mismatches(db: &Database) -> Result<Vec<EpisodeMismatch>> {
+    let conn = db.conn();
+    let mut stmt = conn.prepare(
+        "SELECT mal_id, anime_title, selected_title, confidence, reason,
+                mal_episodes, selected_episodes
+         FROM anime_selection_cache
+         WHERE episode_match = 'mismatch'
+         ORDER BY mal_id"
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get::<_, String>(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+        ))
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        let (mal_id, anime_title, selected_title, confidence, reason, mal_episodes, selected_episodes) = row?;
+        result.push(EpisodeMismatch {
+            mal_id,
+            anime_title,
+            selected_title,
+            confidence: confidence.parse()?,
+            reason,
+            mal_episodes,
+            selected_episodes,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Review selections with an episode-count mismatch, regardless of confidence
+fn review_mismatches(db: &Database) -> Result<()> {
+    info!("=== Episode Count Mismatches ===");
+
+    let mismatches = get_episode_mismatches(db)?;
+
+    fn fmt_count(count: Option<i32>) -> String {
+        count.map_or_else(|| "?".to_string(), |c| c.to_string())
+    }
+
+    for m in &mismatches {
+        println!();
+        println!("MAL ID: {}", m.mal_id);
+        println!("Anime: {}", m.anime_title);
+        println!("Selected: {}", m.selected_title);
+        println!(
+            "Episodes: MAL {} / selected {}",
+            fmt_count(m.mal_episodes),
+            fmt_count(m.selected_episodes)
+        );
+        println!("Confidence: {}", m.confidence);
+        if let Some(r) = &m.reason {
+            println!("Reason: {}", r);
+        }
+    }
+
+    println!();
+    info!("Total episode-count mismatches: {}", mismatches.len());
+
+    if !mismatches.is_empty() {
+        info!("To manually correct a selection (kept by later selection runs), use:");
+        info!("  anime-selector --mal-id XXXXX --set-selection N 'Title'");
     }
 
     Ok(())
@@ -538,3 +956,295 @@ impl Clone for SelectionStats {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Backend returning fixed candidates and always picking the first one
+    struct FixedBackend {
+        candidates: Vec<String>,
+    }
+
+    impl SelectionBackend for FixedBackend {
+        async fn candidates(&self, _title: &str) -> Result<Vec<String>> {
+            Ok(self.candidates.clone())
+        }
+
+        async fn select(&self, anime: &AnimeRecord, _candidates: &[String]) -> Result<SelectionResult> {
+            Ok(SelectionResult {
+                index: 1,
+                confidence: Confidence::High,
+                reason: "Main series".to_string(),
+                mal_episodes: anime.episodes_total,
+                selected_episodes: Some(12),
+                episode_match: Some("exact".to_string()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recheck_no_candidates() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let db_path_str = db_path.to_string_lossy().to_string();
+
+        let db = Database::open(&db_path)?;
+        db.conn().execute(
+            "INSERT INTO anime (mal_id, title, episodes_total) VALUES (5114, 'Test Anime', 12)",
+            [],
+        )?;
+        let mut queue = JobQueue::new(db);
+        queue.cache_selection(
+            5114, "Test Anime", "Test Anime", -1, "N/A", Confidence::NoCandidates,
+            None, Some(12), None, Some("unknown"),
+        )?;
+
+        let db = Database::open(&db_path)?;
+        let to_recheck = get_no_candidates_list(&db, None)?;
+        assert_eq!(to_recheck.len(), 1);
+
+        // Still nothing on AllAnime: row stays no_candidates
+        let empty = FixedBackend { candidates: Vec::new() };
+        let anime = to_recheck.into_iter().next().unwrap();
+        let result = process_anime(anime, &db_path_str, &empty, false, true).await?;
+        assert_eq!(result, Some(Confidence::NoCandidates));
+
+        // Title has since appeared: row is re-evaluated and updated
+        let available = FixedBackend {
+            candidates: vec!["Test Anime (12 eps)".to_string()],
+        };
+        let anime = get_no_candidates_list(&db, Some(5114))?.into_iter().next().unwrap();
+        let result = process_anime(anime, &db_path_str, &available, false, true).await?;
+        assert_eq!(result, Some(Confidence::High));
+
+        let selection = queue.get_selection(5114)?.unwrap();
+        assert_eq!(selection.selected_index, 1);
+        assert_eq!(selection.selected_title, "Test Anime (12 eps)");
+        assert_eq!(selection.confidence, Confidence::High);
+        assert!(get_no_candidates_list(&db, None)?.is_empty());
+
+        Ok(())
+    }
+
+    /// Create a database with two anime that have candidates and one that does not
+    fn seed_db(dir: &TempDir, name: &str) -> Result<String> {
+        let db_path = dir.path().join(name);
+        let db = Database::open(&db_path)?;
+        db.conn().execute(
+            "INSERT INTO anime (mal_id, title, episodes_total) VALUES
+             (1, 'First', 12), (2, 'Second', 24), (3, 'Missing', 12)",
+            [],
+        )?;
+        Ok(db_path.to_string_lossy().to_string())
+    }
+
+    /// Backend with no candidates for titles starting with "Missing"
+    struct PartialBackend(FixedBackend);
+
+    impl SelectionBackend for PartialBackend {
+        async fn candidates(&self, title: &str) -> Result<Vec<String>> {
+            if title.starts_with("Missing") {
+                Ok(Vec::new())
+            } else {
+                self.0.candidates(title).await
+            }
+        }
+
+        async fn select(&self, anime: &AnimeRecord, candidates: &[String]) -> Result<SelectionResult> {
+            self.0.select(anime, candidates).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batched_selection_matches_single() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let single_path = seed_db(&temp_dir, "single.db")?;
+        let batch_path = seed_db(&temp_dir, "batch.db")?;
+        let backend = PartialBackend(FixedBackend {
+            candidates: vec!["Show (12 eps)".to_string(), "Show Specials".to_string()],
+        });
+
+        let anime_list = get_anime_list(&Database::open(&single_path)?, None, false)?;
+        let mut single_results = Vec::new();
+        for anime in anime_list {
+            single_results.push(process_anime(anime, &single_path, &backend, false, false).await?);
+        }
+
+        let anime_list = get_anime_list(&Database::open(&batch_path)?, None, false)?;
+        let batch_results = process_anime_chunk(anime_list, &batch_path, &backend, false, false)
+            .await?
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(single_results, batch_results);
+        assert_eq!(
+            batch_results,
+            vec![Some(Confidence::High), Some(Confidence::High), Some(Confidence::NoCandidates)]
+        );
+
+        let single = JobQueue::new(Database::open(&single_path)?);
+        let batch = JobQueue::new(Database::open(&batch_path)?);
+        for mal_id in 1..=3 {
+            let a = single.get_selection(mal_id)?.unwrap();
+            let b = batch.get_selection(mal_id)?.unwrap();
+            assert_eq!(a.selected_index, b.selected_index);
+            assert_eq!(a.selected_title, b.selected_title);
+            assert_eq!(a.confidence, b.confidence);
+            assert_eq!(a.episode_match, b.episode_match);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_range_index_is_not_cached() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = seed_db(&temp_dir, "jobs.db")?;
+        let mut queue = JobQueue::new(Database::open(&db_path)?);
+        let anime = get_anime_list(&Database::open(&db_path)?, Some(1), false)?.remove(0);
+        let candidates = vec!["First (12 eps)".to_string(), "First Specials".to_string()];
+        let pick = |index| SelectionResult {
+            index,
+            confidence: Confidence::High,
+            reason: "Main series".to_string(),
+            mal_episodes: Some(12),
+            selected_episodes: Some(12),
+            episode_match: Some("exact".to_string()),
+        };
+
+        for index in [-1, 0, 3] {
+            assert!(finish_anime(&anime, &candidates, pick(index), &mut queue, false).is_err());
+        }
+        assert!(queue.get_selection(1)?.is_none());
+
+        finish_anime(&anime, &candidates, pick(2), &mut queue, false)?;
+        assert_eq!(queue.get_selection(1)?.unwrap().selected_title, "First Specials");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_uncached_skips_selected_anime() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = seed_db(&temp_dir, "jobs.db")?;
+        let mut queue = JobQueue::new(Database::open(&db_path)?);
+        queue.cache_selection(
+            1, "First", "First", 1, "First (12 eps)", Confidence::High,
+            None, Some(12), Some(12), Some("exact"),
+        )?;
+        queue.cache_selection(
+            3, "Missing", "Missing", -1, "N/A", Confidence::NoCandidates,
+            None, Some(12), None, Some("unknown"),
+        )?;
+
+        let db = Database::open(&db_path)?;
+        let mal_ids = |list: Vec<AnimeRecord>| list.into_iter().map(|a| a.mal_id).collect::<Vec<_>>();
+        assert_eq!(mal_ids(get_anime_list(&db, None, false)?).len(), 3);
+        assert_eq!(mal_ids(get_anime_list(&db, None, true)?), vec![2]);
+        assert!(get_anime_list(&db, Some(1), true)?.is_empty());
+        assert_eq!(mal_ids(get_anime_list(&db, Some(1), false)?), vec![1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_counts_uncached_anime() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = seed_db(&temp_dir, "jobs.db")?;
+        let mut queue = JobQueue::new(Database::open(&db_path)?);
+        queue.cache_selection(
+            1, "First", "First", 1, "First (12 eps)", Confidence::High,
+            None, Some(12), Some(12), Some("exact"),
+        )?;
+        queue.cache_selection(
+            3, "Missing", "Missing", -1, "N/A", Confidence::NoCandidates,
+            None, Some(12), None, Some("unknown"),
+        )?;
+
+        // Only the uncached anime would be sent to Claude
+        let db = Database::open(&db_path)?;
+        let uncached = get_anime_list(&db, None, true)?;
+        let estimate = SelectionEstimate::new(&queue, &get_anime_list(&db, None, false)?, false)?;
+        assert_eq!(estimate.total, 3);
+        assert_eq!(estimate.to_select, uncached.len());
+        assert_eq!(estimate.requests(), 1);
+        assert_eq!(estimate.input_tokens(), INPUT_TOKENS_PER_SELECTION);
+        assert!(estimate.cost_usd() > 0.0);
+
+        // Re-checking counts the no_candidates anime, unless set by hand
+        let estimate = SelectionEstimate::new(&queue, &get_no_candidates_list(&db, None)?, true)?;
+        assert_eq!(estimate.to_select, 1);
+        queue.set_manual_selection(3, 1, "Missing (12 eps)")?;
+        let estimate = SelectionEstimate::new(&queue, &get_anime_list(&db, None, false)?, true)?;
+        assert_eq!(estimate.to_select, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_episode_mismatches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db = Database::open(temp_dir.path().join("jobs.db"))?;
+        db.conn().execute(
+            "INSERT INTO anime (mal_id, title) VALUES (1, 'Exact'), (2, 'Wrong Season'), (3, 'Low')",
+            [],
+        )?;
+        let mut queue = JobQueue::new(db);
+
+        queue.cache_selection(
+            1, "Exact", "Exact", 1, "Exact (12 eps)", Confidence::High,
+            Some("Main series"), Some(12), Some(12), Some("exact"),
+        )?;
+        queue.cache_selection(
+            2, "Wrong Season", "Wrong Season", 1, "Wrong Season (25 eps)", Confidence::High,
+            Some("Title matches"), Some(12), Some(25), Some("mismatch"),
+        )?;
+        queue.cache_selection(
+            3, "Low", "Low", 2, "Low (13 eps)", Confidence::Low,
+            Some("Unsure"), Some(12), Some(13), Some("close"),
+        )?;
+
+        let mismatches = get_episode_mismatches(&Database::open(temp_dir.path().join("jobs.db"))?)?;
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].mal_id, 2);
+        assert_eq!(mismatches[0].confidence, Confidence::High);
+        assert_eq!(mismatches[0].mal_episodes, Some(12));
+        assert_eq!(mismatches[0].selected_episodes, Some(25));
+        assert_eq!(mismatches[0].reason.as_deref(), Some("Title matches"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_api_key_fails_at_startup() -> Result<()> {
+        let no_env = |_: &str| None;
+        let config = AnthropicConfig::default();
+
+        let err = require_api_key(&config, false, no_env).unwrap_err().to_string();
+        assert!(err.contains("No Anthropic API key configured"), "{}", err);
+        assert!(err.contains("GDA2025_ANTHROPIC_API_KEY"), "{}", err);
+        assert_eq!(require_api_key(&config, true, no_env)?, "");
+
+        // Blank values count as missing
+        let blank = AnthropicConfig {
+            api_key: "  ".to_string(),
+            ..Default::default()
+        };
+        assert!(require_api_key(&blank, false, |_| Some(String::new())).is_err());
+
+        // The config file wins, then GDA2025_ANTHROPIC_API_KEY, then ANTHROPIC_API_KEY
+        let env = |name: &str| Some(format!("key-from-{}", name));
+        assert_eq!(require_api_key(&config, false, env)?, "key-from-GDA2025_ANTHROPIC_API_KEY");
+        let fallback = |name: &str| (name == "ANTHROPIC_API_KEY").then(|| "sk-ant-env".to_string());
+        assert_eq!(require_api_key(&config, false, fallback)?, "sk-ant-env");
+        let configured = AnthropicConfig {
+            api_key: "sk-ant-config".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(require_api_key(&configured, false, env)?, "sk-ant-config");
+
+        Ok(())
+    }
+}
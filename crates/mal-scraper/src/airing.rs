@@ -0,0 +1,87 @@
+//! Episodes aired so far of currently airing anime.
+//!
+//! For a show that is still airing, MAL's episode count is the planned total
+//! (or missing), so enqueueing every episode would create jobs for episodes
+//! that cannot be downloaded yet. Assuming the usual weekly broadcast from the
+//! first air date, only the episodes likely aired by now are enqueued; the
+//! `update-airing` command picks up the rest as they air.
+
+use chrono::NaiveDate;
+use shared::Anime;
+
+/// Days between the broadcasts of consecutive episodes
+const DAYS_PER_EPISODE: i64 = 7;
+
+/// Number of episodes of `anime` that have likely aired by `today`.
+///
+/// Anime that are not airing count all their episodes, except announced ones
+/// that have not started, which count none. An airing anime counts one
+/// episode per week since its first air date, up to the planned total; one
+/// without a first air date counts its total, as before.
+pub fn episodes_aired(anime: &Anime, today: NaiveDate) -> u32 {
+    let total = anime.episodes_total;
+    if anime.status.as_deref() == Some(Anime::STATUS_NOT_YET_AIRED) {
+        return 0;
+    }
+    if !anime.is_airing() || anime.aired_to.is_some_and(|end| end <= today) {
+        return total.unwrap_or(0);
+    }
+    let Some(start) = anime.aired_from else {
+        return total.unwrap_or(0);
+    };
+
+    let days = (today - start).num_days();
+    if days < 0 {
+        return 0;
+    }
+    let aired = u32::try_from(days / DAYS_PER_EPISODE + 1).unwrap_or(u32::MAX);
+    total.map_or(aired, |total| aired.min(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn test_anime(status: &str, episodes_total: Option<u32>, aired_from: Option<NaiveDate>) -> Anime {
+        Anime {
+            episodes_total,
+            status: Some(status.to_string()),
+            aired_from,
+            season: Some("fall".to_string()),
+            year: Some(2023),
+            duration_minutes: Some(24),
+            ..Anime::test_fixture(52991, "Sousou no Frieren", 0)
+        }
+    }
+
+    #[test]
+    fn test_episodes_aired() {
+        let start = NaiveDate::from_ymd_opt(2023, 9, 29).unwrap();
+        let airing = test_anime(Anime::STATUS_AIRING, Some(28), Some(start));
+
+        assert_eq!(episodes_aired(&airing, start), 1);
+        assert_eq!(episodes_aired(&airing, start + Duration::days(6)), 1);
+        assert_eq!(episodes_aired(&airing, start + Duration::days(7 * 7)), 8);
+        assert_eq!(episodes_aired(&airing, start + Duration::days(365)), 28);
+        assert_eq!(episodes_aired(&airing, start - Duration::days(1)), 0);
+
+        // The planned total is often unknown while airing
+        let open_ended = test_anime(Anime::STATUS_AIRING, None, Some(start));
+        assert_eq!(episodes_aired(&open_ended, start + Duration::days(7 * 7)), 8);
+
+        // Once MAL lists an end date that has passed, every episode is out
+        let ended = Anime {
+            aired_to: Some(start + Duration::days(30)),
+            ..airing.clone()
+        };
+        assert_eq!(episodes_aired(&ended, start + Duration::days(31)), 28);
+
+        let finished = test_anime("Finished Airing", Some(28), Some(start));
+        assert_eq!(episodes_aired(&finished, start), 28);
+        let announced = test_anime(Anime::STATUS_NOT_YET_AIRED, Some(12), None);
+        assert_eq!(episodes_aired(&announced, start), 0);
+        let undated = test_anime(Anime::STATUS_AIRING, Some(12), None);
+        assert_eq!(episodes_aired(&undated, start), 12);
+    }
+}
@@ -0,0 +1,74 @@
+//! Request budget: a hard cap on the number of API requests in one run.
+//!
+//! Unlike the rate limiter, which only spaces requests out, the budget stops
+//! them altogether once it is used up. Clones share the same counter.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Returned instead of a request once the request budget is used up
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("request budget of {limit} requests is used up")]
+pub struct BudgetExhausted {
+    /// Total number of requests the budget allowed
+    pub limit: u64,
+}
+
+/// Number of requests still allowed, shared between clones
+#[derive(Debug, Clone)]
+pub struct RequestBudget {
+    /// Total number of requests allowed
+    limit: u64,
+    /// Requests made so far
+    used: Arc<AtomicU64>,
+}
+
+impl RequestBudget {
+    /// Create a budget of `limit` requests
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Take one request from the budget
+    pub fn try_acquire(&self) -> Result<(), BudgetExhausted> {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| (used < self.limit).then_some(used + 1))
+            .map(|_| ())
+            .map_err(|_| BudgetExhausted { limit: self.limit })
+    }
+
+    /// Total number of requests allowed
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Requests made so far
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Requests still allowed
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_is_shared_between_clones() {
+        let budget = RequestBudget::new(2);
+        let clone = budget.clone();
+
+        assert!(budget.try_acquire().is_ok());
+        assert!(clone.try_acquire().is_ok());
+        assert_eq!(budget.remaining(), 0);
+        assert_eq!(budget.try_acquire(), Err(BudgetExhausted { limit: 2 }));
+        assert_eq!(clone.used(), 2);
+    }
+}
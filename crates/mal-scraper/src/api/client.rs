@@ -1,25 +1,83 @@
 //! Jikan API client with rate limiting and retry logic.
 
-use super::rate_limiter::RateLimiter;
+use super::budget::RequestBudget;
 use super::types::*;
-use anyhow::{anyhow, Context, Result};
-use reqwest::{Client, StatusCode};
-use std::time::Duration;
-use tokio::time::sleep;
+use shared::{Backoff, RateLimiter, RetryPolicy, RetryingClient, Season};
+use anyhow::{Context, Result};
+use reqwest::{Client, Url};
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
+/// Anime fetched by `JikanClient::health_check` (Cowboy Bebop, a stable entry)
+const HEALTH_CHECK_ANIME_ID: u32 = 1;
+
+/// Timeout of each request attempt, unless set with `with_request_timeout`
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fraction of each retry delay that is random
+const RETRY_JITTER: f64 = 0.25;
+
+/// Outcome of `JikanClient::health_check`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiHealth {
+    /// The response deserialized into the expected types
+    Healthy { latency: Duration },
+    /// The API answered, but the response no longer matches our types
+    Incompatible { error: String },
+    /// The API could not be reached (network error or error status)
+    Unreachable { error: String },
+}
+
+impl ApiHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, ApiHealth::Healthy { .. })
+    }
+}
+
+/// Returned instead of a request when the client is offline, meaning the
+/// response was not in the cache
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{endpoint} is not cached and the client is offline")]
+pub struct CacheMiss {
+    /// API endpoint that would have been requested
+    pub endpoint: String,
+}
+
+/// Connection pool and keep-alive settings for the HTTP client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Idle connections kept open to the API host
+    pub pool_max_idle: usize,
+    /// How long an idle connection stays in the pool (None keeps it forever)
+    pub pool_idle_timeout: Option<Duration>,
+    /// Interval of HTTP/2 keep-alive pings, also sent while idle (None disables them)
+    pub http2_keep_alive: Option<Duration>,
+    /// Proxy URL for every request (None uses the `HTTP_PROXY`/`HTTPS_PROXY`
+    /// environment variables, if set)
+    pub proxy: Option<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle: 8,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            http2_keep_alive: Some(Duration::from_secs(30)),
+            proxy: None,
+        }
+    }
+}
+
 /// Jikan API v4 client
 pub struct JikanClient {
-    /// HTTP client
-    client: Client,
+    /// HTTP client, rate limited and retrying failed requests
+    http: RetryingClient,
+    /// Settings the HTTP client was built with
+    http_config: ClientConfig,
     /// Base URL for Jikan API
     base_url: String,
-    /// Rate limiter
-    rate_limiter: RateLimiter,
-    /// Maximum retries for failed requests
-    max_retries: u32,
-    /// Base delay for retry (exponential backoff)
-    retry_delay_ms: u64,
+    /// Never make requests; every call fails with `CacheMiss`
+    offline: bool,
 }
 
 impl JikanClient {
@@ -31,101 +89,95 @@ impl JikanClient {
         max_retries: u32,
         retry_delay_ms: u64,
     ) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("GDA2025-Zipf-Analysis/0.1.0")
-            .build()
-            .context("Failed to create HTTP client")?;
+        let http_config = ClientConfig::default();
+        let http = RetryingClient::new(build_http_client(&http_config)?)
+            .with_max_retries(max_retries)
+            .with_backoff(Backoff::new(Duration::from_millis(retry_delay_ms)).with_jitter(RETRY_JITTER))
+            .with_timeout(REQUEST_TIMEOUT)
+            .with_rate_limiter(RateLimiter::new(requests_per_second, requests_per_minute));
 
         Ok(Self {
-            client,
+            http,
+            http_config,
             base_url,
-            rate_limiter: RateLimiter::new(requests_per_second, requests_per_minute),
-            max_retries,
-            retry_delay_ms,
+            offline: false,
         })
     }
 
+    /// Never touch the network: every request fails with `CacheMiss`, so
+    /// callers only get what they already have cached
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Stop making requests once `budget` is used up: every request, retries
+    /// included, takes one from it, and `get` fails with `BudgetExhausted`
+    /// when none are left
+    pub fn with_request_budget(mut self, budget: RequestBudget) -> Self {
+        self.http = self.http.with_attempt_guard(move || Ok(budget.try_acquire()?));
+        self
+    }
+
+    /// Rebuild the HTTP client with the given pool and keep-alive settings
+    pub fn with_http_config(mut self, config: ClientConfig) -> Result<Self> {
+        self.http = self.http.with_client(build_http_client(&config)?);
+        self.http_config = config;
+        Ok(self)
+    }
+
+    /// Retry failed requests as `policy` says (`[mal_scraper.retry]`),
+    /// instead of the retries and delay given to `new`
+    pub fn with_retry_policy(mut self, policy: &RetryPolicy) -> Self {
+        self.http = self.http.with_retry_policy(policy);
+        self
+    }
+
+    /// Give up on a request attempt (and retry it) after `timeout`
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.with_timeout(timeout);
+        self
+    }
+
+    /// Timeout of each request attempt
+    pub fn request_timeout(&self) -> Duration {
+        self.http.timeout().unwrap_or(REQUEST_TIMEOUT)
+    }
+
+    /// Log a warning for every response that takes longer than `threshold`,
+    /// to spot a degraded API or a timeout set too tight
+    pub fn with_slow_request_threshold(mut self, threshold: Duration) -> Self {
+        self.http = self.http.with_slow_threshold(threshold);
+        self
+    }
+
+    /// Pool and keep-alive settings of the HTTP client
+    pub fn http_config(&self) -> &ClientConfig {
+        &self.http_config
+    }
+
     /// Make a GET request with rate limiting and retry logic
     async fn get<T: serde::de::DeserializeOwned>(&mut self, endpoint: &str) -> Result<T> {
+        if self.offline {
+            return Err(CacheMiss {
+                endpoint: endpoint.to_string(),
+            }
+            .into());
+        }
+
         let url = format!("{}{}", self.base_url, endpoint);
+        let response = self.http.send(|client| client.get(&url)).await?;
 
-        for attempt in 0..=self.max_retries {
-            // Apply rate limiting before each request
-            self.rate_limiter.acquire().await;
-
-            debug!(url = %url, attempt = attempt + 1, "Making API request");
-
-            match self.client.get(&url).send().await {
-                Ok(response) => {
-                    let status = response.status();
-
-                    if status.is_success() {
-                        // Parse response
-                        match response.json::<T>().await {
-                            Ok(data) => {
-                                debug!(url = %url, "Request successful");
-                                return Ok(data);
-                            }
-                            Err(e) => {
-                                warn!(url = %url, error = %e, "Failed to parse response");
-                                return Err(anyhow!("Failed to parse response: {}", e));
-                            }
-                        }
-                    } else if status == StatusCode::TOO_MANY_REQUESTS {
-                        // Rate limited by server - wait longer
-                        let delay = Duration::from_millis(self.retry_delay_ms * 2u64.pow(attempt));
-                        warn!(
-                            url = %url,
-                            delay_ms = delay.as_millis(),
-                            "Rate limited by server, waiting"
-                        );
-                        sleep(delay).await;
-                        continue;
-                    } else {
-                        // Try to parse error response
-                        let error_text = response
-                            .text()
-                            .await
-                            .unwrap_or_else(|_| "Unknown error".to_string());
-
-                        warn!(
-                            url = %url,
-                            status = %status,
-                            error = %error_text,
-                            "Request failed"
-                        );
-
-                        if attempt < self.max_retries {
-                            let delay = Duration::from_millis(self.retry_delay_ms * 2u64.pow(attempt));
-                            debug!(delay_ms = delay.as_millis(), "Retrying after delay");
-                            sleep(delay).await;
-                            continue;
-                        } else {
-                            return Err(anyhow!(
-                                "Request failed with status {}: {}",
-                                status,
-                                error_text
-                            ));
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!(url = %url, error = %e, "Request error");
-
-                    if attempt < self.max_retries {
-                        let delay = Duration::from_millis(self.retry_delay_ms * 2u64.pow(attempt));
-                        debug!(delay_ms = delay.as_millis(), "Retrying after delay");
-                        sleep(delay).await;
-                        continue;
-                    } else {
-                        return Err(anyhow!("Request failed after {} retries: {}", self.max_retries, e));
-                    }
-                }
+        match response.json::<T>().await {
+            Ok(data) => {
+                debug!(url = %url, "Request successful");
+                Ok(data)
+            }
+            Err(e) => {
+                warn!(url = %url, error = %e, "Failed to parse response");
+                Err(e).context("Failed to parse response")
             }
         }
-
-        Err(anyhow!("Request failed after all retries"))
     }
 
     /// Fetch all genres
@@ -162,10 +214,10 @@ impl JikanClient {
         self.get(&format!("/producers?page={}", page)).await
     }
 
-    /// Fetch top anime for a specific genre
-    pub async fn get_top_anime_by_genre(&mut self, genre_id: u32, page: u32) -> Result<TopAnimeResponse> {
+    /// Fetch anime for a specific genre/theme/demographic, most popular first (paginated)
+    pub async fn get_top_anime_by_genre(&mut self, genre_id: u32, page: u32) -> Result<PaginatedResponse<TopAnimeEntry>> {
         info!(genre_id = genre_id, page = page, "Fetching top anime by genre");
-        self.get(&format!("/top/anime?filter=bypopularity&genre={}&page={}", genre_id, page)).await
+        self.get(&format!("/anime?genres={}&order_by=popularity&sort=asc&page={}", genre_id, page)).await
     }
 
     /// Fetch top anime for a specific producer/studio
@@ -174,24 +226,148 @@ impl JikanClient {
         self.get(&format!("/anime?producer={}&page={}&order_by=members&sort=desc", producer_id, page)).await
     }
 
+    /// Search anime by title
+    pub async fn search_anime(&mut self, query: &str, limit: u32) -> Result<Vec<TopAnimeEntry>> {
+        info!(query = query, limit = limit, "Searching anime");
+        let response: PaginatedResponse<TopAnimeEntry> = self.get(&search_endpoint(query, limit)).await?;
+        Ok(response.data)
+    }
+
+    /// Fetch all anime of a broadcast season (all pages)
+    pub async fn get_season(&mut self, year: u32, season: Season) -> Result<Vec<TopAnimeEntry>> {
+        let mut entries = Vec::new();
+        let mut page = 1;
+
+        loop {
+            info!(year = year, season = %season, page = page, "Fetching seasonal anime");
+            let response: PaginatedResponse<TopAnimeEntry> =
+                self.get(&season_endpoint(year, season, page)).await?;
+            entries.extend(response.data);
+
+            match next_page(&response.pagination, page) {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+
+        Ok(entries)
+    }
+
     /// Fetch full anime details by MAL ID
+    ///
+    /// Uses the full endpoint, as only it lists the theme songs.
     pub async fn get_anime_details(&mut self, mal_id: u32) -> Result<AnimeDetails> {
         debug!(mal_id = mal_id, "Fetching anime details");
-        let response: AnimeDetailsResponse = self.get(&format!("/anime/{}", mal_id)).await?;
+        let response: AnimeDetailsResponse = self.get(&format!("/anime/{}/full", mal_id)).await?;
         Ok(response.data)
     }
 
+    /// Fetch relations (sequels, prequels, side stories, ...) of an anime
+    pub async fn get_anime_relations(&mut self, mal_id: u32) -> Result<Vec<Relation>> {
+        debug!(mal_id = mal_id, "Fetching anime relations");
+        let response: DataResponse<Relation> = self.get(&format!("/anime/{}/relations", mal_id)).await?;
+        Ok(response.data)
+    }
+
+    /// Check that the API is reachable and still returns the shapes we parse
+    ///
+    /// Fetches a single well-known anime and deserializes it into
+    /// `AnimeDetails`, so a breaking API change shows up here instead of as
+    /// scattered parse errors during a long run.
+    pub async fn health_check(&mut self) -> ApiHealth {
+        let endpoint = format!("/anime/{}/full", HEALTH_CHECK_ANIME_ID);
+        let start = Instant::now();
+
+        match self.get::<serde_json::Value>(&endpoint).await {
+            Ok(payload) => check_anime_payload(payload, start.elapsed()),
+            Err(e) => ApiHealth::Unreachable { error: format!("{:#}", e) },
+        }
+    }
+
     /// Get current rate limit statistics
     pub fn rate_limit_stats(&mut self) -> (usize, u32) {
-        let current_minute = self.rate_limiter.current_minute_count();
+        let current_minute = self
+            .http
+            .rate_limiter()
+            .and_then(|limiter| limiter.try_lock().ok())
+            .map_or(0, |mut limiter| limiter.current_minute_count());
         let max_minute = 50; // From config
         (current_minute, max_minute)
     }
 }
 
+/// Check a health check payload against `AnimeDetailsResponse`
+fn check_anime_payload(payload: serde_json::Value, latency: Duration) -> ApiHealth {
+    match serde_json::from_value::<AnimeDetailsResponse>(payload) {
+        Ok(response) if response.data.mal_id == HEALTH_CHECK_ANIME_ID => ApiHealth::Healthy { latency },
+        Ok(response) => ApiHealth::Incompatible {
+            error: format!(
+                "expected anime {}, got {}",
+                HEALTH_CHECK_ANIME_ID, response.data.mal_id
+            ),
+        },
+        Err(e) => ApiHealth::Incompatible { error: e.to_string() },
+    }
+}
+
+/// Maximum page size accepted by Jikan's search endpoint
+const MAX_SEARCH_LIMIT: u32 = 25;
+
+/// Build the `/anime` search endpoint with a URL-encoded query
+fn search_endpoint(query: &str, limit: u32) -> String {
+    let mut url = Url::parse("https://localhost/anime").expect("static URL is valid");
+    url.query_pairs_mut()
+        .append_pair("q", query)
+        .append_pair("limit", &limit.clamp(1, MAX_SEARCH_LIMIT).to_string());
+
+    format!("/anime?{}", url.query().unwrap_or_default())
+}
+
+/// Upper bound on pages fetched for a single season
+const MAX_SEASON_PAGES: u32 = 20;
+
+/// Build the `/seasons/{year}/{season}` endpoint for a page
+fn season_endpoint(year: u32, season: Season, page: u32) -> String {
+    format!("/seasons/{}/{}?page={}", year, season, page)
+}
+
+/// Next page to fetch, or None when the last page (or the page cap) is reached
+fn next_page(pagination: &Pagination, page: u32) -> Option<u32> {
+    if !pagination.has_next_page {
+        return None;
+    }
+    if page >= MAX_SEASON_PAGES {
+        warn!(page = page, "Reached page limit for season");
+        return None;
+    }
+    Some(page + 1)
+}
+
+/// Build the HTTP client used for every API request
+fn build_http_client(config: &ClientConfig) -> Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent("GDA2025-Zipf-Analysis/0.1.0")
+        .pool_max_idle_per_host(config.pool_max_idle)
+        .pool_idle_timeout(config.pool_idle_timeout);
+
+    if let Some(interval) = config.http2_keep_alive {
+        builder = builder
+            .http2_keep_alive_interval(interval)
+            .http2_keep_alive_while_idle(true);
+    }
+
+    if let Some(url) = &config.proxy {
+        let proxy = reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy URL: {}", url))?;
+        builder = builder.proxy(proxy);
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shared::test_server::{TestResponse, TestServer};
 
     #[tokio::test]
     async fn test_client_creation() {
@@ -204,4 +380,240 @@ mod tests {
         );
         assert!(client.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_client_refuses_requests_without_budget() -> Result<()> {
+        use crate::api::BudgetExhausted;
+
+        // Nothing listens on port 1, so each attempt fails fast
+        let budget = RequestBudget::new(2);
+        let mut client = JikanClient::new("http://127.0.0.1:1".to_string(), 100.0, 100, 0, 1)?
+            .with_request_budget(budget.clone());
+
+        for _ in 0..2 {
+            let err = client.get_genres().await.unwrap_err();
+            assert!(err.downcast_ref::<BudgetExhausted>().is_none());
+        }
+        assert_eq!(budget.remaining(), 0);
+
+        let err = client.get_genres().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<BudgetExhausted>(), Some(&BudgetExhausted { limit: 2 }));
+        assert_eq!(budget.used(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_with_http_config() -> Result<()> {
+        let config = ClientConfig {
+            pool_max_idle: 2,
+            pool_idle_timeout: None,
+            http2_keep_alive: Some(Duration::from_secs(10)),
+            proxy: None,
+        };
+
+        let client = JikanClient::new("https://api.jikan.moe/v4".to_string(), 2.0, 50, 3, 1000)?
+            .with_http_config(config.clone())?;
+        assert_eq!(client.http_config(), &config);
+
+        let client = client.with_http_config(ClientConfig {
+            http2_keep_alive: None,
+            ..ClientConfig::default()
+        })?;
+        assert_eq!(client.http_config().http2_keep_alive, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_with_request_timeout() -> Result<()> {
+        let client = JikanClient::new("https://api.jikan.moe/v4".to_string(), 2.0, 50, 3, 1000)?;
+        assert_eq!(client.request_timeout(), REQUEST_TIMEOUT);
+
+        // A server that accepts the connection but never answers
+        let server = TestServer::hanging().await?;
+
+        let mut client = JikanClient::new(server.url().to_string(), 100.0, 100, 0, 1)?
+            .with_request_timeout(Duration::from_millis(200))
+            .with_slow_request_threshold(Duration::from_millis(50))
+            .with_http_config(ClientConfig::default())?;
+        assert_eq!(client.request_timeout(), Duration::from_millis(200));
+
+        let start = Instant::now();
+        assert!(client.get_genres().await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_uses_configured_proxy() -> Result<()> {
+        // A "proxy" that answers 404
+        let proxy = TestServer::start(|_| Some(TestResponse::new(404))).await?;
+        let proxy_url = proxy.url().to_string();
+
+        // The API host does not resolve, so only the proxy can answer
+        let mut client = JikanClient::new("http://jikan.invalid/v4".to_string(), 100.0, 100, 0, 1)?
+            .with_http_config(ClientConfig {
+                proxy: Some(proxy_url.clone()),
+                ..ClientConfig::default()
+            })?;
+        assert_eq!(client.http_config().proxy, Some(proxy_url));
+
+        assert!(client.get_anime_details(5114).await.is_err());
+        assert_eq!(proxy.requests()[0].line, "GET http://jikan.invalid/v4/anime/5114/full HTTP/1.1");
+
+        assert!(JikanClient::new("http://jikan.invalid/v4".to_string(), 2.0, 50, 0, 1)?
+            .with_http_config(ClientConfig {
+                proxy: Some("not a url".to_string()),
+                ..ClientConfig::default()
+            })
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_endpoint() {
+        assert_eq!(search_endpoint("Naruto", 10), "/anime?q=Naruto&limit=10");
+        assert_eq!(
+            search_endpoint("Steins;Gate 0 & more", 5),
+            "/anime?q=Steins%3BGate+0+%26+more&limit=5"
+        );
+        assert_eq!(search_endpoint("x", 0), "/anime?q=x&limit=1");
+        assert_eq!(search_endpoint("x", 100), "/anime?q=x&limit=25");
+    }
+
+    #[test]
+    fn test_parse_search_response() {
+        let json = r#"{
+            "pagination": {"last_visible_page": 1, "has_next_page": false, "current_page": 1,
+                           "items": {"count": 1, "total": 1, "per_page": 5}},
+            "data": [{
+                "mal_id": 9253,
+                "url": "https://myanimelist.net/anime/9253/Steins_Gate",
+                "images": {"jpg": {"image_url": null, "small_image_url": null, "large_image_url": null}},
+                "title": "Steins;Gate",
+                "title_english": "Steins;Gate",
+                "title_japanese": "シュタインズ・ゲート",
+                "type": "TV",
+                "source": "Visual novel",
+                "episodes": 24,
+                "status": "Finished Airing",
+                "score": 9.07,
+                "scored_by": 1400000,
+                "rank": 3,
+                "popularity": 13,
+                "members": 2600000,
+                "favorites": 190000,
+                "year": 2011
+            }]
+        }"#;
+
+        let response: PaginatedResponse<TopAnimeEntry> = serde_json::from_str(json).unwrap();
+        assert!(!response.pagination.has_next_page);
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(response.data[0].mal_id, 9253);
+        assert_eq!(response.data[0].title, "Steins;Gate");
+        assert_eq!(response.data[0].anime_type.as_deref(), Some("TV"));
+        assert_eq!(response.data[0].episodes, Some(24));
+    }
+
+    /// Trimmed `/anime/1` payload (all fields `AnimeDetails` requires)
+    const ANIME_1_PAYLOAD: &str = r#"{
+        "data": {
+            "mal_id": 1,
+            "url": "https://myanimelist.net/anime/1/Cowboy_Bebop",
+            "images": {"jpg": {"image_url": null, "small_image_url": null, "large_image_url": null}},
+            "title": "Cowboy Bebop",
+            "title_english": "Cowboy Bebop",
+            "title_japanese": "カウボーイビバップ",
+            "title_synonyms": [],
+            "type": "TV",
+            "source": "Original",
+            "episodes": 26,
+            "status": "Finished Airing",
+            "airing": false,
+            "aired": {
+                "from": "1998-04-03T00:00:00+00:00",
+                "to": "1999-04-24T00:00:00+00:00",
+                "prop": {"from": {"day": 3, "month": 4, "year": 1998}, "to": {"day": 24, "month": 4, "year": 1999}}
+            },
+            "duration": "24 min per ep",
+            "rating": "R - 17+ (violence & profanity)",
+            "score": 8.75,
+            "scored_by": 1000000,
+            "rank": 46,
+            "popularity": 43,
+            "members": 1900000,
+            "favorites": 85000,
+            "synopsis": null,
+            "background": null,
+            "season": "spring",
+            "year": 1998,
+            "broadcast": {"day": "Saturdays", "time": "01:00", "timezone": "Asia/Tokyo", "string": "Saturdays at 01:00 (JST)"},
+            "producers": [],
+            "licensors": [],
+            "studios": [{"mal_id": 14, "type": "anime", "name": "Sunrise", "url": "https://myanimelist.net/anime/producer/14/Sunrise"}],
+            "genres": [{"mal_id": 1, "type": "anime", "name": "Action", "url": "https://myanimelist.net/anime/genre/1/Action"}],
+            "explicit_genres": [],
+            "themes": [],
+            "demographics": []
+        }
+    }"#;
+
+    #[test]
+    fn test_health_check_payload() {
+        let latency = Duration::from_millis(120);
+
+        let payload: serde_json::Value = serde_json::from_str(ANIME_1_PAYLOAD).unwrap();
+        let health = check_anime_payload(payload.clone(), latency);
+        assert_eq!(health, ApiHealth::Healthy { latency });
+        assert!(health.is_healthy());
+
+        // A renamed field (e.g. "genres" -> "genre_list") breaks deserialization
+        let mut renamed = payload.clone();
+        let data = renamed["data"].as_object_mut().unwrap();
+        let genres = data.remove("genres").unwrap();
+        data.insert("genre_list".to_string(), genres);
+        match check_anime_payload(renamed, latency) {
+            ApiHealth::Incompatible { error } => assert!(error.contains("genres"), "{}", error),
+            other => panic!("expected Incompatible, got {:?}", other),
+        }
+
+        // A changed type (string mal_id)
+        let mut retyped = payload.clone();
+        retyped["data"]["mal_id"] = serde_json::json!("1");
+        assert!(matches!(check_anime_payload(retyped, latency), ApiHealth::Incompatible { .. }));
+
+        // Parses, but is not the entry that was asked for
+        let mut wrong = payload;
+        wrong["data"]["mal_id"] = serde_json::json!(5);
+        assert!(!check_anime_payload(wrong, latency).is_healthy());
+    }
+
+    #[test]
+    fn test_season_endpoint() {
+        assert_eq!(season_endpoint(2024, Season::Winter, 1), "/seasons/2024/winter?page=1");
+        assert_eq!(season_endpoint(2023, Season::Fall, 3), "/seasons/2023/fall?page=3");
+    }
+
+    #[test]
+    fn test_season_pagination() {
+        let pagination = |current_page, last_visible_page, has_next_page| Pagination {
+            last_visible_page,
+            has_next_page,
+            current_page,
+            items: None,
+        };
+
+        assert_eq!(next_page(&pagination(1, 3, true), 1), Some(2));
+        assert_eq!(next_page(&pagination(2, 3, true), 2), Some(3));
+        assert_eq!(next_page(&pagination(3, 3, false), 3), None);
+        assert_eq!(next_page(&pagination(1, 1, false), 1), None);
+
+        // Stops at the page cap even if the API reports more pages
+        let many = MAX_SEASON_PAGES + 5;
+        assert_eq!(next_page(&pagination(MAX_SEASON_PAGES, many, true), MAX_SEASON_PAGES), None);
+    }
 }
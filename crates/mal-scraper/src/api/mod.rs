@@ -3,10 +3,11 @@
 //! This module provides a rate-limited, retry-enabled client for interacting
 //! with the Jikan API (MyAnimeList unofficial API).
 
+pub mod budget;
 pub mod client;
-pub mod rate_limiter;
 pub mod types;
 
-pub use client::JikanClient;
-pub use rate_limiter::RateLimiter;
+pub use budget::{BudgetExhausted, RequestBudget};
+pub use client::{ApiHealth, CacheMiss, ClientConfig, JikanClient};
+pub use shared::RateLimiter;
 pub use types::*;
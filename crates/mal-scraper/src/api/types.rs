@@ -59,12 +59,6 @@ pub struct ProducerTitle {
     pub title: String,
 }
 
-/// Top anime response (used for category top lists)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TopAnimeResponse {
-    pub data: Vec<TopAnimeEntry>,
-}
-
 /// Top anime entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopAnimeEntry {
@@ -147,6 +141,20 @@ pub struct AnimeDetails {
     pub explicit_genres: Vec<MalEntity>,
     pub themes: Vec<MalEntity>,
     pub demographics: Vec<MalEntity>,
+
+    // Theme songs (missing from details cached before they were fetched
+    // from the full endpoint)
+    #[serde(default)]
+    pub theme: Option<ThemeSongs>,
+}
+
+/// Opening and ending theme songs, e.g. `1: "again" by YUI (eps 1-14)`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeSongs {
+    #[serde(default)]
+    pub openings: Vec<String>,
+    #[serde(default)]
+    pub endings: Vec<String>,
 }
 
 /// Anime images
@@ -204,6 +212,30 @@ pub struct MalEntity {
     pub url: String,
 }
 
+/// Relation between an anime and other MAL entries (e.g. "Sequel")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relation {
+    pub relation: String,
+    pub entry: Vec<RelationEntry>,
+}
+
+impl Relation {
+    /// Entries of this relation that are anime (skips manga, light novels, ...)
+    pub fn anime_entries(&self) -> impl Iterator<Item = &RelationEntry> {
+        self.entry.iter().filter(|e| e.entry_type == "anime")
+    }
+}
+
+/// Entry referenced by a relation (anime or manga)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationEntry {
+    pub mal_id: u32,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub name: String,
+    pub url: String,
+}
+
 /// Error response from Jikan API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JikanError {
@@ -212,3 +244,53 @@ pub struct JikanError {
     #[serde(rename = "type")]
     pub error_type: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed response of `/anime/16498/relations` (Shingeki no Kyojin)
+    const RELATIONS_JSON: &str = r#"{
+        "data": [
+            {
+                "relation": "Adaptation",
+                "entry": [
+                    {"mal_id": 23390, "type": "manga", "name": "Shingeki no Kyojin", "url": "https://myanimelist.net/manga/23390/Shingeki_no_Kyojin"}
+                ]
+            },
+            {
+                "relation": "Sequel",
+                "entry": [
+                    {"mal_id": 25777, "type": "anime", "name": "Shingeki no Kyojin Season 2", "url": "https://myanimelist.net/anime/25777/Shingeki_no_Kyojin_Season_2"}
+                ]
+            },
+            {
+                "relation": "Side Story",
+                "entry": [
+                    {"mal_id": 18397, "type": "anime", "name": "Shingeki no Kyojin OVA", "url": "https://myanimelist.net/anime/18397/Shingeki_no_Kyojin_OVA"},
+                    {"mal_id": 19285, "type": "anime", "name": "Shingeki no Kyojin: Ano Hi Kara", "url": "https://myanimelist.net/anime/19285/Shingeki_no_Kyojin__Ano_Hi_Kara"}
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_relations() {
+        let response: DataResponse<Relation> = serde_json::from_str(RELATIONS_JSON).unwrap();
+        assert_eq!(response.data.len(), 3);
+
+        let adaptation = &response.data[0];
+        assert_eq!(adaptation.relation, "Adaptation");
+        assert_eq!(adaptation.entry[0].entry_type, "manga");
+        assert_eq!(adaptation.anime_entries().count(), 0);
+
+        let sequel = &response.data[1];
+        assert_eq!(sequel.relation, "Sequel");
+        let ids: Vec<u32> = sequel.anime_entries().map(|e| e.mal_id).collect();
+        assert_eq!(ids, vec![25777]);
+
+        let side_story = &response.data[2];
+        let ids: Vec<u32> = side_story.anime_entries().map(|e| e.mal_id).collect();
+        assert_eq!(ids, vec![18397, 19285]);
+    }
+}
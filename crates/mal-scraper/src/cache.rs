@@ -83,6 +83,22 @@ impl CacheManager {
         self.cache_path(key).exists()
     }
 
+    /// Remove a cache entry, so the next lookup fetches it again
+    pub fn remove(&self, key: &str) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let path = self.cache_path(key);
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove cache file: {}", path.display()))?;
+            debug!(key = key, "Cache entry removed");
+        }
+
+        Ok(())
+    }
+
     /// Get the cache file path for a given key
     fn cache_path(&self, key: &str) -> PathBuf {
         // Sanitize key to create valid filename
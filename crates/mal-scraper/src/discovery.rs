@@ -3,11 +3,11 @@
 //! Auto-discovers all categories (genres, themes, demographics, studios) with
 //! at least min_items entries, then fetches anime from each category.
 
-use crate::api::JikanClient;
+use crate::api::{AnimeDetails, BudgetExhausted, JikanClient, ProducerItem, ProducerTitle, Relation, TopAnimeEntry};
 use crate::cache::CacheManager;
 use anyhow::Result;
 use chrono::Utc;
-use shared::{Anime, ProcessingStatus};
+use shared::{Anime, AnimeFilterConfig, ProcessingStatus, Season};
 use std::collections::HashSet;
 use tracing::{info, warn};
 
@@ -42,11 +42,67 @@ pub struct Category {
     pub count: u32,
 }
 
+/// Quality thresholds applied to discovered anime
+///
+/// A threshold that is set drops anime whose corresponding field is missing
+/// (e.g. unscored anime are dropped when `min_score` is set).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiscoveryFilter {
+    /// Minimum MAL score
+    pub min_score: Option<f64>,
+    /// Maximum popularity rank (1 = most popular)
+    pub max_popularity_rank: Option<u32>,
+    /// Minimum number of users who scored the anime
+    pub min_scored_by: Option<u32>,
+    /// MAL types and episode counts to keep (`[mal_scraper.filter]`)
+    pub format: AnimeFilterConfig,
+}
+
+impl DiscoveryFilter {
+    /// Check whether an anime passes all thresholds
+    pub fn matches(&self, entry: &TopAnimeEntry) -> bool {
+        if let Some(min) = self.min_score {
+            if entry.score.is_none_or(|s| s < min) {
+                return false;
+            }
+        }
+        if let Some(max) = self.max_popularity_rank {
+            if entry.popularity.is_none_or(|p| p > max) {
+                return false;
+            }
+        }
+        if let Some(min) = self.min_scored_by {
+            if entry.scored_by.is_none_or(|n| n < min) {
+                return false;
+            }
+        }
+        self.format.matches(entry.anime_type.as_deref(), entry.episodes)
+    }
+
+    /// Check whether any threshold is set
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Outcome of `DiscoveryManager::warm_anime_details`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarmCacheStats {
+    pub requested: usize,
+    /// Skipped because a previous run already cached them
+    pub already_cached: usize,
+    pub fetched: usize,
+    pub failed: usize,
+}
+
 /// Discovery manager for finding categories and anime
 pub struct DiscoveryManager {
     client: JikanClient,
     cache: CacheManager,
     min_category_items: usize,
+    filter: DiscoveryFilter,
+    include_explicit: bool,
+    max_pages_per_category: Option<usize>,
 }
 
 impl DiscoveryManager {
@@ -60,9 +116,30 @@ impl DiscoveryManager {
             client,
             cache,
             min_category_items,
+            filter: DiscoveryFilter::default(),
+            include_explicit: false,
+            max_pages_per_category: None,
         }
     }
 
+    /// Include explicit genres in category discovery (off by default)
+    pub fn with_explicit_genres(mut self, include: bool) -> Self {
+        self.include_explicit = include;
+        self
+    }
+
+    /// Stop after this many listing pages per category (None = all pages)
+    pub fn with_max_pages_per_category(mut self, max_pages: Option<usize>) -> Self {
+        self.max_pages_per_category = max_pages;
+        self
+    }
+
+    /// Drop discovered anime that do not pass the given thresholds
+    pub fn with_filter(mut self, filter: DiscoveryFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
     /// Discover all categories that meet the minimum item threshold
     pub async fn discover_categories(&mut self) -> Result<Vec<Category>> {
         info!(
@@ -95,28 +172,32 @@ impl DiscoveryManager {
                 }),
         );
 
-        // Fetch explicit genres
-        info!("Discovering explicit genres");
-        let cache_key = "explicit_genres";
-        let explicit_genres = if let Some(cached) = self.cache.get(cache_key)? {
-            cached
-        } else {
-            let data = self.client.get_explicit_genres().await?;
-            self.cache.set(cache_key, &data)?;
-            data
-        };
+        // Fetch explicit genres (opt-in)
+        if self.include_explicit {
+            info!("Discovering explicit genres");
+            let cache_key = "explicit_genres";
+            let explicit_genres = if let Some(cached) = self.cache.get(cache_key)? {
+                cached
+            } else {
+                let data = self.client.get_explicit_genres().await?;
+                self.cache.set(cache_key, &data)?;
+                data
+            };
 
-        categories.extend(
-            explicit_genres
-                .into_iter()
-                .filter(|g| g.count >= self.min_category_items as u32)
-                .map(|g| Category {
-                    category_type: CategoryType::ExplicitGenre,
-                    mal_id: g.mal_id,
-                    name: g.name,
-                    count: g.count,
-                }),
-        );
+            categories.extend(
+                explicit_genres
+                    .into_iter()
+                    .filter(|g| g.count >= self.min_category_items as u32)
+                    .map(|g| Category {
+                        category_type: CategoryType::ExplicitGenre,
+                        mal_id: g.mal_id,
+                        name: g.name,
+                        count: g.count,
+                    }),
+            );
+        } else {
+            info!("Skipping explicit genres (include_explicit = false)");
+        }
 
         // Fetch themes
         info!("Discovering themes");
@@ -185,21 +266,11 @@ impl DiscoveryManager {
                 .collect();
 
             studios_count += filtered.len();
-            categories.extend(filtered.into_iter().map(|p| {
-                // Get the Default title, or first available title
-                let name = p.titles
-                    .iter()
-                    .find(|t| t.title_type == "Default")
-                    .or_else(|| p.titles.first())
-                    .map(|t| t.title.clone())
-                    .unwrap_or_else(|| format!("Studio {}", p.mal_id));
-
-                Category {
-                    category_type: CategoryType::Studio,
-                    mal_id: p.mal_id,
-                    name,
-                    count: p.count,
-                }
+            categories.extend(filtered.into_iter().map(|p| Category {
+                category_type: CategoryType::Studio,
+                mal_id: p.mal_id,
+                name: studio_name(&p),
+                count: p.count,
             }));
 
             if !response.pagination.has_next_page {
@@ -254,23 +325,14 @@ impl DiscoveryManager {
                         data
                     };
 
-                    for anime in &response.data {
+                    for anime in response.data.iter().filter(|a| self.filter.matches(a)) {
                         anime_ids.insert(anime.mal_id);
                     }
 
-                    if !response.pagination.has_next_page {
+                    if !response.pagination.has_next_page || self.page_cap_reached(category, page) {
                         break;
                     }
                     page += 1;
-
-                    // Limit to reasonable number of pages
-                    if page > 10 {
-                        warn!(
-                            category = %category.name,
-                            "Reached page limit for category"
-                        );
-                        break;
-                    }
                 }
             }
             _ => {
@@ -278,7 +340,7 @@ impl DiscoveryManager {
                 let mut page = 1;
                 loop {
                     let cache_key = format!(
-                        "anime_{}_{}_page_{}",
+                        "anime_{}_{}_by_popularity_page_{}",
                         category.category_type.as_str(),
                         category.mal_id,
                         page
@@ -292,24 +354,14 @@ impl DiscoveryManager {
                         data
                     };
 
-                    for anime in &response.data {
+                    for anime in response.data.iter().filter(|a| self.filter.matches(a)) {
                         anime_ids.insert(anime.mal_id);
                     }
 
-                    // Top anime endpoint doesn't have reliable pagination
-                    if response.data.is_empty() {
+                    if !response.pagination.has_next_page || self.page_cap_reached(category, page) {
                         break;
                     }
                     page += 1;
-
-                    // Limit to reasonable number of pages
-                    if page > 10 {
-                        warn!(
-                            category = %category.name,
-                            "Reached page limit for category"
-                        );
-                        break;
-                    }
                 }
             }
         }
@@ -323,25 +375,159 @@ impl DiscoveryManager {
         Ok(anime_ids.into_iter().collect())
     }
 
-    /// Fetch full anime details by MAL ID
-    pub async fn fetch_anime_details(&mut self, mal_id: u32) -> Result<Anime> {
-        let cache_key = format!("anime_{}", mal_id);
+    /// Whether `page` is the last one allowed for a category (logs when it is)
+    fn page_cap_reached(&self, category: &Category, page: u32) -> bool {
+        let reached = self
+            .max_pages_per_category
+            .is_some_and(|max| page as usize >= max);
+        if reached {
+            warn!(
+                category = %category.name,
+                pages = page,
+                "Reached max_pages_per_category, remaining pages skipped"
+            );
+        }
+        reached
+    }
+
+    /// Fetch the MAL IDs of all anime broadcast in a season
+    pub async fn discover_season(&mut self, year: u32, season: Season) -> Result<Vec<u32>> {
+        info!(year = year, season = %season, "Discovering seasonal anime");
 
-        let details = if let Some(cached) = self.cache.get(&cache_key)? {
+        let cache_key = format!("season_{}_{}", year, season);
+        let entries: Vec<TopAnimeEntry> = if let Some(cached) = self.cache.get(&cache_key)? {
             cached
         } else {
-            let data = self.client.get_anime_details(mal_id).await?;
+            let data = self.client.get_season(year, season).await?;
             self.cache.set(&cache_key, &data)?;
             data
         };
 
-        // Convert aired dates
-        let aired_from = details.aired.from.as_ref().and_then(|s| {
-            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
-        });
-        let aired_to = details.aired.to.as_ref().and_then(|s| {
-            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
-        });
+        // The same anime can be listed more than once (e.g. continuing series)
+        let mut seen = HashSet::new();
+        let anime_ids: Vec<u32> = entries
+            .into_iter()
+            .filter(|e| self.filter.matches(e))
+            .map(|e| e.mal_id)
+            .filter(|id| seen.insert(*id))
+            .collect();
+
+        info!(
+            year = year,
+            season = %season,
+            anime_count = anime_ids.len(),
+            "Discovered seasonal anime"
+        );
+
+        Ok(anime_ids)
+    }
+
+    /// Search anime by title
+    pub async fn search_anime(&mut self, query: &str, limit: u32) -> Result<Vec<TopAnimeEntry>> {
+        let cache_key = format!("search_{}_limit_{}", query.trim().to_lowercase(), limit);
+
+        if let Some(cached) = self.cache.get(&cache_key)? {
+            return Ok(cached);
+        }
+
+        let data = self.client.search_anime(query.trim(), limit).await?;
+        self.cache.set(&cache_key, &data)?;
+        Ok(data)
+    }
+
+    /// Fetch anime related to the given MAL ID
+    ///
+    /// Returns `(relation, mal_id)` pairs, skipping manga and other non-anime entries.
+    pub async fn fetch_related_anime(&mut self, mal_id: u32) -> Result<Vec<(String, u32)>> {
+        let cache_key = format!("relations_{}", mal_id);
+
+        let relations: Vec<Relation> = if let Some(cached) = self.cache.get(&cache_key)? {
+            cached
+        } else {
+            let data = self.client.get_anime_relations(mal_id).await?;
+            self.cache.set(&cache_key, &data)?;
+            data
+        };
+
+        Ok(relations
+            .iter()
+            .flat_map(|r| r.anime_entries().map(|e| (r.relation.clone(), e.mal_id)))
+            .collect())
+    }
+
+    /// Fetch and cache the details of every anime in `mal_ids` that is not
+    /// cached yet
+    ///
+    /// Cached anime are skipped, so an interrupted run can simply be started
+    /// again. A failed fetch is logged and counted, and the rest continue,
+    /// unless the client's request budget is used up.
+    pub async fn warm_anime_details(&mut self, mal_ids: &[u32]) -> WarmCacheStats {
+        let mut stats = WarmCacheStats {
+            requested: mal_ids.len(),
+            ..Default::default()
+        };
+
+        for (idx, &mal_id) in mal_ids.iter().enumerate() {
+            if (idx + 1) % 100 == 0 || idx + 1 == mal_ids.len() {
+                info!(
+                    progress = format!("{}/{}", idx + 1, mal_ids.len()),
+                    fetched = stats.fetched,
+                    "Warming anime details cache"
+                );
+            }
+
+            if self.cache.exists(&anime_details_key(mal_id)) {
+                stats.already_cached += 1;
+                continue;
+            }
+
+            match self.anime_details(mal_id).await {
+                Ok(_) => stats.fetched += 1,
+                Err(e) if e.downcast_ref::<BudgetExhausted>().is_some() => {
+                    warn!(error = %e, remaining = mal_ids.len() - idx, "Stopping cache warming");
+                    break;
+                }
+                Err(e) => {
+                    warn!(mal_id = mal_id, error = %e, "Failed to fetch anime details");
+                    stats.failed += 1;
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Anime details from the cache, fetching and caching them on a miss
+    async fn anime_details(&mut self, mal_id: u32) -> Result<AnimeDetails> {
+        let cache_key = anime_details_key(mal_id);
+
+        if let Some(cached) = self.cache.get(&cache_key)? {
+            return Ok(cached);
+        }
+
+        let data = self.client.get_anime_details(mal_id).await?;
+        self.cache.set(&cache_key, &data)?;
+        Ok(data)
+    }
+
+    /// Drop the cached details of an anime, so the next fetch gets them
+    /// from the API again (e.g. to see new episodes of an airing show)
+    pub fn forget_anime_details(&self, mal_id: u32) -> Result<()> {
+        self.cache.remove(&anime_details_key(mal_id))
+    }
+
+    /// Fetch full anime details by MAL ID
+    pub async fn fetch_anime_details(&mut self, mal_id: u32) -> Result<Anime> {
+        let details = self.anime_details(mal_id).await?;
+
+        // Convert aired dates (Jikan gives full timestamps such as
+        // "2009-04-05T00:00:00+00:00")
+        let parse_date = |s: &String| {
+            chrono::NaiveDate::parse_from_str(s.get(..10)?, "%Y-%m-%d").ok()
+        };
+        let aired_from = details.aired.from.as_ref().and_then(parse_date);
+        let aired_to = details.aired.to.as_ref().and_then(parse_date);
+        let theme = details.theme.unwrap_or_default();
 
         // Convert to our Anime model
         let anime = Anime {
@@ -363,6 +549,8 @@ impl DiscoveryManager {
             themes: details.themes.iter().map(|t| t.name.clone()).collect(),
             demographics: details.demographics.iter().map(|d| d.name.clone()).collect(),
             studios: details.studios.iter().map(|s| s.name.clone()).collect(),
+            opening_themes: theme.openings.iter().filter_map(|t| theme_title(t)).collect(),
+            ending_themes: theme.endings.iter().filter_map(|t| theme_title(t)).collect(),
             score: details.score,
             scored_by: details.scored_by,
             rank: details.rank,
@@ -377,6 +565,10 @@ impl DiscoveryManager {
             }),
             episodes_processed: 0,
             processing_status: ProcessingStatus::Pending,
+            zipf_exponent: None,
+            zipf_r_squared: None,
+            total_tokens: None,
+            zipf_warning: None,
             fetched_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -384,3 +576,443 @@ impl DiscoveryManager {
         Ok(anime)
     }
 }
+
+/// Cache key of an anime's details
+pub(crate) fn anime_details_key(mal_id: u32) -> String {
+    format!("anime_{}", mal_id)
+}
+
+/// Song title of a Jikan theme song entry
+///
+/// Entries look like `1: "again" by YUI (eps 1-14)`: the number is only
+/// there when the anime has several, and the title is between the quotes
+/// (romanized titles keep the original in parentheses, e.g. `"Uso (嘘)"`).
+/// Entries without quotes are taken whole.
+fn theme_title(entry: &str) -> Option<String> {
+    let entry = entry.trim();
+    let entry = match entry.split_once(':') {
+        Some((number, rest)) if number.trim_start_matches('#').parse::<u32>().is_ok() => rest.trim_start(),
+        _ => entry,
+    };
+
+    let title = match entry.strip_prefix('"') {
+        Some(quoted) => quoted
+            .rsplit_once("\" by ")
+            .or_else(|| quoted.split_once('"'))
+            .map_or(quoted, |(title, _)| title),
+        None => entry,
+    };
+
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Pick a display name for a producer/studio
+///
+/// Prefers the "Default" title, then the "English" title, then the first
+/// non-empty title. Only synthesizes a name when the producer has none.
+fn studio_name(producer: &ProducerItem) -> String {
+    let non_empty = |t: &&ProducerTitle| !t.title.trim().is_empty();
+
+    let title = ["Default", "English"]
+        .iter()
+        .find_map(|kind| {
+            producer
+                .titles
+                .iter()
+                .filter(non_empty)
+                .find(|t| t.title_type == *kind)
+        })
+        .or_else(|| producer.titles.iter().find(non_empty));
+
+    match title {
+        Some(t) => t.title.trim().to_string(),
+        None => {
+            warn!(mal_id = producer.mal_id, "Studio has no titles, using a placeholder name");
+            format!("Studio {}", producer.mal_id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{AnimeImages, CacheMiss, CategoryItem, ImageSet, PaginatedResponse, Pagination};
+    use shared::{Database, JobQueue};
+    use shared::test_server::{TestResponse, TestServer};
+    use tempfile::TempDir;
+
+    fn entry(mal_id: u32, score: Option<f64>, popularity: Option<u32>, scored_by: Option<u32>) -> TopAnimeEntry {
+        TopAnimeEntry {
+            mal_id,
+            url: String::new(),
+            images: AnimeImages {
+                jpg: ImageSet {
+                    image_url: None,
+                    small_image_url: None,
+                    large_image_url: None,
+                },
+                webp: None,
+            },
+            title: format!("Anime {}", mal_id),
+            title_english: None,
+            title_japanese: None,
+            anime_type: Some("TV".to_string()),
+            episodes: Some(12),
+            status: None,
+            score,
+            scored_by,
+            rank: None,
+            popularity,
+            members: None,
+            favorites: None,
+        }
+    }
+
+    #[test]
+    fn test_discovery_filter() {
+        let candidates = [
+            entry(1, Some(8.5), Some(100), Some(500_000)),  // well-known
+            entry(2, Some(6.1), Some(200), Some(300_000)),  // low score
+            entry(3, Some(8.0), Some(9_000), Some(2_000)),  // obscure OVA
+            entry(4, None, None, None),                      // unscored
+            entry(5, Some(7.0), Some(1_000), Some(50_000)), // exactly at thresholds
+        ];
+
+        let keep = |filter: &DiscoveryFilter| -> Vec<u32> {
+            candidates.iter().filter(|e| filter.matches(e)).map(|e| e.mal_id).collect()
+        };
+
+        let none = DiscoveryFilter::default();
+        assert!(!none.is_active());
+        assert_eq!(keep(&none), vec![1, 2, 3, 4, 5]);
+
+        let by_score = DiscoveryFilter {
+            min_score: Some(7.0),
+            ..Default::default()
+        };
+        assert_eq!(keep(&by_score), vec![1, 3, 5]);
+
+        let all = DiscoveryFilter {
+            min_score: Some(7.0),
+            max_popularity_rank: Some(1_000),
+            min_scored_by: Some(50_000),
+            format: AnimeFilterConfig::default(),
+        };
+        assert!(all.is_active());
+        assert_eq!(keep(&all), vec![1, 5]);
+
+        // The entries above are 12-episode TV series; a movie is not
+        let mut movie = entry(6, Some(8.8), Some(50), Some(900_000));
+        movie.anime_type = Some("Movie".to_string());
+        movie.episodes = Some(1);
+        let series_only = DiscoveryFilter {
+            format: AnimeFilterConfig {
+                min_episodes: Some(2),
+                types: vec!["TV".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(series_only.is_active());
+        assert!(!series_only.matches(&movie));
+        assert_eq!(keep(&series_only), vec![1, 2, 3, 4, 5]);
+    }
+
+    fn producer(mal_id: u32, titles: &[(&str, &str)]) -> ProducerItem {
+        ProducerItem {
+            mal_id,
+            titles: titles
+                .iter()
+                .map(|(kind, title)| ProducerTitle {
+                    title_type: kind.to_string(),
+                    title: title.to_string(),
+                })
+                .collect(),
+            url: String::new(),
+            count: 100,
+        }
+    }
+
+    #[test]
+    fn test_studio_name() {
+        let default = producer(1, &[("Japanese", "ボンズ"), ("Default", "Bones"), ("English", "BONES Inc.")]);
+        assert_eq!(studio_name(&default), "Bones");
+
+        let english_only = producer(2, &[("Japanese", "京都アニメーション"), ("English", "Kyoto Animation")]);
+        assert_eq!(studio_name(&english_only), "Kyoto Animation");
+
+        let empty_default = producer(3, &[("Default", " "), ("Synonym", "Madhouse")]);
+        assert_eq!(studio_name(&empty_default), "Madhouse");
+
+        let no_titles = producer(4, &[]);
+        assert_eq!(studio_name(&no_titles), "Studio 4");
+    }
+
+    #[test]
+    fn test_theme_title() {
+        assert_eq!(theme_title(r#"1: "again" by YUI (eps 1-14)"#).as_deref(), Some("again"));
+        assert_eq!(theme_title(r#""Uso (嘘)" by SID (eps 1-13)"#).as_deref(), Some("Uso (嘘)"));
+        assert_eq!(theme_title(r#"#2: "Hologram" by NICO Touches the Walls"#).as_deref(), Some("Hologram"));
+        // Quotes inside the title are kept
+        assert_eq!(
+            theme_title(r#""Say "Hello"" by Someone"#).as_deref(),
+            Some(r#"Say "Hello""#)
+        );
+        assert_eq!(theme_title("Rain by Yoko Kanno").as_deref(), Some("Rain by Yoko Kanno"));
+        assert_eq!(theme_title(" "), None);
+    }
+
+    fn category_item(mal_id: u32, name: &str) -> CategoryItem {
+        CategoryItem {
+            mal_id,
+            name: name.to_string(),
+            url: String::new(),
+            count: 100,
+        }
+    }
+
+    /// Discovery manager whose cache already holds every category listing,
+    /// with a client pointing nowhere so any cache miss fails the test
+    fn cached_discovery(temp_dir: &TempDir) -> Result<DiscoveryManager> {
+        let cache = CacheManager::new(temp_dir.path(), true)?;
+        cache.set("genres", &vec![category_item(1, "Action")])?;
+        cache.set("explicit_genres", &vec![category_item(12, "Hentai")])?;
+        cache.set("themes", &vec![category_item(50, "School")])?;
+        cache.set("demographics", &vec![category_item(27, "Shounen")])?;
+        cache.set(
+            "studios_page_1",
+            &PaginatedResponse {
+                data: vec![producer(4, &[("Default", "Bones")])],
+                pagination: Pagination {
+                    last_visible_page: 1,
+                    has_next_page: false,
+                    current_page: 1,
+                    items: None,
+                },
+            },
+        )?;
+
+        let client = JikanClient::new("http://127.0.0.1:9".to_string(), 100.0, 1000, 0, 1)?;
+        Ok(DiscoveryManager::new(client, cache, 50))
+    }
+
+    #[tokio::test]
+    async fn test_discovery_skips_explicit_genres() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let mut discovery = cached_discovery(&temp_dir)?;
+        let categories = discovery.discover_categories().await?;
+        assert_eq!(categories.len(), 4);
+        assert!(categories.iter().all(|c| c.category_type != CategoryType::ExplicitGenre));
+
+        let mut discovery = cached_discovery(&temp_dir)?.with_explicit_genres(true);
+        let categories = discovery.discover_categories().await?;
+        assert_eq!(categories.len(), 5);
+        assert!(categories.iter().any(|c| c.category_type == CategoryType::ExplicitGenre));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_offline_serves_only_cached_responses() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut discovery = cached_discovery(&temp_dir)?;
+        discovery.client = discovery.client.with_offline(true);
+        discovery
+            .cache
+            .set("search_bebop_limit_5", &vec![entry(1, Some(8.8), Some(40), None)])?;
+
+        // Cached keys are served as usual
+        assert_eq!(discovery.discover_categories().await?.len(), 4);
+        assert_eq!(discovery.search_anime("Bebop", 5).await?[0].mal_id, 1);
+
+        // A miss fails with CacheMiss instead of reaching the client
+        let err = discovery.search_anime("Bebop", 10).await.unwrap_err();
+        let miss = err.downcast_ref::<CacheMiss>().expect("expected a cache miss");
+        assert_eq!(miss.endpoint, "/anime?q=Bebop&limit=10");
+
+        Ok(())
+    }
+
+    /// Serve minimal `/anime/{id}/full` detail responses on a local port
+    async fn serve_anime_details() -> Result<TestServer> {
+        TestServer::start(|request| {
+            let mal_id = request
+                .target
+                .strip_prefix("/anime/")
+                .and_then(|path| path.strip_suffix("/full"))
+                .and_then(|id| id.parse::<u32>().ok());
+            Some(match mal_id {
+                Some(mal_id) => TestResponse::json(&serde_json::json!({"data": {
+                    "mal_id": mal_id, "url": "", "images": {"jpg": {}},
+                    "title": format!("Anime {}", mal_id), "title_synonyms": [],
+                    "airing": false, "aired": {"prop": {"from": {}, "to": {}}},
+                    "producers": [], "licensors": [], "studios": [], "genres": [],
+                    "explicit_genres": [], "themes": [], "demographics": []
+                }})),
+                None => TestResponse::new(404),
+            })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_warm_anime_details() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let server = serve_anime_details().await?;
+        let client = JikanClient::new(server.url().to_string(), 100.0, 1000, 0, 1)?;
+        let mut discovery = DiscoveryManager::new(client, CacheManager::new(temp_dir.path(), true)?, 50);
+
+        let stats = discovery.warm_anime_details(&[1, 5114]).await;
+        assert_eq!(
+            stats,
+            WarmCacheStats {
+                requested: 2,
+                fetched: 2,
+                ..Default::default()
+            }
+        );
+        for mal_id in [1, 5114] {
+            let cached: AnimeDetails = discovery.cache.get(&anime_details_key(mal_id))?.unwrap();
+            assert_eq!(cached.mal_id, mal_id);
+        }
+        assert_eq!(server.request_count(), 2);
+
+        // A rerun only fetches what is missing
+        let stats = discovery.warm_anime_details(&[1, 5114, 9253]).await;
+        assert_eq!((stats.already_cached, stats.fetched), (2, 1));
+        assert_eq!(server.request_count(), 3);
+
+        // Warmed details are then served offline
+        discovery.client = discovery.client.with_offline(true);
+        assert_eq!(discovery.fetch_anime_details(9253).await?.title, "Anime 9253");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_theme_songs_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut discovery = cached_discovery(&temp_dir)?;
+        discovery.client = discovery.client.with_offline(true);
+
+        let payload = serde_json::json!({
+            "mal_id": 5114, "url": "", "images": {"jpg": {}},
+            "title": "Fullmetal Alchemist: Brotherhood", "title_synonyms": [],
+            "airing": false, "aired": {"prop": {"from": {}, "to": {}}},
+            "producers": [], "licensors": [], "studios": [], "genres": [],
+            "explicit_genres": [], "themes": [], "demographics": [],
+            "theme": {
+                "openings": [
+                    "1: \"again\" by YUI (eps 1-14)",
+                    "2: \"Hologram\" by NICO Touches the Walls (eps 15-26)"
+                ],
+                "endings": ["1: \"Uso (嘘)\" by SID (eps 1-13)"]
+            }
+        });
+        let details: AnimeDetails = serde_json::from_value(payload)?;
+        discovery.cache.set(&anime_details_key(5114), &details)?;
+
+        let anime = discovery.fetch_anime_details(5114).await?;
+        assert_eq!(anime.opening_themes, vec!["again", "Hologram"]);
+        assert_eq!(anime.ending_themes, vec!["Uso (嘘)"]);
+
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("test.db"))?);
+        queue.get_or_create_anime(&anime)?;
+        let saved = queue.get_anime(5114)?.unwrap();
+        assert_eq!(saved.opening_themes, anime.opening_themes);
+        assert_eq!(saved.ending_themes, anime.ending_themes);
+
+        // Details cached before theme songs were fetched have none
+        let mut payload = serde_json::to_value(&details)?;
+        payload.as_object_mut().unwrap().remove("theme");
+        let old: AnimeDetails = serde_json::from_value(payload)?;
+        discovery.cache.set(&anime_details_key(5114), &old)?;
+        assert!(discovery.fetch_anime_details(5114).await?.opening_themes.is_empty());
+
+        Ok(())
+    }
+
+    fn anime_page(ids: &[u32], page: u32, has_next_page: bool) -> PaginatedResponse<TopAnimeEntry> {
+        PaginatedResponse {
+            data: ids.iter().map(|&id| entry(id, None, None, None)).collect(),
+            pagination: Pagination {
+                last_visible_page: page + has_next_page as u32,
+                has_next_page,
+                current_page: page,
+                items: None,
+            },
+        }
+    }
+
+    /// Cache `pages` listing pages for the Action genre; page N holds anime N
+    fn cache_genre_pages(discovery: &DiscoveryManager, pages: u32) -> Result<Category> {
+        for page in 1..=pages {
+            discovery.cache.set(
+                &format!("anime_genre_1_by_popularity_page_{}", page),
+                &anime_page(&[page], page, page < pages),
+            )?;
+        }
+        Ok(Category {
+            category_type: CategoryType::Genre,
+            mal_id: 1,
+            name: "Action".to_string(),
+            count: pages,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_genre_pagination_follows_has_next_page() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut discovery = cached_discovery(&temp_dir)?;
+
+        // An empty page in the middle must not end the listing; the page after
+        // the last one must never be requested
+        let pages = [
+            anime_page(&[1, 2], 1, true),
+            anime_page(&[], 2, true),
+            anime_page(&[3], 3, false),
+            anime_page(&[4], 4, false),
+        ];
+        for (i, page) in pages.iter().enumerate() {
+            discovery.cache.set(&format!("anime_genre_1_by_popularity_page_{}", i + 1), page)?;
+        }
+
+        let category = Category {
+            category_type: CategoryType::Genre,
+            mal_id: 1,
+            name: "Action".to_string(),
+            count: 4,
+        };
+        let mut ids = discovery.fetch_anime_ids_for_category(&category).await?;
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_pages_per_category() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        // Unbounded: pages past the old hardcoded limit of 10 are fetched
+        let mut discovery = cached_discovery(&temp_dir)?;
+        let category = cache_genre_pages(&discovery, 14)?;
+        let mut ids = discovery.fetch_anime_ids_for_category(&category).await?;
+        ids.sort();
+        assert_eq!(ids, (1..=14).collect::<Vec<_>>());
+
+        // Capped: stops after the configured number of pages
+        let mut discovery = cached_discovery(&temp_dir)?.with_max_pages_per_category(Some(3));
+        let mut ids = discovery.fetch_anime_ids_for_category(&category).await?;
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        // A cap above the page count changes nothing
+        let mut discovery = cached_discovery(&temp_dir)?.with_max_pages_per_category(Some(50));
+        assert_eq!(discovery.fetch_anime_ids_for_category(&category).await?.len(), 14);
+
+        Ok(())
+    }
+}
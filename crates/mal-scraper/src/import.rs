@@ -0,0 +1,236 @@
+//! Import of externally-provided transcripts.
+//!
+//! Transcripts made elsewhere (fansub scripts, another Whisper run) skip the
+//! download and transcription stages: each file is copied to where the
+//! transcriber would have written it and its job is created directly at
+//! `transcribed`, so the tokenizer picks it up next. Episodes whose job is
+//! already being processed or past transcription are left alone.
+//!
+//! The import directory holds one directory per anime, named by MAL ID, with
+//! one plain-text transcript per episode named by episode number:
+//!
+//! ```text
+//! transcripts/
+//!   5114/
+//!     ep001.txt
+//!     ep002.txt
+//!   16498/
+//!     1.txt
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use shared::{Anime, DataPaths, JobMetadata, JobQueue, JobStage, NewJob, Season};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Recorded as the transcript model of imported episodes
+pub const IMPORTED_MODEL: &str = "imported";
+
+/// One transcript found in an import directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptFile {
+    pub mal_id: u32,
+    pub episode: u32,
+    pub path: PathBuf,
+}
+
+/// Statistics for a transcript import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportStats {
+    /// Anime with at least one imported episode
+    pub anime_imported: usize,
+    /// Episodes imported at `transcribed`
+    pub episodes_imported: usize,
+    /// Episodes skipped because their job is in progress or already
+    /// transcribed
+    pub episodes_skipped: usize,
+    /// Anime or episodes that could not be imported
+    pub errors: usize,
+}
+
+/// Find the transcripts in `dir`, sorted by MAL ID and episode.
+///
+/// Directories not named by a MAL ID and files not named `epNNN.txt` or
+/// `NNN.txt` are skipped with a warning.
+pub fn find_transcripts(dir: &Path) -> Result<Vec<TranscriptFile>> {
+    let mut transcripts = Vec::new();
+
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let Some(mal_id) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            warn!(path = %entry.path().display(), "Skipping directory not named by a MAL ID");
+            continue;
+        };
+
+        for file in std::fs::read_dir(entry.path())? {
+            let path = file?.path();
+            match parse_episode(&path) {
+                Some(episode) => transcripts.push(TranscriptFile { mal_id, episode, path }),
+                None => warn!(path = %path.display(), "Skipping file not named by an episode number"),
+            }
+        }
+    }
+
+    transcripts.sort_by_key(|t| (t.mal_id, t.episode));
+    Ok(transcripts)
+}
+
+/// Episode number of a transcript named `epNNN.txt` or `NNN.txt`
+fn parse_episode(path: &Path) -> Option<u32> {
+    if path.extension()? != "txt" {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    let number = stem.strip_prefix("ep").unwrap_or(stem);
+    number.parse().ok().filter(|&episode| episode > 0)
+}
+
+/// Import one transcript of `anime` (already saved as `anime_id`): create or
+/// reuse its job, copy the transcript into the data directory and move the
+/// job to `transcribed`.
+///
+/// Only a job that has not been transcribed yet (`queued`, `downloaded` or
+/// `failed`) is moved; one a worker holds or that is at `transcribed` or
+/// beyond is left as it is and `None` is returned. Returns the job ID.
+pub fn import_transcript(
+    queue: &mut JobQueue,
+    data_paths: &DataPaths,
+    anime_id: i64,
+    anime: &Anime,
+    transcript: &TranscriptFile,
+) -> Result<Option<i64>> {
+    let job_id = queue.enqueue(&NewJob {
+        anime_id,
+        mal_id: anime.mal_id,
+        anime_title: anime.title.clone(),
+        episode: transcript.episode,
+        season: anime.season.as_deref().and_then(|s| s.parse::<Season>().ok()).map(|s| s.number()),
+        year: anime.year,
+        priority: 0,
+    })?;
+
+    let stage = queue.get_job(anime.mal_id, transcript.episode)?.map(|job| job.stage);
+    if !matches!(stage, Some(JobStage::Queued | JobStage::Downloaded | JobStage::Failed)) {
+        debug!(
+            job_id = job_id,
+            mal_id = anime.mal_id,
+            episode = transcript.episode,
+            stage = ?stage,
+            "Job already transcribed or in progress, not importing"
+        );
+        return Ok(None);
+    }
+
+    let destination = data_paths.transcript_txt(anime.mal_id, &anime.title, transcript.episode);
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let size = std::fs::copy(&transcript.path, &destination).with_context(|| {
+        format!("Failed to copy {} to {}", transcript.path.display(), destination.display())
+    })?;
+
+    queue.update_metadata(
+        job_id,
+        &JobMetadata {
+            transcript_size_bytes: Some(size),
+            transcript_model: Some(IMPORTED_MODEL.to_string()),
+            transcript_path: Some(destination.to_string_lossy().to_string()),
+            ..Default::default()
+        },
+    )?;
+    queue.update_stage(job_id, JobStage::Transcribed)?;
+
+    debug!(
+        job_id = job_id,
+        mal_id = anime.mal_id,
+        episode = transcript.episode,
+        "Imported transcript"
+    );
+
+    Ok(Some(job_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::Database;
+    use tempfile::TempDir;
+
+    fn test_anime(mal_id: u32, title: &str) -> Anime {
+        Anime {
+            season: Some("spring".to_string()),
+            year: Some(2009),
+            duration_minutes: Some(24),
+            ..Anime::test_fixture(mal_id, title, 64)
+        }
+    }
+
+    #[test]
+    fn test_find_transcripts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        std::fs::create_dir_all(dir.join("5114"))?;
+        std::fs::create_dir_all(dir.join("16498"))?;
+        std::fs::create_dir_all(dir.join("notes"))?;
+        std::fs::write(dir.join("5114/ep002.txt"), "二話")?;
+        std::fs::write(dir.join("5114/1.txt"), "一話")?;
+        std::fs::write(dir.join("5114/ep001.srt"), "")?;
+        std::fs::write(dir.join("5114/ep000.txt"), "")?;
+        std::fs::write(dir.join("16498/ep010.txt"), "十話")?;
+        std::fs::write(dir.join("notes/ep001.txt"), "")?;
+        std::fs::write(dir.join("README.txt"), "")?;
+
+        let found: Vec<_> = find_transcripts(dir)?.into_iter().map(|t| (t.mal_id, t.episode)).collect();
+        assert_eq!(found, [(5114, 1), (5114, 2), (16498, 10)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_transcript_creates_transcribed_job() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path().join("data"));
+        let source = temp_dir.path().join("import/5114/ep003.txt");
+        std::fs::create_dir_all(source.parent().unwrap())?;
+        std::fs::write(&source, "錬金術の基本は等価交換\n")?;
+
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("test.db"))?);
+        let anime = test_anime(5114, "Fullmetal Alchemist: Brotherhood");
+        let anime_id = queue.get_or_create_anime(&anime)?;
+
+        let transcript = TranscriptFile {
+            mal_id: 5114,
+            episode: 3,
+            path: source,
+        };
+        let job_id = import_transcript(&mut queue, &data_paths, anime_id, &anime, &transcript)?.unwrap();
+
+        let jobs = queue.get_jobs_by_stage(JobStage::Transcribed)?;
+        assert_eq!(jobs.len(), 1);
+        let job = &jobs[0];
+        assert_eq!((job.id, job.mal_id, job.episode), (job_id, 5114, 3));
+
+        // Written where the transcriber would have put it
+        let destination = data_paths.transcript_dir(5114).join("Fullmetal Alchemist_ Brotherhood_ep003.txt");
+        assert_eq!(job.transcript_path, Some(destination.to_string_lossy().to_string()));
+        assert_eq!(job.transcript_model.as_deref(), Some(IMPORTED_MODEL));
+        assert_eq!(std::fs::read_to_string(&destination)?, "錬金術の基本は等価交換\n");
+
+        // A failed job is imported again
+        queue.fail_job(job_id, "boom")?;
+        assert_eq!(import_transcript(&mut queue, &data_paths, anime_id, &anime, &transcript)?, Some(job_id));
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Transcribed)?.len(), 1);
+
+        // A job already past transcription is left alone
+        queue.update_stage(job_id, JobStage::Complete)?;
+        assert_eq!(import_transcript(&mut queue, &data_paths, anime_id, &anime, &transcript)?, None);
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Complete)?.len(), 1);
+        assert_eq!(queue.get_all_jobs()?.len(), 1);
+
+        Ok(())
+    }
+}
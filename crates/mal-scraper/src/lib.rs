@@ -3,12 +3,16 @@
 //! This library provides functionality to discover categories and fetch
 //! anime information from the Jikan API v4.
 
+pub mod airing;
 pub mod api;
 pub mod cache;
 pub mod discovery;
+pub mod import;
+pub mod priority;
 pub mod scraper;
 
-pub use api::{JikanClient, RateLimiter};
+pub use api::{ApiHealth, BudgetExhausted, CacheMiss, ClientConfig, JikanClient, RateLimiter, RequestBudget};
 pub use cache::CacheManager;
-pub use discovery::{Category, CategoryType, DiscoveryManager};
-pub use scraper::{MalScraper, ScraperStats};
+pub use discovery::{Category, CategoryType, DiscoveryFilter, DiscoveryManager, WarmCacheStats};
+pub use import::ImportStats;
+pub use scraper::{AiringStats, MalScraper, ScraperStats};
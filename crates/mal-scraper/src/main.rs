@@ -1,26 +1,119 @@
 //! MAL Scraper CLI application.
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use mal_scraper::{CacheManager, DiscoveryManager, JikanClient, MalScraper};
-use shared::{Config, Database, DataPaths, JobQueue};
-use std::path::PathBuf;
-use tracing::info;
+use clap::{Parser, Subcommand};
+use mal_scraper::{
+    ApiHealth, CacheManager, ClientConfig, DiscoveryFilter, DiscoveryManager, JikanClient, MalScraper, RequestBudget,
+};
+use shared::{Config, Database, DataPaths, JobQueue, Season, DEFAULT_CONFIG_PATH};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config.toml")]
-    config: PathBuf,
+    /// Path to configuration file (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
 
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
 
+    /// Show progress bars for category discovery and detail fetching (console logs are limited to warnings; ignored when stderr is not a terminal or with --verbose)
+    #[arg(long)]
+    progress: bool,
+
     /// Clear cache before running
     #[arg(long)]
     clear_cache: bool,
+
+    /// Scrape a single broadcast season (winter, spring, summer, fall) instead of all categories
+    #[arg(long, requires = "year")]
+    season: Option<Season>,
+
+    /// Year of the season to scrape
+    #[arg(long, requires = "season")]
+    year: Option<u32>,
+
+    /// Skip anime with a MAL score below this value
+    #[arg(long)]
+    min_score: Option<f64>,
+
+    /// Skip anime less popular than this popularity rank (1 = most popular)
+    #[arg(long)]
+    max_popularity_rank: Option<u32>,
+
+    /// Skip anime scored by fewer users than this
+    #[arg(long)]
+    min_scored_by: Option<u32>,
+
+    /// Fetch relations and link sequels/prequels of the same franchise
+    #[arg(long)]
+    relations: bool,
+
+    /// Serve everything from the cache and fail on anything not cached
+    #[arg(long)]
+    offline: bool,
+
+    /// Make at most this many API requests (retries included), then stop
+    /// cleanly; cached responses are free, so a rerun picks up where this one stopped
+    #[arg(long)]
+    max_requests: Option<u64>,
+
+    /// Fetch details for at most N of the discovered anime, for quick test runs
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Write final scraping statistics as JSON to this file
+    #[arg(long)]
+    stats_json: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search MAL by title and add a single anime to the job queue
+    Add {
+        /// Title to search for
+        query: String,
+
+        /// Maximum number of search results to show
+        #[arg(long, default_value = "10")]
+        limit: u32,
+
+        /// Pick the Nth result (1-based) instead of prompting
+        #[arg(long)]
+        pick: Option<usize>,
+    },
+
+    /// Fetch and cache anime details without touching the database
+    WarmCache {
+        /// MAL IDs to fetch
+        #[arg(required_unless_present = "queued")]
+        mal_ids: Vec<u32>,
+
+        /// Fetch every anime that still has queued jobs
+        #[arg(long, conflicts_with = "mal_ids")]
+        queued: bool,
+    },
+
+    /// Import transcripts from DIR/<mal_id>/epNNN.txt as jobs at `transcribed`
+    Import {
+        /// Directory with one subdirectory of transcripts per MAL ID
+        dir: PathBuf,
+    },
+
+    /// Re-fetch anime saved while airing and enqueue their newly aired episodes
+    UpdateAiring,
 }
 
 #[tokio::main]
@@ -28,8 +121,12 @@ async fn main() -> Result<()> {
     let args = Args::parse();
 
     // Load configuration
-    let config = Config::from_file(&args.config)
-        .with_context(|| format!("Failed to load config from {}", args.config.display()))?;
+    let config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+
+    // Progress bars have to be on before logging is set up around them
+    if shared::progress::should_enable(args.progress, args.verbose) {
+        shared::progress::enable();
+    }
 
     // Initialize logging
     let log_level = if args.verbose {
@@ -45,13 +142,17 @@ async fn main() -> Result<()> {
         console: true,
         file: true,
         json_format: false,
+        module_levels: config.logging.module_levels.clone(),
     })?;
 
     info!("MAL Scraper starting");
-    info!(config_file = %args.config.display(), "Loaded configuration");
+    info!(
+        config_file = %args.config.as_deref().unwrap_or(Path::new(DEFAULT_CONFIG_PATH)).display(),
+        "Loaded configuration"
+    );
 
     // Initialize data paths
-    let data_paths = DataPaths::new(config.data_dir());
+    let data_paths = config.data_paths();
     data_paths
         .create_dirs()
         .context("Failed to create data directories")?;
@@ -67,6 +168,10 @@ async fn main() -> Result<()> {
     let cache = CacheManager::new(&cache_dir, config.mal_scraper.cache.enabled)
         .context("Failed to initialize cache")?;
 
+    if args.offline && (args.clear_cache || !config.mal_scraper.cache.enabled) {
+        anyhow::bail!("--offline needs an enabled, non-cleared cache");
+    }
+
     if args.clear_cache {
         info!("Clearing cache");
         cache.clear().context("Failed to clear cache")?;
@@ -81,28 +186,113 @@ async fn main() -> Result<()> {
     );
 
     // Initialize API client
-    let client = JikanClient::new(
+    let mut client = JikanClient::new(
         config.mal_scraper.base_url.clone(),
         config.mal_scraper.rate_limit.requests_per_second,
         config.mal_scraper.rate_limit.requests_per_minute,
         config.mal_scraper.max_retries,
         config.mal_scraper.retry_delay_ms,
     )
-    .context("Failed to create Jikan client")?;
+    .context("Failed to create Jikan client")?
+    .with_retry_policy(&config.mal_scraper.retry_policy())
+    .with_request_timeout(Duration::from_secs(config.mal_scraper.request_timeout_seconds))
+    .with_offline(args.offline);
+    if config.mal_scraper.slow_request_ms > 0 {
+        client = client.with_slow_request_threshold(Duration::from_millis(config.mal_scraper.slow_request_ms));
+    }
+
+    if let Some(proxy) = &config.mal_scraper.proxy {
+        info!(proxy = %proxy, "Sending API requests through proxy");
+        client = client.with_http_config(ClientConfig {
+            proxy: Some(proxy.clone()),
+            ..ClientConfig::default()
+        })?;
+    }
+
+    let budget = args.max_requests.map(RequestBudget::new);
+    if let Some(budget) = &budget {
+        info!(max_requests = budget.limit(), "Limiting API requests");
+        client = client.with_request_budget(budget.clone());
+    }
+
+    // Catch breaking API changes up front rather than as parse errors hours in
+    if args.offline {
+        info!("Offline mode: serving only cached responses");
+    } else {
+        match client.health_check().await {
+            ApiHealth::Healthy { latency } => {
+                info!(latency_ms = latency.as_millis() as u64, "Jikan API is healthy")
+            }
+            ApiHealth::Unreachable { error } => {
+                warn!(error = %error, "Jikan API is unreachable, continuing with cached data")
+            }
+            ApiHealth::Incompatible { error } => {
+                anyhow::bail!("Jikan API responses no longer match the expected format: {}", error)
+            }
+        }
+    }
 
     // Initialize discovery manager
+    let filter = DiscoveryFilter {
+        min_score: args.min_score,
+        max_popularity_rank: args.max_popularity_rank,
+        min_scored_by: args.min_scored_by,
+        format: config.mal_scraper.filter.clone(),
+    };
+    if filter.is_active() {
+        info!(?filter, "Filtering discovered anime");
+    }
     let discovery = DiscoveryManager::new(
         client,
         cache,
         config.mal_scraper.min_category_items,
-    );
+    )
+    .with_filter(filter)
+    .with_explicit_genres(config.mal_scraper.include_explicit)
+    .with_max_pages_per_category(config.mal_scraper.max_pages_per_category);
+
+    if let Some(Command::WarmCache { mal_ids, queued }) = &args.command {
+        if args.offline {
+            anyhow::bail!("warm-cache cannot run with --offline");
+        }
+        let mal_ids = if *queued {
+            job_queue.get_queued_mal_ids()?
+        } else {
+            mal_ids.clone()
+        };
+        return warm_cache(discovery, &mal_ids).await;
+    }
 
     // Initialize scraper
-    let mut scraper = MalScraper::new(discovery, job_queue);
+    let mut scraper = MalScraper::new(discovery, job_queue)
+        .with_relations(args.relations)
+        .with_data_paths(data_paths.clone())
+        .with_priority(config.mal_scraper.priority.clone())
+        .with_filter(config.mal_scraper.filter.clone())
+        .with_limit(args.limit);
+
+    if let Some(Command::Add { query, limit, pick }) = &args.command {
+        return add_anime(&mut scraper, query, *limit, *pick).await;
+    }
+
+    if let Some(Command::Import { dir }) = &args.command {
+        return import_transcripts(&mut scraper, dir, &data_paths).await;
+    }
+
+    if let Some(Command::UpdateAiring) = &args.command {
+        if args.offline {
+            anyhow::bail!("update-airing cannot run with --offline");
+        }
+        return update_airing(&mut scraper).await;
+    }
 
     // Run scraper
     info!("Starting MAL scraper process");
-    let stats = scraper.run().await.context("Scraper failed")?;
+    let stats = match (args.season, args.year) {
+        (Some(season), Some(year)) => scraper.run_season(year, season).await,
+        _ => scraper.run().await,
+    }
+    .context("Scraper failed")?;
 
     // Display final statistics
     info!("=== Scraping Complete ===");
@@ -110,8 +300,29 @@ async fn main() -> Result<()> {
     info!("Total anime discovered: {}", stats.total_anime_discovered);
     info!("Unique anime: {}", stats.unique_anime);
     info!("Anime saved to database: {}", stats.anime_saved);
+    if args.limit.is_some() {
+        info!("Anime skipped by --limit: {}", stats.anime_skipped);
+    }
     info!("Jobs created: {}", stats.jobs_created);
+    if args.relations {
+        info!("Relations linked: {}", stats.relations_linked);
+    }
     info!("Errors: {}", stats.errors);
+    if args.offline {
+        info!("Cache misses: {}", stats.cache_misses);
+    }
+    if let Some(budget) = &budget {
+        info!("API requests: {} of {}", budget.used(), budget.limit());
+    }
+    if stats.budget_exhausted {
+        warn!("Stopped early: --max-requests used up. Run again to continue; fetched responses are cached");
+    }
+
+    if let Some(path) = &args.stats_json {
+        std::fs::write(path, stats.to_json()?)
+            .with_context(|| format!("Failed to write stats to {}", path.display()))?;
+        info!(path = %path.display(), "Wrote scraping statistics");
+    }
 
     // Display job queue statistics
     let queue_stats = scraper.get_queue_stats().context("Failed to get queue stats")?;
@@ -127,7 +338,125 @@ async fn main() -> Result<()> {
     info!("Complete: {}", queue_stats.complete);
     info!("Failed: {}", queue_stats.failed);
 
+    if stats.cache_misses > 0 {
+        anyhow::bail!(
+            "{} request(s) were not cached; run without --offline to fetch them",
+            stats.cache_misses
+        );
+    }
+
     info!("MAL Scraper finished successfully");
 
     Ok(())
 }
+
+/// Fetch and cache details for `mal_ids`, skipping those already cached
+async fn warm_cache(mut discovery: DiscoveryManager, mal_ids: &[u32]) -> Result<()> {
+    info!(count = mal_ids.len(), "Warming anime details cache");
+
+    let stats = discovery.warm_anime_details(mal_ids).await;
+
+    info!("=== Cache Warming Complete ===");
+    info!("Requested: {}", stats.requested);
+    info!("Already cached: {}", stats.already_cached);
+    info!("Fetched: {}", stats.fetched);
+    info!("Failed: {}", stats.failed);
+
+    if stats.failed > 0 {
+        anyhow::bail!("{} anime could not be fetched; run again to retry them", stats.failed);
+    }
+
+    Ok(())
+}
+
+/// Import the transcripts in `dir` and report what was imported
+async fn import_transcripts(scraper: &mut MalScraper, dir: &Path, data_paths: &DataPaths) -> Result<()> {
+    let stats = scraper.import_transcripts(dir, data_paths).await.context("Import failed")?;
+
+    info!("=== Import Complete ===");
+    info!("Anime imported: {}", stats.anime_imported);
+    info!("Episodes imported: {}", stats.episodes_imported);
+    info!("Episodes skipped (already transcribed or in progress): {}", stats.episodes_skipped);
+    info!("Errors: {}", stats.errors);
+
+    if stats.errors > 0 {
+        anyhow::bail!("{} anime or episode(s) could not be imported; run again to retry them", stats.errors);
+    }
+
+    Ok(())
+}
+
+/// Enqueue the newly aired episodes of airing anime and report them
+async fn update_airing(scraper: &mut MalScraper) -> Result<()> {
+    let stats = scraper.update_airing().await.context("Airing update failed")?;
+
+    info!("=== Airing Update Complete ===");
+    info!("Anime updated: {}", stats.anime_updated);
+    info!("Still airing: {}", stats.still_airing);
+    info!("Jobs created: {}", stats.jobs_created);
+    info!("Errors: {}", stats.errors);
+
+    if stats.errors > 0 {
+        anyhow::bail!("{} anime could not be fetched; run again to retry them", stats.errors);
+    }
+
+    Ok(())
+}
+
+/// Search for an anime by title, let the user pick a result and enqueue it
+async fn add_anime(
+    scraper: &mut MalScraper,
+    query: &str,
+    limit: u32,
+    pick: Option<usize>,
+) -> Result<()> {
+    let results = scraper.search(query, limit).await.context("Search failed")?;
+
+    if results.is_empty() {
+        println!("No anime found for \"{}\"", query);
+        return Ok(());
+    }
+
+    for (i, entry) in results.iter().enumerate() {
+        println!(
+            "{:>2}. {} [{}] {} eps, {} (MAL {})",
+            i + 1,
+            entry.title,
+            entry.anime_type.as_deref().unwrap_or("?"),
+            entry.episodes.map_or_else(|| "?".to_string(), |e| e.to_string()),
+            entry.status.as_deref().unwrap_or("unknown status"),
+            entry.mal_id,
+        );
+    }
+
+    let index = match pick {
+        Some(n) => n,
+        None => {
+            print!("Select anime [1-{}]: ", results.len());
+            std::io::stdout().flush()?;
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            input.trim().parse().context("Invalid selection")?
+        }
+    };
+
+    let entry = index
+        .checked_sub(1)
+        .and_then(|i| results.get(i))
+        .with_context(|| format!("Selection must be between 1 and {}", results.len()))?;
+
+    let jobs_created = scraper
+        .add_anime(entry.mal_id)
+        .await
+        .with_context(|| format!("Failed to add anime {}", entry.mal_id))?;
+
+    info!(
+        mal_id = entry.mal_id,
+        title = %entry.title,
+        jobs_created = jobs_created,
+        "Added anime"
+    );
+    println!("Added {} (MAL {}): {} jobs created", entry.title, entry.mal_id, jobs_created);
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Job priority from MAL popularity and score.
+//!
+//! Jobs are dequeued highest priority first, so ranking well-known anime
+//! higher gets useful results out sooner on a partial run. The weights come
+//! from `[mal_scraper.priority]` in the config.
+
+use shared::{Anime, PriorityConfig};
+
+/// Points for the most popular anime, and for a perfect score
+const MAX_POINTS: f64 = 1000.0;
+
+/// Priority of the jobs of `anime` under `weights`.
+///
+/// The popularity component falls off with the logarithm of the rank (1000
+/// points at #1, 500 at #10, 250 at #1000), so differences among the top
+/// ranks count for more than among obscure ones. The score component is
+/// linear (100 points per score point). Missing fields count as 0.
+pub fn job_priority(anime: &Anime, weights: &PriorityConfig) -> i32 {
+    let popularity = anime
+        .popularity
+        .filter(|&rank| rank > 0)
+        .map_or(0.0, |rank| MAX_POINTS / (1.0 + f64::from(rank).log10()));
+    let score = anime.score.map_or(0.0, |score| score.clamp(0.0, 10.0) / 10.0 * MAX_POINTS);
+
+    (weights.popularity_weight * popularity + weights.score_weight * score).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_anime(popularity: Option<u32>, score: Option<f64>) -> Anime {
+        Anime {
+            score,
+            popularity,
+            duration_minutes: Some(24),
+            ..Anime::test_fixture(5114, "Test Anime", 12)
+        }
+    }
+
+    #[test]
+    fn test_more_popular_anime_get_higher_priority() {
+        let weights = PriorityConfig::default();
+        let priority = |rank| job_priority(&test_anime(Some(rank), None), &weights);
+
+        assert_eq!(priority(1), 1000);
+        assert_eq!(priority(10), 500);
+        assert_eq!(priority(1000), 250);
+        let ranks = [1, 3, 50, 400, 2500, 18000];
+        assert!(ranks.windows(2).all(|w| priority(w[0]) > priority(w[1])));
+
+        // Unranked anime go last
+        assert_eq!(job_priority(&test_anime(None, Some(9.1)), &weights), 0);
+        assert_eq!(job_priority(&test_anime(Some(0), None), &weights), 0);
+    }
+
+    #[test]
+    fn test_priority_weights() {
+        let popular_but_mediocre = test_anime(Some(5), Some(6.5));
+        let obscure_but_acclaimed = test_anime(Some(3000), Some(9.0));
+
+        let by_score = PriorityConfig {
+            popularity_weight: 0.0,
+            score_weight: 1.0,
+        };
+        assert_eq!(job_priority(&obscure_but_acclaimed, &by_score), 900);
+        assert!(job_priority(&obscure_but_acclaimed, &by_score) > job_priority(&popular_but_mediocre, &by_score));
+        assert!(
+            job_priority(&popular_but_mediocre, &PriorityConfig::default())
+                > job_priority(&obscure_but_acclaimed, &PriorityConfig::default())
+        );
+
+        let fifo = PriorityConfig {
+            popularity_weight: 0.0,
+            score_weight: 0.0,
+        };
+        assert_eq!(job_priority(&popular_but_mediocre, &fifo), 0);
+    }
+}
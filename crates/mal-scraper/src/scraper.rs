@@ -3,19 +3,69 @@
 //! Coordinates the entire MAL scraping process: discover categories,
 //! fetch anime, and save to database.
 
+use crate::airing::episodes_aired;
+use crate::api::{BudgetExhausted, CacheMiss, TopAnimeEntry};
 use crate::discovery::DiscoveryManager;
+use crate::import::{self, ImportStats, TranscriptFile};
+use crate::priority::job_priority;
 use anyhow::{Context, Result};
-use shared::{JobQueue, NewJob};
-use std::collections::HashSet;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use shared::progress::ProgressBar;
+use shared::{Anime, AnimeFilterConfig, DataPaths, JobQueue, NewJob, PriorityConfig, Season};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
 use tracing::{error, info, warn};
 
 /// Statistics for scraping session
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ScraperStats {
     pub total_categories: usize,
     pub total_anime_discovered: usize,
     pub unique_anime: usize,
     pub anime_saved: usize,
+    /// Discovered anime left unfetched because of `--limit`
+    #[serde(default)]
+    pub anime_skipped: usize,
+    pub jobs_created: usize,
+    pub relations_linked: usize,
+    pub errors: usize,
+    /// Errors caused by uncached responses in offline mode
+    #[serde(default)]
+    pub cache_misses: usize,
+    /// Whether the run stopped early because the request budget was used up
+    #[serde(default)]
+    pub budget_exhausted: bool,
+}
+
+impl ScraperStats {
+    /// Count a failed fetch. Running out of request budget is not counted as
+    /// an error but sets `budget_exhausted`, after which the run stops.
+    fn record_error(&mut self, error: &anyhow::Error) {
+        if error.downcast_ref::<BudgetExhausted>().is_some() {
+            self.budget_exhausted = true;
+            return;
+        }
+        self.errors += 1;
+        if error.downcast_ref::<CacheMiss>().is_some() {
+            self.cache_misses += 1;
+        }
+    }
+
+    /// Serialize the statistics as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize scraper statistics")
+    }
+}
+
+/// Statistics for an `update-airing` pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AiringStats {
+    /// Airing anime whose details were fetched again
+    pub anime_updated: usize,
+    /// Of those, anime that are still airing
+    pub still_airing: usize,
+    /// Jobs enqueued, including those of episodes enqueued before
     pub jobs_created: usize,
     pub errors: usize,
 }
@@ -24,6 +74,16 @@ pub struct ScraperStats {
 pub struct MalScraper {
     discovery: DiscoveryManager,
     job_queue: JobQueue,
+    /// Whether to fetch relations and link franchise entries
+    fetch_relations: bool,
+    /// Where each saved anime's `metadata.json` is written, if anywhere
+    data_paths: Option<DataPaths>,
+    /// Weights of the priority given to new jobs
+    priority: PriorityConfig,
+    /// Kinds of anime that get jobs
+    filter: AnimeFilterConfig,
+    /// Maximum number of discovered anime to fetch details for
+    limit: Option<usize>,
 }
 
 impl MalScraper {
@@ -32,9 +92,49 @@ impl MalScraper {
         Self {
             discovery,
             job_queue,
+            fetch_relations: false,
+            data_paths: None,
+            priority: PriorityConfig::default(),
+            filter: AnimeFilterConfig::default(),
+            limit: None,
         }
     }
 
+    /// Fetch details for at most `limit` of the discovered anime (in
+    /// discovery order), for quick test runs
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Weigh new jobs' priority by these weights (see
+    /// [`crate::priority::job_priority`]) instead of the defaults
+    pub fn with_priority(mut self, priority: PriorityConfig) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Only create jobs for anime of the types and episode counts `filter`
+    /// allows; others are still saved, so their metadata is available
+    pub fn with_filter(mut self, filter: AnimeFilterConfig) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Write each saved anime's database row to its `metadata.json`
+    /// (`DataPaths::anime_metadata`), so its data directory describes itself
+    pub fn with_data_paths(mut self, data_paths: DataPaths) -> Self {
+        self.data_paths = Some(data_paths);
+        self
+    }
+
+    /// Enable fetching relations to link sequels, prequels and other
+    /// entries of the same franchise (one extra API request per anime)
+    pub fn with_relations(mut self, enabled: bool) -> Self {
+        self.fetch_relations = enabled;
+        self
+    }
+
     /// Run the complete scraping process
     ///
     /// This is the main entry point that orchestrates:
@@ -49,11 +149,15 @@ impl MalScraper {
 
         // Phase 1: Discover all categories
         info!("Phase 1: Discovering categories");
-        let categories = self
-            .discovery
-            .discover_categories()
-            .await
-            .context("Failed to discover categories")?;
+        let categories = match self.discovery.discover_categories().await {
+            Ok(categories) => categories,
+            Err(e) if e.downcast_ref::<BudgetExhausted>().is_some() => {
+                warn!(error = %e, "Stopping before category discovery finished");
+                stats.budget_exhausted = true;
+                return Ok(stats);
+            }
+            Err(e) => return Err(e.context("Failed to discover categories")),
+        };
 
         stats.total_categories = categories.len();
         info!(
@@ -61,12 +165,15 @@ impl MalScraper {
             "Discovered categories"
         );
 
-        // Track unique anime across all categories
-        let mut all_anime_ids = HashSet::new();
+        // Track unique anime across all categories, in discovery order
+        let mut seen = HashSet::new();
+        let mut all_anime_ids = Vec::new();
 
         // Phase 2: Fetch anime IDs for each category (streaming)
         info!("Phase 2: Fetching anime IDs for categories");
+        let bar = ProgressBar::new("categories", categories.len() as u64);
         for (idx, category) in categories.iter().enumerate() {
+            bar.set_message(category.name.clone());
             info!(
                 progress = format!("{}/{}", idx + 1, categories.len()),
                 category = %category.name,
@@ -81,9 +188,7 @@ impl MalScraper {
             {
                 Ok(anime_ids) => {
                     stats.total_anime_discovered += anime_ids.len();
-                    for id in anime_ids {
-                        all_anime_ids.insert(id);
-                    }
+                    all_anime_ids.extend(anime_ids.into_iter().filter(|id| seen.insert(*id)));
                 }
                 Err(e) => {
                     error!(
@@ -91,10 +196,17 @@ impl MalScraper {
                         error = %e,
                         "Failed to fetch anime for category"
                     );
-                    stats.errors += 1;
+                    stats.record_error(&e);
+                    if stats.budget_exhausted {
+                        warn!("Request budget used up, stopping");
+                        bar.finish();
+                        return Ok(stats);
+                    }
                 }
             }
+            bar.inc(1);
         }
+        bar.finish();
 
         stats.unique_anime = all_anime_ids.len();
         info!(
@@ -105,12 +217,80 @@ impl MalScraper {
 
         // Phase 3: Fetch anime details and save to database (streaming)
         info!("Phase 3: Fetching anime details and saving to database");
-        let anime_vec: Vec<u32> = all_anime_ids.into_iter().collect();
+        self.save_anime(&all_anime_ids, &mut stats).await;
+
+        info!(
+            categories = stats.total_categories,
+            total_anime_discovered = stats.total_anime_discovered,
+            unique_anime = stats.unique_anime,
+            anime_saved = stats.anime_saved,
+            anime_skipped = stats.anime_skipped,
+            jobs_created = stats.jobs_created,
+            relations_linked = stats.relations_linked,
+            errors = stats.errors,
+            "MAL scraper complete"
+        );
+
+        Ok(stats)
+    }
+
+    /// Scrape every anime broadcast in a single season
+    ///
+    /// Skips category discovery and fetches the season listing instead.
+    pub async fn run_season(&mut self, year: u32, season: Season) -> Result<ScraperStats> {
+        info!(year = year, season = %season, "Starting seasonal MAL scrape");
+
+        let mut stats = ScraperStats::default();
+
+        let anime_ids = match self.discovery.discover_season(year, season).await {
+            Ok(anime_ids) => anime_ids,
+            Err(e) if e.downcast_ref::<BudgetExhausted>().is_some() => {
+                warn!(error = %e, "Stopping before season discovery finished");
+                stats.budget_exhausted = true;
+                return Ok(stats);
+            }
+            Err(e) => return Err(e.context(format!("Failed to discover {} {} anime", season, year))),
+        };
+
+        stats.total_anime_discovered = anime_ids.len();
+        stats.unique_anime = anime_ids.len();
+
+        info!("Fetching anime details and saving to database");
+        self.save_anime(&anime_ids, &mut stats).await;
+
+        info!(
+            year = year,
+            season = %season,
+            unique_anime = stats.unique_anime,
+            anime_saved = stats.anime_saved,
+            anime_skipped = stats.anime_skipped,
+            jobs_created = stats.jobs_created,
+            relations_linked = stats.relations_linked,
+            errors = stats.errors,
+            "Seasonal MAL scrape complete"
+        );
 
-        for (idx, mal_id) in anime_vec.iter().enumerate() {
-            if (idx + 1) % 100 == 0 || idx + 1 == anime_vec.len() {
+        Ok(stats)
+    }
+
+    /// Fetch details for each anime, save them and create jobs, recording
+    /// progress in `stats`. Stops early if the request budget is used up.
+    /// Only the first `limit` anime are fetched, if a limit is set.
+    async fn save_anime(&mut self, anime_ids: &[u32], stats: &mut ScraperStats) {
+        let anime_ids = match self.limit {
+            Some(limit) if limit < anime_ids.len() => {
+                info!(limit, discovered = anime_ids.len(), "Limiting anime detail fetches");
+                stats.anime_skipped = anime_ids.len() - limit;
+                &anime_ids[..limit]
+            }
+            _ => anime_ids,
+        };
+        let bar = ProgressBar::new("anime", anime_ids.len() as u64);
+        for (idx, mal_id) in anime_ids.iter().enumerate() {
+            bar.set_message(format!("MAL {}", mal_id));
+            if (idx + 1) % 100 == 0 || idx + 1 == anime_ids.len() {
                 info!(
-                    progress = format!("{}/{}", idx + 1, anime_vec.len()),
+                    progress = format!("{}/{}", idx + 1, anime_ids.len()),
                     "Fetching anime details"
                 );
             }
@@ -122,22 +302,51 @@ impl MalScraper {
                 }
                 Err(e) => {
                     error!(mal_id = mal_id, error = %e, "Failed to fetch anime");
-                    stats.errors += 1;
+                    stats.record_error(&e);
+                    if stats.budget_exhausted {
+                        warn!("Request budget used up, stopping");
+                        break;
+                    }
+                    bar.inc(1);
+                    continue;
                 }
             }
+
+            if self.fetch_relations {
+                match self.link_relations(*mal_id).await {
+                    Ok(linked) => stats.relations_linked += linked,
+                    Err(e) => {
+                        warn!(mal_id = mal_id, error = %e, "Failed to fetch relations");
+                        stats.record_error(&e);
+                        if stats.budget_exhausted {
+                            warn!("Request budget used up, stopping");
+                            break;
+                        }
+                    }
+                }
+            }
+            bar.inc(1);
         }
+        bar.finish();
+    }
 
-        info!(
-            categories = stats.total_categories,
-            total_anime_discovered = stats.total_anime_discovered,
-            unique_anime = stats.unique_anime,
-            anime_saved = stats.anime_saved,
-            jobs_created = stats.jobs_created,
-            errors = stats.errors,
-            "MAL scraper complete"
-        );
+    /// Search anime by title
+    pub async fn search(&mut self, query: &str, limit: u32) -> Result<Vec<TopAnimeEntry>> {
+        self.discovery.search_anime(query, limit).await
+    }
 
-        Ok(stats)
+    /// Fetch a single anime by MAL ID, save it and create its jobs
+    ///
+    /// Returns the number of jobs created
+    pub async fn add_anime(&mut self, mal_id: u32) -> Result<usize> {
+        let jobs_created = self.fetch_and_save_anime(mal_id).await?;
+
+        if self.fetch_relations {
+            let linked = self.link_relations(mal_id).await?;
+            info!(mal_id = mal_id, linked = linked, "Linked related anime");
+        }
+
+        Ok(jobs_created)
     }
 
     /// Fetch anime details and save to database (with deduplication)
@@ -152,23 +361,49 @@ impl MalScraper {
             .with_context(|| format!("Failed to fetch anime {}", mal_id))?;
 
         // Save to database (with deduplication)
-        let anime_id = self
-            .job_queue
-            .get_or_create_anime(&anime)
-            .context("Failed to save anime to database")?;
+        let anime_id = self.save_anime_row(&anime)?;
 
-        // Create jobs for each episode
-        let episodes = anime.episodes_total.unwrap_or(0);
+        self.enqueue_episodes(anime_id, &anime)
+    }
+
+    /// Create jobs for the episodes of `anime` aired so far (see
+    /// [`crate::airing::episodes_aired`])
+    ///
+    /// Returns the number of jobs created
+    fn enqueue_episodes(&mut self, anime_id: i64, anime: &Anime) -> Result<usize> {
+        let mal_id = anime.mal_id;
+        if !self.filter.matches(anime.anime_type.as_deref(), anime.episodes_total) {
+            info!(
+                mal_id = mal_id,
+                title = %anime.title,
+                anime_type = ?anime.anime_type,
+                episodes = ?anime.episodes_total,
+                "Anime excluded by [mal_scraper.filter], skipping job creation"
+            );
+            return Ok(0);
+        }
+        let episodes = episodes_aired(anime, Utc::now().date_naive());
 
         if episodes == 0 {
             warn!(
                 mal_id = mal_id,
                 title = %anime.title,
-                "Anime has 0 episodes, skipping job creation"
+                status = ?anime.status,
+                "Anime has no aired episodes, skipping job creation"
             );
             return Ok(0);
         }
+        if anime.is_airing() {
+            info!(
+                mal_id = mal_id,
+                title = %anime.title,
+                aired = episodes,
+                planned = ?anime.episodes_total,
+                "Anime is still airing, enqueueing aired episodes only; run update-airing later for the rest"
+            );
+        }
 
+        let priority = job_priority(anime, &self.priority);
         let mut jobs_created = 0;
         for episode in 1..=episodes {
             let new_job = NewJob {
@@ -176,7 +411,9 @@ impl MalScraper {
                 mal_id: anime.mal_id,
                 anime_title: anime.title.clone(),
                 episode,
-                priority: 0, // Default priority
+                season: anime.season.as_deref().and_then(|s| s.parse::<Season>().ok()).map(|s| s.number()),
+                year: anime.year,
+                priority,
             };
 
             match self.job_queue.enqueue(&new_job) {
@@ -196,8 +433,358 @@ impl MalScraper {
         Ok(jobs_created)
     }
 
+    /// Fetch the details of every anime saved while airing again, bypassing
+    /// the cache, and enqueue the episodes that have aired since
+    ///
+    /// An anime that has finished airing in the meantime gets all its
+    /// episodes and is not checked again.
+    pub async fn update_airing(&mut self) -> Result<AiringStats> {
+        let mal_ids = self.job_queue.get_airing_mal_ids()?;
+        info!(anime = mal_ids.len(), "Updating airing anime");
+
+        let mut stats = AiringStats::default();
+        for mal_id in mal_ids {
+            self.discovery.forget_anime_details(mal_id)?;
+            let anime = match self.discovery.fetch_anime_details(mal_id).await {
+                Ok(anime) => anime,
+                Err(e) if e.downcast_ref::<BudgetExhausted>().is_some() => {
+                    warn!("Request budget used up, stopping");
+                    break;
+                }
+                Err(e) => {
+                    error!(mal_id = mal_id, error = %e, "Failed to fetch anime");
+                    stats.errors += 1;
+                    continue;
+                }
+            };
+
+            self.job_queue.update_anime_airing(&anime)?;
+            let anime_id = self.save_anime_row(&anime)?;
+            stats.anime_updated += 1;
+            if anime.is_airing() {
+                stats.still_airing += 1;
+            }
+            stats.jobs_created += self.enqueue_episodes(anime_id, &anime)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Import the transcripts in `dir` (see [`crate::import`]) as jobs at
+    /// `transcribed`, fetching details (cached) for each anime first
+    pub async fn import_transcripts(&mut self, dir: &Path, data_paths: &DataPaths) -> Result<ImportStats> {
+        let mut by_anime: BTreeMap<u32, Vec<TranscriptFile>> = BTreeMap::new();
+        for transcript in import::find_transcripts(dir)? {
+            by_anime.entry(transcript.mal_id).or_default().push(transcript);
+        }
+        info!(anime = by_anime.len(), dir = %dir.display(), "Importing transcripts");
+
+        let mut stats = ImportStats::default();
+        for (mal_id, transcripts) in &by_anime {
+            let anime = match self.discovery.fetch_anime_details(*mal_id).await {
+                Ok(anime) => anime,
+                Err(e) if e.downcast_ref::<BudgetExhausted>().is_some() => {
+                    warn!("Request budget used up, stopping");
+                    break;
+                }
+                Err(e) => {
+                    error!(mal_id = mal_id, error = %e, "Failed to fetch anime, skipping its transcripts");
+                    stats.errors += 1;
+                    continue;
+                }
+            };
+            let anime_id = self.save_anime_row(&anime)?;
+
+            let mut imported = 0;
+            for transcript in transcripts {
+                match import::import_transcript(&mut self.job_queue, data_paths, anime_id, &anime, transcript) {
+                    Ok(Some(_)) => imported += 1,
+                    Ok(None) => stats.episodes_skipped += 1,
+                    Err(e) => {
+                        error!(
+                            mal_id = mal_id,
+                            episode = transcript.episode,
+                            error = %e,
+                            "Failed to import transcript"
+                        );
+                        stats.errors += 1;
+                    }
+                }
+            }
+
+            info!(mal_id = mal_id, title = %anime.title, episodes = imported, "Imported transcripts");
+            if imported > 0 {
+                stats.anime_imported += 1;
+                stats.episodes_imported += imported;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Save an anime to the database (with deduplication) and write its row
+    /// to `metadata.json`. Returns its database ID.
+    fn save_anime_row(&mut self, anime: &Anime) -> Result<i64> {
+        let anime_id = self
+            .job_queue
+            .get_or_create_anime(anime)
+            .context("Failed to save anime to database")?;
+
+        if let Some(data_paths) = &self.data_paths {
+            if let Some(saved) = self.job_queue.get_anime(anime.mal_id)? {
+                data_paths
+                    .write_anime_metadata(&saved)
+                    .with_context(|| format!("Failed to write metadata for anime {}", anime.mal_id))?;
+            }
+        }
+
+        Ok(anime_id)
+    }
+
+    /// Fetch relations for an anime and link related anime entries
+    ///
+    /// Returns the number of links recorded
+    async fn link_relations(&mut self, mal_id: u32) -> Result<usize> {
+        let related = self
+            .discovery
+            .fetch_related_anime(mal_id)
+            .await
+            .with_context(|| format!("Failed to fetch relations for anime {}", mal_id))?;
+
+        for (relation, related_id) in &related {
+            let (parent, child) = relation_link(relation, mal_id, *related_id);
+            self.job_queue.link_related(parent, child)?;
+        }
+
+        Ok(related.len())
+    }
+
     /// Get current scraping statistics
     pub fn get_queue_stats(&self) -> Result<shared::queue::JobStats> {
         self.job_queue.get_stats()
     }
 }
+
+/// Order a relation as `(parent, child)`
+///
+/// Prequels and parent/full stories of `mal_id` become its parent; every
+/// other relation (sequel, side story, spin-off, ...) becomes its child.
+fn relation_link(relation: &str, mal_id: u32, related_id: u32) -> (u32, u32) {
+    match relation {
+        "Prequel" | "Parent Story" | "Full Story" => (related_id, mal_id),
+        _ => (mal_id, related_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scraper_stats_json_round_trip() -> Result<()> {
+        let stats = ScraperStats {
+            total_categories: 120,
+            total_anime_discovered: 4000,
+            unique_anime: 2500,
+            anime_saved: 2490,
+            anime_skipped: 0,
+            jobs_created: 30000,
+            relations_linked: 800,
+            errors: 10,
+            cache_misses: 2,
+            budget_exhausted: true,
+        };
+
+        let json = stats.to_json()?;
+        let parsed: ScraperStats = serde_json::from_str(&json)?;
+        assert_eq!(parsed, stats);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_added_anime_has_metadata_json() -> Result<()> {
+        use crate::cache::CacheManager;
+        use crate::discovery::anime_details_key;
+        use crate::JikanClient;
+        use shared::Database;
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let cache = CacheManager::new(temp_dir.path().join("cache"), true)?;
+        cache.set(
+            &anime_details_key(5114),
+            &serde_json::json!({
+                "mal_id": 5114, "url": "", "images": {"jpg": {}},
+                "title": "Fullmetal Alchemist: Brotherhood", "title_synonyms": ["Hagane no Renkinjutsushi"],
+                "type": "TV", "episodes": 64, "airing": false,
+                "aired": {"from": "2009-04-05T00:00:00+00:00", "prop": {"from": {}, "to": {}}},
+                "duration": "24 min per ep", "score": 9.1, "season": "spring", "year": 2009,
+                "producers": [], "licensors": [], "studios": [{"mal_id": 4, "type": "anime", "name": "Bones", "url": ""}],
+                "genres": [], "explicit_genres": [], "themes": [], "demographics": []
+            }),
+        )?;
+
+        // Served from the cache only
+        let client = JikanClient::new("http://127.0.0.1:9".to_string(), 100.0, 1000, 0, 1)?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let data_paths = DataPaths::new(temp_dir.path().join("data"));
+        let mut scraper = MalScraper::new(DiscoveryManager::new(client, cache, 50), JobQueue::new(Database::open(&db_path)?))
+            .with_data_paths(data_paths.clone());
+
+        assert_eq!(scraper.add_anime(5114).await?, 64);
+
+        let saved = JobQueue::new(Database::open(&db_path)?).get_anime(5114)?.unwrap();
+        assert!(data_paths.anime_metadata(5114).exists());
+        assert_eq!(data_paths.read_anime_metadata(5114)?, saved);
+        assert_eq!(saved.studios, ["Bones"]);
+
+        Ok(())
+    }
+
+    /// Jikan details of an airing 24-episode show that started `weeks_ago`
+    fn airing_details(weeks_ago: i64) -> serde_json::Value {
+        let start = Utc::now().date_naive() - chrono::Duration::weeks(weeks_ago);
+        serde_json::json!({
+            "mal_id": 52991, "url": "", "images": {"jpg": {}},
+            "title": "Sousou no Frieren", "title_synonyms": [],
+            "type": "TV", "episodes": 24, "status": "Currently Airing", "airing": true,
+            "aired": {"from": format!("{}T00:00:00+00:00", start), "prop": {"from": {}, "to": {}}},
+            "season": "fall", "year": 2023, "producers": [], "licensors": [], "studios": [],
+            "genres": [], "explicit_genres": [], "themes": [], "demographics": []
+        })
+    }
+
+    #[tokio::test]
+    async fn test_airing_anime_enqueues_aired_episodes_only() -> Result<()> {
+        use crate::cache::CacheManager;
+        use crate::discovery::anime_details_key;
+        use crate::JikanClient;
+        use shared::test_server::{TestResponse, TestServer};
+        use shared::{Database, JobStage};
+
+        // Serves the details three weeks later
+        let server = TestServer::start(|_| Some(TestResponse::json(&serde_json::json!({"data": airing_details(10)})))).await?;
+        let base_url = server.url().to_string();
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let cache = CacheManager::new(temp_dir.path().join("cache"), true)?;
+        cache.set(&anime_details_key(52991), &airing_details(7))?;
+
+        let client = JikanClient::new(base_url, 100.0, 1000, 0, 1)?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut scraper =
+            MalScraper::new(DiscoveryManager::new(client, cache, 50), JobQueue::new(Database::open(&db_path)?));
+
+        // 24 planned, 8 aired so far
+        assert_eq!(scraper.add_anime(52991).await?, 8);
+        let queue = JobQueue::new(Database::open(&db_path)?);
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Queued)?.len(), 8);
+        assert_eq!(queue.get_airing_mal_ids()?, [52991]);
+
+        // The later pass fetches fresh details and adds the new episodes
+        let stats = scraper.update_airing().await?;
+        assert_eq!((stats.anime_updated, stats.still_airing, stats.errors), (1, 1, 0));
+        let mut episodes: Vec<u32> = queue.get_jobs_by_stage(JobStage::Queued)?.iter().map(|j| j.episode).collect();
+        episodes.sort();
+        assert_eq!(episodes, (1..=11).collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_limit_caps_anime_fetched() -> Result<()> {
+        use crate::cache::CacheManager;
+        use crate::discovery::anime_details_key;
+        use crate::JikanClient;
+        use shared::{Database, Season};
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let cache = CacheManager::new(temp_dir.path().join("cache"), true)?;
+        let mal_ids = [5114, 9253, 11061];
+        cache.set(
+            "season_2009_spring",
+            &mal_ids
+                .map(|mal_id| serde_json::json!({"mal_id": mal_id, "url": "", "images": {"jpg": {}}, "title": "Test Anime"})),
+        )?;
+        for mal_id in mal_ids {
+            cache.set(
+                &anime_details_key(mal_id),
+                &serde_json::json!({
+                    "mal_id": mal_id, "url": "", "images": {"jpg": {}}, "title": "Test Anime", "title_synonyms": [],
+                    "type": "TV", "episodes": 12, "airing": false, "aired": {"prop": {"from": {}, "to": {}}},
+                    "producers": [], "licensors": [], "studios": [],
+                    "genres": [], "explicit_genres": [], "themes": [], "demographics": []
+                }),
+            )?;
+        }
+
+        // Served from the cache only
+        let client = JikanClient::new("http://127.0.0.1:9".to_string(), 100.0, 1000, 0, 1)?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut scraper = MalScraper::new(DiscoveryManager::new(client, cache, 50), JobQueue::new(Database::open(&db_path)?))
+            .with_limit(Some(2));
+
+        let stats = scraper.run_season(2009, Season::Spring).await?;
+        assert_eq!(
+            (stats.unique_anime, stats.anime_saved, stats.anime_skipped, stats.jobs_created, stats.errors),
+            (3, 2, 1, 24, 0)
+        );
+
+        // The first anime discovered are the ones fetched
+        let queue = JobQueue::new(Database::open(&db_path)?);
+        assert!(queue.get_anime(5114)?.is_some());
+        assert!(queue.get_anime(9253)?.is_some());
+        assert!(queue.get_anime(11061)?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter_skips_jobs_of_movies() -> Result<()> {
+        use crate::cache::CacheManager;
+        use crate::discovery::anime_details_key;
+        use crate::JikanClient;
+        use shared::{Database, JobStage};
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let cache = CacheManager::new(temp_dir.path().join("cache"), true)?;
+        for (mal_id, anime_type, episodes) in [(5114, "TV", 64), (199, "Movie", 1)] {
+            cache.set(
+                &anime_details_key(mal_id),
+                &serde_json::json!({
+                    "mal_id": mal_id, "url": "", "images": {"jpg": {}}, "title": "Test Anime", "title_synonyms": [],
+                    "type": anime_type, "episodes": episodes, "airing": false, "aired": {"prop": {"from": {}, "to": {}}},
+                    "producers": [], "licensors": [], "studios": [],
+                    "genres": [], "explicit_genres": [], "themes": [], "demographics": []
+                }),
+            )?;
+        }
+
+        let client = JikanClient::new("http://127.0.0.1:9".to_string(), 100.0, 1000, 0, 1)?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut scraper = MalScraper::new(DiscoveryManager::new(client, cache, 50), JobQueue::new(Database::open(&db_path)?))
+            .with_filter(AnimeFilterConfig {
+                min_episodes: Some(2),
+                types: vec!["TV".to_string()],
+                ..Default::default()
+            });
+
+        assert_eq!(scraper.add_anime(5114).await?, 64);
+        assert_eq!(scraper.add_anime(199).await?, 0);
+
+        // The movie is saved, just without jobs
+        let queue = JobQueue::new(Database::open(&db_path)?);
+        assert!(queue.get_anime(199)?.is_some());
+        assert!(queue.get_jobs_by_stage(JobStage::Queued)?.iter().all(|job| job.mal_id == 5114));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relation_link_direction() {
+        assert_eq!(relation_link("Sequel", 16498, 25777), (16498, 25777));
+        assert_eq!(relation_link("Prequel", 25777, 16498), (16498, 25777));
+        assert_eq!(relation_link("Parent Story", 2, 1), (1, 2));
+        assert_eq!(relation_link("Side Story", 1, 2), (1, 2));
+    }
+}
@@ -0,0 +1,35 @@
+//! Prometheus metrics endpoint.
+//!
+//! Serves `shared::metrics::collect` output at `/metrics` over plain HTTP.
+
+use anyhow::{anyhow, Result};
+use shared::{DiskMonitor, JobQueue};
+use tiny_http::{Header, Response, Server};
+
+/// Content type of the Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Serve metrics on `addr` (e.g. `0.0.0.0:9184`) until the process is killed
+pub fn serve(addr: &str, job_queue: &JobQueue, disk_monitor: &DiskMonitor) -> Result<()> {
+    let server = Server::http(addr).map_err(|e| anyhow!("Failed to listen on {}: {}", addr, e))?;
+    println!("Serving metrics on http://{}/metrics", addr);
+
+    let content_type: Header = format!("Content-Type: {}", CONTENT_TYPE)
+        .parse()
+        .map_err(|_| anyhow!("Invalid content type header"))?;
+
+    for request in server.incoming_requests() {
+        let response = match request.url() {
+            "/metrics" => match shared::metrics::collect(job_queue, disk_monitor) {
+                Ok(metrics) => Response::from_string(metrics.to_prometheus()).with_header(content_type.clone()),
+                Err(e) => Response::from_string(format!("Failed to collect metrics: {:#}\n", e)).with_status_code(500),
+            },
+            _ => Response::from_string("Not found\n").with_status_code(404),
+        };
+
+        // A client hanging up mid-response is not fatal for the exporter
+        let _ = request.respond(response);
+    }
+
+    Ok(())
+}
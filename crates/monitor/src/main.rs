@@ -0,0 +1,239 @@
+//! Live terminal dashboard for the processing pipeline.
+//!
+//! Opens the job database read-only and periodically renders job counts per
+//! stage, disk usage and recent failures. Safe to run alongside the workers.
+//! Built with the `metrics` feature, it can instead serve the same figures
+//! as a Prometheus endpoint (`--serve-metrics`).
+//!
+//! `monitor export DIR` writes the anime table and the completed jobs as CSV
+//! or JSON Lines for analysis in pandas or R, `monitor failures` counts
+//! the failed jobs by error signature, and `monitor retries` reports the
+//! retries jobs have consumed.
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::DefaultTerminal;
+use shared::{Config, Database, DiskMonitor, ExportFormat, JobQueue, JobStage};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+mod exporter;
+mod snapshot;
+mod ui;
+
+use snapshot::build_snapshot;
+use ui::Dashboard;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to configuration file (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Refresh interval in seconds
+    #[arg(short, long, default_value = "2")]
+    interval: u64,
+
+    /// Number of recent failures to show
+    #[arg(long, default_value = "50")]
+    failures: usize,
+
+    /// Retry limit the workers run with, for the jobs reported at it (default: [pipeline.retry], else each job's max_retries)
+    #[arg(long, global = true)]
+    max_retries: Option<u32>,
+
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9184) instead of the dashboard
+    #[cfg(feature = "metrics")]
+    #[arg(long)]
+    serve_metrics: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export the anime table and completed jobs for external analysis
+    Export {
+        /// Directory to write `anime.<ext>` and `jobs.<ext>` into
+        #[arg(default_value = "export")]
+        output_dir: PathBuf,
+
+        /// Output format: csv or jsonl
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+    },
+
+    /// Count failed jobs by cause (error message with ids and paths stripped)
+    Failures,
+
+    /// Report the retries consumed, overall and by the anime with the most
+    Retries,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Load configuration
+    let config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+
+    // Open database read-only (never creates or migrates it)
+    let db_path = config.database_path();
+    let database = Database::open_read_only(&db_path)
+        .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+    let job_queue = JobQueue::new(database);
+    let max_retries = args.max_retries.or(config.pipeline.retry.map(|retry| retry.max_retries()));
+
+    match &args.command {
+        Some(Command::Export { output_dir, format }) => return export(&job_queue, output_dir, *format),
+        Some(Command::Failures) => return print_failures(&job_queue),
+        Some(Command::Retries) => return print_retries(&job_queue, max_retries),
+        None => {}
+    }
+
+    // Disk usage is cached for one refresh interval so a slow walk of the
+    // videos directory doesn't happen more often than the screen updates
+    let interval = Duration::from_secs(args.interval.max(1));
+    let disk_monitor = DiskMonitor::new(
+        config.data_dir(),
+        config.storage_dir(),
+        config.disk_management.hard_limit_gb,
+        config.disk_management.pause_threshold_gb,
+        config.disk_management.resume_threshold_gb,
+        interval,
+    )
+    .context("Failed to initialize disk monitor")?
+    .with_layout(config.path_layout());
+
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = &args.serve_metrics {
+        return exporter::serve(addr, &job_queue, &disk_monitor);
+    }
+
+    // Worker counts the downloader and transcriber default to, capped by
+    // the stage limits, for the ETA
+    let limits = config.stage_concurrency();
+    let workers = [JobStage::Downloading, JobStage::Transcribing].map(|stage| {
+        let default_workers = match stage {
+            JobStage::Downloading => config.disk_management.max_concurrent_downloads,
+            _ => config.disk_management.max_concurrent_transcriptions,
+        };
+        (stage, limits.limit(stage).map_or(default_workers, |limit| limit.min(default_workers)))
+    });
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &job_queue, &disk_monitor, interval, args.failures, &workers, max_retries);
+    ratatui::restore();
+
+    result
+}
+
+/// Write `anime.<ext>` and `jobs.<ext>` into `output_dir`
+fn export(job_queue: &JobQueue, output_dir: &Path, format: ExportFormat) -> Result<()> {
+    let anime_path = output_dir.join(format!("anime.{}", format.extension()));
+    let anime = job_queue.export_anime(format, &anime_path)?;
+    println!("Wrote {} anime to {}", anime, anime_path.display());
+
+    let jobs_path = output_dir.join(format!("jobs.{}", format.extension()));
+    let jobs = job_queue.export_jobs(format, &jobs_path)?;
+    println!("Wrote {} completed jobs to {}", jobs, jobs_path.display());
+
+    Ok(())
+}
+
+/// Print the failure summary, most common cause first
+fn print_failures(job_queue: &JobQueue) -> Result<()> {
+    let summary = job_queue.failure_summary()?;
+    if summary.is_empty() {
+        println!("No failed jobs");
+        return Ok(());
+    }
+
+    let width = summary.iter().map(|(_, count)| count.to_string().len()).max().unwrap_or(1);
+    for (signature, count) in &summary {
+        println!("{:>width$}  {}", count, signature, width = width);
+    }
+    println!("{} failed jobs", summary.iter().map(|(_, count)| count).sum::<usize>());
+
+    Ok(())
+}
+
+/// Print the retry report, anime with the most retries first
+fn print_retries(job_queue: &JobQueue, max_retries: Option<u32>) -> Result<()> {
+    let report = job_queue.retry_report(max_retries)?;
+    println!("{} retries consumed by {} jobs", report.total_retries, report.jobs_retried);
+    println!("{} jobs have used all their retries", report.jobs_at_max_retries);
+    if report.top_anime.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    let width = report
+        .top_anime
+        .iter()
+        .map(|anime| anime.retries.to_string().len())
+        .max()
+        .unwrap_or(1);
+    for anime in &report.top_anime {
+        println!(
+            "{:>width$}  {} (MAL {}, {} jobs)",
+            anime.retries,
+            anime.title,
+            anime.mal_id,
+            anime.jobs_retried,
+            width = width
+        );
+    }
+
+    Ok(())
+}
+
+/// Refresh and redraw until the user quits
+fn run(
+    terminal: &mut DefaultTerminal,
+    job_queue: &JobQueue,
+    disk_monitor: &DiskMonitor,
+    interval: Duration,
+    failure_limit: usize,
+    workers: &[(JobStage, usize)],
+    max_retries: Option<u32>,
+) -> Result<()> {
+    let mut dashboard = Dashboard::default();
+    let mut last_refresh: Option<Instant> = None;
+
+    loop {
+        if last_refresh.is_none_or(|t| t.elapsed() >= interval) {
+            match build_snapshot(job_queue, Some(disk_monitor), failure_limit, workers, max_retries) {
+                Ok(snapshot) => dashboard.update(snapshot),
+                Err(e) => dashboard.error = Some(format!("{:#}", e)),
+            }
+            last_refresh = Some(Instant::now());
+        }
+
+        terminal.draw(|frame| ui::render(frame, &mut dashboard))?;
+
+        // Wait for input until the next refresh is due
+        let timeout = interval.saturating_sub(last_refresh.map_or(Duration::ZERO, |t| t.elapsed()));
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('r') => last_refresh = None,
+                    KeyCode::Down | KeyCode::Char('j') => dashboard.scroll_down(),
+                    KeyCode::Up | KeyCode::Char('k') => dashboard.scroll_up(),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Data collection for the monitor dashboard.
+//!
+//! Everything the dashboard shows is gathered into a `Snapshot` here, so the
+//! rendering code never touches the database or the filesystem.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use shared::{DiskMonitor, Job, JobQueue, JobStage, JobStats, RemainingWork, RetryReport, SpaceBreakdown};
+use std::time::Duration;
+
+/// Per-stage row of the dashboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageRow {
+    pub stage: JobStage,
+    /// Number of jobs currently in this stage
+    pub jobs: usize,
+    /// Average seconds jobs have been sitting in this stage
+    pub avg_seconds_in_stage: f64,
+    /// Seconds the oldest job in this stage has been waiting (None when empty,
+    /// and for complete/failed, where jobs are not waiting on anything)
+    pub oldest_seconds: Option<f64>,
+}
+
+/// Failed job as shown in the failures pane
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedJob {
+    pub id: i64,
+    pub mal_id: u32,
+    pub anime_title: String,
+    pub episode: u32,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl From<Job> for FailedJob {
+    fn from(job: Job) -> Self {
+        Self {
+            id: job.id,
+            mal_id: job.mal_id,
+            anime_title: job.anime_title,
+            episode: job.episode,
+            error: job.error_message.unwrap_or_default(),
+            failed_at: job.updated_at,
+        }
+    }
+}
+
+/// Point-in-time view of the pipeline
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    pub stats: JobStats,
+    /// One row per pipeline stage, in pipeline order (stages without jobs included)
+    pub stages: Vec<StageRow>,
+    /// Jobs not yet complete or failed
+    pub remaining: RemainingWork,
+    /// Rough time until the remaining jobs are done (None without timings)
+    pub eta: Option<Duration>,
+    /// Disk usage, if a disk monitor is available and the walk succeeded
+    pub space: Option<SpaceBreakdown>,
+    /// Most recent failures, newest first
+    pub failures: Vec<FailedJob>,
+    /// Retries consumed so far
+    pub retries: RetryReport,
+}
+
+impl Snapshot {
+    /// Fraction of all jobs that are complete (0.0 to 1.0)
+    pub fn completion_ratio(&self) -> f64 {
+        if self.stats.total == 0 {
+            0.0
+        } else {
+            self.stats.complete as f64 / self.stats.total as f64
+        }
+    }
+}
+
+/// Collect a snapshot from the job queue and (optionally) the disk monitor.
+///
+/// `workers` is the number of workers per in-progress stage, used for the ETA,
+/// and `max_retries` the retry limit they run with (None = each job's own).
+pub fn build_snapshot(
+    queue: &JobQueue,
+    disk_monitor: Option<&DiskMonitor>,
+    failure_limit: usize,
+    workers: &[(JobStage, usize)],
+    max_retries: Option<u32>,
+) -> Result<Snapshot> {
+    let stats = queue.get_stats()?;
+    let timings = queue.get_stage_timings()?;
+
+    let stages = JobStage::all()
+        .iter()
+        .map(|stage| {
+            let timing = timings.iter().find(|t| t.stage == *stage);
+            let oldest = match stage {
+                JobStage::Complete | JobStage::Failed => None,
+                _ => queue.oldest_pending_age(*stage)?,
+            };
+            Ok(StageRow {
+                stage: *stage,
+                jobs: timing.map_or(0, |t| t.jobs),
+                avg_seconds_in_stage: timing.map_or(0.0, |t| t.avg_seconds_in_stage),
+                oldest_seconds: oldest.map(|age| age.as_secs_f64()),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let remaining = queue.remaining_work()?;
+    let eta = remaining.estimate(&timings, workers);
+
+    // A failed disk walk (e.g. unmounted external drive) should not take
+    // the whole dashboard down
+    let space = disk_monitor.and_then(|m| m.get_breakdown().ok());
+
+    let failures = queue
+        .get_recent_failures(failure_limit)?
+        .into_iter()
+        .map(FailedJob::from)
+        .collect();
+
+    let retries = queue.retry_report(max_retries)?;
+
+    Ok(Snapshot {
+        taken_at: Utc::now(),
+        stats,
+        stages,
+        remaining,
+        eta,
+        space,
+        failures,
+        retries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{Anime, Database, NewJob};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_build_snapshot() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("jobs.db");
+
+        // Seed a database through the normal read-write path
+        {
+            let mut queue = JobQueue::new(Database::open(&db_path)?);
+            let anime = Anime::test_fixture(5114, "Test Anime", 4);
+            let anime_id = queue.get_or_create_anime(&anime)?;
+
+            let mut job_ids = Vec::new();
+            for episode in 1..=4 {
+                job_ids.push(queue.enqueue(&NewJob {
+                    anime_id,
+                    mal_id: 5114,
+                    anime_title: "Test Anime".to_string(),
+                    episode,
+                    season: None,
+                    year: None,
+                    priority: 0,
+                })?);
+            }
+            queue.update_stage(job_ids[0], JobStage::Complete)?;
+            queue.update_stage(job_ids[1], JobStage::Downloaded)?;
+            queue.fail_job(job_ids[2], "ani-cli exited with status 1")?;
+        }
+
+        let storage_dir = TempDir::new()?;
+        std::fs::create_dir_all(storage_dir.path().join("videos"))?;
+        std::fs::write(storage_dir.path().join("videos").join("ep1.mp4"), vec![0u8; 1000])?;
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            storage_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+
+        let queue = JobQueue::new(Database::open_read_only(&db_path)?);
+        let snapshot = build_snapshot(&queue, Some(&disk_monitor), 10, &[], Some(1))?;
+
+        assert_eq!(snapshot.stats.total, 4);
+        assert_eq!(snapshot.completion_ratio(), 0.25);
+
+        assert_eq!(snapshot.stages.len(), JobStage::all().len());
+        let jobs_in = |stage: JobStage| snapshot.stages.iter().find(|r| r.stage == stage).unwrap().jobs;
+        assert_eq!(jobs_in(JobStage::Queued), 1);
+        assert_eq!(jobs_in(JobStage::Downloaded), 1);
+        assert_eq!(jobs_in(JobStage::Complete), 1);
+        assert_eq!(jobs_in(JobStage::Failed), 1);
+        assert_eq!(jobs_in(JobStage::Transcribing), 0);
+        let queued = snapshot.stages.iter().find(|r| r.stage == JobStage::Queued).unwrap();
+        assert!(queued.oldest_seconds.is_some());
+        let transcribing = snapshot.stages.iter().find(|r| r.stage == JobStage::Transcribing).unwrap();
+        assert_eq!(transcribing.oldest_seconds, None);
+
+        // Nothing is in progress, so there is nothing to time the ETA with
+        assert_eq!(snapshot.remaining.total, 2);
+        assert_eq!(snapshot.eta, None);
+
+        assert_eq!(snapshot.failures.len(), 1);
+        assert_eq!(snapshot.failures[0].episode, 3);
+        assert_eq!(snapshot.failures[0].error, "ani-cli exited with status 1");
+
+        // Failing the job counted as its one retry, which is all the workers allow
+        assert_eq!((snapshot.retries.total_retries, snapshot.retries.jobs_at_max_retries), (1, 1));
+
+        let space = snapshot.space.expect("disk breakdown");
+        assert_eq!(space.usage.videos_bytes, 1000);
+
+        // Without a disk monitor the snapshot still builds
+        let snapshot = build_snapshot(&queue, None, 10, &[], None)?;
+        assert!(snapshot.space.is_none());
+        assert_eq!(snapshot.retries.jobs_at_max_retries, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! Dashboard rendering.
+//!
+//! Draws a `Snapshot` with ratatui. No data is fetched here.
+
+use crate::snapshot::Snapshot;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::Frame;
+use shared::JobStage;
+
+/// Width (in cells) of the per-stage bars
+const BAR_WIDTH: usize = 30;
+
+/// Dashboard state kept between frames
+#[derive(Default)]
+pub struct Dashboard {
+    /// Latest snapshot (None until the first refresh succeeds)
+    pub snapshot: Option<Snapshot>,
+    /// Error from the last refresh, shown in the header
+    pub error: Option<String>,
+    /// Selection/scroll position of the failures pane
+    pub failures: ListState,
+}
+
+impl Dashboard {
+    /// Replace the snapshot, keeping the failures selection in range
+    pub fn update(&mut self, snapshot: Snapshot) {
+        let len = snapshot.failures.len();
+        match self.failures.selected() {
+            Some(_) if len == 0 => self.failures.select(None),
+            Some(i) if i >= len => self.failures.select(Some(len - 1)),
+            None if len > 0 => self.failures.select(Some(0)),
+            _ => {}
+        }
+        self.snapshot = Some(snapshot);
+        self.error = None;
+    }
+
+    /// Move the failures selection down
+    pub fn scroll_down(&mut self) {
+        let len = self.snapshot.as_ref().map_or(0, |s| s.failures.len());
+        if len > 0 {
+            let next = self.failures.selected().map_or(0, |i| (i + 1).min(len - 1));
+            self.failures.select(Some(next));
+        }
+    }
+
+    /// Move the failures selection up
+    pub fn scroll_up(&mut self) {
+        if let Some(i) = self.failures.selected() {
+            self.failures.select(Some(i.saturating_sub(1)));
+        }
+    }
+}
+
+/// Draw the whole dashboard
+pub fn render(frame: &mut Frame, dashboard: &mut Dashboard) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(JobStage::all().len() as u16 + 3),
+            Constraint::Min(5),
+        ])
+        .split(frame.area());
+
+    render_header(frame, rows[0], dashboard);
+
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+        .split(rows[1]);
+
+    render_stages(frame, middle[0], dashboard.snapshot.as_ref());
+    render_disk(frame, middle[1], dashboard.snapshot.as_ref());
+    render_failures(frame, rows[2], dashboard);
+}
+
+fn render_header(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let block = Block::default().borders(Borders::ALL).title(" GDA2025 pipeline (q: quit, ↑/↓: scroll failures) ");
+
+    if let Some(error) = &dashboard.error {
+        let text = Paragraph::new(Span::styled(format!("Refresh failed: {}", error), Style::default().fg(Color::Red)))
+            .block(block);
+        frame.render_widget(text, area);
+        return;
+    }
+
+    match &dashboard.snapshot {
+        Some(snapshot) => {
+            let gauge = Gauge::default()
+                .block(block)
+                .gauge_style(Style::default().fg(Color::Green))
+                .ratio(snapshot.completion_ratio().clamp(0.0, 1.0))
+                .label(format!(
+                    "{} / {} complete, {} failed, {} left{} (updated {})",
+                    snapshot.stats.complete,
+                    snapshot.stats.total,
+                    snapshot.stats.failed,
+                    snapshot.remaining.total,
+                    match snapshot.eta {
+                        Some(eta) if snapshot.remaining.total > 0 => format!(", ETA ~{}", format_duration(eta.as_secs_f64())),
+                        _ => String::new(),
+                    },
+                    snapshot.taken_at.with_timezone(&chrono::Local).format("%H:%M:%S"),
+                ));
+            frame.render_widget(gauge, area);
+        }
+        None => frame.render_widget(Paragraph::new("Loading...").block(block), area),
+    }
+}
+
+fn render_stages(frame: &mut Frame, area: Rect, snapshot: Option<&Snapshot>) {
+    let block = Block::default().borders(Borders::ALL).title(" Stages ");
+    let Some(snapshot) = snapshot else {
+        frame.render_widget(block, area);
+        return;
+    };
+
+    let max = snapshot.stages.iter().map(|r| r.jobs).max().unwrap_or(0).max(1);
+
+    let rows = snapshot.stages.iter().map(|row| {
+        let filled = (row.jobs * BAR_WIDTH).div_ceil(max);
+        let color = match row.stage {
+            JobStage::Failed => Color::Red,
+            JobStage::Complete => Color::Green,
+            _ => Color::Cyan,
+        };
+
+        Row::new(vec![
+            Span::raw(row.stage.to_string()),
+            Span::raw(row.jobs.to_string()),
+            Span::styled("█".repeat(filled), Style::default().fg(color)),
+            Span::raw(if row.jobs > 0 { format_duration(row.avg_seconds_in_stage) } else { String::new() }),
+            Span::raw(row.oldest_seconds.map(format_duration).unwrap_or_default()),
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(13),
+            Constraint::Length(8),
+            Constraint::Length(BAR_WIDTH as u16 + 1),
+            Constraint::Length(8),
+            Constraint::Min(8),
+        ],
+    )
+    .header(
+        Row::new(vec!["Stage", "Jobs", "", "Avg age", "Oldest"]).style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(block);
+
+    frame.render_widget(table, area);
+}
+
+fn render_disk(frame: &mut Frame, area: Rect, snapshot: Option<&Snapshot>) {
+    let block = Block::default().borders(Borders::ALL).title(" Disk ");
+
+    let lines = match snapshot.and_then(|s| s.space.as_ref()) {
+        Some(space) => {
+            let usage = &space.usage;
+            let status = if space.can_download {
+                Span::styled("downloads running", Style::default().fg(Color::Green))
+            } else {
+                Span::styled("downloads paused", Style::default().fg(Color::Yellow))
+            };
+
+            vec![
+                Line::from(format!("Total:       {} ({:.1}%)", format_bytes(usage.total_bytes), space.percentage)),
+                Line::from(format!("Available:   {}", format_bytes(space.available_bytes))),
+                Line::from(""),
+                Line::from(format!("Videos:      {}", format_bytes(usage.videos_bytes))),
+                Line::from(format!("Audio:       {}", format_bytes(usage.audio_bytes))),
+                Line::from(format!("Transcripts: {}", format_bytes(usage.transcripts_bytes))),
+                Line::from(format!("Tokens:      {}", format_bytes(usage.tokens_bytes))),
+                Line::from(format!("Cache:       {}", format_bytes(usage.cache_bytes))),
+                Line::from(format!("Database:    {}", format_bytes(usage.db_bytes))),
+                Line::from(""),
+                Line::from(status),
+            ]
+        }
+        None => vec![Line::from("Disk usage unavailable")],
+    };
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn render_failures(frame: &mut Frame, area: Rect, dashboard: &mut Dashboard) {
+    let failures = dashboard.snapshot.as_ref().map(|s| s.failures.as_slice()).unwrap_or_default();
+    let mut title = format!(" Recent failures ({}) ", failures.len());
+    if let Some(retries) = dashboard.snapshot.as_ref().map(|s| &s.retries).filter(|r| r.total_retries > 0) {
+        title.push_str(&format!(
+            "· {} retries by {} jobs, {} at the limit{} ",
+            retries.total_retries,
+            retries.jobs_retried,
+            retries.jobs_at_max_retries,
+            match retries.top_anime.first() {
+                Some(anime) => format!(", most on {} ({})", anime.title, anime.retries),
+                None => String::new(),
+            },
+        ));
+    }
+    let block = Block::default().borders(Borders::ALL).title(title);
+
+    let items: Vec<ListItem> = failures
+        .iter()
+        .map(|job| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    job.failed_at.with_timezone(&chrono::Local).format("%m-%d %H:%M ").to_string(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(format!("#{} {} ep{} (MAL {}): ", job.id, job.anime_title, job.episode, job.mal_id)),
+                Span::styled(job.error.clone(), Style::default().fg(Color::Red)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    frame.render_stateful_widget(list, area, &mut dashboard.failures);
+}
+
+/// Format a byte count in decimal units (matching the GB limits in config)
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Format seconds as a short human-readable duration
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+        s => format!("{}d{:02}h", s / 86400, (s % 86400) / 3600),
+    }
+}
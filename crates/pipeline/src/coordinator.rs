@@ -0,0 +1,678 @@
+//! Download and transcription workers in one process.
+//!
+//! Run as separate processes, the downloader and transcriber each keep their
+//! own `DiskMonitor` cache, so a paused download only notices the space a
+//! transcription freed after its next full rescan, and both race on the job
+//! database. The coordinator runs both worker pools as tasks sharing one
+//! `JobQueue` and one `DiskMonitor`: every file a transcriber deletes is
+//! subtracted from the usage the paused downloaders are polling.
+//!
+//! Optionally it also tokenizes the transcripts as they come in and, once
+//! everything is tokenized, analyzes each anime. Every stage draws from the
+//! same `StageLimits`.
+
+use anime_downloader::AnimeDownloader;
+use anyhow::{Context, Result};
+use shared::analysis::{analyze_anime, delete_token_lists};
+use shared::{
+    AnalysisConfig, CleanupConfig, Config, DataPaths, Database, DiskMonitor, JobQueue, JobRetryPolicy, JobStage,
+    StageLimits, TokenizerConfig,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokenizer::{RecapDetector, TokenFilter, TokenizeStats};
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+use tokio::time::sleep;
+use tracing::{error, info};
+use transcriber::Transcriber;
+
+/// Download and transcription worker pools sharing one queue and disk monitor
+pub struct Coordinator {
+    downloaders: Vec<AnimeDownloader>,
+    transcribers: Vec<Transcriber>,
+    tokenizers: Vec<TokenizeWorker>,
+    analysis: Option<Analysis>,
+    stage_limits: StageLimits,
+    /// How long an idle transcriber or tokenizer waits before looking for
+    /// new work
+    poll: Duration,
+}
+
+/// What happened during a coordinated run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoordinatorReport {
+    /// Times a download worker paused for disk space
+    pub download_pauses: usize,
+    /// Times a transcription worker was (re)started
+    pub transcriber_runs: usize,
+    /// Episodes tokenized by the tokenize workers
+    pub episodes_tokenized: usize,
+    /// Anime analyzed (see `Coordinator::with_analysis`)
+    pub anime_analyzed: usize,
+    /// Workers (or anime analyses) that stopped with an error
+    pub failed_workers: usize,
+}
+
+/// Workers started by `Coordinator::from_config`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerCounts {
+    pub download: usize,
+    pub transcribe: usize,
+    pub tokenize: usize,
+}
+
+/// Tokenizes transcribed episodes, one per `Tokenizing` slot.
+///
+/// Tokenizers are not `Send` (a MeCab dictionary is loaded per tokenizer),
+/// so each worker builds its own on a blocking thread and works through its
+/// own database connection.
+pub struct TokenizeWorker {
+    worker_id: usize,
+    db_path: PathBuf,
+    data_paths: DataPaths,
+    config: TokenizerConfig,
+    cleanup: CleanupConfig,
+    stage_limits: StageLimits,
+}
+
+impl TokenizeWorker {
+    pub fn new(
+        worker_id: usize,
+        db_path: impl Into<PathBuf>,
+        data_paths: DataPaths,
+        config: TokenizerConfig,
+        cleanup: CleanupConfig,
+    ) -> Self {
+        Self {
+            worker_id,
+            db_path: db_path.into(),
+            data_paths,
+            config,
+            cleanup,
+            stage_limits: StageLimits::unlimited(),
+        }
+    }
+
+    /// Share `limits` with the other workers
+    pub fn with_stage_limits(mut self, limits: StageLimits) -> Self {
+        self.stage_limits = limits;
+        self
+    }
+
+    /// Tokenize until nothing is left, then look again every `poll` until
+    /// `done` says transcription has finished (blocks; run it with
+    /// `spawn_blocking`)
+    fn run(self, handle: Handle, mut done: watch::Receiver<bool>, poll: Duration) -> Result<TokenizeStats> {
+        let tokenizer = tokenizer::from_config(&self.config).context("Failed to create tokenizer")?;
+        let filter = TokenFilter::from_config(&self.config.filter).context("Failed to create token filter")?;
+        let recap = RecapDetector::from_config(&self.config.recap);
+        let mut queue = JobQueue::new(Database::open(&self.db_path)?);
+
+        let mut stats = TokenizeStats::default();
+        loop {
+            let final_run = *done.borrow();
+            loop {
+                let _permit = handle.block_on(self.stage_limits.acquire(JobStage::Tokenizing));
+                let one = tokenizer::tokenize_pending(
+                    &mut queue,
+                    &self.data_paths,
+                    tokenizer.as_ref(),
+                    &filter,
+                    &recap,
+                    &self.cleanup,
+                    Some(1),
+                )?;
+                if one.tokenized + one.failed == 0 {
+                    break;
+                }
+                stats.tokenized += one.tokenized;
+                stats.failed += one.failed;
+            }
+            if final_run {
+                return Ok(stats);
+            }
+            handle.block_on(async {
+                tokio::select! {
+                    _ = sleep(poll) => {}
+                    _ = done.wait_for(|done| *done) => {}
+                }
+            });
+        }
+    }
+}
+
+/// Per-anime analysis once every episode has been tokenized
+struct Analysis {
+    db_path: PathBuf,
+    data_paths: DataPaths,
+    config: AnalysisConfig,
+    delete_tokens: bool,
+}
+
+impl Analysis {
+    /// Analyze `mal_id` on its own database connection; true if it had
+    /// enough words for a fit
+    fn analyze(&self, mal_id: u32) -> Result<bool> {
+        let mut queue = JobQueue::new(Database::open(&self.db_path)?);
+        if analyze_anime(mal_id, &self.data_paths, &mut queue, &self.config)?.is_none() {
+            return Ok(false);
+        }
+        if self.delete_tokens {
+            delete_token_lists(mal_id, &self.data_paths, &mut queue)?;
+        }
+        Ok(true)
+    }
+}
+
+impl Coordinator {
+    /// Create a coordinator for already configured workers.
+    ///
+    /// The workers should share one `Arc<Mutex<JobQueue>>` and clones of
+    /// one `DiskMonitor`.
+    pub fn new(downloaders: Vec<AnimeDownloader>, transcribers: Vec<Transcriber>, poll: Duration) -> Self {
+        Self {
+            downloaders,
+            transcribers,
+            tokenizers: Vec::new(),
+            analysis: None,
+            stage_limits: StageLimits::unlimited(),
+            poll,
+        }
+    }
+
+    /// Build `download_workers` downloaders, `transcribe_workers`
+    /// transcribers and `tokenize_workers` tokenizers from the config.
+    ///
+    /// All workers share the limits of `Config::stage_concurrency`, so e.g.
+    /// eight transcribers with `transcribing = 2` still run two
+    /// transcriptions at most.
+    pub fn from_config(
+        config: &Config,
+        workers: WorkerCounts,
+        model: &str,
+        dry_run: bool,
+    ) -> Result<Self> {
+        let data_paths = config.data_paths();
+        data_paths
+            .create_dirs()
+            .context("Failed to create data directories")?;
+
+        let db_path = config.database_path();
+        let database = Database::open(&db_path)
+            .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+        let queue = Arc::new(Mutex::new(
+            JobQueue::new(database)
+                .with_dequeue_order(config.pipeline.dequeue_order)
+                .with_retry_policy(config.pipeline.retry)
+                .with_anime_filter(config.pipeline.anime_filter.clone()),
+        ));
+        if config.pipeline.anime_filter.is_active() {
+            let matching = queue.lock().unwrap().find_anime(&config.pipeline.anime_filter)?.len();
+            info!(filter = ?config.pipeline.anime_filter, matching_anime = matching, "Only processing anime matching the filter");
+        }
+
+        let disk = &config.disk_management;
+        let disk_monitor = DiskMonitor::new(
+            config.data_dir(),
+            config.storage_dir(),
+            disk.hard_limit_gb,
+            disk.pause_threshold_gb,
+            disk.resume_threshold_gb,
+            Duration::from_secs(disk.cache_duration_seconds),
+        )
+        .context("Failed to initialize disk monitor")?
+        .with_layout(config.path_layout())
+        .with_reconcile_interval(disk.reconcile_interval_seconds.map(Duration::from_secs));
+        // `kill -USR1 <pid>` rescans the disk after out-of-band changes
+        disk_monitor.refresh_on_sigusr1()?;
+
+        let budget = shared::RunBudget::unlimited();
+        let stage_limits = StageLimits::from_config(&config.stage_concurrency());
+        info!(
+            downloading = ?stage_limits.limit(JobStage::Downloading),
+            transcribing = ?stage_limits.limit(JobStage::Transcribing),
+            tokenizing = ?stage_limits.limit(JobStage::Tokenizing),
+            analyzing = ?stage_limits.limit(JobStage::Analyzing),
+            "Stage concurrency limits"
+        );
+        let retry_policy = JobRetryPolicy::new(true, config.pipeline.retry.map(|retry| retry.max_retries()));
+        let downloaders = (0..workers.download)
+            .map(|worker_id| {
+                AnimeDownloader::new(
+                    worker_id,
+                    Arc::clone(&queue),
+                    disk_monitor.clone(),
+                    data_paths.clone(),
+                    dry_run,
+                    None,
+                    budget.clone(),
+                    config.downloader.min_download_confidence,
+                )
+                .with_stage_limits(stage_limits.clone())
+                .with_retry_policy(retry_policy)
+                .with_estimated_bitrate(config.downloader.estimated_bitrate_kbps)
+                .with_process_timeout(config.downloader.process_timeout())
+                .with_space_check_interval(Duration::from_secs(disk.check_interval_seconds))
+            })
+            .collect();
+        let transcribers = (0..workers.transcribe)
+            .map(|worker_id| {
+                Transcriber::new(
+                    worker_id,
+                    Arc::clone(&queue),
+                    disk_monitor.clone(),
+                    data_paths.clone(),
+                    model.to_string(),
+                    disk.cleanup.clone(),
+                    dry_run,
+                    budget.clone(),
+                )
+                .with_stage_limits(stage_limits.clone())
+                .with_retry_policy(retry_policy)
+                .with_audio_format(config.transcriber.audio.clone())
+                .with_temp_dir(config.transcriber_temp_dir())
+                .with_transcript_formats(config.transcriber.transcript_formats.clone())
+                .with_chunk_seconds(config.transcriber.chunk_seconds)
+                .with_process_timeout(config.transcriber.process_timeout())
+                .with_initial_prompt(config.transcriber.initial_prompt.clone())
+            })
+            .collect();
+        if workers.tokenize > 0 {
+            // Fail now rather than in every worker
+            tokenizer::from_config(&config.tokenizer).context("Failed to create tokenizer")?;
+        }
+        let tokenizers = (0..workers.tokenize)
+            .map(|worker_id| {
+                TokenizeWorker::new(
+                    worker_id,
+                    &db_path,
+                    data_paths.clone(),
+                    config.tokenizer.clone(),
+                    disk.cleanup.clone(),
+                )
+                .with_stage_limits(stage_limits.clone())
+            })
+            .collect();
+
+        Ok(Self::new(
+            downloaders,
+            transcribers,
+            Duration::from_secs(config.pipeline.transcriber_poll_seconds.max(1)),
+        )
+        .with_tokenizers(tokenizers)
+        .with_stage_limits(stage_limits))
+    }
+
+    /// Tokenize transcripts with `tokenizers` while transcription runs
+    pub fn with_tokenizers(mut self, tokenizers: Vec<TokenizeWorker>) -> Self {
+        self.tokenizers = tokenizers;
+        self
+    }
+
+    /// Share `limits` with the analysis (the workers get theirs when built)
+    pub fn with_stage_limits(mut self, limits: StageLimits) -> Self {
+        self.stage_limits = limits;
+        self
+    }
+
+    /// Analyze every anime with token lists once tokenization has finished,
+    /// as many at once as the `Analyzing` limit allows
+    pub fn with_analysis(mut self, config: &Config) -> Self {
+        self.analysis = Some(Analysis {
+            db_path: config.database_path(),
+            data_paths: config.data_paths(),
+            config: config.analysis.clone(),
+            delete_tokens: config.disk_management.cleanup.delete_tokens_after_analysis,
+        });
+        self
+    }
+
+    /// Record `version` as the whisper version of every job transcribed
+    pub fn with_whisper_version(mut self, version: Option<String>) -> Self {
+        self.transcribers = self
+            .transcribers
+            .into_iter()
+            .map(|transcriber| transcriber.with_whisper_version(version.clone()))
+            .collect();
+        self
+    }
+
+    /// Run until the download queue is drained and every downloaded episode
+    /// has been transcribed (and tokenized and analyzed, if set up).
+    ///
+    /// Transcribers stop when they find nothing to do, so while downloads are
+    /// running each one is restarted every `poll`, and once more after the
+    /// last download worker has finished. Tokenizers wait for the
+    /// transcribers the same way.
+    pub async fn run(self) -> CoordinatorReport {
+        info!(
+            download_workers = self.downloaders.len(),
+            transcribe_workers = self.transcribers.len(),
+            tokenize_workers = self.tokenizers.len(),
+            analysis = self.analysis.is_some(),
+            "Starting coordinated workers"
+        );
+
+        let (downloads_done, done_rx) = watch::channel(false);
+        let (transcriptions_done, transcribed_rx) = watch::channel(false);
+        let poll = self.poll;
+
+        let download_tasks: Vec<_> = self
+            .downloaders
+            .into_iter()
+            .map(|mut downloader| {
+                tokio::spawn(async move {
+                    let result = downloader.run().await;
+                    (downloader, result)
+                })
+            })
+            .collect();
+
+        let transcribe_tasks: Vec<_> = self
+            .transcribers
+            .into_iter()
+            .map(|mut transcriber| {
+                let mut done_rx = done_rx.clone();
+                tokio::spawn(async move {
+                    let mut runs = 0;
+                    let result = loop {
+                        let final_run = *done_rx.borrow();
+                        runs += 1;
+                        if let Err(e) = transcriber.run().await {
+                            break Err(e);
+                        }
+                        if final_run {
+                            break Ok(());
+                        }
+                        tokio::select! {
+                            _ = sleep(poll) => {}
+                            _ = done_rx.wait_for(|done| *done) => {}
+                        }
+                    };
+                    (transcriber.worker_id(), runs, result)
+                })
+            })
+            .collect();
+
+        let tokenize_tasks: Vec<_> = self
+            .tokenizers
+            .into_iter()
+            .map(|tokenizer| {
+                let (handle, transcribed_rx) = (Handle::current(), transcribed_rx.clone());
+                let worker_id = tokenizer.worker_id;
+                let task = tokio::task::spawn_blocking(move || tokenizer.run(handle, transcribed_rx, poll));
+                (worker_id, task)
+            })
+            .collect();
+
+        let mut report = CoordinatorReport::default();
+
+        for task in download_tasks {
+            match task.await {
+                Ok((downloader, result)) => {
+                    report.download_pauses += downloader.pauses();
+                    if let Err(e) = result {
+                        error!(worker_id = downloader.worker_id(), error = %e, "Download worker failed");
+                        report.failed_workers += 1;
+                    }
+                }
+                Err(e) => {
+                    error!(error = %e, "Download worker panicked");
+                    report.failed_workers += 1;
+                }
+            }
+        }
+        info!("All download workers finished");
+        let _ = downloads_done.send(true);
+
+        for task in transcribe_tasks {
+            match task.await {
+                Ok((worker_id, runs, result)) => {
+                    report.transcriber_runs += runs;
+                    if let Err(e) = result {
+                        error!(worker_id, error = %e, "Transcription worker failed");
+                        report.failed_workers += 1;
+                    }
+                }
+                Err(e) => {
+                    error!(error = %e, "Transcription worker panicked");
+                    report.failed_workers += 1;
+                }
+            }
+        }
+        info!("All transcription workers finished");
+        let _ = transcriptions_done.send(true);
+
+        for (worker_id, task) in tokenize_tasks {
+            match task.await {
+                Ok(Ok(stats)) => report.episodes_tokenized += stats.tokenized,
+                Ok(Err(e)) => {
+                    error!(worker_id, error = %e, "Tokenize worker failed");
+                    report.failed_workers += 1;
+                }
+                Err(e) => {
+                    error!(worker_id, error = %e, "Tokenize worker panicked");
+                    report.failed_workers += 1;
+                }
+            }
+        }
+
+        if let Some(analysis) = self.analysis {
+            analyze_all(Arc::new(analysis), &self.stage_limits, &mut report).await;
+        }
+
+        report
+    }
+}
+
+/// Analyze every anime with token lists, each as soon as it gets an
+/// `Analyzing` slot
+async fn analyze_all(analysis: Arc<Analysis>, limits: &StageLimits, report: &mut CoordinatorReport) {
+    let anime = match Database::open(&analysis.db_path).and_then(|db| JobQueue::new(db).get_anime_classifications()) {
+        Ok(anime) => anime,
+        Err(e) => {
+            error!(error = %e, "Failed to list anime for analysis");
+            report.failed_workers += 1;
+            return;
+        }
+    };
+
+    let tasks: Vec<_> = anime
+        .into_iter()
+        .map(|anime| anime.mal_id)
+        .filter(|&mal_id| analysis.data_paths.tokens_dir(mal_id).exists())
+        .map(|mal_id| {
+            let (analysis, limits) = (Arc::clone(&analysis), limits.clone());
+            let task = tokio::spawn(async move {
+                let _permit = limits.acquire(JobStage::Analyzing).await;
+                tokio::task::spawn_blocking(move || analysis.analyze(mal_id)).await
+            });
+            (mal_id, task)
+        })
+        .collect();
+
+    for (mal_id, task) in tasks {
+        match task.await {
+            Ok(Ok(Ok(analyzed))) => report.anime_analyzed += usize::from(analyzed),
+            Ok(Ok(Err(e))) => {
+                error!(mal_id, error = %e, "Analysis failed");
+                report.failed_workers += 1;
+            }
+            Ok(Err(e)) | Err(e) => {
+                error!(mal_id, error = %e, "Analysis panicked");
+                report.failed_workers += 1;
+            }
+        }
+    }
+    info!("Analyzed {} anime", report.anime_analyzed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{Anime, Confidence, DiskCategory, NewJob, RunBudget, TokenizerBackend};
+    use tempfile::TempDir;
+
+    /// The disk monitor only tracks the 1.5 GB of made-up video usage (above
+    /// the 1 GB pause threshold) of an already downloaded episode.
+    /// Transcribing that episode deletes its video, which is the only way the
+    /// downloader can get below the resume threshold.
+    ///
+    /// The runtime's clock is paused, so the downloader's space checks and
+    /// the coordinator's polls advance it instead of waiting. The downloader
+    /// is spawned first and checks the disk before it first yields, so on
+    /// this single-threaded runtime it pauses before the transcriber starts.
+    #[tokio::test(start_paused = true)]
+    async fn test_downloads_pause_until_transcription_frees_space() -> Result<()> {
+        const VIDEO_BYTES: u64 = 1_500_000_000;
+
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        data_paths.create_dirs()?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut queue = JobQueue::new(Database::open(&db_path)?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 2);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        queue.cache_selection(5114, &anime.title, &anime.title, 1, &anime.title, Confidence::High, None, Some(2), Some(2), None)?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=2 {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?);
+        }
+
+        // Episode 1 is already downloaded; its video is what fills the disk
+        let video_path = data_paths.video_file(5114, &anime.title, 1, "mkv");
+        std::fs::create_dir_all(video_path.parent().unwrap())?;
+        std::fs::write(&video_path, b"")?;
+        queue.update_job_with_video(job_ids[0], video_path, VIDEO_BYTES)?;
+        queue.update_stage(job_ids[0], JobStage::Downloaded)?;
+
+        // Transcripts that pass the quality check, so the transcriber deletes
+        // the videos (the dry run transcript would keep them for a redo)
+        std::fs::create_dir_all(data_paths.transcript_dir(5114))?;
+        for episode in 1..=2 {
+            std::fs::write(data_paths.transcript_txt(5114, &anime.title, episode), "今日はいい天気ですね。\n")?;
+        }
+
+        let disk_monitor = DiskMonitor::tracking_only(2, 1, 1)?;
+        disk_monitor.add_bytes(DiskCategory::Videos, VIDEO_BYTES);
+        assert!(disk_monitor.should_pause_downloads()?);
+
+        let queue = Arc::new(Mutex::new(queue));
+        let downloader = AnimeDownloader::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor.clone(),
+            data_paths.clone(),
+            true,
+            None,
+            RunBudget::unlimited(),
+            Confidence::Low,
+        )
+        .with_space_check_interval(Duration::from_secs(1));
+        let transcriber = Transcriber::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor.clone(),
+            data_paths.clone(),
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            RunBudget::unlimited(),
+        );
+
+        let report = Coordinator::new(vec![downloader], vec![transcriber], Duration::from_secs(5))
+            .run()
+            .await;
+
+        // The download paused, resumed once the transcriber deleted the
+        // video, and the new episode was transcribed in the final pass
+        assert_eq!(report.download_pauses, 1);
+        assert_eq!(report.failed_workers, 0);
+        assert!(report.transcriber_runs >= 2, "{:?}", report);
+        assert_eq!(disk_monitor.current_usage()?.videos_bytes, 0);
+
+        let queue = queue.lock().unwrap();
+        let transcribed = queue.get_jobs_by_stage(JobStage::Transcribed)?;
+        assert_eq!(transcribed.len(), 2);
+        assert!(transcribed.iter().all(|job| job.video_deleted));
+
+        Ok(())
+    }
+
+    /// Jobs already transcribed go through the tokenize workers and the
+    /// analysis, under the limits `[pipeline.concurrency]` and
+    /// `[disk_management]` set together.
+    #[tokio::test]
+    async fn test_from_config_limits_every_stage_and_tokenizes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut config = Config::default();
+        config.data.root_dir = temp_dir.path().to_string_lossy().to_string();
+        config.disk_management.max_concurrent_downloads = 3;
+        config.pipeline.concurrency.transcribing = Some(1);
+        config.pipeline.concurrency.tokenizing = Some(2);
+        config.pipeline.concurrency.analyzing = Some(1);
+        config.tokenizer.backend = TokenizerBackend::Whitespace;
+
+        let data_paths = config.data_paths();
+        data_paths.create_dirs()?;
+        let mut queue = JobQueue::new(Database::open(config.database_path())?);
+        let mut episodes = 0;
+        for (mal_id, title) in [(5114, "Test Anime"), (9253, "Other Anime")] {
+            let anime = Anime::test_fixture(mal_id, title, 2);
+            let anime_id = queue.get_or_create_anime(&anime)?;
+            std::fs::create_dir_all(data_paths.transcript_dir(mal_id))?;
+            for episode in 1..=2 {
+                let job_id = queue.enqueue(&NewJob {
+                    anime_id,
+                    mal_id,
+                    anime_title: anime.title.clone(),
+                    episode,
+                    season: None,
+                    year: None,
+                    priority: 0,
+                })?;
+                queue.update_stage(job_id, JobStage::Transcribed)?;
+                std::fs::write(data_paths.transcript_txt(mal_id, title, episode), "a b c a b a\nd a b\n")?;
+                episodes += 1;
+            }
+        }
+        drop(queue);
+
+        let workers = WorkerCounts {
+            download: 1,
+            transcribe: 1,
+            tokenize: 3,
+        };
+        let coordinator = Coordinator::from_config(&config, workers, "base", true)?.with_analysis(&config);
+
+        // Downloads fall back to max_concurrent_downloads
+        let limits = &coordinator.stage_limits;
+        assert_eq!(limits.limit(JobStage::Downloading), Some(3));
+        assert_eq!(limits.limit(JobStage::Transcribing), Some(1));
+        assert_eq!(limits.limit(JobStage::Tokenizing), Some(2));
+        assert_eq!(limits.limit(JobStage::Analyzing), Some(1));
+        assert!(coordinator.tokenizers.iter().all(|t| t.stage_limits.limit(JobStage::Tokenizing) == Some(2)));
+
+        let report = coordinator.run().await;
+        assert_eq!(report.failed_workers, 0, "{:?}", report);
+        assert_eq!(report.episodes_tokenized, episodes);
+        assert_eq!(report.anime_analyzed, 2);
+
+        let queue = JobQueue::new(Database::open(config.database_path())?);
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Tokenized)?.len(), episodes);
+        assert!(data_paths.word_freq_csv(9253).is_file());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+//! `pipeline doctor`: everything a full run needs, checked in one go.
+//!
+//! Runs the workers' pre-flight checks (config, database, data directories)
+//! read-only, so nothing is created or migrated, and checks every external
+//! tool of every stage: that it is installed,
+//! runs, and is not older than the oldest version known to work. Each
+//! failure comes with a hint on how to fix it. Missing tools fail the run;
+//! checks that only matter for some setups are reported as warnings.
+
+use shared::manifest::{capture_tool_version, ToolVersion};
+use shared::preflight::{self, ExternalTool};
+use shared::{Config, TokenizerBackend};
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+
+/// An external tool a stage needs, with what to do when it is missing
+#[derive(Debug, Clone, Copy)]
+pub struct ToolRequirement {
+    pub tool: ExternalTool,
+    /// Stage that shells out to the tool
+    pub needed_by: &'static str,
+    /// Oldest version known to work (None = any)
+    pub min_version: Option<&'static str>,
+    pub hint: &'static str,
+}
+
+/// Installation hint and minimum version of each known tool, by name
+fn requirement(tool: ExternalTool, needed_by: &'static str) -> ToolRequirement {
+    let (min_version, hint) = match tool.name {
+        "ffmpeg" => (Some("4.0"), "Install ffmpeg 4.0 or newer (e.g. `sudo apt install ffmpeg` or `brew install ffmpeg`)"),
+        "whisper" => (None, "Install openai-whisper into the active environment: `pip install -U openai-whisper`"),
+        "ani-cli" => (Some("4.0"), "Install ani-cli 4.0 or newer: https://github.com/pystardust/ani-cli#install"),
+        "aria2c" => (None, "Install aria2 (e.g. `sudo apt install aria2` or `brew install aria2`)"),
+        _ => (None, "Install it and make sure it is on PATH"),
+    };
+    ToolRequirement {
+        tool,
+        needed_by,
+        min_version,
+        hint,
+    }
+}
+
+/// Every external tool of the download and transcription stages
+pub fn requirements() -> Vec<ToolRequirement> {
+    let download = anime_downloader::EXTERNAL_TOOLS.iter().map(|&tool| requirement(tool, "download"));
+    let transcribe = transcriber::EXTERNAL_TOOLS.iter().map(|&tool| requirement(tool, "transcribe"));
+    download.chain(transcribe).collect()
+}
+
+/// Finds and runs external tools; stubbed in tests
+pub trait CommandRunner {
+    /// Locate `tool` and capture its version
+    fn capture(&self, tool: &ExternalTool) -> ToolVersion;
+}
+
+/// Runs tools from `PATH`
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn capture(&self, tool: &ExternalTool) -> ToolVersion {
+        capture_tool_version(tool, &std::env::var_os("PATH").unwrap_or_default())
+    }
+}
+
+/// Check that the tool of `requirement` is installed, runs and is recent
+/// enough.
+///
+/// A version that cannot be compared (e.g. a git build of ffmpeg) passes,
+/// noting that it was not checked.
+pub fn check_tool(runner: &dyn CommandRunner, requirement: &ToolRequirement) -> Result<String> {
+    let captured = runner.capture(&requirement.tool);
+    if let Some(error) = captured.error {
+        anyhow::bail!(error);
+    }
+
+    let version = captured.version.or(captured.output).unwrap_or_else(|| "unknown version".to_string());
+    let location = captured.path.map(|p| p.display().to_string()).unwrap_or_default();
+
+    let Some(min_version) = requirement.min_version else {
+        return Ok(format!("{} ({})", version, location));
+    };
+    match compare_versions(&version, min_version) {
+        Some(Ordering::Less) => anyhow::bail!("version {} is older than the required {}", version, min_version),
+        Some(_) => Ok(format!("{} ({})", version, location)),
+        None => Ok(format!("{} ({}; version not checked)", version, location)),
+    }
+}
+
+/// Compare the leading `major.minor.patch` numbers of two versions, missing
+/// components counting as 0 (None if either has no leading number)
+fn compare_versions(version: &str, other: &str) -> Option<Ordering> {
+    fn numbers(version: &str) -> Option<Vec<u64>> {
+        let prefix: String = version.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+        let numbers: Vec<u64> = prefix.split('.').map_while(|part| part.parse().ok()).collect();
+        (!numbers.is_empty()).then_some(numbers)
+    }
+
+    let (a, b) = (numbers(version)?, numbers(other)?);
+    let len = a.len().max(b.len());
+    let component = |numbers: &[u64], i: usize| numbers.get(i).copied().unwrap_or(0);
+    Some((0..len).map(|i| component(&a, i).cmp(&component(&b, i))).find(|o| o.is_ne()).unwrap_or(Ordering::Equal))
+}
+
+/// Outcome of one doctor check
+#[derive(Debug, Clone)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub passed: bool,
+    /// A failed critical check fails the whole run; others are warnings
+    pub critical: bool,
+    /// What was found (on success) or what went wrong (on failure)
+    pub detail: String,
+    /// How to fix a failure
+    pub hint: Option<String>,
+}
+
+/// All check results of a doctor run
+#[derive(Debug, Clone, Default)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    /// Record the outcome of a check, with the hint shown if it failed
+    pub fn add(&mut self, name: impl Into<String>, critical: bool, result: Result<String>, hint: &str) {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(e) => (false, format!("{:#}", e)),
+        };
+        self.checks.push(DoctorCheck {
+            name: name.into(),
+            passed,
+            critical,
+            detail,
+            hint: (!passed).then(|| hint.to_string()),
+        });
+    }
+
+    /// Whether every critical check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed || !c.critical)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match (check.passed, check.critical) {
+                (true, _) => " OK ",
+                (false, true) => "FAIL",
+                (false, false) => "WARN",
+            };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "       -> {}", hint)?;
+            }
+        }
+        let failed = self.checks.iter().filter(|c| !c.passed && c.critical).count();
+        let warnings = self.checks.iter().filter(|c| !c.passed && !c.critical).count();
+        match (failed, warnings) {
+            (0, 0) => write!(f, "All {} checks passed", self.checks.len()),
+            (0, _) => write!(f, "No critical problems, {} warning(s)", warnings),
+            _ => write!(f, "{} critical problem(s), {} warning(s)", failed, warnings),
+        }
+    }
+}
+
+/// Hint for a failed pre-flight check, by check name
+fn preflight_hint(name: &str) -> &'static str {
+    match name {
+        "config" => "Fix the setting named above; `pipeline config validate` checks the file against the schema",
+        "database schema" => "Restore a backup made by this version, or move the database aside so the next run creates a fresh one from schema.sql",
+        _ => "Create the directory or fix its permissions, or point it elsewhere under [data] in config.toml",
+    }
+}
+
+/// Check the tokenizer's MeCab dictionary, if the MeCab backend is selected
+fn check_dictionary(config: &Config) -> Option<Result<String>> {
+    if config.tokenizer.backend != TokenizerBackend::Mecab {
+        return None;
+    }
+    Some(match &config.tokenizer.dictionary {
+        None => Err(anyhow::anyhow!("[tokenizer] dictionary is not set")),
+        Some(dictionary) if !Path::new(dictionary).is_dir() => {
+            Err(anyhow::anyhow!("{} is not a directory", dictionary))
+        }
+        Some(dictionary) => Ok(dictionary.clone()),
+    })
+}
+
+/// Run every check. `config_path` and `profile` are the `--config` and
+/// `--profile` flags, if given.
+pub fn run(config_path: Option<&Path>, profile: Option<&str>, runner: &dyn CommandRunner) -> DoctorReport {
+    let mut report = DoctorReport::default();
+
+    let config = match preflight::check_config(config_path, profile) {
+        Ok((config, detail)) => {
+            report.add("config", true, Ok(detail), preflight_hint("config"));
+            Some(config)
+        }
+        Err(e) => {
+            report.add("config", true, Err(e), preflight_hint("config"));
+            None
+        }
+    };
+    if let Some(config) = &config {
+        for (name, dir) in preflight::directories(config) {
+            report.add(name, true, preflight::check_dir_access(&dir), preflight_hint(name));
+        }
+        let schema = preflight::check_schema_read_only(&config.database_path());
+        report.add("database schema", true, schema, preflight_hint("database schema"));
+    }
+
+    for requirement in requirements() {
+        let name = format!("{} ({})", requirement.tool.name, requirement.needed_by);
+        report.add(name, true, check_tool(runner, &requirement), requirement.hint);
+    }
+
+    // The tokenizer is optional in a run, so its dictionary only warns
+    if let Some(result) = config.as_ref().and_then(check_dictionary) {
+        report.add(
+            "MeCab dictionary (tokenize)",
+            false,
+            result,
+            "Download a compiled IPADIC dictionary and set [tokenizer] dictionary, or use backend = \"whitespace\"",
+        );
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    /// Answers with canned `--version` output per tool; other tools are missing
+    struct StubRunner(HashMap<&'static str, &'static str>);
+
+    impl CommandRunner for StubRunner {
+        fn capture(&self, tool: &ExternalTool) -> ToolVersion {
+            match self.0.get(tool.name) {
+                Some(output) => ToolVersion {
+                    name: tool.name.to_string(),
+                    path: Some(PathBuf::from("/usr/bin").join(tool.name)),
+                    version: shared::manifest::parse_version(output),
+                    output: Some(output.to_string()),
+                    error: None,
+                },
+                None => ToolVersion {
+                    name: tool.name.to_string(),
+                    path: None,
+                    version: None,
+                    output: None,
+                    error: Some(format!("{} not found in PATH", tool.name)),
+                },
+            }
+        }
+    }
+
+    fn requirement_for(name: &str) -> ToolRequirement {
+        requirements().into_iter().find(|r| r.tool.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_check_tool() {
+        let runner = StubRunner(HashMap::from([
+            ("ffmpeg", "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers"),
+            ("ani-cli", "3.4"),
+            ("aria2c", "aria2 version 1.37.0"),
+        ]));
+
+        assert_eq!(
+            check_tool(&runner, &requirement_for("ffmpeg")).unwrap(),
+            "6.1.1-3ubuntu5 (/usr/bin/ffmpeg)"
+        );
+        assert_eq!(check_tool(&runner, &requirement_for("aria2c")).unwrap(), "1.37.0 (/usr/bin/aria2c)");
+
+        let err = check_tool(&runner, &requirement_for("ani-cli")).unwrap_err();
+        assert_eq!(err.to_string(), "version 3.4 is older than the required 4.0");
+
+        let err = check_tool(&runner, &requirement_for("whisper")).unwrap_err();
+        assert_eq!(err.to_string(), "whisper not found in PATH");
+
+        // Git builds have no comparable version
+        let runner = StubRunner(HashMap::from([("ffmpeg", "ffmpeg version N-112345-g6ac9d2b Copyright")]));
+        assert!(check_tool(&runner, &requirement_for("ffmpeg")).unwrap().ends_with("version not checked)"));
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("6.1.1-3ubuntu5", "4.0"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("4", "4.0.0"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("3.10", "4.0"), Some(Ordering::Less));
+        assert_eq!(compare_versions("4.10", "4.9"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("N-112345", "4.0"), None);
+    }
+
+    #[test]
+    fn test_report_fails_only_on_critical_problems() {
+        let mut report = DoctorReport::default();
+        report.add("ffmpeg (transcribe)", true, Ok("6.1.1 (/usr/bin/ffmpeg)".to_string()), "install it");
+        report.add("MeCab dictionary (tokenize)", false, Err(anyhow::anyhow!("not set")), "set it");
+        assert!(report.passed());
+        let text = report.to_string();
+        assert!(text.contains("[ OK ] ffmpeg (transcribe): 6.1.1 (/usr/bin/ffmpeg)\n"));
+        assert!(text.contains("[WARN] MeCab dictionary (tokenize): not set\n       -> set it\n"));
+        assert!(text.ends_with("No critical problems, 1 warning(s)"));
+
+        report.add("whisper (transcribe)", true, Err(anyhow::anyhow!("whisper not found in PATH")), "pip install");
+        assert!(!report.passed());
+        assert!(report.to_string().ends_with("1 critical problem(s), 1 warning(s)"));
+    }
+}
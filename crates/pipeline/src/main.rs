@@ -0,0 +1,445 @@
+//! Pipeline orchestrator - runs every stage with one command.
+//!
+//! Launches mal-scraper, anime-selector, anime-downloader and transcriber in
+//! order with a shared config file. The downloader and transcriber can run
+//! concurrently so transcription frees disk space for further downloads.
+//! Each stage remains independently runnable; this binary only sequences
+//! them and prints a combined summary at the end.
+//!
+//! With `--coordinator`, it instead runs download and transcription workers
+//! (and, with `--tokenize-workers`, tokenization workers) inside this
+//! process, sharing one job queue and disk monitor. This is the intended
+//! steady-state mode once the queue has been filled.
+//!
+//! With `--aggregate`, a successful run finishes by fitting Zipf's law per
+//! anime (recorded on the anime rows) and combining the per-anime word
+//! frequency tables into genre- and studio-level analyses.
+//!
+//! `pipeline config schema` prints a JSON Schema of `config.toml` for editor
+//! autocomplete, and `pipeline config validate` checks a config file against
+//! it without running anything.
+//!
+//! `pipeline doctor` checks the config, database, data directories and every
+//! external tool at once, with a hint for each problem found.
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use shared::preflight::ExternalTool;
+use shared::{parse_year_range, AnimeFilter, AnimeFilterConfig, Config, Database, DequeueOrder, JobQueue, RunManifest, DEFAULT_CONFIG_PATH};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+mod coordinator;
+mod doctor;
+mod process;
+mod stages;
+
+use coordinator::{Coordinator, WorkerCounts};
+use process::ProcessRunner;
+use stages::{plan, plan_from_config, run_plan, Stage, StageOutcome, StageReport};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to configuration file (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Stages to run, comma separated (overrides `pipeline.stages`)
+    #[arg(long, value_delimiter = ',')]
+    stages: Option<Vec<Stage>>,
+
+    /// Run the downloader and transcriber one after the other
+    #[arg(long)]
+    sequential: bool,
+
+    /// Pass --dry-run to the selector, downloader and transcriber
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Download and transcribe one episode of each anime in turn (overrides `pipeline.dequeue_order`)
+    #[arg(long)]
+    breadth_first: bool,
+
+    /// Directory containing the stage binaries (default: next to this binary)
+    #[arg(long)]
+    bin_dir: Option<PathBuf>,
+
+    /// Run download and transcription workers in this process instead of the stages
+    #[arg(long, conflicts_with_all = ["stages", "sequential", "bin_dir"])]
+    coordinator: bool,
+
+    /// Number of download workers in coordinator mode
+    #[arg(long, requires = "coordinator")]
+    download_workers: Option<usize>,
+
+    /// Number of transcription workers in coordinator mode
+    #[arg(long, requires = "coordinator")]
+    transcribe_workers: Option<usize>,
+
+    /// Number of tokenization workers in coordinator mode (default: 0, tokenize separately)
+    #[arg(long, default_value_t = 0, requires = "coordinator")]
+    tokenize_workers: usize,
+
+    /// Whisper model used in coordinator mode
+    #[arg(long, default_value = "base", requires = "coordinator")]
+    model: String,
+
+    /// Analyze each anime and write genre and studio analyses after all stages succeed
+    #[arg(long)]
+    aggregate: bool,
+
+    /// With --aggregate: include anime below `analysis.min_tokens` in the genre and studio analyses
+    #[arg(long, requires = "aggregate")]
+    include_small_samples: bool,
+
+    /// Only download and transcribe anime with all of these genres (repeatable; the --filter-* flags replace [pipeline.anime_filter])
+    #[arg(long, value_name = "GENRE")]
+    filter_genre: Vec<String>,
+
+    /// Only download and transcribe anime with all of these themes (repeatable)
+    #[arg(long, value_name = "THEME")]
+    filter_theme: Vec<String>,
+
+    /// Only download and transcribe anime with one of these demographics (repeatable)
+    #[arg(long, value_name = "DEMOGRAPHIC")]
+    filter_demographic: Vec<String>,
+
+    /// Only download and transcribe anime made by one of these studios (repeatable)
+    #[arg(long, value_name = "STUDIO")]
+    filter_studio: Vec<String>,
+
+    /// Only download and transcribe anime of one of these MAL types, e.g. TV (repeatable)
+    #[arg(long, value_name = "TYPE")]
+    filter_type: Vec<String>,
+
+    /// Only download and transcribe anime that started airing in these years: 2020, 2018..2022, 2018.. or ..2010
+    #[arg(long, value_name = "YEARS", value_parser = parse_year_range)]
+    filter_year: Option<(Option<i32>, Option<i32>)>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Args {
+    /// The anime filter set by the --filter-* flags (inactive without any)
+    fn anime_filter(&self) -> AnimeFilter {
+        let (min_year, max_year) = self.filter_year.unwrap_or_default();
+        AnimeFilter {
+            genres: self.filter_genre.clone(),
+            themes: self.filter_theme.clone(),
+            demographics: self.filter_demographic.clone(),
+            studios: self.filter_studio.clone(),
+            kind: AnimeFilterConfig {
+                types: self.filter_type.clone(),
+                ..Default::default()
+            },
+            min_year,
+            max_year,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Work with the configuration file instead of running the pipeline
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Check the config, database, data directories and external tools, with hints for fixing problems
+    Doctor,
+
+    /// Merge anime saved more than once under the same MAL ID into one row (database repair)
+    CompactAnime,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the JSON Schema of the configuration file
+    Schema,
+
+    /// Check the configuration file (--config, default config.toml) against the schema and its settings
+    Validate,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match &args.command {
+        Some(Command::Config { action }) => return run_config_command(action, args.config.as_deref()),
+        Some(Command::Doctor) => return run_doctor(args.config.as_deref(), args.profile.as_deref()),
+        Some(Command::CompactAnime) => return compact_anime(args.config.as_deref(), args.profile.as_deref()),
+        None => {}
+    }
+
+    // Load configuration
+    let mut config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+    if args.breadth_first {
+        config.pipeline.dequeue_order = DequeueOrder::BreadthFirst;
+    }
+    if args.include_small_samples {
+        config.analysis.include_small_samples = true;
+    }
+    let anime_filter = args.anime_filter();
+    if anime_filter.is_active() {
+        config.pipeline.anime_filter = anime_filter.clone();
+    }
+
+    // Initialize logging
+    shared::logging::init(shared::LogConfig {
+        log_dir: config.log_dir().to_string_lossy().to_string(),
+        component: "pipeline".to_string(),
+        module_levels: config.logging.module_levels.clone(),
+        ..Default::default()
+    })?;
+
+    if args.coordinator {
+        return run_coordinator(&args, &config).await;
+    }
+
+    let interleave = config.pipeline.interleave_workers && !args.sequential;
+    let steps = match &args.stages {
+        Some(stages) => plan(stages, interleave)?,
+        None => plan_from_config(&shared::PipelineConfig {
+            interleave_workers: interleave,
+            ..config.pipeline.clone()
+        })?,
+    };
+
+    let bin_dir = match args.bin_dir {
+        Some(dir) => dir,
+        None => std::env::current_exe()?
+            .parent()
+            .context("Failed to locate the binary directory")?
+            .to_path_buf(),
+    };
+    let mut runner = ProcessRunner::new(bin_dir, args.config.clone()).with_profile(args.profile.clone());
+    if args.dry_run {
+        for stage in [Stage::Select, Stage::Download, Stage::Transcribe] {
+            runner = runner.with_args(stage, &["--dry-run"]);
+        }
+    }
+    if args.breadth_first {
+        for stage in [Stage::Download, Stage::Transcribe] {
+            runner = runner.with_args(stage, &["--breadth-first"]);
+        }
+    }
+    // The stages read [pipeline.anime_filter] themselves, but not the flags
+    if anime_filter.is_active() {
+        let filter_args = anime_filter.to_args();
+        let filter_args: Vec<&str> = filter_args.iter().map(String::as_str).collect();
+        for stage in [Stage::Download, Stage::Transcribe] {
+            runner = runner.with_args(stage, &filter_args);
+        }
+    }
+
+    info!(steps = ?steps, dry_run = args.dry_run, "Pipeline starting");
+
+    let poll = Duration::from_secs(config.pipeline.transcriber_poll_seconds.max(1));
+    let reports = run_plan(&steps, &runner, poll).await;
+
+    print_summary(&reports);
+    print_job_stats(&config);
+
+    let failed = reports
+        .iter()
+        .filter(|r| matches!(r.outcome, StageOutcome::Failed(_)))
+        .count();
+    if failed > 0 {
+        anyhow::bail!("{} pipeline stage(s) failed", failed);
+    }
+
+    if args.aggregate {
+        write_aggregates(&config)?;
+    }
+
+    Ok(())
+}
+
+/// Print the config schema, or check a config file against it
+fn run_config_command(action: &ConfigCommand, config_path: Option<&Path>) -> Result<()> {
+    match action {
+        ConfigCommand::Schema => {
+            println!("{}", serde_json::to_string_pretty(&Config::json_schema())?);
+        }
+        ConfigCommand::Validate => {
+            let path = config_path.unwrap_or(Path::new(DEFAULT_CONFIG_PATH));
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+            Config::check_schema(&content).with_context(|| format!("{} is invalid", path.display()))?;
+            Config::from_file_strict(path)?
+                .validate()
+                .with_context(|| format!("{} is invalid", path.display()))?;
+            println!("{} is valid", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Run every doctor check and print the report
+fn run_doctor(config_path: Option<&Path>, profile: Option<&str>) -> Result<()> {
+    let report = doctor::run(config_path, profile, &doctor::SystemRunner);
+    println!("{}", report);
+
+    if !report.passed() {
+        anyhow::bail!("Doctor found critical problems");
+    }
+    Ok(())
+}
+
+/// Merge duplicate anime rows (see `JobQueue::compact_anime`) and print what
+/// changed
+fn compact_anime(config_path: Option<&Path>, profile: Option<&str>) -> Result<()> {
+    let config = Config::load(config_path, profile).context("Failed to load config")?;
+    let db_path = config.database_path();
+    let mut queue = JobQueue::new(
+        Database::open(&db_path).with_context(|| format!("Failed to open database at {}", db_path.display()))?,
+    );
+
+    let compaction = queue.compact_anime().context("Failed to compact anime")?;
+    println!("Duplicate anime rows removed: {}", compaction.anime_removed);
+    println!("Jobs moved to the kept row: {}", compaction.jobs_repointed);
+    println!("Jobs dropped for a more advanced job of the same episode: {}", compaction.jobs_removed);
+    Ok(())
+}
+
+/// Run download and transcription workers in-process until both are done
+async fn run_coordinator(args: &Args, config: &Config) -> Result<()> {
+    let download_workers = args
+        .download_workers
+        .unwrap_or(config.disk_management.max_concurrent_downloads);
+    let transcribe_workers = args
+        .transcribe_workers
+        .unwrap_or(config.disk_management.max_concurrent_transcriptions);
+    let workers = WorkerCounts {
+        download: download_workers,
+        transcribe: transcribe_workers,
+        tokenize: args.tokenize_workers,
+    };
+
+    info!(
+        download_workers,
+        transcribe_workers,
+        tokenize_workers = args.tokenize_workers,
+        model = %args.model,
+        dry_run = args.dry_run,
+        "Coordinator starting"
+    );
+
+    // Record the tool versions and config of both worker pools
+    let tools: Vec<ExternalTool> = anime_downloader::EXTERNAL_TOOLS
+        .iter()
+        .chain(transcriber::EXTERNAL_TOOLS)
+        .copied()
+        .collect();
+    let (mut manifest, manifest_path) = RunManifest::start("pipeline", config, &tools, &config.data_paths())?;
+
+    let mut coordinator = Coordinator::from_config(config, workers, &args.model, args.dry_run)?
+        .with_whisper_version(manifest.tool_version("whisper").map(str::to_string));
+    if args.aggregate {
+        coordinator = coordinator.with_analysis(config);
+    }
+    let report = coordinator.run().await;
+    manifest.finish(&manifest_path)?;
+
+    info!("=== Coordinator Summary ===");
+    info!("Download pauses for disk space: {}", report.download_pauses);
+    info!("Transcriber runs: {}", report.transcriber_runs);
+    info!("Episodes tokenized: {}", report.episodes_tokenized);
+    print_job_stats(config);
+
+    if report.failed_workers > 0 {
+        anyhow::bail!("{} worker(s) failed", report.failed_workers);
+    }
+
+    if args.aggregate {
+        // The coordinator has analyzed each anime already
+        write_group_analyses(config, &open_queue(config)?)?;
+    }
+
+    Ok(())
+}
+
+/// Analyze every anime with frequency tables, then combine the analyses by
+/// genre and studio
+fn write_aggregates(config: &Config) -> Result<()> {
+    let mut queue = open_queue(config)?;
+    let data_paths = config.data_paths();
+
+    let mut analyzed = 0;
+    for anime in queue.get_anime_classifications()? {
+        if !data_paths.tokens_dir(anime.mal_id).exists() {
+            continue;
+        }
+        if shared::analysis::analyze_anime(anime.mal_id, &data_paths, &mut queue, &config.analysis)?.is_some() {
+            analyzed += 1;
+            if config.disk_management.cleanup.delete_tokens_after_analysis {
+                shared::analysis::delete_token_lists(anime.mal_id, &data_paths, &mut queue)?;
+            }
+        }
+    }
+    info!("Analyzed {} anime", analyzed);
+
+    write_group_analyses(config, &queue)
+}
+
+/// Combine the per-anime analyses by genre and studio
+fn write_group_analyses(config: &Config, queue: &JobQueue) -> Result<()> {
+    let grouped = shared::aggregate::write_group_analyses(queue, &config.data_paths(), &config.analysis)?;
+    info!(
+        "Aggregated analyses: {} genres, {} studios",
+        grouped.by_genre.len(),
+        grouped.by_studio.len()
+    );
+
+    Ok(())
+}
+
+/// Open the job queue of `config`'s database
+fn open_queue(config: &Config) -> Result<JobQueue> {
+    let db_path = config.database_path();
+    Ok(JobQueue::new(
+        Database::open(&db_path).with_context(|| format!("Failed to open database at {}", db_path.display()))?,
+    ))
+}
+
+/// Print the outcome of each stage
+fn print_summary(reports: &[StageReport]) {
+    info!("=== Pipeline Summary ===");
+    for report in reports {
+        let elapsed = format!("{:.0}s", report.elapsed.as_secs_f64());
+        match &report.outcome {
+            StageOutcome::Succeeded if report.runs > 1 => {
+                info!("{:<10} ok      {:>8} ({} runs)", report.stage, elapsed, report.runs)
+            }
+            StageOutcome::Succeeded => info!("{:<10} ok      {:>8}", report.stage, elapsed),
+            StageOutcome::Failed(error) => warn!("{:<10} FAILED  {:>8}  {}", report.stage, elapsed, error),
+            StageOutcome::Skipped => info!("{:<10} skipped", report.stage),
+        }
+    }
+}
+
+/// Print the final job counts
+fn print_job_stats(config: &Config) {
+    let db_path = config.database_path();
+    let stats = Database::open_read_only(&db_path).and_then(|db| JobQueue::new(db).get_stats());
+    match stats {
+        Ok(stats) => {
+            info!("Jobs: {} total", stats.total);
+            info!("Queued: {}", stats.queued);
+            info!("Downloaded: {}", stats.downloaded);
+            info!("Transcribed: {}", stats.transcribed);
+            info!("Complete: {}", stats.complete);
+            info!("Failed: {}", stats.failed);
+        }
+        Err(e) => warn!(error = %e, "Could not read job statistics from {}", db_path.display()),
+    }
+}
@@ -0,0 +1,89 @@
+//! Runs pipeline stages as child processes.
+//!
+//! Each stage is its own binary, so the orchestrator only launches them with
+//! the shared config file and waits for them to exit; the stages coordinate
+//! through the job database and the disk monitor as they do when run by hand.
+
+use crate::stages::{Stage, StageRunner};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::process::Command;
+use tracing::info;
+
+/// Launches stage binaries from `bin_dir`
+pub struct ProcessRunner {
+    bin_dir: PathBuf,
+    /// Passed on as `--config` when set
+    config_path: Option<PathBuf>,
+    /// Passed on as `--profile` when set
+    profile: Option<String>,
+    /// Extra arguments per stage (e.g. `--dry-run`)
+    extra_args: HashMap<Stage, Vec<String>>,
+}
+
+impl ProcessRunner {
+    /// Create a runner for the binaries in `bin_dir`
+    pub fn new(bin_dir: PathBuf, config_path: Option<PathBuf>) -> Self {
+        Self {
+            bin_dir,
+            config_path,
+            profile: None,
+            extra_args: HashMap::new(),
+        }
+    }
+
+    /// Run every stage with `--profile profile`, if one is given
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Pass `args` to every run of `stage`
+    pub fn with_args(mut self, stage: Stage, args: &[&str]) -> Self {
+        self.extra_args
+            .entry(stage)
+            .or_default()
+            .extend(args.iter().map(|a| a.to_string()));
+        self
+    }
+
+    /// Path of the binary for `stage`
+    pub fn binary_path(&self, stage: Stage) -> PathBuf {
+        self.bin_dir.join(format!("{}{}", stage.binary(), std::env::consts::EXE_SUFFIX))
+    }
+}
+
+impl StageRunner for ProcessRunner {
+    async fn run(&self, stage: Stage) -> Result<()> {
+        let binary = self.binary_path(stage);
+        let args = self.extra_args.get(&stage).cloned().unwrap_or_default();
+
+        info!(stage = %stage, binary = %binary.display(), args = ?args, "Starting stage");
+
+        let mut command = Command::new(&binary);
+        if let Some(config_path) = &self.config_path {
+            command.arg("--config").arg(config_path);
+        }
+        if let Some(profile) = &self.profile {
+            command.arg("--profile").arg(profile);
+        }
+        let status = command
+            .args(&args)
+            .status()
+            .await
+            .with_context(|| format!("Failed to run {}", binary.display()))?;
+
+        if !status.success() {
+            anyhow::bail!(
+                "{} exited with code {}",
+                stage.binary(),
+                status.code().map_or("none (killed by signal)".to_string(), |c| c.to_string())
+            );
+        }
+
+        info!(stage = %stage, "Stage finished");
+
+        Ok(())
+    }
+}
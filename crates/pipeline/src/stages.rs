@@ -0,0 +1,416 @@
+//! Stage sequencing for the pipeline orchestrator.
+//!
+//! A run is planned as a list of steps: most stages run on their own, one
+//! after the other, while the downloader and transcriber can be interleaved.
+//! In an interleaved step the transcriber is restarted whenever it runs out
+//! of downloaded episodes, until the downloader has finished and a final
+//! transcriber pass has drained the queue.
+
+use anyhow::Result;
+use shared::PipelineConfig;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// One of the independently runnable pipeline stages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Scrape,
+    Select,
+    Download,
+    Transcribe,
+}
+
+impl Stage {
+    /// All stages, in pipeline order
+    pub const ALL: [Stage; 4] = [Stage::Scrape, Stage::Select, Stage::Download, Stage::Transcribe];
+
+    /// Name of the binary that runs this stage
+    pub fn binary(&self) -> &'static str {
+        match self {
+            Stage::Scrape => "mal-scraper",
+            Stage::Select => "anime-selector",
+            Stage::Download => "anime-downloader",
+            Stage::Transcribe => "transcriber",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Scrape => "scrape",
+            Stage::Select => "select",
+            Stage::Download => "download",
+            Stage::Transcribe => "transcribe",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Stage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Stage::ALL
+            .into_iter()
+            .find(|stage| stage.to_string() == s.to_lowercase())
+            .ok_or_else(|| anyhow::anyhow!("Unknown stage '{}' (expected scrape, select, download or transcribe)", s))
+    }
+}
+
+/// A step of a pipeline run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Run a stage to completion
+    Run(Stage),
+    /// Run `producer` while repeatedly running `consumer` until both are done
+    Interleave { producer: Stage, consumer: Stage },
+}
+
+/// Plan the steps for `stages`.
+///
+/// Stages keep the given order. With `interleave_workers`, a download stage
+/// directly followed by a transcribe stage becomes one interleaved step.
+pub fn plan(stages: &[Stage], interleave_workers: bool) -> Result<Vec<Step>> {
+    for (i, stage) in stages.iter().enumerate() {
+        if stages[..i].contains(stage) {
+            anyhow::bail!("Stage '{}' is listed more than once", stage);
+        }
+    }
+
+    let mut steps = Vec::new();
+    let mut i = 0;
+    while i < stages.len() {
+        if interleave_workers
+            && stages[i] == Stage::Download
+            && stages.get(i + 1) == Some(&Stage::Transcribe)
+        {
+            steps.push(Step::Interleave {
+                producer: Stage::Download,
+                consumer: Stage::Transcribe,
+            });
+            i += 2;
+        } else {
+            steps.push(Step::Run(stages[i]));
+            i += 1;
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Plan the steps configured in the `[pipeline]` section
+pub fn plan_from_config(config: &PipelineConfig) -> Result<Vec<Step>> {
+    let stages = config
+        .stages
+        .iter()
+        .map(|s| s.parse())
+        .collect::<Result<Vec<Stage>>>()?;
+    plan(&stages, config.interleave_workers)
+}
+
+/// Runs a single stage (a child process in production, a mock in tests)
+pub trait StageRunner {
+    /// Run `stage` to completion
+    async fn run(&self, stage: Stage) -> Result<()>;
+}
+
+/// How a stage ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageOutcome {
+    Succeeded,
+    Failed(String),
+    /// Not run because an earlier stage failed
+    Skipped,
+}
+
+/// Result of one stage of a pipeline run
+#[derive(Debug, Clone)]
+pub struct StageReport {
+    pub stage: Stage,
+    pub outcome: StageOutcome,
+    /// Number of times the stage was started
+    pub runs: u32,
+    pub elapsed: Duration,
+}
+
+impl StageReport {
+    fn new(stage: Stage, result: Result<()>, runs: u32, elapsed: Duration) -> Self {
+        let outcome = match result {
+            Ok(()) => StageOutcome::Succeeded,
+            Err(e) => StageOutcome::Failed(format!("{:#}", e)),
+        };
+        Self {
+            stage,
+            outcome,
+            runs,
+            elapsed,
+        }
+    }
+
+    fn skipped(stage: Stage) -> Self {
+        Self {
+            stage,
+            outcome: StageOutcome::Skipped,
+            runs: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    fn failed(&self) -> bool {
+        matches!(self.outcome, StageOutcome::Failed(_))
+    }
+}
+
+/// Run `steps` in order with `runner`.
+///
+/// A failed stage stops the run; the stages after it are reported as skipped.
+/// `poll` is how long an interleaved consumer waits before it is restarted
+/// while the producer is still running.
+pub async fn run_plan<R: StageRunner>(steps: &[Step], runner: &R, poll: Duration) -> Vec<StageReport> {
+    let mut reports = Vec::new();
+
+    for step in steps {
+        if reports.iter().any(StageReport::failed) {
+            match *step {
+                Step::Run(stage) => reports.push(StageReport::skipped(stage)),
+                Step::Interleave { producer, consumer } => {
+                    reports.push(StageReport::skipped(producer));
+                    reports.push(StageReport::skipped(consumer));
+                }
+            }
+            continue;
+        }
+
+        match *step {
+            Step::Run(stage) => {
+                let start = Instant::now();
+                let result = runner.run(stage).await;
+                reports.push(StageReport::new(stage, result, 1, start.elapsed()));
+            }
+            Step::Interleave { producer, consumer } => {
+                let (producer, consumer) = run_interleaved(runner, producer, consumer, poll).await;
+                reports.push(producer);
+                reports.push(consumer);
+            }
+        }
+    }
+
+    reports
+}
+
+/// Run `producer` once and `consumer` alongside it until both are done.
+///
+/// The consumer exits when it has nothing to do, so it is restarted after
+/// `poll` (or as soon as the producer finishes) for as long as the producer
+/// runs. The last consumer run starts after the producer has finished.
+async fn run_interleaved<R: StageRunner>(
+    runner: &R,
+    producer: Stage,
+    consumer: Stage,
+    poll: Duration,
+) -> (StageReport, StageReport) {
+    let (done_tx, mut done_rx) = watch::channel(false);
+
+    let producer_run = async {
+        let start = Instant::now();
+        let result = runner.run(producer).await;
+        let _ = done_tx.send(true);
+        StageReport::new(producer, result, 1, start.elapsed())
+    };
+
+    let consumer_runs = async {
+        let start = Instant::now();
+        let mut runs = 0;
+        let result = loop {
+            let final_run = *done_rx.borrow();
+            runs += 1;
+            if let Err(e) = runner.run(consumer).await {
+                break Err(e);
+            }
+            if final_run {
+                break Ok(());
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(poll) => {}
+                _ = done_rx.wait_for(|done| *done) => {}
+            }
+        };
+        StageReport::new(consumer, result, runs, start.elapsed())
+    };
+
+    tokio::join!(producer_run, consumer_runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Records start/end events; stages take the configured time and fail
+    /// when listed in `failing`
+    #[derive(Default)]
+    struct MockRunner {
+        durations: HashMap<Stage, Duration>,
+        failing: Vec<Stage>,
+        events: Mutex<Vec<String>>,
+    }
+
+    impl MockRunner {
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+
+        fn count(&self, event: &str) -> usize {
+            self.events().iter().filter(|e| *e == event).count()
+        }
+
+        fn position(&self, event: &str) -> usize {
+            self.events().iter().position(|e| e == event).unwrap()
+        }
+
+        fn last_position(&self, event: &str) -> usize {
+            self.events().iter().rposition(|e| e == event).unwrap()
+        }
+    }
+
+    impl StageRunner for MockRunner {
+        async fn run(&self, stage: Stage) -> Result<()> {
+            self.events.lock().unwrap().push(format!("start {}", stage));
+            if let Some(duration) = self.durations.get(&stage) {
+                tokio::time::sleep(*duration).await;
+            }
+            self.events.lock().unwrap().push(format!("end {}", stage));
+            if self.failing.contains(&stage) {
+                anyhow::bail!("{} exited with code 1", stage.binary());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plan() -> Result<()> {
+        use Stage::*;
+
+        assert_eq!(
+            plan(&Stage::ALL, true)?,
+            vec![
+                Step::Run(Scrape),
+                Step::Run(Select),
+                Step::Interleave { producer: Download, consumer: Transcribe },
+            ]
+        );
+        assert_eq!(
+            plan(&Stage::ALL, false)?,
+            Stage::ALL.map(Step::Run).to_vec()
+        );
+
+        // Only adjacent download -> transcribe is interleaved
+        assert_eq!(plan(&[Transcribe, Download], true)?, vec![Step::Run(Transcribe), Step::Run(Download)]);
+        assert_eq!(plan(&[Download], true)?, vec![Step::Run(Download)]);
+
+        assert!(plan(&[Scrape, Scrape], true).is_err());
+
+        let config = PipelineConfig {
+            stages: vec!["select".to_string(), "Download".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(plan_from_config(&config)?, vec![Step::Run(Select), Step::Run(Download)]);
+        let config = PipelineConfig {
+            stages: vec!["tokenize".to_string()],
+            ..Default::default()
+        };
+        assert!(plan_from_config(&config).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sequential_stages_run_in_order() -> Result<()> {
+        let runner = MockRunner::default();
+        let reports = run_plan(&plan(&Stage::ALL, false)?, &runner, Duration::from_millis(1)).await;
+
+        assert_eq!(
+            runner.events(),
+            vec![
+                "start scrape", "end scrape",
+                "start select", "end select",
+                "start download", "end download",
+                "start transcribe", "end transcribe",
+            ]
+        );
+        assert!(reports.iter().all(|r| r.outcome == StageOutcome::Succeeded && r.runs == 1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_stage_skips_the_rest() -> Result<()> {
+        let runner = MockRunner {
+            failing: vec![Stage::Select],
+            ..Default::default()
+        };
+        let reports = run_plan(&plan(&Stage::ALL, true)?, &runner, Duration::from_millis(1)).await;
+
+        assert_eq!(runner.events(), vec!["start scrape", "end scrape", "start select", "end select"]);
+        let outcomes: Vec<_> = reports.iter().map(|r| (r.stage, r.outcome.clone())).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (Stage::Scrape, StageOutcome::Succeeded),
+                (Stage::Select, StageOutcome::Failed("anime-selector exited with code 1".to_string())),
+                (Stage::Download, StageOutcome::Skipped),
+                (Stage::Transcribe, StageOutcome::Skipped),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_transcriber_restarts_until_downloads_finish() -> Result<()> {
+        let runner = MockRunner {
+            durations: HashMap::from([(Stage::Download, Duration::from_millis(200))]),
+            ..Default::default()
+        };
+        let steps = plan(&[Stage::Download, Stage::Transcribe], true)?;
+        let reports = run_plan(&steps, &runner, Duration::from_millis(20)).await;
+
+        // The transcriber starts alongside the downloader, is restarted while
+        // downloads continue and makes one last pass after they finish
+        assert!(runner.position("start transcribe") < runner.position("end download"));
+        assert!(runner.count("start transcribe") >= 3, "{:?}", runner.events());
+        assert!(runner.last_position("start transcribe") > runner.position("end download"));
+        assert_eq!(runner.events().last().unwrap(), "end transcribe");
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].runs, 1);
+        assert_eq!(reports[1].runs as usize, runner.count("start transcribe"));
+        assert!(reports.iter().all(|r| r.outcome == StageOutcome::Succeeded));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_consumer_failure_waits_for_producer() -> Result<()> {
+        let runner = MockRunner {
+            durations: HashMap::from([(Stage::Download, Duration::from_millis(50))]),
+            failing: vec![Stage::Transcribe],
+            ..Default::default()
+        };
+        let steps = plan(&Stage::ALL[1..], true)?;
+        let reports = run_plan(&steps, &runner, Duration::from_millis(10)).await;
+
+        // The transcriber is not restarted after failing, but the running
+        // download is allowed to finish
+        assert_eq!(runner.count("start transcribe"), 1);
+        assert_eq!(runner.events().last().unwrap(), "end download");
+        assert_eq!(reports[1].outcome, StageOutcome::Succeeded);
+        assert!(matches!(reports[2].outcome, StageOutcome::Failed(_)));
+
+        Ok(())
+    }
+}
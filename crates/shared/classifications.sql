@@ -0,0 +1,53 @@
+-- Classification lookup tables, one row per anime and name
+-- Normalized copies of the JSON arrays in the anime table (which are kept
+-- for compatibility), so filtering by classification can use the indexes.
+-- Names compare case-insensitively (ASCII).
+CREATE TABLE IF NOT EXISTS anime_genres (
+    mal_id INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,  -- "Comedy"
+
+    PRIMARY KEY (mal_id, name),
+    FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_genres_name ON anime_genres(name, mal_id);
+
+CREATE TABLE IF NOT EXISTS anime_explicit_genres (
+    mal_id INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,  -- "Ecchi"
+
+    PRIMARY KEY (mal_id, name),
+    FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_explicit_genres_name ON anime_explicit_genres(name, mal_id);
+
+CREATE TABLE IF NOT EXISTS anime_themes (
+    mal_id INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,  -- "School"
+
+    PRIMARY KEY (mal_id, name),
+    FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_themes_name ON anime_themes(name, mal_id);
+
+CREATE TABLE IF NOT EXISTS anime_demographics (
+    mal_id INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,  -- "Shounen"
+
+    PRIMARY KEY (mal_id, name),
+    FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_demographics_name ON anime_demographics(name, mal_id);
+
+CREATE TABLE IF NOT EXISTS anime_studios (
+    mal_id INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,  -- "Kyoto Animation"
+
+    PRIMARY KEY (mal_id, name),
+    FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_studios_name ON anime_studios(name, mal_id);
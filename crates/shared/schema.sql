@@ -65,6 +65,26 @@ CREATE TABLE IF NOT EXISTS jobs (
     priority INTEGER DEFAULT 0,
     depends_on INTEGER,
 
+    -- Transcript quality score (0.0 to 1.0, see transcriber::quality)
+    transcript_quality REAL,
+    -- Whisper model that produced the kept transcript
+    transcript_model TEXT,
+
+    -- Earliest time a retried job may be dequeued again (NULL = immediately)
+    next_attempt_at TIMESTAMP,
+
+    -- Opening transcript lines dropped as a recap of the previous episode
+    -- (NULL = not checked)
+    recap_lines INTEGER,
+
+    -- Version of the Whisper package that produced the kept transcript
+    whisper_version TEXT,
+
+    -- Transcript deleted after tokenization, token list deleted after
+    -- analysis (see [disk_management.cleanup])
+    transcript_deleted BOOLEAN DEFAULT 0,
+    tokens_deleted BOOLEAN DEFAULT 0,
+
     FOREIGN KEY (depends_on) REFERENCES jobs(id),
     FOREIGN KEY (anime_id) REFERENCES anime(id),
 
@@ -78,6 +98,7 @@ CREATE INDEX IF NOT EXISTS idx_jobs_anime_episode ON jobs(anime_id, episode);
 CREATE INDEX IF NOT EXISTS idx_jobs_priority ON jobs(priority DESC, created_at);
 CREATE INDEX IF NOT EXISTS idx_jobs_updated_at ON jobs(updated_at);
 CREATE INDEX IF NOT EXISTS idx_jobs_mal_id ON jobs(mal_id);
+CREATE INDEX IF NOT EXISTS idx_jobs_mal_id_episode ON jobs(mal_id, episode);
 
 -- Anime metadata table
 CREATE TABLE IF NOT EXISTS anime (
@@ -108,6 +129,10 @@ CREATE TABLE IF NOT EXISTS anime (
     demographics TEXT,     -- ["Shounen", ...]
     studios TEXT,          -- ["Bones", ...]
 
+    -- Theme songs (JSON arrays of song titles)
+    opening_themes TEXT,   -- ["again", ...]
+    ending_themes TEXT,    -- ["Uso", ...]
+
     -- Scores and rankings
     score REAL,
     scored_by INTEGER,
@@ -125,6 +150,12 @@ CREATE TABLE IF NOT EXISTS anime (
         'pending', 'processing', 'completed', 'failed'
     )),
 
+    -- Zipf analysis results (written back by the analyzer)
+    zipf_exponent REAL,
+    zipf_r_squared REAL,
+    total_tokens INTEGER,
+    zipf_warning TEXT,                    -- Set when the fit rests on too few tokens
+
     -- Timestamps
     fetched_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
@@ -135,6 +166,10 @@ CREATE INDEX IF NOT EXISTS idx_anime_mal_id ON anime(mal_id);
 CREATE INDEX IF NOT EXISTS idx_anime_rank ON anime(rank);
 CREATE INDEX IF NOT EXISTS idx_anime_score ON anime(score);
 CREATE INDEX IF NOT EXISTS idx_anime_processing_status ON anime(processing_status);
+CREATE INDEX IF NOT EXISTS idx_anime_zipf_exponent ON anime(zipf_exponent);
+
+-- Classification lookup tables (anime_genres, anime_themes, ...) are in
+-- classifications.sql, which also creates them in older databases.
 
 -- Analysis results table
 CREATE TABLE IF NOT EXISTS analysis_results (
@@ -184,14 +219,32 @@ CREATE TABLE IF NOT EXISTS anime_selection_cache (
     search_query TEXT NOT NULL,
     selected_index INTEGER NOT NULL,      -- 1-based index from candidates list
     selected_title TEXT NOT NULL,         -- The title that was selected
-    confidence TEXT NOT NULL CHECK(confidence IN ('high', 'medium', 'low')),
+    confidence TEXT NOT NULL CHECK(confidence IN ('high', 'medium', 'low', 'no_candidates')),
     reason TEXT,
+    mal_episodes INTEGER,                 -- Episode count from MAL
+    selected_episodes INTEGER,            -- Episode count of the selected candidate
+    episode_match TEXT CHECK(episode_match IN ('exact', 'close', 'acceptable', 'mismatch', 'unknown', NULL)),
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    manual INTEGER NOT NULL DEFAULT 0,    -- Set by hand; automated selection never overwrites it
 
     FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
 );
 
 CREATE INDEX IF NOT EXISTS idx_selection_cache_confidence ON anime_selection_cache(confidence);
+CREATE INDEX IF NOT EXISTS idx_selection_cache_episode_match ON anime_selection_cache(episode_match);
+
+-- Related anime (franchise links such as sequels and prequels)
+-- Lets analysis aggregate separate MAL entries of the same franchise.
+-- No foreign keys: related entries may not have been scraped.
+CREATE TABLE IF NOT EXISTS related_anime (
+    parent_mal_id INTEGER NOT NULL,
+    child_mal_id INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (parent_mal_id, child_mal_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_related_anime_child ON related_anime(child_mal_id);
 
 -- Triggers for automatic updated_at
 CREATE TRIGGER IF NOT EXISTS update_jobs_timestamp
@@ -0,0 +1,310 @@
+//! Genre- and studio-level analysis.
+//!
+//! Per-anime word frequency tables (see `frequency::aggregate_frequencies`)
+//! are combined for every genre and studio an anime belongs to, and a Zipf
+//! fit is computed on each combined table. The results are written under
+//! `analysis/aggregated/by_genre/<genre>/` and `by_studio/<studio>/`.
+//! Anime with too few tokens for a meaningful fit are left out (see
+//! `analysis::include_in_aggregates`).
+
+use crate::analysis::include_in_aggregates;
+use crate::config::AnalysisConfig;
+use crate::frequency::{fit_zipf, read_frequency_table, write_frequency_table, ZipfFit};
+use crate::models::AnimeClassification;
+use crate::paths::DataPaths;
+use crate::queue::JobQueue;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{debug, info};
+
+/// Number of most frequent words listed in a group's `zipf_params.json`
+const TOP_WORDS: usize = 20;
+
+/// A completed per-anime analysis with the classifications to group it by
+#[derive(Debug, Clone)]
+pub struct AnimeAnalysis {
+    pub classification: AnimeClassification,
+    pub frequencies: BTreeMap<String, u64>,
+}
+
+/// Combined word frequencies of every anime in one genre or studio
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupAnalysis {
+    /// MAL IDs of the anime in the group, in input order
+    pub mal_ids: Vec<u32>,
+    pub frequencies: BTreeMap<String, u64>,
+}
+
+/// Group analyses keyed by genre and by studio name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupedAnalyses {
+    pub by_genre: BTreeMap<String, GroupAnalysis>,
+    pub by_studio: BTreeMap<String, GroupAnalysis>,
+}
+
+/// One entry of `top_words` in a group's `zipf_params.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankedWord {
+    pub word: String,
+    pub count: u64,
+    pub rank: usize,
+    pub frequency: f64,
+}
+
+/// Contents of a group's `zipf_params.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupZipfParams {
+    pub group: String,
+    pub anime_ids: Vec<u32>,
+    pub total_words: u64,
+    pub unique_words: usize,
+    pub vocabulary_richness: f64,
+    pub zipf_fit: Option<ZipfFit>,
+    pub top_words: Vec<RankedWord>,
+    pub analyzed_at: DateTime<Utc>,
+}
+
+/// Combine per-anime frequency tables by genre and by studio.
+///
+/// An anime contributes its whole table to each of its genres and studios.
+/// Listing the same genre twice on one anime counts it once.
+pub fn group_analyses(analyses: &[AnimeAnalysis]) -> GroupedAnalyses {
+    let mut grouped = GroupedAnalyses::default();
+
+    for analysis in analyses {
+        let classification = &analysis.classification;
+        for (groups, names) in [
+            (&mut grouped.by_genre, &classification.genres),
+            (&mut grouped.by_studio, &classification.studios),
+        ] {
+            for name in names {
+                let group = groups.entry(name.clone()).or_default();
+                if group.mal_ids.contains(&classification.mal_id) {
+                    continue;
+                }
+                group.mal_ids.push(classification.mal_id);
+                for (word, count) in &analysis.frequencies {
+                    *group.frequencies.entry(word.clone()).or_insert(0) += count;
+                }
+            }
+        }
+    }
+
+    grouped
+}
+
+/// Summarise a group's frequency table the way per-anime `zipf_params.json` does
+pub fn group_zipf_params(name: &str, group: &GroupAnalysis) -> GroupZipfParams {
+    let total_words: u64 = group.frequencies.values().sum();
+    let unique_words = group.frequencies.len();
+
+    let mut ranked: Vec<_> = group.frequencies.iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let top_words = ranked
+        .into_iter()
+        .take(TOP_WORDS)
+        .enumerate()
+        .map(|(i, (word, &count))| RankedWord {
+            word: word.clone(),
+            count,
+            rank: i + 1,
+            frequency: count as f64 / total_words as f64,
+        })
+        .collect();
+
+    GroupZipfParams {
+        group: name.to_string(),
+        anime_ids: group.mal_ids.clone(),
+        total_words,
+        unique_words,
+        vocabulary_richness: if total_words == 0 {
+            0.0
+        } else {
+            unique_words as f64 / total_words as f64
+        },
+        zipf_fit: fit_zipf(&group.frequencies),
+        top_words,
+        analyzed_at: Utc::now(),
+    }
+}
+
+/// Load every anime with a whole-show `word_freq.csv` and its classifications,
+/// except those `config` leaves out of the aggregates
+pub fn load_completed_analyses(
+    queue: &JobQueue,
+    data_paths: &DataPaths,
+    config: &AnalysisConfig,
+) -> Result<Vec<AnimeAnalysis>> {
+    let mut analyses = Vec::new();
+    for classification in queue.get_anime_classifications()? {
+        let path = data_paths.word_freq_csv(classification.mal_id);
+        if !path.exists() {
+            debug!(mal_id = classification.mal_id, "No word frequency table yet, skipping");
+            continue;
+        }
+        let frequencies = read_frequency_table(&path)?;
+        let total_tokens: u64 = frequencies.values().sum();
+        if !include_in_aggregates(total_tokens, config) {
+            info!(
+                mal_id = classification.mal_id,
+                total_tokens,
+                min_tokens = config.min_tokens,
+                "Too few tokens, leaving anime out of the genre and studio analyses"
+            );
+            continue;
+        }
+        analyses.push(AnimeAnalysis {
+            classification,
+            frequencies,
+        });
+    }
+    Ok(analyses)
+}
+
+/// Write `word_freq.csv` and `zipf_params.json` for every genre and studio
+/// of the completed per-anime analyses.
+///
+/// Returns the grouped tables that were written.
+pub fn write_group_analyses(
+    queue: &JobQueue,
+    data_paths: &DataPaths,
+    config: &AnalysisConfig,
+) -> Result<GroupedAnalyses> {
+    let analyses = load_completed_analyses(queue, data_paths, config)?;
+    let grouped = group_analyses(&analyses);
+
+    for (name, group) in &grouped.by_genre {
+        write_group(&data_paths.genre_analysis_dir(name), name, group)?;
+    }
+    for (name, group) in &grouped.by_studio {
+        write_group(&data_paths.studio_analysis_dir(name), name, group)?;
+    }
+
+    info!(
+        anime = analyses.len(),
+        genres = grouped.by_genre.len(),
+        studios = grouped.by_studio.len(),
+        path = %data_paths.aggregated_dir().display(),
+        "Wrote genre and studio analyses"
+    );
+
+    Ok(grouped)
+}
+
+/// Write one group's frequency table and Zipf parameters into `dir`
+fn write_group(dir: &Path, name: &str, group: &GroupAnalysis) -> Result<()> {
+    write_frequency_table(&dir.join("word_freq.csv"), &group.frequencies)?;
+
+    let params = group_zipf_params(name, group);
+    let path = dir.join("zipf_params.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&params)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Anime;
+    use crate::Database;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn analysis(mal_id: u32, genres: &[&str], studios: &[&str], words: &[(&str, u64)]) -> AnimeAnalysis {
+        AnimeAnalysis {
+            classification: AnimeClassification {
+                mal_id,
+                title: format!("Anime {}", mal_id),
+                genres: genres.iter().map(|g| g.to_string()).collect(),
+                studios: studios.iter().map(|s| s.to_string()).collect(),
+            },
+            frequencies: words.iter().map(|(w, c)| (w.to_string(), *c)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_group_analyses_combines_shared_genre() {
+        let analyses = [
+            analysis(5114, &["Action", "Adventure"], &["Bones"], &[("の", 10), ("錬金術", 4)]),
+            analysis(1, &["Action", "Action"], &["Sunrise"], &[("の", 7), ("は", 3)]),
+        ];
+
+        let grouped = group_analyses(&analyses);
+
+        assert_eq!(
+            grouped.by_genre["Action"],
+            GroupAnalysis {
+                mal_ids: vec![5114, 1],
+                frequencies: BTreeMap::from([
+                    ("の".to_string(), 17),
+                    ("は".to_string(), 3),
+                    ("錬金術".to_string(), 4),
+                ]),
+            }
+        );
+        assert_eq!(grouped.by_genre["Adventure"].mal_ids, vec![5114]);
+        assert_eq!(grouped.by_genre["Adventure"].frequencies, analyses[0].frequencies);
+        assert_eq!(grouped.by_studio.keys().collect::<Vec<_>>(), ["Bones", "Sunrise"]);
+        assert_eq!(grouped.by_studio["Sunrise"].frequencies, analyses[1].frequencies);
+    }
+
+    #[test]
+    fn test_write_group_analyses() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let mut fmab = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64);
+        fmab.genres = vec!["Action".to_string()];
+        fmab.studios = vec!["Bones".to_string()];
+        queue.get_or_create_anime(&fmab)?;
+        // Not analysed yet, so left out of the Action aggregate
+        let mut bebop = Anime::test_fixture(1, "Cowboy Bebop", 26);
+        bebop.genres = vec!["Action".to_string()];
+        queue.get_or_create_anime(&bebop)?;
+        // Too few tokens, so left out too
+        let mut trigun = Anime::test_fixture(6, "Trigun", 26);
+        trigun.genres = vec!["Action".to_string()];
+        queue.get_or_create_anime(&trigun)?;
+
+        write_frequency_table(
+            &data_paths.word_freq_csv(5114),
+            &BTreeMap::from([("の".to_string(), 30), ("は".to_string(), 15), ("錬金術".to_string(), 10)]),
+        )?;
+        write_frequency_table(&data_paths.word_freq_csv(6), &BTreeMap::from([("の".to_string(), 5)]))?;
+
+        let config = AnalysisConfig {
+            min_tokens: 50,
+            ..AnalysisConfig::default()
+        };
+        let grouped = write_group_analyses(&queue, &data_paths, &config)?;
+        assert_eq!(grouped.by_genre["Action"].mal_ids, vec![5114]);
+
+        let genre_dir = data_paths.genre_analysis_dir("Action");
+        assert_eq!(
+            fs::read_to_string(genre_dir.join("word_freq.csv"))?,
+            "word,count\nの,30\nは,15\n錬金術,10\n"
+        );
+        let params: GroupZipfParams = serde_json::from_str(&fs::read_to_string(genre_dir.join("zipf_params.json"))?)?;
+        assert_eq!(params.group, "Action");
+        assert_eq!(params.anime_ids, vec![5114]);
+        assert_eq!(params.total_words, 55);
+        assert_eq!(params.top_words[0].word, "の");
+        assert!(params.zipf_fit.is_some());
+
+        assert!(data_paths.studio_analysis_dir("Bones").join("zipf_params.json").exists());
+
+        let config = AnalysisConfig {
+            include_small_samples: true,
+            ..config
+        };
+        let grouped = write_group_analyses(&queue, &data_paths, &config)?;
+        assert_eq!(grouped.by_genre["Action"].mal_ids, vec![6, 5114]);
+
+        Ok(())
+    }
+}
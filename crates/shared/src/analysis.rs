@@ -0,0 +1,211 @@
+//! Per-anime Zipf analysis.
+//!
+//! Combines an anime's episode frequency tables, fits Zipf's law to the
+//! result and records the fit on the anime row, so anime can be queried by
+//! exponent (`JobQueue::query_anime_by_exponent`) without reading analysis
+//! files. The anime's `metadata.json` is rewritten to include the fit.
+//!
+//! A fit on only a few hundred tokens says little, so anime below
+//! `[analysis] min_tokens` get a `zipf_warning` and are left out of the genre
+//! and studio fits unless `include_small_samples` is set.
+//!
+//! With `[disk_management.cleanup] delete_tokens_after_analysis`, the
+//! episode token lists are deleted once an anime is analyzed. The frequency
+//! tables every fit is built on are kept, so the anime can still be analyzed
+//! again as episodes are added.
+
+use crate::config::AnalysisConfig;
+use crate::frequency::{aggregate_frequencies, fit_zipf, ZipfFit};
+use crate::models::FileType;
+use crate::paths::DataPaths;
+use crate::queue::JobQueue;
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+/// Warning recorded for a fit on `total_tokens` tokens, if that is fewer
+/// than `min_tokens`
+pub fn sample_size_warning(total_tokens: u64, min_tokens: u64) -> Option<String> {
+    (total_tokens < min_tokens).then(|| {
+        format!(
+            "Only {} tokens (minimum {}), too few for a meaningful Zipf fit",
+            total_tokens, min_tokens
+        )
+    })
+}
+
+/// Whether an anime with `total_tokens` tokens goes into genre and studio fits
+pub fn include_in_aggregates(total_tokens: u64, config: &AnalysisConfig) -> bool {
+    config.include_small_samples || sample_size_warning(total_tokens, config.min_tokens).is_none()
+}
+
+/// Analyze one anime and store its Zipf fit and token count in the database
+///
+/// Writes the whole-show `word_freq.csv` as a side effect. Returns None,
+/// leaving the anime row untouched, when there are too few words to fit.
+/// A fit on fewer than `config.min_tokens` tokens is stored with a warning.
+pub fn analyze_anime(
+    mal_id: u32,
+    data_paths: &DataPaths,
+    queue: &mut JobQueue,
+    config: &AnalysisConfig,
+) -> Result<Option<ZipfFit>> {
+    let totals = aggregate_frequencies(mal_id, data_paths)?;
+    let total_tokens: u64 = totals.values().sum();
+
+    let Some(fit) = fit_zipf(&totals) else {
+        warn!(mal_id, unique_words = totals.len(), "Too few words for a Zipf fit");
+        return Ok(None);
+    };
+
+    let warning = sample_size_warning(total_tokens, config.min_tokens);
+    if let Some(warning) = &warning {
+        warn!(mal_id, total_tokens, "{}", warning);
+    }
+    queue.update_anime_analysis(mal_id, fit.alpha, fit.r_squared, total_tokens, warning.as_deref())?;
+    if let Some(anime) = queue.get_anime(mal_id)? {
+        data_paths.write_anime_metadata(&anime)?;
+    }
+    info!(
+        mal_id,
+        alpha = fit.alpha,
+        r_squared = fit.r_squared,
+        total_tokens,
+        "Analyzed anime"
+    );
+
+    Ok(Some(fit))
+}
+
+/// Delete the episode token lists (`epNNN_tokens.json`) of an analyzed
+/// anime, marking each deletion on its job.
+///
+/// Only episodes whose frequency table is on disk, and so went into the
+/// anime's `word_freq.csv`, lose their token list. Returns the bytes freed.
+pub fn delete_token_lists(mal_id: u32, data_paths: &DataPaths, queue: &mut JobQueue) -> Result<u64> {
+    if !data_paths.word_freq_csv(mal_id).is_file() {
+        anyhow::bail!("Anime {} has not been analyzed, keeping its token lists", mal_id);
+    }
+
+    let mut freed = 0;
+    for job in queue.get_anime_jobs(mal_id)? {
+        let tokens_path = data_paths.tokens_json(mal_id, job.episode);
+        if job.tokens_deleted || !tokens_path.exists() || !data_paths.freq_csv(mal_id, job.episode).is_file() {
+            continue;
+        }
+        let size = std::fs::metadata(&tokens_path).map(|m| m.len()).unwrap_or(0);
+        std::fs::remove_file(&tokens_path)
+            .with_context(|| format!("Failed to delete token list: {}", tokens_path.display()))?;
+        queue.mark_file_deleted(job.id, FileType::Tokens)?;
+        freed += size;
+    }
+
+    if freed > 0 {
+        info!(mal_id, freed_mb = freed / 1_000_000, "Deleted token lists after analysis");
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Anime, NewJob};
+    use crate::Database;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_analyze_anime_records_fit() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        queue.get_or_create_anime(&Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64))?;
+
+        fs::create_dir_all(data_paths.tokens_dir(5114))?;
+        fs::write(
+            data_paths.freq_csv(5114, 1),
+            "word,count,pos,reading\nの,600,助詞,ノ\nは,300,助詞,ハ\nを,200,助詞,ヲ\n",
+        )?;
+
+        let config = AnalysisConfig::default();
+        let fit = analyze_anime(5114, &data_paths, &mut queue, &config)?.unwrap();
+        assert!((fit.alpha - 1.0).abs() < 1e-9, "{:?}", fit);
+
+        let analyzed = queue.query_anime_by_exponent(0.9..1.1)?;
+        assert_eq!(analyzed.len(), 1);
+        assert_eq!(analyzed[0].total_tokens, Some(1100));
+        assert!(data_paths.word_freq_csv(5114).exists());
+        assert_eq!(data_paths.read_anime_metadata(5114)?.zipf_exponent, analyzed[0].zipf_exponent);
+
+        // 1100 tokens is under the default minimum
+        assert_eq!(analyzed[0].zipf_warning, sample_size_warning(1100, config.min_tokens));
+        assert!(analyzed[0].zipf_warning.is_some());
+        let config = AnalysisConfig {
+            min_tokens: 1000,
+            ..config
+        };
+        analyze_anime(5114, &data_paths, &mut queue, &config)?;
+        assert_eq!(queue.get_anime(5114)?.unwrap().zipf_warning, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_token_lists() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime_id = queue.get_or_create_anime(&Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64))?;
+        fs::create_dir_all(data_paths.tokens_dir(5114))?;
+        for episode in 1..=2 {
+            queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: 5114,
+                anime_title: "Fullmetal Alchemist: Brotherhood".to_string(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            fs::write(data_paths.tokens_json(5114, episode), "{}")?;
+        }
+        // Episode 2's tokenization stopped before the frequency table
+        fs::write(data_paths.freq_csv(5114, 1), "word,count,pos,reading\nの,600,助詞,ノ\nは,300,助詞,ハ\n")?;
+
+        assert!(delete_token_lists(5114, &data_paths, &mut queue).is_err());
+        assert!(data_paths.tokens_json(5114, 1).exists());
+
+        analyze_anime(5114, &data_paths, &mut queue, &AnalysisConfig::default())?;
+        assert_eq!(delete_token_lists(5114, &data_paths, &mut queue)?, 2);
+
+        assert!(!data_paths.tokens_json(5114, 1).exists());
+        assert!(data_paths.freq_csv(5114, 1).exists());
+        assert!(data_paths.tokens_json(5114, 2).exists());
+        let jobs = queue.get_anime_jobs(5114)?;
+        assert!(jobs[0].tokens_deleted);
+        assert!(!jobs[1].tokens_deleted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_small_samples_left_out_of_aggregates() {
+        let config = AnalysisConfig {
+            min_tokens: 5000,
+            include_small_samples: false,
+        };
+        assert!(sample_size_warning(4999, 5000).unwrap().contains("Only 4999 tokens (minimum 5000)"));
+        assert_eq!(sample_size_warning(5000, 5000), None);
+
+        assert!(!include_in_aggregates(300, &config));
+        assert!(!include_in_aggregates(4999, &config));
+        assert!(include_in_aggregates(5000, &config));
+        assert!(include_in_aggregates(334_520, &config));
+
+        let config = AnalysisConfig {
+            include_small_samples: true,
+            ..config
+        };
+        assert!(include_in_aggregates(300, &config));
+    }
+}
@@ -0,0 +1,124 @@
+//! Work budgets for bounded worker runs.
+//!
+//! A worker loop normally runs until the queue drains. A `RunBudget` lets a
+//! run stop cleanly after a fixed number of jobs or a wall-clock duration,
+//! leaving the remaining work queued for the next run.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Job count and time limits shared by all workers of a single run.
+///
+/// Cloning a budget shares the underlying job counter, so every worker
+/// spawned from the same budget draws from the same pool.
+#[derive(Debug, Clone)]
+pub struct RunBudget {
+    /// Maximum number of jobs to start (None = unlimited)
+    max_jobs: Option<usize>,
+    /// Point in time after which no new jobs are started (None = unlimited)
+    deadline: Option<Instant>,
+    /// Number of jobs started so far
+    started: Arc<AtomicUsize>,
+}
+
+impl RunBudget {
+    /// Create a new budget.
+    ///
+    /// The duration is measured from the moment the budget is created.
+    pub fn new(max_jobs: Option<usize>, max_duration: Option<Duration>) -> Self {
+        Self {
+            max_jobs,
+            deadline: max_duration.map(|d| Instant::now() + d),
+            started: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Create a budget with no limits.
+    pub fn unlimited() -> Self {
+        Self::new(None, None)
+    }
+
+    /// Try to reserve a slot for one more job.
+    ///
+    /// Returns false if the job limit or the deadline has been reached, in
+    /// which case the caller should stop picking up new work.
+    pub fn try_start_job(&self) -> bool {
+        if self.is_expired() {
+            return false;
+        }
+
+        match self.max_jobs {
+            Some(max) => self
+                .started
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                    (n < max).then_some(n + 1)
+                })
+                .is_ok(),
+            None => {
+                self.started.fetch_add(1, Ordering::SeqCst);
+                true
+            }
+        }
+    }
+
+    /// Return a slot reserved with `try_start_job` that was not used
+    /// (e.g. because the queue turned out to be empty).
+    pub fn release_job(&self) {
+        let _ = self
+            .started
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+
+    /// Check whether the time limit has passed.
+    pub fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Number of jobs started so far.
+    pub fn jobs_started(&self) -> usize {
+        self.started.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for RunBudget {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_limit() {
+        let budget = RunBudget::new(Some(2), None);
+        assert!(budget.try_start_job());
+        assert!(budget.try_start_job());
+        assert!(!budget.try_start_job());
+        assert_eq!(budget.jobs_started(), 2);
+
+        budget.release_job();
+        assert!(budget.try_start_job());
+    }
+
+    #[test]
+    fn test_shared_between_clones() {
+        let budget = RunBudget::new(Some(1), None);
+        let other = budget.clone();
+        assert!(budget.try_start_job());
+        assert!(!other.try_start_job());
+    }
+
+    #[test]
+    fn test_deadline() {
+        let budget = RunBudget::new(None, Some(Duration::ZERO));
+        assert!(budget.is_expired());
+        assert!(!budget.try_start_job());
+
+        let unlimited = RunBudget::unlimited();
+        assert!(!unlimited.is_expired());
+        assert!(unlimited.try_start_job());
+    }
+}
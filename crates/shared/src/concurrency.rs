@@ -0,0 +1,111 @@
+//! Per-stage concurrency limits for worker pools.
+//!
+//! The number of workers started for a stage and the number of jobs allowed
+//! to run in it at once are separate knobs. `StageLimits` holds one semaphore
+//! per limited stage; a worker takes a permit before claiming a job and keeps
+//! it until the job is done, so however many workers share the limits, no
+//! more than the configured number of jobs are ever in that stage.
+
+use crate::config::StageConcurrency;
+use crate::models::JobStage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Concurrency limits shared by all workers of a single run.
+///
+/// Cloning shares the underlying semaphores, so every worker handed a clone
+/// of the same limits draws from the same permits.
+#[derive(Debug, Clone, Default)]
+pub struct StageLimits {
+    semaphores: HashMap<JobStage, (usize, Arc<Semaphore>)>,
+}
+
+impl StageLimits {
+    /// Create limits that never make a worker wait.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Create limits for the stages `config` limits (see
+    /// `Config::stage_concurrency`).
+    pub fn from_config(config: &StageConcurrency) -> Self {
+        StageConcurrency::STAGES
+            .into_iter()
+            .fold(Self::unlimited(), |limits, stage| match config.limit(stage) {
+                Some(limit) => limits.with_limit(stage, limit),
+                None => limits,
+            })
+    }
+
+    /// Allow at most `limit` jobs (at least one) in `stage` at once.
+    pub fn with_limit(mut self, stage: JobStage, limit: usize) -> Self {
+        let limit = limit.max(1);
+        self.semaphores.insert(stage, (limit, Arc::new(Semaphore::new(limit))));
+        self
+    }
+
+    /// The limit for `stage` (None = unlimited).
+    pub fn limit(&self, stage: JobStage) -> Option<usize> {
+        self.semaphores.get(&stage).map(|(limit, _)| *limit)
+    }
+
+    /// Wait for a slot in `stage`.
+    ///
+    /// The slot is held until the returned permit is dropped. For a stage
+    /// without a limit this returns None at once.
+    pub async fn acquire(&self, stage: JobStage) -> Option<OwnedSemaphorePermit> {
+        let (_, semaphore) = self.semaphores.get(&stage)?;
+        Arc::clone(semaphore).acquire_owned().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_limits_cap_concurrent_jobs_per_stage() {
+        let limits = StageLimits::from_config(&StageConcurrency {
+            downloading: Some(3),
+            transcribing: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(limits.limit(JobStage::Downloading), Some(3));
+        assert_eq!(limits.limit(JobStage::Tokenizing), None);
+
+        // Eight workers per stage, each running a few short jobs
+        let run = |stage: JobStage| {
+            let active = Arc::new(AtomicUsize::new(0));
+            let peak = Arc::new(AtomicUsize::new(0));
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    let (limits, active, peak) = (limits.clone(), Arc::clone(&active), Arc::clone(&peak));
+                    tokio::spawn(async move {
+                        for _ in 0..3 {
+                            let _permit = limits.acquire(stage).await;
+                            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(5)).await;
+                            active.fetch_sub(1, Ordering::SeqCst);
+                        }
+                    })
+                })
+                .collect();
+            async move {
+                for worker in workers {
+                    worker.await.unwrap();
+                }
+                peak.load(Ordering::SeqCst)
+            }
+        };
+
+        let (downloads, transcriptions, tokenizations) =
+            tokio::join!(run(JobStage::Downloading), run(JobStage::Transcribing), run(JobStage::Tokenizing));
+        assert_eq!(downloads, 3);
+        assert_eq!(transcriptions, 1);
+        assert!(tokenizations > 3, "unlimited stage peaked at {}", tokenizations);
+    }
+}
@@ -3,12 +3,18 @@
 //! This module handles loading and parsing configuration from TOML files,
 //! with sensible defaults for all settings.
 
+use crate::models::{AnimeFilter, Confidence, JobStage};
+use crate::paths::{DataPaths, PathLayout};
+use crate::retry::RetryPolicy;
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Main configuration structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     /// Data directory settings
     pub data: DataConfig,
@@ -29,10 +35,30 @@ pub struct Config {
     /// Anthropic API settings
     #[serde(default)]
     pub anthropic: AnthropicConfig,
+
+    /// Downloader settings
+    #[serde(default)]
+    pub downloader: DownloaderConfig,
+
+    /// Pipeline orchestrator settings
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+
+    /// Tokenizer settings
+    #[serde(default)]
+    pub tokenizer: TokenizerConfig,
+
+    /// Transcriber settings
+    #[serde(default)]
+    pub transcriber: TranscriberConfig,
+
+    /// Zipf analysis settings
+    #[serde(default)]
+    pub analysis: AnalysisConfig,
 }
 
 /// Data directory configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DataConfig {
     /// Root data directory path (for database and logs on local SSD)
     pub root_dir: String,
@@ -40,17 +66,68 @@ pub struct DataConfig {
     /// Storage directory path (for videos and transcripts on external HDD)
     /// If not specified, uses root_dir for all data
     pub storage_dir: Option<String>,
+
+    /// Videos directory (relative to storage_dir or absolute, default "videos")
+    pub videos_dir: Option<String>,
+
+    /// Audio directory (relative to root_dir or absolute, default "audio")
+    pub audio_dir: Option<String>,
+
+    /// Transcripts directory (relative to root_dir or absolute, default "transcripts")
+    pub transcripts_dir: Option<String>,
+
+    /// Tokens directory (relative to root_dir or absolute, default "tokens")
+    pub tokens_dir: Option<String>,
+
+    /// Analysis directory (relative to root_dir or absolute, default "analysis")
+    pub analysis_dir: Option<String>,
+
+    /// Corpus profile: keeps the data, database, logs and cache of this
+    /// corpus under `<root_dir>/profiles/<name>` (and the videos under
+    /// `<storage_dir>/profiles/<name>`), apart from every other profile.
+    /// Absolute directory overrides get a `profiles/<name>` subdirectory
+    /// too. Overridden by `--profile`
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+impl DataConfig {
+    /// Environment variables that override the per-category directories
+    pub const ENV_VARS: [&'static str; 5] = [
+        "GDA_VIDEOS_DIR",
+        "GDA_AUDIO_DIR",
+        "GDA_TRANSCRIPTS_DIR",
+        "GDA_TOKENS_DIR",
+        "GDA_ANALYSIS_DIR",
+    ];
+
+    /// Override the per-category directories with the non-empty values of
+    /// `ENV_VARS`, as looked up by `var`
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        let dirs = [
+            &mut self.videos_dir,
+            &mut self.audio_dir,
+            &mut self.transcripts_dir,
+            &mut self.tokens_dir,
+            &mut self.analysis_dir,
+        ];
+        for (name, dir) in Self::ENV_VARS.into_iter().zip(dirs) {
+            if let Some(value) = var(name).filter(|value| !value.is_empty()) {
+                *dir = Some(value);
+            }
+        }
+    }
 }
 
 /// Database configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DatabaseConfig {
     /// Database file path (relative to data directory or absolute)
     pub path: String,
 }
 
 /// Logging configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LoggingConfig {
     /// Log directory path (relative to data directory or absolute)
     pub log_dir: String,
@@ -66,10 +143,15 @@ pub struct LoggingConfig {
 
     /// Enable JSON formatting for file logs
     pub json_format: bool,
+
+    /// Per-module log levels, e.g. `"mal_scraper::api" = "debug"`
+    /// (`RUST_LOG` still overrides everything)
+    #[serde(default)]
+    pub module_levels: BTreeMap<String, String>,
 }
 
 /// MAL scraper configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MalScraperConfig {
     /// Jikan API base URL
     pub base_url: String,
@@ -88,10 +170,129 @@ pub struct MalScraperConfig {
 
     /// Retry delay in milliseconds
     pub retry_delay_ms: u64,
+
+    /// Retry policy of Jikan requests, in place of `max_retries` and
+    /// `retry_delay_ms`
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+
+    /// Timeout of each request attempt in seconds
+    #[serde(default = "default_request_timeout_seconds")]
+    pub request_timeout_seconds: u64,
+
+    /// Log a warning for responses that take longer than this many
+    /// milliseconds (0 = never)
+    #[serde(default = "default_slow_request_ms")]
+    pub slow_request_ms: u64,
+
+    /// Include explicit genres (hentai, erotica) in category discovery
+    #[serde(default)]
+    pub include_explicit: bool,
+
+    /// Maximum listing pages fetched per category (None = until the last page)
+    #[serde(default)]
+    pub max_pages_per_category: Option<usize>,
+
+    /// Proxy for all Jikan requests, e.g. `http://proxy.example.com:8080`
+    /// (None = the `HTTP_PROXY`/`HTTPS_PROXY` environment variables, if set)
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// Weights of the priority given to each anime's jobs
+    #[serde(default)]
+    pub priority: PriorityConfig,
+
+    /// Kinds of anime (by MAL type and episode count) that get jobs
+    #[serde(default)]
+    pub filter: AnimeFilterConfig,
+}
+
+impl MalScraperConfig {
+    /// Retry policy of Jikan requests: `retry`, or else `max_retries` and
+    /// `retry_delay_ms` with the client's usual cap and jitter
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry.unwrap_or(RetryPolicy {
+            max_attempts: self.max_retries.saturating_add(1),
+            base_delay_ms: self.retry_delay_ms,
+            max_delay_ms: 60_000,
+            jitter: 0.25,
+        })
+    }
+}
+
+fn default_request_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_slow_request_ms() -> u64 {
+    5000
+}
+
+/// Kinds of anime that are discovered and get jobs, by MAL type and episode
+/// count, e.g. to keep single-episode movies and specials out of a corpus of
+/// series
+///
+/// An anime whose episode count is unknown (typically one still airing)
+/// passes the episode bounds; one without a type fails a `types` allowlist.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AnimeFilterConfig {
+    /// Skip anime with fewer episodes (2 skips movies and one-off specials)
+    pub min_episodes: Option<u32>,
+
+    /// Skip anime with more episodes
+    pub max_episodes: Option<u32>,
+
+    /// MAL types to keep, e.g. ["TV"] or ["TV", "ONA"] (case-insensitive;
+    /// empty = every type, including Movie, OVA and Special)
+    pub types: Vec<String>,
+}
+
+impl AnimeFilterConfig {
+    /// Check whether an anime of `anime_type` with `episodes` passes
+    pub fn matches(&self, anime_type: Option<&str>, episodes: Option<u32>) -> bool {
+        if let Some(episodes) = episodes {
+            if self.min_episodes.is_some_and(|min| episodes < min) || self.max_episodes.is_some_and(|max| episodes > max) {
+                return false;
+            }
+        }
+        self.types.is_empty()
+            || anime_type.is_some_and(|anime_type| self.types.iter().any(|t| t.eq_ignore_ascii_case(anime_type)))
+    }
+
+    /// Check whether any bound or type is set
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Weights of the job priority computed from each anime's MAL data, so
+/// well-known anime are processed first on a partial run
+///
+/// Each weight multiplies a component worth up to 1000 points; all zero
+/// gives every job priority 0 (first come, first served).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PriorityConfig {
+    /// Weight of the MAL popularity rank (1000 points at #1, 500 at #10,
+    /// 250 at #1000)
+    pub popularity_weight: f64,
+
+    /// Weight of the MAL score (100 points per score point)
+    pub score_weight: f64,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            popularity_weight: 1.0,
+            score_weight: 0.0,
+        }
+    }
 }
 
 /// Rate limiting configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RateLimitConfig {
     /// Maximum requests per second
     pub requests_per_second: f64,
@@ -101,7 +302,7 @@ pub struct RateLimitConfig {
 }
 
 /// Cache configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CacheConfig {
     /// Enable caching
     pub enabled: bool,
@@ -114,7 +315,7 @@ pub struct CacheConfig {
 }
 
 /// Disk management configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DiskManagementConfig {
     /// Hard limit in GB
     pub hard_limit_gb: u64,
@@ -131,10 +332,18 @@ pub struct DiskManagementConfig {
     /// Cache duration for disk usage results in seconds
     pub cache_duration_seconds: u64,
 
-    /// Maximum concurrent downloads
+    /// Full rescan interval in seconds when workers report size changes
+    /// (None = rescan every cache_duration_seconds)
+    pub reconcile_interval_seconds: Option<u64>,
+
+    /// Maximum concurrent downloads (the default number of download workers,
+    /// and the coordinator's download limit unless `[pipeline.concurrency]`
+    /// sets one)
     pub max_concurrent_downloads: usize,
 
-    /// Maximum concurrent transcriptions
+    /// Maximum concurrent transcriptions (the default number of transcription
+    /// workers, and the coordinator's transcription limit unless
+    /// `[pipeline.concurrency]` sets one)
     pub max_concurrent_transcriptions: usize,
 
     /// Cleanup configuration
@@ -142,7 +351,7 @@ pub struct DiskManagementConfig {
 }
 
 /// Cleanup configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CleanupConfig {
     /// Delete video after transcription
     pub delete_video_after_transcription: bool,
@@ -155,19 +364,484 @@ pub struct CleanupConfig {
 
     /// Delete tokens after analysis
     pub delete_tokens_after_analysis: bool,
+
+    /// Keep the video of one in every N jobs (by job ID) even when videos are
+    /// deleted after transcription, as a sample for spot-checking; 0 keeps none
+    #[serde(default)]
+    pub keep_video_sample_rate: u32,
+
+    /// Keep the video of each anime's first episode even when videos are
+    /// deleted after transcription
+    #[serde(default)]
+    pub keep_first_episode_video: bool,
+}
+
+impl CleanupConfig {
+    /// Whether the video of job `job_id` (episode `episode`) is kept as a
+    /// spot-check sample. Deterministic, so a rerun keeps the same videos.
+    pub fn keep_video_sample(&self, job_id: i64, episode: u32) -> bool {
+        (self.keep_first_episode_video && episode == 1)
+            || (self.keep_video_sample_rate > 0 && job_id % i64::from(self.keep_video_sample_rate) == 0)
+    }
+}
+
+/// Downloader configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DownloaderConfig {
+    /// Minimum selection confidence required to download (high, medium, low)
+    /// Jobs below this level fail with a "needs manual review" error
+    pub min_download_confidence: Confidence,
+
+    /// Video bitrate assumed when estimating the size of an episode before
+    /// any episode of the same anime has been downloaded (kbit/s)
+    pub estimated_bitrate_kbps: u32,
+
+    /// Minutes an ani-cli run may take before it is killed and the download
+    /// retried (0 = no limit)
+    pub process_timeout_minutes: u64,
+}
+
+impl DownloaderConfig {
+    /// Time an ani-cli run may take, if limited
+    pub fn process_timeout(&self) -> Option<Duration> {
+        minutes(self.process_timeout_minutes)
+    }
+}
+
+impl Default for DownloaderConfig {
+    fn default() -> Self {
+        Self {
+            min_download_confidence: Confidence::Low,
+            estimated_bitrate_kbps: 1500,
+            process_timeout_minutes: 60,
+        }
+    }
+}
+
+/// `minutes` as a duration, with 0 meaning no limit
+fn minutes(minutes: u64) -> Option<Duration> {
+    (minutes > 0).then(|| Duration::from_secs(minutes * 60))
+}
+
+/// Pipeline orchestrator configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PipelineConfig {
+    /// Stages to run, in order (scrape, select, download, transcribe)
+    pub stages: Vec<String>,
+
+    /// Run the downloader and transcriber at the same time, so transcription
+    /// frees disk space for further downloads
+    pub interleave_workers: bool,
+
+    /// Seconds to wait before restarting the transcriber when it runs out of
+    /// work while downloads are still running
+    pub transcriber_poll_seconds: u64,
+
+    /// Order in which the downloader and transcriber claim jobs
+    #[serde(default)]
+    pub dequeue_order: DequeueOrder,
+
+    /// Jobs allowed in each stage at once, however many workers run
+    #[serde(default)]
+    pub concurrency: StageConcurrency,
+
+    /// Retry policy of failed download and transcription jobs: overrides
+    /// each job's `max_retries` and the built-in delays (None = 1 minute,
+    /// doubling up to 1 hour)
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+
+    /// Only download and transcribe jobs of anime with these classifications
+    /// (`--filter-genre` etc. override it)
+    #[serde(default)]
+    pub anime_filter: AnimeFilter,
+}
+
+/// Per-stage concurrency limits enforced by the coordinator (`[pipeline.concurrency]`)
+///
+/// Unlike `--download-workers`/`--transcribe-workers`/`--tokenize-workers`,
+/// which set how many workers are started, these cap how many jobs those
+/// workers run at once. See `Config::stage_concurrency` for the limits in
+/// effect.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct StageConcurrency {
+    /// Episodes downloaded at once (default: `max_concurrent_downloads`)
+    pub downloading: Option<usize>,
+
+    /// Episodes transcribed at once (default: `max_concurrent_transcriptions`)
+    pub transcribing: Option<usize>,
+
+    /// Episodes tokenized at once (default: one per tokenize worker)
+    pub tokenizing: Option<usize>,
+
+    /// Anime analyzed at once with `--aggregate` (default: all of them)
+    pub analyzing: Option<usize>,
+}
+
+impl StageConcurrency {
+    /// Stages that can be limited
+    pub const STAGES: [JobStage; 4] = [
+        JobStage::Downloading,
+        JobStage::Transcribing,
+        JobStage::Tokenizing,
+        JobStage::Analyzing,
+    ];
+
+    /// The limit for `stage` (None = unlimited)
+    pub fn limit(&self, stage: JobStage) -> Option<usize> {
+        match stage {
+            JobStage::Downloading => self.downloading,
+            JobStage::Transcribing => self.transcribing,
+            JobStage::Tokenizing => self.tokenizing,
+            JobStage::Analyzing => self.analyzing,
+            _ => None,
+        }
+    }
+}
+
+/// Order in which workers claim jobs from the queue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DequeueOrder {
+    /// Highest priority first, oldest first within a priority, so workers
+    /// finish one anime before starting the next
+    #[default]
+    Priority,
+    /// One episode of each anime in turn (in priority order), so many anime
+    /// get a first episode through early
+    BreadthFirst,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            stages: ["scrape", "select", "download", "transcribe"]
+                .map(String::from)
+                .to_vec(),
+            interleave_workers: true,
+            transcriber_poll_seconds: 30,
+            dequeue_order: DequeueOrder::default(),
+            concurrency: StageConcurrency::default(),
+            retry: None,
+            anime_filter: AnimeFilter::default(),
+        }
+    }
+}
+
+/// Transcriber configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TranscriberConfig {
+    /// Format of the audio extracted from each video for Whisper
+    #[serde(default)]
+    pub audio: AudioFormat,
+
+    /// Text passed to Whisper as `--initial_prompt`; `{title}` is replaced
+    /// with the anime title
+    #[serde(default)]
+    pub initial_prompt: Option<String>,
+
+    /// Directory for the extracted audio, which only lives until its episode
+    /// is transcribed (e.g. a RAM disk or another volume). If not specified,
+    /// audio goes to the data root's audio directory
+    #[serde(default)]
+    pub temp_dir: Option<String>,
+
+    /// Transcript files written for each episode. txt is required (the
+    /// tokenizer reads it); video and audio are only deleted once every
+    /// format has been written
+    #[serde(default = "default_transcript_formats")]
+    pub transcript_formats: Vec<TranscriptFormat>,
+
+    /// Transcribe in chunks of this many seconds, keeping each chunk's
+    /// transcript so a failed or interrupted episode resumes where it
+    /// stopped. If not specified, each episode is transcribed in one go
+    #[serde(default)]
+    pub chunk_seconds: Option<u64>,
+
+    /// Minutes a single ffmpeg or whisper run (one chunk, with
+    /// `chunk_seconds`) may take before it is killed and the transcription
+    /// retried (0 = no limit)
+    #[serde(default = "default_transcriber_timeout_minutes")]
+    pub process_timeout_minutes: u64,
+}
+
+impl TranscriberConfig {
+    /// Time a single ffmpeg or whisper run may take, if limited
+    pub fn process_timeout(&self) -> Option<Duration> {
+        minutes(self.process_timeout_minutes)
+    }
+}
+
+fn default_transcriber_timeout_minutes() -> u64 {
+    240
+}
+
+impl Default for TranscriberConfig {
+    fn default() -> Self {
+        Self {
+            audio: AudioFormat::default(),
+            initial_prompt: None,
+            temp_dir: None,
+            transcript_formats: default_transcript_formats(),
+            chunk_seconds: None,
+            process_timeout_minutes: default_transcriber_timeout_minutes(),
+        }
+    }
+}
+
+fn default_transcript_formats() -> Vec<TranscriptFormat> {
+    vec![TranscriptFormat::Txt]
+}
+
+/// Transcript file format written by Whisper
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    /// Plain text, one line per segment
+    Txt,
+    /// Whisper's full output, with segment timestamps
+    Json,
+}
+
+impl TranscriptFormat {
+    /// File extension for this format (also its Whisper `--output_format`)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Txt => "txt",
+            TranscriptFormat::Json => "json",
+        }
+    }
+}
+
+/// Format of the intermediate audio file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AudioFormat {
+    /// Sample rate in Hz (Whisper resamples to 16 kHz anyway)
+    pub sample_rate: u32,
+
+    /// Number of channels
+    pub channels: u32,
+
+    /// Container and codec
+    pub codec: AudioCodec,
+}
+
+impl Default for AudioFormat {
+    fn default() -> Self {
+        Self {
+            sample_rate: 16_000,
+            channels: 1,
+            codec: AudioCodec::Wav,
+        }
+    }
+}
+
+/// Codec of the intermediate audio file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    /// 16-bit PCM WAV
+    #[default]
+    Wav,
+    /// Lossless FLAC, roughly half the size of WAV
+    Flac,
+}
+
+impl AudioCodec {
+    /// File extension for this codec
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioCodec::Wav => "wav",
+            AudioCodec::Flac => "flac",
+        }
+    }
+
+    /// ffmpeg `-acodec` name
+    pub fn ffmpeg_codec(&self) -> &'static str {
+        match self {
+            AudioCodec::Wav => "pcm_s16le",
+            AudioCodec::Flac => "flac",
+        }
+    }
+}
+
+/// Zipf analysis configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AnalysisConfig {
+    /// Fewest tokens an anime needs for a meaningful Zipf fit. Fits on fewer
+    /// are flagged with a warning and left out of genre and studio fits
+    pub min_tokens: u64,
+
+    /// Include anime below `min_tokens` in genre and studio fits anyway
+    pub include_small_samples: bool,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            min_tokens: 5_000,
+            include_small_samples: false,
+        }
+    }
+}
+
+/// Tokenizer backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerBackend {
+    /// Morphological analysis with a MeCab dictionary (needs the `lindera` feature)
+    #[default]
+    Mecab,
+    /// Split on whitespace, for non-CJK content
+    Whitespace,
+}
+
+/// Tokenizer configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TokenizerConfig {
+    /// Which tokenizer to use
+    #[serde(default)]
+    pub backend: TokenizerBackend,
+
+    /// Path to a compiled MeCab dictionary (IPADIC layout), required by `mecab`
+    #[serde(default)]
+    pub dictionary: Option<String>,
+
+    /// Which tokens to leave out of the frequency tables
+    #[serde(default)]
+    pub filter: TokenFilterConfig,
+
+    /// Recap detection against the previous episode
+    #[serde(default)]
+    pub recap: RecapConfig,
+}
+
+/// Token filtering applied when counting word frequencies
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TokenFilterConfig {
+    /// Filter the frequency tables at all
+    pub enabled: bool,
+
+    /// Major parts of speech to drop (matched against the tokenizer's output)
+    pub exclude_pos: Vec<String>,
+
+    /// Words to drop, matched against the lemma
+    pub stopwords: Vec<String>,
+
+    /// File with more stopwords, one per line (`#` starts a comment)
+    pub stopwords_file: Option<String>,
+
+    /// Also write the unfiltered table next to the filtered one
+    pub keep_unfiltered: bool,
+}
+
+impl Default for TokenFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exclude_pos: ["助詞", "助動詞", "記号"].map(String::from).to_vec(),
+            stopwords: Vec::new(),
+            stopwords_file: None,
+            keep_unfiltered: false,
+        }
+    }
+}
+
+/// Detection of recaps: dialogue at the start of an episode that repeats the
+/// end of the previous one and would otherwise be counted twice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RecapConfig {
+    /// Strip recaps before tokenizing
+    pub enabled: bool,
+
+    /// Opening lines of an episode compared against the closing lines of the
+    /// previous one
+    pub lines: usize,
+
+    /// Characters per shingle
+    pub shingle_size: usize,
+
+    /// Jaccard similarity at which two lines count as the same
+    pub threshold: f64,
+
+    /// Fewest repeated lines that make a recap
+    pub min_lines: usize,
+}
+
+impl Default for RecapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lines: 40,
+            shingle_size: 3,
+            threshold: 0.6,
+            min_lines: 3,
+        }
+    }
 }
 
 /// Anthropic API configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct AnthropicConfig {
     /// Anthropic API key for Claude Haiku anime selection
     pub api_key: String,
+
+    /// Requests per minute allowed by the account's rate limit tier; the
+    /// anime selector never sends more, however many workers it runs
+    pub requests_per_minute: u32,
+
+    /// Times a failed Claude request (rate limited, overloaded, server or
+    /// network error) is retried
+    pub max_retries: u32,
+
+    /// Retry policy of failed Claude requests, in place of `max_retries`
+    /// (a `Retry-After` from Anthropic takes precedence over its delays)
+    pub retry: Option<RetryPolicy>,
+}
+
+impl AnthropicConfig {
+    /// Environment variables holding the API key when `api_key` is empty,
+    /// in order of preference
+    pub const API_KEY_ENV_VARS: [&'static str; 2] = ["GDA2025_ANTHROPIC_API_KEY", "ANTHROPIC_API_KEY"];
+
+    /// The API key to use: `api_key`, or else the first non-empty value of
+    /// `API_KEY_ENV_VARS`, as looked up by `var`
+    pub fn resolve_api_key(&self, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+        Some(self.api_key.trim())
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .or_else(|| {
+                Self::API_KEY_ENV_VARS
+                    .into_iter()
+                    .find_map(|name| var(name).filter(|value| !value.trim().is_empty()))
+            })
+    }
+
+    /// Retry policy of Claude requests: `retry`, or else `max_retries`
+    /// retries starting 5 seconds apart
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry.unwrap_or(RetryPolicy {
+            max_attempts: self.max_retries.saturating_add(1),
+            base_delay_ms: 5_000,
+            max_delay_ms: 60_000,
+            jitter: 0.0,
+        })
+    }
 }
 
 impl Default for AnthropicConfig {
     fn default() -> Self {
         Self {
             api_key: String::new(),
+            requests_per_minute: 50,
+            max_retries: 3,
+            retry: None,
         }
     }
 }
@@ -180,6 +854,7 @@ impl Default for DiskManagementConfig {
             resume_threshold_gb: 200,
             check_interval_seconds: 30,
             cache_duration_seconds: 5,
+            reconcile_interval_seconds: None,
             max_concurrent_downloads: 5,
             max_concurrent_transcriptions: 2,
             cleanup: CleanupConfig::default(),
@@ -194,6 +869,8 @@ impl Default for CleanupConfig {
             delete_audio_after_transcription: true,
             delete_transcript_after_tokenization: false,
             delete_tokens_after_analysis: false,
+            keep_video_sample_rate: 0,
+            keep_first_episode_video: false,
         }
     }
 }
@@ -204,6 +881,12 @@ impl Default for Config {
             data: DataConfig {
                 root_dir: "data".to_string(),
                 storage_dir: None,
+                videos_dir: None,
+                audio_dir: None,
+                transcripts_dir: None,
+                tokens_dir: None,
+                analysis_dir: None,
+                profile: None,
             },
             database: DatabaseConfig {
                 path: "jobs.db".to_string(),
@@ -214,6 +897,7 @@ impl Default for Config {
                 console: true,
                 file: true,
                 json_format: false,
+                module_levels: BTreeMap::new(),
             },
             mal_scraper: MalScraperConfig {
                 base_url: "https://api.jikan.moe/v4".to_string(),
@@ -229,17 +913,54 @@ impl Default for Config {
                 min_category_items: 50,
                 max_retries: 3,
                 retry_delay_ms: 1000,
+                retry: None,
+                request_timeout_seconds: default_request_timeout_seconds(),
+                slow_request_ms: default_slow_request_ms(),
+                include_explicit: false,
+                max_pages_per_category: None,
+                proxy: None,
+                priority: PriorityConfig::default(),
+                filter: AnimeFilterConfig::default(),
             },
             disk_management: DiskManagementConfig::default(),
             anthropic: AnthropicConfig::default(),
+            downloader: DownloaderConfig::default(),
+            pipeline: PipelineConfig::default(),
+            tokenizer: TokenizerConfig::default(),
+            transcriber: TranscriberConfig::default(),
+            analysis: AnalysisConfig::default(),
         }
     }
 }
 
+/// Config file used when no `--config` flag is given
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Profile names become a directory name, so only letters, digits, `-`, `_`
+/// and (not leading) `.` are allowed
+fn validate_profile_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid profile name '{}': use letters, digits, '-', '_' and '.' (not leading)",
+            name
+        );
+    }
+    Ok(())
+}
+
 impl Config {
     /// Load configuration from a TOML file
     ///
+    /// A missing file gives the default configuration; use `from_file_strict`
+    /// for paths the user named explicitly.
+    ///
     /// If the file doesn't exist, returns the default configuration.
+    ///
+    /// Either way, the `GDA_*_DIR` environment variables (see
+    /// `DataConfig::ENV_VARS`) override the per-category data directories.
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
 
@@ -248,14 +969,17 @@ impl Config {
                 path = %path.display(),
                 "Config file not found, using defaults"
             );
-            return Ok(Self::default());
+            let mut config = Self::default();
+            config.data.apply_env(|name| std::env::var(name).ok());
+            return Ok(config);
         }
 
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-        let config: Config = toml::from_str(&content)
+        let mut config: Config = toml::from_str(&content)
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        config.data.apply_env(|name| std::env::var(name).ok());
 
         tracing::info!(
             path = %path.display(),
@@ -265,6 +989,77 @@ impl Config {
         Ok(config)
     }
 
+    /// Load configuration from a TOML file that must exist
+    ///
+    /// For paths given explicitly (e.g. via `--config`), where a missing file
+    /// means a wrong path rather than "no config".
+    pub fn from_file_strict(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            anyhow::bail!("Config file not found: {}", path.display());
+        }
+
+        Self::from_file(path)
+    }
+
+    /// Load the config named by a `--config` flag, or `DEFAULT_CONFIG_PATH`
+    /// (falling back to defaults if it doesn't exist) when none was given
+    pub fn from_arg(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file_strict(path),
+            None => Self::from_file(DEFAULT_CONFIG_PATH),
+        }
+    }
+
+    /// Load the config as `from_arg` does, then switch to `profile` (a
+    /// `--profile` flag) if one is given
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+        let mut config = Self::from_arg(path)?;
+        if let Some(profile) = profile {
+            config.set_profile(profile)?;
+        }
+        Ok(config)
+    }
+
+    /// Keep the data of corpus profile `name` apart from every other
+    /// profile (see `DataConfig::profile`)
+    pub fn set_profile(&mut self, name: &str) -> Result<()> {
+        validate_profile_name(name)?;
+        self.data.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// JSON Schema of the config file, for editor autocomplete and validation
+    /// (`pipeline config schema`)
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Config)).expect("config schema serializes to JSON")
+    }
+
+    /// Check the TOML config `content` against `json_schema`.
+    ///
+    /// Unlike loading the config, which stops at the first bad value, the
+    /// error lists every value that does not match, by its path in the file.
+    pub fn check_schema(content: &str) -> Result<()> {
+        let document: toml::Value = toml::from_str(content).context("Failed to parse config as TOML")?;
+        let instance = serde_json::to_value(&document).context("Failed to convert config to JSON")?;
+
+        let schema = Self::json_schema();
+        let validator = jsonschema::JSONSchema::compile(&schema)
+            .map_err(|e| anyhow::anyhow!("Invalid config schema: {}", e))?;
+        if let Err(errors) = validator.validate(&instance) {
+            let errors: Vec<String> = errors
+                .map(|e| match e.instance_path.to_string() {
+                    path if path.is_empty() => e.to_string(),
+                    path => format!("{}: {}", path, e),
+                })
+                .collect();
+            anyhow::bail!("Config does not match the schema:\n  {}", errors.join("\n  "));
+        }
+
+        Ok(())
+    }
+
     /// Load configuration from a TOML file or create default if not found
     pub fn load_or_default(path: impl AsRef<Path>) -> Self {
         Self::from_file(path).unwrap_or_else(|e| {
@@ -291,9 +1086,106 @@ impl Config {
         Ok(())
     }
 
-    /// Get the absolute path for the data directory
+    /// Check settings that parse fine but would make the workers misbehave
+    pub fn validate(&self) -> Result<()> {
+        let disk = &self.disk_management;
+        if disk.pause_threshold_gb > disk.hard_limit_gb {
+            anyhow::bail!(
+                "disk_management.pause_threshold_gb ({}) exceeds hard_limit_gb ({})",
+                disk.pause_threshold_gb,
+                disk.hard_limit_gb
+            );
+        }
+        if disk.resume_threshold_gb > disk.pause_threshold_gb {
+            anyhow::bail!(
+                "disk_management.resume_threshold_gb ({}) exceeds pause_threshold_gb ({})",
+                disk.resume_threshold_gb,
+                disk.pause_threshold_gb
+            );
+        }
+        if self.downloader.min_download_confidence == Confidence::NoCandidates {
+            anyhow::bail!(
+                "downloader.min_download_confidence must be high, medium or low (got '{}')",
+                self.downloader.min_download_confidence
+            );
+        }
+        let recap = &self.tokenizer.recap;
+        if recap.shingle_size == 0 {
+            anyhow::bail!("tokenizer.recap.shingle_size must be greater than 0");
+        }
+        if !(recap.threshold > 0.0 && recap.threshold <= 1.0) {
+            anyhow::bail!("tokenizer.recap.threshold must be in (0, 1] (got {})", recap.threshold);
+        }
+        if self.downloader.estimated_bitrate_kbps == 0 {
+            anyhow::bail!("downloader.estimated_bitrate_kbps must be greater than 0");
+        }
+        if self.mal_scraper.request_timeout_seconds == 0 {
+            anyhow::bail!("mal_scraper.request_timeout_seconds must be greater than 0");
+        }
+        let filter = &self.mal_scraper.filter;
+        if let (Some(min), Some(max)) = (filter.min_episodes, filter.max_episodes) {
+            if min > max {
+                anyhow::bail!("mal_scraper.filter.min_episodes ({}) exceeds max_episodes ({})", min, max);
+            }
+        }
+        let priority = &self.mal_scraper.priority;
+        if !(priority.popularity_weight >= 0.0 && priority.score_weight >= 0.0) {
+            anyhow::bail!("mal_scraper.priority weights must not be negative");
+        }
+        if self.anthropic.requests_per_minute == 0 {
+            anyhow::bail!("anthropic.requests_per_minute must be greater than 0");
+        }
+        if self.transcriber.chunk_seconds == Some(0) {
+            anyhow::bail!("transcriber.chunk_seconds must be greater than 0");
+        }
+        for stage in StageConcurrency::STAGES {
+            if self.pipeline.concurrency.limit(stage) == Some(0) {
+                anyhow::bail!("pipeline.concurrency.{} must be greater than 0", stage);
+            }
+        }
+        if let Some(profile) = &self.data.profile {
+            validate_profile_name(profile)?;
+            if Path::new(&self.database.path).is_absolute() {
+                anyhow::bail!(
+                    "database.path must be relative to the data directory with a profile (got {}), \
+                     or every profile would share it",
+                    self.database.path
+                );
+            }
+        }
+        for (section, retry) in [
+            ("mal_scraper.retry", &self.mal_scraper.retry),
+            ("anthropic.retry", &self.anthropic.retry),
+            ("pipeline.retry", &self.pipeline.retry),
+        ] {
+            if let Some(retry) = retry {
+                retry.validate(section)?;
+            }
+        }
+        let filter = &self.pipeline.anime_filter;
+        if let (Some(min), Some(max)) = (filter.min_year, filter.max_year) {
+            if min > max {
+                anyhow::bail!("pipeline.anime_filter.min_year ({}) exceeds max_year ({})", min, max);
+            }
+        }
+        if !self.transcriber.transcript_formats.contains(&TranscriptFormat::Txt) {
+            anyhow::bail!("transcriber.transcript_formats must include \"txt\", which the tokenizer reads");
+        }
+        Ok(())
+    }
+
+    /// Get the absolute path for the data directory (the profile's
+    /// directory under root_dir when a profile is set)
     pub fn data_dir(&self) -> PathBuf {
-        PathBuf::from(&self.data.root_dir)
+        self.profile_dir(PathBuf::from(&self.data.root_dir))
+    }
+
+    /// `base/profiles/<name>` for the active profile, or `base` without one
+    fn profile_dir(&self, base: PathBuf) -> PathBuf {
+        match &self.data.profile {
+            Some(profile) => base.join("profiles").join(profile),
+            None => base,
+        }
     }
 
     /// Get the absolute path for the database file
@@ -308,21 +1200,29 @@ impl Config {
 
     /// Get the absolute path for the log directory
     pub fn log_dir(&self) -> PathBuf {
-        let log_path = Path::new(&self.logging.log_dir);
-        if log_path.is_absolute() {
-            log_path.to_path_buf()
-        } else {
-            self.data_dir().join(log_path)
-        }
+        self.data_dir().join(self.layout_dir(&self.logging.log_dir))
     }
 
     /// Get the absolute path for the cache directory
     pub fn cache_dir(&self) -> PathBuf {
-        let cache_path = Path::new(&self.mal_scraper.cache.cache_dir);
-        if cache_path.is_absolute() {
-            cache_path.to_path_buf()
+        self.data_dir().join(self.layout_dir(&self.mal_scraper.cache.cache_dir))
+    }
+
+    /// Get the absolute path for the transcriber's temporary audio directory,
+    /// if one is configured
+    pub fn transcriber_temp_dir(&self) -> Option<PathBuf> {
+        self.transcriber.temp_dir.as_ref().map(|dir| self.data_dir().join(self.layout_dir(dir)))
+    }
+
+    /// A configured directory as given when relative (it is then joined to
+    /// the profile's data or storage directory); an absolute one is nested
+    /// under it as `<dir>/profiles/<name>` so profiles don't share it
+    fn layout_dir(&self, dir: &str) -> PathBuf {
+        let path = PathBuf::from(dir);
+        if path.is_absolute() {
+            self.profile_dir(path)
         } else {
-            self.data_dir().join(cache_path)
+            path
         }
     }
 
@@ -331,14 +1231,48 @@ impl Config {
     pub fn storage_dir(&self) -> PathBuf {
         self.data.storage_dir
             .as_ref()
-            .map(PathBuf::from)
+            .map(|dir| self.profile_dir(PathBuf::from(dir)))
             .unwrap_or_else(|| self.data_dir())
     }
+
+    /// Get the per-category layout, with the `[data]` directory overrides
+    /// and the cache and log directories configured elsewhere
+    pub fn path_layout(&self) -> PathLayout {
+        let default = PathLayout::default();
+        let dir = |dir: &Option<String>, default: PathBuf| dir.as_ref().map_or(default, |dir| self.layout_dir(dir));
+        PathLayout {
+            videos: dir(&self.data.videos_dir, default.videos),
+            audio: dir(&self.data.audio_dir, default.audio),
+            transcripts: dir(&self.data.transcripts_dir, default.transcripts),
+            tokens: dir(&self.data.tokens_dir, default.tokens),
+            analysis: dir(&self.data.analysis_dir, default.analysis),
+            cache: self.layout_dir(&self.mal_scraper.cache.cache_dir),
+            logs: self.layout_dir(&self.logging.log_dir),
+        }
+    }
+
+    /// Get the per-stage concurrency limits: `[pipeline.concurrency]`, with
+    /// downloads and transcriptions it leaves unset limited to
+    /// `max_concurrent_downloads`/`max_concurrent_transcriptions`
+    pub fn stage_concurrency(&self) -> StageConcurrency {
+        let concurrency = &self.pipeline.concurrency;
+        StageConcurrency {
+            downloading: concurrency.downloading.or(Some(self.disk_management.max_concurrent_downloads)),
+            transcribing: concurrency.transcribing.or(Some(self.disk_management.max_concurrent_transcriptions)),
+            ..concurrency.clone()
+        }
+    }
+
+    /// Get the data paths for the configured root, storage and layout
+    pub fn data_paths(&self) -> DataPaths {
+        DataPaths::new_with_layout(self.data_dir(), self.storage_dir(), self.path_layout())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use tempfile::TempDir;
 
     #[test]
@@ -370,6 +1304,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_check_schema() -> Result<()> {
+        let default = toml::to_string_pretty(&Config::default())?;
+        Config::check_schema(&default)?;
+        Config::check_schema(include_str!("../../../config.example.toml"))?;
+
+        // Sections with defaults may be left out entirely
+        Config::check_schema(&default.replace("[tokenizer]", "[unused]"))?;
+
+        let wrong_types = default
+            .replace("hard_limit_gb = 250", "hard_limit_gb = \"250GB\"")
+            .replace("root_dir = \"data\"", "root_dir = 5");
+        let err = Config::check_schema(&wrong_types).unwrap_err().to_string();
+        assert!(err.contains("/disk_management/hard_limit_gb"), "{}", err);
+        assert!(err.contains("/data/root_dir"), "{}", err);
+
+        let missing = default.replace("[database]\npath = \"jobs.db\"", "[database]");
+        assert!(Config::check_schema(&missing).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_load_nonexistent_config() {
         let config = Config::from_file("nonexistent.toml").unwrap();
@@ -377,6 +1333,26 @@ mod tests {
         assert_eq!(config.data.root_dir, "data");
     }
 
+    #[test]
+    fn test_explicit_config_must_exist() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let missing = temp_dir.path().join("typo.toml");
+
+        let err = Config::from_arg(Some(&missing)).unwrap_err();
+        assert!(err.to_string().contains("typo.toml"), "{}", err);
+        assert!(Config::from_file_strict(&missing).is_err());
+
+        let config_path = temp_dir.path().join("config.toml");
+        Config::default().save(&config_path)?;
+        assert_eq!(Config::from_arg(Some(&config_path))?.data.root_dir, "data");
+
+        // Without --config, a missing default config still falls back
+        assert!(!Path::new(DEFAULT_CONFIG_PATH).exists());
+        assert_eq!(Config::from_arg(None)?.data.root_dir, "data");
+
+        Ok(())
+    }
+
     #[test]
     fn test_path_resolution() {
         let config = Config::default();
@@ -390,4 +1366,173 @@ mod tests {
         let cache_dir = config.cache_dir();
         assert!(cache_dir.ends_with("data/cache"));
     }
+
+    #[test]
+    fn test_profiles_resolve_to_separate_paths() -> Result<()> {
+        let mut config = Config::default();
+        config.data.storage_dir = Some("/storage".to_string());
+        config.transcriber.temp_dir = Some("tmp".to_string());
+
+        let paths = |config: &Config, profile: &str| -> Result<Vec<PathBuf>> {
+            let mut config = config.clone();
+            config.set_profile(profile)?;
+            config.validate()?;
+            let data_paths = config.data_paths();
+            Ok(vec![
+                config.data_dir(),
+                config.storage_dir(),
+                config.database_path(),
+                config.log_dir(),
+                config.cache_dir(),
+                config.transcriber_temp_dir().unwrap(),
+                data_paths.videos_base(),
+                data_paths.audio_base(),
+                data_paths.transcripts_base(),
+                data_paths.tokens_base(),
+                data_paths.analysis_base(),
+                data_paths.root().join(&data_paths.layout().cache),
+                data_paths.root().join(&data_paths.layout().logs),
+            ])
+        };
+        let assert_separate = |tv: &[PathBuf], shounen: &[PathBuf]| {
+            // No path of one profile lies inside (or is) a path of the other
+            for a in tv {
+                for b in shounen {
+                    assert!(!a.starts_with(b) && !b.starts_with(a), "{} overlaps {}", a.display(), b.display());
+                }
+            }
+        };
+        let tv = paths(&config, "2020s-tv")?;
+        let shounen = paths(&config, "all_shounen")?;
+
+        assert_eq!(tv[0], Path::new("data/profiles/2020s-tv"));
+        assert_eq!(tv[1], Path::new("/storage/profiles/2020s-tv"));
+        assert_eq!(tv[2], Path::new("data/profiles/2020s-tv/jobs.db"));
+        for path in &tv[2..] {
+            assert!(path.starts_with(&tv[0]) || path.starts_with(&tv[1]), "{}", path.display());
+        }
+        assert_separate(&tv, &shounen);
+
+        // Absolute overrides are nested under each profile
+        let mut absolute = config.clone();
+        absolute.data.videos_dir = Some("/mnt/videos".to_string());
+        absolute.data.audio_dir = Some("/mnt/audio".to_string());
+        absolute.data.transcripts_dir = Some("/mnt/transcripts".to_string());
+        absolute.data.tokens_dir = Some("/mnt/tokens".to_string());
+        absolute.data.analysis_dir = Some("/mnt/analysis".to_string());
+        absolute.logging.log_dir = "/var/log/gda".to_string();
+        absolute.mal_scraper.cache.cache_dir = "/var/cache/gda".to_string();
+        absolute.transcriber.temp_dir = Some("/dev/shm/gda".to_string());
+        let tv = paths(&absolute, "2020s-tv")?;
+        let shounen = paths(&absolute, "all_shounen")?;
+        assert_eq!(tv[3], Path::new("/var/log/gda/profiles/2020s-tv"));
+        assert_eq!(tv[5], Path::new("/dev/shm/gda/profiles/2020s-tv"));
+        assert_eq!(tv[6], Path::new("/mnt/videos/profiles/2020s-tv"));
+        assert_eq!(tv[11], Path::new("/var/cache/gda/profiles/2020s-tv"));
+        assert_separate(&tv, &shounen);
+        assert_eq!(absolute.data_paths().videos_base(), Path::new("/mnt/videos"));
+
+        assert!(config.clone().set_profile("../other").is_err());
+        assert!(config.clone().set_profile("").is_err());
+        config.database.path = "/var/lib/jobs.db".to_string();
+        config.data.profile = Some("2020s-tv".to_string());
+        assert!(config.validate().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_dir_overrides() {
+        let mut config = Config::default();
+        config.data.storage_dir = Some("/storage".to_string());
+
+        let default = config.data_paths();
+        assert_eq!(default.videos_base(), Path::new("/storage/videos"));
+        assert_eq!(default.transcripts_base(), Path::new("data/transcripts"));
+
+        // An override redirects only its own category
+        config.data.transcripts_dir = Some("/durable/transcripts".to_string());
+        let paths = config.data_paths();
+        assert_eq!(paths.transcripts_base(), Path::new("/durable/transcripts"));
+        assert_eq!(paths.transcript_dir(5114), Path::new("/durable/transcripts/5114"));
+        assert_eq!(paths.videos_base(), default.videos_base());
+        assert_eq!(paths.audio_base(), default.audio_base());
+        assert_eq!(paths.tokens_base(), default.tokens_base());
+        assert_eq!(paths.analysis_base(), default.analysis_base());
+
+        // Environment variables win over the config file; empty ones are ignored
+        let env = HashMap::from([("GDA_VIDEOS_DIR", "/scratch/videos"), ("GDA_AUDIO_DIR", "")]);
+        config.data.audio_dir = Some("wav".to_string());
+        config.data.apply_env(|name| env.get(name).map(|value| value.to_string()));
+        let paths = config.data_paths();
+        assert_eq!(paths.videos_base(), Path::new("/scratch/videos"));
+        assert_eq!(paths.audio_base(), Path::new("data/wav"));
+        assert_eq!(paths.transcripts_base(), Path::new("/durable/transcripts"));
+        assert_eq!(paths.tokens_base(), default.tokens_base());
+    }
+
+    #[test]
+    fn test_anime_filter() {
+        let series_only = AnimeFilterConfig {
+            min_episodes: Some(2),
+            types: vec!["TV".to_string()],
+            ..Default::default()
+        };
+        assert!(series_only.is_active());
+        assert!(series_only.matches(Some("TV"), Some(12)));
+        assert!(series_only.matches(Some("tv"), Some(2)));
+        // A movie fails both the episode count and the type
+        assert!(!series_only.matches(Some("Movie"), Some(1)));
+        assert!(!series_only.matches(Some("TV"), Some(1)));
+        assert!(!series_only.matches(Some("OVA"), Some(6)));
+        assert!(!series_only.matches(None, Some(12)));
+        // Still airing, total unknown
+        assert!(series_only.matches(Some("TV"), None));
+
+        let short = AnimeFilterConfig {
+            max_episodes: Some(26),
+            ..Default::default()
+        };
+        assert!(short.matches(Some("Movie"), Some(1)));
+        assert!(!short.matches(Some("TV"), Some(500)));
+
+        assert!(!AnimeFilterConfig::default().is_active());
+        assert!(AnimeFilterConfig::default().matches(None, None));
+    }
+
+    #[test]
+    fn test_keep_video_sample() {
+        let kept = |cleanup: &CleanupConfig, jobs: &[(i64, u32)]| {
+            jobs.iter()
+                .filter(|(id, episode)| cleanup.keep_video_sample(*id, *episode))
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>()
+        };
+        // Jobs 1..=45 for episodes 1..=15 of three anime
+        let jobs: Vec<(i64, u32)> = (1..=45).map(|id| (id, (id as u32 - 1) % 15 + 1)).collect();
+
+        assert!(kept(&CleanupConfig::default(), &jobs).is_empty());
+
+        let every_20th = CleanupConfig {
+            keep_video_sample_rate: 20,
+            ..Default::default()
+        };
+        assert_eq!(kept(&every_20th, &jobs), [20, 40]);
+
+        let first_episodes = CleanupConfig {
+            keep_first_episode_video: true,
+            ..Default::default()
+        };
+        assert_eq!(kept(&first_episodes, &jobs), [1, 16, 31]);
+
+        let both = CleanupConfig {
+            keep_video_sample_rate: 20,
+            keep_first_episode_video: true,
+            ..Default::default()
+        };
+        assert_eq!(kept(&both, &jobs), [1, 16, 20, 31, 40]);
+
+        // The same decision every time
+        assert_eq!(kept(&both, &jobs), kept(&both.clone(), &jobs));
+    }
 }
@@ -3,13 +3,22 @@
 //! This module handles all database connections, schema creation, and migrations.
 
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 use std::path::Path;
 use tracing::{debug, info};
 
+/// JSON array columns of the anime table that are mirrored, one row per
+/// name, in `anime_<column>` lookup tables
+pub(crate) const CLASSIFICATION_COLUMNS: [&str; 5] = ["genres", "explicit_genres", "themes", "demographics", "studios"];
+
+/// The tables, columns and indexes every database has
+const SCHEMA_SQL: &str = concat!(include_str!("../schema.sql"), include_str!("../classifications.sql"));
+
 /// Database connection wrapper
 pub struct Database {
     conn: Connection,
+    /// Opened with `open_read_only`
+    read_only: bool,
 }
 
 impl Database {
@@ -27,7 +36,7 @@ impl Database {
         conn.execute("PRAGMA foreign_keys = ON", [])
             .context("Failed to enable foreign keys")?;
 
-        let mut db = Self { conn };
+        let mut db = Self { conn, read_only: false };
 
         if is_new {
             info!("Creating new database schema");
@@ -36,14 +45,39 @@ impl Database {
             debug!("Database already exists");
             // Run migrations for existing databases
             db.run_migrations()?;
+            db.verify_schema()
+                .with_context(|| format!("Database at {} does not match schema.sql", path.display()))?;
         }
 
         Ok(db)
     }
 
+    /// Open an existing database read-only
+    ///
+    /// No schema is created and no migrations are run, so this is safe to use
+    /// from monitoring tools while the workers are writing. Writes are refused
+    /// by SQLite (the connection is read-only and `query_only`), and
+    /// `conn_mut` panics, so a `JobQueue` on this handle can only be read.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        debug!(path = %path.display(), "Opening database read-only");
+
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open database read-only at {}", path.display()))?;
+
+        conn.pragma_update(None, "query_only", true)
+            .context("Failed to make database connection query-only")?;
+
+        Ok(Self { conn, read_only: true })
+    }
+
     /// Create the database schema
     fn create_schema(&mut self) -> Result<()> {
-        self.conn.execute_batch(include_str!("../schema.sql"))
+        self.conn.execute_batch(SCHEMA_SQL)
             .context("Failed to create database schema")?;
 
         info!("Database schema created successfully");
@@ -56,10 +90,20 @@ impl Database {
     }
 
     /// Get a mutable reference to the underlying connection
+    ///
+    /// # Panics
+    ///
+    /// If the database was opened with `open_read_only`.
     pub fn conn_mut(&mut self) -> &mut Connection {
+        assert!(!self.read_only, "Database was opened read-only and cannot be written to");
         &mut self.conn
     }
 
+    /// Whether the database was opened with `open_read_only`
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Check if a table exists
     pub fn table_exists(&self, table_name: &str) -> Result<bool> {
         let count: i64 = self.conn.query_row(
@@ -70,6 +114,101 @@ impl Database {
         Ok(count > 0)
     }
 
+    /// Check if an index exists
+    pub fn index_exists(&self, index_name: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='index' AND name=?1",
+            [index_name],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Check if a column exists on a table
+    pub fn column_exists(&self, table_name: &str, column_name: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name=?2",
+            [table_name, column_name],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// List tables and columns from `schema.sql` that this database lacks
+    ///
+    /// Returns entries like `table related_anime` or `column jobs.transcript_model`;
+    /// an up-to-date database returns an empty list.
+    pub fn schema_differences(&self) -> Result<Vec<String>> {
+        let reference = Connection::open_in_memory()?;
+        reference.execute_batch(SCHEMA_SQL)
+            .context("Failed to build reference schema")?;
+
+        let mut stmt = reference.prepare(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut missing = Vec::new();
+        for table in tables {
+            if !self.table_exists(&table)? {
+                missing.push(format!("table {}", table));
+                continue;
+            }
+
+            let mut stmt = reference.prepare("SELECT name FROM pragma_table_info(?1)")?;
+            let columns = stmt
+                .query_map([&table], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for column in columns {
+                if !self.column_exists(&table, &column)? {
+                    missing.push(format!("column {}.{}", table, column));
+                }
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// Fail with the list of `schema_differences` if there are any
+    ///
+    /// Run by `open` after the migrations, so a database that drifted from
+    /// `schema.sql` is reported up front instead of as confusing errors from
+    /// the first query that touches a missing column.
+    pub fn verify_schema(&self) -> Result<()> {
+        let missing = self.schema_differences()?;
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "missing {}; add them (e.g. with ALTER TABLE ... ADD COLUMN) or rebuild the database from schema.sql",
+                missing.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// The `schema_differences` that `open` would leave after migrating the
+    /// database at `path`, found without writing to it
+    ///
+    /// The migrations run on an in-memory copy of the database's schema (not
+    /// its rows), so an empty list means `open` will succeed.
+    pub fn schema_differences_after_migrations(path: impl AsRef<Path>) -> Result<Vec<String>> {
+        let source = Self::open_read_only(path)?;
+        let mut stmt = source.conn.prepare(
+            "SELECT sql FROM sqlite_master WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' ORDER BY rowid",
+        )?;
+        let statements = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut copy = Self { conn: Connection::open_in_memory()?, read_only: false };
+        for sql in statements {
+            copy.conn.execute_batch(&sql).context("Failed to copy the database schema")?;
+        }
+        copy.run_migrations()?;
+        copy.schema_differences()
+    }
+
     /// Get the database version (from user_version pragma)
     pub fn get_version(&self) -> Result<i32> {
         let version: i32 = self.conn.query_row(
@@ -117,9 +256,222 @@ impl Database {
             info!("Migration completed: anime_selection_cache table created");
         }
 
+        for (column, definition) in [
+            ("mal_episodes", "INTEGER"),
+            ("selected_episodes", "INTEGER"),
+            ("episode_match", "TEXT CHECK(episode_match IN ('exact', 'close', 'acceptable', 'mismatch', 'unknown', NULL))"),
+        ] {
+            if !self.column_exists("anime_selection_cache", column)? {
+                info!(column, "Running migration: Adding anime_selection_cache episode column");
+                self.conn.execute_batch(&format!("ALTER TABLE anime_selection_cache ADD COLUMN {} {};", column, definition))
+                    .with_context(|| format!("Failed to add {} column", column))?;
+                info!(column, "Migration completed: anime_selection_cache episode column added");
+            }
+        }
+
+        if !self.index_exists("idx_selection_cache_episode_match")? {
+            info!("Running migration: Creating anime_selection_cache episode_match index");
+            self.conn.execute_batch(
+                "CREATE INDEX IF NOT EXISTS idx_selection_cache_episode_match ON anime_selection_cache(episode_match);"
+            ).context("Failed to create idx_selection_cache_episode_match index")?;
+            info!("Migration completed: anime_selection_cache episode_match index created");
+        }
+
+        let selection_cache_sql: String = self.conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type='table' AND name='anime_selection_cache'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !selection_cache_sql.contains("'no_candidates'") {
+            info!("Running migration: Rebuilding anime_selection_cache to accept no_candidates");
+            self.rebuild_selection_cache()
+                .context("Failed to rebuild anime_selection_cache")?;
+            info!("Migration completed: anime_selection_cache rebuilt");
+        }
+
+        if !self.table_exists("related_anime")? {
+            info!("Running migration: Creating related_anime table");
+            self.conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS related_anime (
+                    parent_mal_id INTEGER NOT NULL,
+                    child_mal_id INTEGER NOT NULL,
+                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY (parent_mal_id, child_mal_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_related_anime_child
+                ON related_anime(child_mal_id);"
+            ).context("Failed to create related_anime table")?;
+            info!("Migration completed: related_anime table created");
+        }
+
+        if !self.column_exists("jobs", "transcript_quality")? {
+            info!("Running migration: Adding jobs.transcript_quality column");
+            self.conn.execute_batch("ALTER TABLE jobs ADD COLUMN transcript_quality REAL;")
+                .context("Failed to add transcript_quality column")?;
+            info!("Migration completed: jobs.transcript_quality column added");
+        }
+
+        if !self.column_exists("jobs", "transcript_model")? {
+            info!("Running migration: Adding jobs.transcript_model column");
+            self.conn.execute_batch("ALTER TABLE jobs ADD COLUMN transcript_model TEXT;")
+                .context("Failed to add transcript_model column")?;
+            info!("Migration completed: jobs.transcript_model column added");
+        }
+
+        if !self.column_exists("jobs", "next_attempt_at")? {
+            info!("Running migration: Adding jobs.next_attempt_at column");
+            self.conn.execute_batch("ALTER TABLE jobs ADD COLUMN next_attempt_at TIMESTAMP;")
+                .context("Failed to add next_attempt_at column")?;
+            info!("Migration completed: jobs.next_attempt_at column added");
+        }
+
+        if !self.column_exists("jobs", "recap_lines")? {
+            info!("Running migration: Adding jobs.recap_lines column");
+            self.conn.execute_batch("ALTER TABLE jobs ADD COLUMN recap_lines INTEGER;")
+                .context("Failed to add recap_lines column")?;
+            info!("Migration completed: jobs.recap_lines column added");
+        }
+
+        if !self.column_exists("jobs", "whisper_version")? {
+            info!("Running migration: Adding jobs.whisper_version column");
+            self.conn.execute_batch("ALTER TABLE jobs ADD COLUMN whisper_version TEXT;")
+                .context("Failed to add whisper_version column")?;
+            info!("Migration completed: jobs.whisper_version column added");
+        }
+
+        if !self.column_exists("anime", "zipf_exponent")? {
+            info!("Running migration: Adding anime Zipf analysis columns");
+            self.conn.execute_batch(
+                "ALTER TABLE anime ADD COLUMN zipf_exponent REAL;
+                ALTER TABLE anime ADD COLUMN zipf_r_squared REAL;
+                ALTER TABLE anime ADD COLUMN total_tokens INTEGER;
+                CREATE INDEX IF NOT EXISTS idx_anime_zipf_exponent ON anime(zipf_exponent);"
+            ).context("Failed to add anime Zipf analysis columns")?;
+            info!("Migration completed: anime Zipf analysis columns added");
+        }
+
+        if !self.column_exists("anime", "zipf_warning")? {
+            info!("Running migration: Adding anime.zipf_warning column");
+            self.conn.execute_batch("ALTER TABLE anime ADD COLUMN zipf_warning TEXT;")
+                .context("Failed to add zipf_warning column")?;
+            info!("Migration completed: anime.zipf_warning column added");
+        }
+
+        if !self.column_exists("anime", "opening_themes")? {
+            info!("Running migration: Adding anime theme song columns");
+            self.conn.execute_batch(
+                "ALTER TABLE anime ADD COLUMN opening_themes TEXT;
+                ALTER TABLE anime ADD COLUMN ending_themes TEXT;"
+            ).context("Failed to add anime theme song columns")?;
+            info!("Migration completed: anime theme song columns added");
+        }
+
+        if !self.column_exists("anime_selection_cache", "manual")? {
+            info!("Running migration: Adding anime_selection_cache.manual column");
+            self.conn.execute_batch("ALTER TABLE anime_selection_cache ADD COLUMN manual INTEGER NOT NULL DEFAULT 0;")
+                .context("Failed to add manual column")?;
+            info!("Migration completed: anime_selection_cache.manual column added");
+        }
+
+        if !self.column_exists("jobs", "transcript_deleted")? {
+            info!("Running migration: Adding jobs transcript_deleted and tokens_deleted columns");
+            self.conn.execute_batch(
+                "ALTER TABLE jobs ADD COLUMN transcript_deleted BOOLEAN DEFAULT 0;
+                ALTER TABLE jobs ADD COLUMN tokens_deleted BOOLEAN DEFAULT 0;"
+            ).context("Failed to add transcript_deleted and tokens_deleted columns")?;
+            info!("Migration completed: jobs transcript_deleted and tokens_deleted columns added");
+        }
+
+        if !self.index_exists("idx_jobs_mal_id_episode")? {
+            info!("Running migration: Creating jobs (mal_id, episode) index");
+            self.conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_jobs_mal_id_episode ON jobs(mal_id, episode);")
+                .context("Failed to create idx_jobs_mal_id_episode index")?;
+            info!("Migration completed: jobs (mal_id, episode) index created");
+        }
+
+        if !self.table_exists("anime_genres")? {
+            info!("Running migration: Creating anime classification lookup tables");
+            let rows = self.backfill_classifications()
+                .context("Failed to create anime classification lookup tables")?;
+            info!(rows, "Migration completed: anime classification lookup tables created and backfilled");
+        }
+
+        Ok(())
+    }
+
+    /// Recreate `anime_selection_cache` as `schema.sql` defines it, keeping
+    /// its rows, all in one transaction
+    ///
+    /// SQLite cannot change a CHECK constraint in place, so tables from before
+    /// the `no_candidates` confidence are copied into a new table instead.
+    /// Columns the old table lacks get their defaults.
+    fn rebuild_selection_cache(&mut self) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute_batch(
+            "CREATE TABLE anime_selection_cache_new (
+                mal_id INTEGER PRIMARY KEY,
+                anime_title TEXT NOT NULL,
+                search_query TEXT NOT NULL,
+                selected_index INTEGER NOT NULL,
+                selected_title TEXT NOT NULL,
+                confidence TEXT NOT NULL CHECK(confidence IN ('high', 'medium', 'low', 'no_candidates')),
+                reason TEXT,
+                mal_episodes INTEGER,
+                selected_episodes INTEGER,
+                episode_match TEXT CHECK(episode_match IN ('exact', 'close', 'acceptable', 'mismatch', 'unknown', NULL)),
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                manual INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+            );",
+        )?;
+
+        // Only the columns both tables have
+        let columns = {
+            let mut stmt = tx.prepare(
+                "SELECT name FROM pragma_table_info('anime_selection_cache')
+                 WHERE name IN (SELECT name FROM pragma_table_info('anime_selection_cache_new'))",
+            )?;
+            let names = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            names.join(", ")
+        };
+        tx.execute_batch(&format!(
+            "INSERT INTO anime_selection_cache_new ({columns}) SELECT {columns} FROM anime_selection_cache;
+             DROP TABLE anime_selection_cache;
+             ALTER TABLE anime_selection_cache_new RENAME TO anime_selection_cache;
+             CREATE INDEX IF NOT EXISTS idx_selection_cache_confidence ON anime_selection_cache(confidence);
+             CREATE INDEX IF NOT EXISTS idx_selection_cache_episode_match ON anime_selection_cache(episode_match);"
+        ))?;
+        tx.commit()?;
         Ok(())
     }
 
+    /// Create the `anime_<column>` lookup tables and fill them from the JSON
+    /// arrays of every saved anime, all in one transaction
+    ///
+    /// Returns the number of rows written. Anime whose column is not valid
+    /// JSON get no rows, rather than failing the migration.
+    fn backfill_classifications(&mut self) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        tx.execute_batch(include_str!("../classifications.sql"))?;
+        let mut rows = 0;
+        for column in CLASSIFICATION_COLUMNS {
+            rows += tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO anime_{column} (mal_id, name)
+                     SELECT anime.mal_id, names.value
+                     FROM (SELECT mal_id, {column} FROM anime WHERE json_valid({column})) AS anime,
+                          json_each(anime.{column}) AS names
+                     WHERE names.type = 'text'"
+                ),
+                [],
+            )?;
+        }
+        tx.commit()?;
+        Ok(rows)
+    }
+
     /// Begin a transaction
     pub fn begin_transaction(&mut self) -> Result<rusqlite::Transaction<'_>> {
         self.conn.transaction()
@@ -143,10 +495,232 @@ mod tests {
         // Check that tables were created
         assert!(db.table_exists("anime")?);
         assert!(db.table_exists("jobs")?);
+        assert!(db.column_exists("jobs", "transcript_quality")?);
+        assert!(!db.column_exists("jobs", "no_such_column")?);
+        assert!(db.index_exists("idx_jobs_mal_id_episode")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_differences() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        // Fresh databases match the schema
+        let db = Database::open(temp_dir.path().join("test.db"))?;
+        assert!(db.schema_differences()?.is_empty());
+
+        // A stripped-down database reports what is missing
+        let conn = Connection::open(temp_dir.path().join("old.db"))?;
+        conn.execute_batch("CREATE TABLE jobs (id INTEGER PRIMARY KEY, anime_id INTEGER);")?;
+        let db = Database { conn, read_only: false };
+        let missing = db.schema_differences()?;
+        assert!(missing.contains(&"table anime".to_string()));
+        assert!(missing.contains(&"column jobs.transcript_quality".to_string()));
+        assert!(!missing.contains(&"column jobs.anime_id".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_verifies_schema() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("test.db");
+        Database::open(&db_path)?.verify_schema()?;
+
+        // A column that no migration adds back
+        let conn = Connection::open(&db_path)?;
+        conn.execute_batch("ALTER TABLE anime DROP COLUMN rating;")?;
+        drop(conn);
+
+        let err = Database::open(&db_path).err().expect("drifted database opened");
+        let message = format!("{:#}", err);
+        assert!(message.contains("does not match schema.sql"), "{}", message);
+        assert!(message.contains("column anime.rating"), "{}", message);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_differences_after_migrations() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("test.db");
+        Database::open(&db_path)?;
+
+        // A column the migrations add back, which the check leaves missing
+        let conn = Connection::open(&db_path)?;
+        conn.execute_batch("ALTER TABLE jobs DROP COLUMN whisper_version;")?;
+        assert!(Database::schema_differences_after_migrations(&db_path)?.is_empty());
+        assert_eq!(
+            Database::open_read_only(&db_path)?.schema_differences()?,
+            ["column jobs.whisper_version"]
+        );
+
+        // One that no migration adds back
+        conn.execute_batch("ALTER TABLE anime DROP COLUMN rating;")?;
+        assert_eq!(
+            Database::schema_differences_after_migrations(&db_path)?,
+            ["column anime.rating"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_baseline_database() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("test.db");
+
+        // A database built by the first release, with a selection in it
+        let conn = Connection::open(&db_path)?;
+        conn.execute_batch(include_str!("../testdata/baseline_schema.sql"))?;
+        conn.execute_batch(
+            "INSERT INTO anime (mal_id, title) VALUES (5114, 'Fullmetal Alchemist: Brotherhood');
+            INSERT INTO anime_selection_cache
+                (mal_id, anime_title, search_query, selected_index, selected_title, confidence)
+                VALUES (5114, 'Fullmetal Alchemist: Brotherhood', 'Fullmetal Alchemist: Brotherhood', 1, 'FMA:B (64 eps)', 'high');",
+        )?;
+        drop(conn);
+
+        // Every column the check requires is added by a migration
+        assert!(Database::schema_differences_after_migrations(&db_path)?.is_empty());
+        let db = Database::open(&db_path)?;
+        assert!(db.schema_differences()?.is_empty());
+        let selected: String = db.conn().query_row(
+            "SELECT selected_title FROM anime_selection_cache WHERE mal_id = 5114",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(selected, "FMA:B (64 eps)");
 
         Ok(())
     }
 
+    #[test]
+    fn test_selection_cache_migration() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("test.db");
+        Database::open(&db_path)?;
+
+        // The selection cache as first released: no episode columns, no
+        // manual flag and only three confidences
+        let conn = Connection::open(&db_path)?;
+        conn.execute_batch(
+            "DROP TABLE anime_selection_cache;
+            CREATE TABLE anime_selection_cache (
+                mal_id INTEGER PRIMARY KEY,
+                anime_title TEXT NOT NULL,
+                search_query TEXT NOT NULL,
+                selected_index INTEGER NOT NULL,
+                selected_title TEXT NOT NULL,
+                confidence TEXT NOT NULL CHECK(confidence IN ('high', 'medium', 'low')),
+                reason TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (mal_id) REFERENCES anime(mal_id)
+            );
+            INSERT INTO anime (mal_id, title) VALUES (1, 'Kept'), (2, 'Unavailable');
+            INSERT INTO anime_selection_cache
+                (mal_id, anime_title, search_query, selected_index, selected_title, confidence, reason)
+                VALUES (1, 'Kept', 'Kept', 2, 'Kept (12 eps)', 'medium', 'Main series');",
+        )?;
+        drop(conn);
+
+        let db = Database::open(&db_path)?;
+        assert!(db.index_exists("idx_selection_cache_episode_match")?);
+        assert!(db.index_exists("idx_selection_cache_confidence")?);
+        let kept: (i32, String, String, Option<String>, i32) = db.conn().query_row(
+            "SELECT selected_index, selected_title, confidence, episode_match, manual
+             FROM anime_selection_cache WHERE mal_id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )?;
+        assert_eq!(kept, (2, "Kept (12 eps)".to_string(), "medium".to_string(), None, 0));
+
+        // no_candidates rows can be stored now
+        db.conn().execute(
+            "INSERT INTO anime_selection_cache
+             (mal_id, anime_title, search_query, selected_index, selected_title, confidence, episode_match)
+             VALUES (2, 'Unavailable', 'Unavailable', -1, 'N/A', 'no_candidates', 'unknown')",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_classification_backfill() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("test.db");
+        Database::open(&db_path)?;
+
+        // A database from before the lookup tables, with anime saved as JSON only
+        let conn = Connection::open(&db_path)?;
+        for column in CLASSIFICATION_COLUMNS {
+            conn.execute_batch(&format!("DROP TABLE anime_{};", column))?;
+        }
+        conn.execute_batch(
+            r#"INSERT INTO anime (mal_id, title, genres, explicit_genres, themes, demographics, studios) VALUES
+                (1, 'Tested', '["Slice of Life", "Comedy", "comedy"]', '[]', '["School"]', '["Seinen"]', '["Kyoto Animation"]'),
+                (2, 'Unclassified', NULL, NULL, NULL, NULL, NULL),
+                (3, 'Broken', 'Action, Drama', '["Ecchi"]', '[]', '[]', '[]');"#,
+        )?;
+        drop(conn);
+
+        let db = Database::open(&db_path)?;
+        let rows = |table: &str| -> Result<Vec<(u32, String)>> {
+            let mut stmt = db.conn().prepare(&format!("SELECT mal_id, name FROM {} ORDER BY mal_id, name", table))?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        };
+        // Names differing only in case are one row; invalid JSON gets no rows
+        assert_eq!(rows("anime_genres")?, [(1, "Comedy".to_string()), (1, "Slice of Life".to_string())]);
+        assert_eq!(rows("anime_explicit_genres")?, [(3, "Ecchi".to_string())]);
+        assert_eq!(rows("anime_themes")?, [(1, "School".to_string())]);
+        assert_eq!(rows("anime_demographics")?, [(1, "Seinen".to_string())]);
+        assert_eq!(rows("anime_studios")?, [(1, "Kyoto Animation".to_string())]);
+        assert!(db.index_exists("idx_anime_genres_name")?);
+
+        // Runs only once
+        db.conn().execute("DELETE FROM anime_themes", [])?;
+        drop(db);
+        let db = Database::open(&db_path)?;
+        let themes: i64 = db.conn().query_row("SELECT COUNT(*) FROM anime_themes", [], |row| row.get(0))?;
+        assert_eq!(themes, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_refuses_writes() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("test.db");
+        Database::open(&db_path)?;
+
+        let db = Database::open_read_only(&db_path)?;
+        assert!(db.is_read_only());
+        assert!(db.table_exists("jobs")?);
+        assert!(db
+            .conn()
+            .execute("INSERT INTO anime (mal_id, title) VALUES (1, 'Test Anime')", [])
+            .is_err());
+        assert!(db.set_version(1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "read-only")]
+    fn test_read_only_conn_mut_panics() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        Database::open(&db_path).unwrap();
+
+        let mut db = Database::open_read_only(&db_path).unwrap();
+        db.conn_mut();
+    }
+
     #[test]
     fn test_version() -> Result<()> {
         let temp_dir = TempDir::new()?;
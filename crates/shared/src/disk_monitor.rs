@@ -3,14 +3,40 @@
 //! This module provides utilities to monitor disk usage and determine when
 //! to pause downloads to avoid exceeding storage limits.
 
+use crate::paths::PathLayout;
 use anyhow::{Context, Result};
+use rayon::prelude::*;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
+/// Episode length assumed when MAL has none (a standard TV episode).
+const DEFAULT_EPISODE_MINUTES: u32 = 24;
+
+/// Estimate the size of an episode before downloading it.
+///
+/// The median of the anime's already downloaded episodes is used when there
+/// are any, since they come from the same provider at the same quality.
+/// Otherwise the MAL episode length is multiplied by `bitrate_kbps`.
+pub fn estimate_episode_bytes(video_sizes: &[u64], duration_minutes: Option<u32>, bitrate_kbps: u32) -> u64 {
+    if !video_sizes.is_empty() {
+        let mut sizes = video_sizes.to_vec();
+        sizes.sort_unstable();
+        let middle = sizes.len() / 2;
+        return if sizes.len().is_multiple_of(2) {
+            (sizes[middle - 1] + sizes[middle]) / 2
+        } else {
+            sizes[middle]
+        };
+    }
+
+    let seconds = u64::from(duration_minutes.unwrap_or(DEFAULT_EPISODE_MINUTES)) * 60;
+    seconds * u64::from(bitrate_kbps) * 1000 / 8
+}
+
 /// Disk usage information.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DiskUsage {
     /// Total bytes used by all files in data directory
     pub total_bytes: u64,
@@ -52,6 +78,46 @@ impl DiskUsage {
     }
 }
 
+/// Storage category that workers report size changes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskCategory {
+    Videos,
+    Audio,
+    Transcripts,
+    Tokens,
+    Cache,
+    Database,
+    Other,
+}
+
+impl DiskUsage {
+    fn category_bytes_mut(&mut self, category: DiskCategory) -> &mut u64 {
+        match category {
+            DiskCategory::Videos => &mut self.videos_bytes,
+            DiskCategory::Audio => &mut self.audio_bytes,
+            DiskCategory::Transcripts => &mut self.transcripts_bytes,
+            DiskCategory::Tokens => &mut self.tokens_bytes,
+            DiskCategory::Cache => &mut self.cache_bytes,
+            DiskCategory::Database => &mut self.db_bytes,
+            DiskCategory::Other => &mut self.other_bytes,
+        }
+    }
+
+    /// Add bytes to a category (and the total).
+    pub fn add(&mut self, category: DiskCategory, bytes: u64) {
+        *self.category_bytes_mut(category) += bytes;
+        self.total_bytes += bytes;
+    }
+
+    /// Remove bytes from a category (and the total), saturating at zero.
+    pub fn sub(&mut self, category: DiskCategory, bytes: u64) {
+        let current = self.category_bytes_mut(category);
+        let removed = bytes.min(*current);
+        *current -= removed;
+        self.total_bytes -= removed;
+    }
+}
+
 /// Detailed space breakdown with analysis.
 #[derive(Debug, Clone)]
 pub struct SpaceBreakdown {
@@ -68,6 +134,7 @@ pub struct SpaceBreakdown {
 /// Cached disk usage result.
 struct CachedUsage {
     usage: DiskUsage,
+    /// When the last full scan happened (deltas don't reset this)
     timestamp: Instant,
 }
 
@@ -76,12 +143,19 @@ struct CachedUsage {
 /// Monitors disk usage across both local SSD (data directory) and external
 /// storage (videos directory), providing pause/resume signals to prevent
 /// exceeding storage limits. Uses caching to avoid excessive filesystem I/O.
+///
+/// Workers report the files they create and delete with `add_bytes` /
+/// `sub_bytes`, which adjust the cached totals in place. With a reconcile
+/// interval set, the cache is trusted for that long and a full scan only
+/// corrects drift (e.g. from other processes) periodically.
 #[derive(Clone)]
 pub struct DiskMonitor {
     /// Path to data directory (local SSD: audio, transcripts, cache, db)
     data_dir: PathBuf,
     /// Path to storage directory (external HDD: videos)
     storage_dir: PathBuf,
+    /// Per-category directories, relative to data_dir (storage_dir for videos)
+    layout: PathLayout,
     /// Hard limit in bytes (e.g., 250 GB)
     hard_limit: u64,
     /// Threshold to pause downloads (e.g., 230 GB)
@@ -90,8 +164,13 @@ pub struct DiskMonitor {
     resume_threshold: u64,
     /// Cache duration for usage results
     cache_duration: Duration,
+    /// Full-scan interval when tracking size deltas (replaces cache_duration)
+    reconcile_interval: Option<Duration>,
     /// Cached usage (protected by mutex for thread safety)
     cached_usage: Arc<Mutex<Option<CachedUsage>>>,
+    /// Whether usage comes from scanning the directories (false = only from
+    /// `add_bytes` / `sub_bytes`, see `tracking_only`)
+    scans_disk: bool,
 }
 
 impl DiskMonitor {
@@ -136,21 +215,54 @@ impl DiskMonitor {
         Ok(Self {
             data_dir,
             storage_dir,
+            layout: PathLayout::default(),
             hard_limit: hard_limit_gb * 1_000_000_000,
             pause_threshold: pause_threshold_gb * 1_000_000_000,
             resume_threshold: resume_threshold_gb * 1_000_000_000,
             cache_duration,
+            reconcile_interval: None,
             cached_usage: Arc::new(Mutex::new(None)),
+            scans_disk: true,
         })
     }
 
+    /// Create a monitor for tests whose usage starts at zero and only
+    /// changes through `add_bytes` / `sub_bytes`, without scanning any
+    /// directory.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn tracking_only(hard_limit_gb: u64, pause_threshold_gb: u64, resume_threshold_gb: u64) -> Result<Self> {
+        let mut monitor = Self::new("", "", hard_limit_gb, pause_threshold_gb, resume_threshold_gb, Duration::MAX)?;
+        monitor.scans_disk = false;
+        *monitor.cached_usage.lock().unwrap() = Some(CachedUsage {
+            usage: DiskUsage::default(),
+            timestamp: Instant::now(),
+        });
+        Ok(monitor)
+    }
+
+    /// Scan the category directories of `layout` (e.g. `Config::path_layout`)
+    /// instead of the default ones.
+    pub fn with_layout(mut self, layout: PathLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Trust the incrementally tracked usage for `interval` between full scans.
+    ///
+    /// `None` keeps rescanning every `cache_duration`.
+    pub fn with_reconcile_interval(mut self, interval: Option<Duration>) -> Self {
+        self.reconcile_interval = interval;
+        self
+    }
+
     /// Get current disk usage, using cache if available.
     pub fn current_usage(&self) -> Result<DiskUsage> {
         // Check cache first
         {
             let cached = self.cached_usage.lock().unwrap();
             if let Some(cached) = cached.as_ref() {
-                if cached.timestamp.elapsed() < self.cache_duration {
+                let lifetime = self.reconcile_interval.unwrap_or(self.cache_duration);
+                if cached.timestamp.elapsed() < lifetime {
                     debug!("Using cached disk usage");
                     return Ok(cached.usage.clone());
                 }
@@ -158,17 +270,7 @@ impl DiskMonitor {
         }
 
         // Cache miss or expired, recalculate
-        debug!("Calculating disk usage");
-        let usage = self.calculate_usage()?;
-
-        // Update cache
-        {
-            let mut cached = self.cached_usage.lock().unwrap();
-            *cached = Some(CachedUsage {
-                usage: usage.clone(),
-                timestamp: Instant::now(),
-            });
-        }
+        let usage = self.reconcile()?;
 
         info!(
             total_gb = usage.total_gb(),
@@ -215,6 +317,26 @@ impl DiskMonitor {
         Ok(can_resume)
     }
 
+    /// Check whether `bytes` more would still fit under the hard limit.
+    ///
+    /// Used before a download starts, so an episode that cannot fit is not
+    /// left half-written when the disk fills up.
+    pub fn has_room_for(&self, bytes: u64) -> Result<bool> {
+        let usage = self.current_usage()?;
+        let has_room = usage.total_bytes.saturating_add(bytes) <= self.hard_limit;
+
+        if !has_room {
+            warn!(
+                current_gb = usage.total_gb(),
+                needed_gb = bytes as f64 / 1_000_000_000.0,
+                hard_limit_gb = self.hard_limit as f64 / 1_000_000_000.0,
+                "Not enough space under the hard limit for the next download"
+            );
+        }
+
+        Ok(has_room)
+    }
+
     /// Get detailed space breakdown with analysis.
     pub fn get_breakdown(&self) -> Result<SpaceBreakdown> {
         let usage = self.current_usage()?;
@@ -230,27 +352,143 @@ impl DiskMonitor {
         })
     }
 
+    /// Record that `bytes` were written to `category`.
+    pub fn add_bytes(&self, category: DiskCategory, bytes: u64) {
+        if let Some(cached) = self.cached_usage.lock().unwrap().as_mut() {
+            cached.usage.add(category, bytes);
+            debug!(?category, bytes, "Added bytes to tracked disk usage");
+        }
+    }
+
+    /// Record that `bytes` were deleted from `category`.
+    pub fn sub_bytes(&self, category: DiskCategory, bytes: u64) {
+        if let Some(cached) = self.cached_usage.lock().unwrap().as_mut() {
+            cached.usage.sub(category, bytes);
+            debug!(?category, bytes, "Removed bytes from tracked disk usage");
+        }
+    }
+
+    /// Rescan the directories and replace the tracked usage.
+    ///
+    /// Logs how far the incrementally tracked total had drifted.
+    pub fn reconcile(&self) -> Result<DiskUsage> {
+        debug!("Calculating disk usage");
+        let usage = self.calculate_usage()?;
+
+        let mut cached = self.cached_usage.lock().unwrap();
+        if let Some(previous) = cached.as_ref() {
+            let drift = usage.total_bytes as i64 - previous.usage.total_bytes as i64;
+            if drift != 0 {
+                debug!(drift_bytes = drift, "Reconciled tracked disk usage");
+            }
+        }
+        *cached = Some(CachedUsage {
+            usage: usage.clone(),
+            timestamp: Instant::now(),
+        });
+
+        Ok(usage)
+    }
+
     /// Invalidate cache to force recalculation on next access.
     pub fn invalidate_cache(&self) {
+        if !self.scans_disk {
+            return;
+        }
         let mut cached = self.cached_usage.lock().unwrap();
         *cached = None;
         debug!("Invalidated disk usage cache");
     }
 
+    /// Drop the cached usage, rescan now and log the fresh breakdown.
+    ///
+    /// For when files were changed out-of-band and waiting out the cache
+    /// (or reconcile interval) would hide it.
+    pub fn refresh(&self) -> Result<SpaceBreakdown> {
+        self.invalidate_cache();
+        let breakdown = self.get_breakdown()?;
+
+        let gb = |bytes: u64| bytes as f64 / 1_000_000_000.0;
+        let usage = &breakdown.usage;
+        info!(
+            total_gb = usage.total_gb(),
+            videos_gb = gb(usage.videos_bytes),
+            audio_gb = gb(usage.audio_bytes),
+            transcripts_gb = gb(usage.transcripts_bytes),
+            tokens_gb = gb(usage.tokens_bytes),
+            cache_gb = gb(usage.cache_bytes),
+            db_gb = gb(usage.db_bytes),
+            other_gb = gb(usage.other_bytes),
+            percentage = breakdown.percentage,
+            can_download = breakdown.can_download,
+            "Refreshed disk usage"
+        );
+
+        Ok(breakdown)
+    }
+
+    /// Call `refresh` whenever the process receives SIGUSR1
+    /// (`kill -USR1 <pid>`). Must be called from within a tokio runtime; does
+    /// nothing on platforms without Unix signals.
+    pub fn refresh_on_sigusr1(&self) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut signals =
+                signal(SignalKind::user_defined1()).context("Failed to install SIGUSR1 handler")?;
+            let monitor = self.clone();
+            tokio::spawn(async move {
+                while signals.recv().await.is_some() {
+                    info!("Received SIGUSR1, refreshing disk usage");
+                    let monitor = monitor.clone();
+                    match tokio::task::spawn_blocking(move || monitor.refresh()).await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(e)) => warn!(error = %e, "Failed to refresh disk usage"),
+                        Err(e) => warn!(error = %e, "Disk usage refresh panicked"),
+                    }
+                }
+            });
+        }
+        Ok(())
+    }
+
     /// Calculate actual disk usage by walking directories.
     ///
     /// Videos are stored in storage_dir (external HDD), while audio, transcripts,
-    /// cache, and database are stored in data_dir (local SSD).
+    /// cache, and database are stored in data_dir (local SSD). The category
+    /// roots are walked in parallel.
     fn calculate_usage(&self) -> Result<DiskUsage> {
-        // Videos are on external storage (storage_dir)
-        let videos_bytes = self.calculate_dir_size(&self.storage_dir.join("videos"))?;
+        if !self.scans_disk {
+            let cached = self.cached_usage.lock().unwrap();
+            return Ok(cached.as_ref().map(|cached| cached.usage.clone()).unwrap_or_default());
+        }
 
-        // Everything else is on local SSD (data_dir)
-        let audio_bytes = self.calculate_dir_size(&self.data_dir.join("audio"))?;
-        let transcripts_bytes = self.calculate_dir_size(&self.data_dir.join("transcripts"))?;
-        let tokens_bytes = self.calculate_dir_size(&self.data_dir.join("tokens"))?;
-        let cache_bytes = self.calculate_dir_size(&self.data_dir.join("cache"))?;
-        let analysis_bytes = self.calculate_dir_size(&self.data_dir.join("analysis"))?;
+        let roots = [
+            // Videos are on external storage (storage_dir)
+            self.storage_dir.join(&self.layout.videos),
+            // Everything else is on local SSD (data_dir)
+            self.data_dir.join(&self.layout.audio),
+            self.data_dir.join(&self.layout.transcripts),
+            self.data_dir.join(&self.layout.tokens),
+            self.data_dir.join(&self.layout.cache),
+            self.data_dir.join(&self.layout.analysis),
+            self.data_dir.join(&self.layout.logs),
+        ];
+
+        let sizes = roots
+            .par_iter()
+            .map(|root| self.calculate_dir_size(root))
+            .collect::<Result<Vec<u64>>>()?;
+        let [
+            videos_bytes,
+            audio_bytes,
+            transcripts_bytes,
+            tokens_bytes,
+            cache_bytes,
+            analysis_bytes,
+            logs_bytes,
+        ]: [u64; 7] = sizes.try_into().expect("one size per root");
 
         // Database file (on local SSD)
         let db_path = self.data_dir.join("jobs.db");
@@ -262,9 +500,6 @@ impl DiskMonitor {
             0
         };
 
-        // Logs directory (on local SSD)
-        let logs_bytes = self.calculate_dir_size(&self.data_dir.join("logs"))?;
-
         let other_bytes = analysis_bytes + logs_bytes;
 
         let total_bytes = videos_bytes
@@ -288,38 +523,64 @@ impl DiskMonitor {
     }
 
     /// Calculate total size of a directory recursively.
+    ///
+    /// Entries of each directory are sized in parallel (subdirectories
+    /// recurse on the rayon pool), which matters for `videos/` trees with
+    /// thousands of per-anime directories on slow storage.
     fn calculate_dir_size(&self, path: &Path) -> Result<u64> {
         if !path.exists() {
             return Ok(0);
         }
 
-        let mut total = 0u64;
-
         let entries = std::fs::read_dir(path)
-            .with_context(|| format!("Failed to read directory: {}", path.display()))?;
+            .with_context(|| format!("Failed to read directory: {}", path.display()))?
+            .collect::<std::io::Result<Vec<_>>>()
+            .context("Failed to read directory entry")?;
+
+        entries
+            .par_iter()
+            .map(|entry| {
+                let metadata = entry
+                    .metadata()
+                    .context("Failed to get entry metadata")?;
+
+                if metadata.is_file() {
+                    Ok(metadata.len())
+                } else if metadata.is_dir() {
+                    self.calculate_dir_size(&entry.path())
+                } else {
+                    Ok(0)
+                }
+            })
+            .try_reduce(|| 0, |a, b| Ok(a + b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
 
-        for entry in entries {
-            let entry = entry.context("Failed to read directory entry")?;
-            let metadata = entry
-                .metadata()
-                .context("Failed to get entry metadata")?;
+    /// Single-threaded reference walk to check the parallel one against
+    fn serial_dir_size(path: &Path) -> Result<u64> {
+        if !path.exists() {
+            return Ok(0);
+        }
 
+        let mut total = 0u64;
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
             if metadata.is_file() {
                 total += metadata.len();
             } else if metadata.is_dir() {
-                total += self.calculate_dir_size(&entry.path())?;
+                total += serial_dir_size(&entry.path())?;
             }
         }
 
         Ok(total)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::TempDir;
 
     #[test]
     fn test_disk_usage_percentage() {
@@ -371,6 +632,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_estimate_episode_bytes() {
+        // Median of earlier episodes, whatever MAL says
+        assert_eq!(estimate_episode_bytes(&[300, 100, 200], Some(24), 1500), 200);
+        assert_eq!(estimate_episode_bytes(&[400, 100, 200, 300], None, 1500), 250);
+
+        // 24 minutes at 1500 kbit/s
+        assert_eq!(estimate_episode_bytes(&[], Some(24), 1500), 270_000_000);
+        assert_eq!(estimate_episode_bytes(&[], None, 1500), 270_000_000);
+        assert_eq!(estimate_episode_bytes(&[], Some(3), 1000), 22_500_000);
+    }
+
+    #[test]
+    fn test_has_room_for_near_full_disk() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let storage_dir = TempDir::new()?;
+
+        let monitor = DiskMonitor::new(
+            data_dir.path(),
+            storage_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_millis(1),
+        )?
+        .with_reconcile_interval(Some(Duration::from_secs(3600)));
+        monitor.reconcile()?;
+
+        // 8.5 GB used: below the pause threshold, but a 2 GB episode would
+        // cross the hard limit
+        monitor.add_bytes(DiskCategory::Videos, 8_500_000_000);
+        assert!(!monitor.should_pause_downloads()?);
+        assert!(!monitor.has_room_for(2_000_000_000)?);
+        assert!(monitor.has_room_for(1_500_000_000)?);
+        assert!(monitor.has_room_for(300_000_000)?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_calculate_dir_size() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -427,4 +727,176 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_incremental_tracking_matches_reconcile() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let storage_dir = TempDir::new()?;
+        let videos_dir = storage_dir.path().join("videos");
+        let audio_dir = data_dir.path().join("audio");
+        let transcripts_dir = data_dir.path().join("transcripts");
+        fs::create_dir_all(&videos_dir)?;
+        fs::create_dir_all(&audio_dir)?;
+        fs::create_dir_all(&transcripts_dir)?;
+        fs::write(videos_dir.join("existing.mp4"), vec![0u8; 500])?;
+
+        let monitor = DiskMonitor::new(
+            data_dir.path(),
+            storage_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_millis(1),
+        )?
+        .with_reconcile_interval(Some(Duration::from_secs(3600)));
+
+        // Without a baseline scan there is nothing to adjust
+        monitor.add_bytes(DiskCategory::Videos, 1_000_000);
+        assert_eq!(monitor.current_usage()?.total_bytes, 500);
+
+        // Simulate a download, audio extraction, transcription and cleanup,
+        // reporting each change instead of rescanning
+        fs::write(videos_dir.join("ep1.mp4"), vec![0u8; 3000])?;
+        monitor.add_bytes(DiskCategory::Videos, 3000);
+        fs::write(audio_dir.join("ep1.wav"), vec![0u8; 800])?;
+        monitor.add_bytes(DiskCategory::Audio, 800);
+        fs::write(transcripts_dir.join("ep1.txt"), vec![0u8; 40])?;
+        monitor.add_bytes(DiskCategory::Transcripts, 40);
+        fs::remove_file(videos_dir.join("ep1.mp4"))?;
+        monitor.sub_bytes(DiskCategory::Videos, 3000);
+        fs::remove_file(audio_dir.join("ep1.wav"))?;
+        monitor.sub_bytes(DiskCategory::Audio, 800);
+
+        // Still served from the tracked totals (cache_duration has long passed)
+        std::thread::sleep(Duration::from_millis(5));
+        let tracked = monitor.current_usage()?;
+        assert_eq!(tracked.videos_bytes, 500);
+        assert_eq!(tracked.audio_bytes, 0);
+        assert_eq!(tracked.transcripts_bytes, 40);
+        assert_eq!(tracked.total_bytes, 540);
+
+        // Over-subtracting saturates instead of wrapping
+        monitor.sub_bytes(DiskCategory::Audio, 10);
+        assert_eq!(monitor.current_usage()?.total_bytes, 540);
+
+        // A full scan agrees with the tracked figures
+        let scanned = monitor.reconcile()?;
+        assert_eq!(scanned.videos_bytes, tracked.videos_bytes);
+        assert_eq!(scanned.audio_bytes, tracked.audio_bytes);
+        assert_eq!(scanned.transcripts_bytes, tracked.transcripts_bytes);
+        assert_eq!(scanned.total_bytes, tracked.total_bytes);
+
+        // Unreported changes are picked up by the next reconciliation
+        fs::write(videos_dir.join("other_process.mp4"), vec![0u8; 200])?;
+        assert_eq!(monitor.current_usage()?.total_bytes, 540);
+        assert_eq!(monitor.reconcile()?.total_bytes, 740);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_picks_up_out_of_band_changes() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let storage_dir = TempDir::new()?;
+        let videos_dir = storage_dir.path().join("videos");
+        fs::create_dir_all(&videos_dir)?;
+        fs::write(videos_dir.join("ep1.mp4"), vec![0u8; 500])?;
+
+        let monitor = DiskMonitor::new(
+            data_dir.path(),
+            storage_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(3600),
+        )?
+        .with_reconcile_interval(Some(Duration::from_secs(3600)));
+        assert_eq!(monitor.current_usage()?.videos_bytes, 500);
+
+        // Deleted by hand: the cached figure is stale until refreshed
+        fs::remove_file(videos_dir.join("ep1.mp4"))?;
+        fs::write(videos_dir.join("ep2.mp4"), vec![0u8; 200])?;
+        assert_eq!(monitor.current_usage()?.videos_bytes, 500);
+
+        let breakdown = monitor.refresh()?;
+        assert_eq!(breakdown.usage.videos_bytes, 200);
+        assert_eq!(breakdown.usage.total_bytes, 200);
+        assert_eq!(monitor.current_usage()?.videos_bytes, 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_walk_matches_serial() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let storage_dir = TempDir::new()?;
+
+        // Generated tree: many per-anime directories with nested episode files
+        let mut expected_videos = 0u64;
+        for mal_id in 0..200u64 {
+            let anime_dir = storage_dir.path().join("videos").join(mal_id.to_string());
+            fs::create_dir_all(anime_dir.join("extras"))?;
+            for episode in 0..10u64 {
+                let size = (mal_id * 7 + episode * 13) % 500 + 1;
+                fs::write(anime_dir.join(format!("ep{}.mp4", episode)), vec![0u8; size as usize])?;
+                expected_videos += size;
+            }
+            fs::write(anime_dir.join("extras").join("op.mp4"), vec![0u8; 64])?;
+            expected_videos += 64;
+        }
+        for category in ["audio", "transcripts", "tokens", "cache", "analysis", "logs"] {
+            let dir = data_dir.path().join(category).join("5114");
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("file"), vec![0u8; 100])?;
+        }
+
+        let monitor = DiskMonitor::new(
+            data_dir.path(),
+            storage_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(60),
+        )?;
+        let videos_dir = storage_dir.path().join("videos");
+
+        let serial = serial_dir_size(&videos_dir)?;
+        let parallel = monitor.calculate_dir_size(&videos_dir)?;
+        assert_eq!(serial, expected_videos);
+        assert_eq!(parallel, serial);
+
+        // Per-category totals agree with serial walks of each root
+        let usage = monitor.current_usage()?;
+        assert_eq!(usage.videos_bytes, serial);
+        assert_eq!(usage.audio_bytes, serial_dir_size(&data_dir.path().join("audio"))?);
+        assert_eq!(usage.cache_bytes, 100);
+        assert_eq!(usage.other_bytes, 200);
+        assert_eq!(usage.total_bytes, serial + 600);
+
+        // Cached until invalidated
+        fs::write(videos_dir.join("0").join("ep99.mp4"), vec![0u8; 1000])?;
+        assert_eq!(monitor.current_usage()?.videos_bytes, serial);
+        monitor.invalidate_cache();
+        assert_eq!(monitor.current_usage()?.videos_bytes, serial + 1000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tracking_only_monitor_never_scans() -> Result<()> {
+        let monitor = DiskMonitor::tracking_only(2, 1, 1)?;
+        assert_eq!(monitor.current_usage()?.total_bytes, 0);
+
+        monitor.add_bytes(DiskCategory::Videos, 1_500_000_000);
+        assert!(monitor.should_pause_downloads()?);
+
+        // Nothing on disk to rescan: the tracked usage is kept
+        monitor.invalidate_cache();
+        assert_eq!(monitor.reconcile()?.videos_bytes, 1_500_000_000);
+
+        monitor.sub_bytes(DiskCategory::Videos, 1_500_000_000);
+        assert!(monitor.can_resume_downloads()?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,279 @@
+//! Export of the anime and job tables for external analysis.
+//!
+//! Writes plain CSV or JSON Lines so the metadata can be loaded into
+//! pandas or R without going through SQLite. In CSV, list fields (genres,
+//! studios, ...) are joined with `|`; in JSON Lines they stay arrays.
+
+use crate::models::{Anime, Job};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Display;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Separator between list items in a CSV field
+pub const CSV_LIST_SEPARATOR: &str = "|";
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// File extension for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "jsonlines" | "ndjson" => Ok(ExportFormat::JsonLines),
+            _ => Err(anyhow::anyhow!("Invalid export format: {} (expected csv or jsonl)", s)),
+        }
+    }
+}
+
+/// Column names of the anime CSV export
+const ANIME_COLUMNS: &[&str] = &[
+    "mal_id",
+    "title",
+    "title_english",
+    "title_japanese",
+    "title_synonyms",
+    "type",
+    "episodes_total",
+    "status",
+    "aired_from",
+    "aired_to",
+    "season",
+    "year",
+    "genres",
+    "explicit_genres",
+    "themes",
+    "demographics",
+    "studios",
+    "opening_themes",
+    "ending_themes",
+    "score",
+    "scored_by",
+    "rank",
+    "popularity",
+    "source",
+    "rating",
+    "duration_minutes",
+    "episodes_processed",
+    "processing_status",
+    "zipf_exponent",
+    "zipf_r_squared",
+    "total_tokens",
+    "zipf_warning",
+    "fetched_at",
+    "updated_at",
+];
+
+/// Column names of the job CSV export
+const JOB_COLUMNS: &[&str] = &[
+    "id",
+    "mal_id",
+    "anime_title",
+    "episode",
+    "stage",
+    "duration_seconds",
+    "video_size_bytes",
+    "audio_size_bytes",
+    "transcript_size_bytes",
+    "tokens_size_bytes",
+    "word_count",
+    "token_count",
+    "transcript_quality",
+    "transcript_model",
+    "whisper_version",
+    "recap_lines",
+    "video_path",
+    "transcript_path",
+    "tokens_path",
+    "analysis_path",
+    "updated_at",
+];
+
+/// Write anime rows to `path`
+pub(crate) fn write_anime(anime: &[Anime], format: ExportFormat, path: &Path) -> Result<()> {
+    write_records(anime, format, path, ANIME_COLUMNS, |a| {
+        vec![
+            a.mal_id.to_string(),
+            a.title.clone(),
+            opt(&a.title_english),
+            opt(&a.title_japanese),
+            a.title_synonyms.join(CSV_LIST_SEPARATOR),
+            opt(&a.anime_type),
+            opt(&a.episodes_total),
+            opt(&a.status),
+            opt(&a.aired_from),
+            opt(&a.aired_to),
+            opt(&a.season),
+            opt(&a.year),
+            a.genres.join(CSV_LIST_SEPARATOR),
+            a.explicit_genres.join(CSV_LIST_SEPARATOR),
+            a.themes.join(CSV_LIST_SEPARATOR),
+            a.demographics.join(CSV_LIST_SEPARATOR),
+            a.studios.join(CSV_LIST_SEPARATOR),
+            a.opening_themes.join(CSV_LIST_SEPARATOR),
+            a.ending_themes.join(CSV_LIST_SEPARATOR),
+            opt(&a.score),
+            opt(&a.scored_by),
+            opt(&a.rank),
+            opt(&a.popularity),
+            opt(&a.source),
+            opt(&a.rating),
+            opt(&a.duration_minutes),
+            a.episodes_processed.to_string(),
+            a.processing_status.to_string(),
+            opt(&a.zipf_exponent),
+            opt(&a.zipf_r_squared),
+            opt(&a.total_tokens),
+            opt(&a.zipf_warning),
+            a.fetched_at.to_rfc3339(),
+            a.updated_at.to_rfc3339(),
+        ]
+    })
+}
+
+/// Write job rows to `path`
+pub(crate) fn write_jobs(jobs: &[Job], format: ExportFormat, path: &Path) -> Result<()> {
+    write_records(jobs, format, path, JOB_COLUMNS, |j| {
+        vec![
+            j.id.to_string(),
+            j.mal_id.to_string(),
+            j.anime_title.clone(),
+            j.episode.to_string(),
+            j.stage.to_string(),
+            opt(&j.duration_seconds),
+            opt(&j.video_size_bytes),
+            opt(&j.audio_size_bytes),
+            opt(&j.transcript_size_bytes),
+            opt(&j.tokens_size_bytes),
+            opt(&j.word_count),
+            opt(&j.token_count),
+            opt(&j.transcript_quality),
+            opt(&j.transcript_model),
+            opt(&j.whisper_version),
+            opt(&j.recap_lines),
+            opt(&j.video_path),
+            opt(&j.transcript_path),
+            opt(&j.tokens_path),
+            opt(&j.analysis_path),
+            j.updated_at.to_rfc3339(),
+        ]
+    })
+}
+
+/// Write records as CSV (one `fields` row per record) or as JSON Lines
+fn write_records<T: Serialize>(
+    records: &[T],
+    format: ExportFormat,
+    path: &Path,
+    columns: &[&str],
+    fields: impl Fn(&T) -> Vec<String>,
+) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    match format {
+        ExportFormat::Csv => {
+            let mut csv = csv::WriterBuilder::new()
+                .terminator(csv::Terminator::Any(b'\n'))
+                .from_writer(out);
+            csv.write_record(columns)?;
+            for record in records {
+                csv.write_record(fields(record))?;
+            }
+            csv.flush().with_context(|| format!("Failed to write {}", path.display()))
+        }
+        ExportFormat::JsonLines => {
+            for record in records {
+                serde_json::to_writer(&mut out, record)?;
+                writeln!(out)?;
+            }
+            out.flush().with_context(|| format!("Failed to write {}", path.display()))
+        }
+    }
+}
+
+/// Format an optional value, empty when missing
+fn opt<T: Display>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, JobQueue};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_anime_csv_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let mut fmab = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64);
+        fmab.genres = vec!["Action".to_string(), "Adventure".to_string()];
+        fmab.studios = vec!["Bones".to_string()];
+        fmab.score = Some(9.1);
+        queue.get_or_create_anime(&fmab)?;
+        queue.get_or_create_anime(&Anime::test_fixture(1, "Cowboy Bebop, \"Session\" 1", 26))?;
+
+        let path = temp_dir.path().join("export").join("anime.csv");
+        assert_eq!(queue.export_anime(ExportFormat::Csv, &path)?, 2);
+
+        let mut reader = csv::Reader::from_path(&path)?;
+        let header = reader.headers()?.clone();
+        let column = |name: &str| header.iter().position(|c| c == name).unwrap();
+        let rows: Vec<Vec<String>> = reader.deserialize().collect::<csv::Result<_>>()?;
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.len() == header.len()));
+
+        assert_eq!(rows[0][column("mal_id")], "1");
+        assert_eq!(rows[0][column("title")], "Cowboy Bebop, \"Session\" 1");
+        assert_eq!(rows[0][column("genres")], "");
+        assert_eq!(rows[1][column("mal_id")], "5114");
+        assert_eq!(rows[1][column("title")], "Fullmetal Alchemist: Brotherhood");
+        assert_eq!(rows[1][column("episodes_total")], "64");
+        assert_eq!(rows[1][column("score")], "9.1");
+        assert_eq!(
+            rows[1][column("genres")].split(CSV_LIST_SEPARATOR).collect::<Vec<_>>(),
+            ["Action", "Adventure"]
+        );
+        assert_eq!(rows[1][column("studios")], "Bones");
+
+        // JSON Lines keeps the lists as arrays
+        let path = temp_dir.path().join("anime.jsonl");
+        queue.export_anime(ExportFormat::JsonLines, &path)?;
+        let exported: Vec<Anime> = fs::read_to_string(&path)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<_>>()?;
+        assert_eq!(exported[1].genres, fmab.genres);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Word frequency tables.
+//!
+//! The tokenizer writes one `epNNN_freq.csv` per episode (`word,count,pos,reading`
+//! with a header row). Zipf analysis works on whole shows, so
+//! `aggregate_frequencies` sums an anime's episode tables into one, and
+//! `fit_zipf` fits Zipf's law to such a table.
+//!
+//! Tables are written with `write_frequency_csv`, most frequent first with
+//! ties broken by word, so the same counts give byte-identical files.
+
+use crate::paths::DataPaths;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Sum the per-episode frequency CSVs of an anime and write the combined
+/// table to `DataPaths::word_freq_csv`.
+///
+/// Every `*_freq.csv` in the anime's tokens directory is included, so missing
+/// episodes are simply absent from the totals. Rows that cannot be parsed
+/// (e.g. a truncated last line) are skipped with a warning. An anime without
+/// any frequency files yields an empty table.
+pub fn aggregate_frequencies(anime_id: u32, data_paths: &DataPaths) -> Result<BTreeMap<String, u64>> {
+    let files = frequency_files(&data_paths.tokens_dir(anime_id))?;
+
+    let mut totals = BTreeMap::new();
+    for path in &files {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        merge_frequency_csv(&content, path, &mut totals);
+    }
+
+    let output = data_paths.word_freq_csv(anime_id);
+    write_frequency_table(&output, &totals)?;
+
+    info!(
+        anime_id,
+        episodes = files.len(),
+        unique_words = totals.len(),
+        total_words = totals.values().sum::<u64>(),
+        path = %output.display(),
+        "Aggregated word frequencies"
+    );
+
+    Ok(totals)
+}
+
+/// Read a frequency table written by `aggregate_frequencies` (or any CSV
+/// whose first two columns are `word,count`)
+pub fn read_frequency_table(path: &Path) -> Result<BTreeMap<String, u64>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut totals = BTreeMap::new();
+    merge_frequency_csv(&content, path, &mut totals);
+    Ok(totals)
+}
+
+/// Write a frequency table as `word,count` CSV, most frequent first,
+/// creating parent directories
+pub(crate) fn write_frequency_table(path: &Path, totals: &BTreeMap<String, u64>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(path).with_context(|| format!("Failed to write {}", path.display()))?;
+    let rows = totals.iter().map(|(word, &count)| (word.as_str(), count, [])).collect();
+    write_frequency_csv(file, &["word", "count"], rows)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write `(word, count, extra columns)` rows as CSV under `header`, most
+/// frequent first with equal counts ordered by word.
+///
+/// Fields are quoted only when they contain a separator, quote or line
+/// break; lines end in `\n`.
+pub fn write_frequency_csv<'a, W, E>(writer: W, header: &[&str], mut rows: Vec<(&'a str, u64, E)>) -> Result<()>
+where
+    W: io::Write,
+    E: IntoIterator<Item = &'a str>,
+{
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let mut csv = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::Any(b'\n'))
+        .flexible(true)
+        .from_writer(writer);
+    csv.write_record(header)?;
+    for (word, count, extra) in rows {
+        csv.write_field(word)?;
+        csv.write_field(count.to_string())?;
+        for field in extra {
+            csv.write_field(field)?;
+        }
+        csv.write_record(None::<&[u8]>)?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+/// Parameters of Zipf's law `count ≈ constant / rank^alpha`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZipfFit {
+    pub alpha: f64,
+    pub constant: f64,
+    pub r_squared: f64,
+    pub method: String,
+}
+
+/// Fit Zipf's law to a frequency table by linear regression of log(count)
+/// on log(rank).
+///
+/// Returns None when there are fewer than two distinct words to fit.
+pub fn fit_zipf(totals: &BTreeMap<String, u64>) -> Option<ZipfFit> {
+    let mut counts: Vec<u64> = totals.values().copied().filter(|&c| c > 0).collect();
+    if counts.len() < 2 {
+        return None;
+    }
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+
+    let points: Vec<(f64, f64)> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| (((i + 1) as f64).ln(), (count as f64).ln()))
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in &points {
+        sxx += (x - mean_x) * (x - mean_x);
+        sxy += (x - mean_x) * (y - mean_y);
+        syy += (y - mean_y) * (y - mean_y);
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    // All counts equal: the flat line through them is an exact fit
+    let r_squared = if syy == 0.0 { 1.0 } else { (sxy * sxy) / (sxx * syy) };
+
+    Some(ZipfFit {
+        alpha: -slope,
+        constant: intercept.exp(),
+        r_squared,
+        method: "log-log linear regression".to_string(),
+    })
+}
+
+/// Per-episode frequency CSVs in `dir`, sorted by name
+fn frequency_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))? {
+        let path = entry?.path();
+        let is_freq_csv = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with("_freq.csv"));
+        if path.is_file() && is_freq_csv {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Add the counts of one frequency CSV to `totals`
+fn merge_frequency_csv(content: &str, path: &Path, totals: &mut BTreeMap<String, u64>) {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    for (i, record) in reader.records().enumerate() {
+        let fields = match record {
+            Ok(fields) => fields,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Skipping malformed frequency row");
+                continue;
+            }
+        };
+        if i == 0 && fields.get(0) == Some("word") {
+            continue;
+        }
+        let line = fields.position().map_or(0, |p| p.line());
+
+        let count = fields.get(1).and_then(|c| c.trim().parse::<u64>().ok());
+        match (fields.get(0), count) {
+            (Some(word), Some(count)) if !word.is_empty() => {
+                *totals.entry(word.to_string()).or_insert(0) += count;
+            }
+            _ => warn!(path = %path.display(), line, "Skipping malformed frequency row"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_aggregate_frequencies() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        fs::create_dir_all(data_paths.tokens_dir(5114))?;
+
+        fs::write(
+            data_paths.freq_csv(5114, 1),
+            "word,count,pos,reading\nの,245,助詞,ノ\n錬金術,87,名詞,レンキンジュツ\n\"a,b\",2,記号,\n",
+        )?;
+        // Episode 2 is missing, episode 3 has CRLF line endings and no final
+        // newline, and episode 4 has an unparseable count
+        fs::write(
+            data_paths.freq_csv(5114, 3),
+            "word,count,pos,reading\r\nの,200,助詞,ノ\r\nは,198,助詞,ハ\r\n錬金術,1",
+        )?;
+        fs::write(data_paths.tokens_json(5114, 3), "{}")?;
+        fs::write(data_paths.tokens_dir(5114).join("ep004_freq.csv"), "word,count,pos,reading\nだ,x,助動詞,ダ\n")?;
+
+        let totals = aggregate_frequencies(5114, &data_paths)?;
+        assert_eq!(
+            totals,
+            BTreeMap::from([
+                ("a,b".to_string(), 2),
+                ("の".to_string(), 445),
+                ("は".to_string(), 198),
+                ("錬金術".to_string(), 88),
+            ])
+        );
+
+        let written = fs::read_to_string(data_paths.word_freq_csv(5114))?;
+        assert_eq!(written, "word,count\nの,445\nは,198\n錬金術,88\n\"a,b\",2\n");
+
+        // An anime without frequency files gives an empty table
+        assert!(aggregate_frequencies(1, &data_paths)?.is_empty());
+        assert_eq!(fs::read_to_string(data_paths.word_freq_csv(1))?, "word,count\n");
+
+        // The written table reads back unchanged
+        assert_eq!(read_frequency_table(&data_paths.word_freq_csv(5114))?, totals);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_frequency_csv_order_and_ties() -> Result<()> {
+        let rows = || {
+            vec![
+                ("は", 3, ["助詞"]),
+                ("猫", 7, ["名詞"]),
+                ("が", 3, ["助詞"]),
+                ("\"x\"", 1, ["記号"]),
+                ("の", 7, ["助詞"]),
+            ]
+        };
+
+        let mut by_count = Vec::new();
+        write_frequency_csv(&mut by_count, &["word", "count", "pos"], rows())?;
+        assert_eq!(
+            String::from_utf8(by_count.clone())?,
+            "word,count,pos\nの,7,助詞\n猫,7,名詞\nが,3,助詞\nは,3,助詞\n\"\"\"x\"\"\",1,記号\n"
+        );
+
+        // Ties are broken by word, so the input order never matters
+        let mut reversed = Vec::new();
+        let mut shuffled = rows();
+        shuffled.reverse();
+        write_frequency_csv(&mut reversed, &["word", "count", "pos"], shuffled)?;
+        assert_eq!(reversed, by_count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fit_zipf() {
+        // Counts exactly 1200 / rank
+        let totals: BTreeMap<String, u64> = (1..=6u64).map(|rank| (format!("w{}", rank), 1200 / rank)).collect();
+        let fit = fit_zipf(&totals).unwrap();
+        assert!((fit.alpha - 1.0).abs() < 1e-9, "{:?}", fit);
+        assert!((fit.constant - 1200.0).abs() < 1e-6, "{:?}", fit);
+        assert!((fit.r_squared - 1.0).abs() < 1e-9, "{:?}", fit);
+
+        let single = BTreeMap::from([("の".to_string(), 10)]);
+        assert!(fit_zipf(&single).is_none());
+    }
+}
@@ -0,0 +1,357 @@
+//! HTTP client with retries for external APIs.
+//!
+//! `RetryingClient` wraps a `reqwest::Client` with the retry behavior every
+//! API client wants: network errors, timeouts, 429 and 5xx responses are
+//! retried with exponential backoff and jitter, and a `Retry-After` header
+//! takes precedence over the backoff. Other error statuses (404, 401, ...)
+//! fail straight away, since retrying cannot help.
+//!
+//! The Jikan client and the anime selector's Claude client build on it,
+//! configured by `[mal_scraper.retry]` and `[anthropic.retry]`. A client is
+//! shared by reference between concurrent tasks, which then also share its
+//! rate limiter.
+
+use crate::retry::RetryPolicy;
+use crate::RateLimiter;
+use anyhow::Result;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// Default upper bound for the backoff delay
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Delay before a retry: exponential backoff with jitter, unless the server
+/// said how long to wait
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    /// Delay before the first retry; doubled for every further retry
+    base_delay: Duration,
+    /// Upper bound for the backoff (a `Retry-After` may be longer)
+    max_delay: Duration,
+    /// Fraction of the backoff that is random (0.25 = 75-100% of it)
+    jitter: f64,
+}
+
+impl Backoff {
+    /// Backoff starting at `base_delay`, without jitter
+    pub fn new(base_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: 0.0,
+        }
+    }
+
+    /// Cap the backoff at `max_delay`
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Randomize up to `jitter` (0.0 to 1.0) of each delay, so clients that
+    /// failed together don't all retry at the same moment
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before retrying after `attempt` retries have already been made
+    ///
+    /// `retry_after` (from the server) is used as is; otherwise the base delay
+    /// doubles with every retry up to the maximum, less the jitter.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(delay) = retry_after {
+            return delay;
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        backoff.mul_f64(1.0 - self.jitter * fastrand::f64())
+    }
+}
+
+/// Returned for an error status that is not retried, or still failed after
+/// the last retry
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("request failed with status {status}: {body}")]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    /// Response body, for the error message
+    pub body: String,
+}
+
+/// Whether a response with `status` is worth retrying
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT || status.is_server_error()
+}
+
+/// Delay requested by a `Retry-After` header in seconds (HTTP dates are
+/// ignored, falling back to the backoff)
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: f64 = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// Check run before every attempt; an error stops the request
+type AttemptGuard = Box<dyn Fn() -> Result<()> + Send + Sync>;
+
+/// HTTP client that retries failed requests
+pub struct RetryingClient {
+    client: Client,
+    /// Retries after the first attempt
+    max_retries: u32,
+    backoff: Backoff,
+    /// Timeout of each attempt (None = the client's own)
+    timeout: Option<Duration>,
+    /// Attempts taking longer than this are logged as slow
+    slow_threshold: Option<Duration>,
+    /// Paces every attempt, retries included
+    rate_limiter: Option<Mutex<RateLimiter>>,
+    attempt_guard: Option<AttemptGuard>,
+}
+
+impl RetryingClient {
+    /// Wrap `client`, with 3 retries starting at 1 second
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            max_retries: 3,
+            backoff: Backoff::new(Duration::from_secs(1)),
+            timeout: None,
+            slow_threshold: None,
+            rate_limiter: None,
+            attempt_guard: None,
+        }
+    }
+
+    /// Retry a failed request at most `max_retries` times
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Wait between retries as `backoff` says
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Retry and wait between retries as `policy` says
+    pub fn with_retry_policy(self, policy: &RetryPolicy) -> Self {
+        self.with_max_retries(policy.max_retries()).with_backoff(policy.backoff())
+    }
+
+    /// Give up on an attempt (and retry it) after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of each attempt, if one was set
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Log a warning for every attempt that takes longer than `threshold`
+    /// to answer (or fail), rate limiting not included
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Wait for `rate_limiter` before every attempt
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Mutex::new(rate_limiter));
+        self
+    }
+
+    /// Run `guard` before every attempt, retries included; the request fails
+    /// with its error if it returns one (e.g. a request budget used up)
+    pub fn with_attempt_guard(mut self, guard: impl Fn() -> Result<()> + Send + Sync + 'static) -> Self {
+        self.attempt_guard = Some(Box::new(guard));
+        self
+    }
+
+    /// Replace the underlying client, keeping the retry settings
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// The rate limiter pacing requests, if there is one
+    pub fn rate_limiter(&self) -> Option<&Mutex<RateLimiter>> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// Send the request built by `request` until it succeeds or the retries
+    /// are used up
+    ///
+    /// `request` is called again for every attempt. Fails with
+    /// `HttpStatusError` for an error status, or with the last network error.
+    pub async fn send(&self, request: impl Fn(&Client) -> RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            if let Some(guard) = &self.attempt_guard {
+                guard()?;
+            }
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.lock().await.acquire().await;
+            }
+
+            let mut builder = request(&self.client);
+            if let Some(timeout) = self.timeout {
+                builder = builder.timeout(timeout);
+            }
+            let request = builder.build()?;
+            let url = request.url().clone();
+            debug!(url = %url, attempt = attempt + 1, "Sending request");
+
+            let started = Instant::now();
+            let result = self.client.execute(request).await;
+            let elapsed = started.elapsed();
+            if self.slow_threshold.is_some_and(|threshold| elapsed > threshold) {
+                warn!(
+                    url = %url,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    status = ?result.as_ref().ok().map(|r| r.status().as_u16()),
+                    "Slow response"
+                );
+            }
+
+            let (error, retry_after) = match result {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = retry_after(&response);
+                    let body = response.text().await.unwrap_or_default();
+                    let error = HttpStatusError { status, body };
+                    if !is_retryable(status) {
+                        warn!(url = %url, status = %status, "Request failed");
+                        return Err(error.into());
+                    }
+                    (anyhow::Error::from(error), retry_after)
+                }
+                Err(e) => (anyhow::Error::from(e), None),
+            };
+
+            if attempt >= self.max_retries {
+                warn!(url = %url, error = %error, attempts = attempt + 1, "Request failed, giving up");
+                return Err(error.context(format!("Request to {} failed after {} attempts", url, attempt + 1)));
+            }
+
+            let delay = self.backoff.delay(attempt, retry_after);
+            warn!(
+                url = %url,
+                error = %error,
+                retry_in_ms = delay.as_millis() as u64,
+                "Request failed, retrying"
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::test_server::{TestResponse, TestServer};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::new(Duration::from_millis(100)).with_max_delay(Duration::from_millis(500));
+        assert_eq!(backoff.delay(0, None), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2, None), Duration::from_millis(400));
+        assert_eq!(backoff.delay(3, None), Duration::from_millis(500));
+        assert_eq!(backoff.delay(u32::MAX, None), Duration::from_millis(500));
+
+        // The server's Retry-After wins, even over the maximum
+        assert_eq!(backoff.delay(0, Some(Duration::from_secs(2))), Duration::from_secs(2));
+
+        let jittered = backoff.with_jitter(0.5);
+        for _ in 0..20 {
+            let delay = jittered.delay(1, None);
+            assert!((Duration::from_millis(100)..=Duration::from_millis(200)).contains(&delay), "{:?}", delay);
+        }
+    }
+
+    fn client(max_retries: u32) -> RetryingClient {
+        RetryingClient::new(Client::new())
+            .with_max_retries(max_retries)
+            .with_backoff(Backoff::new(Duration::from_millis(1)).with_jitter(0.5))
+            .with_timeout(Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() -> Result<()> {
+        let server = TestServer::sequence(vec![
+            TestResponse::new(503),
+            TestResponse::new(429).with_header("Retry-After", "0"),
+            TestResponse::new(200).with_body("ok"),
+        ])
+        .await?;
+        let url = format!("{}/anime/1", server.url());
+
+        let response = client(3).send(|client| client.get(&url)).await?;
+        assert_eq!(response.text().await?, "ok");
+        assert_eq!(server.request_count(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() -> Result<()> {
+        let server = TestServer::sequence(vec![TestResponse::new(500); 3]).await?;
+        let url = format!("{}/anime/1", server.url());
+
+        let err = client(2).send(|client| client.get(&url)).await.unwrap_err();
+        let status = err.downcast_ref::<HttpStatusError>().map(|e| e.status);
+        assert_eq!(status, Some(StatusCode::INTERNAL_SERVER_ERROR));
+        assert_eq!(server.request_count(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() -> Result<()> {
+        let server = TestServer::sequence(vec![TestResponse::new(404); 2]).await?;
+        let url = format!("{}/anime/1", server.url());
+
+        let err = client(3).send(|client| client.get(&url)).await.unwrap_err();
+        let status = err.downcast_ref::<HttpStatusError>().map(|e| e.status);
+        assert_eq!(status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(server.request_count(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_attempt_guard_stops_retries() -> Result<()> {
+        let server = TestServer::sequence(vec![TestResponse::new(503); 3]).await?;
+        let url = format!("{}/anime/1", server.url());
+
+        // Allow two attempts in total
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&attempts);
+        let client = client(5).with_attempt_guard(move || {
+            if counter.fetch_add(1, Ordering::SeqCst) >= 2 {
+                anyhow::bail!("out of attempts");
+            }
+            Ok(())
+        });
+
+        let err = client.send(|client| client.get(&url)).await.unwrap_err();
+        assert_eq!(err.to_string(), "out of attempts");
+        assert_eq!(server.request_count(), 2);
+
+        Ok(())
+    }
+}
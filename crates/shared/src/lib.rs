@@ -2,28 +2,66 @@
 //!
 //! This crate provides common functionality used across all binary crates:
 //! - Configuration management
+//! - Work budgets and per-stage concurrency limits for worker runs
 //! - Database models and operations
 //! - Job queue management
+//! - CSV / JSON Lines export of the anime and job tables
 //! - File path utilities
+//! - Word frequency aggregation and Zipf fitting
+//! - Per-anime Zipf analysis and genre-/studio-level aggregates
+//! - Rate limiting and an HTTP client with retries for external APIs
+//! - Retry policies for API requests and failed jobs
+//! - Timeouts for external processes
+//! - Progress bars for CLI runs
 //! - Logging infrastructure
+//! - Pipeline metrics for monitoring
+//! - Pre-flight checks for worker `--validate` runs
+//! - Run manifests recording the provenance of each worker run
 //! - Shared error types
 
+pub mod aggregate;
+pub mod analysis;
+pub mod budget;
+pub mod concurrency;
 pub mod config;
 pub mod db;
 pub mod disk_monitor;
+pub mod export;
+pub mod frequency;
+pub mod http;
 pub mod logging;
+pub mod manifest;
+pub mod metrics;
 pub mod models;
 pub mod paths;
+pub mod preflight;
+pub mod process;
+pub mod progress;
 pub mod queue;
+pub mod rate_limiter;
+pub mod retry;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_server;
 
 // Re-export commonly used types
-pub use config::{AnthropicConfig, CleanupConfig, Config};
+pub use budget::RunBudget;
+pub use concurrency::StageLimits;
+pub use config::{
+    AnalysisConfig, AnimeFilterConfig, AnthropicConfig, AudioCodec, AudioFormat, CleanupConfig, Config, DequeueOrder, DownloaderConfig,
+    PipelineConfig, PriorityConfig, RecapConfig, StageConcurrency, TokenFilterConfig, TokenizerBackend, TokenizerConfig, TranscriberConfig,
+    TranscriptFormat, DEFAULT_CONFIG_PATH,
+};
 pub use db::Database;
-pub use disk_monitor::{DiskMonitor, DiskUsage, SpaceBreakdown};
+pub use disk_monitor::{estimate_episode_bytes, DiskCategory, DiskMonitor, DiskUsage, SpaceBreakdown};
+pub use export::ExportFormat;
+pub use http::{Backoff, HttpStatusError, RetryingClient};
 pub use logging::LogConfig;
+pub use manifest::RunManifest;
 pub use models::*;
-pub use paths::DataPaths;
-pub use queue::{JobQueue, JobStats};
+pub use paths::{episode_file_stem, is_video_file, sanitize_filename, DataPaths, PathLayout, VIDEO_EXTENSIONS};
+pub use queue::{AnimeCompaction, AnimeRetries, JobQueue, JobStats, RemainingWork, RetokenizeReset, RetryReport, StageTiming, NEEDS_REVIEW_ERROR};
+pub use rate_limiter::RateLimiter;
+pub use retry::{retry_async, JobRetryPolicy, Retry, RetryPolicy};
 
 /// Common result type using anyhow::Error
 pub type Result<T> = anyhow::Result<T>;
@@ -4,9 +4,12 @@
 //! and module-specific log levels.
 
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::path::Path;
 use tracing::Level;
+use crate::progress::{self, ConsoleWriter};
 use tracing_subscriber::{
+    filter::LevelFilter,
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
     util::SubscriberInitExt,
@@ -28,6 +31,8 @@ pub struct LogConfig {
     pub file: bool,
     /// Enable JSON formatting for file logs
     pub json_format: bool,
+    /// Per-module log levels (e.g. `mal_scraper::api` = `debug`)
+    pub module_levels: BTreeMap<String, String>,
 }
 
 impl Default for LogConfig {
@@ -39,10 +44,27 @@ impl Default for LogConfig {
             console: true,
             file: true,
             json_format: false,
+            module_levels: BTreeMap::new(),
         }
     }
 }
 
+/// Build the `EnvFilter` directives for `config`
+///
+/// Per-module levels come after the defaults, in module order.
+pub fn filter_directives(config: &LogConfig) -> String {
+    let mut directives = format!(
+        "{}={},shared={},mal_scraper={},hyper=warn,reqwest=warn,h2=warn",
+        config.component, config.default_level, config.default_level, config.default_level
+    );
+
+    for (module, level) in &config.module_levels {
+        directives.push_str(&format!(",{}={}", module, level));
+    }
+
+    directives
+}
+
 /// Initialize logging with the given configuration
 ///
 /// Sets up tracing with:
@@ -50,22 +72,24 @@ impl Default for LogConfig {
 /// - Structured logging with contextual fields
 /// - Module-specific log levels
 /// - Optional JSON formatting
+///
+/// With progress bars enabled (`progress::enable`), the console only gets
+/// warnings and errors, printed above the bars.
 pub fn init(config: LogConfig) -> Result<()> {
     let log_dir = Path::new(&config.log_dir);
     std::fs::create_dir_all(log_dir)
         .with_context(|| format!("Failed to create log directory: {}", config.log_dir))?;
 
     // Build environment filter
-    // Default to configured level, but allow override via RUST_LOG
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        EnvFilter::new(format!(
-            "{}={},shared={},mal_scraper={},hyper=warn,reqwest=warn,h2=warn",
-            config.component,
-            config.default_level,
-            config.default_level,
-            config.default_level
-        ))
-    });
+    // Default to configured levels, but allow override via RUST_LOG
+    let env_filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => {
+            let directives = filter_directives(&config);
+            EnvFilter::try_new(&directives)
+                .with_context(|| format!("Invalid logging.module_levels: {}", directives))?
+        }
+    };
 
     let mut layers = Vec::new();
 
@@ -76,9 +100,15 @@ pub fn init(config: LogConfig) -> Result<()> {
             .with_level(true)
             .with_thread_ids(false)
             .with_thread_names(false)
-            .with_span_events(FmtSpan::NONE)
-            .with_writer(std::io::stdout)
-            .boxed();
+            .with_span_events(FmtSpan::NONE);
+        let console_layer = if progress::is_enabled() {
+            console_layer
+                .with_writer(|| ConsoleWriter)
+                .with_filter(LevelFilter::WARN)
+                .boxed()
+        } else {
+            console_layer.with_writer(std::io::stdout).boxed()
+        };
         layers.push(console_layer);
     }
 
@@ -157,6 +187,29 @@ mod tests {
         assert!(config.file);
     }
 
+    #[test]
+    fn test_filter_directives_include_module_levels() {
+        let config = LogConfig {
+            component: "anime-downloader".to_string(),
+            module_levels: BTreeMap::from([
+                ("shared::queue".to_string(), "trace".to_string()),
+                ("mal_scraper::api".to_string(), "debug".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let directives = filter_directives(&config);
+        assert_eq!(
+            directives,
+            "anime-downloader=INFO,shared=INFO,mal_scraper=INFO,hyper=warn,reqwest=warn,h2=warn,\
+             mal_scraper::api=debug,shared::queue=trace"
+        );
+        assert!(EnvFilter::try_new(&directives).is_ok());
+
+        // Without overrides only the defaults remain
+        assert!(!filter_directives(&LogConfig::default()).contains("::"));
+    }
+
     #[test]
     fn test_init_creates_log_dir() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -0,0 +1,344 @@
+//! Run manifests: provenance of the data each worker run produces.
+//!
+//! At startup a worker writes `run_manifest.json` to its own directory under
+//! `data/runs/`, recording the versions of the external tools it shells out
+//! to, a hash of its effective configuration, the git commit of the checkout
+//! and its command line. The finish time is added when the run ends, so a
+//! manifest without one belongs to a run that is still going or crashed.
+
+use crate::config::Config;
+use crate::paths::DataPaths;
+use crate::preflight::{find_in_path, ExternalTool};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+/// Version of an external tool, as found when the run started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolVersion {
+    pub name: String,
+    /// Where the tool was found on `PATH`
+    pub path: Option<PathBuf>,
+    /// Version number parsed from `output` (see `parse_version`)
+    pub version: Option<String>,
+    /// First line the version command printed
+    pub output: Option<String>,
+    /// Why the version could not be captured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Provenance of one worker run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Worker that ran (e.g. `transcriber`)
+    pub component: String,
+    /// Version of the GDA2025 crates
+    pub package_version: String,
+    /// Commit of the checkout the run was started from, if it is a git checkout
+    pub git_commit: Option<String>,
+    /// Command line of the run
+    pub args: Vec<String>,
+    /// SHA-256 of the effective configuration (see `config_hash`)
+    pub config_hash: String,
+    pub tools: Vec<ToolVersion>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl RunManifest {
+    /// Capture the manifest of a `component` run that uses `tools`
+    pub fn capture(component: &str, config: &Config, tools: &[ExternalTool]) -> Result<Self> {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        Ok(Self {
+            component: component.to_string(),
+            package_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: git_commit(),
+            args: std::env::args().collect(),
+            config_hash: config_hash(config)?,
+            tools: tools.iter().map(|tool| capture_tool_version(tool, &path)).collect(),
+            started_at: Utc::now(),
+            finished_at: None,
+        })
+    }
+
+    /// Capture the manifest and write it to `DataPaths::run_manifest`.
+    ///
+    /// Returns the manifest and where it was written, for `finish`.
+    pub fn start(
+        component: &str,
+        config: &Config,
+        tools: &[ExternalTool],
+        data_paths: &DataPaths,
+    ) -> Result<(Self, PathBuf)> {
+        let manifest = Self::capture(component, config, tools)?;
+        let path = data_paths.run_manifest(component, manifest.started_at);
+        manifest.write(&path)?;
+
+        for tool in &manifest.tools {
+            match &tool.error {
+                Some(error) => warn!(tool = %tool.name, error = %error, "Could not capture tool version"),
+                None => info!(tool = %tool.name, version = ?tool.version, "External tool"),
+            }
+        }
+        info!(path = %path.display(), "Wrote run manifest");
+
+        Ok((manifest, path))
+    }
+
+    /// Record the end of the run and rewrite the manifest at `path`
+    pub fn finish(&mut self, path: &Path) -> Result<()> {
+        self.finished_at = Some(Utc::now());
+        self.write(path)
+    }
+
+    /// Version of the tool called `name`, if it was captured
+    pub fn tool_version(&self, name: &str) -> Option<&str> {
+        self.tools.iter().find(|tool| tool.name == name)?.version.as_deref()
+    }
+
+    /// Write the manifest as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write run manifest {}", path.display()))
+    }
+
+    /// Read a manifest written by `write`
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+/// SHA-256 (hex) of `config` serialized as TOML.
+///
+/// Hashing the loaded configuration rather than the file means defaults and
+/// `GDA_*_DIR` overrides count, and comments and formatting do not. The
+/// Anthropic API key is left out.
+pub fn config_hash(config: &Config) -> Result<String> {
+    let mut config = config.clone();
+    config.anthropic.api_key.clear();
+    let toml = toml::to_string(&config).context("Failed to serialize config")?;
+
+    Ok(Sha256::digest(toml.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Commit checked out in the current directory, if it is a git checkout
+pub fn git_commit() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+/// Capture the version of `tool`, searching the `PATH`-style list `path`.
+///
+/// Tools with a `python_package` are asked for that package's version by the
+/// Python interpreter in their `#!` line, since e.g. openai-whisper has no
+/// `--version` flag. Failures are recorded in `error` rather than returned.
+pub fn capture_tool_version(tool: &ExternalTool, path: &OsStr) -> ToolVersion {
+    let mut captured = ToolVersion {
+        name: tool.name.to_string(),
+        path: find_in_path(tool.name, path),
+        version: None,
+        output: None,
+        error: None,
+    };
+    let Some(binary) = captured.path.clone() else {
+        captured.error = Some(format!("{} not found in PATH", tool.name));
+        return captured;
+    };
+
+    let command = match tool.python_package {
+        Some(package) => match python_interpreter(&binary) {
+            Some(python) => {
+                let mut command = Command::new(python);
+                command.arg("-c").arg(format!(
+                    "from importlib.metadata import version; print(version({:?}))",
+                    package
+                ));
+                command
+            }
+            None => {
+                captured.error = Some(format!("{} is not a Python script", binary.display()));
+                return captured;
+            }
+        },
+        None => {
+            let mut command = Command::new(&binary);
+            command.args(tool.version_args);
+            command
+        }
+    };
+
+    match first_output_line(command) {
+        Ok(line) => {
+            captured.version = parse_version(&line);
+            captured.output = Some(line);
+        }
+        Err(e) => captured.error = Some(format!("{:#}", e)),
+    }
+    captured
+}
+
+/// Run `command` and return the first non-empty line it prints (on stdout,
+/// or stderr for tools that print their version there)
+fn first_output_line(mut command: Command) -> Result<String> {
+    let output = command.output().context("Failed to run version command")?;
+    if !output.status.success() {
+        anyhow::bail!("Version command exited with code {}", output.status.code().unwrap_or(-1));
+    }
+
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    String::from_utf8_lossy(&text)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+        .context("Version command printed nothing")
+}
+
+/// Interpreter named in the `#!` line of `script`, if it is a Python one
+fn python_interpreter(script: &Path) -> Option<PathBuf> {
+    let content = std::fs::read(script).ok()?;
+    let first_line = content.split(|&b| b == b'\n').next()?;
+    let shebang = std::str::from_utf8(first_line).ok()?.strip_prefix("#!")?;
+
+    let mut parts = shebang.split_whitespace();
+    let interpreter = parts.next()?;
+    // `#!/usr/bin/env python3`
+    let interpreter = if interpreter.ends_with("/env") { parts.next()? } else { interpreter };
+    interpreter.contains("python").then(|| PathBuf::from(interpreter))
+}
+
+/// Version number in the first line a tool prints for `--version` (or
+/// `-version`).
+///
+/// The word after `version` wins (`ffmpeg version 6.1.1-3ubuntu5 Copyright
+/// ...`, `aria2 version 1.37.0`); otherwise the first word starting with a
+/// digit, with any `v` prefix dropped (`ani-cli v4.8.0`, `20231117`).
+pub fn parse_version(output: &str) -> Option<String> {
+    let line = output.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    if let Some(i) = words.iter().position(|word| word.eq_ignore_ascii_case("version")) {
+        if let Some(version) = words.get(i + 1) {
+            return Some(version.trim_end_matches([',', ';']).to_string());
+        }
+    }
+
+    words
+        .iter()
+        .map(|word| word.strip_prefix(['v', 'V']).unwrap_or(word))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|word| word.trim_end_matches([',', ';']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let samples = [
+            (
+                "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers\nbuilt with gcc 13",
+                Some("6.1.1-3ubuntu5"),
+            ),
+            ("ffmpeg version N-112345-g6ac9d2b Copyright (c) 2000-2023", Some("N-112345-g6ac9d2b")),
+            ("aria2 version 1.37.0\nCopyright (C) 2006, 2019 Tatsuhiro Tsujikawa", Some("1.37.0")),
+            ("4.8.0\n", Some("4.8.0")),
+            ("ani-cli v4.9", Some("4.9")),
+            ("\n20231117\n", Some("20231117")),
+            ("yt-dlp 2024.03.10, built from source", Some("2024.03.10")),
+            ("usage: whisper [-h] [--model MODEL] audio [audio ...]", None),
+            ("", None),
+        ];
+        for (output, expected) in samples {
+            assert_eq!(parse_version(output).as_deref(), expected, "{:?}", output);
+        }
+    }
+
+    #[test]
+    fn test_config_hash() -> Result<()> {
+        let config = Config::default();
+        let hash = config_hash(&config)?;
+        assert_eq!(hash.len(), 64);
+        assert_eq!(config_hash(&config.clone())?, hash);
+
+        // The API key is not part of the hash, everything else is
+        let mut with_key = config.clone();
+        with_key.anthropic.api_key = "sk-ant-secret".to_string();
+        assert_eq!(config_hash(&with_key)?, hash);
+        let mut changed = config;
+        changed.transcriber.initial_prompt = Some("{title}".to_string());
+        assert_ne!(config_hash(&changed)?, hash);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_hash_ignores_module_level_order() -> Result<()> {
+        let levels = [
+            ("shared::queue", "trace"),
+            ("mal_scraper::api", "debug"),
+            ("transcriber", "warn"),
+            ("anime_downloader::downloader", "debug"),
+            ("tokenizer::worker", "info"),
+        ];
+        let mut hashes = Vec::new();
+        for order in [levels.to_vec(), levels.iter().rev().copied().collect()] {
+            let mut config = Config::default();
+            for (module, level) in order {
+                config.logging.module_levels.insert(module.to_string(), level.to_string());
+            }
+            hashes.push(config_hash(&config)?);
+        }
+        assert_eq!(hashes[0], hashes[1]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_tool_version() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin_dir = tempfile::TempDir::new()?;
+        let write_script = |name: &str, body: &str| -> Result<()> {
+            let path = bin_dir.path().join(name);
+            std::fs::write(&path, body)?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+            Ok(())
+        };
+        write_script("aria2c", "#!/bin/sh\necho 'aria2 version 1.37.0'\n")?;
+        write_script("whisper", "#!/usr/bin/env python3\nimport whisper\n")?;
+
+        let path = bin_dir.path().as_os_str();
+        let aria2c = ExternalTool { name: "aria2c", version_args: &["--version"], python_package: None };
+        let captured = capture_tool_version(&aria2c, path);
+        assert_eq!(captured.version.as_deref(), Some("1.37.0"));
+        assert_eq!(captured.output.as_deref(), Some("aria2 version 1.37.0"));
+        assert_eq!(captured.path, Some(bin_dir.path().join("aria2c")));
+
+        let missing = ExternalTool { name: "ani-cli", version_args: &["--version"], python_package: None };
+        let captured = capture_tool_version(&missing, path);
+        assert_eq!(captured.version, None);
+        assert_eq!(captured.error.as_deref(), Some("ani-cli not found in PATH"));
+
+        assert_eq!(python_interpreter(&bin_dir.path().join("whisper")), Some(PathBuf::from("python3")));
+        assert_eq!(python_interpreter(&bin_dir.path().join("aria2c")), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//! Pipeline metrics in Prometheus text format.
+//!
+//! `collect` gathers job and disk figures from the queue and the disk monitor;
+//! `PipelineMetrics::to_prometheus` renders them in the text exposition format
+//! served by the monitor's metrics endpoint.
+
+use crate::disk_monitor::DiskMonitor;
+use crate::models::JobStage;
+use crate::queue::JobQueue;
+use anyhow::Result;
+use std::fmt::Write;
+
+/// Snapshot of pipeline metrics
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineMetrics {
+    /// Number of jobs in each stage, in pipeline order
+    pub jobs_by_stage: Vec<(JobStage, usize)>,
+    /// Seconds the oldest job has been waiting, per non-empty stage short of complete/failed
+    pub oldest_pending_age_seconds: Vec<(JobStage, f64)>,
+    /// Bytes used per storage category (videos, audio, ...)
+    pub disk_bytes: Vec<(&'static str, u64)>,
+    /// Total bytes used by the pipeline
+    pub disk_total_bytes: u64,
+    /// Percentage of the configured hard limit in use
+    pub disk_limit_percentage: f64,
+    /// Bytes used by the MAL API cache
+    pub cache_bytes: u64,
+    /// Jobs currently in the `complete` stage
+    pub jobs_completed: usize,
+    /// Jobs currently marked as failed
+    pub jobs_failed: usize,
+}
+
+/// Collect metrics from the job queue and the disk monitor
+pub fn collect(queue: &JobQueue, disk_monitor: &DiskMonitor) -> Result<PipelineMetrics> {
+    let stats = queue.get_stats()?;
+    let breakdown = disk_monitor.get_breakdown()?;
+    let usage = &breakdown.usage;
+
+    let jobs_by_stage = JobStage::all()
+        .iter()
+        .map(|stage| {
+            let count = match stage {
+                JobStage::Queued => stats.queued,
+                JobStage::Downloading => stats.downloading,
+                JobStage::Downloaded => stats.downloaded,
+                JobStage::Transcribing => stats.transcribing,
+                JobStage::Transcribed => stats.transcribed,
+                JobStage::Tokenizing => stats.tokenizing,
+                JobStage::Tokenized => stats.tokenized,
+                JobStage::Analyzing => stats.analyzing,
+                JobStage::Complete => stats.complete,
+                JobStage::Failed => stats.failed,
+            };
+            (*stage, count)
+        })
+        .collect();
+
+    let mut oldest_pending_age_seconds = Vec::new();
+    for stage in JobStage::all() {
+        if matches!(stage, JobStage::Complete | JobStage::Failed) {
+            continue;
+        }
+        if let Some(age) = queue.oldest_pending_age(*stage)? {
+            oldest_pending_age_seconds.push((*stage, age.as_secs_f64().round()));
+        }
+    }
+
+    Ok(PipelineMetrics {
+        jobs_by_stage,
+        oldest_pending_age_seconds,
+        disk_bytes: vec![
+            ("videos", usage.videos_bytes),
+            ("audio", usage.audio_bytes),
+            ("transcripts", usage.transcripts_bytes),
+            ("tokens", usage.tokens_bytes),
+            ("cache", usage.cache_bytes),
+            ("database", usage.db_bytes),
+            ("other", usage.other_bytes),
+        ],
+        disk_total_bytes: usage.total_bytes,
+        disk_limit_percentage: breakdown.percentage,
+        cache_bytes: usage.cache_bytes,
+        jobs_completed: stats.complete,
+        jobs_failed: stats.failed,
+    })
+}
+
+impl PipelineMetrics {
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        write_header(&mut out, "gda_jobs", "Number of jobs per pipeline stage", "gauge");
+        for (stage, count) in &self.jobs_by_stage {
+            let _ = writeln!(out, "gda_jobs{{stage=\"{}\"}} {}", stage, count);
+        }
+
+        write_header(
+            &mut out,
+            "gda_oldest_pending_age_seconds",
+            "Seconds the oldest job in each stage has been waiting",
+            "gauge",
+        );
+        for (stage, seconds) in &self.oldest_pending_age_seconds {
+            let _ = writeln!(out, "gda_oldest_pending_age_seconds{{stage=\"{}\"}} {}", stage, seconds);
+        }
+
+        write_header(&mut out, "gda_disk_bytes", "Bytes used per storage category", "gauge");
+        for (category, bytes) in &self.disk_bytes {
+            let _ = writeln!(out, "gda_disk_bytes{{category=\"{}\"}} {}", category, bytes);
+        }
+
+        write_metric(&mut out, "gda_disk_total_bytes", "Total bytes used by the pipeline", "gauge", self.disk_total_bytes);
+        write_metric(
+            &mut out,
+            "gda_disk_limit_percent",
+            "Percentage of the disk hard limit in use",
+            "gauge",
+            self.disk_limit_percentage,
+        );
+        write_metric(&mut out, "gda_cache_bytes", "Bytes used by the MAL API cache", "gauge", self.cache_bytes);
+        // Current counts, which drop when jobs are reset or retried, so gauges
+        write_metric(&mut out, "gda_jobs_completed", "Jobs that completed the pipeline", "gauge", self.jobs_completed);
+        write_metric(&mut out, "gda_jobs_failed", "Jobs marked as failed", "gauge", self.jobs_failed);
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_metric(out: &mut String, name: &str, help: &str, kind: &str, value: impl std::fmt::Display) {
+    write_header(out, name, help, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Anime, NewJob};
+    use crate::Database;
+    use std::fs;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let storage_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(data_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 3);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=3 {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?);
+        }
+        queue.update_stage(job_ids[0], JobStage::Complete)?;
+        queue.fail_job(job_ids[1], "boom")?;
+
+        fs::create_dir_all(storage_dir.path().join("videos"))?;
+        fs::write(storage_dir.path().join("videos").join("ep3.mp4"), vec![0u8; 4000])?;
+        fs::create_dir_all(data_dir.path().join("cache"))?;
+        fs::write(data_dir.path().join("cache").join("anime_5114.json"), vec![0u8; 500])?;
+
+        let monitor = DiskMonitor::new(data_dir.path(), storage_dir.path(), 10, 9, 8, Duration::from_secs(1))?;
+        let metrics = collect(&queue, &monitor)?;
+
+        let jobs = |stage| metrics.jobs_by_stage.iter().find(|(s, _)| *s == stage).unwrap().1;
+        assert_eq!(jobs(JobStage::Queued), 1);
+        assert_eq!(jobs(JobStage::Complete), 1);
+        assert_eq!(jobs(JobStage::Failed), 1);
+        assert_eq!(jobs(JobStage::Downloading), 0);
+        assert_eq!(metrics.jobs_completed, 1);
+        assert_eq!(metrics.jobs_failed, 1);
+        assert_eq!(metrics.oldest_pending_age_seconds.len(), 1);
+        assert_eq!(metrics.oldest_pending_age_seconds[0].0, JobStage::Queued);
+
+        let disk = |category| metrics.disk_bytes.iter().find(|(c, _)| *c == category).unwrap().1;
+        assert_eq!(disk("videos"), 4000);
+        assert_eq!(disk("cache"), 500);
+        assert_eq!(metrics.cache_bytes, 500);
+        assert!(metrics.disk_total_bytes >= 4500);
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE gda_jobs gauge\n"));
+        assert!(text.contains("gda_jobs{stage=\"queued\"} 1\n"));
+        assert!(text.contains("# TYPE gda_oldest_pending_age_seconds gauge\ngda_oldest_pending_age_seconds{stage=\"queued\"} "));
+        assert!(text.contains("gda_disk_bytes{category=\"videos\"} 4000\n"));
+        assert!(text.contains("gda_cache_bytes 500\n"));
+        assert!(text.contains("# TYPE gda_jobs_failed gauge\ngda_jobs_failed 1\n"));
+
+        Ok(())
+    }
+}
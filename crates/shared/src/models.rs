@@ -3,11 +3,13 @@
 //! This module defines all the data structures used throughout the pipeline,
 //! including anime metadata, job information, and analysis results.
 
+use crate::config::AnimeFilterConfig;
 use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Anime metadata from MyAnimeList
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Anime {
     pub id: Option<i64>,          // Database ID (None before insertion)
     pub mal_id: u32,              // MyAnimeList ID
@@ -36,6 +38,13 @@ pub struct Anime {
     pub demographics: Vec<String>,
     pub studios: Vec<String>,
 
+    // Theme song titles, openings and endings (stored as JSON arrays in
+    // database; songs recur every episode, so they are worth recognizing)
+    #[serde(default)]
+    pub opening_themes: Vec<String>,
+    #[serde(default)]
+    pub ending_themes: Vec<String>,
+
     // Scores and rankings
     pub score: Option<f64>,
     pub scored_by: Option<u32>,
@@ -51,11 +60,124 @@ pub struct Anime {
     pub episodes_processed: u32,
     pub processing_status: ProcessingStatus,
 
+    // Zipf analysis results (None until the anime has been analyzed)
+    pub zipf_exponent: Option<f64>,
+    pub zipf_r_squared: Option<f64>,
+    pub total_tokens: Option<u64>,
+    /// Why the Zipf fit is unreliable (too few tokens), if it is
+    #[serde(default)]
+    pub zipf_warning: Option<String>,
+
     // Timestamps
     pub fetched_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Anime {
+    /// MAL status of an anime still being broadcast
+    pub const STATUS_AIRING: &'static str = "Currently Airing";
+
+    /// MAL status of an announced anime whose broadcast has not started
+    pub const STATUS_NOT_YET_AIRED: &'static str = "Not yet aired";
+
+    /// Whether MAL lists the anime as currently airing
+    pub fn is_airing(&self) -> bool {
+        self.status.as_deref() == Some(Self::STATUS_AIRING)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Anime {
+    /// Minimal anime entry for tests (other crates enable `test-util`)
+    pub fn test_fixture(mal_id: u32, title: &str, episodes: u32) -> Self {
+        Self {
+            id: None,
+            mal_id,
+            title: title.to_string(),
+            title_english: None,
+            title_japanese: None,
+            title_synonyms: Vec::new(),
+            anime_type: Some("TV".to_string()),
+            episodes_total: Some(episodes),
+            status: None,
+            aired_from: None,
+            aired_to: None,
+            season: None,
+            year: None,
+            genres: Vec::new(),
+            explicit_genres: Vec::new(),
+            themes: Vec::new(),
+            demographics: Vec::new(),
+            studios: Vec::new(),
+            opening_themes: Vec::new(),
+            ending_themes: Vec::new(),
+            score: None,
+            scored_by: None,
+            rank: None,
+            popularity: None,
+            source: None,
+            rating: None,
+            duration_minutes: None,
+            episodes_processed: 0,
+            processing_status: ProcessingStatus::Pending,
+            zipf_exponent: None,
+            zipf_r_squared: None,
+            total_tokens: None,
+            zipf_warning: None,
+            fetched_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Anime broadcast season, as used by MAL
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Fall,
+}
+
+impl Season {
+    /// Season number within the year (winter = 1 ... fall = 4), as stored
+    /// in the `season` column of the jobs table
+    pub fn number(&self) -> i32 {
+        match self {
+            Season::Winter => 1,
+            Season::Spring => 2,
+            Season::Summer => 3,
+            Season::Fall => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for Season {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Season::Winter => write!(f, "winter"),
+            Season::Spring => write!(f, "spring"),
+            Season::Summer => write!(f, "summer"),
+            Season::Fall => write!(f, "fall"),
+        }
+    }
+}
+
+impl std::str::FromStr for Season {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "winter" => Ok(Season::Winter),
+            "spring" => Ok(Season::Spring),
+            "summer" => Ok(Season::Summer),
+            "fall" | "autumn" => Ok(Season::Fall),
+            _ => Err(anyhow::anyhow!("Invalid season: {}", s)),
+        }
+    }
+}
+
 /// Processing status for anime
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -92,7 +214,7 @@ impl std::str::FromStr for ProcessingStatus {
 }
 
 /// Job stage in the processing pipeline
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum JobStage {
     Queued,
@@ -107,6 +229,36 @@ pub enum JobStage {
     Failed,
 }
 
+impl JobStage {
+    /// All stages in pipeline order
+    pub fn all() -> &'static [JobStage] {
+        &[
+            JobStage::Queued,
+            JobStage::Downloading,
+            JobStage::Downloaded,
+            JobStage::Transcribing,
+            JobStage::Transcribed,
+            JobStage::Tokenizing,
+            JobStage::Tokenized,
+            JobStage::Analyzing,
+            JobStage::Complete,
+            JobStage::Failed,
+        ]
+    }
+
+    /// Stage a job waiting at this stage is moved to while a worker
+    /// processes it, or None for stages workers don't claim jobs from
+    pub fn in_progress(self) -> Option<JobStage> {
+        match self {
+            JobStage::Queued => Some(JobStage::Downloading),
+            JobStage::Downloaded => Some(JobStage::Transcribing),
+            JobStage::Transcribed => Some(JobStage::Tokenizing),
+            JobStage::Tokenized => Some(JobStage::Analyzing),
+            _ => None,
+        }
+    }
+}
+
 impl std::fmt::Display for JobStage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -195,6 +347,35 @@ pub struct Job {
     // Priority
     pub priority: i32,
     pub depends_on: Option<i64>,
+
+    // Transcript quality score (0.0 to 1.0), set after transcription
+    pub transcript_quality: Option<f64>,
+    // Whisper model that produced the kept transcript
+    pub transcript_model: Option<String>,
+
+    // Earliest time a retried job may be dequeued again
+    pub next_attempt_at: Option<DateTime<Utc>>,
+
+    // Opening transcript lines dropped as a recap (None = not checked)
+    pub recap_lines: Option<u32>,
+
+    // Version of the Whisper package that produced the kept transcript
+    pub whisper_version: Option<String>,
+
+    // Transcript deleted after tokenization, token list after analysis
+    pub transcript_deleted: bool,
+    pub tokens_deleted: bool,
+}
+
+/// Transcripts scoring below this are flagged as low quality
+pub const LOW_QUALITY_THRESHOLD: f64 = 0.5;
+
+impl Job {
+    /// Whether the kept transcript scored below `LOW_QUALITY_THRESHOLD`, in
+    /// which case the transcriber keeps the video and audio for a redo
+    pub fn has_low_quality_transcript(&self) -> bool {
+        self.transcript_quality.is_some_and(|quality| quality < LOW_QUALITY_THRESHOLD)
+    }
 }
 
 /// New job to be created
@@ -204,6 +385,9 @@ pub struct NewJob {
     pub mal_id: u32,
     pub anime_title: String,
     pub episode: u32,
+    /// Broadcast season number (see `Season::number`)
+    pub season: Option<i32>,
+    pub year: Option<i32>,
     pub priority: i32,
 }
 
@@ -212,6 +396,10 @@ pub struct NewJob {
 pub enum FileType {
     Video,
     Audio,
+    /// Transcript (every format), once tokenized
+    Transcript,
+    /// Token list (`epNNN_tokens.json`), once analyzed
+    Tokens,
 }
 
 /// Job metadata update
@@ -224,19 +412,188 @@ pub struct JobMetadata {
     pub duration_seconds: Option<u32>,
     pub word_count: Option<u32>,
     pub token_count: Option<u32>,
+    pub transcript_quality: Option<f64>,
+    pub transcript_model: Option<String>,
+    pub whisper_version: Option<String>,
+    pub recap_lines: Option<u32>,
     pub video_path: Option<String>,
     pub transcript_path: Option<String>,
     pub tokens_path: Option<String>,
 }
 
 /// Anime selection result (cached from Claude Haiku)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AnimeSelection {
     pub selected_index: i32,      // 1-based index from candidates list
     pub selected_title: String,   // The title that was selected
-    pub confidence: String,        // "high", "medium", or "low"
-    pub reason: String,            // Reason for selection
+    pub confidence: Confidence,
+    pub reason: String,            // Reason for selection (empty if none was cached)
     pub mal_episodes: Option<i32>,      // Episode count from MAL
     pub selected_episodes: Option<i32>, // Episode count from selected anime
     pub episode_match: Option<String>,  // "exact", "close", "acceptable", "mismatch", "unknown"
+    pub manual: bool,                   // Set by hand, kept over automated selections
+}
+
+/// The classifications of an anime that aggregate analyses are grouped by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnimeClassification {
+    pub mal_id: u32,
+    pub title: String,
+    pub genres: Vec<String>,
+    pub studios: Vec<String>,
+}
+
+/// Classifications an anime must have for its jobs to be processed
+/// (`[pipeline.anime_filter]`, `--filter-genre` etc.), matched by
+/// `JobQueue::find_anime`
+///
+/// Names match case-insensitively (ASCII). An anime needs every listed genre
+/// and theme, but only one of the listed demographics and studios; empty
+/// lists and unset years match everything. Types and episode counts are
+/// matched like the scraper's `[mal_scraper.filter]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AnimeFilter {
+    /// Genres the anime must all have, e.g. ["Slice of Life", "Comedy"]
+    /// (explicit genres count too)
+    pub genres: Vec<String>,
+
+    /// Themes the anime must all have, e.g. ["School"]
+    pub themes: Vec<String>,
+
+    /// Demographics of which the anime must have one, e.g. ["Shounen", "Seinen"]
+    pub demographics: Vec<String>,
+
+    /// Studios of which one must have made the anime
+    pub studios: Vec<String>,
+
+    /// MAL types and episode counts the anime must have
+    #[serde(flatten)]
+    pub kind: AnimeFilterConfig,
+
+    /// Skip anime that started airing before this year (or with no known year)
+    pub min_year: Option<i32>,
+
+    /// Skip anime that started airing after this year (or with no known year)
+    pub max_year: Option<i32>,
+}
+
+impl AnimeFilter {
+    /// Check whether any classification is set
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Command-line arguments (`--filter-genre` etc.) that select this filter
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, values) in [
+            ("--filter-genre", &self.genres),
+            ("--filter-theme", &self.themes),
+            ("--filter-demographic", &self.demographics),
+            ("--filter-studio", &self.studios),
+            ("--filter-type", &self.kind.types),
+        ] {
+            for value in values {
+                args.push(flag.to_string());
+                args.push(value.clone());
+            }
+        }
+        if self.min_year.is_some() || self.max_year.is_some() {
+            args.push("--filter-year".to_string());
+            args.push(format_year_range((self.min_year, self.max_year)));
+        }
+        args
+    }
+}
+
+/// Parse a `--filter-year` value: `2020`, `2018..2022`, `2018..` or `..2010`
+/// (bounds inclusive)
+pub fn parse_year_range(s: &str) -> anyhow::Result<(Option<i32>, Option<i32>)> {
+    let year = |y: &str| -> anyhow::Result<Option<i32>> {
+        let y = y.trim();
+        if y.is_empty() {
+            return Ok(None);
+        }
+        y.parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("Invalid year: {:?}", y))
+    };
+    let (min, max) = match s.split_once("..") {
+        Some((min, max)) => (year(min)?, year(max)?),
+        None => {
+            let y = year(s)?.ok_or_else(|| anyhow::anyhow!("Empty year range"))?;
+            (Some(y), Some(y))
+        }
+    };
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            anyhow::bail!("Year range {}..{} is empty", min, max);
+        }
+    }
+    Ok((min, max))
+}
+
+/// Format a year range the way `parse_year_range` reads it
+fn format_year_range(range: (Option<i32>, Option<i32>)) -> String {
+    match range {
+        (Some(min), Some(max)) if min == max => min.to_string(),
+        (min, max) => format!(
+            "{}..{}",
+            min.map(|y| y.to_string()).unwrap_or_default(),
+            max.map(|y| y.to_string()).unwrap_or_default()
+        ),
+    }
+}
+
+/// Confidence of an anime-selector selection.
+///
+/// Ordered from `NoCandidates` (nothing to select from) up to `High`. The
+/// database column stays textual; its `CHECK` constraint allows exactly the
+/// names in `Confidence::ALL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// AllAnime returned no candidates, so nothing was selected
+    NoCandidates,
+    Low,
+    Medium,
+    High,
+}
+
+impl Confidence {
+    /// All levels, lowest first
+    pub const ALL: [Confidence; 4] = [
+        Confidence::NoCandidates,
+        Confidence::Low,
+        Confidence::Medium,
+        Confidence::High,
+    ];
+
+    /// Name as stored in `anime_selection_cache.confidence`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::NoCandidates => "no_candidates",
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Confidence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Confidence::ALL
+            .into_iter()
+            .find(|c| c.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Invalid confidence: {}", s))
+    }
 }
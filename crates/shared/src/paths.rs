@@ -3,7 +3,54 @@
 //! This module provides a centralized way to manage file paths for all data files
 //! (videos, audio, transcripts, tokens, analysis results, cache, etc.).
 
+use crate::config::CleanupConfig;
+use crate::models::{Anime, FileType, Job, JobStage};
+use crate::queue::JobQueue;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Extensions of the video containers ani-cli may deliver
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm"];
+
+/// Base directory for each data category.
+///
+/// Relative entries are resolved against the root directory (or the storage
+/// directory, for videos); absolute entries are used as-is. This allows e.g.
+/// putting videos on a fast scratch disk and transcripts on durable storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathLayout {
+    /// Video base directory (relative to storage)
+    pub videos: PathBuf,
+    /// Audio base directory (relative to root)
+    pub audio: PathBuf,
+    /// Transcript base directory (relative to root)
+    pub transcripts: PathBuf,
+    /// Token base directory (relative to root)
+    pub tokens: PathBuf,
+    /// Analysis base directory (relative to root)
+    pub analysis: PathBuf,
+    /// Cache base directory (relative to root)
+    pub cache: PathBuf,
+    /// Log directory (relative to root)
+    pub logs: PathBuf,
+}
+
+impl Default for PathLayout {
+    fn default() -> Self {
+        Self {
+            videos: PathBuf::from("videos"),
+            audio: PathBuf::from("audio"),
+            transcripts: PathBuf::from("transcripts"),
+            tokens: PathBuf::from("tokens"),
+            analysis: PathBuf::from("analysis"),
+            cache: PathBuf::from("cache"),
+            logs: PathBuf::from("logs"),
+        }
+    }
+}
 
 /// File path manager for data files
 #[derive(Debug, Clone)]
@@ -12,6 +59,8 @@ pub struct DataPaths {
     root: PathBuf,
     /// Storage directory for videos and transcripts (can be on external HDD)
     storage: PathBuf,
+    /// Per-category base directories
+    layout: PathLayout,
 }
 
 impl DataPaths {
@@ -19,17 +68,24 @@ impl DataPaths {
     /// (storage directory will be the same as root)
     pub fn new(root: impl AsRef<Path>) -> Self {
         let root_path = root.as_ref().to_path_buf();
-        Self {
-            root: root_path.clone(),
-            storage: root_path,
-        }
+        Self::new_with_layout(&root_path, &root_path, PathLayout::default())
     }
 
     /// Create a new DataPaths with separate root and storage directories
     pub fn new_with_storage(root: impl AsRef<Path>, storage: impl AsRef<Path>) -> Self {
+        Self::new_with_layout(root, storage, PathLayout::default())
+    }
+
+    /// Create a new DataPaths with a custom per-category layout
+    pub fn new_with_layout(
+        root: impl AsRef<Path>,
+        storage: impl AsRef<Path>,
+        layout: PathLayout,
+    ) -> Self {
         Self {
             root: root.as_ref().to_path_buf(),
             storage: storage.as_ref().to_path_buf(),
+            layout,
         }
     }
 
@@ -43,21 +99,53 @@ impl DataPaths {
         &self.storage
     }
 
+    /// Get the per-category layout
+    pub fn layout(&self) -> &PathLayout {
+        &self.layout
+    }
+
+    // ========== Category base directories ==========
+    // Joining an absolute layout entry replaces the root/storage prefix
+
+    /// Get the base directory for all videos
+    pub fn videos_base(&self) -> PathBuf {
+        self.storage.join(&self.layout.videos)
+    }
+
+    /// Get the base directory for all audio files
+    pub fn audio_base(&self) -> PathBuf {
+        self.root.join(&self.layout.audio)
+    }
+
+    /// Get the base directory for all transcripts
+    pub fn transcripts_base(&self) -> PathBuf {
+        self.root.join(&self.layout.transcripts)
+    }
+
+    /// Get the base directory for all token files
+    pub fn tokens_base(&self) -> PathBuf {
+        self.root.join(&self.layout.tokens)
+    }
+
+    /// Get the base directory for all analysis results
+    pub fn analysis_base(&self) -> PathBuf {
+        self.root.join(&self.layout.analysis)
+    }
+
     // ========== Video paths (TEMPORARY - auto-deleted) ==========
     // Videos are stored on external storage
 
     /// Get video directory for an anime
     pub fn video_dir(&self, anime_id: u32) -> PathBuf {
-        self.storage
-            .join("videos")
+        self.videos_base()
             .join(anime_id.to_string())
             .join("episodes")
     }
 
-    /// Get video file path for an episode
-    pub fn video_file(&self, anime_id: u32, episode: u32) -> PathBuf {
+    /// Get video file path for an episode in the given container (see `VIDEO_EXTENSIONS`)
+    pub fn video_file(&self, anime_id: u32, title: &str, episode: u32, extension: &str) -> PathBuf {
         self.video_dir(anime_id)
-            .join(format!("ep{:03}.mkv", episode))
+            .join(format!("{}.{}", episode_file_stem(title, episode), extension))
     }
 
     // ========== Audio paths (TEMPORARY - auto-deleted) ==========
@@ -65,13 +153,13 @@ impl DataPaths {
 
     /// Get audio directory for an anime
     pub fn audio_dir(&self, anime_id: u32) -> PathBuf {
-        self.root.join("audio").join(anime_id.to_string())
+        self.audio_base().join(anime_id.to_string())
     }
 
-    /// Get audio file path for an episode
-    pub fn audio_file(&self, anime_id: u32, episode: u32) -> PathBuf {
+    /// Get audio file path for an episode in the given format (`wav`, `flac`)
+    pub fn audio_file(&self, anime_id: u32, title: &str, episode: u32, extension: &str) -> PathBuf {
         self.audio_dir(anime_id)
-            .join(format!("ep{:03}.wav", episode))
+            .join(format!("{}.{}", episode_file_stem(title, episode), extension))
     }
 
     // ========== Transcript paths (PERMANENT) ==========
@@ -79,26 +167,35 @@ impl DataPaths {
 
     /// Get transcript directory for an anime
     pub fn transcript_dir(&self, anime_id: u32) -> PathBuf {
-        self.root.join("transcripts").join(anime_id.to_string())
+        self.transcripts_base().join(anime_id.to_string())
     }
 
     /// Get plain text transcript path
-    pub fn transcript_txt(&self, anime_id: u32, episode: u32) -> PathBuf {
+    pub fn transcript_txt(&self, anime_id: u32, title: &str, episode: u32) -> PathBuf {
         self.transcript_dir(anime_id)
-            .join(format!("ep{:03}.txt", episode))
+            .join(format!("{}.txt", episode_file_stem(title, episode)))
     }
 
     /// Get JSON transcript path (with timestamps and metadata)
-    pub fn transcript_json(&self, anime_id: u32, episode: u32) -> PathBuf {
+    pub fn transcript_json(&self, anime_id: u32, title: &str, episode: u32) -> PathBuf {
         self.transcript_dir(anime_id)
-            .join(format!("ep{:03}.json", episode))
+            .join(format!("{}.json", episode_file_stem(title, episode)))
+    }
+
+    /// Plain text transcript of `job`: the path recorded when it was
+    /// transcribed or imported, or where the transcriber writes it
+    pub fn job_transcript(&self, job: &Job) -> PathBuf {
+        match &job.transcript_path {
+            Some(path) => PathBuf::from(path),
+            None => self.transcript_txt(job.mal_id, &job.anime_title, job.episode),
+        }
     }
 
     // ========== Token paths (PERMANENT) ==========
 
     /// Get tokens directory for an anime
     pub fn tokens_dir(&self, anime_id: u32) -> PathBuf {
-        self.root.join("tokens").join(anime_id.to_string())
+        self.tokens_base().join(anime_id.to_string())
     }
 
     /// Get full tokenization JSON path
@@ -113,16 +210,26 @@ impl DataPaths {
             .join(format!("ep{:03}_freq.csv", episode))
     }
 
+    /// Get word frequency CSV path for the table before token filtering
+    pub fn unfiltered_freq_csv(&self, anime_id: u32, episode: u32) -> PathBuf {
+        self.tokens_dir(anime_id)
+            .join(format!("ep{:03}_freq_unfiltered.csv", episode))
+    }
+
     // ========== Analysis paths (PERMANENT) ==========
 
     /// Get analysis directory for an anime
     pub fn analysis_dir(&self, anime_id: u32) -> PathBuf {
-        self.root
-            .join("analysis")
+        self.analysis_base()
             .join("per_anime")
             .join(anime_id.to_string())
     }
 
+    /// Get aggregated (whole-show) word frequency CSV path
+    pub fn word_freq_csv(&self, anime_id: u32) -> PathBuf {
+        self.analysis_dir(anime_id).join("word_freq.csv")
+    }
+
     /// Get Zipf parameters JSON path
     pub fn zipf_params(&self, anime_id: u32) -> PathBuf {
         self.analysis_dir(anime_id).join("zipf_params.json")
@@ -143,16 +250,49 @@ impl DataPaths {
     /// Get anime metadata JSON path
     pub fn anime_metadata(&self, anime_id: u32) -> PathBuf {
         self.root
-            .join("videos")
+            .join(&self.layout.videos)
             .join(anime_id.to_string())
             .join("metadata.json")
     }
 
+    /// Write `anime` to its metadata JSON, replacing any earlier version
+    pub fn write_anime_metadata(&self, anime: &Anime) -> Result<()> {
+        let path = self.anime_metadata(anime.mal_id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first so readers never see a partial file
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(anime)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Read an anime's metadata JSON
+    pub fn read_anime_metadata(&self, anime_id: u32) -> Result<Anime> {
+        let path = self.anime_metadata(anime_id);
+        let json = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    // ========== Run manifests ==========
+
+    /// Get the run manifest path of a `component` run started at `started_at`
+    pub fn run_manifest(&self, component: &str, started_at: DateTime<Utc>) -> PathBuf {
+        self.root
+            .join("runs")
+            .join(format!("{}_{}", started_at.format("%Y%m%dT%H%M%SZ"), component))
+            .join("run_manifest.json")
+    }
+
     // ========== Cache ==========
 
     /// Get cache directory
     pub fn cache_dir(&self) -> PathBuf {
-        self.root.join("cache")
+        self.root.join(&self.layout.cache)
     }
 
     /// Get MAL cache directory
@@ -195,7 +335,7 @@ impl DataPaths {
 
     /// Get logs directory
     pub fn logs_dir(&self) -> PathBuf {
-        self.root.join("logs")
+        self.root.join(&self.layout.logs)
     }
 
     /// Get log file path for a specific component
@@ -219,7 +359,7 @@ impl DataPaths {
 
     /// Get aggregated analysis directory
     pub fn aggregated_dir(&self) -> PathBuf {
-        self.root.join("analysis").join("aggregated")
+        self.analysis_base().join("aggregated")
     }
 
     /// Get genre-specific analysis directory
@@ -242,19 +382,19 @@ impl DataPaths {
     pub fn create_dirs(&self) -> std::io::Result<()> {
         let dirs = vec![
             // Storage directories (external HDD) - only videos
-            self.storage.join("videos"),
+            self.videos_base(),
             // Root directories (local SSD)
-            self.root.join("audio"),
-            self.root.join("transcripts"),
-            self.root.join("tokens"),
-            self.root.join("analysis/per_anime"),
-            self.root.join("analysis/aggregated/by_genre"),
-            self.root.join("analysis/aggregated/by_studio"),
-            self.root.join("cache/mal_cache/categories/genres"),
-            self.root.join("cache/mal_cache/categories/themes"),
-            self.root.join("cache/mal_cache/categories/demographics"),
-            self.root.join("cache/mal_cache/categories/studios"),
-            self.root.join("cache/mal_cache/anime"),
+            self.audio_base(),
+            self.transcripts_base(),
+            self.tokens_base(),
+            self.analysis_base().join("per_anime"),
+            self.aggregated_dir().join("by_genre"),
+            self.aggregated_dir().join("by_studio"),
+            self.category_cache_dir("genres"),
+            self.category_cache_dir("themes"),
+            self.category_cache_dir("demographics"),
+            self.category_cache_dir("studios"),
+            self.anime_cache_dir(),
             self.logs_dir(),
             self.models_dir(),
         ];
@@ -266,6 +406,122 @@ impl DataPaths {
         Ok(())
     }
 
+    /// Get total bytes on disk for a single anime (videos, audio, transcripts,
+    /// tokens and per-anime analysis)
+    pub fn disk_usage_for_anime(&self, anime_id: u32) -> std::io::Result<u64> {
+        let mut total = 0;
+        for dir in self.anime_dirs(anime_id) {
+            total += dir_size(&dir)?;
+        }
+        Ok(total)
+    }
+
+    /// Delete all of an anime's videos, audio, transcripts, tokens and
+    /// per-anime analysis.
+    ///
+    /// Returns the number of bytes freed.
+    pub fn remove_anime_files(&self, anime_id: u32) -> std::io::Result<u64> {
+        let mut freed = 0;
+        for dir in self.anime_dirs(anime_id) {
+            if dir.exists() {
+                freed += dir_size(&dir)?;
+                std::fs::remove_dir_all(&dir)?;
+            }
+        }
+        Ok(freed)
+    }
+
+    /// Per-anime directories holding generated files
+    fn anime_dirs(&self, anime_id: u32) -> [PathBuf; 5] {
+        [
+            self.video_dir(anime_id),
+            self.audio_dir(anime_id),
+            self.transcript_dir(anime_id),
+            self.tokens_dir(anime_id),
+            self.analysis_dir(anime_id),
+        ]
+    }
+
+    /// List video/audio files whose jobs are already past the stage where
+    /// the file should exist (or that are marked as deleted in the database).
+    ///
+    /// Files the `cleanup` rules keep are left alone: sources the cleanup
+    /// does not delete, videos kept as spot-check samples, and the video and
+    /// audio of a low quality transcript kept for a redo. So are files that
+    /// cannot be matched to a job.
+    pub fn orphaned_files(&self, job_queue: &JobQueue, cleanup: &CleanupConfig) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .find_orphans(job_queue, cleanup)?
+            .into_iter()
+            .map(|(path, _, _)| path)
+            .collect())
+    }
+
+    /// Delete the `orphaned_files` and mark them as deleted.
+    ///
+    /// Returns the number of bytes freed.
+    pub fn cleanup_orphans(&self, job_queue: &mut JobQueue, cleanup: &CleanupConfig) -> Result<u64> {
+        let orphans = self.find_orphans(job_queue, cleanup)?;
+        let mut freed = 0;
+
+        for (path, job_id, file_type) in orphans {
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete orphaned file: {}", path.display()))?;
+            job_queue.mark_file_deleted(job_id, file_type)?;
+
+            info!(
+                job_id = job_id,
+                path = %path.display(),
+                size_mb = size / 1_000_000,
+                "Deleted orphaned file"
+            );
+            freed += size;
+        }
+
+        Ok(freed)
+    }
+
+    /// Match video/audio files on disk to jobs and collect the stale ones
+    fn find_orphans(&self, job_queue: &JobQueue, cleanup: &CleanupConfig) -> Result<Vec<(PathBuf, i64, FileType)>> {
+        let jobs = job_queue.get_all_jobs()?;
+        let by_episode: HashMap<(u32, u32), &Job> =
+            jobs.iter().map(|j| ((j.mal_id, j.episode), j)).collect();
+
+        let mut orphans = Vec::new();
+
+        for (mal_id, dir) in anime_subdirs(&self.videos_base())? {
+            for (episode, path) in episode_files(&dir.join("episodes"))? {
+                let Some(job) = by_episode.get(&(mal_id, episode)) else { continue };
+                if job.has_low_quality_transcript() || cleanup.keep_video_sample(job.id, job.episode) {
+                    continue;
+                }
+                if job.video_deleted
+                    || (cleanup.delete_video_after_transcription && stage_past_transcription(job.stage))
+                {
+                    orphans.push((path, job.id, FileType::Video));
+                }
+            }
+        }
+
+        for (mal_id, dir) in anime_subdirs(&self.audio_base())? {
+            for (episode, path) in episode_files(&dir)? {
+                let Some(job) = by_episode.get(&(mal_id, episode)) else { continue };
+                if job.has_low_quality_transcript() {
+                    continue;
+                }
+                if job.audio_deleted
+                    || job.stage == JobStage::Failed
+                    || (cleanup.delete_audio_after_transcription && stage_past_transcription(job.stage))
+                {
+                    orphans.push((path, job.id, FileType::Audio));
+                }
+            }
+        }
+
+        Ok(orphans)
+    }
+
     /// Create title slug from anime title (for cache filenames)
     pub fn title_to_slug(title: &str) -> String {
         title
@@ -280,6 +536,159 @@ impl DataPaths {
     }
 }
 
+/// Stem of an episode's video, audio and transcript files, e.g.
+/// `Cowboy Bebop_ep001` (the title passed through `sanitize_filename`)
+pub fn episode_file_stem(title: &str, episode: u32) -> String {
+    format!("{}_ep{:03}", sanitize_filename(title), episode)
+}
+
+/// Whether `path` has one of the `VIDEO_EXTENSIONS` (case-insensitive)
+pub fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| VIDEO_EXTENSIONS.iter().any(|v| e.eq_ignore_ascii_case(v)))
+}
+
+/// Maximum length of a sanitized filename component in bytes.
+///
+/// Most filesystems cap names at 255 bytes; this leaves room for suffixes
+/// such as `_ep001.mp4`.
+const MAX_FILENAME_BYTES: usize = 200;
+
+/// Names reserved by Windows (and thus by many NAS/SMB shares)
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Sanitize a title for use as a filename component.
+///
+/// Replaces path separators, reserved punctuation and control characters with
+/// `_`, collapses repeated underscores, strips trailing dots and spaces,
+/// avoids reserved Windows device names and caps the length (on a character
+/// boundary, so multi-byte titles are never split).
+pub fn sanitize_filename(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        };
+        if c == '_' && sanitized.ends_with('_') {
+            continue;
+        }
+        sanitized.push(c);
+    }
+
+    if sanitized.len() > MAX_FILENAME_BYTES {
+        let mut end = MAX_FILENAME_BYTES;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+
+    // Trimmed after truncating, so the cut cannot leave a trailing dot or space
+    let mut sanitized = sanitized
+        .trim_start()
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+
+    if sanitized.is_empty() {
+        return "untitled".to_string();
+    }
+
+    // "CON", "con.txt" etc. are reserved regardless of case or extension
+    let base = sanitized.split('.').next().unwrap_or("");
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(base)) {
+        sanitized.insert(base.len(), '_');
+    }
+
+    sanitized
+}
+
+/// Whether a job has finished transcription (video and audio are no longer needed)
+fn stage_past_transcription(stage: JobStage) -> bool {
+    matches!(
+        stage,
+        JobStage::Transcribed
+            | JobStage::Tokenizing
+            | JobStage::Tokenized
+            | JobStage::Analyzing
+            | JobStage::Complete
+    )
+}
+
+/// List `<base>/<mal_id>` subdirectories
+fn anime_subdirs(base: &Path) -> Result<Vec<(u32, PathBuf)>> {
+    if !base.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(base)
+        .with_context(|| format!("Failed to read directory: {}", base.display()))?
+    {
+        let path = entry?.path();
+        let mal_id = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.parse::<u32>().ok());
+        if let (Some(mal_id), true) = (mal_id, path.is_dir()) {
+            dirs.push((mal_id, path));
+        }
+    }
+    Ok(dirs)
+}
+
+/// List files in a directory whose names carry an `epNNN` episode number
+fn episode_files(dir: &Path) -> Result<Vec<(u32, PathBuf)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        match path.file_stem().and_then(|s| s.to_str()).and_then(episode_from_stem) {
+            Some(episode) => files.push((episode, path)),
+            None => warn!(path = %path.display(), "Skipping file without episode number"),
+        }
+    }
+    Ok(files)
+}
+
+/// Parse the episode number from a stem like `ep001` or `Title_ep001`
+fn episode_from_stem(stem: &str) -> Option<u32> {
+    let idx = stem.rfind("ep")?;
+    stem[idx + 2..].parse().ok()
+}
+
+/// Calculate total size of a directory recursively
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            total += metadata.len();
+        } else if metadata.is_dir() {
+            total += dir_size(&entry.path())?;
+        }
+    }
+    Ok(total)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,13 +698,13 @@ mod tests {
         let paths = DataPaths::new("/data");
 
         assert_eq!(
-            paths.video_file(5114, 1),
-            PathBuf::from("/data/videos/5114/episodes/ep001.mkv")
+            paths.video_file(5114, "Fullmetal Alchemist: Brotherhood", 1, "mkv"),
+            PathBuf::from("/data/videos/5114/episodes/Fullmetal Alchemist_ Brotherhood_ep001.mkv")
         );
 
         assert_eq!(
-            paths.transcript_json(5114, 1),
-            PathBuf::from("/data/transcripts/5114/ep001.json")
+            paths.transcript_json(5114, "Fullmetal Alchemist: Brotherhood", 1),
+            PathBuf::from("/data/transcripts/5114/Fullmetal Alchemist_ Brotherhood_ep001.json")
         );
 
         assert_eq!(
@@ -304,6 +713,238 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_video_file() {
+        assert!(is_video_file(Path::new("/data/videos/5114/episodes/ep001.mkv")));
+        assert!(is_video_file(Path::new("Title_ep001.MP4")));
+        assert!(is_video_file(Path::new("ep001.webm")));
+        assert!(!is_video_file(Path::new("ep001.mkv.part")));
+        assert!(!is_video_file(Path::new("ep001.wav")));
+        assert!(!is_video_file(Path::new("mkv")));
+    }
+
+    #[test]
+    fn test_default_layout_unchanged() {
+        let default_paths = DataPaths::new_with_storage("/data", "/storage");
+        let layout_paths =
+            DataPaths::new_with_layout("/data", "/storage", PathLayout::default());
+
+        assert_eq!(
+            default_paths.video_dir(5114),
+            PathBuf::from("/storage/videos/5114/episodes")
+        );
+        assert_eq!(
+            default_paths.audio_file(5114, "Test Anime", 1, "wav"),
+            PathBuf::from("/data/audio/5114/Test Anime_ep001.wav")
+        );
+        assert_eq!(
+            default_paths.aggregated_dir(),
+            PathBuf::from("/data/analysis/aggregated")
+        );
+        assert_eq!(default_paths.video_dir(5114), layout_paths.video_dir(5114));
+        assert_eq!(default_paths.logs_dir(), layout_paths.logs_dir());
+    }
+
+    #[test]
+    fn test_custom_layout() -> std::io::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let scratch = temp_dir.path().join("scratch");
+        let durable = temp_dir.path().join("durable");
+
+        let layout = PathLayout {
+            videos: scratch.join("videos"),
+            transcripts: durable.join("transcripts"),
+            ..PathLayout::default()
+        };
+        let paths = DataPaths::new_with_layout(temp_dir.path().join("data"), "/unused", layout);
+
+        assert_eq!(
+            paths.video_file(5114, "Test Anime", 1, "mp4"),
+            scratch.join("videos/5114/episodes/Test Anime_ep001.mp4")
+        );
+        assert_eq!(
+            paths.transcript_txt(5114, "Test Anime", 1),
+            durable.join("transcripts/5114/Test Anime_ep001.txt")
+        );
+        // Categories left at their defaults still resolve against root
+        assert_eq!(
+            paths.tokens_dir(5114),
+            temp_dir.path().join("data/tokens/5114")
+        );
+
+        paths.create_dirs()?;
+        assert!(scratch.join("videos").is_dir());
+        assert!(durable.join("transcripts").is_dir());
+        assert!(!temp_dir.path().join("data/transcripts").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(
+            sanitize_filename("Fullmetal Alchemist: Brotherhood"),
+            "Fullmetal Alchemist_ Brotherhood"
+        );
+        assert_eq!(
+            sanitize_filename("Attack on Titan: Season 2"),
+            "Attack on Titan_ Season 2"
+        );
+        assert_eq!(sanitize_filename("Normal Title"), "Normal Title");
+        assert_eq!(
+            sanitize_filename("Title/with\\invalid:chars"),
+            "Title_with_invalid_chars"
+        );
+        assert_eq!(sanitize_filename("What?!: The Movie"), "What_!_ The Movie");
+        assert_eq!(sanitize_filename("Re:Zero..."), "Re_Zero");
+        assert_eq!(sanitize_filename("鋼の錬金術師"), "鋼の錬金術師");
+        assert_eq!(sanitize_filename("???"), "_");
+        assert_eq!(sanitize_filename("   "), "untitled");
+    }
+
+    #[test]
+    fn test_sanitize_filename_reserved_names() {
+        assert_eq!(sanitize_filename("CON"), "CON_");
+        assert_eq!(sanitize_filename("nul"), "nul_");
+        assert_eq!(sanitize_filename("Com1.txt"), "Com1_.txt");
+        assert_eq!(sanitize_filename("Console Wars"), "Console Wars");
+    }
+
+    #[test]
+    fn test_sanitize_filename_control_chars() {
+        assert_eq!(sanitize_filename("Tab\tTitle\n"), "Tab_Title_");
+        assert_eq!(sanitize_filename("Bell\u{7}\u{7}Title"), "Bell_Title");
+    }
+
+    #[test]
+    fn test_sanitize_filename_length() {
+        let long_ascii = "a".repeat(500);
+        assert_eq!(sanitize_filename(&long_ascii).len(), MAX_FILENAME_BYTES);
+
+        // 3-byte characters must not be split
+        let long_japanese = "錬".repeat(100);
+        let sanitized = sanitize_filename(&long_japanese);
+        assert!(sanitized.len() <= MAX_FILENAME_BYTES);
+        assert_eq!(sanitized.chars().count(), MAX_FILENAME_BYTES / 3);
+
+        // Dots and (full-width) spaces the cut leaves at the end are trimmed
+        for filler in [". ", "\u{3000}.", "a."] {
+            let sanitized = sanitize_filename(&format!("{}{}", "a".repeat(MAX_FILENAME_BYTES - 2), filler.repeat(50)));
+            assert!(sanitized.len() <= MAX_FILENAME_BYTES);
+            assert!(!sanitized.ends_with(['.', ' ', '\u{3000}']), "{:?}", sanitized);
+        }
+    }
+
+    #[test]
+    fn test_episode_from_stem() {
+        assert_eq!(episode_from_stem("ep001"), Some(1));
+        assert_eq!(episode_from_stem("Step Up_ep012"), Some(12));
+        assert_eq!(episode_from_stem("metadata"), None);
+    }
+
+    #[test]
+    fn test_orphaned_files() -> Result<()> {
+        use crate::{Anime, Database, NewJob};
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let paths = DataPaths::new(temp_dir.path());
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut queue = JobQueue::new(Database::open(&db_path)?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 4);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=4 {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?);
+        }
+        // Keeps the video of every second job, i.e. episode 2
+        let cleanup = CleanupConfig {
+            keep_video_sample_rate: 2,
+            ..CleanupConfig::default()
+        };
+        assert!(cleanup.keep_video_sample(job_ids[1], 2));
+
+        // Episode 1 is transcribed, but its video and audio were left behind
+        queue.update_stage(job_ids[0], JobStage::Transcribed)?;
+        // Episode 2's video is a spot-check sample
+        queue.update_stage(job_ids[1], JobStage::Transcribed)?;
+        // Episode 3's transcript is low quality, so both are kept for a redo
+        queue.update_stage(job_ids[2], JobStage::Transcribed)?;
+        Database::open(&db_path)?.conn().execute(
+            "UPDATE jobs SET transcript_quality = 0.2 WHERE id = ?1",
+            [job_ids[2]],
+        )?;
+        // Episode 4 is waiting for transcription, so its video is still needed
+        queue.update_stage(job_ids[3], JobStage::Downloaded)?;
+
+        std::fs::create_dir_all(paths.video_dir(5114))?;
+        std::fs::create_dir_all(paths.audio_dir(5114))?;
+        let file = |dir: PathBuf, episode: u32, extension: &str, size: usize| -> Result<PathBuf> {
+            let path = dir.join(format!("Test Anime_ep{:03}.{}", episode, extension));
+            std::fs::write(&path, vec![0u8; size])?;
+            Ok(path)
+        };
+        let stale_video = file(paths.video_dir(5114), 1, "mp4", 1000)?;
+        let stale_audio = file(paths.audio_dir(5114), 1, "wav", 500)?;
+        let sample_video = file(paths.video_dir(5114), 2, "mp4", 1000)?;
+        let redo_video = file(paths.video_dir(5114), 3, "mp4", 1000)?;
+        let redo_audio = file(paths.audio_dir(5114), 3, "wav", 500)?;
+        let live_video = file(paths.video_dir(5114), 4, "mp4", 2000)?;
+
+        let mut orphans = paths.orphaned_files(&queue, &cleanup)?;
+        orphans.sort();
+        assert_eq!(orphans, vec![stale_audio.clone(), stale_video.clone()]);
+
+        // Sources the cleanup does not delete are not orphans
+        let keep_all = CleanupConfig {
+            delete_video_after_transcription: false,
+            delete_audio_after_transcription: false,
+            ..cleanup.clone()
+        };
+        assert!(paths.orphaned_files(&queue, &keep_all)?.is_empty());
+
+        let freed = paths.cleanup_orphans(&mut queue, &cleanup)?;
+        assert_eq!(freed, 1500);
+        assert!(!stale_video.exists());
+        assert!(!stale_audio.exists());
+        for kept in [&sample_video, &redo_video, &redo_audio, &live_video] {
+            assert!(kept.exists(), "{}", kept.display());
+        }
+
+        let jobs = queue.get_all_jobs()?;
+        let job = jobs.iter().find(|j| j.id == job_ids[0]).unwrap();
+        assert!(job.video_deleted && job.audio_deleted);
+        assert!(paths.orphaned_files(&queue, &cleanup)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_usage_for_anime() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let paths = DataPaths::new(temp_dir.path());
+
+        std::fs::create_dir_all(paths.video_dir(5114))?;
+        std::fs::create_dir_all(paths.audio_dir(5114))?;
+        std::fs::create_dir_all(paths.video_dir(9253))?;
+        std::fs::write(paths.video_dir(5114).join("Test Anime_ep001.mp4"), vec![0u8; 1000])?;
+        std::fs::write(paths.audio_dir(5114).join("Test Anime_ep001.wav"), vec![0u8; 500])?;
+        std::fs::write(paths.video_dir(9253).join("Other Anime_ep001.mp4"), vec![0u8; 2000])?;
+
+        assert_eq!(paths.disk_usage_for_anime(5114)?, 1500);
+        assert_eq!(paths.disk_usage_for_anime(1535)?, 0);
+
+        Ok(())
+    }
+
     #[test]
     fn test_title_slug() {
         assert_eq!(
@@ -0,0 +1,373 @@
+//! Pre-flight checks for the workers' `--validate` mode.
+//!
+//! Verifies that the configuration is valid, the database schema is current,
+//! the data directories are writable and the external tools a worker shells
+//! out to are installed, without touching any jobs. `pipeline doctor` runs
+//! the read-only variants of these checks (`check_dir_access`,
+//! `check_schema_read_only`), which create and migrate nothing.
+
+use crate::config::{Config, DEFAULT_CONFIG_PATH};
+use crate::db::Database;
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// External tool a worker depends on, with the arguments that print its version
+#[derive(Debug, Clone, Copy)]
+pub struct ExternalTool {
+    pub name: &'static str,
+    pub version_args: &'static [&'static str],
+    /// Python package whose version is the tool's version, for Python
+    /// scripts without a version flag (see `manifest::capture_tool_version`)
+    pub python_package: Option<&'static str>,
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    /// What was found (on success) or what went wrong (on failure)
+    pub detail: String,
+}
+
+/// All check results of a validation run
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl PreflightReport {
+    /// Record the outcome of a check
+    pub fn add(&mut self, name: impl Into<String>, result: Result<String>) {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(e) => (false, format!("{:#}", e)),
+        };
+        self.checks.push(CheckResult {
+            name: name.into(),
+            passed,
+            detail,
+        });
+    }
+
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = if check.passed { " OK " } else { "FAIL" };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+        let failed = self.checks.iter().filter(|c| !c.passed).count();
+        if failed == 0 {
+            write!(f, "All {} checks passed", self.checks.len())
+        } else {
+            write!(f, "{} of {} checks failed", failed, self.checks.len())
+        }
+    }
+}
+
+/// Run every check for a worker that needs `tools`.
+///
+/// `config_path` and `profile` are the `--config` and `--profile` flags, if
+/// given (see `Config::load`).
+pub fn run(config_path: Option<&Path>, profile: Option<&str>, tools: &[ExternalTool]) -> PreflightReport {
+    let mut report = PreflightReport::default();
+
+    let config = match check_config(config_path, profile) {
+        Ok((config, detail)) => {
+            report.add("config", Ok(detail));
+            Some(config)
+        }
+        Err(e) => {
+            report.add("config", Err(e));
+            None
+        }
+    };
+
+    if let Some(config) = &config {
+        // The directories first, as opening the database needs its directory
+        for (name, dir) in directories(config) {
+            report.add(name, check_writable(&dir));
+        }
+        report.add("database schema", check_schema(&config.database_path()));
+    }
+
+    for tool in tools {
+        report.add(tool.name, check_binary(tool.name, tool.version_args));
+    }
+
+    report
+}
+
+/// Load and validate the config, returning it with where it was read from
+pub fn check_config(config_path: Option<&Path>, profile: Option<&str>) -> Result<(Config, String)> {
+    let config = Config::load(config_path, profile)?;
+    config.validate()?;
+
+    let config_path = config_path.unwrap_or(Path::new(DEFAULT_CONFIG_PATH));
+    let detail = if config_path.exists() {
+        config_path.display().to_string()
+    } else {
+        format!("{} not found, using defaults", config_path.display())
+    };
+    Ok((config, detail))
+}
+
+/// The directories the workers write to, by check name.
+///
+/// The data, storage and log directories come first, followed by the
+/// categories moved elsewhere by a `[data]` override.
+pub fn directories(config: &Config) -> Vec<(&'static str, PathBuf)> {
+    let mut dirs = vec![
+        ("data directory", config.data_dir()),
+        ("storage directory", config.storage_dir()),
+        ("log directory", config.log_dir()),
+    ];
+
+    let data_paths = config.data_paths();
+    for (name, dir, base) in [
+        ("videos directory", &config.data.videos_dir, data_paths.videos_base()),
+        ("audio directory", &config.data.audio_dir, data_paths.audio_base()),
+        ("transcripts directory", &config.data.transcripts_dir, data_paths.transcripts_base()),
+        ("tokens directory", &config.data.tokens_dir, data_paths.tokens_base()),
+        ("analysis directory", &config.data.analysis_dir, data_paths.analysis_base()),
+    ] {
+        if dir.is_some() {
+            dirs.push((name, base));
+        }
+    }
+    dirs
+}
+
+/// Open (and migrate) the database, which compares it with `schema.sql`.
+pub fn check_schema(db_path: &Path) -> Result<String> {
+    Database::open(db_path).with_context(|| format!("Failed to open database at {}", db_path.display()))?;
+    Ok(db_path.display().to_string())
+}
+
+/// Check that opening the database will succeed, without creating or
+/// migrating it.
+///
+/// Differences from `schema.sql` that the automatic migrations fix are
+/// listed but pass.
+pub fn check_schema_read_only(db_path: &Path) -> Result<String> {
+    if !db_path.exists() {
+        return Ok(format!("{} (created on the first run)", db_path.display()));
+    }
+
+    let missing = Database::schema_differences_after_migrations(db_path)
+        .with_context(|| format!("Failed to read database at {}", db_path.display()))?;
+    if !missing.is_empty() {
+        anyhow::bail!("{} lacks {}, which no migration adds", db_path.display(), missing.join(", "));
+    }
+
+    let migrated = Database::open_read_only(db_path)?.schema_differences()?;
+    if migrated.is_empty() {
+        Ok(db_path.display().to_string())
+    } else {
+        Ok(format!("{} (migrated on the next run: {})", db_path.display(), migrated.join(", ")))
+    }
+}
+
+/// Create `dir` if needed and check a file can be written to it.
+pub fn check_writable(dir: &Path) -> Result<String> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let probe = dir.join(".gda2025_write_test");
+    std::fs::write(&probe, b"ok").with_context(|| format!("{} is not writable", dir.display()))?;
+    std::fs::remove_file(&probe)?;
+
+    Ok(dir.display().to_string())
+}
+
+/// Check that `dir`, or the directory it would be created in, is writable,
+/// without creating or writing anything.
+pub fn check_dir_access(dir: &Path) -> Result<String> {
+    let existing = dir
+        .ancestors()
+        .map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p })
+        .find(|p| p.exists())
+        .with_context(|| format!("No existing parent of {}", dir.display()))?;
+
+    if !existing.is_dir() {
+        anyhow::bail!("{} is not a directory", existing.display());
+    }
+    if !is_writable(existing) {
+        anyhow::bail!("{} is not writable", existing.display());
+    }
+
+    if existing == dir {
+        Ok(dir.display().to_string())
+    } else {
+        Ok(format!("{} (created on the first run)", dir.display()))
+    }
+}
+
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string for the call's duration
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    path.metadata().map(|m| !m.permissions().readonly()).unwrap_or(false)
+}
+
+/// Check that `name` is on `PATH` and runs with `version_args`.
+pub fn check_binary(name: &str, version_args: &[&str]) -> Result<String> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    check_binary_in(name, version_args, &path)
+}
+
+/// Like `check_binary`, searching the given `PATH`-style list of directories.
+///
+/// Returns the first line the tool prints (usually its version) and where
+/// it was found.
+pub fn check_binary_in(name: &str, version_args: &[&str], path: &OsStr) -> Result<String> {
+    let binary = find_in_path(name, path).with_context(|| format!("{} not found in PATH", name))?;
+
+    let output = Command::new(&binary)
+        .args(version_args)
+        .output()
+        .with_context(|| format!("Failed to run {}", binary.display()))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "`{} {}` exited with code {}",
+            binary.display(),
+            version_args.join(" "),
+            output.status.code().unwrap_or(-1)
+        );
+    }
+
+    // Some tools print their version to stderr
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    let text = String::from_utf8_lossy(&text);
+    let version = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("unknown version");
+
+    Ok(format!("{} ({})", version, binary.display()))
+}
+
+/// Find an executable called `name` in a `PATH`-style list of directories.
+pub(crate) fn find_in_path(name: &str, path: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    fn write_script(dir: &Path, name: &str, body: &str) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_binary_with_fake_path() -> Result<()> {
+        let empty_dir = TempDir::new()?;
+        let bin_dir = TempDir::new()?;
+        write_script(bin_dir.path(), "ffmpeg", "echo 'ffmpeg version 6.1.1'")?;
+        write_script(bin_dir.path(), "broken", "exit 3")?;
+        // Present but not executable
+        fs::write(bin_dir.path().join("whisper"), "not a program")?;
+
+        let path = std::env::join_paths([empty_dir.path(), bin_dir.path()])?;
+
+        let found = check_binary_in("ffmpeg", &["-version"], &path)?;
+        assert!(found.starts_with("ffmpeg version 6.1.1 ("), "{}", found);
+        assert!(found.contains(&bin_dir.path().display().to_string()));
+
+        let err = check_binary_in("ani-cli", &["--version"], &path).unwrap_err();
+        assert_eq!(err.to_string(), "ani-cli not found in PATH");
+
+        assert!(check_binary_in("whisper", &["--help"], &path).is_err());
+
+        let err = check_binary_in("broken", &["--version"], &path).unwrap_err();
+        assert!(err.to_string().ends_with("exited with code 3"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_checks_create_nothing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_dir = temp_dir.path().join("data").join("videos");
+        let db_path = temp_dir.path().join("data").join("jobs.db");
+
+        assert!(check_dir_access(&data_dir)?.ends_with("(created on the first run)"));
+        assert!(check_schema_read_only(&db_path)?.ends_with("(created on the first run)"));
+        assert!(!temp_dir.path().join("data").exists());
+
+        check_writable(&data_dir)?;
+        assert_eq!(check_dir_access(&data_dir)?, data_dir.display().to_string());
+
+        // A migratable difference passes and is left for the next open
+        Database::open(&db_path)?;
+        rusqlite::Connection::open(&db_path)?.execute_batch("ALTER TABLE jobs DROP COLUMN recap_lines;")?;
+        let detail = check_schema_read_only(&db_path)?;
+        assert!(detail.ends_with("(migrated on the next run: column jobs.recap_lines)"), "{}", detail);
+        assert_eq!(Database::open_read_only(&db_path)?.schema_differences()?, ["column jobs.recap_lines"]);
+
+        rusqlite::Connection::open(&db_path)?.execute_batch("ALTER TABLE anime DROP COLUMN rating;")?;
+        let err = check_schema_read_only(&db_path).unwrap_err().to_string();
+        assert!(err.ends_with("lacks column anime.rating, which no migration adds"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+
+        let mut report = PreflightReport::default();
+        report.add("data directory", check_writable(&temp_dir.path().join("data")));
+        report.add("database schema", check_schema(&temp_dir.path().join("jobs.db")));
+        assert!(report.passed());
+
+        report.add("aria2c", Err(anyhow::anyhow!("aria2c not found in PATH")));
+        assert!(!report.passed());
+
+        let text = report.to_string();
+        assert!(text.contains("[ OK ] data directory: "));
+        assert!(text.contains("[FAIL] aria2c: aria2c not found in PATH\n"));
+        assert!(text.ends_with("1 of 3 checks failed"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Timeouts for external processes.
+//!
+//! `Child::wait` blocks for as long as the child runs, so a tool that hangs
+//! (ani-cli on a stalled connection, whisper stuck on bad audio) would wedge
+//! its worker for good. `wait_with_timeout` kills the child once its timeout
+//! has passed and fails with `ProcessTimeout`, which workers retry like any
+//! other transient failure.
+//!
+//! Children started with `own_process_group` are killed together with every
+//! process they started (ani-cli's downloader, ffmpeg under whisper), so
+//! nothing keeps writing files or holding the pipes after a timeout.
+//! Elsewhere only the child itself is killed. Output is read on separate
+//! threads, which are left behind if a pipe stays open.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often a child is checked for having exited once its stdout is done
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Returned when an external process ran past its timeout and was killed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{program} did not finish within {timeout:?} and was killed")]
+pub struct ProcessTimeout {
+    pub program: String,
+    pub timeout: Duration,
+}
+
+/// Start `command` in a process group of its own (Unix), so a timeout kills
+/// whatever it started too
+pub fn own_process_group(command: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command
+}
+
+/// Wait for `child` to exit, killing it once `timeout` has passed (never,
+/// without one).
+///
+/// The child's stdout, if piped, is read line by line to the end and handed
+/// to `on_line` as it arrives; invalid UTF-8 is replaced rather than ending
+/// the read. `program` names the child in the error.
+pub fn wait_with_timeout(
+    child: &mut Child,
+    program: &str,
+    timeout: Option<Duration>,
+    mut on_line: impl FnMut(&str),
+) -> Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        if let Some(stdout) = child.stdout.take() {
+            for_each_line(stdout, |line| {
+                on_line(&line);
+                true
+            });
+        }
+        return child.wait().with_context(|| format!("Failed to wait for {}", program));
+    };
+    let deadline = Instant::now() + timeout;
+
+    if let Some(stdout) = child.stdout.take() {
+        let lines = read_lines(stdout);
+        loop {
+            match lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) => on_line(&line),
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => return Err(kill(child, program, timeout)),
+            }
+        }
+    }
+
+    loop {
+        if let Some(status) = child.try_wait().with_context(|| format!("Failed to wait for {}", program))? {
+            return Ok(status);
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(kill(child, program, timeout));
+        }
+        thread::sleep(left.min(POLL_INTERVAL));
+    }
+}
+
+/// Read all of `pipe` (e.g. a child's stderr) on a separate thread, so the
+/// child can't block on a full pipe while it is being waited for
+pub fn collect_output(pipe: impl Read + Send + 'static) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = BufReader::new(pipe).read_to_end(&mut output);
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
+/// Hand each line of `pipe` (without its line ending, invalid UTF-8
+/// replaced) to `on_line` until the pipe is closed or `on_line` returns
+/// `false`.
+///
+/// Tools like ffmpeg print file names and metadata in whatever encoding they
+/// find, so a line that isn't UTF-8 must not end the read: the pipe would
+/// stop being drained and the child would block on it.
+pub fn for_each_line(pipe: impl Read, mut on_line: impl FnMut(String) -> bool) {
+    let mut reader = BufReader::new(pipe);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+        if buf.ends_with(b"\n") {
+            buf.pop();
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+        if !on_line(String::from_utf8_lossy(&buf).into_owned()) {
+            break;
+        }
+    }
+}
+
+/// Lines of `pipe`, read on a separate thread
+fn read_lines(pipe: impl Read + Send + 'static) -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || for_each_line(pipe, |line| tx.send(line).is_ok()));
+    rx
+}
+
+/// Kill and reap `child`, which ran past `timeout`
+fn kill(child: &mut Child, program: &str, timeout: Duration) -> anyhow::Error {
+    warn!(program, pid = child.id(), timeout_secs = timeout.as_secs(), "Process timed out, killing it");
+    kill_process_group(child);
+    // It may have exited in the meantime, which is fine
+    let _ = child.kill();
+    let _ = child.wait();
+    ProcessTimeout {
+        program: program.to_string(),
+        timeout,
+    }
+    .into()
+}
+
+/// Kill the process group `child` leads, if it was started with
+/// `own_process_group`
+#[cfg(unix)]
+fn kill_process_group(child: &Child) {
+    let Ok(pid) = libc::pid_t::try_from(child.id()) else {
+        return;
+    };
+    // SAFETY: getpgid and kill take no pointers. The group is only signalled
+    // if the child leads it, so the worker's own group is never hit.
+    unsafe {
+        if libc::getpgid(pid) == pid {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_child: &Child) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_hung_process_is_killed() -> Result<()> {
+        // Prints a line, then hangs without closing stdout
+        let mut child = Command::new("sh")
+            .args(["-c", "echo started; exec sleep 30"])
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let mut lines = Vec::new();
+        let started = Instant::now();
+        let err = wait_with_timeout(&mut child, "sleep", Some(Duration::from_millis(300)), |line| {
+            lines.push(line.to_string())
+        })
+        .unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(10), "{:?}", started.elapsed());
+        assert_eq!(
+            err.downcast_ref::<ProcessTimeout>(),
+            Some(&ProcessTimeout {
+                program: "sleep".to_string(),
+                timeout: Duration::from_millis(300),
+            })
+        );
+        assert_eq!(lines, ["started"]);
+        // Killed and reaped
+        assert!(child.try_wait()?.is_some());
+
+        // Without piped stdout too
+        let mut child = Command::new("sleep").arg("30").spawn()?;
+        let err = wait_with_timeout(&mut child, "sleep", Some(Duration::from_millis(100)), |_| {}).unwrap_err();
+        assert!(err.downcast_ref::<ProcessTimeout>().is_some());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_process_group() -> Result<()> {
+        // The background sleep holds stderr open until it is killed too
+        let mut child = own_process_group(&mut Command::new("sh"))
+            .args(["-c", "sleep 30 & wait"])
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stderr = collect_output(child.stderr.take().unwrap());
+
+        let started = Instant::now();
+        let err = wait_with_timeout(&mut child, "sh", Some(Duration::from_millis(300)), |_| {}).unwrap_err();
+        assert!(err.downcast_ref::<ProcessTimeout>().is_some());
+        stderr.join().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10), "{:?}", started.elapsed());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_utf8_line_is_replaced() -> Result<()> {
+        for timeout in [Some(Duration::from_secs(30)), None] {
+            let mut child = Command::new("sh")
+                .args(["-c", r"printf 'a\377b\r\nnext\nlast'"])
+                .stdout(Stdio::piped())
+                .spawn()?;
+
+            let mut lines = Vec::new();
+            wait_with_timeout(&mut child, "sh", timeout, |line| lines.push(line.to_string()))?;
+            assert_eq!(lines, ["a\u{FFFD}b", "next", "last"]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_within_timeout() -> Result<()> {
+        for timeout in [Some(Duration::from_secs(30)), None] {
+            let mut child = Command::new("sh")
+                .args(["-c", "echo one; echo two >&2; echo three; exit 3"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            let stderr = collect_output(child.stderr.take().unwrap());
+
+            let mut lines = Vec::new();
+            let status = wait_with_timeout(&mut child, "sh", timeout, |line| lines.push(line.to_string()))?;
+            assert_eq!(status.code(), Some(3));
+            assert_eq!(lines, ["one", "three"]);
+            assert_eq!(stderr.join().unwrap(), "two\n");
+        }
+        Ok(())
+    }
+}
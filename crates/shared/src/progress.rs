@@ -0,0 +1,214 @@
+//! Progress bars for long CLI runs (`--progress`).
+//!
+//! Bars are drawn on stderr, and only once `enable` has been called, which
+//! binaries do when `--progress` is given and stderr is a terminal. While
+//! bars are drawn the console log layer only prints warnings and errors, and
+//! prints them above the bars (see `ConsoleWriter`), so the two never end up
+//! on the same line; the log file still gets every event.
+//!
+//! A `ProgressBar` does nothing until bars are enabled, so code reporting
+//! progress does not need to know whether anyone is watching.
+
+use indicatif::{HumanDuration, MultiProgress, ProgressStyle};
+use std::borrow::Cow;
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Every bar of this process, once bars are enabled
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Whether `--progress` (`requested`) can draw bars: not when stderr is not
+/// a terminal (piped or redirected), nor with `--verbose`, whose debug logs
+/// are meant to be read on the console
+pub fn should_enable(requested: bool, verbose: bool) -> bool {
+    requested && !verbose && io::stderr().is_terminal()
+}
+
+/// Draw progress bars from now on
+///
+/// Call this before `logging::init`, which only makes console logs give
+/// way to the bars when they are enabled.
+pub fn enable() {
+    BARS.get_or_init(MultiProgress::new);
+}
+
+/// Whether `enable` has been called
+pub fn is_enabled() -> bool {
+    BARS.get().is_some()
+}
+
+/// How far along a bar is, computed apart from drawing it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressState {
+    /// Items done so far
+    pub done: u64,
+    /// Items in total (0 = unknown)
+    pub total: u64,
+    /// Time since the bar was started
+    pub elapsed: Duration,
+}
+
+impl ProgressState {
+    /// Fraction of the items done, from 0.0 to 1.0 (None without a total)
+    pub fn fraction(&self) -> Option<f64> {
+        (self.total > 0).then(|| (self.done as f64 / self.total as f64).min(1.0))
+    }
+
+    /// Time left at the average rate so far (None before the first item is
+    /// done, or without a total)
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 || self.total == 0 {
+            return None;
+        }
+        let remaining = self.total.saturating_sub(self.done);
+        Some(self.elapsed.mul_f64(remaining as f64 / self.done as f64))
+    }
+}
+
+impl From<&indicatif::ProgressState> for ProgressState {
+    fn from(state: &indicatif::ProgressState) -> Self {
+        Self {
+            done: state.pos(),
+            total: state.len().unwrap_or(0),
+            elapsed: state.elapsed(),
+        }
+    }
+}
+
+/// A progress bar, drawn only when bars are enabled
+///
+/// Clones draw the same bar, so workers on several threads or tasks can
+/// each hold one and advance it.
+#[derive(Clone)]
+pub struct ProgressBar {
+    bar: Option<indicatif::ProgressBar>,
+}
+
+impl ProgressBar {
+    /// A bar labelled `label` for `total` items (0 = unknown)
+    pub fn new(label: &str, total: u64) -> Self {
+        let bar = BARS.get().map(|bars| {
+            let bar = bars.add(indicatif::ProgressBar::new(total));
+            bar.set_style(style());
+            bar.set_prefix(label.to_string());
+            bar
+        });
+        Self { bar }
+    }
+
+    /// A bar that is never drawn
+    pub fn hidden() -> Self {
+        Self { bar: None }
+    }
+
+    /// Count `n` more items as done
+    pub fn inc(&self, n: u64) {
+        if let Some(bar) = &self.bar {
+            bar.inc(n);
+        }
+    }
+
+    /// Change the total, e.g. once it is known
+    pub fn set_total(&self, total: u64) {
+        if let Some(bar) = &self.bar {
+            bar.set_length(total);
+        }
+    }
+
+    /// Show `message` after the bar (e.g. the item being worked on)
+    pub fn set_message(&self, message: impl Into<Cow<'static, str>>) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(message);
+        }
+    }
+
+    /// Stop the bar, leaving its final state on screen
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish();
+        }
+    }
+}
+
+fn style() -> ProgressStyle {
+    ProgressStyle::with_template("{prefix:>10} [{bar:30}] {pos}/{len} {percent_done} ETA {time_left} {wide_msg}")
+        .expect("progress bar template is valid")
+        .with_key("percent_done", |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+            if let Some(fraction) = ProgressState::from(state).fraction() {
+                let _ = write!(w, "{:5.1}%", fraction * 100.0);
+            }
+        })
+        .with_key("time_left", |state: &indicatif::ProgressState, w: &mut dyn std::fmt::Write| {
+            let _ = match ProgressState::from(state).eta() {
+                Some(eta) => write!(w, "{}", HumanDuration(eta)),
+                None => write!(w, "-"),
+            };
+        })
+        .progress_chars("=> ")
+}
+
+/// Writer for the console log layer: writes to stdout, first clearing any
+/// bars and redrawing them below the log line
+pub struct ConsoleWriter;
+
+impl Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match BARS.get() {
+            Some(bars) => bars.suspend(|| io::stdout().write(buf)),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        // One event, one suspension
+        match BARS.get() {
+            Some(bars) => bars.suspend(|| io::stdout().write_all(buf)),
+            None => io::stdout().write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_state_fraction_and_eta() {
+        let state = |done, total, secs| ProgressState {
+            done,
+            total,
+            elapsed: Duration::from_secs(secs),
+        };
+
+        // 25 of 100 in 10s: 30s to go at the same rate
+        assert_eq!(state(25, 100, 10).fraction(), Some(0.25));
+        assert_eq!(state(25, 100, 10).eta(), Some(Duration::from_secs(30)));
+
+        // Nothing done yet, or no total: no estimate
+        assert_eq!(state(0, 100, 10).fraction(), Some(0.0));
+        assert_eq!(state(0, 100, 10).eta(), None);
+        assert_eq!(state(5, 0, 10).fraction(), None);
+        assert_eq!(state(5, 0, 10).eta(), None);
+
+        // More done than expected (the total grew) caps at done
+        assert_eq!(state(120, 100, 10).fraction(), Some(1.0));
+        assert_eq!(state(120, 100, 10).eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_hidden_bar_is_inert() {
+        // Never enabled in tests, so bars are not drawn
+        let bar = ProgressBar::new("anime", 10);
+        assert!(bar.bar.is_none());
+        bar.inc(1);
+        bar.set_total(20);
+        bar.set_message("Test Anime");
+        bar.finish();
+        assert!(ProgressBar::hidden().bar.is_none());
+    }
+}
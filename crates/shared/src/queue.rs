@@ -3,32 +3,146 @@
 //! This module provides a high-level API for managing jobs in the SQLite database,
 //! including creating jobs, updating status, and deduplication.
 
+use crate::config::DequeueOrder;
+use crate::db::CLASSIFICATION_COLUMNS;
+use crate::export::{self, ExportFormat};
 use crate::models::*;
+use crate::paths::DataPaths;
+use crate::retry::{retry_delay, RetryPolicy};
 use crate::Database;
 use anyhow::{Context, Result};
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, Connection, OptionalExtension, Savepoint, Transaction, TransactionBehavior};
+use std::collections::HashMap;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
+/// Error message prefix for jobs blocked on a low-confidence anime selection
+pub const NEEDS_REVIEW_ERROR: &str = "Needs manual review";
+
+/// Signature `failure_summary` reports for failed jobs without an error message
+pub const NO_ERROR_MESSAGE: &str = "(no error message)";
+
+/// Number of anime `retry_report` lists by retries consumed
+pub const RETRY_REPORT_TOP_ANIME: usize = 10;
+
 /// Job queue manager
 pub struct JobQueue {
     db: Database,
+    dequeue_order: DequeueOrder,
+    /// Priority and MAL ID of the anime last claimed from each set of
+    /// stages, where breadth-first dequeueing carries on from
+    last_dequeued: HashMap<Vec<JobStage>, (i32, u32)>,
+    /// Delays before failed jobs are retried (None = `retry_delay`)
+    retry_policy: Option<RetryPolicy>,
+    /// Only jobs of anime matching this are dequeued (None = every anime)
+    anime_filter: Option<AnimeFilter>,
 }
 
 impl JobQueue {
     /// Create a new job queue with the given database
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self {
+            db,
+            dequeue_order: DequeueOrder::default(),
+            last_dequeued: HashMap::new(),
+            retry_policy: None,
+            anime_filter: None,
+        }
+    }
+
+    /// Wait as `policy` says, rather than `retry_delay`, before a failed
+    /// job can be dequeued again (`[pipeline.retry]`)
+    pub fn with_retry_policy(mut self, policy: Option<RetryPolicy>) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Only claim jobs of anime matching `filter` in `dequeue`,
+    /// `dequeue_next`, `dequeue_batch` and `dequeue_next_any`
+    ///
+    /// The matching anime are looked up on every claim, so anime saved
+    /// after the queue was created are picked up too.
+    pub fn with_anime_filter(mut self, filter: AnimeFilter) -> Self {
+        self.anime_filter = filter.is_active().then_some(filter);
+        self
+    }
+
+    /// Set the order in which `dequeue`, `dequeue_next`, `dequeue_batch`
+    /// and `dequeue_next_any` claim jobs
+    ///
+    /// With `BreadthFirst`, consecutive claims go round the anime with ready
+    /// jobs, in priority order, taking each one's next episode in turn. The
+    /// position in the round is kept by this queue, so workers sharing it
+    /// share the round, while separate processes each keep their own.
+    pub fn with_dequeue_order(mut self, order: DequeueOrder) -> Self {
+        self.dequeue_order = order;
+        self
+    }
+
+    /// Run `f` against this queue inside a single transaction
+    ///
+    /// Every queue call `f` makes is committed at once when it returns `Ok`,
+    /// and all of them are rolled back when it returns `Err` or panics (the
+    /// panic then carries on unwinding). Transactions nest: a
+    /// `with_transaction` inside `f`, or a method that is atomic on its own
+    /// such as `dequeue`, becomes part of the outer transaction.
+    ///
+    /// `f` borrows the queue mutably for its whole run, so workers sharing an
+    /// `Arc<Mutex<JobQueue>>` hold the lock throughout: take it once around
+    /// the call, and keep slow work (file I/O, external tools) out of `f`.
+    pub fn with_transaction<T>(&mut self, f: impl FnOnce(&mut JobQueue) -> Result<T>) -> Result<T> {
+        self.db
+            .conn_mut()
+            .execute_batch("SAVEPOINT job_queue_transaction")
+            .context("Failed to begin transaction")?;
+
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self))) {
+            Ok(Ok(value)) => {
+                if let Err(e) = self.db.conn_mut().execute_batch("RELEASE job_queue_transaction") {
+                    self.rollback_transaction();
+                    return Err(e).context("Failed to commit transaction");
+                }
+                Ok(value)
+            }
+            Ok(Err(e)) => {
+                self.rollback_transaction();
+                Err(e)
+            }
+            Err(panic) => {
+                self.rollback_transaction();
+                std::panic::resume_unwind(panic)
+            }
+        }
+    }
+
+    /// Undo and close the innermost `with_transaction`
+    fn rollback_transaction(&mut self) {
+        let rollback = self
+            .db
+            .conn_mut()
+            .execute_batch("ROLLBACK TO job_queue_transaction; RELEASE job_queue_transaction");
+        if let Err(e) = rollback {
+            warn!(error = %e, "Failed to roll back transaction");
+        }
     }
 
     /// Get or create an anime entry (deduplication)
     ///
-    /// If an anime with the given MAL ID already exists, return its database ID.
-    /// Otherwise, insert the new anime and return the new ID.
+    /// If an anime with the given MAL ID already exists, return its database
+    /// ID, after filling in the list columns (classifications, opening and
+    /// ending themes) it has none of yet, e.g. a row scraped before a column
+    /// was added. Otherwise, insert the new anime, with its genres, themes,
+    /// demographics and studios in the classification lookup tables, and
+    /// return the new ID.
     pub fn get_or_create_anime(&mut self, anime: &Anime) -> Result<i64> {
         let conn = self.db.conn_mut();
+        let tx = conn.savepoint()?;
 
         // Try to find existing anime by MAL ID
-        let existing_id: Option<i64> = conn
+        let existing_id: Option<i64> = tx
             .query_row(
                 "SELECT id FROM anime WHERE mal_id = ?1",
                 params![anime.mal_id],
@@ -38,17 +152,49 @@ impl JobQueue {
             .context("Failed to query for existing anime")?;
 
         if let Some(id) = existing_id {
-            debug!(mal_id = anime.mal_id, db_id = id, "Anime already exists");
+            let mut filled = Vec::new();
+            for (column, names) in [
+                ("genres", &anime.genres),
+                ("explicit_genres", &anime.explicit_genres),
+                ("themes", &anime.themes),
+                ("demographics", &anime.demographics),
+                ("studios", &anime.studios),
+                ("opening_themes", &anime.opening_themes),
+                ("ending_themes", &anime.ending_themes),
+            ] {
+                if names.is_empty() {
+                    continue;
+                }
+                let updated = tx
+                    .execute(
+                        &format!(
+                            "UPDATE anime SET {column} = ?2 WHERE id = ?1 AND ({column} IS NULL OR {column} = '[]')"
+                        ),
+                        params![id, serde_json::to_string(names)?],
+                    )
+                    .with_context(|| format!("Failed to fill in anime {}", column))?;
+                if updated == 0 {
+                    continue;
+                }
+                if CLASSIFICATION_COLUMNS.contains(&column) {
+                    insert_classification(&tx, anime.mal_id, column, names)?;
+                }
+                filled.push(column);
+            }
+            tx.commit()?;
+
+            debug!(mal_id = anime.mal_id, db_id = id, filled = ?filled, "Anime already exists");
             return Ok(id);
         }
 
         // Insert new anime
-        conn.execute(
+        tx.execute(
             "INSERT INTO anime (
                 mal_id, title, title_english, title_japanese, title_synonyms,
                 type, episodes_total, status,
                 aired_from, aired_to, season, year,
                 genres, explicit_genres, themes, demographics, studios,
+                opening_themes, ending_themes,
                 score, scored_by, rank, popularity,
                 source, rating, duration_minutes,
                 processing_status, fetched_at, updated_at
@@ -57,9 +203,10 @@ impl JobQueue {
                 ?6, ?7, ?8,
                 ?9, ?10, ?11, ?12,
                 ?13, ?14, ?15, ?16, ?17,
-                ?18, ?19, ?20, ?21,
-                ?22, ?23, ?24,
-                ?25, ?26, ?27
+                ?18, ?19,
+                ?20, ?21, ?22, ?23,
+                ?24, ?25, ?26,
+                ?27, ?28, ?29
             )",
             params![
                 anime.mal_id,
@@ -79,6 +226,8 @@ impl JobQueue {
                 serde_json::to_string(&anime.themes)?,
                 serde_json::to_string(&anime.demographics)?,
                 serde_json::to_string(&anime.studios)?,
+                serde_json::to_string(&anime.opening_themes)?,
+                serde_json::to_string(&anime.ending_themes)?,
                 anime.score,
                 anime.scored_by,
                 anime.rank,
@@ -92,8 +241,19 @@ impl JobQueue {
             ],
         )
         .context("Failed to insert anime")?;
+        let id = tx.last_insert_rowid();
+
+        for (column, names) in CLASSIFICATION_COLUMNS.into_iter().zip([
+            &anime.genres,
+            &anime.explicit_genres,
+            &anime.themes,
+            &anime.demographics,
+            &anime.studios,
+        ]) {
+            insert_classification(&tx, anime.mal_id, column, names)?;
+        }
+        tx.commit()?;
 
-        let id = conn.last_insert_rowid();
         info!(mal_id = anime.mal_id, db_id = id, title = %anime.title, "Created new anime entry");
 
         Ok(id)
@@ -108,13 +268,15 @@ impl JobQueue {
 
         // Try to insert, handle UNIQUE constraint violation
         match conn.execute(
-            "INSERT INTO jobs (anime_id, mal_id, anime_title, episode, stage, priority)
-             VALUES (?1, ?2, ?3, ?4, 'queued', ?5)",
+            "INSERT INTO jobs (anime_id, mal_id, anime_title, episode, season, year, stage, priority)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'queued', ?7)",
             params![
                 job.anime_id,
                 job.mal_id,
                 job.anime_title,
                 job.episode,
+                job.season,
+                job.year,
                 job.priority,
             ],
         ) {
@@ -158,35 +320,26 @@ impl JobQueue {
     pub fn dequeue(&mut self, from_stage: JobStage, to_stage: JobStage) -> Result<Option<Job>> {
         let conn = self.db.conn_mut();
 
-        // Start a transaction for atomicity
-        let tx = conn.transaction()?;
+        // Holds the write lock from picking the job to moving it
+        let tx = begin_claim(conn)?;
 
         // Find and update the next job
-        let updated = tx.execute(
-            "UPDATE jobs SET stage = ?1, started_at = CURRENT_TIMESTAMP
-             WHERE id = (
-                 SELECT id FROM jobs
-                 WHERE stage = ?2
-                 ORDER BY priority DESC, created_at ASC
-                 LIMIT 1
-             )",
-            params![to_stage.to_string(), from_stage.to_string()],
-        )?;
-
-        if updated == 0 {
+        let last = self.last_dequeued.get(&[from_stage][..]).copied();
+        let next = next_jobs(&tx, &[from_stage], 1, self.dequeue_order, last, self.anime_filter.as_ref())?;
+        let Some(&(id, priority, mal_id)) = next.first() else {
             // No jobs available
             tx.commit()?;
             return Ok(None);
-        }
+        };
 
-        // Fetch the job we just updated
-        let job = tx.query_row(
-            "SELECT * FROM jobs WHERE stage = ?1 ORDER BY updated_at DESC LIMIT 1",
-            params![to_stage.to_string()],
-            row_to_job,
+        tx.execute(
+            "UPDATE jobs SET stage = ?1, started_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![to_stage.to_string(), id],
         )?;
+        let job = tx.query_row("SELECT * FROM jobs WHERE id = ?1", params![id], row_to_job)?;
 
         tx.commit()?;
+        self.last_dequeued.insert(vec![from_stage], (priority, mal_id));
 
         debug!(
             job_id = job.id,
@@ -198,6 +351,46 @@ impl JobQueue {
         Ok(Some(job))
     }
 
+    /// Claim up to `limit` jobs at once (atomic operation)
+    ///
+    /// Like `dequeue`, but moves up to `limit` jobs from `from_stage` to
+    /// `to_stage` in one transaction, so a worker with many short jobs takes
+    /// the queue lock once per batch instead of once per job. Jobs are
+    /// returned in dequeue order (priority, then age, or round the anime
+    /// when breadth-first).
+    pub fn dequeue_batch(&mut self, from_stage: JobStage, to_stage: JobStage, limit: usize) -> Result<Vec<Job>> {
+        let conn = self.db.conn_mut();
+
+        // Holds the write lock from picking the jobs to moving them
+        let tx = begin_claim(conn)?;
+
+        let last = self.last_dequeued.get(&[from_stage][..]).copied();
+        let picked = next_jobs(&tx, &[from_stage], limit, self.dequeue_order, last, self.anime_filter.as_ref())?;
+
+        let mut jobs = Vec::with_capacity(picked.len());
+        for &(id, _, _) in &picked {
+            tx.execute(
+                "UPDATE jobs SET stage = ?1, started_at = CURRENT_TIMESTAMP WHERE id = ?2",
+                params![to_stage.to_string(), id],
+            )?;
+            jobs.push(tx.query_row("SELECT * FROM jobs WHERE id = ?1", params![id], row_to_job)?);
+        }
+
+        tx.commit()?;
+        if let Some(&(_, priority, mal_id)) = picked.last() {
+            self.last_dequeued.insert(vec![from_stage], (priority, mal_id));
+        }
+
+        debug!(
+            jobs = jobs.len(),
+            from_stage = %from_stage,
+            to_stage = %to_stage,
+            "Dequeued job batch"
+        );
+
+        Ok(jobs)
+    }
+
     /// Update job progress and optionally change stage
     pub fn update_progress(&mut self, job_id: i64, progress: f64, stage: Option<JobStage>) -> Result<()> {
         let conn = self.db.conn_mut();
@@ -265,6 +458,22 @@ impl JobQueue {
             updates.push("token_count = ?");
             params_vec.push(Box::new(count as i64));
         }
+        if let Some(quality) = metadata.transcript_quality {
+            updates.push("transcript_quality = ?");
+            params_vec.push(Box::new(quality));
+        }
+        if let Some(ref model) = metadata.transcript_model {
+            updates.push("transcript_model = ?");
+            params_vec.push(Box::new(model.clone()));
+        }
+        if let Some(ref version) = metadata.whisper_version {
+            updates.push("whisper_version = ?");
+            params_vec.push(Box::new(version.clone()));
+        }
+        if let Some(lines) = metadata.recap_lines {
+            updates.push("recap_lines = ?");
+            params_vec.push(Box::new(lines as i64));
+        }
         if let Some(ref path) = metadata.video_path {
             updates.push("video_path = ?");
             params_vec.push(Box::new(path.clone()));
@@ -301,6 +510,8 @@ impl JobQueue {
         let column = match file_type {
             FileType::Video => "video_deleted",
             FileType::Audio => "audio_deleted",
+            FileType::Transcript => "transcript_deleted",
+            FileType::Tokens => "tokens_deleted",
         };
 
         let sql = format!("UPDATE jobs SET {} = 1 WHERE id = ?", column);
@@ -312,16 +523,21 @@ impl JobQueue {
     }
 
     /// Mark a job as failed with error message
+    ///
+    /// If the job is later put back by `retry_failed`, it waits out the
+    /// backoff delay before it can be dequeued.
     pub fn fail_job(&mut self, job_id: i64, error: &str) -> Result<()> {
+        let delay = self.next_retry_delay(job_id)?;
         let conn = self.db.conn_mut();
 
         conn.execute(
             "UPDATE jobs
              SET stage = 'failed',
                  error_message = ?1,
-                 retry_count = retry_count + 1
-             WHERE id = ?2",
-            params![error, job_id],
+                 retry_count = retry_count + 1,
+                 next_attempt_at = datetime('now', ?2)
+             WHERE id = ?3",
+            params![error, delay, job_id],
         )?;
 
         warn!(job_id = job_id, error = %error, "Job failed");
@@ -364,6 +580,53 @@ impl JobQueue {
         Ok(jobs)
     }
 
+    /// Get jobs that have been transcribed (or got further), ordered by anime and episode
+    pub fn get_completed_jobs(&self) -> Result<Vec<Job>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT * FROM jobs
+             WHERE stage IN ('transcribed', 'tokenizing', 'tokenized', 'analyzing', 'complete')
+             ORDER BY mal_id, episode"
+        )?;
+
+        let jobs = stmt
+            .query_map([], row_to_job)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
+    /// Get the job for `episode` of the anime with `mal_id`, if there is one
+    pub fn get_job(&self, mal_id: u32, episode: u32) -> Result<Option<Job>> {
+        let conn = self.db.conn();
+
+        let job = conn
+            .query_row(
+                "SELECT * FROM jobs WHERE mal_id = ?1 AND episode = ?2 ORDER BY id LIMIT 1",
+                params![mal_id, episode],
+                row_to_job,
+            )
+            .optional()?;
+
+        Ok(job)
+    }
+
+    /// Get every job of the anime with `mal_id`, by episode
+    pub fn get_anime_jobs(&self, mal_id: u32) -> Result<Vec<Job>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT * FROM jobs WHERE mal_id = ?1 ORDER BY episode"
+        )?;
+
+        let jobs = stmt
+            .query_map(params![mal_id], row_to_job)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
     /// Get jobs by stage
     pub fn get_jobs_by_stage(&self, stage: JobStage) -> Result<Vec<Job>> {
         let conn = self.db.conn();
@@ -379,6 +642,23 @@ impl JobQueue {
         Ok(jobs)
     }
 
+    /// Get failed jobs that are blocked on a low-confidence anime selection
+    pub fn get_jobs_needing_review(&self) -> Result<Vec<Job>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT * FROM jobs
+             WHERE stage = 'failed' AND error_message LIKE ?1
+             ORDER BY mal_id, episode"
+        )?;
+
+        let jobs = stmt
+            .query_map(params![format!("{}%", NEEDS_REVIEW_ERROR)], row_to_job)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
     /// Get job statistics
     pub fn get_stats(&self) -> Result<JobStats> {
         let conn = self.db.conn();
@@ -412,11 +692,204 @@ impl JobQueue {
         })
     }
 
+    /// Get per-stage job counts and how long jobs have been sitting in each stage
+    pub fn get_stage_timings(&self) -> Result<Vec<StageTiming>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT stage, COUNT(*),
+                    AVG((julianday('now') - julianday(updated_at)) * 86400.0)
+             FROM jobs GROUP BY stage",
+        )?;
+
+        let mut timings = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                ))
+            })?
+            .filter_map(|row| match row {
+                Ok((stage, jobs, avg)) => stage.parse().ok().map(|stage| {
+                    Ok(StageTiming {
+                        stage,
+                        jobs: jobs as usize,
+                        avg_seconds_in_stage: avg.unwrap_or(0.0).max(0.0),
+                    })
+                }),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        timings.sort_by_key(|t| JobStage::all().iter().position(|s| *s == t.stage));
+
+        Ok(timings)
+    }
+
+    /// How long the oldest job at `stage` has been waiting (None if the stage is empty)
+    ///
+    /// Measured from when the job was last claimed by a worker, or from when
+    /// it was enqueued if it never was, so a job stuck in `queued` for days
+    /// shows up here even though nothing ever touched it.
+    pub fn oldest_pending_age(&self, stage: JobStage) -> Result<Option<Duration>> {
+        let conn = self.db.conn();
+
+        let seconds: Option<f64> = conn.query_row(
+            "SELECT MAX((julianday('now') - julianday(COALESCE(started_at, created_at))) * 86400.0)
+             FROM jobs WHERE stage = ?1",
+            params![stage.to_string()],
+            |row| row.get(0),
+        )?;
+
+        Ok(seconds.map(|s| Duration::from_secs_f64(s.max(0.0))))
+    }
+
+    /// Count the jobs that are neither complete nor failed, per stage
+    pub fn remaining_work(&self) -> Result<RemainingWork> {
+        let stats = self.get_stats()?;
+        let by_stage: Vec<(JobStage, usize)> = [
+            (JobStage::Queued, stats.queued),
+            (JobStage::Downloading, stats.downloading),
+            (JobStage::Downloaded, stats.downloaded),
+            (JobStage::Transcribing, stats.transcribing),
+            (JobStage::Transcribed, stats.transcribed),
+            (JobStage::Tokenizing, stats.tokenizing),
+            (JobStage::Tokenized, stats.tokenized),
+            (JobStage::Analyzing, stats.analyzing),
+        ]
+        .into_iter()
+        .filter(|(_, jobs)| *jobs > 0)
+        .collect();
+
+        Ok(RemainingWork {
+            total: by_stage.iter().map(|(_, jobs)| jobs).sum(),
+            by_stage,
+        })
+    }
+
+    /// Get the most recently failed jobs
+    pub fn get_recent_failures(&self, limit: usize) -> Result<Vec<Job>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT * FROM jobs WHERE stage = 'failed'
+             ORDER BY updated_at DESC, id DESC LIMIT ?1",
+        )?;
+
+        let jobs = stmt
+            .query_map(params![limit as i64], row_to_job)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
+    /// Get all failed jobs, most recently failed first
+    pub fn get_failed_jobs(&self) -> Result<Vec<Job>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT * FROM jobs WHERE stage = 'failed' ORDER BY updated_at DESC, id DESC",
+        )?;
+
+        let jobs = stmt
+            .query_map([], row_to_job)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
+    /// Count failed jobs by error signature (see `error_signature`), most
+    /// common first
+    pub fn failure_summary(&self) -> Result<Vec<(String, usize)>> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for job in self.get_failed_jobs()? {
+            let signature = match job.error_message.as_deref() {
+                Some(error) if !error.trim().is_empty() => error_signature(error),
+                _ => NO_ERROR_MESSAGE.to_string(),
+            };
+            *counts.entry(signature).or_insert(0) += 1;
+        }
+
+        let mut summary: Vec<_> = counts.into_iter().collect();
+        summary.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(summary)
+    }
+
+    /// Sum up the retries jobs have consumed, overall and for the
+    /// `RETRY_REPORT_TOP_ANIME` anime with the most
+    ///
+    /// A steady climb here while few jobs fail outright points at a
+    /// systemic problem (a provider down, a broken tool) that retries are
+    /// papering over.
+    ///
+    /// `max_retries` is the limit the workers run with (`--max-retries` or
+    /// `[pipeline.retry]`, as in `JobRetryPolicy`); None = each job's own.
+    pub fn retry_report(&self, max_retries: Option<u32>) -> Result<RetryReport> {
+        let conn = self.db.conn();
+
+        let (total_retries, jobs_retried, jobs_at_max_retries) = conn.query_row(
+            "SELECT COALESCE(SUM(retry_count), 0),
+                    COUNT(*) FILTER (WHERE retry_count > 0),
+                    COUNT(*) FILTER (WHERE retry_count > 0 AND retry_count >= COALESCE(?1, max_retries))
+             FROM jobs",
+            params![max_retries],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT mal_id, MAX(anime_title), SUM(retry_count), COUNT(*)
+             FROM jobs
+             WHERE retry_count > 0
+             GROUP BY mal_id
+             ORDER BY SUM(retry_count) DESC, mal_id ASC
+             LIMIT ?1",
+        )?;
+        let top_anime = stmt
+            .query_map(params![RETRY_REPORT_TOP_ANIME as i64], |row| {
+                Ok(AnimeRetries {
+                    mal_id: row.get(0)?,
+                    title: row.get(1)?,
+                    retries: row.get::<_, i64>(2)? as u64,
+                    jobs_retried: row.get::<_, i64>(3)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RetryReport {
+            total_retries: total_retries as u64,
+            jobs_retried: jobs_retried as usize,
+            jobs_at_max_retries: jobs_at_max_retries as usize,
+            top_anime,
+        })
+    }
+
     /// Alias for get_stats() - for compatibility
     pub fn get_queue_stats(&self) -> Result<JobStats> {
         self.get_stats()
     }
 
+    /// Count the jobs at `stage` that workers of this queue would pick up,
+    /// i.e. of anime matching its anime filter, and only of `mal_id` if given
+    pub fn count_pending(&self, stage: JobStage, mal_id: Option<u32>) -> Result<usize> {
+        let (condition, filter_values) = anime_filter_condition(self.anime_filter.as_ref(), 3);
+        let sql = format!(
+            "SELECT COUNT(*) FROM jobs WHERE stage = ?1 AND (?2 IS NULL OR mal_id = ?2){}",
+            condition
+        );
+        let mut values: Vec<rusqlite::types::Value> = vec![stage.to_string().into(), mal_id.map(i64::from).into()];
+        values.extend(filter_values);
+
+        let count: i64 = self
+            .db
+            .conn()
+            .prepare_cached(&sql)?
+            .query_row(rusqlite::params_from_iter(values), |row| row.get(0))
+            .context("Failed to count pending jobs")?;
+
+        Ok(count as usize)
+    }
+
     /// Dequeue next job from a specific stage
     ///
     /// Returns the job immediately, or error if no jobs available
@@ -434,7 +907,7 @@ impl JobQueue {
         let conn = self.db.conn_mut();
 
         // Start a transaction for atomicity
-        let tx = conn.transaction()?;
+        let tx = conn.savepoint()?;
 
         // Find and update the next job for the specific anime
         let updated = tx.execute(
@@ -442,6 +915,7 @@ impl JobQueue {
              WHERE id = (
                  SELECT id FROM jobs
                  WHERE stage = ?2 AND mal_id = ?3
+                   AND (next_attempt_at IS NULL OR next_attempt_at <= CURRENT_TIMESTAMP)
                  ORDER BY priority DESC, created_at ASC
                  LIMIT 1
              )",
@@ -468,6 +942,53 @@ impl JobQueue {
         Ok(job)
     }
 
+    /// Dequeue the next job from any of `stages` (atomic operation)
+    ///
+    /// For workers that handle several stages: claims the next job across
+    /// all of them in the queue's dequeue order, like `dequeue_next` does for
+    /// one stage, and moves it to the in-progress stage of the stage it came
+    /// from (see `JobStage::in_progress`), so no other worker claims it too.
+    /// Returns that source stage with the job, or None if no job is ready in
+    /// any of them.
+    pub fn dequeue_next_any(&mut self, stages: &[JobStage]) -> Result<Option<(JobStage, Job)>> {
+        if stages.is_empty() {
+            return Ok(None);
+        }
+        if let Some(stage) = stages.iter().find(|stage| stage.in_progress().is_none()) {
+            anyhow::bail!("Jobs can't be claimed from stage {}", stage);
+        }
+
+        let conn = self.db.conn_mut();
+
+        // Holds the write lock from picking the job to moving it
+        let tx = begin_claim(conn)?;
+
+        let last = self.last_dequeued.get(stages).copied();
+        let next = next_jobs(&tx, stages, 1, self.dequeue_order, last, self.anime_filter.as_ref())?;
+        let Some(&(id, priority, mal_id)) = next.first() else {
+            // No jobs available
+            tx.commit()?;
+            return Ok(None);
+        };
+
+        let from: JobStage = tx
+            .query_row("SELECT stage FROM jobs WHERE id = ?1", params![id], |row| row.get::<_, String>(0))?
+            .parse()?;
+        let to = from.in_progress().context("Claimed job is at an unexpected stage")?;
+        tx.execute(
+            "UPDATE jobs SET stage = ?1, started_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![to.to_string(), id],
+        )?;
+        let job = tx.query_row("SELECT * FROM jobs WHERE id = ?1", params![id], row_to_job)?;
+
+        tx.commit()?;
+        self.last_dequeued.insert(stages.to_vec(), (priority, mal_id));
+
+        debug!(job_id = job.id, from_stage = %from, to_stage = %to, "Dequeued job from any stage");
+
+        Ok(Some((from, job)))
+    }
+
     /// Update job stage
     pub fn update_stage(&mut self, job_id: i64, stage: JobStage) -> Result<()> {
         let conn = self.db.conn_mut();
@@ -506,19 +1027,43 @@ impl JobQueue {
     }
 
     /// Increment retry count for a job
+    ///
+    /// Also pushes back `next_attempt_at` by `retry_delay` of the new count
+    /// (or the delay of the queue's retry policy), so the dequeue methods
+    /// skip the job until the delay has passed.
     pub fn increment_retry(&mut self, job_id: i64) -> Result<()> {
+        let delay = self.next_retry_delay(job_id)?;
         let conn = self.db.conn_mut();
 
         conn.execute(
-            "UPDATE jobs SET retry_count = retry_count + 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
-            params![job_id],
+            "UPDATE jobs
+             SET retry_count = retry_count + 1,
+                 next_attempt_at = datetime('now', ?1),
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?2",
+            params![delay, job_id],
         )?;
 
-        debug!(job_id = job_id, "Incremented retry count");
+        debug!(job_id = job_id, delay = %delay, "Incremented retry count");
 
         Ok(())
     }
 
+    /// SQLite date modifier (e.g. `+120 seconds`) for the backoff delay of
+    /// the job's next failure
+    fn next_retry_delay(&self, job_id: i64) -> Result<String> {
+        let retry_count: i64 = self
+            .db
+            .conn()
+            .query_row("SELECT retry_count FROM jobs WHERE id = ?1", params![job_id], |row| row.get(0))
+            .with_context(|| format!("Job {} not found", job_id))?;
+        let delay = match &self.retry_policy {
+            Some(policy) => policy.delay(retry_count as u32),
+            None => retry_delay(retry_count as u32 + 1),
+        };
+        Ok(format!("+{} seconds", delay.as_secs()))
+    }
+
     /// Update job stage with error message
     pub fn update_stage_with_error(
         &mut self,
@@ -596,62 +1141,282 @@ impl JobQueue {
         Ok(())
     }
 
-    /// Get cached anime selection
-    pub fn get_selection(&self, mal_id: u32) -> Result<Option<AnimeSelection>> {
-        let conn = self.db.conn();
+    /// Send a job back to `queued` to download its video again, recording
+    /// `error` as the reason.
+    ///
+    /// Used when the video a job needs was already deleted. The old video path
+    /// and size are cleared, along with the deleted flags, so the downloader
+    /// starts from a clean slate; the retry count is left as it is.
+    pub fn requeue_for_download(&mut self, job_id: i64, error: String) -> Result<()> {
+        let conn = self.db.conn_mut();
 
-        let selection = conn
-            .query_row(
-                "SELECT selected_index, selected_title, confidence, reason,
-                        mal_episodes, selected_episodes, episode_match
-                 FROM anime_selection_cache WHERE mal_id = ?1",
-                params![mal_id],
-                |row| {
-                    Ok(AnimeSelection {
-                        selected_index: row.get(0)?,
-                        selected_title: row.get(1)?,
-                        confidence: row.get(2)?,
-                        reason: row.get(3)?,
-                        mal_episodes: row.get(4)?,
-                        selected_episodes: row.get(5)?,
-                        episode_match: row.get(6)?,
-                    })
-                },
-            )
-            .optional()
-            .context("Failed to query anime selection cache")?;
+        conn.execute(
+            "UPDATE jobs
+             SET stage = 'queued',
+                 error_message = ?1,
+                 started_at = NULL,
+                 video_path = NULL,
+                 video_size_bytes = NULL,
+                 video_deleted = 0,
+                 audio_deleted = 0,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?2",
+            params![error, job_id],
+        )?;
 
-        Ok(selection)
+        warn!(job_id = job_id, error = %error, "Requeued job for download");
+
+        Ok(())
     }
 
-    /// Cache anime selection
-    pub fn cache_selection(
-        &mut self,
-        mal_id: u32,
-        anime_title: &str,
-        search_query: &str,
-        selected_index: i32,
-        selected_title: &str,
-        confidence: &str,
-        reason: Option<&str>,
-        mal_episodes: Option<i32>,
-        selected_episodes: Option<i32>,
-        episode_match: Option<&str>,
-    ) -> Result<()> {
-        let conn = self.db.conn_mut();
+    /// Reset all of an anime's jobs so it is processed again from scratch.
+    ///
+    /// Moves every job back to `queued` and clears its paths, sizes, counts,
+    /// errors and retry state, and removes the cached selection so the
+    /// selector reconsiders the anime (a manual selection is kept). With
+    /// `data_paths`, the anime's files are deleted as well, after the reset
+    /// is committed so the database is not locked meanwhile. If that fails,
+    /// the error is returned with the jobs already reset; running the reset
+    /// again deletes the remaining files.
+    ///
+    /// Returns the number of jobs reset.
+    pub fn reset_anime(&mut self, mal_id: u32, data_paths: Option<&DataPaths>) -> Result<usize> {
+        let tx = self.db.conn_mut().savepoint()?;
 
-        conn.execute(
-            "INSERT OR REPLACE INTO anime_selection_cache
-             (mal_id, anime_title, search_query, selected_index, selected_title, confidence, reason,
+        let jobs = tx.execute(
+            "UPDATE jobs
+             SET stage = 'queued',
+                 progress = 0.0,
+                 started_at = NULL,
+                 completed_at = NULL,
+                 error_message = NULL,
+                 retry_count = 0,
+                 next_attempt_at = NULL,
+                 video_path = NULL,
+                 transcript_path = NULL,
+                 tokens_path = NULL,
+                 analysis_path = NULL,
+                 duration_seconds = NULL,
+                 video_size_bytes = NULL,
+                 audio_size_bytes = NULL,
+                 transcript_size_bytes = NULL,
+                 tokens_size_bytes = NULL,
+                 word_count = NULL,
+                 token_count = NULL,
+                 video_deleted = 0,
+                 audio_deleted = 0,
+                 transcript_quality = NULL,
+                 transcript_model = NULL,
+                 recap_lines = NULL,
+                 whisper_version = NULL,
+                 transcript_deleted = 0,
+                 tokens_deleted = 0,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE mal_id = ?1",
+            params![mal_id],
+        )?;
+
+        let selection_cleared = tx.execute(
+            "DELETE FROM anime_selection_cache WHERE mal_id = ?1 AND manual = 0",
+            params![mal_id],
+        )? > 0;
+
+        tx.commit()?;
+
+        let freed = match data_paths {
+            Some(data_paths) => data_paths
+                .remove_anime_files(mal_id)
+                .with_context(|| format!("Reset the jobs of anime {}, but failed to delete its files", mal_id))?,
+            None => 0,
+        };
+
+        info!(
+            mal_id = mal_id,
+            jobs = jobs,
+            selection_cleared = selection_cleared,
+            freed_mb = freed / 1_000_000,
+            "Reset anime"
+        );
+
+        Ok(jobs)
+    }
+
+    /// Send tokenized jobs back to `transcribed` so the tokenizer processes
+    /// them again, e.g. after the stopwords or tokenizer backend changed.
+    ///
+    /// Covers jobs at `tokenized`, `analyzing` and `complete`. Their token
+    /// paths, sizes and counts are cleared; download and transcription
+    /// results are kept. A job whose transcript (at its recorded
+    /// `transcript_path`, see `DataPaths::job_transcript`) is no longer on
+    /// disk cannot be tokenized again, so it is left as it is and reported.
+    pub fn reset_for_retokenize(&mut self, data_paths: &DataPaths) -> Result<RetokenizeReset> {
+        let tx = self.db.conn_mut().savepoint()?;
+
+        let candidates: Vec<Job> = tx
+            .prepare(
+                "SELECT * FROM jobs
+                 WHERE stage IN ('tokenized', 'analyzing', 'complete')
+                 ORDER BY mal_id, episode",
+            )?
+            .query_map([], row_to_job)?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut reset = RetokenizeReset::default();
+        for job in candidates {
+            let job_id = job.id;
+            if !data_paths.job_transcript(&job).exists() {
+                warn!(job_id = job_id, mal_id = job.mal_id, episode = job.episode, "Transcript missing, not re-tokenizing");
+                reset.missing_transcript.push(job_id);
+                continue;
+            }
+            tx.execute(
+                "UPDATE jobs
+                 SET stage = 'transcribed',
+                     progress = 0.0,
+                     started_at = NULL,
+                     completed_at = NULL,
+                     error_message = NULL,
+                     tokens_path = NULL,
+                     analysis_path = NULL,
+                     tokens_size_bytes = NULL,
+                     token_count = NULL,
+                     recap_lines = NULL,
+                     tokens_deleted = 0,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1",
+                params![job_id],
+            )?;
+            reset.reset += 1;
+        }
+
+        tx.commit()?;
+
+        info!(
+            reset = reset.reset,
+            missing_transcript = reset.missing_transcript.len(),
+            "Reset jobs for re-tokenization"
+        );
+
+        Ok(reset)
+    }
+
+    /// Merge anime rows that share a MAL ID into one
+    ///
+    /// Repair tool for databases where the same anime was saved more than
+    /// once (`get_or_create_anime` assumes it never is). The row with the
+    /// lowest ID is kept; jobs and analysis results of the other rows, and
+    /// jobs whose `anime_id` points at no row at all, are moved to it, and the
+    /// other rows are deleted. Where both rows have a job for an episode, the
+    /// one further along the pipeline wins (a failed job counts as least
+    /// advanced, ties go to the kept row's job) and the other is deleted.
+    ///
+    /// Runs in one transaction, so nothing changes if a step fails.
+    pub fn compact_anime(&mut self) -> Result<AnimeCompaction> {
+        // With duplicate MAL IDs, `anime_selection_cache`'s key into anime
+        // isn't unique, so SQLite refuses every change to anime while
+        // foreign keys are enforced. The pragma is a no-op inside a
+        // transaction, so check that it took effect.
+        let conn = self.db.conn_mut();
+        if !conn.is_autocommit() {
+            anyhow::bail!("Cannot compact anime inside a transaction");
+        }
+        conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+        let enforced: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        let result = if enforced {
+            Err(anyhow::anyhow!("Failed to turn off foreign key enforcement"))
+        } else {
+            merge_duplicate_anime(conn)
+        };
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+        let compaction = result?;
+
+        if compaction != AnimeCompaction::default() {
+            info!(
+                anime_removed = compaction.anime_removed,
+                jobs_repointed = compaction.jobs_repointed,
+                jobs_removed = compaction.jobs_removed,
+                "Compacted duplicate anime"
+            );
+        }
+
+        Ok(compaction)
+    }
+
+    /// Get cached anime selection
+    pub fn get_selection(&self, mal_id: u32) -> Result<Option<AnimeSelection>> {
+        let conn = self.db.conn();
+
+        let selection = conn
+            .query_row(
+                "SELECT selected_index, selected_title, confidence, reason,
+                        mal_episodes, selected_episodes, episode_match, manual
+                 FROM anime_selection_cache WHERE mal_id = ?1",
+                params![mal_id],
+                |row| {
+                    Ok(AnimeSelection {
+                        selected_index: row.get(0)?,
+                        selected_title: row.get(1)?,
+                        confidence: row.get::<_, String>(2)?.parse().map_err(|e: anyhow::Error| {
+                            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+                        })?,
+                        reason: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                        mal_episodes: row.get(4)?,
+                        selected_episodes: row.get(5)?,
+                        episode_match: row.get(6)?,
+                        manual: row.get(7)?,
+                    })
+                },
+            )
+            .optional()
+            .context("Failed to query anime selection cache")?;
+
+        Ok(selection)
+    }
+
+    /// Cache anime selection
+    ///
+    /// A manual selection (see `set_manual_selection`) already cached for the
+    /// anime is kept, and this selection is dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn cache_selection(
+        &mut self,
+        mal_id: u32,
+        anime_title: &str,
+        search_query: &str,
+        selected_index: i32,
+        selected_title: &str,
+        confidence: Confidence,
+        reason: Option<&str>,
+        mal_episodes: Option<i32>,
+        selected_episodes: Option<i32>,
+        episode_match: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.db.conn_mut();
+
+        let cached = conn.execute(
+            "INSERT INTO anime_selection_cache
+             (mal_id, anime_title, search_query, selected_index, selected_title, confidence, reason,
               mal_episodes, selected_episodes, episode_match)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(mal_id) DO UPDATE SET
+                 anime_title = excluded.anime_title,
+                 search_query = excluded.search_query,
+                 selected_index = excluded.selected_index,
+                 selected_title = excluded.selected_title,
+                 confidence = excluded.confidence,
+                 reason = excluded.reason,
+                 mal_episodes = excluded.mal_episodes,
+                 selected_episodes = excluded.selected_episodes,
+                 episode_match = excluded.episode_match,
+                 created_at = CURRENT_TIMESTAMP
+             WHERE manual = 0",
             params![
                 mal_id,
                 anime_title,
                 search_query,
                 selected_index,
                 selected_title,
-                confidence,
+                confidence.as_str(),
                 reason,
                 mal_episodes,
                 selected_episodes,
@@ -659,6 +1424,15 @@ impl JobQueue {
             ],
         )?;
 
+        if cached == 0 {
+            info!(
+                mal_id = mal_id,
+                selected = %selected_title,
+                "Keeping the manual selection over the new one"
+            );
+            return Ok(());
+        }
+
         debug!(
             mal_id = mal_id,
             selected = %selected_title,
@@ -670,6 +1444,723 @@ impl JobQueue {
         Ok(())
     }
 
+    /// Select candidate `selected_index` (1-based), titled `selected_title`,
+    /// for an anime by hand
+    ///
+    /// The selection is cached with high confidence and marked manual, so
+    /// later automated selections (`cache_selection`) and `reset_anime` leave
+    /// it alone. Setting another manual selection replaces it.
+    pub fn set_manual_selection(&mut self, mal_id: u32, selected_index: i32, selected_title: &str) -> Result<()> {
+        if selected_index < 1 {
+            anyhow::bail!("Candidate index must be 1 or more, got {}", selected_index);
+        }
+
+        let conn = self.db.conn_mut();
+
+        let updated = conn.execute(
+            "INSERT INTO anime_selection_cache
+             (mal_id, anime_title, search_query, selected_index, selected_title, confidence, reason, manual)
+             SELECT mal_id, title, title, ?2, ?3, 'high', 'Selected manually', 1
+             FROM anime WHERE mal_id = ?1
+             ON CONFLICT(mal_id) DO UPDATE SET
+                 selected_index = excluded.selected_index,
+                 selected_title = excluded.selected_title,
+                 confidence = excluded.confidence,
+                 reason = excluded.reason,
+                 selected_episodes = NULL,
+                 episode_match = NULL,
+                 created_at = CURRENT_TIMESTAMP,
+                 manual = 1",
+            params![mal_id, selected_index, selected_title],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Anime {} not found", mal_id);
+        }
+
+        info!(mal_id = mal_id, selected = %selected_title, "Set manual anime selection");
+
+        Ok(())
+    }
+
+    /// Link two anime of the same franchise (e.g. a season and its sequel)
+    ///
+    /// Linking an already linked pair is a no-op.
+    pub fn link_related(&mut self, parent_mal_id: u32, child_mal_id: u32) -> Result<()> {
+        if parent_mal_id == child_mal_id {
+            return Ok(());
+        }
+
+        let conn = self.db.conn_mut();
+
+        conn.execute(
+            "INSERT OR IGNORE INTO related_anime (parent_mal_id, child_mal_id) VALUES (?1, ?2)",
+            params![parent_mal_id, child_mal_id],
+        )?;
+
+        debug!(
+            parent_mal_id = parent_mal_id,
+            child_mal_id = child_mal_id,
+            "Linked related anime"
+        );
+
+        Ok(())
+    }
+
+    /// Get the MAL IDs of anime that still have queued jobs
+    pub fn get_queued_mal_ids(&self) -> Result<Vec<u32>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT mal_id FROM jobs WHERE stage = 'queued' AND mal_id IS NOT NULL ORDER BY mal_id",
+        )?;
+
+        let mal_ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()
+            .context("Failed to query queued anime")?;
+
+        Ok(mal_ids)
+    }
+
+    /// Get the MAL IDs of anime saved while they were still airing, whose
+    /// newly aired episodes have no jobs yet
+    pub fn get_airing_mal_ids(&self) -> Result<Vec<u32>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare("SELECT mal_id FROM anime WHERE status = ?1 ORDER BY mal_id")?;
+
+        let mal_ids = stmt
+            .query_map(params![Anime::STATUS_AIRING], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()
+            .context("Failed to query airing anime")?;
+
+        Ok(mal_ids)
+    }
+
+    /// Update the airing status, episode count and end date of a saved anime
+    /// from freshly fetched details
+    pub fn update_anime_airing(&mut self, anime: &Anime) -> Result<()> {
+        let conn = self.db.conn_mut();
+
+        let updated = conn
+            .execute(
+                "UPDATE anime
+                 SET status = ?1, episodes_total = ?2, aired_to = ?3, updated_at = CURRENT_TIMESTAMP
+                 WHERE mal_id = ?4",
+                params![anime.status, anime.episodes_total, anime.aired_to, anime.mal_id],
+            )
+            .context("Failed to update anime airing status")?;
+
+        if updated == 0 {
+            anyhow::bail!("Anime {} not found", anime.mal_id);
+        }
+
+        Ok(())
+    }
+
+    /// Get the sizes of the videos downloaded so far for an anime
+    ///
+    /// Sizes are kept after the videos themselves are deleted.
+    pub fn get_video_sizes(&self, mal_id: u32) -> Result<Vec<u64>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT video_size_bytes FROM jobs WHERE mal_id = ?1 AND video_size_bytes > 0",
+        )?;
+
+        let sizes = stmt
+            .query_map([mal_id], |row| row.get::<_, i64>(0).map(|size| size as u64))?
+            .collect::<rusqlite::Result<Vec<u64>>>()
+            .context("Failed to query video sizes")?;
+
+        Ok(sizes)
+    }
+
+    /// Get the episode length of an anime on MAL, in minutes
+    pub fn get_anime_duration(&self, mal_id: u32) -> Result<Option<u32>> {
+        let duration = self
+            .db
+            .conn()
+            .query_row("SELECT duration_minutes FROM anime WHERE mal_id = ?1", [mal_id], |row| row.get(0))
+            .optional()
+            .context("Failed to query anime duration")?;
+
+        Ok(duration.flatten())
+    }
+
+    /// Get all anime, ordered by MAL ID
+    pub fn get_all_anime(&self) -> Result<Vec<Anime>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM anime ORDER BY mal_id", ANIME_COLUMNS))?;
+
+        let anime = stmt
+            .query_map([], row_to_anime)?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to query anime")?;
+
+        Ok(anime)
+    }
+
+    /// Get one anime by MAL ID
+    pub fn get_anime(&self, mal_id: u32) -> Result<Option<Anime>> {
+        let conn = self.db.conn();
+
+        let anime = conn
+            .query_row(
+                &format!("SELECT {} FROM anime WHERE mal_id = ?1", ANIME_COLUMNS),
+                params![mal_id],
+                row_to_anime,
+            )
+            .optional()
+            .context("Failed to query anime")?;
+
+        Ok(anime)
+    }
+
+    /// Record the Zipf fit and token count of an analyzed anime, with a
+    /// warning if the fit is unreliable (None clears an earlier warning)
+    pub fn update_anime_analysis(
+        &mut self,
+        mal_id: u32,
+        zipf_exponent: f64,
+        zipf_r_squared: f64,
+        total_tokens: u64,
+        zipf_warning: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.db.conn_mut();
+
+        let updated = conn
+            .execute(
+                "UPDATE anime
+                 SET zipf_exponent = ?1, zipf_r_squared = ?2, total_tokens = ?3, zipf_warning = ?4,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE mal_id = ?5",
+                params![zipf_exponent, zipf_r_squared, total_tokens as i64, zipf_warning, mal_id],
+            )
+            .context("Failed to update anime analysis")?;
+
+        if updated == 0 {
+            anyhow::bail!("Anime {} not found", mal_id);
+        }
+
+        debug!(mal_id, zipf_exponent, zipf_r_squared, total_tokens, "Recorded anime analysis");
+
+        Ok(())
+    }
+
+    /// Get analyzed anime whose Zipf exponent lies in `range`, ordered by exponent
+    ///
+    /// e.g. `1.2..` for every anime with an exponent of at least 1.2.
+    pub fn query_anime_by_exponent(&self, range: impl RangeBounds<f64>) -> Result<Vec<Anime>> {
+        let conn = self.db.conn();
+
+        let mut conditions = vec!["zipf_exponent IS NOT NULL".to_string()];
+        let mut bounds = Vec::new();
+        match range.start_bound() {
+            Bound::Included(min) => {
+                conditions.push("zipf_exponent >= ?".to_string());
+                bounds.push(*min);
+            }
+            Bound::Excluded(min) => {
+                conditions.push("zipf_exponent > ?".to_string());
+                bounds.push(*min);
+            }
+            Bound::Unbounded => {}
+        }
+        match range.end_bound() {
+            Bound::Included(max) => {
+                conditions.push("zipf_exponent <= ?".to_string());
+                bounds.push(*max);
+            }
+            Bound::Excluded(max) => {
+                conditions.push("zipf_exponent < ?".to_string());
+                bounds.push(*max);
+            }
+            Bound::Unbounded => {}
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM anime WHERE {} ORDER BY zipf_exponent, mal_id",
+            ANIME_COLUMNS,
+            conditions.join(" AND ")
+        ))?;
+
+        let anime = stmt
+            .query_map(rusqlite::params_from_iter(bounds), row_to_anime)?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to query anime by Zipf exponent")?;
+
+        Ok(anime)
+    }
+
+    /// Export all anime to `path` as CSV or JSON Lines
+    ///
+    /// Returns the number of anime written.
+    pub fn export_anime(&self, format: ExportFormat, path: &Path) -> Result<usize> {
+        let anime = self.get_all_anime()?;
+        export::write_anime(&anime, format, path)?;
+        info!(count = anime.len(), format = %format, path = %path.display(), "Exported anime");
+        Ok(anime.len())
+    }
+
+    /// Export completed jobs (see `get_completed_jobs`) with their file
+    /// sizes, word counts and paths to `path` as CSV or JSON Lines
+    ///
+    /// Returns the number of jobs written.
+    pub fn export_jobs(&self, format: ExportFormat, path: &Path) -> Result<usize> {
+        let jobs = self.get_completed_jobs()?;
+        export::write_jobs(&jobs, format, path)?;
+        info!(count = jobs.len(), format = %format, path = %path.display(), "Exported jobs");
+        Ok(jobs.len())
+    }
+
+    /// Get the genres and studios of every anime, ordered by MAL ID
+    pub fn get_anime_classifications(&self) -> Result<Vec<AnimeClassification>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare("SELECT mal_id, title, genres, studios FROM anime ORDER BY mal_id")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to query anime classifications")?;
+
+        let parse = |mal_id: u32, column: &str, json: Option<String>| -> Result<Vec<String>> {
+            match json {
+                Some(json) => serde_json::from_str(&json)
+                    .with_context(|| format!("Invalid {} JSON for anime {}", column, mal_id)),
+                None => Ok(Vec::new()),
+            }
+        };
+
+        rows.into_iter()
+            .map(|(mal_id, title, genres, studios)| {
+                Ok(AnimeClassification {
+                    mal_id,
+                    title,
+                    genres: parse(mal_id, "genres", genres)?,
+                    studios: parse(mal_id, "studios", studios)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Get the MAL IDs of the anime matching `filter`, in ascending order
+    ///
+    /// Genres, themes, demographics and studios are looked up in the
+    /// `anime_<column>` tables, whose indexes on the name find the matching
+    /// anime without reading each one's JSON arrays.
+    pub fn find_anime(&self, filter: &AnimeFilter) -> Result<Vec<u32>> {
+        let conn = self.db.conn();
+
+        let (sql, values) = find_anime_query(filter, 1);
+        let mut stmt = conn.prepare(&format!("{} ORDER BY mal_id", sql))?;
+        let mal_ids = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()
+            .context("Failed to find anime by classification")?;
+
+        Ok(mal_ids)
+    }
+
+    /// Get the MAL IDs directly linked to an anime, in either direction
+    pub fn get_related(&self, mal_id: u32) -> Result<Vec<u32>> {
+        let conn = self.db.conn();
+
+        let mut stmt = conn.prepare(
+            "SELECT child_mal_id FROM related_anime WHERE parent_mal_id = ?1
+             UNION
+             SELECT parent_mal_id FROM related_anime WHERE child_mal_id = ?1
+             ORDER BY 1",
+        )?;
+
+        let related = stmt
+            .query_map(params![mal_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()
+            .context("Failed to query related anime")?;
+
+        Ok(related)
+    }
+}
+
+/// Reduce an error message to a signature shared by failures with the same
+/// cause: only the first line is kept, words that look like paths or URLs
+/// become `<path>` and every run of digits becomes `N`.
+pub fn error_signature(error: &str) -> String {
+    let first_line = error.lines().next().unwrap_or_default();
+    let words: Vec<String> = first_line
+        .split_whitespace()
+        .map(|word| {
+            if word.contains(['/', '\\']) {
+                return "<path>".to_string();
+            }
+            let mut normalized = String::with_capacity(word.len());
+            let mut in_number = false;
+            for c in word.chars() {
+                if c.is_ascii_digit() {
+                    if !in_number {
+                        normalized.push('N');
+                    }
+                    in_number = true;
+                } else {
+                    normalized.push(c);
+                    in_number = false;
+                }
+            }
+            normalized
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// Columns read by `row_to_anime`, in order
+const ANIME_COLUMNS: &str = "id, mal_id, title, title_english, title_japanese, title_synonyms,
+    type, episodes_total, status,
+    aired_from, aired_to, season, year,
+    genres, explicit_genres, themes, demographics, studios,
+    score, scored_by, rank, popularity,
+    source, rating, duration_minutes,
+    episodes_processed, processing_status, fetched_at, updated_at,
+    zipf_exponent, zipf_r_squared, total_tokens, zipf_warning,
+    opening_themes, ending_themes";
+
+/// Helper: Convert a row selecting `ANIME_COLUMNS` to an Anime
+fn row_to_anime(row: &rusqlite::Row) -> rusqlite::Result<Anime> {
+    let list = |idx: usize| -> rusqlite::Result<Vec<String>> {
+        Ok(row
+            .get::<_, Option<String>>(idx)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    };
+
+    Ok(Anime {
+        id: row.get(0)?,
+        mal_id: row.get(1)?,
+        title: row.get(2)?,
+        title_english: row.get(3)?,
+        title_japanese: row.get(4)?,
+        title_synonyms: list(5)?,
+        anime_type: row.get(6)?,
+        episodes_total: row.get(7)?,
+        status: row.get(8)?,
+        aired_from: row.get(9)?,
+        aired_to: row.get(10)?,
+        season: row.get(11)?,
+        year: row.get(12)?,
+        genres: list(13)?,
+        explicit_genres: list(14)?,
+        themes: list(15)?,
+        demographics: list(16)?,
+        studios: list(17)?,
+        score: row.get(18)?,
+        scored_by: row.get(19)?,
+        rank: row.get(20)?,
+        popularity: row.get(21)?,
+        source: row.get(22)?,
+        rating: row.get(23)?,
+        duration_minutes: row.get(24)?,
+        episodes_processed: row.get::<_, Option<u32>>(25)?.unwrap_or(0),
+        processing_status: row
+            .get::<_, Option<String>>(26)?
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(ProcessingStatus::Pending),
+        fetched_at: row.get(27)?,
+        updated_at: row.get(28)?,
+        zipf_exponent: row.get(29)?,
+        zipf_r_squared: row.get(30)?,
+        total_tokens: row.get::<_, Option<i64>>(31)?.map(|x| x as u64),
+        zipf_warning: row.get(32)?,
+        opening_themes: list(33)?,
+        ending_themes: list(34)?,
+    })
+}
+
+/// Helper: add an anime's `names` to the `anime_<column>` lookup table,
+/// keeping the names already there
+fn insert_classification(conn: &Connection, mal_id: u32, column: &str, names: &[String]) -> Result<()> {
+    let mut stmt = conn.prepare_cached(&format!("INSERT OR IGNORE INTO anime_{} (mal_id, name) VALUES (?1, ?2)", column))?;
+    for name in names {
+        stmt.execute(params![mal_id, name])
+            .with_context(|| format!("Failed to insert anime {}", column))?;
+    }
+    Ok(())
+}
+
+/// Helper: the work of `JobQueue::compact_anime`, in one transaction
+fn merge_duplicate_anime(conn: &mut Connection) -> Result<AnimeCompaction> {
+    let tx = conn.savepoint()?;
+    let mut compaction = AnimeCompaction::default();
+
+    // (job, episode, stage, kept anime row) of every job not on its kept
+    // row; jobs without a MAL ID go by the anime row they point at
+    let stray_jobs = tx
+        .prepare(
+            "SELECT j.id, j.episode, j.stage, kept.id
+             FROM jobs j
+             JOIN (SELECT mal_id, MIN(id) AS id FROM anime GROUP BY mal_id) kept
+               ON kept.mal_id = COALESCE(j.mal_id, (SELECT mal_id FROM anime WHERE id = j.anime_id))
+             WHERE j.anime_id != kept.id
+             ORDER BY j.id",
+        )?
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?, row.get::<_, String>(2)?, row.get::<_, i64>(3)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to query jobs of duplicate anime")?;
+
+    for (job_id, episode, stage, anime_id) in stray_jobs {
+        let existing: Option<(i64, String)> = tx
+            .query_row(
+                "SELECT id, stage FROM jobs WHERE anime_id = ?1 AND episode = ?2",
+                params![anime_id, episode],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let mut repoint = true;
+        if let Some((kept_job, kept_stage)) = existing {
+            let (winner, loser) = if stage_rank(stage.parse()?) > stage_rank(kept_stage.parse()?) {
+                (job_id, kept_job)
+            } else {
+                repoint = false;
+                (kept_job, job_id)
+            };
+            tx.execute("UPDATE jobs SET depends_on = ?1 WHERE depends_on = ?2", params![winner, loser])?;
+            tx.execute("UPDATE workers SET current_job_id = NULL WHERE current_job_id = ?1", params![loser])?;
+            tx.execute("DELETE FROM jobs WHERE id = ?1", params![loser])?;
+            compaction.jobs_removed += 1;
+        }
+        if repoint {
+            tx.execute("UPDATE jobs SET anime_id = ?1 WHERE id = ?2", params![anime_id, job_id])?;
+            compaction.jobs_repointed += 1;
+        }
+    }
+
+    tx.execute(
+        "UPDATE analysis_results
+         SET anime_id = (SELECT MIN(kept.id) FROM anime kept JOIN anime dup ON dup.mal_id = kept.mal_id
+                         WHERE dup.id = analysis_results.anime_id)
+         WHERE anime_id NOT IN (SELECT MIN(id) FROM anime GROUP BY mal_id)
+           AND anime_id IN (SELECT id FROM anime)",
+        [],
+    )
+    .context("Failed to move analysis results of duplicate anime")?;
+
+    compaction.anime_removed = tx
+        .execute("DELETE FROM anime WHERE id NOT IN (SELECT MIN(id) FROM anime GROUP BY mal_id)", [])
+        .context("Failed to delete duplicate anime")?;
+
+    tx.commit()?;
+
+    Ok(compaction)
+}
+
+/// Helper: how far along the pipeline a job at `stage` is, failed jobs
+/// counting as least advanced
+fn stage_rank(stage: JobStage) -> usize {
+    match stage {
+        JobStage::Failed => 0,
+        stage => JobStage::all().iter().position(|&s| s == stage).map_or(0, |index| index + 1),
+    }
+}
+
+/// Transaction jobs are claimed in (see `begin_claim`)
+enum Claim<'conn> {
+    Immediate(Transaction<'conn>),
+    Nested(Savepoint<'conn>),
+}
+
+impl std::ops::Deref for Claim<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            Claim::Immediate(tx) => tx,
+            Claim::Nested(savepoint) => savepoint,
+        }
+    }
+}
+
+impl Claim<'_> {
+    fn commit(self) -> rusqlite::Result<()> {
+        match self {
+            Claim::Immediate(tx) => tx.commit(),
+            Claim::Nested(savepoint) => savepoint.commit(),
+        }
+    }
+}
+
+/// Helper: begin the transaction a claim picks and moves its jobs in
+///
+/// `BEGIN IMMEDIATE` takes the write lock before the jobs are picked, so
+/// another connection can neither claim them in between nor make the claim
+/// fail when a read lock can't be upgraded. Inside `with_transaction`, the
+/// claim becomes a savepoint of the outer transaction.
+fn begin_claim(conn: &mut Connection) -> Result<Claim<'_>> {
+    let claim = if conn.is_autocommit() {
+        Claim::Immediate(conn.transaction_with_behavior(TransactionBehavior::Immediate)?)
+    } else {
+        Claim::Nested(conn.savepoint()?)
+    };
+    Ok(claim)
+}
+
+/// Helper: ID, priority and MAL ID of the next `limit` ready jobs at any of
+/// `stages`
+///
+/// Breadth-first, each anime's jobs are numbered in priority order, and the
+/// first job of every anime comes before any anime's second. Within a round,
+/// anime go by priority (then MAL ID), starting after `last`, the anime
+/// claimed last.
+fn next_jobs(
+    conn: &Connection,
+    stages: &[JobStage],
+    limit: usize,
+    order: DequeueOrder,
+    last: Option<(i32, u32)>,
+    filter: Option<&AnimeFilter>,
+) -> Result<Vec<(i64, i32, u32)>> {
+    // Stage names are fixed, so they are written into the SQL
+    let stages = stages.iter().map(|stage| format!("'{}'", stage)).collect::<Vec<_>>().join(", ");
+    let row = |row: &rusqlite::Row| Ok((row.get(0)?, row.get(1)?, row.get(2)?));
+    let jobs = match order {
+        DequeueOrder::Priority => {
+            let (condition, filter_values) = anime_filter_condition(filter, 2);
+            let mut values: Vec<rusqlite::types::Value> = vec![(limit as i64).into()];
+            values.extend(filter_values);
+            conn.prepare_cached(&format!(
+                "SELECT id, priority, mal_id FROM jobs
+                 WHERE stage IN ({})
+                   AND (next_attempt_at IS NULL OR next_attempt_at <= CURRENT_TIMESTAMP){}
+                 ORDER BY priority DESC, created_at ASC, id ASC
+                 LIMIT ?1",
+                stages,
+                condition
+            ))?
+            .query_map(rusqlite::params_from_iter(values), row)?
+            .collect::<Result<Vec<_>, _>>()?
+        }
+        DequeueOrder::BreadthFirst => {
+            // Without a previous claim, every anime counts as after it
+            let (last_priority, last_mal_id) = last.map_or((i64::MAX, 0), |(p, m)| (i64::from(p), i64::from(m)));
+            let (condition, filter_values) = anime_filter_condition(filter, 4);
+            let mut values: Vec<rusqlite::types::Value> =
+                vec![(limit as i64).into(), last_priority.into(), last_mal_id.into()];
+            values.extend(filter_values);
+            conn.prepare_cached(&format!(
+                "SELECT id, priority, mal_id FROM (
+                     SELECT id, priority, mal_id,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY mal_id ORDER BY priority DESC, created_at ASC, id ASC
+                            ) AS round
+                     FROM jobs
+                     WHERE stage IN ({})
+                       AND (next_attempt_at IS NULL OR next_attempt_at <= CURRENT_TIMESTAMP){}
+                 )
+                 ORDER BY round ASC,
+                          (priority < ?2 OR (priority = ?2 AND mal_id > ?3)) DESC,
+                          priority DESC, mal_id ASC
+                 LIMIT ?1",
+                stages,
+                condition
+            ))?
+            .query_map(rusqlite::params_from_iter(values), row)?
+            .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok(jobs)
+}
+
+/// Query and parameters selecting the MAL IDs of the anime matching `filter`
+///
+/// Parameters are numbered from `?{first_param}` on, so the query can be
+/// nested in one whose own parameters come first.
+fn find_anime_query(filter: &AnimeFilter, first_param: usize) -> (String, Vec<rusqlite::types::Value>) {
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+    // Bind a value to the next parameter and return its placeholder
+    let mut bind = |value: rusqlite::types::Value| {
+        values.push(value);
+        format!("?{}", first_param + values.len() - 1)
+    };
+    // MAL IDs in a lookup table under any of the `placeholders`' names
+    // (compared case-insensitively by the tables' NOCASE collation)
+    let lookup = |column: &str, placeholders: &[String]| {
+        format!("SELECT mal_id FROM anime_{} WHERE name IN ({})", column, placeholders.join(", "))
+    };
+    let mut conditions = Vec::new();
+
+    // Every genre (explicit ones included) and theme
+    for genre in &filter.genres {
+        let name = [bind(genre.clone().into())];
+        conditions.push(format!(
+            "mal_id IN ({} UNION {})",
+            lookup("genres", &name),
+            lookup("explicit_genres", &name)
+        ));
+    }
+    for theme in &filter.themes {
+        conditions.push(format!("mal_id IN ({})", lookup("themes", &[bind(theme.clone().into())])));
+    }
+    // Any of the demographics and studios
+    for (column, names) in [("demographics", &filter.demographics), ("studios", &filter.studios)] {
+        if !names.is_empty() {
+            let names: Vec<_> = names.iter().map(|name| bind(name.clone().into())).collect();
+            conditions.push(format!("mal_id IN ({})", lookup(column, &names)));
+        }
+    }
+    // Like `AnimeFilterConfig::matches`: unknown episode counts pass, anime
+    // without a type fail a type allowlist
+    if !filter.kind.types.is_empty() {
+        let types: Vec<_> = filter
+            .kind
+            .types
+            .iter()
+            .map(|name| format!("lower({})", bind(name.clone().into())))
+            .collect();
+        conditions.push(format!("lower(type) IN ({})", types.join(", ")));
+    }
+    if let Some(min) = filter.kind.min_episodes {
+        conditions.push(format!("(episodes_total IS NULL OR episodes_total >= {})", bind(i64::from(min).into())));
+    }
+    if let Some(max) = filter.kind.max_episodes {
+        conditions.push(format!("(episodes_total IS NULL OR episodes_total <= {})", bind(i64::from(max).into())));
+    }
+    if let Some(min) = filter.min_year {
+        conditions.push(format!("year >= {}", bind(i64::from(min).into())));
+    }
+    if let Some(max) = filter.max_year {
+        conditions.push(format!("year <= {}", bind(i64::from(max).into())));
+    }
+    if conditions.is_empty() {
+        conditions.push("1".to_string());
+    }
+
+    let sql = format!("SELECT mal_id FROM anime WHERE {}", conditions.join(" AND "));
+    (sql, values)
+}
+
+/// `AND mal_id IN (...)` restricting a jobs query to the anime matching
+/// `filter`, if given, with the values of its parameters
+///
+/// The anime are looked up by the query itself (see `find_anime_query`),
+/// with parameters numbered from `?{first_param}` on, after the jobs
+/// query's own. Its SQL only changes with the filter, so workers reuse one
+/// cached statement for every claim.
+fn anime_filter_condition(filter: Option<&AnimeFilter>, first_param: usize) -> (String, Vec<rusqlite::types::Value>) {
+    match filter {
+        Some(filter) => {
+            let (sql, values) = find_anime_query(filter, first_param);
+            (format!(" AND mal_id IN ({})", sql), values)
+        }
+        None => (String::new(), Vec::new()),
+    }
 }
 
 /// Helper: Convert a database row to a Job
@@ -707,11 +2198,98 @@ fn row_to_job(row: &rusqlite::Row) -> rusqlite::Result<Job> {
             audio_deleted: row.get(29)?,
             priority: row.get::<_, i64>(30)? as i32,
             depends_on: row.get::<_, Option<i64>>(31)?,
+            transcript_quality: row.get(32)?,
+            transcript_model: row.get(33)?,
+            next_attempt_at: row.get(34)?,
+            recap_lines: row.get::<_, Option<i64>>(35)?.map(|x| x as u32),
+            whisper_version: row.get(36)?,
+            transcript_deleted: row.get(37)?,
+            tokens_deleted: row.get(38)?,
         })
 }
 
+/// Time jobs have spent in their current stage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: JobStage,
+    /// Number of jobs currently in this stage
+    pub jobs: usize,
+    /// Average seconds since these jobs were last updated
+    pub avg_seconds_in_stage: f64,
+}
+
+/// Stages a job is in while a worker processes it, in pipeline order
+const WORK_STAGES: [JobStage; 4] = [
+    JobStage::Downloading,
+    JobStage::Transcribing,
+    JobStage::Tokenizing,
+    JobStage::Analyzing,
+];
+
+/// Jobs that still have to go through (part of) the pipeline
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RemainingWork {
+    /// Jobs that are neither complete nor failed
+    pub total: usize,
+    /// Those jobs per stage, in pipeline order (stages without jobs left out)
+    pub by_stage: Vec<(JobStage, usize)>,
+}
+
+impl RemainingWork {
+    /// Rough time until every remaining job is complete.
+    ///
+    /// Each step (download, transcribe, tokenize, analyze) is assumed to take
+    /// `avg_seconds_in_stage` of its in-progress stage per job, split across
+    /// `workers` for that stage (1 if not listed). The steps run as a chain,
+    /// so the slowest step's backlog sets the pace and every other step adds
+    /// the time of one job on top. Since the timings are how long the current
+    /// jobs have been running so far, the estimate errs on the short side.
+    ///
+    /// Steps with no job in progress have no timing and are left out; returns
+    /// None if that leaves nothing to estimate from.
+    pub fn estimate(&self, timings: &[StageTiming], workers: &[(JobStage, usize)]) -> Option<Duration> {
+        if self.total == 0 {
+            return Some(Duration::ZERO);
+        }
+
+        let position = |stage: JobStage| JobStage::all().iter().position(|s| *s == stage);
+        let mut latency = 0.0;
+        let mut slowest_backlog: f64 = 0.0;
+        let mut measured = false;
+
+        for step in WORK_STAGES {
+            // Jobs that have not gone past this step yet
+            let waiting: usize = self
+                .by_stage
+                .iter()
+                .filter(|(stage, _)| position(*stage) <= position(step))
+                .map(|(_, jobs)| jobs)
+                .sum();
+            let per_job = timings
+                .iter()
+                .find(|t| t.stage == step && t.jobs > 0)
+                .map(|t| t.avg_seconds_in_stage);
+            let (Some(per_job), true) = (per_job, waiting > 0) else {
+                continue;
+            };
+
+            let step_workers = workers
+                .iter()
+                .find(|(stage, _)| *stage == step)
+                .map_or(1, |(_, n)| *n)
+                .max(1);
+            let busy = waiting as f64 * per_job / step_workers as f64;
+            latency += per_job;
+            slowest_backlog = slowest_backlog.max(busy - per_job);
+            measured = true;
+        }
+
+        measured.then(|| Duration::from_secs_f64((latency + slowest_backlog).max(0.0)))
+    }
+}
+
 /// Job statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct JobStats {
     pub total: usize,
     pub queued: usize,
@@ -725,3 +2303,1160 @@ pub struct JobStats {
     pub complete: usize,
     pub failed: usize,
 }
+
+impl JobStats {
+    /// Serialize the statistics as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize job statistics")
+    }
+}
+
+/// Retries consumed across the queue, from `JobQueue::retry_report`
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RetryReport {
+    /// Retries used by all jobs together
+    pub total_retries: u64,
+    /// Jobs retried at least once
+    pub jobs_retried: usize,
+    /// Retried jobs that have used up all their retries
+    pub jobs_at_max_retries: usize,
+    /// Anime with the most retries, most first
+    pub top_anime: Vec<AnimeRetries>,
+}
+
+/// What `JobQueue::compact_anime` changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AnimeCompaction {
+    /// Duplicate anime rows deleted
+    pub anime_removed: usize,
+    /// Jobs moved to the kept anime row
+    pub jobs_repointed: usize,
+    /// Jobs deleted because a more advanced job for the same episode was kept
+    pub jobs_removed: usize,
+}
+
+/// What `JobQueue::reset_for_retokenize` changed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RetokenizeReset {
+    /// Jobs sent back to `transcribed`
+    pub reset: usize,
+    /// IDs of jobs left as they were because their transcript is gone
+    pub missing_transcript: Vec<i64>,
+}
+
+/// Retries consumed by the jobs of one anime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnimeRetries {
+    pub mal_id: u32,
+    pub title: String,
+    pub retries: u64,
+    /// Jobs of this anime retried at least once
+    pub jobs_retried: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AnimeFilterConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_selection_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        queue.get_or_create_anime(&Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64))?;
+        queue.get_or_create_anime(&Anime::test_fixture(1, "Cowboy Bebop", 26))?;
+
+        queue.cache_selection(
+            5114,
+            "Fullmetal Alchemist: Brotherhood",
+            "Fullmetal Alchemist: Brotherhood",
+            2,
+            "Fullmetal Alchemist: Brotherhood (64 eps)",
+            Confidence::High,
+            Some("Main series"),
+            Some(64),
+            Some(64),
+            Some("exact"),
+        )?;
+
+        assert_eq!(
+            queue.get_selection(5114)?,
+            Some(AnimeSelection {
+                selected_index: 2,
+                selected_title: "Fullmetal Alchemist: Brotherhood (64 eps)".to_string(),
+                confidence: Confidence::High,
+                reason: "Main series".to_string(),
+                mal_episodes: Some(64),
+                selected_episodes: Some(64),
+                episode_match: Some("exact".to_string()),
+                manual: false,
+            })
+        );
+
+        // Rows without a reason or episode info still read back
+        queue.cache_selection(1, "Cowboy Bebop", "Cowboy Bebop", 1, "Cowboy Bebop", Confidence::Low, None, None, None, None)?;
+        let selection = queue.get_selection(1)?.unwrap();
+        assert_eq!(selection.reason, "");
+        assert_eq!(selection.mal_episodes, None);
+        assert_eq!(selection.episode_match, None);
+
+        assert_eq!(queue.get_selection(2)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_manual_selection_survives_reselection() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64);
+        queue.get_or_create_anime(&anime)?;
+        let automated = |queue: &mut JobQueue, index, title: &str| {
+            queue.cache_selection(5114, &anime.title, &anime.title, index, title, Confidence::Low, None, None, None, None)
+        };
+
+        automated(&mut queue, 1, "Hagane no Renkinjutsushi")?;
+        queue.set_manual_selection(5114, 3, "Hagane no Renkinjutsushi: Fullmetal Alchemist")?;
+
+        // A later automated selection (e.g. a --recheck) and a reset leave it alone
+        automated(&mut queue, 1, "Hagane no Renkinjutsushi")?;
+        queue.reset_anime(5114, None)?;
+        let selection = queue.get_selection(5114)?.unwrap();
+        assert_eq!(
+            (selection.selected_index, selection.selected_title.as_str()),
+            (3, "Hagane no Renkinjutsushi: Fullmetal Alchemist")
+        );
+        assert_eq!(selection.confidence, Confidence::High);
+        assert!(selection.manual);
+
+        // Another manual selection replaces it
+        queue.set_manual_selection(5114, 2, "Fullmetal Alchemist: Brotherhood")?;
+        assert_eq!(queue.get_selection(5114)?.unwrap().selected_index, 2);
+
+        // Indexes are 1-based, so 0 or below is rejected
+        assert!(queue.set_manual_selection(5114, 0, "Hagane no Renkinjutsushi").is_err());
+        assert_eq!(queue.get_selection(5114)?.unwrap().selected_index, 2);
+
+        assert!(queue.set_manual_selection(1, 1, "Cowboy Bebop").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_related_anime_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        // Attack on Titan S1 -> S2 -> S3
+        queue.link_related(16498, 25777)?;
+        queue.link_related(25777, 35760)?;
+        // Duplicate and self links are ignored
+        queue.link_related(16498, 25777)?;
+        queue.link_related(16498, 16498)?;
+
+        assert_eq!(queue.get_related(16498)?, vec![25777]);
+        assert_eq!(queue.get_related(25777)?, vec![16498, 35760]);
+        assert_eq!(queue.get_related(35760)?, vec![25777]);
+        assert!(queue.get_related(1)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_stats_json_round_trip() -> Result<()> {
+        let stats = JobStats {
+            total: 10,
+            queued: 3,
+            downloading: 1,
+            downloaded: 1,
+            transcribing: 0,
+            transcribed: 2,
+            tokenizing: 0,
+            tokenized: 0,
+            analyzing: 0,
+            complete: 2,
+            failed: 1,
+        };
+
+        let json = stats.to_json()?;
+        let parsed: JobStats = serde_json::from_str(&json)?;
+        assert_eq!(parsed, stats);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retried_job_waits_for_backoff() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let job_id = queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+
+        // Fail the job and put it back, as a worker does on a retryable error
+        let job = queue.dequeue_next(JobStage::Queued)?;
+        assert_eq!(job.next_attempt_at, None);
+        queue.increment_retry(job_id)?;
+        queue.update_stage(job_id, JobStage::Queued)?;
+
+        let job = queue.get_jobs_by_stage(JobStage::Queued)?.remove(0);
+        let delay = (job.next_attempt_at.unwrap() - job.updated_at).num_seconds();
+        assert_eq!(delay, retry_delay(1).as_secs() as i64);
+        assert!(queue.dequeue(JobStage::Queued, JobStage::Downloading)?.is_none());
+        assert!(queue.dequeue_next_filtered(JobStage::Queued, 5114).is_err());
+
+        // Once the delay has passed the job is picked up again
+        queue.db.conn().execute(
+            "UPDATE jobs SET next_attempt_at = datetime('now', '-1 seconds') WHERE id = ?1",
+            params![job_id],
+        )?;
+        assert_eq!(queue.dequeue_next_filtered(JobStage::Queued, 5114)?.id, job_id);
+
+        // Later failures wait longer
+        queue.increment_retry(job_id)?;
+        let job = queue.get_jobs_by_stage(JobStage::Queued)?.remove(0);
+        let delay = (job.next_attempt_at.unwrap() - job.updated_at).num_seconds();
+        assert_eq!(delay, retry_delay(2).as_secs() as i64);
+
+        // A configured policy replaces the built-in delays
+        let mut queue = queue.with_retry_policy(Some(RetryPolicy {
+            max_attempts: 5,
+            base_delay_ms: 10_000,
+            max_delay_ms: 100_000,
+            jitter: 0.0,
+        }));
+        queue.increment_retry(job_id)?;
+        let job = queue.get_jobs_by_stage(JobStage::Queued)?.remove(0);
+        let delay = (job.next_attempt_at.unwrap() - job.updated_at).num_seconds();
+        assert_eq!(delay, 40);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_anime_analysis_and_exponent_query() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        for (mal_id, title) in [(1, "Cowboy Bebop"), (5114, "Fullmetal Alchemist: Brotherhood"), (9253, "Steins;Gate")] {
+            queue.get_or_create_anime(&Anime::test_fixture(mal_id, title, 24))?;
+        }
+
+        queue.update_anime_analysis(1, 1.25, 0.98, 120_000, None)?;
+        queue.update_anime_analysis(5114, 0.95, 0.97, 334_520, None)?;
+        assert!(queue.update_anime_analysis(42, 1.0, 1.0, 1, None).is_err());
+
+        let fmab = queue.get_all_anime()?.into_iter().find(|a| a.mal_id == 5114).unwrap();
+        assert_eq!(fmab.zipf_exponent, Some(0.95));
+        assert_eq!(fmab.zipf_r_squared, Some(0.97));
+        assert_eq!(fmab.total_tokens, Some(334_520));
+
+        let mal_ids = |anime: Vec<Anime>| anime.iter().map(|a| a.mal_id).collect::<Vec<_>>();
+        assert_eq!(mal_ids(queue.query_anime_by_exponent(1.2..)?), vec![1]);
+        assert_eq!(mal_ids(queue.query_anime_by_exponent(0.9..=1.25)?), vec![5114, 1]);
+        assert_eq!(mal_ids(queue.query_anime_by_exponent(0.9..1.25)?), vec![5114]);
+        // Anime without an analysis never match, even an unbounded range
+        assert_eq!(mal_ids(queue.query_anime_by_exponent(..)?), vec![5114, 1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_anime_gets_missing_lists_filled_in() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        // Scraped before themes and songs were stored
+        let mut fmab = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 64);
+        fmab.genres = vec!["Action".to_string()];
+        let id = queue.get_or_create_anime(&fmab)?;
+        queue.db.conn().execute("UPDATE anime SET opening_themes = NULL WHERE id = ?1", [id])?;
+
+        // A rescrape fills in what is missing and keeps what is stored
+        fmab.genres = vec!["Drama".to_string()];
+        fmab.themes = vec!["Military".to_string()];
+        fmab.opening_themes = vec!["again".to_string()];
+        fmab.ending_themes = vec!["Uso".to_string()];
+        assert_eq!(queue.get_or_create_anime(&fmab)?, id);
+
+        let stored = queue.get_anime(5114)?.unwrap();
+        assert_eq!(stored.genres, ["Action"]);
+        assert_eq!(stored.themes, ["Military"]);
+        assert_eq!((stored.opening_themes, stored.ending_themes), (vec!["again".to_string()], vec!["Uso".to_string()]));
+
+        let find = |filter: AnimeFilter| queue.find_anime(&filter);
+        assert_eq!(find(AnimeFilter { themes: vec!["Military".to_string()], ..Default::default() })?, vec![5114]);
+        assert!(find(AnimeFilter { genres: vec!["Drama".to_string()], ..Default::default() })?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_anime_by_classification() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let strings = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        for (mal_id, anime_type, year, genres, themes, demographic, studio) in [
+            (1, "TV", 2007, &["Slice of Life", "Comedy"][..], &["School"][..], "Seinen", "Kyoto Animation"),
+            (2, "TV", 2013, &["Comedy", "Slice of Lifestyle"], &[], "Shounen", "Shaft"),
+            (3, "Movie", 2016, &["Slice of Life", "Comedy", "Drama"], &["School"], "Shoujo", "Kyoto Animation"),
+            (4, "TV", 2020, &["Action"], &[], "Shounen", "MAPPA"),
+        ] {
+            let episodes = if anime_type == "Movie" { 1 } else { 12 };
+            let mut anime = Anime::test_fixture(mal_id, "Test Anime", episodes);
+            anime.anime_type = Some(anime_type.to_string());
+            anime.year = Some(year);
+            anime.genres = strings(genres);
+            anime.themes = strings(themes);
+            anime.demographics = strings(&[demographic]);
+            anime.studios = strings(&[studio]);
+            let anime_id = queue.get_or_create_anime(&anime)?;
+            queue.enqueue(&NewJob {
+                anime_id,
+                mal_id,
+                anime_title: anime.title.clone(),
+                episode: 1,
+                season: None,
+                year: None,
+                // The filtered-out anime come first otherwise
+                priority: mal_id as i32,
+            })?;
+        }
+        let mut ecchi = Anime::test_fixture(5, "Test Anime", 12);
+        ecchi.genres = strings(&["Comedy"]);
+        ecchi.explicit_genres = strings(&["Ecchi"]);
+        queue.get_or_create_anime(&ecchi)?;
+
+        let find = |filter: AnimeFilter| queue.find_anime(&filter);
+        assert_eq!(find(AnimeFilter::default())?, vec![1, 2, 3, 4, 5]);
+
+        // Every genre is required, matched by whole name and case-insensitively
+        let slice_of_life_comedy = AnimeFilter {
+            genres: strings(&["slice of life", "Comedy"]),
+            ..Default::default()
+        };
+        assert_eq!(find(slice_of_life_comedy.clone())?, vec![1, 3]);
+        assert_eq!(find(AnimeFilter { genres: strings(&["Ecchi"]), ..Default::default() })?, vec![5]);
+
+        // Any one of the studios or demographics, and types and episode
+        // counts as in [mal_scraper.filter]
+        let filter = AnimeFilter {
+            studios: strings(&["Shaft", "MAPPA"]),
+            ..Default::default()
+        };
+        assert_eq!(find(filter)?, vec![2, 4]);
+        let filter = AnimeFilter {
+            kind: AnimeFilterConfig { types: strings(&["tv"]), ..Default::default() },
+            demographics: strings(&["Seinen", "Shoujo"]),
+            ..Default::default()
+        };
+        assert_eq!(find(filter)?, vec![1]);
+        let filter = AnimeFilter {
+            kind: AnimeFilterConfig { min_episodes: Some(2), ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(find(filter)?, vec![1, 2, 4, 5]);
+
+        // Years are inclusive; anime without a year never match a range
+        let filter = AnimeFilter {
+            themes: strings(&["School"]),
+            min_year: Some(2010),
+            ..Default::default()
+        };
+        assert_eq!(find(filter)?, vec![3]);
+        let filter = AnimeFilter {
+            min_year: Some(2013),
+            max_year: Some(2016),
+            ..Default::default()
+        };
+        assert_eq!(find(filter.clone())?, vec![2, 3]);
+
+        // Passed on to stage binaries as flags
+        assert_eq!(filter.to_args(), ["--filter-year", "2013..2016"]);
+        assert_eq!(parse_year_range("2013..2016")?, (Some(2013), Some(2016)));
+        assert_eq!(parse_year_range("2020")?, (Some(2020), Some(2020)));
+        assert_eq!(parse_year_range("..2010")?, (None, Some(2010)));
+        assert!(parse_year_range("2016..2013").is_err());
+        assert!(parse_year_range("recent").is_err());
+
+        // Genres are found through the lookup table's index, not by scanning
+        // the anime table's JSON
+        let (sql, values) = find_anime_query(&AnimeFilter {
+            genres: strings(&["Comedy"]),
+            ..Default::default()
+        }, 1);
+        let mut stmt = queue.db.conn().prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        let plan = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| row.get::<_, String>(3))?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .join("\n");
+        assert!(plan.contains("anime_genres USING COVERING INDEX idx_anime_genres_name (name=?)"), "{}", plan);
+        assert!(plan.contains("anime_explicit_genres USING COVERING INDEX"), "{}", plan);
+        drop(stmt);
+
+        // A filtered queue only hands out jobs of matching anime
+        let mut queue = queue.with_anime_filter(slice_of_life_comedy.clone());
+        assert_eq!(queue.count_pending(JobStage::Queued, None)?, 2);
+        assert_eq!(queue.count_pending(JobStage::Queued, Some(3))?, 1);
+        assert_eq!(queue.count_pending(JobStage::Queued, Some(4))?, 0);
+        assert_eq!(queue.dequeue_next(JobStage::Queued)?.mal_id, 3);
+        let batch = queue.dequeue_batch(JobStage::Queued, JobStage::Downloaded, 10)?;
+        assert_eq!(batch.iter().map(|job| job.mal_id).collect::<Vec<_>>(), vec![3, 1]);
+        let any = queue.dequeue_next_any(&[JobStage::Queued, JobStage::Downloaded])?;
+        assert!(any.is_some_and(|(_, job)| [1, 3].contains(&job.mal_id)));
+        queue.update_stage(1, JobStage::Complete)?;
+        queue.update_stage(3, JobStage::Complete)?;
+        assert!(queue.dequeue(JobStage::Queued, JobStage::Downloading)?.is_none());
+
+        // The filter is applied by each claim's query, so anime scraped
+        // after the queue was created are picked up
+        let mut anime = Anime::test_fixture(6, "Test Anime", 12);
+        anime.genres = strings(&["Comedy", "Slice of Life"]);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: 6,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        assert_eq!(queue.count_pending(JobStage::Queued, None)?, 1);
+        let mut breadth_first = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?)
+            .with_anime_filter(slice_of_life_comedy)
+            .with_dequeue_order(DequeueOrder::BreadthFirst);
+        let claimed = breadth_first.dequeue(JobStage::Queued, JobStage::Downloading)?;
+        assert_eq!(claimed.map(|job| job.mal_id), Some(6));
+        assert!(breadth_first.dequeue(JobStage::Queued, JobStage::Downloading)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_confidence_round_trip() -> Result<()> {
+        for confidence in Confidence::ALL {
+            assert_eq!(confidence.to_string().parse::<Confidence>()?, confidence);
+        }
+        assert!("unknown".parse::<Confidence>().is_err());
+        assert!("High".parse::<Confidence>().is_err());
+        assert!(Confidence::NoCandidates < Confidence::Low && Confidence::Medium < Confidence::High);
+
+        // The CHECK constraint on the column accepts every level
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        for (mal_id, confidence) in (1..).zip(Confidence::ALL) {
+            queue.get_or_create_anime(&Anime::test_fixture(mal_id, "Test Anime", 1))?;
+            queue.cache_selection(mal_id, "Test Anime", "Test Anime", 1, "Test Anime", confidence, None, None, None, None)?;
+            assert_eq!(queue.get_selection(mal_id)?.unwrap().confidence, confidence);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failure_summary_groups_by_signature() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 6);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+
+        let errors = [
+            Some("ani-cli failed with exit code: 1"),
+            Some("ani-cli failed with exit code: 2"),
+            Some("Video file not found: /data/videos/5114/episodes/ep003.mkv"),
+            Some("Video file not found: C:\\data\\videos\\1\\ep012.mkv\nCaused by: missing"),
+            Some("ani-cli failed with exit code: 127"),
+            None,
+        ];
+        for (episode, error) in (1..).zip(errors) {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            match error {
+                Some(error) => queue.fail_job(job_id, error)?,
+                None => queue.update_stage(job_id, JobStage::Failed)?,
+            }
+        }
+        // Still queued, so not part of the summary
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 7,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+
+        assert_eq!(queue.get_failed_jobs()?.len(), 6);
+        assert_eq!(
+            queue.failure_summary()?,
+            vec![
+                ("ani-cli failed with exit code: N".to_string(), 3),
+                ("Video file not found: <path>".to_string(), 2),
+                (NO_ERROR_MESSAGE.to_string(), 1),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_report() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        assert_eq!(queue.retry_report(None)?, RetryReport::default());
+
+        // Retries per episode; each job allows 3
+        for (mal_id, title, retries) in [
+            (5114, "Fullmetal Alchemist: Brotherhood", vec![3, 1]),
+            (9253, "Steins;Gate", vec![2, 3]),
+            (1535, "Death Note", vec![0, 0, 0]),
+        ] {
+            let anime = Anime::test_fixture(mal_id, title, retries.len() as u32);
+            let anime_id = queue.get_or_create_anime(&anime)?;
+            for (episode, &count) in (1..).zip(&retries) {
+                let job_id = queue.enqueue(&NewJob {
+                    anime_id,
+                    mal_id,
+                    anime_title: title.to_string(),
+                    episode,
+                    season: None,
+                    year: None,
+                    priority: 0,
+                })?;
+                for _ in 0..count {
+                    queue.increment_retry(job_id)?;
+                }
+            }
+        }
+
+        let report = queue.retry_report(None)?;
+        assert_eq!(report.total_retries, 9);
+        assert_eq!(report.jobs_retried, 4);
+        assert_eq!(report.jobs_at_max_retries, 2);
+        // A lower limit set for the workers puts more jobs at it
+        assert_eq!(queue.retry_report(Some(2))?.jobs_at_max_retries, 3);
+        assert_eq!(queue.retry_report(Some(5))?.jobs_at_max_retries, 0);
+        assert_eq!(
+            report.top_anime,
+            [
+                AnimeRetries {
+                    mal_id: 9253,
+                    title: "Steins;Gate".to_string(),
+                    retries: 5,
+                    jobs_retried: 2,
+                },
+                AnimeRetries {
+                    mal_id: 5114,
+                    title: "Fullmetal Alchemist: Brotherhood".to_string(),
+                    retries: 4,
+                    jobs_retried: 2,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dequeue_batch_claims_disjoint_jobs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut first = JobQueue::new(Database::open(&db_path)?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 10);
+        let anime_id = first.get_or_create_anime(&anime)?;
+        for episode in 1..=10 {
+            first.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                // Episode 10 jumps the queue
+                priority: if episode == 10 { 1 } else { 0 },
+            })?;
+        }
+
+        // A second worker with its own connection to the same database
+        let mut second = JobQueue::new(Database::open(&db_path)?);
+        let a = first.dequeue_batch(JobStage::Queued, JobStage::Downloading, 5)?;
+        let b = second.dequeue_batch(JobStage::Queued, JobStage::Downloading, 5)?;
+
+        let episodes = |jobs: &[Job]| jobs.iter().map(|j| j.episode).collect::<Vec<_>>();
+        assert_eq!(episodes(&a), [10, 1, 2, 3, 4]);
+        assert_eq!(episodes(&b), [5, 6, 7, 8, 9]);
+        assert!(a.iter().chain(&b).all(|j| j.stage == JobStage::Downloading));
+
+        assert!(first.dequeue_batch(JobStage::Queued, JobStage::Downloading, 5)?.is_empty());
+        assert_eq!(second.get_jobs_by_stage(JobStage::Downloading)?.len(), 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_breadth_first_dequeue_cycles_through_anime() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("jobs.db");
+        let mut queue = JobQueue::new(Database::open(&db_path)?);
+        for (mal_id, episodes, priority) in [(5114, 3, 2), (9253, 2, 1), (1535, 1, 1)] {
+            let anime = Anime::test_fixture(mal_id, "Test Anime", episodes);
+            let anime_id = queue.get_or_create_anime(&anime)?;
+            for episode in 1..=episodes {
+                queue.enqueue(&NewJob {
+                    anime_id,
+                    mal_id,
+                    anime_title: anime.title.clone(),
+                    episode,
+                    season: None,
+                    year: None,
+                    priority,
+                })?;
+            }
+        }
+        let claimed = |jobs: Vec<Job>| jobs.iter().map(|j| (j.mal_id, j.episode)).collect::<Vec<_>>();
+
+        // By default the highest-priority anime is drained first
+        let by_priority = queue.dequeue_batch(JobStage::Queued, JobStage::Queued, 6)?;
+        assert_eq!(
+            claimed(by_priority),
+            [(5114, 1), (5114, 2), (5114, 3), (9253, 1), (9253, 2), (1535, 1)]
+        );
+
+        // Breadth-first takes one episode of each anime in turn, by priority
+        // then MAL ID, and batches carry on the same round
+        let mut queue = JobQueue::new(Database::open(&db_path)?).with_dequeue_order(DequeueOrder::BreadthFirst);
+        let mut order = Vec::new();
+        let mut job_ids = Vec::new();
+        for _ in 0..2 {
+            let job = queue.dequeue(JobStage::Queued, JobStage::Downloading)?.unwrap();
+            order.push((job.mal_id, job.episode));
+            job_ids.push(job.id);
+        }
+        let batch = queue.dequeue_batch(JobStage::Queued, JobStage::Downloading, 3)?;
+        job_ids.extend(batch.iter().map(|j| j.id));
+        order.extend(claimed(batch));
+        let job = queue.dequeue(JobStage::Queued, JobStage::Downloading)?.unwrap();
+        order.push((job.mal_id, job.episode));
+        job_ids.push(job.id);
+        let breadth_first = [(5114, 1), (1535, 1), (9253, 1), (5114, 2), (9253, 2), (5114, 3)];
+        assert_eq!(order, breadth_first);
+        assert!(queue.dequeue(JobStage::Queued, JobStage::Downloading)?.is_none());
+
+        // dequeue_next and dequeue_next_any go in the same order
+        for &id in &job_ids {
+            queue.update_stage(id, JobStage::Downloaded)?;
+        }
+        let mut order = Vec::new();
+        for _ in 0..breadth_first.len() {
+            let job = queue.dequeue_next(JobStage::Downloaded)?;
+            order.push((job.mal_id, job.episode));
+            queue.update_stage(job.id, JobStage::Transcribed)?;
+        }
+        assert_eq!(order, breadth_first);
+        assert!(queue.dequeue_next(JobStage::Downloaded).is_err());
+        let mut order = Vec::new();
+        while let Some((_, job)) = queue.dequeue_next_any(&[JobStage::Queued, JobStage::Transcribed])? {
+            order.push((job.mal_id, job.episode));
+        }
+        assert_eq!(order, breadth_first);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dequeue_next_any_claims_by_priority_across_stages() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 4);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let mut job_ids = Vec::new();
+        for (episode, priority) in [(1, 0), (2, 2), (3, 1), (4, 5)] {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority,
+            })?);
+        }
+        // Episodes 1 and 2 are downloaded, 3 is still queued and 4 is at a
+        // stage this worker does not handle
+        queue.update_stage(job_ids[0], JobStage::Downloaded)?;
+        queue.update_stage(job_ids[1], JobStage::Downloaded)?;
+        queue.update_stage(job_ids[3], JobStage::Transcribed)?;
+
+        // Claimed jobs leave the eligible stages for their in-progress ones
+        let stages = [JobStage::Queued, JobStage::Downloaded];
+        let mut claimed = Vec::new();
+        while let Some((from, job)) = queue.dequeue_next_any(&stages)? {
+            claimed.push((job.episode, from, job.stage));
+        }
+        assert_eq!(
+            claimed,
+            [
+                (2, JobStage::Downloaded, JobStage::Transcribing),
+                (3, JobStage::Queued, JobStage::Downloading),
+                (1, JobStage::Downloaded, JobStage::Transcribing),
+            ]
+        );
+
+        assert!(queue.dequeue_next_any(&[])?.is_none());
+        assert!(queue.dequeue_next_any(&[JobStage::Transcribing]).is_err());
+        let (from, job) = queue.dequeue_next_any(&[JobStage::Transcribed])?.unwrap();
+        assert_eq!((from, job.stage), (JobStage::Transcribed, JobStage::Tokenizing));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_transaction_commits_or_rolls_back_together() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let job_id = queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        queue.update_stage(job_id, JobStage::Transcribing)?;
+
+        let transcribe = |queue: &mut JobQueue| -> Result<()> {
+            queue.update_metadata(
+                job_id,
+                &JobMetadata {
+                    transcript_quality: Some(0.9),
+                    ..Default::default()
+                },
+            )?;
+            queue.mark_video_deleted(job_id)?;
+            // Self-contained transactions nest inside
+            assert!(queue.dequeue(JobStage::Transcribing, JobStage::Transcribing)?.is_some());
+            queue.update_stage(job_id, JobStage::Transcribed)
+        };
+        let job = |queue: &JobQueue| queue.get_all_jobs().map(|mut jobs| jobs.remove(0));
+
+        // A panic halfway through leaves nothing behind
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            queue.with_transaction(|queue| -> Result<()> {
+                transcribe(queue)?;
+                panic!("worker crashed");
+            })
+        }));
+        assert!(panicked.is_err());
+        let unchanged = job(&queue)?;
+        assert_eq!(unchanged.stage, JobStage::Transcribing);
+        assert_eq!(unchanged.transcript_quality, None);
+        assert!(!unchanged.video_deleted);
+        assert!(unchanged.started_at.is_none());
+
+        // So does an error
+        let err = queue.with_transaction(|queue| -> Result<()> {
+            transcribe(queue)?;
+            anyhow::bail!("disk full")
+        });
+        assert!(err.is_err());
+        assert_eq!(job(&queue)?.stage, JobStage::Transcribing);
+
+        // Success commits every change at once
+        queue.with_transaction(transcribe)?;
+        let committed = job(&queue)?;
+        assert_eq!(committed.stage, JobStage::Transcribed);
+        assert_eq!(committed.transcript_quality, Some(0.9));
+        assert!(committed.video_deleted);
+
+        // Nothing is left open after a rollback: other writes still persist
+        queue.update_stage(job_id, JobStage::Tokenized)?;
+        drop(queue);
+        let reopened = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        assert_eq!(job(&reopened)?.stage, JobStage::Tokenized);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_oldest_pending_age() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 3);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=3 {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?);
+        }
+        assert_eq!(queue.oldest_pending_age(JobStage::Downloading)?, None);
+
+        // Queued jobs age from when they were enqueued
+        fn seed(queue: &JobQueue, id: i64, created: &str, started: Option<&str>) -> rusqlite::Result<usize> {
+            queue.db.conn().execute(
+                "UPDATE jobs SET created_at = datetime('now', ?1),
+                                 started_at = CASE WHEN ?2 IS NULL THEN NULL ELSE datetime('now', ?2) END
+                 WHERE id = ?3",
+                params![created, started, id],
+            )
+        }
+        seed(&queue, job_ids[0], "-3 days", None)?;
+        seed(&queue, job_ids[1], "-2 hours", None)?;
+        let age = queue.oldest_pending_age(JobStage::Queued)?.unwrap();
+        assert!((age.as_secs() as i64 - 3 * 86400).abs() <= 2, "{:?}", age);
+
+        // Claimed jobs age from when the worker picked them up
+        queue.update_stage(job_ids[2], JobStage::Downloading)?;
+        seed(&queue, job_ids[2], "-3 days", Some("-10 minutes"))?;
+        let age = queue.oldest_pending_age(JobStage::Downloading)?.unwrap();
+        assert!((age.as_secs() as i64 - 600).abs() <= 2, "{:?}", age);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remaining_work_estimate() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 4);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=4 {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?);
+        }
+        queue.update_stage(job_ids[0], JobStage::Complete)?;
+        queue.update_stage(job_ids[1], JobStage::Failed)?;
+        queue.update_stage(job_ids[2], JobStage::Transcribing)?;
+        assert_eq!(
+            queue.remaining_work()?,
+            RemainingWork {
+                total: 2,
+                by_stage: vec![(JobStage::Queued, 1), (JobStage::Transcribing, 1)],
+            }
+        );
+
+        let timing = |stage, seconds| StageTiming {
+            stage,
+            jobs: 1,
+            avg_seconds_in_stage: seconds,
+        };
+        let remaining = RemainingWork {
+            total: 16,
+            by_stage: vec![
+                (JobStage::Queued, 10),
+                (JobStage::Downloading, 2),
+                (JobStage::Downloaded, 3),
+                (JobStage::Transcribing, 1),
+            ],
+        };
+        let timings = [
+            timing(JobStage::Downloading, 60.0),
+            timing(JobStage::Transcribing, 120.0),
+            timing(JobStage::Complete, 9000.0),
+        ];
+
+        // 12 downloads on 2 workers take 360s, 16 transcriptions on 1 worker
+        // take 1920s; transcription is the bottleneck and the first download
+        // has to finish before it can start
+        let workers = [(JobStage::Downloading, 2), (JobStage::Transcribing, 1)];
+        assert_eq!(remaining.estimate(&timings, &workers), Some(Duration::from_secs(60 + 1920)));
+
+        // With 4 transcription workers (480s) downloads are still faster
+        let workers = [(JobStage::Downloading, 2), (JobStage::Transcribing, 4)];
+        assert_eq!(remaining.estimate(&timings, &workers), Some(Duration::from_secs(60 + 480)));
+
+        // With 16 (120s) the 12 downloads become the bottleneck
+        let workers = [(JobStage::Downloading, 2), (JobStage::Transcribing, 16)];
+        assert_eq!(remaining.estimate(&timings, &workers), Some(Duration::from_secs(360 + 120)));
+
+        assert_eq!(remaining.estimate(&[], &workers), None);
+        assert_eq!(RemainingWork::default().estimate(&[], &workers), Some(Duration::ZERO));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_anime() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let mut job_ids = Vec::new();
+        for anime in [Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 3), Anime::test_fixture(1, "Cowboy Bebop", 1)] {
+            let anime_id = queue.get_or_create_anime(&anime)?;
+            for episode in 1..=anime.episodes_total.unwrap() {
+                job_ids.push(queue.enqueue(&NewJob {
+                    anime_id,
+                    mal_id: anime.mal_id,
+                    anime_title: anime.title.clone(),
+                    episode,
+                    season: None,
+                    year: None,
+                    priority: 0,
+                })?);
+            }
+            queue.cache_selection(anime.mal_id, &anime.title, &anime.title, 1, &anime.title, Confidence::High, None, None, None, None)?;
+        }
+
+        // Episodes of 5114 in various states
+        queue.update_job_with_video(job_ids[0], data_paths.video_file(5114, "Fullmetal Alchemist: Brotherhood", 1, "mkv"), 4000)?;
+        queue.update_metadata(job_ids[0], &JobMetadata {
+            transcript_path: Some("transcripts/5114/ep1.txt".to_string()),
+            word_count: Some(1200),
+            transcript_quality: Some(0.9),
+            transcript_model: Some("base".to_string()),
+            ..Default::default()
+        })?;
+        queue.mark_video_deleted(job_ids[0])?;
+        queue.update_stage(job_ids[0], JobStage::Complete)?;
+        queue.fail_job(job_ids[1], "boom")?;
+        queue.update_stage(job_ids[2], JobStage::Transcribed)?;
+        queue.update_stage(job_ids[3], JobStage::Complete)?;
+
+        std::fs::create_dir_all(data_paths.transcript_dir(5114))?;
+        std::fs::write(data_paths.transcript_txt(5114, "Fullmetal Alchemist: Brotherhood", 1), "こんにちは")?;
+        std::fs::create_dir_all(data_paths.transcript_dir(1))?;
+        std::fs::write(data_paths.transcript_txt(1, "Cowboy Bebop", 1), "こんにちは")?;
+
+        assert_eq!(queue.reset_anime(5114, Some(&data_paths))?, 3);
+
+        for job in queue.get_all_jobs()?.into_iter().filter(|j| j.mal_id == 5114) {
+            assert_eq!(job.stage, JobStage::Queued);
+            assert_eq!(job.progress, 0.0);
+            assert_eq!(job.error_message, None);
+            assert_eq!(job.retry_count, 0);
+            assert_eq!(job.next_attempt_at, None);
+            assert_eq!(job.video_path, None);
+            assert_eq!(job.transcript_path, None);
+            assert_eq!(job.video_size_bytes, None);
+            assert_eq!(job.word_count, None);
+            assert_eq!(job.transcript_quality, None);
+            assert_eq!(job.transcript_model, None);
+            assert!(!job.video_deleted);
+        }
+        assert_eq!(queue.get_selection(5114)?, None);
+        assert!(!data_paths.transcript_dir(5114).exists());
+
+        // Other anime are untouched
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Complete)?.len(), 1);
+        assert!(queue.get_selection(1)?.is_some());
+        assert!(data_paths.transcript_txt(1, "Cowboy Bebop", 1).exists());
+
+        // Without data paths the files stay
+        queue.update_stage(job_ids[3], JobStage::Complete)?;
+        assert_eq!(queue.reset_anime(1, None)?, 1);
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Queued)?.len(), 4);
+        assert!(data_paths.transcript_txt(1, "Cowboy Bebop", 1).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_job() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let mut job_ids = HashMap::new();
+        for anime in [Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 3), Anime::test_fixture(1, "Cowboy Bebop", 3)] {
+            let anime_id = queue.get_or_create_anime(&anime)?;
+            for episode in 1..=3 {
+                let job_id = queue.enqueue(&NewJob {
+                    anime_id,
+                    mal_id: anime.mal_id,
+                    anime_title: anime.title.clone(),
+                    episode,
+                    season: None,
+                    year: None,
+                    priority: 0,
+                })?;
+                job_ids.insert((anime.mal_id, episode), job_id);
+            }
+        }
+        queue.update_stage(job_ids[&(5114, 2)], JobStage::Complete)?;
+
+        let job = queue.get_job(5114, 2)?.expect("episode 2 has a job");
+        assert_eq!(job.id, job_ids[&(5114, 2)]);
+        assert_eq!((job.mal_id, job.episode), (5114, 2));
+        assert_eq!(job.stage, JobStage::Complete);
+        assert_eq!(queue.get_job(1, 2)?.map(|j| j.id), Some(job_ids[&(1, 2)]));
+
+        assert!(queue.get_job(5114, 4)?.is_none());
+        assert!(queue.get_job(21, 1)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_for_retokenize() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 5);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=5 {
+            job_ids.push(queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?);
+        }
+
+        // Episodes 1-3 tokenized or further, 4 still waiting on download, 5
+        // complete but its transcript was deleted. Episodes 3 and 5 record
+        // their transcript somewhere other than the default path; episode
+        // 5's default path holds a stray file.
+        std::fs::create_dir_all(data_paths.transcript_dir(5114))?;
+        for (index, stage) in [JobStage::Tokenized, JobStage::Analyzing, JobStage::Complete, JobStage::Queued, JobStage::Complete]
+            .into_iter()
+            .enumerate()
+        {
+            let episode = index as u32 + 1;
+            let recorded = data_paths.transcript_dir(5114).join(format!("imported_ep{:03}.txt", episode));
+            queue.update_metadata(job_ids[index], &JobMetadata {
+                word_count: Some(1200),
+                token_count: Some(900),
+                tokens_path: Some(data_paths.tokens_json(5114, episode).to_string_lossy().to_string()),
+                transcript_path: [3, 5].contains(&episode).then(|| recorded.to_string_lossy().to_string()),
+                ..Default::default()
+            })?;
+            queue.update_stage(job_ids[index], stage)?;
+            match episode {
+                1 | 2 | 5 => std::fs::write(data_paths.transcript_txt(5114, &anime.title, episode), "こんにちは")?,
+                3 => std::fs::write(&recorded, "こんにちは")?,
+                _ => {}
+            }
+        }
+
+        let reset = queue.reset_for_retokenize(&data_paths)?;
+        assert_eq!(reset, RetokenizeReset { reset: 3, missing_transcript: vec![job_ids[4]] });
+
+        let jobs = queue.get_all_jobs()?;
+        let job = |id: i64| jobs.iter().find(|j| j.id == id).unwrap();
+        for &id in &job_ids[..3] {
+            assert_eq!(job(id).stage, JobStage::Transcribed);
+            assert_eq!(job(id).token_count, None);
+            assert_eq!(job(id).tokens_path, None);
+            assert_eq!(job(id).word_count, Some(1200));
+        }
+        assert_eq!(job(job_ids[3]).stage, JobStage::Queued);
+        assert_eq!(job(job_ids[4]).stage, JobStage::Complete);
+        assert_eq!(job(job_ids[4]).token_count, Some(900));
+
+        // Nothing is left to reset until the jobs are tokenized again
+        assert_eq!(queue.reset_for_retokenize(&data_paths)?.reset, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_anime_merges_duplicates() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 2);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        for episode in 1..=2 {
+            queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+        }
+        queue.get_or_create_anime(&Anime::test_fixture(1, "Cowboy Bebop", 1))?;
+
+        // Rebuild the anime table without the UNIQUE on mal_id, as in a
+        // database that predates it, then save 5114 again with its own jobs
+        // (episodes 1 and 2 clash) and add a job pointing at no anime row
+        queue.db.conn_mut().execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             CREATE TABLE anime_copy AS SELECT * FROM anime;
+             DROP TABLE anime;
+             ALTER TABLE anime_copy RENAME TO anime;
+             CREATE UNIQUE INDEX idx_anime_id ON anime(id);
+             INSERT INTO anime (id, mal_id, title) VALUES (100, 5114, 'Fullmetal Alchemist: Brotherhood');
+             INSERT INTO jobs (anime_id, mal_id, anime_title, episode, stage) VALUES
+                 (100, 5114, 'Fullmetal Alchemist: Brotherhood', 1, 'failed'),
+                 (100, 5114, 'Fullmetal Alchemist: Brotherhood', 2, 'complete'),
+                 (100, 5114, 'Fullmetal Alchemist: Brotherhood', 3, 'queued'),
+                 (999, 5114, 'Fullmetal Alchemist: Brotherhood', 4, 'queued');
+             INSERT INTO analysis_results (anime_id, total_words) VALUES (100, 1200);
+             PRAGMA foreign_keys = ON;",
+        )?;
+
+        let compaction = queue.compact_anime()?;
+        assert_eq!(
+            compaction,
+            AnimeCompaction {
+                anime_removed: 1,
+                jobs_repointed: 3,
+                jobs_removed: 2,
+            }
+        );
+
+        let jobs: Vec<_> = queue.get_all_jobs()?.into_iter().filter(|j| j.mal_id == 5114).collect();
+        assert!(jobs.iter().all(|j| j.anime_id == anime_id));
+        let mut episodes: Vec<u32> = jobs.iter().map(|j| j.episode).collect();
+        episodes.sort();
+        assert_eq!(episodes, vec![1, 2, 3, 4]);
+        // The duplicate's complete job for episode 2 wins over the kept
+        // row's queued one; its failed job for episode 1 loses
+        let stage = |episode| jobs.iter().find(|j| j.episode == episode).unwrap().stage;
+        assert_eq!(stage(2), JobStage::Complete);
+        assert_eq!([stage(1), stage(3), stage(4)], [JobStage::Queued; 3]);
+
+        let conn = queue.db.conn();
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM anime WHERE mal_id = 5114", [], |row| row.get(0))?;
+        assert_eq!(rows, 1);
+        let analysis_anime: i64 = conn.query_row("SELECT anime_id FROM analysis_results", [], |row| row.get(0))?;
+        assert_eq!(analysis_anime, anime_id);
+
+        // Nothing left to do on a second run
+        assert_eq!(queue.compact_anime()?, AnimeCompaction::default());
+
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 //! Rate limiter implementation using token bucket algorithm.
 //!
 //! Enforces both per-second and per-minute rate limits for API requests.
+//! Used for the Jikan API by the scraper and for the Anthropic API by the
+//! anime selector.
 
 use std::time::{Duration, Instant};
 use tokio::time::sleep;
@@ -0,0 +1,305 @@
+//! Retry policies.
+//!
+//! A `RetryPolicy` is retry behavior as set in the config: how many attempts
+//! to make and how long to wait between them. `[mal_scraper.retry]` applies
+//! to Jikan requests and `[anthropic.retry]` to the anime selector's Claude
+//! requests (both through `RetryingClient`), and `[pipeline.retry]` to
+//! failed jobs;
+//! `retry_async` runs any fallible operation under a policy.
+//!
+//! By default a worker puts a failed job back in its input stage until the
+//! job's own `max_retries` is used up. A `JobRetryPolicy` lets a run override
+//! that limit, or disable retries so failures stay `failed` for inspection.
+//!
+//! Retried jobs are not eligible for dequeue again until `retry_delay` (or
+//! the delay of the queue's `RetryPolicy`) has passed, so a source that keeps
+//! failing isn't retried back-to-back.
+
+use crate::http::Backoff;
+use crate::models::Job;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Delay before the first retry
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Upper bound for the retry delay
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Delay before a job that has failed `retry_count` times is attempted again.
+///
+/// Doubles with every failure: 1 minute, 2 minutes, 4 minutes, ... capped at
+/// one hour.
+pub fn retry_delay(retry_count: u32) -> Duration {
+    let exponent = retry_count.saturating_sub(1).min(31);
+    BASE_RETRY_DELAY
+        .saturating_mul(1 << exponent)
+        .min(MAX_RETRY_DELAY)
+}
+
+/// How often, and how far apart, an operation is attempted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included (1 = never retry)
+    pub max_attempts: u32,
+
+    /// Delay before the first retry in milliseconds; doubled for every
+    /// further retry
+    pub base_delay_ms: u64,
+
+    /// Upper bound for the delay in milliseconds
+    pub max_delay_ms: u64,
+
+    /// Fraction of each delay that is random (0.25 = 75-100% of it)
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Retries after the first attempt
+    pub fn max_retries(&self) -> u32 {
+        self.max_attempts.saturating_sub(1)
+    }
+
+    /// The policy's delays as a `Backoff`
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(Duration::from_millis(self.base_delay_ms))
+            .with_max_delay(Duration::from_millis(self.max_delay_ms))
+            .with_jitter(self.jitter)
+    }
+
+    /// Delay before retrying after `retries` retries have already been made
+    pub fn delay(&self, retries: u32) -> Duration {
+        self.backoff().delay(retries, None)
+    }
+
+    /// Check the policy makes sense, naming it `section` in the error
+    pub fn validate(&self, section: &str) -> Result<()> {
+        if self.max_attempts == 0 {
+            anyhow::bail!("{}.max_attempts must be greater than 0", section);
+        }
+        if self.base_delay_ms > self.max_delay_ms {
+            anyhow::bail!(
+                "{}.base_delay_ms ({}) exceeds max_delay_ms ({})",
+                section,
+                self.base_delay_ms,
+                self.max_delay_ms
+            );
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            anyhow::bail!("{}.jitter must be between 0 and 1 (got {})", section, self.jitter);
+        }
+        Ok(())
+    }
+}
+
+/// Whether, and when, `retry_async` retries a failed attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    /// Give up and return the error
+    No,
+    /// Retry after the policy's backoff delay
+    Backoff,
+    /// Retry after the given delay (e.g. a server's `Retry-After`)
+    After(Duration),
+}
+
+/// Run `operation` until it succeeds, `classify` says its error is not
+/// worth retrying, or `policy.max_attempts` attempts have been made.
+///
+/// `operation` is passed the number of retries made so far (0 for the first
+/// attempt). Fails with the error of the last attempt.
+pub async fn retry_async<T, F, Fut>(
+    policy: &RetryPolicy,
+    classify: impl Fn(&anyhow::Error) -> Retry,
+    mut operation: F,
+) -> Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let backoff = policy.backoff();
+    let mut attempt = 0;
+
+    loop {
+        let error = match operation(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        if attempt >= policy.max_retries() {
+            return Err(error);
+        }
+        let delay = match classify(&error) {
+            Retry::No => return Err(error),
+            Retry::Backoff => backoff.delay(attempt, None),
+            Retry::After(delay) => backoff.delay(attempt, Some(delay)),
+        };
+
+        warn!(
+            error = %error,
+            attempt = attempt + 1,
+            retry_in_ms = delay.as_millis() as u64,
+            "Attempt failed, retrying"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// How a worker handles a failed job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobRetryPolicy {
+    /// Reset failed jobs for another attempt (false = fail immediately)
+    auto_retry: bool,
+    /// Overrides each job's `max_retries` (None = use the job's value)
+    max_retries: Option<u32>,
+}
+
+impl Default for JobRetryPolicy {
+    fn default() -> Self {
+        Self::new(true, None)
+    }
+}
+
+impl JobRetryPolicy {
+    /// Create a new job retry policy.
+    pub fn new(auto_retry: bool, max_retries: Option<u32>) -> Self {
+        Self {
+            auto_retry,
+            max_retries,
+        }
+    }
+
+    /// Retry limit that applies to `job`.
+    pub fn max_retries(&self, job: &Job) -> u32 {
+        self.max_retries.unwrap_or(job.max_retries)
+    }
+
+    /// Whether a failed `job` should be reset for another attempt.
+    pub fn should_retry(&self, job: &Job) -> bool {
+        self.auto_retry && job.retry_count < self.max_retries(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Anime, NewJob};
+    use crate::{Database, JobQueue};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_should_retry() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime = Anime::test_fixture(5114, "Test Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let job_id = queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        queue.increment_retry(job_id)?;
+        let job = queue.get_jobs_by_stage(crate::JobStage::Queued)?.remove(0);
+        assert_eq!((job.retry_count, job.max_retries), (1, 3));
+
+        // Default: the job's own limit
+        assert!(JobRetryPolicy::default().should_retry(&job));
+        assert_eq!(JobRetryPolicy::default().max_retries(&job), 3);
+
+        // Overridden limit
+        assert!(!JobRetryPolicy::new(true, Some(1)).should_retry(&job));
+        assert!(JobRetryPolicy::new(true, Some(5)).should_retry(&job));
+
+        // Retries disabled
+        assert!(!JobRetryPolicy::new(false, None).should_retry(&job));
+        assert!(!JobRetryPolicy::new(false, Some(5)).should_retry(&job));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(0), Duration::from_secs(60));
+        assert_eq!(retry_delay(1), Duration::from_secs(60));
+        assert_eq!(retry_delay(2), Duration::from_secs(120));
+        assert_eq!(retry_delay(3), Duration::from_secs(240));
+        assert_eq!(retry_delay(7), Duration::from_secs(3600));
+        assert_eq!(retry_delay(u32::MAX), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_policy_delays() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay_ms: 100,
+            max_delay_ms: 500,
+            jitter: 0.0,
+        };
+        assert_eq!(policy.max_retries(), 3);
+        let delays: Vec<_> = (0..5).map(|retries| policy.delay(retries).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        for _ in 0..20 {
+            let delay = jittered.delay(1);
+            assert!((Duration::from_millis(100)..=Duration::from_millis(200)).contains(&delay), "{:?}", delay);
+        }
+
+        assert!(policy.validate("mal_scraper.retry").is_ok());
+        let err = RetryPolicy { max_attempts: 0, ..policy }.validate("mal_scraper.retry").unwrap_err();
+        assert_eq!(err.to_string(), "mal_scraper.retry.max_attempts must be greater than 0");
+        assert!(RetryPolicy { base_delay_ms: 600, ..policy }.validate("x").is_err());
+        assert!(RetryPolicy { jitter: 1.5, ..policy }.validate("x").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_async_enforces_max_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            max_delay_ms: 5,
+            jitter: 0.0,
+        };
+
+        // Always failing: exactly max_attempts attempts, then the last error
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = retry_async(&policy, |_| Retry::Backoff, |retries| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async move { anyhow::bail!("attempt {}", retries) }
+        })
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), "attempt 2");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Succeeding on the second attempt
+        let attempts = AtomicU32::new(0);
+        let result = retry_async(&policy, |_| Retry::After(Duration::ZERO), |_| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                anyhow::ensure!(attempt > 0, "rate limited");
+                Ok(attempt)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 1);
+
+        // An error that is not worth retrying stops at once
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = retry_async(&policy, |_| Retry::No, |_| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { anyhow::bail!("not found") }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
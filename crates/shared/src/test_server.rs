@@ -0,0 +1,168 @@
+//! Local HTTP server for the HTTP clients' tests.
+//!
+//! Binds a free port on 127.0.0.1 and answers one connection at a time, so
+//! the Jikan, selector and retry tests don't each hand-roll a `TcpListener`.
+//! Other crates get it with the `test-util` feature.
+
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// A request received by a `TestServer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestRequest {
+    /// Request line, e.g. `GET /anime/1/full HTTP/1.1`
+    pub line: String,
+    /// Path (or, through a proxy, absolute URL) of the request line
+    pub target: String,
+}
+
+/// Response sent by a `TestServer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl TestResponse {
+    /// Response with `status` and an empty body
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// 200 response with `value` as its JSON body
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self::new(200)
+            .with_header("Content-Type", "application/json")
+            .with_body(value.to_string())
+    }
+
+    /// Add a header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the body
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = reqwest::StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("Unknown");
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.len()));
+        [head.into_bytes(), self.body.clone().into_bytes()].concat()
+    }
+}
+
+/// What a `TestServer` does with a request
+enum Reply {
+    /// Answer it
+    Respond(TestResponse),
+    /// Close the connection without answering
+    Close,
+    /// Keep the connection open without answering (a hung server)
+    Hang,
+}
+
+/// HTTP server on a local port, stopped when dropped
+pub struct TestServer {
+    url: String,
+    requests: Arc<Mutex<Vec<TestRequest>>>,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    /// Answer each request with `respond(request)`; None closes the
+    /// connection without an answer
+    pub async fn start<F>(respond: F) -> Result<Self>
+    where
+        F: Fn(&TestRequest) -> Option<TestResponse> + Send + 'static,
+    {
+        Self::with_replies(move |request| respond(request).map_or(Reply::Close, Reply::Respond)).await
+    }
+
+    /// Answer the requests with `responses` in turn; later requests get no
+    /// answer
+    pub async fn sequence(responses: Vec<TestResponse>) -> Result<Self> {
+        let responses = Mutex::new(VecDeque::from(responses));
+        Self::start(move |_| responses.lock().unwrap().pop_front()).await
+    }
+
+    /// Accept connections but never answer
+    pub async fn hanging() -> Result<Self> {
+        Self::with_replies(|_| Reply::Hang).await
+    }
+
+    async fn with_replies<F>(reply: F) -> Result<Self>
+    where
+        F: Fn(&TestRequest) -> Reply + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        let task = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]);
+                let line = head.lines().next().unwrap_or_default().to_string();
+                let target = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let request = TestRequest { line, target };
+                received.lock().unwrap().push(request.clone());
+
+                match reply(&request) {
+                    Reply::Respond(response) => {
+                        let _ = stream.write_all(&response.to_bytes()).await;
+                    }
+                    Reply::Close => {}
+                    Reply::Hang => {
+                        tokio::time::sleep(Duration::from_secs(3600)).await;
+                    }
+                }
+            }
+        });
+
+        Ok(Self { url, requests, task })
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Requests received so far
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Number of requests received so far
+    pub fn request_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,126 @@
+//! Token filtering for frequency counting.
+//!
+//! Particles and auxiliary verbs dominate a raw frequency table. A
+//! `TokenFilter` drops tokens by the part of speech the tokenizer assigned
+//! and by a stopword list, before the counts are written. The token lists
+//! themselves are never filtered.
+
+use crate::tokenizer::Token;
+use anyhow::{Context, Result};
+use shared::TokenFilterConfig;
+use std::collections::HashSet;
+
+/// Decides which tokens are counted in the frequency tables
+#[derive(Debug, Clone, Default)]
+pub struct TokenFilter {
+    exclude_pos: HashSet<String>,
+    stopwords: HashSet<String>,
+    keep_unfiltered: bool,
+}
+
+impl TokenFilter {
+    /// A filter that keeps every token
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Build the filter from `[tokenizer.filter]`, reading `stopwords_file`
+    /// if set. A disabled filter keeps every token.
+    pub fn from_config(config: &TokenFilterConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::none());
+        }
+
+        let mut stopwords: HashSet<String> = config.stopwords.iter().cloned().collect();
+        if let Some(path) = &config.stopwords_file {
+            let content =
+                std::fs::read_to_string(path).with_context(|| format!("Failed to read stopwords file {}", path))?;
+            stopwords.extend(
+                content
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or_default().trim())
+                    .filter(|word| !word.is_empty())
+                    .map(String::from),
+            );
+        }
+
+        Ok(Self {
+            exclude_pos: config.exclude_pos.iter().cloned().collect(),
+            stopwords,
+            keep_unfiltered: config.keep_unfiltered,
+        })
+    }
+
+    /// Drop tokens with one of these parts of speech
+    pub fn with_excluded_pos(mut self, pos: &[&str]) -> Self {
+        self.exclude_pos.extend(pos.iter().map(|p| p.to_string()));
+        self
+    }
+
+    /// Drop tokens whose lemma is one of these words
+    pub fn with_stopwords(mut self, words: &[&str]) -> Self {
+        self.stopwords.extend(words.iter().map(|w| w.to_string()));
+        self
+    }
+
+    /// Also write the unfiltered frequency table
+    pub fn with_keep_unfiltered(mut self, keep: bool) -> Self {
+        self.keep_unfiltered = keep;
+        self
+    }
+
+    /// Whether the filter drops anything at all
+    pub fn is_active(&self) -> bool {
+        !self.exclude_pos.is_empty() || !self.stopwords.is_empty()
+    }
+
+    /// Whether the unfiltered table should be written as well
+    pub fn keep_unfiltered(&self) -> bool {
+        self.keep_unfiltered && self.is_active()
+    }
+
+    /// Whether `token` is counted
+    pub fn keeps(&self, token: &Token) -> bool {
+        !self.exclude_pos.contains(&token.pos) && !self.stopwords.contains(&token.lemma)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_from_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let stopwords_path = temp_dir.path().join("stopwords.txt");
+        fs::write(&stopwords_path, "# fillers\nええと\n\nあの  # also a determiner\n")?;
+
+        let mut config = TokenFilterConfig {
+            stopwords: vec!["する".to_string()],
+            stopwords_file: Some(stopwords_path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(!TokenFilter::from_config(&config)?.is_active());
+
+        config.enabled = true;
+        let filter = TokenFilter::from_config(&config)?;
+        let token = |lemma: &str, pos: &str| Token {
+            surface: lemma.to_string(),
+            lemma: lemma.to_string(),
+            pos: pos.to_string(),
+            reading: None,
+        };
+        assert!(!filter.keeps(&token("は", "助詞")));
+        assert!(!filter.keeps(&token("ええと", "感動詞")));
+        assert!(!filter.keeps(&token("あの", "連体詞")));
+        assert!(!filter.keeps(&token("する", "動詞")));
+        assert!(filter.keeps(&token("錬金術", "名詞")));
+
+        config.stopwords_file = Some(temp_dir.path().join("missing.txt").to_string_lossy().to_string());
+        assert!(TokenFilter::from_config(&config).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+//! Tokenizer library.
+//!
+//! Turns transcripts into per-episode token lists and word frequency tables.
+//! Segmentation is behind the `Tokenizer` trait, so the stage (and its
+//! tests) also runs without a MeCab dictionary.
+
+pub mod filter;
+pub mod recap;
+pub mod tokenizer;
+pub mod worker;
+
+pub use filter::TokenFilter;
+pub use recap::RecapDetector;
+#[cfg(feature = "lindera")]
+pub use tokenizer::MecabTokenizer;
+pub use tokenizer::{from_config, Token, Tokenizer, WhitespaceTokenizer};
+pub use worker::{tokenize_episode, tokenize_pending, EpisodeTokens, TokenizeStats};
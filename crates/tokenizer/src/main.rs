@@ -0,0 +1,115 @@
+//! Tokenizer - turns transcripts into token lists and word frequency tables.
+//!
+//! Picks up every job at `transcribed`, tokenizes its transcript with the
+//! tokenizer selected by `[tokenizer]` in the config, and moves it to
+//! `tokenized`.
+//!
+//! With `--retokenize`, jobs that were already tokenized are first sent back
+//! to `transcribed`, so a changed tokenizer or stopword list is applied to
+//! every transcript still on disk. If the run is interrupted, running the
+//! tokenizer again without the flag finishes the remaining jobs.
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use shared::{Config, Database, JobQueue, RunManifest};
+use std::path::PathBuf;
+use tokenizer::{RecapDetector, TokenFilter};
+use tracing::{info, warn};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to configuration file (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Enable verbose logging
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Stop after this many jobs have been processed
+    #[arg(long)]
+    max_jobs: Option<usize>,
+
+    /// Send tokenized, analyzing and complete jobs back to `transcribed` and tokenize them again
+    #[arg(long)]
+    retokenize: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Load configuration
+    let config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+
+    // Initialize logging
+    let log_level = if args.verbose {
+        tracing::Level::DEBUG
+    } else {
+        tracing::Level::INFO
+    };
+
+    shared::logging::init(shared::LogConfig {
+        log_dir: config.log_dir().to_string_lossy().to_string(),
+        component: "tokenizer".to_string(),
+        default_level: log_level,
+        console: true,
+        file: true,
+        json_format: false,
+        module_levels: config.logging.module_levels.clone(),
+    })?;
+
+    let tokenizer = tokenizer::from_config(&config.tokenizer).context("Failed to create tokenizer")?;
+    let filter = TokenFilter::from_config(&config.tokenizer.filter).context("Failed to create token filter")?;
+    let recap = RecapDetector::from_config(&config.tokenizer.recap);
+    info!(
+        tokenizer = tokenizer.name(),
+        filter = filter.is_active(),
+        recap_detection = recap.is_active(),
+        max_jobs = ?args.max_jobs,
+        "Tokenizer starting"
+    );
+
+    let data_paths = config.data_paths();
+    let db_path = config.database_path();
+    let mut queue = JobQueue::new(
+        Database::open(&db_path).with_context(|| format!("Failed to open database at {}", db_path.display()))?,
+    );
+
+    // Tokenization uses no external tools, but record the config and commit
+    let (mut manifest, manifest_path) = RunManifest::start("tokenizer", &config, &[], &data_paths)?;
+
+    if args.retokenize {
+        let reset = queue.reset_for_retokenize(&data_paths)?;
+        info!("Re-tokenizing {} job(s)", reset.reset);
+        if !reset.missing_transcript.is_empty() {
+            warn!(
+                "Skipped {} job(s) whose transcript is missing: {:?}",
+                reset.missing_transcript.len(),
+                reset.missing_transcript
+            );
+        }
+    }
+
+    let stats = tokenizer::tokenize_pending(
+        &mut queue,
+        &data_paths,
+        tokenizer.as_ref(),
+        &filter,
+        &recap,
+        &config.disk_management.cleanup,
+        args.max_jobs,
+    )?;
+
+    info!("=== Tokenization Complete ===");
+    info!("Tokenized: {}", stats.tokenized);
+    info!("Failed: {}", stats.failed);
+
+    manifest.finish(&manifest_path)?;
+
+    Ok(())
+}
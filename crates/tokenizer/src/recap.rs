@@ -0,0 +1,195 @@
+//! Recap detection.
+//!
+//! Many shows open with a "previously on..." recap that replays dialogue
+//! from the end of the previous episode, which would otherwise be counted
+//! twice in the frequency tables. The opening lines of an episode are
+//! compared with the closing lines of the previous one: each line is split
+//! into character shingles (Japanese has no spaces to split words on), and a
+//! line whose Jaccard similarity with some closing line reaches the threshold
+//! counts as repeated. A run of repeated lines at the start of the episode is
+//! the recap and is dropped before tokenizing.
+
+use shared::RecapConfig;
+use std::collections::HashSet;
+
+/// Finds recaps at the start of an episode transcript
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecapDetector {
+    lines: usize,
+    shingle_size: usize,
+    threshold: f64,
+    min_lines: usize,
+}
+
+impl Default for RecapDetector {
+    fn default() -> Self {
+        Self::from_config(&RecapConfig::default())
+    }
+}
+
+impl RecapDetector {
+    /// A detector that never finds a recap
+    pub fn none() -> Self {
+        Self {
+            lines: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Build the detector from `[tokenizer.recap]`. A disabled config never
+    /// finds a recap.
+    pub fn from_config(config: &RecapConfig) -> Self {
+        Self {
+            lines: if config.enabled { config.lines } else { 0 },
+            shingle_size: config.shingle_size.max(1),
+            threshold: config.threshold,
+            min_lines: config.min_lines.max(1),
+        }
+    }
+
+    /// Whether the detector looks for recaps at all
+    pub fn is_active(&self) -> bool {
+        self.lines > 0
+    }
+
+    /// Number of opening lines of `current` that recap the end of `previous`.
+    ///
+    /// The recap runs from the first line to the last repeated line for which
+    /// at least `min_lines` lines, and at least half of the non-blank lines,
+    /// are repeated; 0 means no recap.
+    pub fn recap_lines(&self, previous: &str, current: &str) -> usize {
+        if !self.is_active() {
+            return 0;
+        }
+
+        let closing: Vec<HashSet<String>> = {
+            let lines: Vec<&str> = previous.lines().filter(|l| !l.trim().is_empty()).collect();
+            lines[lines.len().saturating_sub(self.lines)..]
+                .iter()
+                .map(|line| shingles(line, self.shingle_size))
+                .collect()
+        };
+        if closing.is_empty() {
+            return 0;
+        }
+
+        let mut recap = 0;
+        let (mut spoken, mut repeated) = (0, 0);
+        for (i, line) in current.lines().take(self.lines).enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            spoken += 1;
+
+            let line = shingles(line, self.shingle_size);
+            if closing.iter().any(|c| jaccard(&line, c) >= self.threshold) {
+                repeated += 1;
+                if repeated >= self.min_lines && repeated * 2 >= spoken {
+                    recap = i + 1;
+                }
+            }
+        }
+        recap
+    }
+}
+
+/// Overlapping runs of `size` characters of `text`, ignoring whitespace. Text
+/// shorter than `size` is a single shingle.
+pub fn shingles(text: &str, size: usize) -> HashSet<String> {
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.is_empty() {
+        return HashSet::new();
+    }
+    if chars.len() <= size {
+        return HashSet::from([chars.into_iter().collect()]);
+    }
+    chars.windows(size).map(|w| w.iter().collect()).collect()
+}
+
+/// Jaccard similarity of two shingle sets (0 when both are empty)
+pub fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPISODE_1: &str = "\
+錬金術の基本は等価交換
+何かを得るためには同等の代価が必要だ
+兄さん、どうしてこんなことに
+母さんを取り戻すんだ
+俺の右腕をくれてやる
+だから返せ、たった一人の弟なんだ
+";
+
+    #[test]
+    fn test_jaccard() {
+        let a = shingles("錬金術の基本は等価交換", 3);
+        assert_eq!(jaccard(&a, &a), 1.0);
+        assert_eq!(jaccard(&a, &shingles("今日はいい天気ですね", 3)), 0.0);
+        assert_eq!(jaccard(&HashSet::new(), &HashSet::new()), 0.0);
+
+        // Whisper rarely transcribes a replayed line identically
+        let b = shingles("錬金術の基本は等価交換だ", 3);
+        assert!(jaccard(&a, &b) > 0.6);
+
+        assert_eq!(shingles("はい", 3), HashSet::from(["はい".to_string()]));
+        assert!(shingles("  ", 3).is_empty());
+    }
+
+    #[test]
+    fn test_recap_of_previous_ending() {
+        let detector = RecapDetector::default();
+
+        // The last four lines of episode 1 replayed (slightly differently)
+        // before the new dialogue
+        let episode_2 = "\
+兄さん、どうしてこんなことに
+
+母さんを取り戻すんだ
+俺の右腕をくれてやる。
+だから返せ たった一人の弟なんだ
+あれから四年が経った
+国家錬金術師の試験を受けるぞ
+";
+        assert_eq!(detector.recap_lines(EPISODE_1, episode_2), 5);
+        let stripped: Vec<_> = episode_2.lines().skip(5).collect();
+        assert_eq!(stripped, ["あれから四年が経った", "国家錬金術師の試験を受けるぞ"]);
+    }
+
+    #[test]
+    fn test_no_recap_in_distinct_episode() {
+        let detector = RecapDetector::default();
+
+        let episode_2 = "\
+あれから四年が経った
+国家錬金術師の試験を受けるぞ
+東方司令部はこっちだ
+";
+        assert_eq!(detector.recap_lines(EPISODE_1, episode_2), 0);
+
+        // A single repeated line is a callback, not a recap
+        let callback = "\
+あれから四年が経った
+錬金術の基本は等価交換
+東方司令部はこっちだ
+";
+        assert_eq!(detector.recap_lines(EPISODE_1, callback), 0);
+
+        // Repeated lines that only come after plenty of new dialogue
+        let late = format!(
+            "{}兄さん、どうしてこんなことに\n母さんを取り戻すんだ\n俺の右腕をくれてやる\n",
+            "新しい台詞です\n".repeat(10)
+        );
+        assert_eq!(detector.recap_lines(EPISODE_1, &late), 0);
+
+        assert_eq!(RecapDetector::none().recap_lines(EPISODE_1, EPISODE_1), 0);
+        assert_eq!(detector.recap_lines("", EPISODE_1), 0);
+    }
+}
@@ -0,0 +1,201 @@
+//! Tokenizer implementations.
+//!
+//! `MecabTokenizer` does Japanese morphological analysis with a compiled
+//! MeCab dictionary through lindera and is only built with the `lindera`
+//! feature. `WhitespaceTokenizer` needs nothing installed and is meant for
+//! non-CJK content, or for running the stage in tests.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use shared::{TokenizerBackend, TokenizerConfig};
+
+/// Part of speech used when the tokenizer cannot tell (MeCab's placeholder)
+pub const UNKNOWN_POS: &str = "*";
+
+/// One token of a transcript
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    /// Text as it appears in the transcript
+    pub surface: String,
+    /// Dictionary form, used as the word in frequency tables
+    pub lemma: String,
+    /// Major part of speech (名詞, 動詞, ...) or `UNKNOWN_POS`
+    pub pos: String,
+    /// Katakana reading, when the dictionary has one
+    pub reading: Option<String>,
+}
+
+/// Splits text into tokens
+pub trait Tokenizer {
+    /// Short name recorded in the token files
+    fn name(&self) -> &str;
+
+    /// Tokenize `text`, skipping whitespace
+    fn tokenize(&self, text: &str) -> Vec<Token>;
+}
+
+/// Build the tokenizer selected by `[tokenizer]` in the config
+pub fn from_config(config: &TokenizerConfig) -> Result<Box<dyn Tokenizer>> {
+    match config.backend {
+        TokenizerBackend::Whitespace => Ok(Box::new(WhitespaceTokenizer)),
+        #[cfg(feature = "lindera")]
+        TokenizerBackend::Mecab => {
+            use anyhow::Context;
+            let dictionary = config
+                .dictionary
+                .as_deref()
+                .context("tokenizer.dictionary must be set for the mecab backend")?;
+            Ok(Box::new(MecabTokenizer::new(dictionary)?))
+        }
+        #[cfg(not(feature = "lindera"))]
+        TokenizerBackend::Mecab => anyhow::bail!(
+            "The mecab tokenizer needs a build with `--features lindera` \
+             (or set tokenizer.backend = \"whitespace\")"
+        ),
+    }
+}
+
+/// Splits on whitespace and strips surrounding punctuation.
+///
+/// Lemmas are lowercased so "The" and "the" count as one word. There is no
+/// part-of-speech information, so every token gets `UNKNOWN_POS`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn name(&self) -> &str {
+        "whitespace"
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        text.split_whitespace()
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|word| !word.is_empty())
+            .map(|word| Token {
+                surface: word.to_string(),
+                lemma: word.to_lowercase(),
+                pos: UNKNOWN_POS.to_string(),
+                reading: None,
+            })
+            .collect()
+    }
+}
+
+/// Japanese morphological analysis with a MeCab dictionary in IPADIC layout
+#[cfg(feature = "lindera")]
+pub struct MecabTokenizer {
+    segmenter: lindera::segmenter::Segmenter,
+}
+
+#[cfg(feature = "lindera")]
+impl MecabTokenizer {
+    /// Load the compiled dictionary at `dictionary` (a directory or lindera URI)
+    pub fn new(dictionary: &str) -> Result<Self> {
+        use anyhow::Context;
+        use lindera::mode::Mode;
+        use lindera::segmenter::Segmenter;
+
+        let dictionary = lindera::dictionary::load_dictionary(dictionary)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| format!("Failed to load MeCab dictionary from {}", dictionary))?;
+        Ok(Self {
+            segmenter: Segmenter::new(Mode::Normal, dictionary, None),
+        })
+    }
+}
+
+#[cfg(feature = "lindera")]
+impl Tokenizer for MecabTokenizer {
+    fn name(&self) -> &str {
+        "mecab"
+    }
+
+    /// Lines are segmented one at a time; a line lindera cannot segment is
+    /// logged and skipped.
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        /// Dictionary field, or None for MeCab's `*` placeholder
+        fn field(token: &mut lindera::token::Token, name: &str) -> Option<String> {
+            token.get(name).filter(|v| *v != UNKNOWN_POS).map(String::from)
+        }
+
+        let mut tokens = Vec::new();
+        for line in text.lines() {
+            let segmented = match self.segmenter.segment(std::borrow::Cow::Borrowed(line)) {
+                Ok(segmented) => segmented,
+                Err(e) => {
+                    tracing::warn!(error = %e, line, "Failed to segment line, skipping");
+                    continue;
+                }
+            };
+            for mut token in segmented {
+                let surface = token.surface.to_string();
+                if surface.trim().is_empty() {
+                    continue;
+                }
+                tokens.push(Token {
+                    lemma: field(&mut token, "base_form").unwrap_or_else(|| surface.clone()),
+                    pos: field(&mut token, "major_pos").unwrap_or_else(|| UNKNOWN_POS.to_string()),
+                    reading: field(&mut token, "reading"),
+                    surface,
+                });
+            }
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whitespace_tokenizer() {
+        let tokens = WhitespaceTokenizer.tokenize("The Philosopher's Stone,\n  the  stone... — 42!");
+        let lemmas: Vec<_> = tokens.iter().map(|t| t.lemma.as_str()).collect();
+        assert_eq!(lemmas, ["the", "philosopher's", "stone", "the", "stone", "42"]);
+        assert_eq!(tokens[0].surface, "The");
+        assert!(tokens.iter().all(|t| t.pos == UNKNOWN_POS && t.reading.is_none()));
+
+        assert!(WhitespaceTokenizer.tokenize(" \n\t ... ").is_empty());
+    }
+
+    #[test]
+    fn test_from_config_selects_backend() -> Result<()> {
+        let config = TokenizerConfig {
+            backend: TokenizerBackend::Whitespace,
+            ..Default::default()
+        };
+        assert_eq!(from_config(&config)?.name(), "whitespace");
+
+        // MeCab always needs a dictionary (and, without lindera, a rebuild)
+        let config = TokenizerConfig {
+            backend: TokenizerBackend::Mecab,
+            ..Default::default()
+        };
+        assert!(from_config(&config).is_err());
+
+        Ok(())
+    }
+
+    /// Needs a compiled IPADIC dictionary, e.g.
+    /// `LINDERA_DICTIONARY=/path/to/ipadic cargo test -p tokenizer --features lindera -- --ignored`
+    #[cfg(feature = "lindera")]
+    #[test]
+    #[ignore = "needs a MeCab dictionary in LINDERA_DICTIONARY"]
+    fn test_mecab_tokenizer() -> Result<()> {
+        let dictionary = std::env::var("LINDERA_DICTIONARY")?;
+        let tokenizer = MecabTokenizer::new(&dictionary)?;
+
+        let tokens = tokenizer.tokenize("錬金術は等価交換だ\n走った");
+        let surfaces: Vec<_> = tokens.iter().map(|t| t.surface.as_str()).collect();
+        assert_eq!(surfaces, ["錬金術", "は", "等価", "交換", "だ", "走っ", "た"]);
+
+        assert_eq!(tokens[0].pos, "名詞");
+        assert_eq!(tokens[0].reading.as_deref(), Some("レンキンジュツ"));
+        assert_eq!(tokens[1].pos, "助詞");
+        assert_eq!(tokens[5].lemma, "走る");
+        assert_eq!(tokens[5].pos, "動詞");
+
+        Ok(())
+    }
+}
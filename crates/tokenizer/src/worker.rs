@@ -0,0 +1,607 @@
+//! Tokenization of transcribed episodes.
+//!
+//! Jobs move `transcribed` → `tokenizing` → `tokenized`. Each episode gets
+//! `epNNN_tokens.json` (every token in order) and `epNNN_freq.csv`
+//! (`word,count,pos,reading`, keyed by lemma) in its tokens directory. The
+//! frequency table only counts tokens the `TokenFilter` keeps; with
+//! `keep_unfiltered` the full table is also written to
+//! `epNNN_freq_unfiltered.csv`. A recap of the previous episode found by the
+//! `RecapDetector` is dropped before tokenizing, and the number of dropped
+//! lines is stored in `jobs.recap_lines`.
+//!
+//! With `[disk_management.cleanup] delete_transcript_after_tokenization`,
+//! an episode's transcript is deleted once its token list and frequency
+//! table have been written and read back. While recap detection is on, a
+//! transcript is kept until the next episode has been tokenized, as that
+//! episode's recap check reads it.
+
+use crate::filter::TokenFilter;
+use crate::recap::RecapDetector;
+use crate::tokenizer::{Token, Tokenizer};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use shared::frequency::write_frequency_csv;
+use shared::{CleanupConfig, DataPaths, FileType, Job, JobMetadata, JobQueue, JobStage};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{debug, error, info, warn};
+
+/// Contents of an `epNNN_tokens.json` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeTokens {
+    pub mal_id: u32,
+    pub episode: u32,
+    /// `Tokenizer::name` of the tokenizer that produced the tokens
+    pub tokenizer: String,
+    /// Opening transcript lines left out as a recap of the previous episode
+    #[serde(default)]
+    pub recap_lines: usize,
+    pub tokens: Vec<Token>,
+}
+
+/// Outcome of a `tokenize_pending` run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenizeStats {
+    pub tokenized: usize,
+    pub failed: usize,
+}
+
+/// Tokenize `job`'s transcript and write its token list and frequency
+/// table.
+///
+/// The transcript is read from the path recorded on the job (see
+/// `DataPaths::job_transcript`). The recap check needs the previous
+/// episode's job and its transcript; without them the whole transcript is
+/// tokenized.
+pub fn tokenize_episode(
+    tokenizer: &dyn Tokenizer,
+    filter: &TokenFilter,
+    recap: &RecapDetector,
+    data_paths: &DataPaths,
+    queue: &JobQueue,
+    job: &Job,
+) -> Result<EpisodeTokens> {
+    let (mal_id, episode) = (job.mal_id, job.episode);
+    let transcript_path = data_paths.job_transcript(job);
+    let transcript = std::fs::read_to_string(&transcript_path)
+        .with_context(|| format!("Failed to read transcript {}", transcript_path.display()))?;
+
+    let previous = match episode.checked_sub(1).filter(|_| recap.is_active()) {
+        Some(previous) => queue
+            .get_job(mal_id, previous)?
+            .and_then(|previous| std::fs::read_to_string(data_paths.job_transcript(&previous)).ok()),
+        None => None,
+    };
+    let recap_lines = previous.map_or(0, |previous| recap.recap_lines(&previous, &transcript));
+    let dialogue = if recap_lines > 0 {
+        transcript.lines().skip(recap_lines).collect::<Vec<_>>().join("\n")
+    } else {
+        transcript
+    };
+
+    let tokens = EpisodeTokens {
+        mal_id,
+        episode,
+        tokenizer: tokenizer.name().to_string(),
+        recap_lines,
+        tokens: tokenizer.tokenize(&dialogue),
+    };
+
+    std::fs::create_dir_all(data_paths.tokens_dir(mal_id))?;
+    let tokens_path = data_paths.tokens_json(mal_id, episode);
+    std::fs::write(&tokens_path, serde_json::to_string(&tokens)?)
+        .with_context(|| format!("Failed to write {}", tokens_path.display()))?;
+
+    let counted: Vec<&Token> = tokens.tokens.iter().filter(|t| filter.keeps(t)).collect();
+    write_episode_frequencies(&data_paths.freq_csv(mal_id, episode), &counted)?;
+    if filter.keep_unfiltered() {
+        let all: Vec<&Token> = tokens.tokens.iter().collect();
+        write_episode_frequencies(&data_paths.unfiltered_freq_csv(mal_id, episode), &all)?;
+    }
+
+    Ok(tokens)
+}
+
+/// Tokenize transcribed jobs until none are left (or `max_jobs` have been
+/// processed).
+///
+/// A job whose transcript cannot be tokenized is marked failed and the run
+/// carries on with the next one. Transcripts are deleted afterwards as
+/// `cleanup` says (see `delete_tokenized_transcripts`).
+pub fn tokenize_pending(
+    queue: &mut JobQueue,
+    data_paths: &DataPaths,
+    tokenizer: &dyn Tokenizer,
+    filter: &TokenFilter,
+    recap: &RecapDetector,
+    cleanup: &CleanupConfig,
+    max_jobs: Option<usize>,
+) -> Result<TokenizeStats> {
+    let mut stats = TokenizeStats::default();
+
+    while max_jobs.is_none_or(|max| stats.tokenized + stats.failed < max) {
+        let Some(job) = queue.dequeue(JobStage::Transcribed, JobStage::Tokenizing)? else {
+            break;
+        };
+
+        match tokenize_episode(tokenizer, filter, recap, data_paths, queue, &job) {
+            Ok(tokens) => {
+                let tokens_path = data_paths.tokens_json(job.mal_id, job.episode);
+                let metadata = JobMetadata {
+                    tokens_size_bytes: std::fs::metadata(&tokens_path).map(|m| m.len()).ok(),
+                    token_count: Some(tokens.tokens.len() as u32),
+                    recap_lines: recap.is_active().then_some(tokens.recap_lines as u32),
+                    tokens_path: Some(tokens_path.to_string_lossy().to_string()),
+                    ..Default::default()
+                };
+                queue.update_metadata(job.id, &metadata)?;
+                queue.update_stage(job.id, JobStage::Tokenized)?;
+
+                info!(
+                    job_id = job.id,
+                    mal_id = job.mal_id,
+                    episode = job.episode,
+                    tokens = tokens.tokens.len(),
+                    recap_lines = tokens.recap_lines,
+                    "Tokenized episode"
+                );
+                stats.tokenized += 1;
+
+                if cleanup.delete_transcript_after_tokenization {
+                    delete_tokenized_transcripts(queue, data_paths, recap, job.mal_id, job.episode)?;
+                }
+            }
+            Err(e) => {
+                error!(job_id = job.id, error = %e, "Tokenization failed");
+                queue.update_stage_with_error(job.id, JobStage::Failed, format!("Tokenization failed: {:#}", e))?;
+                stats.failed += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Delete the transcripts that tokenizing `episode` of `mal_id` made
+/// redundant, marking each deletion on its job.
+///
+/// That is the episode's own transcript and, with recap detection on, the
+/// previous episode's one, which was kept for this episode's recap check.
+/// The transcript at the job's recorded path is deleted along with its JSON
+/// sibling, and the job is only marked once that transcript is removed. A
+/// transcript is only deleted once the job's token files are read back
+/// intact, and while the next episode still awaits tokenization with recap
+/// detection on, it is kept for that episode. Failing to delete a file is
+/// logged, not an error: the tokens are already safe.
+pub fn delete_tokenized_transcripts(
+    queue: &mut JobQueue,
+    data_paths: &DataPaths,
+    recap: &RecapDetector,
+    mal_id: u32,
+    episode: u32,
+) -> Result<()> {
+    let previous = episode.checked_sub(1).filter(|_| recap.is_active());
+    for episode in std::iter::once(episode).chain(previous) {
+        let Some(job) = queue.get_job(mal_id, episode)? else {
+            continue;
+        };
+        if job.transcript_deleted || !is_tokenized(job.stage) {
+            continue;
+        }
+        if recap.is_active() && queue.get_job(mal_id, episode + 1)?.is_some_and(|next| !is_tokenized(next.stage)) {
+            debug!(job_id = job.id, "Keeping transcript for the next episode's recap check");
+            continue;
+        }
+        if let Err(e) = verify_tokens(data_paths, mal_id, episode, job.token_count) {
+            warn!(job_id = job.id, error = %e, "Token files could not be verified, keeping transcript");
+            continue;
+        }
+
+        let txt = data_paths.job_transcript(&job);
+        let json = txt.with_extension("json");
+        let freed = remove_transcript_file(job.id, &json).unwrap_or(0);
+        let Some(size) = remove_transcript_file(job.id, &txt) else {
+            continue;
+        };
+        queue.mark_file_deleted(job.id, FileType::Transcript)?;
+        debug!(job_id = job.id, freed_kb = (freed + size) / 1_000, "Deleted transcript after tokenization");
+    }
+    Ok(())
+}
+
+/// Delete one transcript file of job `job_id`, returning its size, or `None`
+/// if nothing was removed
+fn remove_transcript_file(job_id: i64, path: &Path) -> Option<u64> {
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    match std::fs::remove_file(path) {
+        Ok(()) => Some(size),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!(job_id = job_id, path = %path.display(), error = %e, "Failed to delete transcript");
+            None
+        }
+    }
+}
+
+/// Whether a job in `stage` has its token files written
+fn is_tokenized(stage: JobStage) -> bool {
+    matches!(stage, JobStage::Tokenized | JobStage::Analyzing | JobStage::Complete)
+}
+
+/// Check that the token list of `episode` reads back with `token_count`
+/// tokens and that its frequency table exists
+fn verify_tokens(data_paths: &DataPaths, mal_id: u32, episode: u32, token_count: Option<u32>) -> Result<()> {
+    let tokens_path = data_paths.tokens_json(mal_id, episode);
+    let content = std::fs::read_to_string(&tokens_path)
+        .with_context(|| format!("Failed to read {}", tokens_path.display()))?;
+    let tokens: EpisodeTokens =
+        serde_json::from_str(&content).with_context(|| format!("Invalid token list {}", tokens_path.display()))?;
+    if token_count.is_some_and(|count| count as usize != tokens.tokens.len()) {
+        anyhow::bail!(
+            "{} has {} tokens, the job recorded {:?}",
+            tokens_path.display(),
+            tokens.tokens.len(),
+            token_count
+        );
+    }
+    let freq_path = data_paths.freq_csv(mal_id, episode);
+    if !freq_path.is_file() {
+        anyhow::bail!("{} is missing", freq_path.display());
+    }
+    Ok(())
+}
+
+/// Write `word,count,pos,reading` rows keyed by lemma to `path`, most
+/// frequent first.
+///
+/// The part of speech and reading are those of the lemma's first occurrence.
+fn write_episode_frequencies(path: &Path, tokens: &[&Token]) -> Result<()> {
+    let mut counts: HashMap<&str, (u64, &Token)> = HashMap::new();
+    for &token in tokens {
+        counts.entry(&token.lemma).or_insert((0, token)).0 += 1;
+    }
+
+    let rows = counts
+        .into_iter()
+        .map(|(lemma, (count, token))| (lemma, count, [token.pos.as_str(), token.reading.as_deref().unwrap_or_default()]))
+        .collect();
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to write {}", path.display()))?;
+    write_frequency_csv(file, &["word", "count", "pos", "reading"], rows)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WhitespaceTokenizer;
+    use shared::frequency::read_frequency_table;
+    use shared::{Anime, Database, DiskMonitor, NewJob, RunBudget};
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use transcriber::Transcriber;
+
+    #[test]
+    fn test_tokenize_pending() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime_id = queue.get_or_create_anime(&Anime::test_fixture(1, "Cowboy Bebop", 2))?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=2 {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: 1,
+                anime_title: "Cowboy Bebop".to_string(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            queue.update_stage(job_id, JobStage::Transcribed)?;
+            job_ids.push(job_id);
+        }
+
+        // Episode 2 has no transcript on disk
+        fs::create_dir_all(data_paths.transcript_dir(1))?;
+        fs::write(data_paths.transcript_txt(1, "Cowboy Bebop", 1), "See you, space cowboy\nSee you\n")?;
+
+        let stats = tokenize_pending(
+            &mut queue,
+            &data_paths,
+            &WhitespaceTokenizer,
+            &TokenFilter::none(),
+            &RecapDetector::none(),
+            &CleanupConfig::default(),
+            None,
+        )?;
+        assert_eq!(stats, TokenizeStats { tokenized: 1, failed: 1 });
+
+        assert_eq!(
+            fs::read_to_string(data_paths.freq_csv(1, 1))?,
+            "word,count,pos,reading\nsee,2,*,\nyou,2,*,\ncowboy,1,*,\nspace,1,*,\n"
+        );
+        let tokens: EpisodeTokens = serde_json::from_str(&fs::read_to_string(data_paths.tokens_json(1, 1))?)?;
+        assert_eq!(tokens.tokenizer, "whitespace");
+        assert_eq!(tokens.tokens.len(), 6);
+
+        let tokenized = queue.get_jobs_by_stage(JobStage::Tokenized)?;
+        assert_eq!(tokenized.len(), 1);
+        assert_eq!(tokenized[0].id, job_ids[0]);
+        assert_eq!(tokenized[0].token_count, Some(6));
+        assert_eq!(queue.get_jobs_by_stage(JobStage::Failed)?[0].id, job_ids[1]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tokenizes_transcriber_output() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Fullmetal Alchemist: Brotherhood", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let job_id = queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        let video_path = data_paths.video_file(anime.mal_id, &anime.title, 1, "mkv");
+        fs::create_dir_all(video_path.parent().unwrap())?;
+        fs::write(&video_path, b"")?;
+        queue.update_job_with_video(job_id, video_path, 0)?;
+        queue.update_stage(job_id, JobStage::Downloaded)?;
+
+        // A dry run writes the transcript where a real one would go
+        let disk_monitor = DiskMonitor::new(temp_dir.path(), temp_dir.path(), 10, 9, 8, Duration::from_secs(1))?;
+        let queue = Arc::new(Mutex::new(queue));
+        Transcriber::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths.clone(),
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            RunBudget::unlimited(),
+        )
+        .run()
+        .await?;
+
+        let mut queue = queue.lock().unwrap();
+        let stats = tokenize_pending(
+            &mut queue,
+            &data_paths,
+            &WhitespaceTokenizer,
+            &TokenFilter::none(),
+            &RecapDetector::none(),
+            &CleanupConfig::default(),
+            None,
+        )?;
+        assert_eq!(stats, TokenizeStats { tokenized: 1, failed: 0 });
+
+        let job = queue.get_job(anime.mal_id, 1)?.unwrap();
+        assert_eq!(job.stage, JobStage::Tokenized);
+        assert_eq!(job.token_count, Some(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recap_is_left_out() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime_id = queue.get_or_create_anime(&Anime::test_fixture(5114, "Fullmetal Alchemist", 2))?;
+        let mut job_ids = Vec::new();
+        for episode in 1..=2 {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: 5114,
+                anime_title: "Fullmetal Alchemist".to_string(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            queue.update_stage(job_id, JobStage::Transcribed)?;
+            job_ids.push(job_id);
+        }
+
+        // Episode 2 opens by replaying the end of episode 1, whose transcript
+        // was recorded somewhere other than the default path
+        fs::create_dir_all(data_paths.transcript_dir(5114))?;
+        let imported = data_paths.transcript_dir(5114).join("imported_ep001.txt");
+        fs::write(
+            &imported,
+            "錬金術の基本は等価交換\n母さんを取り戻すんだ\n俺の右腕をくれてやる\nたった一人の弟なんだ\n",
+        )?;
+        queue.update_metadata(
+            job_ids[0],
+            &JobMetadata {
+                transcript_path: Some(imported.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+        )?;
+        fs::write(
+            data_paths.transcript_txt(5114, "Fullmetal Alchemist", 2),
+            "母さんを取り戻すんだ\n俺の右腕をくれてやる\nたった一人の弟なんだ\nあれから四年が経った\n",
+        )?;
+
+        let stats = tokenize_pending(
+            &mut queue,
+            &data_paths,
+            &WhitespaceTokenizer,
+            &TokenFilter::none(),
+            &RecapDetector::default(),
+            &CleanupConfig::default(),
+            None,
+        )?;
+        assert_eq!(stats.tokenized, 2);
+
+        let counts = read_frequency_table(&data_paths.freq_csv(5114, 2))?;
+        assert_eq!(counts.keys().collect::<Vec<_>>(), ["あれから四年が経った"]);
+
+        let mut jobs = queue.get_jobs_by_stage(JobStage::Tokenized)?;
+        jobs.sort_by_key(|job| job.episode);
+        assert_eq!(jobs[0].recap_lines, Some(0));
+        assert_eq!(jobs[1].recap_lines, Some(3));
+        assert_eq!(jobs[1].token_count, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transcripts_deleted_after_tokenization() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let cleanup = CleanupConfig {
+            delete_transcript_after_tokenization: true,
+            ..CleanupConfig::default()
+        };
+
+        // Episodes 1 and 2 are transcribed, episode 3 is still downloading
+        let anime_id = queue.get_or_create_anime(&Anime::test_fixture(5114, "Fullmetal Alchemist", 2))?;
+        fs::create_dir_all(data_paths.transcript_dir(5114))?;
+        for episode in 1..=3 {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: 5114,
+                anime_title: "Fullmetal Alchemist".to_string(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            if episode < 3 {
+                queue.update_stage(job_id, JobStage::Transcribed)?;
+                fs::write(data_paths.transcript_txt(5114, "Fullmetal Alchemist", episode), format!("錬金術 第{}話\n", episode))?;
+                fs::write(data_paths.transcript_json(5114, "Fullmetal Alchemist", episode), "{}")?;
+            }
+        }
+        let deleted = |queue: &JobQueue, episode| -> Result<bool> {
+            Ok(queue.get_job(5114, episode)?.unwrap().transcript_deleted)
+        };
+
+        // Episode 1's transcript is kept for episode 2's recap check
+        let recap = RecapDetector::default();
+        tokenize_pending(&mut queue, &data_paths, &WhitespaceTokenizer, &TokenFilter::none(), &recap, &cleanup, Some(1))?;
+        assert!(data_paths.transcript_txt(5114, "Fullmetal Alchemist", 1).exists());
+        assert!(!deleted(&queue, 1)?);
+
+        // Once episode 2 is tokenized, episode 1's transcript goes; episode
+        // 2's waits for episode 3
+        tokenize_pending(&mut queue, &data_paths, &WhitespaceTokenizer, &TokenFilter::none(), &recap, &cleanup, None)?;
+        assert!(!data_paths.transcript_txt(5114, "Fullmetal Alchemist", 1).exists());
+        assert!(!data_paths.transcript_json(5114, "Fullmetal Alchemist", 1).exists());
+        assert!(deleted(&queue, 1)?);
+        assert!(data_paths.transcript_txt(5114, "Fullmetal Alchemist", 2).exists());
+        assert!(!deleted(&queue, 2)?);
+        assert!(data_paths.tokens_json(5114, 1).exists());
+
+        // Without recap detection, a transcript whose token list does not
+        // read back is kept
+        fs::write(data_paths.tokens_json(5114, 2), "{")?;
+        delete_tokenized_transcripts(&mut queue, &data_paths, &RecapDetector::none(), 5114, 2)?;
+        assert!(data_paths.transcript_txt(5114, "Fullmetal Alchemist", 2).exists());
+        assert!(!deleted(&queue, 2)?);
+
+        // The transcript is deleted from the path recorded on the job, and
+        // the job is only marked once it is actually removed
+        let recorded = data_paths.transcript_dir(5114).join("recorded_ep002.txt");
+        fs::rename(data_paths.transcript_txt(5114, "Fullmetal Alchemist", 2), &recorded)?;
+        fs::write(recorded.with_extension("json"), "{}")?;
+        let job_id = queue.get_job(5114, 2)?.unwrap().id;
+        queue.update_metadata(
+            job_id,
+            &JobMetadata {
+                transcript_path: Some(recorded.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+        )?;
+        let job = queue.get_job(5114, 2)?.unwrap();
+        tokenize_episode(&WhitespaceTokenizer, &TokenFilter::none(), &RecapDetector::none(), &data_paths, &queue, &job)?;
+
+        let moved = temp_dir.path().join("moved.txt");
+        fs::rename(&recorded, &moved)?;
+        delete_tokenized_transcripts(&mut queue, &data_paths, &RecapDetector::none(), 5114, 2)?;
+        assert!(!deleted(&queue, 2)?);
+
+        fs::rename(&moved, &recorded)?;
+        delete_tokenized_transcripts(&mut queue, &data_paths, &RecapDetector::none(), 5114, 2)?;
+        assert!(!recorded.exists());
+        assert!(!recorded.with_extension("json").exists());
+        assert!(deleted(&queue, 2)?);
+
+        Ok(())
+    }
+
+    /// Tags は/が/を as particles and everything else as nouns
+    struct ParticleTokenizer;
+
+    impl Tokenizer for ParticleTokenizer {
+        fn name(&self) -> &str {
+            "particles"
+        }
+
+        fn tokenize(&self, text: &str) -> Vec<Token> {
+            text.split_whitespace()
+                .map(|word| Token {
+                    surface: word.to_string(),
+                    lemma: word.to_string(),
+                    pos: if ["は", "が", "を"].contains(&word) { "助詞" } else { "名詞" }.to_string(),
+                    reading: None,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_pos_filter_drops_particles() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+        let anime_id = queue.get_or_create_anime(&Anime::test_fixture(5114, "Fullmetal Alchemist", 1))?;
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: 5114,
+            anime_title: "Fullmetal Alchemist".to_string(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        let job = queue.get_job(5114, 1)?.unwrap();
+        fs::create_dir_all(data_paths.transcript_dir(5114))?;
+        fs::write(data_paths.job_transcript(&job), "錬金術 は 等価交換 が 基本\n錬金術 を 使う\n")?;
+
+        // Unfiltered: particles are counted like any other word
+        tokenize_episode(&ParticleTokenizer, &TokenFilter::none(), &RecapDetector::none(), &data_paths, &queue, &job)?;
+        let counts = read_frequency_table(&data_paths.freq_csv(5114, 1))?;
+        assert_eq!(counts.get("は"), Some(&1));
+        assert_eq!(counts.len(), 7);
+        assert!(!data_paths.unfiltered_freq_csv(5114, 1).exists());
+
+        let filter = TokenFilter::none()
+            .with_excluded_pos(&["助詞"])
+            .with_stopwords(&["使う"])
+            .with_keep_unfiltered(true);
+        let tokens = tokenize_episode(&ParticleTokenizer, &filter, &RecapDetector::none(), &data_paths, &queue, &job)?;
+        assert_eq!(tokens.tokens.len(), 8);
+        let counts = read_frequency_table(&data_paths.freq_csv(5114, 1))?;
+        assert_eq!(counts.keys().collect::<Vec<_>>(), ["基本", "等価交換", "錬金術"]);
+        assert_eq!(counts["錬金術"], 2);
+        assert_eq!(read_frequency_table(&data_paths.unfiltered_freq_csv(5114, 1))?.len(), 7);
+
+        Ok(())
+    }
+}
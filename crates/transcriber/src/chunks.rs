@@ -0,0 +1,340 @@
+//! Resumable transcription of long episodes in fixed-length chunks.
+//!
+//! With `[transcriber] chunk_seconds` set, the audio of an episode is cut
+//! with ffmpeg into chunks that whisper transcribes one at a time. Each chunk
+//! starts `chunk_seconds` after the previous one and runs `OVERLAP_SECONDS`
+//! into the next, so a word spoken across a cut is heard whole by one of
+//! them. Each chunk's JSON transcript is written next to it, so a run that
+//! fails or crashes part-way picks up at the first chunk without a transcript
+//! instead of starting over. Once every chunk is done, their segments are
+//! joined into the episode's transcript and the chunk directory is removed.
+//!
+//! The directory is named after the model and chunk length, so an escalated
+//! model or a changed `chunk_seconds` never reuses chunks it did not write.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use shared::TranscriptFormat;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// Seconds each chunk runs into the next one
+pub const OVERLAP_SECONDS: u64 = 5;
+
+/// Written once ffmpeg has cut every chunk, listing each chunk's file and
+/// start in the episode (`chunk_000.wav,0.000`); without it the chunks are
+/// cut again, as the last one of an interrupted run may be cut short
+const CHUNK_LIST: &str = "chunks.csv";
+
+/// File name prefix of the chunk audio files (`chunk_000.wav`, ...)
+const CHUNK_PREFIX: &str = "chunk_";
+
+/// A chunk of an episode's audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Audio file of the chunk, next to which its transcript is written
+    pub audio: PathBuf,
+
+    /// Seconds into the episode at which the chunk starts
+    pub start: f64,
+}
+
+/// Directory holding the chunks of `audio_path` transcribed with `model`
+pub fn chunk_dir(audio_path: &Path, model: &str, chunk_seconds: u64) -> PathBuf {
+    let stem = audio_path.file_stem().unwrap_or_default().to_string_lossy();
+    audio_path.with_file_name(format!("{}.chunks-{}-{}s", stem, model, chunk_seconds))
+}
+
+/// Remove every chunk directory of `audio_path` (any model and chunk length),
+/// e.g. those left by a model the job escalated away from
+pub fn remove_chunk_dirs(audio_path: &Path) -> Result<()> {
+    let Some(parent) = audio_path.parent().filter(|p| p.is_dir()) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.chunks-", audio_path.file_stem().unwrap_or_default().to_string_lossy());
+    for entry in fs::read_dir(parent)? {
+        let path = entry?.path();
+        if path.is_dir() && path.file_name().is_some_and(|name| name.to_string_lossy().starts_with(&prefix)) {
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Failed to remove chunk directory: {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Chunks of `dir` covering `total` of audio: one every `chunk_seconds`
+pub fn plan_chunks(dir: &Path, total: Duration, chunk_seconds: u64, extension: &str) -> Vec<Chunk> {
+    let chunk_seconds = chunk_seconds.max(1);
+    (0..)
+        .map(|index: u64| index * chunk_seconds)
+        .take_while(|&start| start == 0 || (start as f64) < total.as_secs_f64())
+        .enumerate()
+        .map(|(index, start)| Chunk {
+            audio: dir.join(format!("{}{:03}.{}", CHUNK_PREFIX, index, extension)),
+            start: start as f64,
+        })
+        .collect()
+}
+
+/// FFmpeg arguments cutting `chunk` (`chunk_seconds` plus the overlap long)
+/// out of `audio_path`, e.g.
+/// `ffmpeg -ss 300 -i audio.wav -t 305 -acodec pcm_s16le -nostats -y dir/chunk_001.wav`
+///
+/// The audio is re-encoded rather than copied, so the cut is made at the
+/// exact sample rather than at the nearest packet and the chunk starts at
+/// `chunk.start`.
+pub fn cut_args(audio_path: &Path, chunk: &Chunk, chunk_seconds: u64, codec: &str) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["-ss".into(), chunk.start.to_string().into()];
+    args.extend(["-i".into(), audio_path.into()]);
+    args.extend(["-t".into(), (chunk_seconds + OVERLAP_SECONDS).to_string().into()]);
+    args.extend(["-acodec".into(), codec.into()]);
+    args.extend(["-nostats".into(), "-y".into()]);
+    args.push(chunk.audio.clone().into());
+    args
+}
+
+/// Record that every one of `chunks` in `dir` has been cut
+pub fn write_chunk_list(dir: &Path, chunks: &[Chunk]) -> Result<()> {
+    let mut list = String::new();
+    for chunk in chunks {
+        let name = chunk.audio.file_name().unwrap_or_default().to_string_lossy();
+        list.push_str(&format!("{},{:.3}\n", name, chunk.start));
+    }
+    fs::write(dir.join(CHUNK_LIST), list)
+        .with_context(|| format!("Failed to write chunk list in {}", dir.display()))
+}
+
+/// Chunks cut into `dir`, in playback order (None = not cut completely)
+pub fn read_chunk_list(dir: &Path) -> Result<Option<Vec<Chunk>>> {
+    let path = dir.join(CHUNK_LIST);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let list = fs::read_to_string(&path).with_context(|| format!("Failed to read chunk list: {}", path.display()))?;
+    let chunks = list
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, start) = line
+                .split_once(',')
+                .with_context(|| format!("Invalid line in {}: {:?}", path.display(), line))?;
+            let start = start
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid chunk start in {}: {:?}", path.display(), line))?;
+            Ok(Chunk { audio: dir.join(name), start })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(chunks))
+}
+
+/// Chunks that still lack a JSON transcript, with their position in `chunks`
+pub fn pending_chunks(chunks: &[Chunk]) -> Vec<(usize, &Chunk)> {
+    chunks
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| !chunk.audio.with_extension("json").exists())
+        .collect()
+}
+
+/// Join the JSON transcripts of `chunks` into the episode transcript at
+/// `transcript_path` (the txt), and its other `formats` next to it.
+///
+/// Segment times are shifted by each chunk's start, so they are relative to
+/// the start of the episode again. Where two chunks overlap, segments
+/// starting before the middle of the overlap are taken from the earlier
+/// chunk and the rest from the later one, so nothing is transcribed twice.
+/// The txt has one line per segment, as whisper writes it.
+pub fn join_chunks(chunks: &[Chunk], transcript_path: &Path, formats: &[TranscriptFormat]) -> Result<()> {
+    let joined = join_json(chunks)?;
+    for format in formats {
+        let output = transcript_path.with_extension(format.extension());
+        let content = match format {
+            TranscriptFormat::Txt => joined["segments"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|segment| segment["text"].as_str())
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            TranscriptFormat::Json => serde_json::to_string(&joined)?,
+        };
+        fs::write(&output, content).with_context(|| format!("Failed to write transcript: {}", output.display()))?;
+    }
+    Ok(())
+}
+
+fn join_json(chunks: &[Chunk]) -> Result<Value> {
+    let mut text = String::new();
+    let mut segments = Vec::new();
+    let mut language = Value::Null;
+    let overlap_middle = OVERLAP_SECONDS as f64 / 2.0;
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let path = chunk.audio.with_extension("json");
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("Failed to read chunk transcript: {}", path.display()))?;
+        let mut result: Value =
+            serde_json::from_str(&content).with_context(|| format!("Invalid chunk transcript: {}", path.display()))?;
+
+        if language.is_null() {
+            language = result["language"].take();
+        }
+
+        // Episode times this chunk's segments may start in
+        let from = if index == 0 { f64::NEG_INFINITY } else { chunk.start + overlap_middle };
+        let until = chunks.get(index + 1).map_or(f64::INFINITY, |next| next.start + overlap_middle);
+
+        let Value::Array(chunk_segments) = result["segments"].take() else {
+            warn!(path = %path.display(), "Chunk transcript has no segments");
+            continue;
+        };
+        for mut segment in chunk_segments {
+            let Some(start) = segment["start"].as_f64().map(|start| start + chunk.start) else {
+                continue;
+            };
+            if start < from || start >= until {
+                continue;
+            }
+            for key in ["start", "end"] {
+                if let Some(time) = segment[key].as_f64() {
+                    segment[key] = (time + chunk.start).into();
+                }
+            }
+            if let Some(segment_text) = segment["text"].as_str() {
+                text.push_str(segment_text);
+            }
+            segment["id"] = segments.len().into();
+            segments.push(segment);
+        }
+    }
+
+    Ok(serde_json::json!({
+        "text": text,
+        "segments": segments,
+        "language": language,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Chunk JSON transcript with segments of (start, end, text)
+    fn write_transcript(chunk: &Chunk, segments: &[(f64, f64, &str)]) -> Result<()> {
+        let segments: Vec<Value> = segments
+            .iter()
+            .enumerate()
+            .map(|(id, (start, end, text))| serde_json::json!({"id": id, "start": start, "end": end, "text": text}))
+            .collect();
+        let text: String = segments.iter().filter_map(|s| s["text"].as_str()).collect();
+        let json = serde_json::json!({"text": text, "segments": segments, "language": "ja"});
+        fs::write(chunk.audio.with_extension("json"), json.to_string())?;
+        Ok(())
+    }
+
+    /// A chunk directory of 700s of audio where chunks 0 and 2 were
+    /// transcribed before a crash
+    fn partial_chunks(dir: &Path) -> Result<Vec<Chunk>> {
+        let chunks = plan_chunks(dir, Duration::from_secs(700), 300, "wav");
+        for chunk in &chunks {
+            fs::write(&chunk.audio, b"RIFF")?;
+        }
+        write_chunk_list(dir, &chunks)?;
+        write_transcript(&chunks[0], &[(1.5, 4.0, "台詞0"), (298.0, 302.0, "境目0")])?;
+        write_transcript(&chunks[2], &[(4.0, 6.0, "台詞2")])?;
+        Ok(chunks)
+    }
+
+    #[test]
+    fn test_pending_chunks_skip_transcribed_ones() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        assert_eq!(read_chunk_list(temp_dir.path())?, None);
+        let chunks = partial_chunks(temp_dir.path())?;
+
+        // The list reads back as written
+        assert_eq!(read_chunk_list(temp_dir.path())?.as_deref(), Some(&chunks[..]));
+        let starts: Vec<_> = chunks.iter().map(|c| c.start).collect();
+        assert_eq!(starts, vec![0.0, 300.0, 600.0]);
+
+        let pending = pending_chunks(&chunks);
+        assert_eq!(pending, vec![(1, &chunks[1])]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_chunks() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut chunks = partial_chunks(temp_dir.path())?;
+        // Offsets come from the recorded starts, not the chunk length
+        chunks[2].start = 601.0;
+        // Lines in an overlap are heard by both chunks; each is kept from
+        // the chunk on its side of the middle of the overlap
+        write_transcript(&chunks[1], &[(0.0, 2.0, "境目0"), (2.5, 4.0, "台詞1"), (304.0, 306.0, "台詞2")])?;
+
+        let transcript_path = temp_dir.path().join("Test_Anime_ep001.txt");
+        join_chunks(&chunks, &transcript_path, &[TranscriptFormat::Txt, TranscriptFormat::Json])?;
+
+        assert_eq!(fs::read_to_string(&transcript_path)?, "台詞0\n境目0\n台詞1\n台詞2");
+        let json: Value = serde_json::from_str(&fs::read_to_string(transcript_path.with_extension("json"))?)?;
+        assert_eq!(json["text"], "台詞0境目0台詞1台詞2");
+        assert_eq!(json["language"], "ja");
+        let starts: Vec<_> = json["segments"].as_array().unwrap().iter().map(|s| s["start"].as_f64().unwrap()).collect();
+        assert_eq!(starts, vec![1.5, 298.0, 302.5, 605.0]);
+        assert_eq!(json["segments"][3]["id"], 3);
+        assert_eq!(json["segments"][3]["end"], 607.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_chunk_dirs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let audio = temp_dir.path().join("Test_Anime_ep001.wav");
+        for dir in [chunk_dir(&audio, "base", 300), chunk_dir(&audio, "medium", 600)] {
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("chunk_000.wav"), b"RIFF")?;
+        }
+        let other = chunk_dir(&temp_dir.path().join("Test_Anime_ep002.wav"), "base", 300);
+        fs::create_dir_all(&other)?;
+
+        remove_chunk_dirs(&audio)?;
+        let left: Vec<_> = fs::read_dir(temp_dir.path())?.map(|e| e.unwrap().path()).collect();
+        assert_eq!(left, vec![other]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_and_cut_chunks() {
+        let audio = Path::new("/data/audio/5114/Test_Anime_ep001.wav");
+        let dir = chunk_dir(audio, "base", 300);
+        assert_eq!(dir, Path::new("/data/audio/5114/Test_Anime_ep001.chunks-base-300s"));
+        assert_ne!(chunk_dir(audio, "medium", 300), dir);
+
+        // The last chunk starts before the end of the audio
+        let starts = |secs| {
+            plan_chunks(&dir, Duration::from_secs_f64(secs), 300, "wav").iter().map(|c| c.start).collect::<Vec<_>>()
+        };
+        assert_eq!(starts(1420.5), vec![0.0, 300.0, 600.0, 900.0, 1200.0]);
+        assert_eq!(starts(600.0), vec![0.0, 300.0]);
+        assert_eq!(starts(0.0), vec![0.0]);
+
+        let chunks = plan_chunks(&dir, Duration::from_secs(1420), 300, "wav");
+        let args: Vec<String> = cut_args(audio, &chunks[1], 300, "pcm_s16le")
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            args.join(" "),
+            "-ss 300 -i /data/audio/5114/Test_Anime_ep001.wav -t 305 -acodec pcm_s16le -nostats -y \
+             /data/audio/5114/Test_Anime_ep001.chunks-base-300s/chunk_001.wav"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! Transcriber library.
+//!
+//! The `transcriber` binary runs these workers on their own; the pipeline
+//! coordinator runs them in the same process as the downloader.
+
+pub mod chunks;
+pub mod progress;
+pub mod quality;
+pub mod transcriber;
+
+pub use transcriber::{Transcriber, VideoDeleted};
+
+use shared::preflight::ExternalTool;
+
+/// External tools the transcriber shells out to
+pub const EXTERNAL_TOOLS: &[ExternalTool] = &[
+    ExternalTool { name: "ffmpeg", version_args: &["-version"], python_package: None },
+    // openai-whisper has no --version flag; its version is the package's
+    ExternalTool { name: "whisper", version_args: &["--help"], python_package: Some("openai-whisper") },
+];
@@ -5,27 +5,39 @@
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use shared::{Config, Database, DataPaths, DiskMonitor, JobQueue};
-use std::path::PathBuf;
+use shared::preflight;
+use shared::progress::ProgressBar;
+use shared::{
+    parse_year_range, AnimeFilter, AnimeFilterConfig, Config, Database, DequeueOrder, DiskMonitor, JobQueue, JobRetryPolicy, JobStage, RunBudget,
+    RunManifest, DEFAULT_CONFIG_PATH,
+};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::{error, info};
 
-mod transcriber;
-
-use transcriber::Transcriber;
+use transcriber::quality::MODEL_LADDER;
+use transcriber::{Transcriber, EXTERNAL_TOOLS};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "config.toml")]
-    config: PathBuf,
+    /// Path to configuration file (default: config.toml, or built-in defaults if it is missing)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Corpus profile: keep the data, database, logs and cache under <root_dir>/profiles/NAME (overrides `data.profile`)
+    #[arg(long)]
+    profile: Option<String>,
 
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
 
+    /// Show a progress bar over the downloaded jobs (console logs are limited to warnings; ignored when stderr is not a terminal or with --verbose)
+    #[arg(long)]
+    progress: bool,
+
     /// Number of concurrent transcription workers
     #[arg(short = 'w', long)]
     workers: Option<usize>,
@@ -34,18 +46,121 @@ struct Args {
     #[arg(short = 'm', long, default_value = "base")]
     model: String,
 
+    /// Re-transcribe low-quality transcripts with larger models, up to this one
+    #[arg(long, value_name = "MODEL")]
+    escalate_to: Option<String>,
+
+    /// Prompt passed to Whisper, overriding transcriber.initial_prompt
+    /// ({title} is replaced with the anime title)
+    #[arg(long, value_name = "TEXT")]
+    initial_prompt: Option<String>,
+
     /// Dry run (don't actually transcribe, for testing)
     #[arg(long)]
     dry_run: bool,
+
+    /// Mark failed transcriptions as failed straight away instead of retrying
+    #[arg(long)]
+    no_auto_retry: bool,
+
+    /// Retry failed transcriptions at most N times (overrides [pipeline.retry] and each job's max_retries)
+    #[arg(long, value_name = "N", conflicts_with = "no_auto_retry")]
+    max_retries: Option<u32>,
+
+    /// Stop after this many jobs have been processed (across all workers)
+    #[arg(long)]
+    max_jobs: Option<usize>,
+
+    /// Take one episode of each anime in turn instead of the highest-priority anime first
+    #[arg(long)]
+    breadth_first: bool,
+
+    /// Stop picking up new jobs after this many seconds
+    #[arg(long)]
+    max_duration: Option<u64>,
+
+    /// Write final queue statistics as JSON to this file
+    #[arg(long)]
+    stats_json: Option<PathBuf>,
+
+    /// Only transcribe anime with all of these genres (repeatable; the --filter-* flags replace [pipeline.anime_filter])
+    #[arg(long, value_name = "GENRE")]
+    filter_genre: Vec<String>,
+
+    /// Only transcribe anime with all of these themes (repeatable)
+    #[arg(long, value_name = "THEME")]
+    filter_theme: Vec<String>,
+
+    /// Only transcribe anime with one of these demographics (repeatable)
+    #[arg(long, value_name = "DEMOGRAPHIC")]
+    filter_demographic: Vec<String>,
+
+    /// Only transcribe anime made by one of these studios (repeatable)
+    #[arg(long, value_name = "STUDIO")]
+    filter_studio: Vec<String>,
+
+    /// Only transcribe anime of one of these MAL types, e.g. TV (repeatable)
+    #[arg(long, value_name = "TYPE")]
+    filter_type: Vec<String>,
+
+    /// Only transcribe anime that started airing in these years: 2020, 2018..2022, 2018.. or ..2010
+    #[arg(long, value_name = "YEARS", value_parser = parse_year_range)]
+    filter_year: Option<(Option<i32>, Option<i32>)>,
+
+    /// Check config, database, directories and external tools, then exit without transcribing
+    #[arg(long)]
+    validate: bool,
+}
+
+impl Args {
+    /// The anime filter set by the --filter-* flags, or `configured` without any
+    fn anime_filter(&self, configured: &AnimeFilter) -> AnimeFilter {
+        let (min_year, max_year) = self.filter_year.unwrap_or_default();
+        let filter = AnimeFilter {
+            genres: self.filter_genre.clone(),
+            themes: self.filter_theme.clone(),
+            demographics: self.filter_demographic.clone(),
+            studios: self.filter_studio.clone(),
+            kind: AnimeFilterConfig {
+                types: self.filter_type.clone(),
+                ..Default::default()
+            },
+            min_year,
+            max_year,
+        };
+        if filter.is_active() {
+            filter
+        } else {
+            configured.clone()
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    if args.validate {
+        return validate(args.config.as_deref(), args.profile.as_deref());
+    }
+
+    if let Some(ceiling) = &args.escalate_to {
+        if !MODEL_LADDER.contains(&ceiling.as_str()) {
+            anyhow::bail!(
+                "--escalate-to must be one of: {} (got {})",
+                MODEL_LADDER.join(", "),
+                ceiling
+            );
+        }
+    }
+
     // Load configuration
-    let config = Config::from_file(&args.config)
-        .with_context(|| format!("Failed to load config from {}", args.config.display()))?;
+    let config = Config::load(args.config.as_deref(), args.profile.as_deref()).context("Failed to load config")?;
+
+    // Progress bars have to be on before logging is set up around them
+    if shared::progress::should_enable(args.progress, args.verbose) {
+        shared::progress::enable();
+    }
 
     // Initialize logging
     let log_level = if args.verbose {
@@ -61,19 +176,34 @@ async fn main() -> Result<()> {
         console: true,
         file: true,
         json_format: false,
+        module_levels: config.logging.module_levels.clone(),
     })?;
 
     info!("Transcriber starting");
-    info!(config_file = %args.config.display(), "Loaded configuration");
+    info!(
+        config_file = %args.config.as_deref().unwrap_or(Path::new(DEFAULT_CONFIG_PATH)).display(),
+        "Loaded configuration"
+    );
+    let dequeue_order = if args.breadth_first {
+        DequeueOrder::BreadthFirst
+    } else {
+        config.pipeline.dequeue_order
+    };
     info!(
         workers = args.workers.unwrap_or(config.disk_management.max_concurrent_transcriptions),
         model = %args.model,
+        escalate_to = ?args.escalate_to,
         dry_run = args.dry_run,
+        no_auto_retry = args.no_auto_retry,
+        max_retries = ?args.max_retries,
+        max_jobs = ?args.max_jobs,
+        max_duration_secs = ?args.max_duration,
+        dequeue_order = ?dequeue_order,
         "Runtime configuration"
     );
 
     // Initialize data paths (with separate storage directory for transcripts)
-    let data_paths = DataPaths::new_with_storage(config.data_dir(), config.storage_dir());
+    let data_paths = config.data_paths();
     data_paths
         .create_dirs()
         .context("Failed to create data directories")?;
@@ -82,7 +212,15 @@ async fn main() -> Result<()> {
     let db_path = config.database_path();
     info!(db_path = %db_path.display(), "Opening database");
     let database = Database::open(&db_path).context("Failed to open database")?;
-    let job_queue = JobQueue::new(database);
+    let anime_filter = args.anime_filter(&config.pipeline.anime_filter);
+    let job_queue = JobQueue::new(database)
+        .with_dequeue_order(dequeue_order)
+        .with_retry_policy(config.pipeline.retry);
+    if anime_filter.is_active() {
+        let matching = job_queue.find_anime(&anime_filter)?.len();
+        info!(filter = ?anime_filter, matching_anime = matching, "Only transcribing anime matching the filter");
+    }
+    let job_queue = job_queue.with_anime_filter(anime_filter);
 
     // Initialize disk monitor (monitors both local SSD and external HDD)
     let disk_monitor = DiskMonitor::new(
@@ -93,7 +231,14 @@ async fn main() -> Result<()> {
         config.disk_management.resume_threshold_gb,
         Duration::from_secs(config.disk_management.cache_duration_seconds),
     )
-    .context("Failed to initialize disk monitor")?;
+    .context("Failed to initialize disk monitor")?
+    .with_layout(config.path_layout())
+    .with_reconcile_interval(
+        config
+            .disk_management
+            .reconcile_interval_seconds
+            .map(Duration::from_secs),
+    );
 
     // Check initial disk usage
     let breakdown = disk_monitor.get_breakdown()?;
@@ -104,6 +249,9 @@ async fn main() -> Result<()> {
         "Initial disk usage"
     );
 
+    // `kill -USR1 <pid>` rescans the disk after out-of-band changes
+    disk_monitor.refresh_on_sigusr1()?;
+
     // Get number of workers
     let num_workers = args
         .workers
@@ -125,9 +273,23 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Record the tool versions and config this run transcribes with
+    let (mut manifest, manifest_path) = RunManifest::start("transcriber", &config, EXTERNAL_TOOLS, &data_paths)?;
+    let whisper_version = manifest.tool_version("whisper").map(str::to_string);
+
+    // One bar for every worker, over the jobs downloaded at the start
+    let downloaded = job_queue.count_pending(JobStage::Downloaded, None)? as u64;
+    let progress = ProgressBar::new("transcripts", args.max_jobs.map_or(downloaded, |max| downloaded.min(max as u64)));
+
     // Wrap queue in Arc for sharing between workers
     let job_queue = Arc::new(Mutex::new(job_queue));
 
+    // Shared budget so the job limit applies to the run as a whole
+    let budget = RunBudget::new(args.max_jobs, args.max_duration.map(Duration::from_secs));
+    let max_retries = args.max_retries.or(config.pipeline.retry.map(|retry| retry.max_retries()));
+    let retry_policy = JobRetryPolicy::new(!args.no_auto_retry, max_retries);
+    let initial_prompt = args.initial_prompt.clone().or_else(|| config.transcriber.initial_prompt.clone());
+
     // Initialize transcribers
     let mut transcribers = Vec::new();
     for worker_id in 0..num_workers {
@@ -139,7 +301,18 @@ async fn main() -> Result<()> {
             args.model.clone(),
             config.disk_management.cleanup.clone(),
             args.dry_run,
-        );
+            budget.clone(),
+        )
+        .with_escalation(args.escalate_to.clone())
+        .with_audio_format(config.transcriber.audio.clone())
+        .with_temp_dir(config.transcriber_temp_dir())
+        .with_transcript_formats(config.transcriber.transcript_formats.clone())
+        .with_chunk_seconds(config.transcriber.chunk_seconds)
+        .with_process_timeout(config.transcriber.process_timeout())
+        .with_initial_prompt(initial_prompt.clone())
+        .with_whisper_version(whisper_version.clone())
+        .with_progress_bar(progress.clone())
+        .with_retry_policy(retry_policy);
         transcribers.push(transcriber);
     }
 
@@ -174,6 +347,8 @@ async fn main() -> Result<()> {
         }
     }
 
+    progress.finish();
+
     // Final statistics
     let final_stats = job_queue
         .lock()
@@ -186,6 +361,12 @@ async fn main() -> Result<()> {
     info!("Transcribed: {}", final_stats.transcribed);
     info!("Failed: {}", final_stats.failed);
 
+    if let Some(path) = &args.stats_json {
+        std::fs::write(path, final_stats.to_json()?)
+            .with_context(|| format!("Failed to write stats to {}", path.display()))?;
+        info!(path = %path.display(), "Wrote queue statistics");
+    }
+
     let final_breakdown = disk_monitor.get_breakdown()?;
     info!(
         total_gb = final_breakdown.usage.total_gb(),
@@ -195,7 +376,19 @@ async fn main() -> Result<()> {
         "Final disk usage"
     );
 
+    manifest.finish(&manifest_path)?;
     info!("Transcriber finished successfully");
 
     Ok(())
 }
+
+/// Run the pre-flight checks and print the report
+fn validate(config_path: Option<&Path>, profile: Option<&str>) -> Result<()> {
+    let report = preflight::run(config_path, profile, EXTERNAL_TOOLS);
+    println!("{}", report);
+
+    if !report.passed() {
+        anyhow::bail!("Pre-flight checks failed");
+    }
+    Ok(())
+}
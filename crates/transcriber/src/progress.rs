@@ -0,0 +1,238 @@
+//! Job progress from ffmpeg and whisper output.
+//!
+//! ffmpeg is run with `-progress pipe:1` and reports how much of the output
+//! it has written (`out_time_us=...`); the input duration comes from the
+//! `Duration:` line of its banner. whisper with `--verbose True` prints one
+//! `[MM:SS.mmm --> MM:SS.mmm] text` line per segment; the audio length comes
+//! from the WAV file size or the FLAC stream header.
+//!
+//! Progress is best-effort: lines that don't parse are ignored and a failed
+//! database update is only logged.
+
+use shared::{AudioCodec, AudioFormat, JobQueue};
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Share of a job's progress taken by audio extraction
+pub const EXTRACT_PROGRESS: Range<f64> = 0.0..0.1;
+
+/// Share of a job's progress taken by transcription
+pub const TRANSCRIBE_PROGRESS: Range<f64> = 0.1..1.0;
+
+/// Minimum time between two progress writes for one job
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Size of a canonical WAV header
+const WAV_HEADER_BYTES: u64 = 44;
+
+/// Parse how much output ffmpeg has written from a `-progress` line
+/// (`out_time_us=`, `out_time_ms=` (also microseconds) or `out_time=`)
+pub fn parse_ffmpeg_progress(line: &str) -> Option<Duration> {
+    let (key, value) = line.trim().split_once('=')?;
+    match key {
+        "out_time_us" | "out_time_ms" => value.parse::<u64>().ok().map(Duration::from_micros),
+        "out_time" => parse_timestamp(value),
+        _ => None,
+    }
+}
+
+/// Parse the input duration from ffmpeg's `  Duration: 00:23:40.05, start: ...` line
+pub fn parse_ffmpeg_duration(line: &str) -> Option<Duration> {
+    let rest = line.trim_start().strip_prefix("Duration:")?;
+    parse_timestamp(rest.split(',').next()?.trim())
+}
+
+/// Parse the end time of a whisper `--verbose True` segment line such as
+/// `[01:02.340 --> 01:05.000]  text`
+pub fn parse_whisper_segment(line: &str) -> Option<Duration> {
+    let inner = line.trim_start().strip_prefix('[')?;
+    let (range, _) = inner.split_once(']')?;
+    let (_, end) = range.split_once("-->")?;
+    parse_timestamp(end.trim())
+}
+
+/// Length of the audio extracted to `path` in `format`, or None if it
+/// cannot be read
+pub fn audio_duration(path: &Path, format: &AudioFormat) -> Option<Duration> {
+    match format.codec {
+        AudioCodec::Wav => {
+            // 16-bit PCM
+            let bytes_per_second = u64::from(format.sample_rate) * u64::from(format.channels) * 2;
+            wav_duration(std::fs::metadata(path).ok()?.len(), bytes_per_second)
+        }
+        AudioCodec::Flac => {
+            let mut header = [0u8; 26];
+            std::fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+            flac_duration(&header)
+        }
+    }
+}
+
+/// Length of the audio in a WAV file of `bytes` bytes
+fn wav_duration(bytes: u64, bytes_per_second: u64) -> Option<Duration> {
+    if bytes_per_second == 0 {
+        return None;
+    }
+    let samples = bytes.saturating_sub(WAV_HEADER_BYTES);
+    Some(Duration::from_secs_f64(samples as f64 / bytes_per_second as f64))
+}
+
+/// Length of a FLAC stream from its `fLaC` marker and STREAMINFO block,
+/// which ffmpeg always writes first
+fn flac_duration(header: &[u8; 26]) -> Option<Duration> {
+    if &header[..4] != b"fLaC" || header[4] & 0x7f != 0 {
+        return None;
+    }
+    // 20 bits sample rate, 3 bits channels, 5 bits sample size, 36 bits total samples
+    let packed = u64::from_be_bytes(header[18..26].try_into().ok()?);
+    let sample_rate = packed >> 44;
+    let total_samples = packed & ((1 << 36) - 1);
+    if sample_rate == 0 || total_samples == 0 {
+        // Total samples is 0 when the encoder did not know it
+        return None;
+    }
+    Some(Duration::from_secs_f64(total_samples as f64 / sample_rate as f64))
+}
+
+/// Parse `HH:MM:SS.frac`, `MM:SS.frac` or `SS.frac`
+fn parse_timestamp(s: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in s.split(':') {
+        let value: f64 = part.parse().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Writes one phase of a job's progress to the queue, throttled to one
+/// update per `PROGRESS_INTERVAL`
+pub(crate) struct ProgressReporter<'a> {
+    queue: &'a Arc<Mutex<JobQueue>>,
+    job_id: i64,
+    phase: Range<f64>,
+    last_update: Option<Instant>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub(crate) fn new(queue: &'a Arc<Mutex<JobQueue>>, job_id: i64, phase: Range<f64>) -> Self {
+        Self {
+            queue,
+            job_id,
+            phase,
+            last_update: None,
+        }
+    }
+
+    /// Report `done` out of `total`, unless the last update was too recent
+    pub(crate) fn report(&mut self, done: Duration, total: Duration) {
+        if total.is_zero() || self.last_update.is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        self.write((done.as_secs_f64() / total.as_secs_f64()).min(1.0));
+    }
+
+    /// Mark the phase as done
+    pub(crate) fn finish(&mut self) {
+        self.write(1.0);
+    }
+
+    fn write(&mut self, fraction: f64) {
+        self.last_update = Some(Instant::now());
+        let progress = self.phase.start + (self.phase.end - self.phase.start) * fraction;
+        if let Err(e) = self.queue.lock().unwrap().update_progress(self.job_id, progress, None) {
+            debug!(job_id = self.job_id, error = %e, "Failed to update job progress");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ffmpeg_progress() {
+        // One block of `ffmpeg -progress pipe:1` output
+        let sample = "\
+bitrate= 256.0kbits/s
+total_size=1966158
+out_time_us=61440000
+out_time_ms=61440000
+out_time=00:01:01.440000
+dup_frames=0
+drop_frames=0
+speed=  123x
+progress=continue
+";
+        let parsed: Vec<_> = sample.lines().filter_map(parse_ffmpeg_progress).collect();
+        assert_eq!(parsed, vec![Duration::from_millis(61_440); 3]);
+
+        // Reported before the first frame is written
+        assert_eq!(parse_ffmpeg_progress("out_time_us=N/A"), None);
+        assert_eq!(parse_ffmpeg_progress("out_time=-577014:32:22.-77546"), None);
+        assert_eq!(parse_ffmpeg_progress("progress=end"), None);
+
+        assert_eq!(
+            parse_ffmpeg_duration("  Duration: 00:23:40.05, start: 0.000000, bitrate: 1523 kb/s"),
+            Some(Duration::from_millis(1_420_050))
+        );
+        assert_eq!(parse_ffmpeg_duration("  Duration: N/A, start: 0.000000"), None);
+        assert_eq!(parse_ffmpeg_duration("Input #0, matroska,webm, from 'ep001.mkv':"), None);
+    }
+
+    #[test]
+    fn test_parse_whisper_segment() {
+        assert_eq!(
+            parse_whisper_segment("[01:02.340 --> 01:05.000]  錬金術の基本は等価交換"),
+            Some(Duration::from_secs(65))
+        );
+        assert_eq!(
+            parse_whisper_segment("[01:00:00.000 --> 01:00:04.500] 行くぞ"),
+            Some(Duration::from_millis(3_604_500))
+        );
+        assert_eq!(parse_whisper_segment("Detected language: Japanese"), None);
+        assert_eq!(parse_whisper_segment("[garbage]"), None);
+    }
+
+    #[test]
+    fn test_audio_duration() -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+
+        // 10 seconds of 44.1 kHz stereo 16-bit audio
+        let wav = temp_dir.path().join("ep001.wav");
+        std::fs::write(&wav, vec![0u8; 44 + 10 * 44_100 * 2 * 2])?;
+        let format = AudioFormat {
+            sample_rate: 44_100,
+            channels: 2,
+            codec: AudioCodec::Wav,
+        };
+        assert_eq!(audio_duration(&wav, &format), Some(Duration::from_secs(10)));
+
+        // 90 seconds of 16 kHz 16-bit audio in the STREAMINFO block
+        let mut header = Vec::from(*b"fLaC");
+        header.extend([0x00, 0x00, 0x00, 0x22]);
+        header.extend([0u8; 10]);
+        let packed: u64 = (16_000 << 44) | (0b000_01111 << 36) | 1_440_000;
+        header.extend(packed.to_be_bytes());
+        header.extend([0u8; 16]);
+        let flac = temp_dir.path().join("ep001.flac");
+        std::fs::write(&flac, &header)?;
+        let format = AudioFormat {
+            codec: AudioCodec::Flac,
+            ..Default::default()
+        };
+        assert_eq!(audio_duration(&flac, &format), Some(Duration::from_secs(90)));
+
+        // A WAV file read as FLAC
+        assert_eq!(audio_duration(&wav, &format), None);
+        assert_eq!(audio_duration(&temp_dir.path().join("missing.flac"), &format), None);
+
+        Ok(())
+    }
+}
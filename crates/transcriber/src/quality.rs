@@ -0,0 +1,206 @@
+//! Transcript quality scoring.
+//!
+//! When the audio is poor, Whisper sometimes falls back to romaji or mixed
+//! scripts, or emits mostly blank/fragmentary lines. Such transcripts would
+//! skew the Japanese word frequency analysis, so each transcript gets a
+//! score before it moves on to tokenization.
+
+pub use shared::models::LOW_QUALITY_THRESHOLD;
+
+/// Average non-empty line length (in characters) below which lines are
+/// considered fragmentary
+const MIN_AVG_LINE_CHARS: f64 = 4.0;
+
+/// Average non-empty line length above which the transcript is probably
+/// missing line breaks (or is one long hallucinated run)
+const MAX_AVG_LINE_CHARS: f64 = 120.0;
+
+/// Whisper models in order of size, used for escalation
+pub const MODEL_LADDER: [&str; 5] = ["tiny", "base", "small", "medium", "large"];
+
+/// Quality measurements for a transcript
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TranscriptQuality {
+    /// Fraction of letters that are kana or kanji (0.0 to 1.0)
+    pub japanese_ratio: f64,
+    /// Fraction of letters that are latin (0.0 to 1.0)
+    pub latin_ratio: f64,
+    /// Average length of non-empty lines, in characters
+    pub avg_line_length: f64,
+    /// Fraction of lines that are empty or whitespace only
+    pub empty_line_fraction: f64,
+    /// Combined score (0.0 to 1.0, higher is better)
+    pub score: f64,
+}
+
+impl TranscriptQuality {
+    /// Whether the transcript scores below `LOW_QUALITY_THRESHOLD`
+    pub fn is_low(&self) -> bool {
+        self.score < LOW_QUALITY_THRESHOLD
+    }
+}
+
+/// Score a transcript.
+///
+/// The score is the product of the Japanese script ratio, a line length
+/// factor (1.0 inside the expected range, falling off outside it) and the
+/// fraction of non-empty lines, so any one bad signal pulls it down.
+pub fn transcript_quality(text: &str) -> TranscriptQuality {
+    let mut japanese = 0usize;
+    let mut latin = 0usize;
+    for c in text.chars() {
+        if is_japanese(c) {
+            japanese += 1;
+        } else if is_latin(c) {
+            latin += 1;
+        }
+    }
+
+    let letters = japanese + latin;
+    let (japanese_ratio, latin_ratio) = if letters == 0 {
+        (0.0, 0.0)
+    } else {
+        (japanese as f64 / letters as f64, latin as f64 / letters as f64)
+    };
+
+    let mut lines = 0usize;
+    let mut empty_lines = 0usize;
+    let mut line_chars = 0usize;
+    for line in text.lines() {
+        lines += 1;
+        let line = line.trim();
+        if line.is_empty() {
+            empty_lines += 1;
+        } else {
+            line_chars += line.chars().count();
+        }
+    }
+
+    let non_empty = lines - empty_lines;
+    let avg_line_length = if non_empty == 0 {
+        0.0
+    } else {
+        line_chars as f64 / non_empty as f64
+    };
+    let empty_line_fraction = if lines == 0 {
+        1.0
+    } else {
+        empty_lines as f64 / lines as f64
+    };
+
+    let line_factor = if avg_line_length < MIN_AVG_LINE_CHARS {
+        avg_line_length / MIN_AVG_LINE_CHARS
+    } else if avg_line_length > MAX_AVG_LINE_CHARS {
+        MAX_AVG_LINE_CHARS / avg_line_length
+    } else {
+        1.0
+    };
+
+    TranscriptQuality {
+        japanese_ratio,
+        latin_ratio,
+        avg_line_length,
+        empty_line_fraction,
+        score: japanese_ratio * line_factor * (1.0 - empty_line_fraction),
+    }
+}
+
+/// Decide which model to retry with after a transcript scored `quality`.
+///
+/// Returns the next larger model after `current` when the quality is low and
+/// that model does not exceed `ceiling`. Models outside `MODEL_LADDER`
+/// (e.g. `large-v3`) are never escalated from or to.
+pub fn next_model(current: &str, quality: &TranscriptQuality, ceiling: &str) -> Option<&'static str> {
+    if !quality.is_low() {
+        return None;
+    }
+
+    let current = MODEL_LADDER.iter().position(|m| *m == current)?;
+    let ceiling = MODEL_LADDER.iter().position(|m| *m == ceiling)?;
+    MODEL_LADDER.get(current + 1).copied().filter(|_| current < ceiling)
+}
+
+/// Hiragana, katakana (full and half width) and CJK ideographs
+fn is_japanese(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{309F}'     // Hiragana
+        | '\u{30A0}'..='\u{30FF}'   // Katakana (including ー)
+        | '\u{FF66}'..='\u{FF9F}'   // Half-width katakana
+        | '\u{3400}'..='\u{4DBF}'   // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK unified ideographs
+        | '\u{3005}'                // 々
+    )
+}
+
+/// Latin letters, including accented and full-width forms
+fn is_latin(c: char) -> bool {
+    let extended = matches!(c, '\u{00C0}'..='\u{024F}' | '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}');
+    c.is_ascii_alphabetic() || (extended && c.is_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_japanese_transcript_scores_high() {
+        let text = "おはようございます。\n今日はいい天気ですね。\nそうですね、散歩に行きましょうか。\nはい、行きましょう！\nOKです。\n";
+        let quality = transcript_quality(text);
+
+        assert!(quality.japanese_ratio > 0.9, "{:?}", quality);
+        assert!(quality.latin_ratio > 0.0);
+        assert_eq!(quality.empty_line_fraction, 0.0);
+        assert!(quality.avg_line_length > MIN_AVG_LINE_CHARS);
+        assert!(quality.score > 0.9);
+        assert!(!quality.is_low());
+    }
+
+    #[test]
+    fn test_latin_only_transcript_scores_low() {
+        let text = "ohayou gozaimasu\n\nkyou wa ii tenki desu ne\n\n\nThank you for watching\n";
+        let quality = transcript_quality(text);
+
+        assert_eq!(quality.japanese_ratio, 0.0);
+        assert_eq!(quality.latin_ratio, 1.0);
+        assert_eq!(quality.empty_line_fraction, 0.5);
+        assert_eq!(quality.score, 0.0);
+        assert!(quality.is_low());
+    }
+
+    #[test]
+    fn test_next_model() {
+        let low = transcript_quality("romaji only\n");
+        let good = transcript_quality("今日はいい天気ですね。\n");
+
+        // Low quality escalates one step at a time up to the ceiling
+        assert_eq!(next_model("base", &low, "medium"), Some("small"));
+        assert_eq!(next_model("small", &low, "medium"), Some("medium"));
+        assert_eq!(next_model("medium", &low, "medium"), None);
+        assert_eq!(next_model("tiny", &low, "large"), Some("base"));
+        assert_eq!(next_model("large", &low, "large"), None);
+
+        // Good transcripts are kept
+        assert_eq!(next_model("base", &good, "medium"), None);
+
+        // Already at or above the ceiling
+        assert_eq!(next_model("medium", &low, "small"), None);
+        assert_eq!(next_model("base", &low, "base"), None);
+
+        // Unknown models are left alone
+        assert_eq!(next_model("large-v3", &low, "large"), None);
+        assert_eq!(next_model("base", &low, "turbo"), None);
+    }
+
+    #[test]
+    fn test_degenerate_transcripts() {
+        // Nothing at all
+        let quality = transcript_quality("");
+        assert_eq!(quality.score, 0.0);
+        assert_eq!(quality.empty_line_fraction, 1.0);
+
+        // Japanese, but only one-character fragments separated by blank lines
+        let quality = transcript_quality("あ\n\nう\n\nえ\n\n");
+        assert_eq!(quality.japanese_ratio, 1.0);
+        assert!(quality.is_low(), "{:?}", quality);
+    }
+}
@@ -2,16 +2,51 @@
 //!
 //! Transcribes audio from videos using Whisper and aggressively cleans up files.
 
+use crate::progress::{
+    parse_ffmpeg_duration, parse_ffmpeg_progress, parse_whisper_segment, audio_duration, ProgressReporter,
+    EXTRACT_PROGRESS, TRANSCRIBE_PROGRESS,
+};
+use crate::chunks;
+use crate::quality::{next_model, transcript_quality, TranscriptQuality};
 use anyhow::{Context, Result};
 use regex::Regex;
-use shared::{CleanupConfig, DataPaths, DiskMonitor, Job, JobQueue, JobStage};
+use shared::progress::ProgressBar;
+use shared::{
+    episode_file_stem, process, AudioFormat, CleanupConfig, DataPaths, DiskCategory, DiskMonitor, Job, JobMetadata,
+    JobQueue, JobStage, JobRetryPolicy, RunBudget, StageLimits, TranscriberConfig, TranscriptFormat,
+};
+use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
+/// Returned when a job's video was deleted after an earlier transcription,
+/// so it cannot be transcribed again without downloading it first
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Video already deleted, re-download required: {}", .path.display())]
+pub struct VideoDeleted {
+    /// Where the video used to be
+    pub path: PathBuf,
+}
+
+/// What `process_job` produced and cleaned up, recorded in the job once the
+/// work is done
+struct TranscribedJob {
+    transcript_path: PathBuf,
+    audio_size: u64,
+    transcript_size: u64,
+    /// Model that produced the kept transcript
+    model: String,
+    /// Quality score of the kept transcript
+    quality: f64,
+    video_deleted: bool,
+    audio_deleted: bool,
+}
+
 /// Transcriber worker.
 pub struct Transcriber {
     /// Worker ID for logging
@@ -24,10 +59,34 @@ pub struct Transcriber {
     data_paths: DataPaths,
     /// Whisper model name
     model: String,
+    /// Largest model to retry with when a transcript scores low (None = never retry)
+    escalate_to: Option<String>,
+    /// Format of the audio extracted for whisper
+    audio_format: AudioFormat,
+    /// Directory for extracted audio instead of the data root's audio directory
+    temp_dir: Option<PathBuf>,
+    /// Prompt template for whisper (`{title}` = anime title)
+    initial_prompt: Option<String>,
+    /// Transcript files to write; video and audio stay until all exist
+    transcript_formats: Vec<TranscriptFormat>,
+    /// Length of the chunks long audio is transcribed in (None = whole episode)
+    chunk_seconds: Option<u64>,
+    /// Version of the whisper package, recorded on each transcribed job
+    whisper_version: Option<String>,
+    /// Time an ffmpeg or whisper run may take before it is killed (None = no limit)
+    process_timeout: Option<Duration>,
     /// Cleanup configuration
     cleanup_config: CleanupConfig,
+    /// Whether (and how often) failed transcriptions are retried
+    retry_policy: JobRetryPolicy,
     /// Dry run mode (don't actually transcribe)
     dry_run: bool,
+    /// Job count / time limit shared with the other workers
+    budget: RunBudget,
+    /// Per-stage concurrency limits shared with the other workers
+    stage_limits: StageLimits,
+    /// Bar advanced for each job transcribed or failed for good
+    progress_bar: ProgressBar,
     /// Number of completed transcriptions
     completed: usize,
     /// Number of failed transcriptions
@@ -36,6 +95,7 @@ pub struct Transcriber {
 
 impl Transcriber {
     /// Create a new transcriber worker.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         worker_id: usize,
         queue: Arc<Mutex<JobQueue>>,
@@ -44,6 +104,7 @@ impl Transcriber {
         model: String,
         cleanup_config: CleanupConfig,
         dry_run: bool,
+        budget: RunBudget,
     ) -> Self {
         Self {
             worker_id,
@@ -51,13 +112,101 @@ impl Transcriber {
             disk_monitor,
             data_paths,
             model,
+            escalate_to: None,
+            audio_format: AudioFormat::default(),
+            temp_dir: None,
+            initial_prompt: None,
+            transcript_formats: vec![TranscriptFormat::Txt],
+            chunk_seconds: None,
+            whisper_version: None,
+            process_timeout: TranscriberConfig::default().process_timeout(),
             cleanup_config,
+            retry_policy: JobRetryPolicy::default(),
             dry_run,
+            budget,
+            stage_limits: StageLimits::unlimited(),
+            progress_bar: ProgressBar::hidden(),
             completed: 0,
             failed: 0,
         }
     }
 
+    /// Re-transcribe low-quality transcripts with larger models, up to `ceiling`.
+    pub fn with_escalation(mut self, ceiling: Option<String>) -> Self {
+        self.escalate_to = ceiling;
+        self
+    }
+
+    /// Override how failed transcriptions are retried.
+    pub fn with_retry_policy(mut self, retry_policy: JobRetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Override the format audio is extracted to.
+    pub fn with_audio_format(mut self, audio_format: AudioFormat) -> Self {
+        self.audio_format = audio_format;
+        self
+    }
+
+    /// Extract audio under `temp_dir` (per anime) instead of the data root's
+    /// audio directory; it then no longer counts towards the data disk usage.
+    pub fn with_temp_dir(mut self, temp_dir: Option<PathBuf>) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    /// Prime whisper with `template` (`{title}` is replaced with the anime title).
+    pub fn with_initial_prompt(mut self, template: Option<String>) -> Self {
+        self.initial_prompt = template;
+        self
+    }
+
+    /// Write a transcript in each of `formats` (txt, the one the tokenizer
+    /// reads, is always written).
+    pub fn with_transcript_formats(mut self, formats: Vec<TranscriptFormat>) -> Self {
+        self.transcript_formats = formats;
+        if !self.transcript_formats.contains(&TranscriptFormat::Txt) {
+            self.transcript_formats.insert(0, TranscriptFormat::Txt);
+        }
+        self
+    }
+
+    /// Transcribe in `chunk_seconds` long chunks, so a failed or interrupted
+    /// transcription resumes after the last finished chunk (None = whole episode).
+    pub fn with_chunk_seconds(mut self, chunk_seconds: Option<u64>) -> Self {
+        self.chunk_seconds = chunk_seconds;
+        self
+    }
+
+    /// Run at most as many transcriptions at once as `limits` allows, across
+    /// every worker sharing them.
+    pub fn with_stage_limits(mut self, limits: StageLimits) -> Self {
+        self.stage_limits = limits;
+        self
+    }
+
+    /// Record `version` as the whisper version of every transcribed job.
+    pub fn with_whisper_version(mut self, version: Option<String>) -> Self {
+        self.whisper_version = version;
+        self
+    }
+
+    /// Kill an ffmpeg or whisper run that takes longer than `timeout` (None =
+    /// no limit); the transcription is then retried like any other failure.
+    /// Each chunk of a chunked transcription gets its own `timeout`.
+    pub fn with_process_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.process_timeout = timeout;
+        self
+    }
+
+    /// Advance `bar` (usually shared with the other workers) for each job
+    /// transcribed or failed for good; retried jobs are counted once done.
+    pub fn with_progress_bar(mut self, bar: ProgressBar) -> Self {
+        self.progress_bar = bar;
+        self
+    }
+
     /// Get worker ID.
     pub fn worker_id(&self) -> usize {
         self.worker_id
@@ -68,6 +217,19 @@ impl Transcriber {
         info!(worker_id = self.worker_id, "Transcription worker started");
 
         loop {
+            // Stop picking up new work once the run budget is spent
+            if !self.budget.try_start_job() {
+                info!(
+                    worker_id = self.worker_id,
+                    jobs_started = self.budget.jobs_started(),
+                    "Run budget reached, leaving remaining jobs queued"
+                );
+                break;
+            }
+
+            // Wait for a transcription slot; it is held until the job is done
+            let _permit = self.stage_limits.acquire(JobStage::Transcribing).await;
+
             // Try to get next job from queue
             let job = match self.queue.lock().unwrap().dequeue_next(JobStage::Downloaded) {
                 Ok(job) => job,
@@ -76,6 +238,7 @@ impl Transcriber {
                     let err_msg = format!("{}", e);
                     if err_msg.contains("No jobs available") {
                         debug!(worker_id = self.worker_id, "No more jobs in queue");
+                        self.budget.release_job();
                         break;
                     }
                     return Err(e).context("Failed to dequeue job");
@@ -99,33 +262,48 @@ impl Transcriber {
 
             // Process the job
             match self.process_job(&job).await {
-                Ok((transcript_path, audio_size, transcript_size)) => {
+                Ok(done) => {
                     info!(
                         worker_id = self.worker_id,
                         job_id = job.id,
-                        audio_size_mb = audio_size / 1_000_000,
-                        transcript_size_kb = transcript_size / 1_000,
+                        audio_size_mb = done.audio_size / 1_000_000,
+                        transcript_size_kb = done.transcript_size / 1_000,
                         "Transcription complete"
                     );
 
-                    // Update job with transcript info
+                    // Record the transcript, its score and the cleanup and
+                    // move the job to transcribed in one commit
                     self.queue
                         .lock()
                         .unwrap()
-                        .update_job_with_transcript(job.id, transcript_path, audio_size, transcript_size)
-                        .context("Failed to update job with transcript info")?;
-
-                    // Update stage to transcribed
-                    self.queue
-                        .lock()
-                        .unwrap()
-                        .update_stage(job.id, JobStage::Transcribed)
-                        .context("Failed to update job stage")?;
+                        .with_transaction(|queue| {
+                            queue.update_metadata(
+                                job.id,
+                                &JobMetadata {
+                                    transcript_quality: Some(done.quality),
+                                    transcript_model: Some(done.model),
+                                    whisper_version: self.whisper_version.clone(),
+                                    ..Default::default()
+                                },
+                            )?;
+                            queue.update_job_with_transcript(
+                                job.id,
+                                done.transcript_path,
+                                done.audio_size,
+                                done.transcript_size,
+                            )?;
+                            if done.video_deleted {
+                                queue.mark_video_deleted(job.id)?;
+                            }
+                            if done.audio_deleted {
+                                queue.mark_audio_deleted(job.id)?;
+                            }
+                            queue.update_stage(job.id, JobStage::Transcribed)
+                        })
+                        .context("Failed to record transcription")?;
 
                     self.completed += 1;
-
-                    // Invalidate disk cache to reflect deleted files
-                    self.disk_monitor.invalidate_cache();
+                    self.progress_bar.inc(1);
                 }
                 Err(e) => {
                     error!(
@@ -135,12 +313,34 @@ impl Transcriber {
                         "Transcription failed"
                     );
 
+                    // A redo whose video is gone needs a fresh download, not
+                    // another transcription attempt
+                    let video_deleted = e.downcast_ref::<VideoDeleted>().is_some();
+
                     // Check if we should retry
-                    if job.retry_count < job.max_retries {
+                    if video_deleted && self.retry_policy.should_retry(&job) {
                         warn!(
                             job_id = job.id,
                             retry_count = job.retry_count + 1,
-                            max_retries = job.max_retries,
+                            max_retries = self.retry_policy.max_retries(&job),
+                            "Video already deleted, requeueing job for download"
+                        );
+
+                        self.queue
+                            .lock()
+                            .unwrap()
+                            .increment_retry(job.id)
+                            .context("Failed to increment retry count")?;
+                        self.queue
+                            .lock()
+                            .unwrap()
+                            .requeue_for_download(job.id, format!("{:#}", e))
+                            .context("Failed to requeue job for download")?;
+                    } else if self.retry_policy.should_retry(&job) {
+                        warn!(
+                            job_id = job.id,
+                            retry_count = job.retry_count + 1,
+                            max_retries = self.retry_policy.max_retries(&job),
                             "Retrying job"
                         );
 
@@ -158,7 +358,7 @@ impl Transcriber {
                     } else {
                         error!(
                             job_id = job.id,
-                            "Max retries exceeded, marking job as failed"
+                            "Not retrying, marking job as failed"
                         );
 
                         // Mark as failed
@@ -169,6 +369,7 @@ impl Transcriber {
                             .context("Failed to update job as failed")?;
 
                         self.failed += 1;
+                        self.progress_bar.inc(1);
                     }
                 }
             }
@@ -189,8 +390,8 @@ impl Transcriber {
 
     /// Process a single job: extract audio, transcribe, cleanup.
     ///
-    /// Returns: (transcript_path, audio_size, transcript_size)
-    async fn process_job(&self, job: &Job) -> Result<(PathBuf, u64, u64)> {
+    /// Only touches files; the caller records the outcome in the job.
+    async fn process_job(&self, job: &Job) -> Result<TranscribedJob> {
         // Get video path from job
         let video_path = job
             .video_path
@@ -199,6 +400,9 @@ impl Transcriber {
         let video_path = PathBuf::from(video_path);
 
         if !video_path.exists() {
+            if job.video_deleted {
+                return Err(VideoDeleted { path: video_path }.into());
+            }
             anyhow::bail!("Video file not found: {}", video_path.display());
         }
 
@@ -212,6 +416,9 @@ impl Transcriber {
         // Step 1: Extract audio
         let audio_path = self.extract_audio(&video_path, job).await?;
         let audio_size = fs::metadata(&audio_path)?.len();
+        // Audio in the temp dir takes no space on the monitored disks
+        let tracked_audio_size = if self.temp_dir.is_some() { 0 } else { audio_size };
+        self.disk_monitor.add_bytes(DiskCategory::Audio, tracked_audio_size);
 
         info!(
             worker_id = self.worker_id,
@@ -220,19 +427,88 @@ impl Transcriber {
             "Audio extracted"
         );
 
-        // Step 2: Transcribe
-        let transcript_path = self.transcribe(&audio_path, job).await?;
+        // Step 2: Transcribe, retrying with larger models while the quality is low
+        let transcript_path = self.transcribe(&audio_path, job, &self.model).await?;
+        let (model, quality) = self.escalate(&audio_path, &transcript_path, job).await?;
         let transcript_size = fs::metadata(&transcript_path)?.len();
+        self.disk_monitor.add_bytes(DiskCategory::Transcripts, transcript_size);
 
         info!(
             worker_id = self.worker_id,
             job_id = job.id,
             transcript_size_kb = transcript_size / 1_000,
+            model = %model,
             "Transcription complete"
         );
 
-        // Step 3: AGGRESSIVE CLEANUP - Delete video and audio immediately
-        if self.cleanup_config.delete_video_after_transcription {
+        // Step 3: Score the transcript (recorded with the job, so romaji/garbage
+        // output can be found later)
+        if quality.is_low() {
+            warn!(
+                worker_id = self.worker_id,
+                job_id = job.id,
+                score = %format!("{:.2}", quality.score),
+                japanese_ratio = %format!("{:.2}", quality.japanese_ratio),
+                empty_line_fraction = %format!("{:.2}", quality.empty_line_fraction),
+                model = %model,
+                "Low transcript quality"
+            );
+        } else {
+            debug!(
+                job_id = job.id,
+                score = %format!("{:.2}", quality.score),
+                "Transcript quality"
+            );
+        }
+        let mut done = TranscribedJob {
+            transcript_path,
+            audio_size,
+            transcript_size,
+            model,
+            quality: quality.score,
+            video_deleted: false,
+            audio_deleted: false,
+        };
+
+        // Step 4: AGGRESSIVE CLEANUP - Delete video and audio immediately,
+        // unless the transcript failed the quality check and may be redone
+        if quality.is_low() {
+            warn!(
+                worker_id = self.worker_id,
+                job_id = job.id,
+                video_path = %video_path.display(),
+                audio_path = %audio_path.display(),
+                "Keeping video and audio of a low quality transcript for a redo"
+            );
+            return Ok(done);
+        }
+
+        // Only delete the sources once every requested transcript exists; a
+        // format that failed to write fails the attempt, so the retry can
+        // still produce it from the kept video
+        let missing = missing_formats(&self.transcript_formats, &done.transcript_path);
+        if !missing.is_empty() {
+            warn!(
+                worker_id = self.worker_id,
+                job_id = job.id,
+                missing = ?missing,
+                video_path = %video_path.display(),
+                audio_path = %audio_path.display(),
+                "Keeping video and audio, not every transcript format was written"
+            );
+            let missing: Vec<_> = missing.iter().map(|format| format.extension()).collect();
+            anyhow::bail!("Transcript formats not written: {}", missing.join(", "));
+        }
+
+        let keep_video = self.cleanup_config.keep_video_sample(job.id, job.episode);
+        if self.cleanup_config.delete_video_after_transcription && keep_video {
+            info!(
+                worker_id = self.worker_id,
+                job_id = job.id,
+                video_path = %video_path.display(),
+                "Keeping video as a spot-check sample"
+            );
+        } else if self.cleanup_config.delete_video_after_transcription {
             info!(
                 worker_id = self.worker_id,
                 job_id = job.id,
@@ -241,13 +517,9 @@ impl Transcriber {
             );
             fs::remove_file(&video_path)
                 .with_context(|| format!("Failed to delete video: {}", video_path.display()))?;
-
-            // Mark video as deleted in database
-            self.queue
-                .lock()
-                .unwrap()
-                .mark_video_deleted(job.id)
-                .context("Failed to mark video as deleted")?;
+            self.disk_monitor
+                .sub_bytes(DiskCategory::Videos, job.video_size_bytes.unwrap_or(0));
+            done.video_deleted = true;
         }
 
         if self.cleanup_config.delete_audio_after_transcription {
@@ -259,13 +531,8 @@ impl Transcriber {
             );
             fs::remove_file(&audio_path)
                 .with_context(|| format!("Failed to delete audio: {}", audio_path.display()))?;
-
-            // Mark audio as deleted in database
-            self.queue
-                .lock()
-                .unwrap()
-                .mark_audio_deleted(job.id)
-                .context("Failed to mark audio as deleted")?;
+            self.disk_monitor.sub_bytes(DiskCategory::Audio, tracked_audio_size);
+            done.audio_deleted = true;
         }
 
         let video_size = job.video_size_bytes.unwrap_or(0);
@@ -276,19 +543,82 @@ impl Transcriber {
             "Freed disk space by deleting video and audio"
         );
 
-        Ok((transcript_path, audio_size, transcript_size))
+        Ok(done)
     }
 
-    /// Extract audio from video using FFmpeg.
+    /// Score the transcript and, while it is low quality, re-transcribe with
+    /// the next larger model (up to `escalate_to`). The best-scoring
+    /// transcript is left at `transcript_path`, along with its other formats.
     ///
-    /// Converts to 16kHz mono WAV format for Whisper.
-    async fn extract_audio(&self, video_path: &PathBuf, job: &Job) -> Result<PathBuf> {
-        let audio_dir = self.data_paths.audio_dir(job.mal_id);
-        fs::create_dir_all(&audio_dir)?;
+    /// Returns: (model that produced the kept transcript, its quality)
+    async fn escalate(
+        &self,
+        audio_path: &Path,
+        transcript_path: &PathBuf,
+        job: &Job,
+    ) -> Result<(String, TranscriptQuality)> {
+        let mut kept_model = self.model.clone();
+        let mut quality = score_transcript(transcript_path)?;
+
+        let Some(ceiling) = self.escalate_to.as_deref() else {
+            return Ok((kept_model, quality));
+        };
+
+        let outputs = output_paths(&self.transcript_formats, transcript_path);
+        let mut tried = self.model.clone();
+        while let Some(next) = next_model(&tried, &quality, ceiling) {
+            info!(
+                worker_id = self.worker_id,
+                job_id = job.id,
+                score = %format!("{:.2}", quality.score),
+                from = %tried,
+                to = next,
+                "Low transcript quality, retrying with larger model"
+            );
 
-        let safe_title = sanitize_filename(&job.anime_title);
-        let filename = format!("{}_ep{:03}.wav", safe_title, job.episode);
-        let audio_path = audio_dir.join(&filename);
+            // Move the current transcripts aside so whisper writes fresh ones
+            let previous = set_aside(&outputs, &kept_model)?;
+            tried = next.to_string();
+
+            let candidate = match self.transcribe(audio_path, job, next).await {
+                Ok(_) => score_transcript(transcript_path)?,
+                Err(e) => {
+                    warn!(job_id = job.id, model = next, error = %e, "Escalated transcription failed, keeping previous transcript");
+                    restore(&outputs, &previous)?;
+                    break;
+                }
+            };
+
+            if candidate.score > quality.score {
+                for (_, aside) in &previous {
+                    fs::remove_file(aside)?;
+                }
+                kept_model = tried.clone();
+                quality = candidate;
+            } else {
+                debug!(job_id = job.id, model = next, "Larger model did not improve the transcript");
+                restore(&outputs, &previous)?;
+            }
+        }
+
+        Ok((kept_model, quality))
+    }
+
+    /// Extract audio from video using FFmpeg.
+    ///
+    /// Converts to the configured `AudioFormat` (16kHz mono WAV by default) for Whisper.
+    /// The audio goes under the temp dir if one is set.
+    async fn extract_audio(&self, video_path: &Path, job: &Job) -> Result<PathBuf> {
+        let extension = self.audio_format.codec.extension();
+        let audio_path = match &self.temp_dir {
+            Some(temp_dir) => temp_dir
+                .join(job.mal_id.to_string())
+                .join(format!("{}.{}", episode_file_stem(&job.anime_title, job.episode), extension)),
+            None => self.data_paths.audio_file(job.mal_id, &job.anime_title, job.episode, extension),
+        };
+        if let Some(audio_dir) = audio_path.parent() {
+            fs::create_dir_all(audio_dir)?;
+        }
 
         // Check if already extracted
         if audio_path.exists() {
@@ -321,28 +651,42 @@ impl Transcriber {
         );
 
         // Use FFmpeg to extract audio
-        // ffmpeg -i input.mp4 -vn -acodec pcm_s16le -ar 16000 -ac 1 output.wav
-        let status = Command::new("ffmpeg")
-            .arg("-i")
-            .arg(video_path)
-            .arg("-vn") // No video
-            .arg("-acodec")
-            .arg("pcm_s16le") // 16-bit PCM
-            .arg("-ar")
-            .arg("16000") // 16kHz sample rate
-            .arg("-ac")
-            .arg("1") // Mono
-            .arg("-y") // Overwrite output file
-            .arg(&audio_path)
-            .status()
+        let mut child = process::own_process_group(&mut Command::new("ffmpeg"))
+            .args(extract_audio_args(video_path, &audio_path, &self.audio_format))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
             .context("Failed to execute ffmpeg command")?;
 
+        // The input duration is in ffmpeg's banner on stderr. It is read to
+        // the end, so ffmpeg never blocks on a full pipe.
+        let stderr = child.stderr.take().context("Failed to capture ffmpeg stderr")?;
+        let (duration_tx, duration_rx) = mpsc::channel();
+        let stderr_reader = std::thread::spawn(move || {
+            process::for_each_line(stderr, |line| {
+                if let Some(duration) = parse_ffmpeg_duration(&line) {
+                    let _ = duration_tx.send(duration);
+                }
+                true
+            })
+        });
+
+        let mut progress = ProgressReporter::new(&self.queue, job.id, EXTRACT_PROGRESS);
+        let mut total = None;
+        let status = process::wait_with_timeout(&mut child, "ffmpeg", self.process_timeout, |line| {
+            total = total.or_else(|| duration_rx.try_recv().ok());
+            if let (Some(done), Some(total)) = (parse_ffmpeg_progress(line), total) {
+                progress.report(done, total);
+            }
+        })?;
+        let _ = stderr_reader.join();
         if !status.success() {
             anyhow::bail!(
                 "ffmpeg failed with exit code: {:?}",
                 status.code().unwrap_or(-1)
             );
         }
+        progress.finish();
 
         // Verify file was created
         if !audio_path.exists() {
@@ -355,13 +699,11 @@ impl Transcriber {
     /// Transcribe audio using Whisper.
     ///
     /// Uses the whisper CLI (from openai-whisper Python package).
-    async fn transcribe(&self, audio_path: &PathBuf, job: &Job) -> Result<PathBuf> {
+    async fn transcribe(&self, audio_path: &Path, job: &Job, model: &str) -> Result<PathBuf> {
         let transcript_dir = self.data_paths.transcript_dir(job.mal_id);
         fs::create_dir_all(&transcript_dir)?;
 
-        let safe_title = sanitize_filename(&job.anime_title);
-        let filename = format!("{}_ep{:03}.txt", safe_title, job.episode);
-        let transcript_path = transcript_dir.join(&filename);
+        let transcript_path = self.data_paths.transcript_txt(job.mal_id, &job.anime_title, job.episode);
 
         // Check if already transcribed
         if transcript_path.exists() {
@@ -380,8 +722,11 @@ impl Transcriber {
                 "Dry run: would transcribe {}",
                 audio_path.display()
             );
-            // Create dummy transcript for testing
+            // Create dummy transcripts for testing
             fs::write(&transcript_path, "Dry run transcript")?;
+            if self.transcript_formats.contains(&TranscriptFormat::Json) {
+                fs::write(transcript_path.with_extension("json"), r#"{"text": "Dry run transcript", "segments": []}"#)?;
+            }
             return Ok(transcript_path);
         }
 
@@ -389,55 +734,180 @@ impl Transcriber {
             worker_id = self.worker_id,
             job_id = job.id,
             audio = %audio_path.display(),
-            model = %self.model,
+            model = model,
+            chunk_seconds = ?self.chunk_seconds,
             "Transcribing with Whisper"
         );
 
-        // Use whisper CLI
-        // whisper audio.wav --model base --language ja --output_dir /path/to/dir --output_format txt
-        let status = Command::new("whisper")
-            .arg(audio_path)
-            .arg("--model")
-            .arg(&self.model)
-            .arg("--language")
-            .arg("ja") // Japanese
-            .arg("--output_dir")
-            .arg(&transcript_dir)
-            .arg("--output_format")
-            .arg("txt")
-            .arg("--verbose")
-            .arg("False") // Less noise in logs
-            .status()
+        let total = audio_duration(audio_path, &self.audio_format);
+        let mut progress = ProgressReporter::new(&self.queue, job.id, TRANSCRIBE_PROGRESS);
+        match self.chunk_seconds {
+            Some(chunk_seconds) => {
+                self.transcribe_chunks(audio_path, job, model, chunk_seconds, &transcript_path, &mut progress, total)?
+            }
+            None => {
+                self.run_whisper(
+                    audio_path,
+                    &transcript_dir,
+                    job,
+                    model,
+                    &self.transcript_formats,
+                    &mut progress,
+                    Duration::ZERO,
+                    total,
+                )?;
+                keep_requested_outputs(&self.transcript_formats, audio_path, &transcript_dir, &transcript_path)?;
+            }
+        }
+
+        // Verify file was created
+        if !transcript_path.exists() {
+            anyhow::bail!(
+                "Transcript file was not created: {}",
+                transcript_path.display()
+            );
+        }
+
+        progress.finish();
+
+        // Chunks of this attempt, and any left by models or chunk lengths
+        // the job moved away from
+        chunks::remove_chunk_dirs(audio_path)?;
+
+        // Post-process: detect and remove hallucinations
+        self.clean_transcript(&transcript_path)?;
+
+        Ok(transcript_path)
+    }
+
+    /// Run whisper on `audio_path`, writing its outputs in `formats` (named
+    /// after the audio) to `output_dir`.
+    ///
+    /// Progress is reported against `total`, with the audio starting
+    /// `offset` into the episode.
+    #[allow(clippy::too_many_arguments)]
+    fn run_whisper(
+        &self,
+        audio_path: &Path,
+        output_dir: &Path,
+        job: &Job,
+        model: &str,
+        formats: &[TranscriptFormat],
+        progress: &mut ProgressReporter,
+        offset: Duration,
+        total: Option<Duration>,
+    ) -> Result<()> {
+        let initial_prompt = self
+            .initial_prompt
+            .as_deref()
+            .map(|template| render_initial_prompt(template, &job.anime_title));
+        let mut child = process::own_process_group(&mut Command::new("whisper"))
+            .args(whisper_args(
+                audio_path,
+                output_dir,
+                model,
+                whisper_output_format(formats),
+                initial_prompt.as_deref(),
+            ))
+            .env("PYTHONUNBUFFERED", "1")
+            .stdout(Stdio::piped())
+            .spawn()
             .context("Failed to execute whisper command")?;
 
+        let status = process::wait_with_timeout(&mut child, "whisper", self.process_timeout, |line| {
+            if let (Some(done), Some(total)) = (parse_whisper_segment(line), total) {
+                progress.report(offset + done, total);
+            }
+        })?;
         if !status.success() {
             anyhow::bail!(
                 "whisper failed with exit code: {:?}",
                 status.code().unwrap_or(-1)
             );
         }
+        Ok(())
+    }
 
-        // Whisper creates output with different naming: <audio_stem>.txt
-        let audio_stem = audio_path.file_stem().unwrap().to_string_lossy();
-        let whisper_output = transcript_dir.join(format!("{}.txt", audio_stem));
+    /// Transcribe `audio_path` in `chunk_seconds` long chunks (see `chunks`),
+    /// skipping chunks transcribed by an earlier attempt, and join them into
+    /// `transcript_path`.
+    #[allow(clippy::too_many_arguments)]
+    fn transcribe_chunks(
+        &self,
+        audio_path: &Path,
+        job: &Job,
+        model: &str,
+        chunk_seconds: u64,
+        transcript_path: &Path,
+        progress: &mut ProgressReporter,
+        total: Option<Duration>,
+    ) -> Result<()> {
+        let dir = chunks::chunk_dir(audio_path, model, chunk_seconds);
+
+        let chunk_list = match chunks::read_chunk_list(&dir)? {
+            Some(chunk_list) => chunk_list,
+            None => {
+                let total = total.with_context(|| {
+                    format!("Cannot split {} into chunks: its length is unknown", audio_path.display())
+                })?;
+                if dir.exists() {
+                    fs::remove_dir_all(&dir)?;
+                }
+                fs::create_dir_all(&dir)?;
+                let chunk_list = chunks::plan_chunks(&dir, total, chunk_seconds, self.audio_format.codec.extension());
+                for chunk in &chunk_list {
+                    self.cut_chunk(audio_path, chunk, chunk_seconds)?;
+                }
+                chunks::write_chunk_list(&dir, &chunk_list)?;
+                chunk_list
+            }
+        };
 
-        // Rename to our expected format if needed
-        if whisper_output != transcript_path && whisper_output.exists() {
-            fs::rename(&whisper_output, &transcript_path)?;
+        let pending = chunks::pending_chunks(&chunk_list);
+        if pending.len() < chunk_list.len() {
+            info!(
+                worker_id = self.worker_id,
+                job_id = job.id,
+                done = chunk_list.len() - pending.len(),
+                chunks = chunk_list.len(),
+                "Resuming chunked transcription"
+            );
         }
 
-        // Verify file was created
-        if !transcript_path.exists() {
-            anyhow::bail!(
-                "Transcript file was not created: {}",
-                transcript_path.display()
-            );
+        // Chunk transcripts are joined from their JSON segments, whatever
+        // formats the episode's transcript is kept in
+        let chunk_formats = [TranscriptFormat::Json];
+        for (_, chunk) in pending {
+            debug!(job_id = job.id, chunk = %chunk.audio.display(), start = chunk.start, "Transcribing chunk");
+            let offset = Duration::from_secs_f64(chunk.start);
+            self.run_whisper(&chunk.audio, &dir, job, model, &chunk_formats, progress, offset, total)?;
+            keep_requested_outputs(&chunk_formats, &chunk.audio, &dir, &chunk.audio.with_extension("txt"))?;
         }
 
-        // Post-process: detect and remove hallucinations
-        self.clean_transcript(&transcript_path)?;
+        chunks::join_chunks(&chunk_list, transcript_path, &self.transcript_formats)
+    }
 
-        Ok(transcript_path)
+    /// Cut `chunk` out of `audio_path` with ffmpeg
+    fn cut_chunk(&self, audio_path: &Path, chunk: &chunks::Chunk, chunk_seconds: u64) -> Result<()> {
+        let codec = self.audio_format.codec.ffmpeg_codec();
+        let mut child = process::own_process_group(&mut Command::new("ffmpeg"))
+            .args(chunks::cut_args(audio_path, chunk, chunk_seconds, codec))
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute ffmpeg command")?;
+        let stderr = process::collect_output(child.stderr.take().context("Failed to capture ffmpeg stderr")?);
+        let status = process::wait_with_timeout(&mut child, "ffmpeg", self.process_timeout, |_| {})?;
+        if !status.success() {
+            let stderr = stderr.join().unwrap_or_default();
+            anyhow::bail!(
+                "ffmpeg failed to cut chunk at {}s with exit code: {:?}: {}",
+                chunk.start,
+                status.code().unwrap_or(-1),
+                stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or_default().trim()
+            );
+        }
+        Ok(())
     }
 
     /// Clean transcript by removing hallucination patterns.
@@ -481,27 +951,615 @@ impl Transcriber {
     }
 }
 
-/// Sanitize filename by removing/replacing invalid characters.
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            _ => c,
-        })
-        .collect::<String>()
-        .trim()
-        .to_string()
+/// FFmpeg arguments extracting the audio of `video_path` to `audio_path`, e.g.
+/// `ffmpeg -i input.mp4 -vn -acodec pcm_s16le -ar 16000 -ac 1 -progress pipe:1 -nostats -y output.wav`
+fn extract_audio_args(video_path: &Path, audio_path: &Path, format: &AudioFormat) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["-i".into(), video_path.into()];
+    args.push("-vn".into()); // No video
+    args.extend(["-acodec".into(), format.codec.ffmpeg_codec().into()]);
+    args.extend(["-ar".into(), format.sample_rate.to_string().into()]);
+    args.extend(["-ac".into(), format.channels.to_string().into()]);
+    // Machine-readable progress on stdout
+    args.extend(["-progress".into(), "pipe:1".into(), "-nostats".into()]);
+    args.push("-y".into()); // Overwrite output file
+    args.push(audio_path.into());
+    args
+}
+
+/// Extensions of the files whisper writes with `--output_format all`
+const WHISPER_OUTPUT_EXTENSIONS: [&str; 5] = ["txt", "vtt", "srt", "tsv", "json"];
+
+/// Whisper `--output_format` that writes every one of `formats`
+fn whisper_output_format(formats: &[TranscriptFormat]) -> &'static str {
+    match formats {
+        [format] => format.extension(),
+        _ => "all",
+    }
+}
+
+/// Rename whisper's outputs for `audio_path` in `output_dir`
+/// (`<audio_stem>.<ext>`) in the requested `formats` to the names of the
+/// transcript at `transcript_path`, and delete the rest (`--output_format all`
+/// also writes subtitles)
+fn keep_requested_outputs(
+    formats: &[TranscriptFormat],
+    audio_path: &Path,
+    output_dir: &Path,
+    transcript_path: &Path,
+) -> Result<()> {
+    let audio_stem = audio_path.file_stem().unwrap_or_default().to_string_lossy();
+    for extension in WHISPER_OUTPUT_EXTENSIONS {
+        let whisper_output = output_dir.join(format!("{}.{}", audio_stem, extension));
+        let wanted = transcript_path.with_extension(extension);
+        if !whisper_output.exists() {
+            continue;
+        }
+        if formats.iter().any(|f| f.extension() == extension) {
+            if whisper_output != wanted {
+                fs::rename(&whisper_output, &wanted)?;
+            }
+        } else {
+            fs::remove_file(&whisper_output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Files of the transcript at `transcript_path` (the txt) in each of `formats`
+fn output_paths(formats: &[TranscriptFormat], transcript_path: &Path) -> Vec<PathBuf> {
+    formats.iter().map(|f| transcript_path.with_extension(f.extension())).collect()
+}
+
+/// Formats of the transcript at `transcript_path` that have no file
+fn missing_formats(formats: &[TranscriptFormat], transcript_path: &Path) -> Vec<TranscriptFormat> {
+    formats
+        .iter()
+        .copied()
+        .filter(|f| !transcript_path.with_extension(f.extension()).exists())
+        .collect()
+}
+
+/// Rename the existing `outputs` to `<name>.<model>.<ext>`, returning
+/// (output, where it went)
+fn set_aside(outputs: &[PathBuf], model: &str) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved = Vec::new();
+    for output in outputs.iter().filter(|p| p.exists()) {
+        let extension = output.extension().unwrap_or_default().to_string_lossy();
+        let aside = output.with_extension(format!("{}.{}", model, extension));
+        fs::rename(output, &aside)?;
+        moved.push((output.clone(), aside));
+    }
+    Ok(moved)
+}
+
+/// Put the outputs moved by `set_aside` back, deleting any written since
+/// that had no previous version
+fn restore(outputs: &[PathBuf], previous: &[(PathBuf, PathBuf)]) -> Result<()> {
+    for output in outputs {
+        match previous.iter().find(|(original, _)| original == output) {
+            Some((_, aside)) => fs::rename(aside, output)?,
+            None if output.exists() => fs::remove_file(output)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whisper CLI arguments transcribing `audio_path` into `output_dir`, e.g.
+/// `whisper audio.wav --model base --language ja --output_dir /path/to/dir --output_format txt --verbose True`
+fn whisper_args(
+    audio_path: &Path,
+    output_dir: &Path,
+    model: &str,
+    output_format: &str,
+    initial_prompt: Option<&str>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![audio_path.into()];
+    args.extend(["--model".into(), model.into()]);
+    args.extend(["--language".into(), "ja".into()]); // Japanese
+    args.extend(["--output_dir".into(), output_dir.into()]);
+    args.extend(["--output_format".into(), output_format.into()]);
+    // One line per segment, read for progress
+    args.extend(["--verbose".into(), "True".into()]);
+    if let Some(prompt) = initial_prompt {
+        args.extend(["--initial_prompt".into(), prompt.into()]);
+    }
+    args
+}
+
+/// Fill in the `{title}` placeholder of an initial prompt template
+fn render_initial_prompt(template: &str, title: &str) -> String {
+    template.replace("{title}", title)
+}
+
+/// Read a transcript and score it.
+fn score_transcript(transcript_path: &PathBuf) -> Result<TranscriptQuality> {
+    let content = fs::read_to_string(transcript_path)
+        .with_context(|| format!("Failed to read transcript: {}", transcript_path.display()))?;
+    Ok(transcript_quality(&content))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shared::{Anime, AudioCodec, Database, NewJob};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_run_stops_after_max_jobs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+
+        let db = Database::open(temp_dir.path().join("jobs.db"))?;
+        let mut queue = JobQueue::new(db);
+
+        // Five downloaded episodes, each with a (dummy) video on disk
+        let anime = Anime::test_fixture(5114, "Test Anime", 5);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        for episode in 1..=5 {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+
+            let video_path = data_paths.video_file(anime.mal_id, &anime.title, episode, "mkv");
+            fs::create_dir_all(video_path.parent().unwrap())?;
+            fs::write(&video_path, b"")?;
+            queue.update_job_with_video(job_id, video_path, 0)?;
+            queue.update_stage(job_id, JobStage::Downloaded)?;
+        }
 
-    #[test]
-    fn test_sanitize_filename() {
-        assert_eq!(
-            sanitize_filename("Fullmetal Alchemist: Brotherhood"),
-            "Fullmetal Alchemist_ Brotherhood"
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            temp_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+
+        let queue = Arc::new(Mutex::new(queue));
+        let mut transcriber = Transcriber::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths,
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            RunBudget::new(Some(2), None),
+        )
+        .with_whisper_version(Some("20231117".to_string()));
+        transcriber.run().await?;
+
+        let stats = queue.lock().unwrap().get_stats()?;
+        assert_eq!(stats.transcribed, 2);
+        assert_eq!(stats.downloaded, 3);
+
+        // The (latin-only) dry run transcripts are scored and stored
+        let transcribed = queue.lock().unwrap().get_jobs_by_stage(JobStage::Transcribed)?;
+        assert!(transcribed.iter().all(|job| job.transcript_quality == Some(0.0)));
+        assert!(transcribed.iter().all(|job| job.transcript_model.as_deref() == Some("base")));
+        assert!(transcribed.iter().all(|job| job.whisper_version.as_deref() == Some("20231117")));
+
+        Ok(())
+    }
+
+    /// Run a transcriber over a single job whose video is missing (and, with
+    /// `video_deleted`, was deleted after an earlier transcription) until the
+    /// job fails or the budget runs out, skipping the retry delay between
+    /// runs, and return the job afterwards
+    async fn run_failing_job(retry_policy: JobRetryPolicy, budget: RunBudget, video_deleted: bool) -> Result<Job> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let job_id = queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        queue.update_job_with_video(job_id, temp_dir.path().join("missing.mp4"), 0)?;
+        if video_deleted {
+            queue.mark_video_deleted(job_id)?;
+        }
+        queue.update_stage(job_id, JobStage::Downloaded)?;
+
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            temp_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+        let queue = Arc::new(Mutex::new(queue));
+        let mut transcriber = Transcriber::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths,
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            budget,
+        )
+        .with_retry_policy(retry_policy);
+
+        let mut last = None;
+        loop {
+            let started = transcriber.budget.jobs_started();
+            transcriber.run().await?;
+            if transcriber.budget.jobs_started() == started {
+                return last.context("No job was started");
+            }
+            let job = queue.lock().unwrap().get_all_jobs()?.remove(0);
+            if job.stage == JobStage::Failed {
+                return Ok(job);
+            }
+            last = Some(job);
+            Database::open(temp_dir.path().join("jobs.db"))?
+                .conn()
+                .execute("UPDATE jobs SET next_attempt_at = NULL", [])?;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failure_retry_policy() -> Result<()> {
+        // Default: a single failed attempt goes back to downloaded
+        let job = run_failing_job(JobRetryPolicy::default(), RunBudget::new(Some(1), None), false).await?;
+        assert_eq!(job.stage, JobStage::Downloaded);
+        assert_eq!(job.retry_count, 1);
+        assert!(job.next_attempt_at.is_some());
+
+        // Overridden retry limit, run until the queue drains
+        let job = run_failing_job(JobRetryPolicy::new(true, Some(2)), RunBudget::unlimited(), false).await?;
+        assert_eq!(job.stage, JobStage::Failed);
+        assert_eq!(job.retry_count, 2);
+
+        // --no-auto-retry: failed straight away
+        let job = run_failing_job(JobRetryPolicy::new(false, None), RunBudget::new(Some(1), None), false).await?;
+        assert_eq!(job.stage, JobStage::Failed);
+        assert_eq!(job.retry_count, 0);
+        assert!(job.error_message.unwrap().contains("Video file not found"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redo_with_deleted_video() -> Result<()> {
+        // A job reset for a redo after its video was cleaned up goes back to
+        // queued for a fresh download
+        let job = run_failing_job(JobRetryPolicy::default(), RunBudget::new(Some(1), None), true).await?;
+        assert_eq!(job.stage, JobStage::Queued);
+        assert_eq!(job.retry_count, 1);
+        assert_eq!(job.video_path, None);
+        assert!(!job.video_deleted);
+        assert!(job.error_message.unwrap().contains("Video already deleted, re-download required"));
+
+        // --no-auto-retry: failed with the same explanation
+        let job = run_failing_job(JobRetryPolicy::new(false, None), RunBudget::new(Some(1), None), true).await?;
+        assert_eq!(job.stage, JobStage::Failed);
+        assert!(job.error_message.unwrap().contains("Video already deleted, re-download required"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_low_quality_transcript_keeps_video() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 2);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        for episode in 1..=2 {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            let video_path = data_paths.video_file(anime.mal_id, &anime.title, episode, "mkv");
+            fs::create_dir_all(video_path.parent().unwrap())?;
+            fs::write(&video_path, b"")?;
+            queue.update_job_with_video(job_id, video_path, 0)?;
+            queue.update_stage(job_id, JobStage::Downloaded)?;
+        }
+
+        // Episode 2 already has a good transcript; episode 1 gets the
+        // (latin-only) dry run transcript
+        let transcript_dir = data_paths.transcript_dir(anime.mal_id);
+        fs::create_dir_all(&transcript_dir)?;
+        fs::write(
+            data_paths.transcript_txt(anime.mal_id, &anime.title, 2),
+            "今日はいい天気ですね。\n",
+        )?;
+
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            temp_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+        let queue = Arc::new(Mutex::new(queue));
+        let mut transcriber = Transcriber::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths.clone(),
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            RunBudget::unlimited(),
         );
+        transcriber.run().await?;
+
+        let jobs = queue.lock().unwrap().get_jobs_by_stage(JobStage::Transcribed)?;
+        let (low, good) = (&jobs[0], &jobs[1]);
+        assert_eq!((low.episode, good.episode), (1, 2));
+
+        assert!(!low.video_deleted && !low.audio_deleted);
+        assert!(data_paths.video_file(anime.mal_id, &anime.title, 1, "mkv").exists());
+
+        assert!(good.video_deleted && good.audio_deleted);
+        assert!(!data_paths.video_file(anime.mal_id, &anime.title, 2, "mkv").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_transcript_format_keeps_video() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 2);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        let transcript_dir = data_paths.transcript_dir(anime.mal_id);
+        fs::create_dir_all(&transcript_dir)?;
+        for episode in 1..=2 {
+            let job_id = queue.enqueue(&NewJob {
+                anime_id,
+                mal_id: anime.mal_id,
+                anime_title: anime.title.clone(),
+                episode,
+                season: None,
+                year: None,
+                priority: 0,
+            })?;
+            let video_path = data_paths.video_file(anime.mal_id, &anime.title, episode, "mkv");
+            fs::create_dir_all(video_path.parent().unwrap())?;
+            fs::write(&video_path, b"")?;
+            queue.update_job_with_video(job_id, video_path, 0)?;
+            queue.update_stage(job_id, JobStage::Downloaded)?;
+
+            let transcript = data_paths.transcript_txt(anime.mal_id, &anime.title, episode);
+            fs::write(&transcript, "今日はいい天気ですね。\n")?;
+            // Only episode 2 got its JSON written
+            if episode == 2 {
+                fs::write(transcript.with_extension("json"), r#"{"segments": []}"#)?;
+            }
+        }
+
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            temp_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+        let queue = Arc::new(Mutex::new(queue));
+        let mut transcriber = Transcriber::new(
+            0,
+            Arc::clone(&queue),
+            disk_monitor,
+            data_paths.clone(),
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            RunBudget::unlimited(),
+        )
+        .with_transcript_formats(vec![TranscriptFormat::Json]);
+        assert_eq!(transcriber.transcript_formats, [TranscriptFormat::Txt, TranscriptFormat::Json]);
+        transcriber.run().await?;
+
+        // Episode 1 failed its attempt and waits for a retry with its video
+        let queue = queue.lock().unwrap();
+        let partial = &queue.get_jobs_by_stage(JobStage::Downloaded)?[0];
+        assert_eq!((partial.episode, partial.retry_count), (1, 1));
+        assert!(!partial.video_deleted && !partial.audio_deleted);
+        assert!(data_paths.video_file(anime.mal_id, &anime.title, 1, "mkv").exists());
+
+        let complete = &queue.get_jobs_by_stage(JobStage::Transcribed)?[0];
+        assert_eq!(complete.episode, 2);
+        assert!(complete.video_deleted && complete.audio_deleted);
+        assert!(!data_paths.video_file(anime.mal_id, &anime.title, 2, "mkv").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_extract_audio_into_temp_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path().join("data"));
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        let job = queue.get_all_jobs()?.remove(0);
+        let queue = Arc::new(Mutex::new(queue));
+
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            temp_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+        let transcriber = |scratch: Option<PathBuf>| {
+            Transcriber::new(
+                0,
+                Arc::clone(&queue),
+                disk_monitor.clone(),
+                data_paths.clone(),
+                "base".to_string(),
+                CleanupConfig::default(),
+                true,
+                RunBudget::unlimited(),
+            )
+            .with_temp_dir(scratch)
+        };
+        let video_path = data_paths.video_file(anime.mal_id, &anime.title, 1, "mkv");
+
+        let scratch = temp_dir.path().join("ramdisk");
+        let audio_path = transcriber(Some(scratch.clone())).extract_audio(&video_path, &job).await?;
+        assert_eq!(audio_path.parent(), Some(scratch.join("5114").as_path()));
+        assert!(audio_path.exists());
+        assert!(!data_paths.audio_dir(anime.mal_id).exists());
+
+        // Without one, audio goes to the data root
+        let audio_path = transcriber(None).extract_audio(&video_path, &job).await?;
+        assert_eq!(audio_path.parent(), Some(data_paths.audio_dir(anime.mal_id).as_path()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_audio_args() {
+        let args = |format: &AudioFormat| -> Vec<String> {
+            extract_audio_args(Path::new("ep001.mkv"), Path::new("ep001.audio"), format)
+                .into_iter()
+                .map(|a| a.to_string_lossy().to_string())
+                .collect()
+        };
+
+        let default = args(&AudioFormat::default());
+        assert_eq!(default[..2], ["-i", "ep001.mkv"]);
+        assert!(default.windows(2).any(|w| w == ["-acodec", "pcm_s16le"]));
+        assert!(default.windows(2).any(|w| w == ["-ar", "16000"]));
+        assert!(default.windows(2).any(|w| w == ["-ac", "1"]));
+        assert_eq!(default.last().map(String::as_str), Some("ep001.audio"));
+
+        let flac = args(&AudioFormat {
+            sample_rate: 22_050,
+            channels: 2,
+            codec: AudioCodec::Flac,
+        });
+        assert!(flac.windows(2).any(|w| w == ["-acodec", "flac"]));
+        assert!(flac.windows(2).any(|w| w == ["-ar", "22050"]));
+        assert!(flac.windows(2).any(|w| w == ["-ac", "2"]));
+    }
+
+    #[test]
+    fn test_whisper_args_initial_prompt() {
+        let args = |prompt: Option<&str>| -> Vec<String> {
+            whisper_args(Path::new("ep001.wav"), Path::new("transcripts/5114"), "base", "txt", prompt)
+                .into_iter()
+                .map(|a| a.to_string_lossy().to_string())
+                .collect()
+        };
+
+        let without = args(None);
+        assert_eq!(without[0], "ep001.wav");
+        assert!(without.windows(2).any(|w| w == ["--output_format", "txt"]));
+        assert_eq!(whisper_output_format(&[TranscriptFormat::Txt, TranscriptFormat::Json]), "all");
+        assert!(without.windows(2).any(|w| w == ["--model", "base"]));
+        assert!(!without.iter().any(|a| a == "--initial_prompt"));
+
+        let prompt = render_initial_prompt("アニメ「{title}」の台詞。", "鋼の錬金術師");
+        assert_eq!(prompt, "アニメ「鋼の錬金術師」の台詞。");
+        let with = args(Some(&prompt));
+        assert!(with.windows(2).any(|w| w == ["--initial_prompt", "アニメ「鋼の錬金術師」の台詞。"]));
+        assert_eq!(with[..without.len()], without[..]);
+    }
+
+    #[tokio::test]
+    async fn test_escalation_keeps_best_transcript() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data_paths = DataPaths::new(temp_dir.path());
+        let mut queue = JobQueue::new(Database::open(temp_dir.path().join("jobs.db"))?);
+
+        let anime = Anime::test_fixture(5114, "Test Anime", 1);
+        let anime_id = queue.get_or_create_anime(&anime)?;
+        queue.enqueue(&NewJob {
+            anime_id,
+            mal_id: anime.mal_id,
+            anime_title: anime.title.clone(),
+            episode: 1,
+            season: None,
+            year: None,
+            priority: 0,
+        })?;
+        let job = queue.get_jobs_by_stage(JobStage::Queued)?.remove(0);
+
+        let disk_monitor = DiskMonitor::new(
+            temp_dir.path(),
+            temp_dir.path(),
+            10,
+            9,
+            8,
+            Duration::from_secs(1),
+        )?;
+        let transcriber = Transcriber::new(
+            0,
+            Arc::new(Mutex::new(queue)),
+            disk_monitor,
+            data_paths.clone(),
+            "base".to_string(),
+            CleanupConfig::default(),
+            true,
+            RunBudget::new(None, None),
+        )
+        .with_escalation(Some("medium".to_string()));
+
+        // A good transcript is kept as-is
+        let transcript_dir = data_paths.transcript_dir(anime.mal_id);
+        fs::create_dir_all(&transcript_dir)?;
+        let transcript_path = data_paths.transcript_txt(anime.mal_id, &anime.title, 1);
+        fs::write(&transcript_path, "今日はいい天気ですね。\n")?;
+        let audio_path = temp_dir.path().join("ep1.wav");
+        let (model, quality) = transcriber.escalate(&audio_path, &transcript_path, &job).await?;
+        assert_eq!(model, "base");
+        assert!(!quality.is_low());
+
+        // Dry-run retries never do better than a romaji transcript, so the
+        // original is kept after trying every model up to the ceiling
+        fs::write(&transcript_path, "kyou wa ii tenki desu ne\n")?;
+        let (model, quality) = transcriber.escalate(&audio_path, &transcript_path, &job).await?;
+        assert_eq!(model, "base");
+        assert!(quality.is_low());
+        assert_eq!(fs::read_to_string(&transcript_path)?, "kyou wa ii tenki desu ne\n");
+        assert_eq!(fs::read_dir(&transcript_dir)?.count(), 1);
+
+        Ok(())
     }
 }